@@ -71,6 +71,9 @@ enum Commands {
         /// The new number of parties to add.
         #[arg(short = 'a', long)]
         n_parties_add: u16,
+        /// The new threshold (if the threshold is modified in the same session).
+        #[arg(long)]
+        new_threshold: Option<u16>,
     },
     /// Runs share removal protocol.
     ShareRemoval {
@@ -275,6 +278,7 @@ fn main() {
             threshold,
             n_parties_init,
             n_parties_add,
+            new_threshold,
         } => {
             println!(
                 "Simulating share addition with threshold={}, quorum-size={}, initial number of parties={}, number of parties to add={}",
@@ -288,6 +292,7 @@ fn main() {
                     threshold,
                     n_parties_init,
                     n_parties_init + n_parties_add,
+                    new_threshold,
                     2,
                 );
             for (i, key) in keys_new.iter().enumerate() {
@@ -0,0 +1,415 @@
+//! Identifiable abort evidence (i.e signed, serializable and independently verifiable bundles of offending messages).
+//!
+//! Enables off-protocol dispute resolution (e.g slashing) when a session with per-round message authentication
+//! (see [`MessageAuthentication`](crate::MessageAuthentication)) aborts blaming a party.
+//!
+//! **NOTE:** Evidence bundles are canonical CBOR encodings (see [`wamu_core::cbor`]).
+
+use std::ops::Deref;
+use wamu_core::cbor::{CanonicalCbor, Decoder, Encoder};
+use wamu_core::crypto::{Signature, VerifyingKey};
+use wamu_core::CborError;
+use wamu_core::IdentityProvider;
+
+/// A protocol message with per-round identity authentication (i.e an encoded message body, its context and its signature).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedMessage {
+    /// Index of the sender.
+    pub sender: u16,
+    /// Index of the receiver (or `None` for broadcast messages).
+    pub receiver: Option<u16>,
+    /// Round for which the message was sent.
+    pub round: u16,
+    /// Identifier of the session.
+    pub session_id: [u8; 32],
+    /// Transcript hash of all broadcast messages of previous rounds.
+    pub transcript_hash: Vec<u8>,
+    /// Encoded message body.
+    pub body: Vec<u8>,
+    /// Verifying key of the sender.
+    pub verifying_key: VerifyingKey,
+    /// Signature of the message.
+    pub signature: Signature,
+}
+
+impl SignedMessage {
+    /// Returns the signed hash of the message
+    /// (i.e binds the message body to its session, sender, receiver, round and the transcript of previous rounds).
+    pub fn message_hash(&self) -> Vec<u8> {
+        message_hash(
+            &self.session_id,
+            self.sender,
+            self.receiver,
+            self.round,
+            &self.transcript_hash,
+            &self.body,
+        )
+    }
+
+    /// Given a list of verifying keys for all parties (indexed by party index),
+    /// returns an ok result if the message is signed by the verified party at the sender index, or an appropriate error otherwise.
+    pub fn verify(&self, verified_parties: &[VerifyingKey]) -> Result<(), wamu_core::Error> {
+        // The verifying key must be the one registered for the sender (i.e offenses are attributed to the sender).
+        verify_party_key(self.sender, &self.verifying_key, verified_parties)?;
+        wamu_core::wrappers::verify_request_with_signature(
+            &self.message_hash(),
+            &self.verifying_key,
+            &self.signature,
+            verified_parties,
+        )
+    }
+}
+
+/// An offense that caused a session to abort.
+///
+/// **NOTE:** Failed message authentication is only blamed locally (i.e it's not an offense),
+/// because anyone can produce an invalid signature on behalf of any party.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Offense {
+    /// Conflicting validly signed messages from the same sender for the same round.
+    ///
+    /// **NOTE:** Both messages are signed by the offender, so this offense is non-repudiable.
+    Equivocation(SignedMessage, SignedMessage),
+}
+
+impl Offense {
+    /// Returns the index of the offending party.
+    pub fn offender(&self) -> u16 {
+        match self {
+            Offense::Equivocation(msg, _) => msg.sender,
+        }
+    }
+}
+
+/// An abort evidence verification error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbortEvidenceError {
+    /// The reporter's signature of the evidence is invalid (or the reporter is not a verified party).
+    InvalidReport(wamu_core::Error),
+    /// The offending messages don't prove the offense.
+    UnprovenOffense,
+}
+
+/// A signed abort evidence bundle (i.e an offense and an attestation from the reporting party).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AbortEvidence {
+    /// The offense that caused the session to abort.
+    pub offense: Offense,
+    /// Index of the reporting party.
+    pub reporter: u16,
+    /// Verifying key of the reporting party.
+    pub verifying_key: VerifyingKey,
+    /// Signature of the evidence by the reporting party.
+    pub signature: Signature,
+}
+
+impl AbortEvidence {
+    /// Given an offense, the index of the reporting party and its identity provider, returns a signed abort evidence bundle.
+    pub fn new(offense: Offense, reporter: u16, identity_provider: &impl IdentityProvider) -> Self {
+        let (verifying_key, signature) = wamu_core::wrappers::initiate_request_with_signature(
+            &Self::evidence_hash(&offense, reporter),
+            identity_provider,
+        );
+        Self {
+            offense,
+            reporter,
+            verifying_key,
+            signature,
+        }
+    }
+
+    /// Given a list of verifying keys for all parties (indexed by party index),
+    /// returns an ok result if the evidence is attested by the verified party at the reporter index and proves the offense,
+    /// or an appropriate error otherwise.
+    pub fn verify(&self, verified_parties: &[VerifyingKey]) -> Result<(), AbortEvidenceError> {
+        // Reporter signature must be valid (and by the verified party at the reporter index).
+        verify_party_key(self.reporter, &self.verifying_key, verified_parties)
+            .and_then(|_| {
+                wamu_core::wrappers::verify_request_with_signature(
+                    &Self::evidence_hash(&self.offense, self.reporter),
+                    &self.verifying_key,
+                    &self.signature,
+                    verified_parties,
+                )
+            })
+            .map_err(AbortEvidenceError::InvalidReport)?;
+
+        // Offending messages must prove the offense.
+        let is_proven = match &self.offense {
+            Offense::Equivocation(first, second) => {
+                first.verify(verified_parties).is_ok()
+                    && second.verify(verified_parties).is_ok()
+                    && first.verifying_key == second.verifying_key
+                    && first.sender == second.sender
+                    && first.round == second.round
+                    // Both messages must be for the same receiver in the same session and context
+                    // (i.e messages from different sessions or to different receivers aren't conflicting).
+                    && first.receiver == second.receiver
+                    && first.session_id == second.session_id
+                    && first.transcript_hash == second.transcript_hash
+                    && first.body != second.body
+            }
+        };
+        if is_proven {
+            Ok(())
+        } else {
+            Err(AbortEvidenceError::UnprovenOffense)
+        }
+    }
+
+    // Binds the offense to the reporting party.
+    fn evidence_hash(offense: &Offense, reporter: u16) -> Vec<u8> {
+        use sha2::{digest::Update, Digest};
+        let hasher = sha2::Sha256::new();
+        hasher
+            .chain(offense.to_cbor())
+            .chain(reporter.to_be_bytes())
+            .finalize()
+            .deref()
+            .to_vec()
+    }
+}
+
+/// Returns the signed hash of a message with per-round identity authentication
+/// (i.e binds the encoded message body to its session, sender, receiver, round and the transcript of previous rounds).
+pub(crate) fn message_hash(
+    session_id: &[u8; 32],
+    sender: u16,
+    receiver: Option<u16>,
+    round: u16,
+    transcript_hash: &[u8],
+    body: &[u8],
+) -> Vec<u8> {
+    use sha2::{digest::Update, Digest};
+    let hasher = sha2::Sha256::new();
+    hasher
+        .chain(session_id)
+        .chain(sender.to_be_bytes())
+        // Broadcast messages have no receiver (i.e `0` is never a valid party index).
+        .chain(receiver.unwrap_or(0).to_be_bytes())
+        .chain(round.to_be_bytes())
+        .chain(transcript_hash)
+        .chain(body)
+        .finalize()
+        .deref()
+        .to_vec()
+}
+
+// Returns an ok result if the verifying key is the one registered for the party index, or an appropriate error otherwise.
+fn verify_party_key(
+    idx: u16,
+    verifying_key: &VerifyingKey,
+    verified_parties: &[VerifyingKey],
+) -> Result<(), wamu_core::Error> {
+    if (idx as usize)
+        .checked_sub(1)
+        .and_then(|i| verified_parties.get(i))
+        == Some(verifying_key)
+    {
+        Ok(())
+    } else {
+        Err(wamu_core::Error::UnauthorizedParty)
+    }
+}
+
+// Decodes a `u16` (i.e a party index or round).
+fn decode_u16(decoder: &mut Decoder) -> Result<u16, CborError> {
+    decoder
+        .uint()?
+        .try_into()
+        .map_err(|_| CborError::InvalidValue)
+}
+
+impl CanonicalCbor for SignedMessage {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.array(8);
+        encoder.uint(self.sender.into());
+        // Broadcast messages have no receiver (i.e `0` is never a valid party index).
+        encoder.uint(self.receiver.unwrap_or(0).into());
+        encoder.uint(self.round.into());
+        encoder.bytes(&self.session_id);
+        encoder.bytes(&self.transcript_hash);
+        encoder.bytes(&self.body);
+        self.verifying_key.encode(encoder);
+        self.signature.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, CborError> {
+        decoder.array_of_len(8)?;
+        Ok(Self {
+            sender: decode_u16(decoder)?,
+            receiver: Some(decode_u16(decoder)?).filter(|receiver| *receiver != 0),
+            round: decode_u16(decoder)?,
+            session_id: decoder
+                .bytes()?
+                .try_into()
+                .map_err(|_| CborError::InvalidValue)?,
+            transcript_hash: decoder.bytes()?,
+            body: decoder.bytes()?,
+            verifying_key: CanonicalCbor::decode(decoder)?,
+            signature: CanonicalCbor::decode(decoder)?,
+        })
+    }
+}
+
+impl CanonicalCbor for Offense {
+    fn encode(&self, encoder: &mut Encoder) {
+        match self {
+            Offense::Equivocation(first, second) => {
+                encoder.array(3);
+                encoder.uint(0);
+                first.encode(encoder);
+                second.encode(encoder);
+            }
+        }
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, CborError> {
+        let len = decoder.array()?;
+        match (len, decoder.uint()?) {
+            (3, 0) => Ok(Offense::Equivocation(
+                CanonicalCbor::decode(decoder)?,
+                CanonicalCbor::decode(decoder)?,
+            )),
+            _ => Err(CborError::InvalidValue),
+        }
+    }
+}
+
+impl CanonicalCbor for AbortEvidence {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.array(4);
+        self.offense.encode(encoder);
+        encoder.uint(self.reporter.into());
+        self.verifying_key.encode(encoder);
+        self.signature.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, CborError> {
+        decoder.array_of_len(4)?;
+        Ok(Self {
+            offense: CanonicalCbor::decode(decoder)?,
+            reporter: decode_u16(decoder)?,
+            verifying_key: CanonicalCbor::decode(decoder)?,
+            signature: CanonicalCbor::decode(decoder)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wamu_core::test_utils::MockECDSAIdentityProvider;
+
+    const SESSION_ID: [u8; 32] = [1; 32];
+
+    // Returns a round 1 message from the sender to the receiver (or a broadcast message if the receiver is `None`)
+    // for the session signed by the identity provider.
+    fn signed_message(
+        session_id: [u8; 32],
+        sender: u16,
+        receiver: Option<u16>,
+        body: &[u8],
+        identity_provider: &MockECDSAIdentityProvider,
+    ) -> SignedMessage {
+        let transcript_hash = session_id.to_vec();
+        let (verifying_key, signature) = wamu_core::wrappers::initiate_request_with_signature(
+            &message_hash(&session_id, sender, receiver, 1, &transcript_hash, body),
+            identity_provider,
+        );
+        SignedMessage {
+            sender,
+            receiver,
+            round: 1,
+            session_id,
+            transcript_hash,
+            body: body.to_vec(),
+            verifying_key,
+            signature,
+        }
+    }
+
+    #[test]
+    fn abort_evidence_works() {
+        // Creates identity providers and verifying keys for all parties.
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (0..3)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+
+        // Equivocation by the sender is proven.
+        let offense = Offense::Equivocation(
+            signed_message(SESSION_ID, 1, None, b"first", &identity_providers[0]),
+            signed_message(SESSION_ID, 1, None, b"second", &identity_providers[0]),
+        );
+        let evidence = AbortEvidence::new(offense.clone(), 3, &identity_providers[2]);
+        assert_eq!(evidence.offense.offender(), 1);
+        assert_eq!(evidence.verify(&verifying_keys), Ok(()));
+
+        // Messages to different receivers or from different sessions don't prove equivocation.
+        for (first, second) in [
+            (
+                signed_message(SESSION_ID, 1, Some(2), b"first", &identity_providers[0]),
+                signed_message(SESSION_ID, 1, Some(3), b"second", &identity_providers[0]),
+            ),
+            (
+                signed_message(SESSION_ID, 1, None, b"first", &identity_providers[0]),
+                signed_message([2; 32], 1, None, b"second", &identity_providers[0]),
+            ),
+        ] {
+            assert_eq!(first.verify(&verifying_keys), Ok(()));
+            assert_eq!(second.verify(&verifying_keys), Ok(()));
+            let unproven_evidence = AbortEvidence::new(
+                Offense::Equivocation(first, second),
+                3,
+                &identity_providers[2],
+            );
+            assert_eq!(
+                unproven_evidence.verify(&verifying_keys),
+                Err(AbortEvidenceError::UnprovenOffense)
+            );
+        }
+
+        // Messages signed by a verified party on behalf of another party don't prove an offense by the other party.
+        let forged_msg = signed_message(SESSION_ID, 1, None, b"first", &identity_providers[1]);
+        assert_eq!(
+            forged_msg.verify(&verifying_keys),
+            Err(wamu_core::Error::UnauthorizedParty)
+        );
+        let forged_evidence = AbortEvidence::new(
+            Offense::Equivocation(
+                forged_msg,
+                signed_message(SESSION_ID, 1, None, b"second", &identity_providers[1]),
+            ),
+            2,
+            &identity_providers[1],
+        );
+        assert_eq!(forged_evidence.offense.offender(), 1);
+        assert_eq!(
+            forged_evidence.verify(&verifying_keys),
+            Err(AbortEvidenceError::UnprovenOffense)
+        );
+
+        // Evidence attested by a verified party on behalf of another reporter is rejected.
+        let forged_report = AbortEvidence::new(offense, 3, &identity_providers[1]);
+        assert_eq!(
+            forged_report.verify(&verifying_keys),
+            Err(AbortEvidenceError::InvalidReport(
+                wamu_core::Error::UnauthorizedParty
+            ))
+        );
+
+        // Failed message authentication isn't an (exportable) offense.
+        let mut encoder = Encoder::new();
+        encoder.array(2);
+        encoder.uint(1);
+        signed_message(SESSION_ID, 1, None, b"first", &identity_providers[0]).encode(&mut encoder);
+        assert_eq!(
+            Offense::from_cbor(&encoder.finish()),
+            Err(CborError::InvalidValue)
+        );
+    }
+}
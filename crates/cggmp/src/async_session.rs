@@ -0,0 +1,262 @@
+//! Async (i.e `futures` based) execution of protocol sessions.
+//!
+//! An async counterpart of [`run_protocol`](crate::run_protocol) that drives any [`StateMachine`](StateMachine)
+//! (including augmented state machines) with a stream of incoming messages and a sink for outgoing messages.
+
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use round_based::{IsCritical, Msg, StateMachine};
+use tokio::time::Instant;
+
+/// An async protocol session error.
+#[derive(Debug)]
+pub enum SessionError<E, T> {
+    /// A critical state machine error (including round timeouts that abort the protocol).
+    StateMachine(E),
+    /// A transport error.
+    Transport(T),
+    /// The incoming message stream ended before the protocol finished.
+    IncomingClosed,
+}
+
+/// Given a state machine, a stream of incoming messages and a sink for outgoing messages,
+/// drives the state machine until it finishes and returns its output,
+/// or a critical state machine error, transport error or an error for a closed incoming message stream otherwise.
+///
+/// Round timeouts (i.e [`StateMachine::round_timeout`]) are enforced from the start of each round.
+///
+/// **NOTE:** Non-critical state machine errors (e.g duplicate messages and round timeouts that don't abort the protocol) are ignored.
+///
+/// **NOTE:** Sessions are cancelled by dropping the returned future (e.g via `tokio::select!`),
+/// after which the state machine can be cancelled (e.g via [`AugmentedStateMachine::cancel`](crate::augmented_state_machine::AugmentedStateMachine::cancel))
+/// to notify other parties.
+pub async fn run_session<SM, I, O, T>(
+    state_machine: &mut SM,
+    mut incoming: I,
+    mut outgoing: O,
+) -> Result<SM::Output, SessionError<SM::Err, T>>
+where
+    SM: StateMachine,
+    I: Stream<Item = Result<Msg<SM::MessageBody>, T>> + Unpin,
+    O: Sink<Msg<SM::MessageBody>, Error = T> + Unpin,
+{
+    let mut round = state_machine.current_round();
+    let mut round_deadline = current_round_deadline(state_machine);
+    loop {
+        // Sends outgoing messages.
+        for msg in state_machine.message_queue().split_off(0) {
+            outgoing.feed(msg).await.map_err(SessionError::Transport)?;
+        }
+        outgoing.flush().await.map_err(SessionError::Transport)?;
+
+        // Returns output if the protocol is finished.
+        if let Some(output) = state_machine.pick_output() {
+            return output.map_err(SessionError::StateMachine);
+        }
+
+        // Restarts the round timer when the state machine advances to the next round.
+        if state_machine.current_round() != round {
+            round = state_machine.current_round();
+            round_deadline = current_round_deadline(state_machine);
+        }
+
+        // Proceeds to the next round, or handles the next incoming message (or round timeout) otherwise.
+        let result = if state_machine.wants_to_proceed() {
+            state_machine.proceed()
+        } else {
+            let next = match round_deadline {
+                Some(deadline) => tokio::time::timeout_at(deadline, incoming.next()).await,
+                None => Ok(incoming.next().await),
+            };
+            match next {
+                Ok(Some(msg)) => {
+                    state_machine.handle_incoming(msg.map_err(SessionError::Transport)?)
+                }
+                Ok(None) => return Err(SessionError::IncomingClosed),
+                Err(_) => {
+                    // Round timeouts are only reported once per round, unless they're non-critical
+                    // (e.g retransmission timers of reliable delivery), in which case the round timer is restarted.
+                    let error = state_machine.round_timeout_reached();
+                    round_deadline = if error.is_critical() {
+                        None
+                    } else {
+                        current_round_deadline(state_machine)
+                    };
+                    Err(error)
+                }
+            }
+        };
+        if let Err(error) = result {
+            if error.is_critical() {
+                return Err(SessionError::StateMachine(error));
+            }
+        }
+    }
+}
+
+/// Returns the deadline for the current round of the state machine (if any).
+fn current_round_deadline(state_machine: &impl StateMachine) -> Option<Instant> {
+    state_machine
+        .round_timeout()
+        .map(|round_timeout| Instant::now() + round_timeout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen::AugmentedKeyGen;
+    use crate::{QuorumApproval, QuorumApprovalMessage};
+    use futures::channel::mpsc;
+    use std::time::Duration;
+    use wamu_core::crypto::VerifyingKey;
+    use wamu_core::quorum_approved_request::QuorumPolicy;
+    use wamu_core::test_utils::MockECDSAIdentityProvider;
+    use wamu_core::IdentityProvider;
+
+    #[tokio::test]
+    async fn run_session_works() {
+        // Creates identity providers and verifying keys for all parties.
+        let (threshold, n_parties) = (1, 3);
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (1..=n_parties)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+
+        // Creates channels for all parties and routes outgoing messages to their receivers.
+        type KeygenMsg =
+            Msg<<AugmentedKeyGen<'static, MockECDSAIdentityProvider> as StateMachine>::MessageBody>;
+        let (outgoing_tx, mut outgoing_rx) = mpsc::unbounded::<KeygenMsg>();
+        let (incoming_txs, incoming_rxs): (Vec<_>, Vec<_>) = (0..n_parties)
+            .map(|_| mpsc::unbounded::<KeygenMsg>())
+            .unzip();
+        let router = async move {
+            while let Some(msg) = outgoing_rx.next().await {
+                for (idx, incoming_tx) in incoming_txs.iter().enumerate() {
+                    let receiver = (idx + 1) as u16;
+                    if receiver != msg.sender && msg.receiver.map_or(true, |it| it == receiver) {
+                        // Parties that already finished have dropped their receivers.
+                        let _ = incoming_tx.unbounded_send(msg.clone());
+                    }
+                }
+            }
+        };
+
+        // Runs key generation sessions for all parties concurrently.
+        let sessions = futures::future::join_all(incoming_rxs.into_iter().enumerate().map(
+            |(idx, incoming_rx)| {
+                let identity_provider = &identity_providers[idx];
+                let verifying_keys = &verifying_keys;
+                let outgoing_tx = outgoing_tx.clone();
+                async move {
+                    let mut keygen = AugmentedKeyGen::new(
+                        identity_provider,
+                        verifying_keys,
+                        (idx + 1) as u16,
+                        threshold,
+                        n_parties,
+                    )
+                    .unwrap();
+                    run_session(&mut keygen, incoming_rx.map(Ok), outgoing_tx).await
+                }
+            },
+        ));
+        drop(outgoing_tx);
+        let (outputs, _) = futures::join!(sessions, router);
+
+        // Verifies that all parties generated the same public key.
+        let outputs: Vec<_> = outputs.into_iter().map(Result::unwrap).collect();
+        assert_eq!(outputs.len(), n_parties as usize);
+        for output in &outputs {
+            assert_eq!(output.base.public_key(), outputs[0].base.public_key());
+        }
+
+        // Closed incoming message streams are reported.
+        let mut keygen = AugmentedKeyGen::new(
+            &identity_providers[0],
+            &verifying_keys,
+            1,
+            threshold,
+            n_parties,
+        )
+        .unwrap();
+        let (sink, _rx) = mpsc::unbounded::<KeygenMsg>();
+        let result = run_session(
+            &mut keygen,
+            futures::stream::empty::<Result<KeygenMsg, mpsc::SendError>>(),
+            sink,
+        )
+        .await;
+        assert!(matches!(result, Err(SessionError::IncomingClosed)));
+    }
+
+    #[tokio::test]
+    async fn run_session_waits_for_time_locks() {
+        // Creates identity providers and verifying keys for all parties.
+        let (threshold, n_parties) = (1, 3);
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (1..=n_parties)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+
+        // Creates channels for all parties and routes outgoing messages to their receivers.
+        type ApprovalMsg = Msg<QuorumApprovalMessage>;
+        let (outgoing_tx, mut outgoing_rx) = mpsc::unbounded::<ApprovalMsg>();
+        let (incoming_txs, incoming_rxs): (Vec<_>, Vec<_>) = (0..n_parties)
+            .map(|_| mpsc::unbounded::<ApprovalMsg>())
+            .unzip();
+        let router = async move {
+            while let Some(msg) = outgoing_rx.next().await {
+                for (idx, incoming_tx) in incoming_txs.iter().enumerate() {
+                    let receiver = (idx + 1) as u16;
+                    if receiver != msg.sender && msg.receiver.map_or(true, |it| it == receiver) {
+                        // Parties that already finished have dropped their receivers.
+                        let _ = incoming_tx.unbounded_send(msg.clone());
+                    }
+                }
+            }
+        };
+
+        // Runs time-locked quorum approval sessions for all parties concurrently
+        // (i.e parties wake up when the time lock window elapses even though no more messages arrive).
+        let started_at = Instant::now();
+        let sessions = futures::future::join_all(incoming_rxs.into_iter().enumerate().map(
+            |(idx, incoming_rx)| {
+                let identity_provider = &identity_providers[idx];
+                let verifying_keys = &verifying_keys;
+                let outgoing_tx = outgoing_tx.clone();
+                async move {
+                    let mut quorum_approval = QuorumApproval::new(
+                        "command",
+                        identity_provider,
+                        verifying_keys,
+                        (idx + 1) as u16,
+                        threshold,
+                        n_parties,
+                        idx == 0,
+                        false,
+                        QuorumPolicy::AllParties,
+                    )
+                    .with_time_lock(2);
+                    run_session(&mut quorum_approval, incoming_rx.map(Ok), outgoing_tx).await
+                }
+            },
+        ));
+        drop(outgoing_tx);
+        let (outputs, _) = tokio::time::timeout(Duration::from_secs(10), async {
+            futures::join!(sessions, router)
+        })
+        .await
+        .unwrap();
+
+        // Verifies that all parties approved the request after the time lock window.
+        for output in outputs {
+            assert!(output.unwrap());
+        }
+        assert!(started_at.elapsed() >= Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,301 @@
+//! A generic [`AugmentedStateMachine`] adapter for arbitrary `round-based` state machines.
+//!
+//! Allows other MPC protocols (e.g a DKG from another crate) to be augmented with closure-based hooks,
+//! without implementing [`AugmentedStateMachine`] (and its macros) for a dedicated wrapper type.
+
+use round_based::{Msg, StateMachine};
+use std::time::Duration;
+
+use crate::augmented_state_machine::{
+    AugmentedStateMachine, AugmentedType, Error, ProgressTracker, TimeoutConfig,
+};
+use crate::event_sink::EventSink;
+
+/// A hook that returns additional parameters (if any) for an outgoing message given its sender and body.
+pub type AugmentOutgoingFn<'a, SM, P> = Box<
+    dyn Fn(
+            u16,
+            &<SM as StateMachine>::MessageBody,
+        ) -> Result<Option<P>, Error<<SM as StateMachine>::Err>>
+        + 'a,
+>;
+
+/// A hook that verifies the additional parameters of an incoming message
+/// given the wrapped state machine (e.g for its current round) and the message.
+pub type VerifyIncomingFn<'a, SM, P> = Box<
+    dyn Fn(
+            &SM,
+            &Msg<AugmentedType<<SM as StateMachine>::MessageBody, P>>,
+        ) -> Result<(), Error<<SM as StateMachine>::Err>>
+        + 'a,
+>;
+
+/// A [`StateMachine`](StateMachine) that augments any wrapped `StateMachine` with closure-based hooks
+/// (i.e a generic implementation of [`AugmentedStateMachine`]).
+pub struct Augmented<'a, SM: StateMachine, P> {
+    /// Wrapped state machine.
+    state_machine: SM,
+    /// An augmented message queue.
+    message_queue: Vec<Msg<AugmentedType<SM::MessageBody, P>>>,
+    /// Hook for augmenting outgoing messages.
+    augment_outgoing: AugmentOutgoingFn<'a, SM, P>,
+    /// Hook for verifying incoming messages.
+    verify_incoming: VerifyIncomingFn<'a, SM, P>,
+    /// Tracks the progress of the protocol (if set).
+    progress_tracker: Option<ProgressTracker>,
+    /// Timeout configuration (if any).
+    timeout_config: Option<TimeoutConfig>,
+    /// Event sink (if any).
+    event_sink: Option<&'a dyn EventSink>,
+}
+
+impl<'a, SM: StateMachine, P> Augmented<'a, SM, P> {
+    /// Given a state machine, a hook for augmenting outgoing messages and a hook for verifying incoming messages,
+    /// returns a state machine that augments the wrapped state machine.
+    pub fn new(
+        state_machine: SM,
+        augment_outgoing: impl Fn(u16, &SM::MessageBody) -> Result<Option<P>, Error<SM::Err>> + 'a,
+        verify_incoming: impl Fn(&SM, &Msg<AugmentedType<SM::MessageBody, P>>) -> Result<(), Error<SM::Err>>
+            + 'a,
+    ) -> Result<Self, Error<SM::Err>> {
+        // Initializes state machine.
+        let mut augmented = Self {
+            state_machine,
+            message_queue: Vec::new(),
+            augment_outgoing: Box::new(augment_outgoing),
+            verify_incoming: Box::new(verify_incoming),
+            progress_tracker: None,
+            timeout_config: None,
+            event_sink: None,
+        };
+
+        // Retrieves messages from immediate state transitions (if any) and augments them.
+        augmented.update_augmented_message_queue()?;
+
+        // Returns augmented state machine.
+        Ok(augmented)
+    }
+
+    /// Sets a progress tracker for the given indices of the parties that send messages in every round (i.e all other parties).
+    pub fn with_progress_tracker(mut self, expected_parties: Vec<u16>) -> Self {
+        self.progress_tracker = Some(ProgressTracker::new(expected_parties));
+        self
+    }
+
+    /// Sets the timeout configuration (i.e per-round timeout, session deadline and behavior on timeout).
+    ///
+    /// **NOTE:** The timeout configuration only applies if a progress tracker is also set.
+    pub fn with_timeout_config(mut self, timeout_config: TimeoutConfig) -> Self {
+        self.timeout_config = Some(timeout_config);
+        self
+    }
+
+    /// Sets the event sink (i.e for publishing round, message, blame and completion events).
+    pub fn with_event_sink(mut self, event_sink: &'a dyn EventSink) -> Self {
+        self.event_sink = Some(event_sink);
+        self
+    }
+}
+
+impl<'a, SM: StateMachine, P> AugmentedStateMachine for Augmented<'a, SM, P> {
+    type StateMachineType = SM;
+    type AdditionalParams = P;
+    type AdditionalOutput = ();
+
+    // Implements all required `AugmentedStateMachine` methods.
+    impl_required_augmented_state_machine_methods!(state_machine, message_queue);
+
+    fn progress_tracker(&self) -> Option<&ProgressTracker> {
+        self.progress_tracker.as_ref()
+    }
+
+    fn progress_tracker_mut(&mut self) -> Option<&mut ProgressTracker> {
+        self.progress_tracker.as_mut()
+    }
+
+    fn timeout_config(&self) -> Option<&TimeoutConfig> {
+        self.timeout_config.as_ref()
+    }
+
+    fn event_sink(&self) -> Option<&dyn EventSink> {
+        self.event_sink
+    }
+
+    fn pre_handle_incoming(
+        &mut self,
+        msg: &Msg<AugmentedType<SM::MessageBody, P>>,
+    ) -> Result<(), Error<SM::Err>> {
+        (self.verify_incoming)(&self.state_machine, msg)
+    }
+
+    fn augment_outgoing_message(
+        &self,
+        sender: u16,
+        msg_body: &SM::MessageBody,
+    ) -> Result<Option<P>, Error<SM::Err>> {
+        (self.augment_outgoing)(sender, msg_body)
+    }
+}
+
+impl<'a, SM: StateMachine, P> StateMachine for Augmented<'a, SM, P> {
+    type MessageBody = AugmentedType<SM::MessageBody, P>;
+    type Err = Error<SM::Err>;
+    type Output = AugmentedType<SM::Output, ()>;
+
+    fn handle_incoming(&mut self, msg: Msg<Self::MessageBody>) -> Result<(), Self::Err> {
+        self.augmented_handle_incoming(msg)
+    }
+
+    fn message_queue(&mut self) -> &mut Vec<Msg<Self::MessageBody>> {
+        self.augmented_message_queue_mut()
+    }
+
+    fn wants_to_proceed(&self) -> bool {
+        self.state_machine.wants_to_proceed()
+    }
+
+    fn proceed(&mut self) -> Result<(), Self::Err> {
+        self.augmented_proceed()
+    }
+
+    fn round_timeout(&self) -> Option<Duration> {
+        self.augmented_round_timeout()
+    }
+
+    fn round_timeout_reached(&mut self) -> Self::Err {
+        self.augmented_round_timeout_reached()
+    }
+
+    fn is_finished(&self) -> bool {
+        self.augmented_is_finished()
+    }
+
+    fn pick_output(&mut self) -> Option<Result<Self::Output, Self::Err>> {
+        self.augmented_pick_output()
+    }
+
+    fn current_round(&self) -> u16 {
+        self.state_machine.current_round()
+    }
+
+    fn total_rounds(&self) -> Option<u16> {
+        self.state_machine.total_rounds()
+    }
+
+    fn party_ind(&self) -> u16 {
+        self.state_machine.party_ind()
+    }
+
+    fn parties(&self) -> u16 {
+        self.state_machine.parties()
+    }
+}
+
+// Hooks can't be formatted.
+impl<'a, SM: StateMachine + std::fmt::Debug, P> std::fmt::Debug for Augmented<'a, SM, P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Augmented({:?})", self.state_machine)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::augmented_state_machine::IdentityAuthParams;
+    use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::keygen::Keygen;
+    use round_based::dev::Simulation;
+    use std::ops::Deref;
+    use wamu_core::crypto::VerifyingKey;
+    use wamu_core::test_utils::MockECDSAIdentityProvider;
+    use wamu_core::IdentityProvider;
+
+    // Binds a message body to its sender for tests.
+    fn message_hash(sender: u16, body: &<Keygen as StateMachine>::MessageBody) -> Vec<u8> {
+        use sha2::{digest::Update, Digest};
+        sha2::Sha256::new()
+            .chain(sender.to_be_bytes())
+            .chain(format!("{body:?}"))
+            .finalize()
+            .deref()
+            .to_vec()
+    }
+
+    #[test]
+    fn generic_augmentation_works() {
+        // Creates identity providers and verifying keys for all parties.
+        let (threshold, n_parties) = (1, 3);
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (1..=n_parties)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+
+        // Augments key generation with identity authentication of all messages via hooks.
+        let new_party = |idx: usize| {
+            let identity_provider = &identity_providers[idx];
+            let verifying_keys = &verifying_keys;
+            Augmented::new(
+                Keygen::new((idx + 1) as u16, threshold, n_parties).unwrap(),
+                move |sender, body| {
+                    let (verifying_key, verifying_signature) =
+                        wamu_core::wrappers::initiate_request_with_signature(
+                            &message_hash(sender, body),
+                            identity_provider,
+                        );
+                    Ok(Some(IdentityAuthParams {
+                        verifying_key,
+                        verifying_signature,
+                    }))
+                },
+                move |state_machine: &Keygen, msg: &Msg<AugmentedType<_, IdentityAuthParams>>| {
+                    match msg.body.extra.as_ref() {
+                        Some(params) => wamu_core::wrappers::verify_request_with_signature(
+                            &message_hash(msg.sender, &msg.body.base),
+                            &params.verifying_key,
+                            &params.verifying_signature,
+                            verifying_keys,
+                        )
+                        .map_err(|error| {
+                            Error::blame(error, msg.sender, params, state_machine.current_round())
+                        }),
+                        None => Err(Error::MissingParams {
+                            bad_actors: vec![msg.sender as usize],
+                            round: state_machine.current_round(),
+                        }),
+                    }
+                },
+            )
+            .unwrap()
+        };
+
+        // Runs augmented key generation.
+        let mut simulation = Simulation::new();
+        for idx in 0..n_parties as usize {
+            simulation.add_party(new_party(idx));
+        }
+        let outputs = simulation.run().unwrap();
+        assert_eq!(outputs.len(), n_parties as usize);
+        for output in &outputs {
+            assert_eq!(output.base.public_key(), outputs[0].base.public_key());
+        }
+
+        // Tampered and unsigned messages are rejected by the hooks.
+        let (mut party_1, mut party_2) = (new_party(0), new_party(1));
+        let msg_1 = party_1.message_queue()[0].clone();
+        let mut tampered_msg = msg_1.clone();
+        tampered_msg.sender = 3;
+        let mut unsigned_msg = msg_1.clone();
+        unsigned_msg.body.extra = None;
+        assert!(matches!(
+            party_2.handle_incoming(tampered_msg),
+            Err(Error::Blame(_))
+        ));
+        assert!(matches!(
+            party_2.handle_incoming(unsigned_msg),
+            Err(Error::MissingParams { .. })
+        ));
+        assert!(party_2.handle_incoming(msg_1).is_ok());
+    }
+}
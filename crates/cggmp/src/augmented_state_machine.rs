@@ -3,11 +3,17 @@
 use curv::elliptic::curves::{ECScalar, Scalar, Secp256k1};
 use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::keygen::LocalKey;
 use round_based::{IsCritical, Msg, StateMachine};
+use std::collections::BTreeSet;
 use std::ops::Deref;
+use std::time::{Duration, Instant};
 use wamu_core::crypto::{Signature, VerifyingKey};
+use wamu_core::envelope::ProtocolEnvelope;
+use wamu_core::error_report::ErrorReport;
 use wamu_core::{IdentityProvider, SecretShare, SigningShare, SubShare};
 use zeroize::Zeroize;
 
+use crate::event_sink::EventSink;
+
 /// A [`StateMachine`](StateMachine) that wraps and augments another [`StateMachine`](StateMachine).
 pub trait AugmentedStateMachine {
     /// The type of the wrapped `StateMachine`.
@@ -89,6 +95,119 @@ pub trait AugmentedStateMachine {
         })
     }
 
+    /// Returns an immutable reference to the progress tracker (if any).
+    fn progress_tracker(&self) -> Option<&ProgressTracker> {
+        None
+    }
+
+    /// Returns a mutable reference to the progress tracker (if any).
+    fn progress_tracker_mut(&mut self) -> Option<&mut ProgressTracker> {
+        None
+    }
+
+    /// Returns the timeout configuration (if any).
+    fn timeout_config(&self) -> Option<&TimeoutConfig> {
+        None
+    }
+
+    /// Returns the event sink (if any).
+    fn event_sink(&self) -> Option<&dyn EventSink> {
+        None
+    }
+
+    /// Publishes an error (i.e as a `tracing` event and the blamed parties to the event sink, if any) and returns it.
+    ///
+    /// **NOTE:** Only the blamed party indices and criticality are traced (i.e the error itself may contain secret material).
+    fn report_error(
+        &self,
+        error: Error<<Self::StateMachineType as StateMachine>::Err>,
+    ) -> Error<<Self::StateMachineType as StateMachine>::Err> {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            bad_actors = ?error.bad_actors(),
+            critical = error.is_critical(),
+            "augmented state machine error"
+        );
+        if let Some(event_sink) = self.event_sink() {
+            for culprit in error.bad_actors() {
+                event_sink.on_party_blamed(None, culprit);
+            }
+        }
+        error
+    }
+
+    /// Returns the timeout for the current round
+    /// (i.e from the timeout configuration if both it and a progress tracker are set, or from the wrapped state machine otherwise).
+    fn augmented_round_timeout(&self) -> Option<Duration> {
+        match (self.timeout_config(), self.progress_tracker()) {
+            (Some(timeout_config), Some(progress_tracker)) => {
+                timeout_config.round_timeout(progress_tracker)
+            }
+            _ => self.state_machine().round_timeout(),
+        }
+    }
+
+    /// Returns an appropriate error for a round timeout
+    /// (i.e based on the timeout configuration if both it and a progress tracker are set, or from the wrapped state machine otherwise).
+    fn augmented_round_timeout_reached(
+        &mut self,
+    ) -> Error<<Self::StateMachineType as StateMachine>::Err> {
+        let timeout_config = self.timeout_config().copied();
+        let error = match (timeout_config, self.progress()) {
+            (Some(timeout_config), Some(progress)) => {
+                let bad_actors = progress
+                    .waiting_on
+                    .into_iter()
+                    .map(|idx| idx as usize)
+                    .collect();
+                let is_deadline_exceeded =
+                    self.progress_tracker().map_or(false, |progress_tracker| {
+                        timeout_config.is_deadline_exceeded(progress_tracker)
+                    });
+                if is_deadline_exceeded {
+                    Error::DeadlineExceeded {
+                        round: progress.current_round,
+                        bad_actors,
+                    }
+                } else {
+                    Error::RoundTimeout {
+                        round: progress.current_round,
+                        bad_actors,
+                        action: timeout_config.on_timeout,
+                    }
+                }
+            }
+            _ => Error::StateMachine(self.state_machine_mut().round_timeout_reached()),
+        };
+        self.report_error(error)
+    }
+
+    /// Returns the progress of the protocol (e.g for rendering progress in UIs), if it's tracked.
+    fn progress(&self) -> Option<RoundProgress> {
+        self.progress_tracker().map(|progress_tracker| {
+            progress_tracker.progress(
+                self.state_machine().current_round(),
+                self.state_machine().total_rounds(),
+            )
+        })
+    }
+
+    /// Cancels the protocol and returns a signed abort notice (for the given session identifier) that other parties can verify.
+    ///
+    /// **NOTE:** The state machine is consumed (i.e dropped), so that the wrapped state machine
+    /// and any reconstructed or buffered secrets it holds are zeroized (for secrets that are zeroized on drop).
+    fn cancel(self, identity_provider: &impl IdentityProvider, session_id: [u8; 32]) -> AbortNotice
+    where
+        Self: Sized,
+    {
+        AbortNotice::new(
+            session_id,
+            self.state_machine().party_ind(),
+            self.state_machine().current_round(),
+            identity_provider,
+        )
+    }
+
     /// Updates the augmented message queue by
     /// retrieving the message queue from the wrapped state machines and processing augmentations (if any) for all the messages in the queue.
     ///
@@ -96,6 +215,12 @@ pub trait AugmentedStateMachine {
     fn update_augmented_message_queue(
         &mut self,
     ) -> Result<(), Error<<Self::StateMachineType as StateMachine>::Err>> {
+        // Observes round changes for progress tracking (if any).
+        let current_round = self.state_machine().current_round();
+        if let Some(progress_tracker) = self.progress_tracker_mut() {
+            progress_tracker.observe_round(current_round);
+        }
+
         let new_messages = self.state_machine_mut().message_queue().split_off(0);
         if !new_messages.is_empty() {
             let mut augmented_new_messages: Vec<
@@ -128,6 +253,14 @@ pub trait AugmentedStateMachine {
     }
 
     /// Handles incoming messages.
+    ///
+    /// **NOTE:** Messages for future rounds (e.g from asynchronous networks) are forwarded to the wrapped state machine as is,
+    /// because augmentations are verified independently of the current round and
+    /// the wrapped state machines store incoming messages for all rounds until they advance to them.
+    ///
+    /// **NOTE:** Augmented messages don't carry the sender's round, so duplicates and equivocation (i.e conflicting messages
+    /// from the same sender for the same round) are detected by wrapping augmented state machines with
+    /// [`MessageAuthentication`](crate::MessageAuthentication) (as the one-shot protocol drivers do by default).
     fn augmented_handle_incoming(
         &mut self,
         msg: Msg<
@@ -137,32 +270,76 @@ pub trait AugmentedStateMachine {
             >,
         >,
     ) -> Result<(), Error<<Self::StateMachineType as StateMachine>::Err>> {
+        let current_round = self.state_machine().current_round();
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "handle_incoming",
+            party = self.state_machine().party_ind(),
+            round = current_round,
+            sender = msg.sender,
+            broadcast = msg.receiver.is_none(),
+        )
+        .entered();
+
         // Hook to run augmentations before calling `handle_incoming`.
-        self.pre_handle_incoming(&msg)?;
+        self.pre_handle_incoming(&msg)
+            .map_err(|error| self.report_error(error))?;
+        if let Some(event_sink) = self.event_sink() {
+            event_sink.on_message_received(None, msg.sender, current_round);
+        }
+
+        // Records the sender for progress tracking (if any).
+        if let Some(progress_tracker) = self.progress_tracker_mut() {
+            progress_tracker.record_message(msg.sender, current_round);
+        }
 
         // Forwards all incoming messages to wrapped state machine.
         self.state_machine_mut()
             .handle_incoming(msg.map_body(|msg_body| msg_body.base))
-            .map_err(Error::StateMachine)?;
+            .map_err(|error| self.report_error(Error::StateMachine(error)))?;
 
         // Updates the augmented message queue.
         self.update_augmented_message_queue()
+            .map_err(|error| self.report_error(error))
     }
 
     /// Performs some expensive computation.
     fn augmented_proceed(
         &mut self,
     ) -> Result<(), Error<<Self::StateMachineType as StateMachine>::Err>> {
+        let current_round = self.state_machine().current_round();
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "proceed",
+            party = self.state_machine().party_ind(),
+            round = current_round,
+        )
+        .entered();
+
         // Hook to run augmentations before calling `proceed`.
-        self.pre_proceed()?;
+        self.pre_proceed()
+            .map_err(|error| self.report_error(error))?;
 
         // Call `proceed` on the wrapped state machine.
         self.state_machine_mut()
             .proceed()
-            .map_err(Error::StateMachine)?;
+            .map_err(|error| self.report_error(Error::StateMachine(error)))?;
+        let next_round = self.state_machine().current_round();
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            next_round,
+            finished = self.state_machine().is_finished(),
+            "proceeded"
+        );
+        if next_round != current_round {
+            if let Some(event_sink) = self.event_sink() {
+                event_sink.on_round_started(None, next_round);
+            }
+        }
 
         // Updates the augmented message queue.
         self.update_augmented_message_queue()
+            .map_err(|error| self.report_error(error))
     }
 
     /// Indicates whether protocol is ready to finish and output can be obtained by calling the [`augmented_pick_output`](Self::augmented_pick_output) method.
@@ -185,6 +362,15 @@ pub trait AugmentedStateMachine {
     > {
         // Picks output result from wrapped state machine, or returns None if protocol isn't finished yet.
         let result = self.state_machine_mut().pick_output()?;
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            party = self.state_machine().party_ind(),
+            success = result.is_ok(),
+            "picked output"
+        );
+        if let Some(event_sink) = self.event_sink() {
+            event_sink.on_session_complete(None, result.is_ok());
+        }
 
         // Augments output with additional parameters or returns wrapped state machine error.
         Some(match result {
@@ -201,6 +387,136 @@ pub trait AugmentedStateMachine {
     }
 }
 
+/// The progress of a protocol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoundProgress {
+    /// Current round of the wrapped state machine.
+    pub current_round: u16,
+    /// Total number of rounds of the wrapped state machine (if known).
+    pub total_rounds: Option<u16>,
+    /// Indices of the parties whose messages for the current round haven't been received yet.
+    pub waiting_on: Vec<u16>,
+    /// Time elapsed since the protocol was initialized.
+    pub elapsed: Duration,
+}
+
+/// Tracks the senders of incoming messages for the current round and the time elapsed since initialization.
+///
+/// **NOTE:** Messages are attributed to the round in which they're received,
+/// so messages received early (i.e before the wrapped state machine advances to their round) aren't counted for their round.
+#[derive(Debug, Clone)]
+pub struct ProgressTracker {
+    /// Time of initialization.
+    started_at: Instant,
+    /// Indices of the parties that send messages in every round.
+    expected_parties: Vec<u16>,
+    /// Round of the recorded senders.
+    round: u16,
+    /// Time at which the recorded round was first observed.
+    round_started_at: Instant,
+    /// Indices of the parties whose messages have been received in the recorded round.
+    received_from: BTreeSet<u16>,
+}
+
+impl ProgressTracker {
+    /// Given the indices of the parties that send messages in every round (i.e all other parties),
+    /// returns a progress tracker that starts timing now.
+    pub fn new(expected_parties: Vec<u16>) -> Self {
+        let now = Instant::now();
+        Self {
+            started_at: now,
+            expected_parties,
+            round: 0,
+            round_started_at: now,
+            received_from: BTreeSet::new(),
+        }
+    }
+
+    /// Observes the current round of the wrapped state machine (i.e resets the recorded senders and round timer on round changes).
+    pub fn observe_round(&mut self, current_round: u16) {
+        if current_round != self.round {
+            self.round = current_round;
+            self.round_started_at = Instant::now();
+            self.received_from.clear();
+        }
+    }
+
+    /// Records the sender of an incoming message received in the given round.
+    pub fn record_message(&mut self, sender: u16, current_round: u16) {
+        self.observe_round(current_round);
+        self.received_from.insert(sender);
+    }
+
+    /// Returns the time elapsed since initialization.
+    pub fn elapsed(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+
+    /// Returns the time elapsed since the current round was first observed.
+    pub fn round_elapsed(&self) -> Duration {
+        self.round_started_at.elapsed()
+    }
+
+    /// Returns the progress of the protocol given the current and total number of rounds of the wrapped state machine.
+    pub fn progress(&self, current_round: u16, total_rounds: Option<u16>) -> RoundProgress {
+        RoundProgress {
+            current_round,
+            total_rounds,
+            waiting_on: self
+                .expected_parties
+                .iter()
+                .copied()
+                .filter(|idx| current_round != self.round || !self.received_from.contains(idx))
+                .collect(),
+            elapsed: self.started_at.elapsed(),
+        }
+    }
+}
+
+/// Behavior on round timeouts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimeoutAction {
+    /// Aborts the protocol (i.e round timeouts are critical errors).
+    #[default]
+    Abort,
+    /// Keeps running the protocol without the missing messages (i.e round timeouts are non-critical errors),
+    /// so that it can still finish if the wrapped state machine doesn't need them (e.g threshold signing with a subset of signers).
+    ProceedWithout,
+}
+
+/// Timeout configuration for augmented state machines (e.g for tuning for rosters with high mobile latency).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TimeoutConfig {
+    /// Timeout for each round (if any).
+    pub round_timeout: Option<Duration>,
+    /// Deadline for the whole session relative to initialization (if any).
+    pub session_deadline: Option<Duration>,
+    /// Behavior on round timeouts.
+    ///
+    /// **NOTE:** Exceeding the session deadline always aborts the protocol.
+    pub on_timeout: TimeoutAction,
+}
+
+impl TimeoutConfig {
+    /// Given a progress tracker, returns the timeout for the current round (if any),
+    /// i.e the round timeout capped by the time remaining before the session deadline.
+    pub fn round_timeout(&self, progress_tracker: &ProgressTracker) -> Option<Duration> {
+        let remaining = self.session_deadline.map(|deadline| {
+            (deadline + progress_tracker.round_elapsed()).saturating_sub(progress_tracker.elapsed())
+        });
+        match (self.round_timeout, remaining) {
+            (Some(round_timeout), Some(remaining)) => Some(round_timeout.min(remaining)),
+            (round_timeout, remaining) => round_timeout.or(remaining),
+        }
+    }
+
+    /// Returns true if the session deadline has been exceeded.
+    pub fn is_deadline_exceeded(&self, progress_tracker: &ProgressTracker) -> bool {
+        self.session_deadline
+            .map_or(false, |deadline| progress_tracker.elapsed() >= deadline)
+    }
+}
+
 /// A generic augmented type.
 #[derive(Clone)]
 pub struct AugmentedType<T, E> {
@@ -210,6 +526,44 @@ pub struct AugmentedType<T, E> {
     pub extra: Option<E>,
 }
 
+/// An augmented protocol message wrapped in a versioned protocol envelope (i.e bound to a wallet and a session).
+pub type AugmentedEnvelope<T, E> = ProtocolEnvelope<Msg<AugmentedType<T, E>>>;
+
+/// Wraps outgoing augmented protocol messages in protocol envelopes for the negotiated protocol version, wallet and session.
+pub fn seal_messages<T, E>(
+    messages: Vec<Msg<AugmentedType<T, E>>>,
+    version: u16,
+    wallet_id: [u8; 32],
+    session_id: [u8; 32],
+) -> Vec<AugmentedEnvelope<T, E>> {
+    messages
+        .into_iter()
+        .map(|msg| ProtocolEnvelope::new(version, wallet_id, session_id, msg))
+        .collect()
+}
+
+/// Wraps a protocol message from a legacy (i.e non-augmented GG18/GG20) party as an augmented protocol message
+/// without additional parameters (e.g for co-signing with legacy parties, see [`AugmentedSigning::with_legacy_parties`](crate::AugmentedSigning::with_legacy_parties)).
+pub fn from_legacy_message<T, E>(msg: Msg<T>) -> Msg<AugmentedType<T, E>> {
+    Msg {
+        sender: msg.sender,
+        receiver: msg.receiver,
+        body: AugmentedType {
+            base: msg.body,
+            extra: None,
+        },
+    }
+}
+
+/// Strips additional parameters from an augmented protocol message for a legacy (i.e non-augmented GG18/GG20) party.
+pub fn to_legacy_message<T, E>(msg: Msg<AugmentedType<T, E>>) -> Msg<T> {
+    Msg {
+        sender: msg.sender,
+        receiver: msg.receiver,
+        body: msg.body.base,
+    }
+}
+
 /// Additional parameters for identity authentication.
 #[derive(Debug, Clone)]
 pub struct IdentityAuthParams {
@@ -219,6 +573,74 @@ pub struct IdentityAuthParams {
     pub verifying_signature: Signature,
 }
 
+/// A signed notice that a party aborted a protocol session (e.g because it was cancelled).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AbortNotice {
+    /// Identifier of the aborted session.
+    pub session_id: [u8; 32],
+    /// Index of the aborting party.
+    pub idx: u16,
+    /// Round in which the session was aborted.
+    pub round: u16,
+    /// Verifying key of the aborting party.
+    pub verifying_key: VerifyingKey,
+    /// Signature of the abort notice.
+    pub signature: Signature,
+}
+
+impl AbortNotice {
+    /// Given a session identifier, the index of the aborting party, the round and the identity provider of the aborting party,
+    /// returns a signed abort notice.
+    pub fn new(
+        session_id: [u8; 32],
+        idx: u16,
+        round: u16,
+        identity_provider: &impl IdentityProvider,
+    ) -> Self {
+        let (verifying_key, signature) = wamu_core::wrappers::initiate_request_with_signature(
+            &Self::message_hash(&session_id, idx, round),
+            identity_provider,
+        );
+        Self {
+            session_id,
+            idx,
+            round,
+            verifying_key,
+            signature,
+        }
+    }
+
+    /// Given the expected session identifier and a list of verifying keys for all parties,
+    /// returns an ok result if the abort notice is for the session and signed by a verified party,
+    /// or an appropriate error otherwise.
+    pub fn verify(
+        &self,
+        session_id: &[u8; 32],
+        verified_parties: &[VerifyingKey],
+    ) -> Result<(), wamu_core::Error> {
+        wamu_core::wrappers::verify_request_with_signature(
+            &Self::message_hash(session_id, self.idx, self.round),
+            &self.verifying_key,
+            &self.signature,
+            verified_parties,
+        )
+    }
+
+    // Binds the abort notice to the session, the aborting party and the round.
+    fn message_hash(session_id: &[u8; 32], idx: u16, round: u16) -> Vec<u8> {
+        use sha2::{digest::Update, Digest};
+        let hasher = sha2::Sha256::new();
+        hasher
+            .chain(b"abort")
+            .chain(session_id)
+            .chain(idx.to_be_bytes())
+            .chain(round.to_be_bytes())
+            .finalize()
+            .deref()
+            .to_vec()
+    }
+}
+
 /// Additional output as "signing share" and "sub-share" tuple.
 pub type SubShareOutput = (SigningShare, SubShare);
 
@@ -229,10 +651,43 @@ pub enum Error<T: IsCritical> {
     Core(wamu_core::Error),
     /// A wrapped state machine error from `cggmp_threshold_ecdsa`.
     StateMachine(T),
-    /// Missing augmentation parameters.
-    MissingParams { bad_actors: Vec<usize> },
+    /// A wrapped verification error from `wamu-core` with the offending party's verifying key, index and round.
+    Blame(wamu_core::Blame<wamu_core::Error>),
+    /// Missing augmentation parameters (from the `bad_actors` in round `round`).
+    MissingParams { bad_actors: Vec<usize>, round: u16 },
     /// An insecure FS-DKR threshold (i.e t > n/2, breaking the honest majority assumption).
     BadFSDKRThreshold,
+    /// A pre-signing output (i.e nonce) that was already consumed for a different message.
+    NonceReuse { pre_signing_output_idx: usize },
+    /// A signing request that violates the signing policy of the wallet.
+    PolicyViolation(wamu_core::PolicyError),
+    /// An assembled signature that doesn't verify against the group public key and message digest
+    /// (with the `bad_actors` whose partial signatures are inconsistent with their public contributions,
+    /// which is empty if the public contributions themselves are misreported and can't be attributed).
+    InvalidSignature { bad_actors: Vec<usize> },
+    /// An invalid protocol configuration (e.g an out of range threshold or party index, or a mismatched list of parties).
+    InvalidConfig,
+    /// A round timeout in round `round` (with the `bad_actors` whose messages are missing).
+    RoundTimeout {
+        round: u16,
+        bad_actors: Vec<usize>,
+        action: TimeoutAction,
+    },
+    /// An exceeded session deadline in round `round` (with the `bad_actors` whose messages are missing).
+    DeadlineExceeded { round: u16, bad_actors: Vec<usize> },
+    /// Conflicting messages (i.e equivocation) from the party with index `idx` and verifying key `verifying_key` in round `round`.
+    Equivocation {
+        idx: u16,
+        verifying_key: VerifyingKey,
+        round: u16,
+    },
+    /// Inconsistent broadcast messages in round `round` (i.e conflicting broadcast messages or echoes from the `bad_actors`).
+    InconsistentBroadcast { round: u16, bad_actors: Vec<usize> },
+    /// Auxiliary parameters (i.e Paillier and ring-Pedersen moduli) that weren't rotated by key refresh
+    /// (for the `bad_actors` that reused their previous parameters).
+    StaleAuxiliaryParams { bad_actors: Vec<usize> },
+    /// An expired retransmission timer in round `round` (with the `receivers` of the retransmitted unacknowledged messages).
+    Retransmission { round: u16, receivers: Vec<usize> },
 }
 
 impl<T: IsCritical> IsCritical for Error<T> {
@@ -240,12 +695,134 @@ impl<T: IsCritical> IsCritical for Error<T> {
         match self {
             // All core errors are critical.
             Error::Core(_) => true,
+            // Blamed verification errors are critical.
+            Error::Blame(_) => true,
             // Wrapped state machine errors call the wrapped implementation.
             Error::StateMachine(error) => error.is_critical(),
             // Augmentation parameters can't be skipped.
             Error::MissingParams { .. } => true,
             // FS-DKR assumptions can't be broken for key refresh.
             Error::BadFSDKRThreshold => true,
+            // Pre-signing outputs must never be reused for different messages.
+            Error::NonceReuse { .. } => true,
+            // Parties never join signing sessions that violate the signing policy.
+            Error::PolicyViolation(_) => true,
+            // Invalid signatures are never returned.
+            Error::InvalidSignature { .. } => true,
+            // Protocols can't start with invalid configurations.
+            Error::InvalidConfig => true,
+            // Round timeouts are only critical if the protocol should be aborted.
+            Error::RoundTimeout { action, .. } => *action == TimeoutAction::Abort,
+            // Session deadlines can't be exceeded.
+            Error::DeadlineExceeded { .. } => true,
+            // Parties can't send conflicting messages.
+            Error::Equivocation { .. } => true,
+            // Broadcast rounds must be reliable.
+            Error::InconsistentBroadcast { .. } => true,
+            // Auxiliary parameters must be rotated if required.
+            Error::StaleAuxiliaryParams { .. } => true,
+            // Retransmissions only resend unacknowledged messages.
+            Error::Retransmission { .. } => false,
+        }
+    }
+}
+
+impl<T: IsCritical> Error<T> {
+    /// Given a verification error, the index of the offending party, its identity authentication parameters and the round,
+    /// returns a verification error with blame information.
+    pub fn blame(
+        error: impl Into<wamu_core::Error>,
+        sender: u16,
+        params: &IdentityAuthParams,
+        round: u16,
+    ) -> Self {
+        Self::Blame(
+            wamu_core::Blame::new(error.into())
+                .with_verifying_key(params.verifying_key.clone())
+                .with_idx(sender)
+                .with_round(round),
+        )
+    }
+}
+
+impl<T: IsCritical> Error<T> {
+    /// Returns the indices of the parties blamed for the error (if any) e.g the culprits of an identifiable abort.
+    pub fn bad_actors(&self) -> Vec<u16> {
+        match self {
+            Error::Blame(blame) => blame.idx.into_iter().collect(),
+            Error::MissingParams { bad_actors, .. }
+            | Error::InvalidSignature { bad_actors }
+            | Error::StaleAuxiliaryParams { bad_actors }
+            | Error::RoundTimeout { bad_actors, .. }
+            | Error::DeadlineExceeded { bad_actors, .. }
+            | Error::InconsistentBroadcast { bad_actors, .. } => {
+                bad_actors.iter().map(|idx| *idx as u16).collect()
+            }
+            Error::Equivocation { idx, .. } => vec![*idx],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Given verifying keys for all parties (in party index order),
+    /// returns the verifying keys (i.e Wamu identities) of the parties blamed for the error (if any).
+    pub fn culprits(&self, verified_parties: &[VerifyingKey]) -> Vec<VerifyingKey> {
+        match self {
+            // Blamed verification errors include the verifying key of the offending party.
+            Error::Blame(wamu_core::Blame {
+                verifying_key: Some(verifying_key),
+                ..
+            }) => vec![verifying_key.clone()],
+            // Equivocation errors include the verifying key of the offending party.
+            Error::Equivocation { verifying_key, .. } => vec![verifying_key.clone()],
+            _ => self
+                .bad_actors()
+                .into_iter()
+                .filter_map(|idx| {
+                    verified_parties
+                        .get((idx as usize).checked_sub(1)?)
+                        .cloned()
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<T: IsCritical + std::fmt::Debug> Error<T> {
+    /// Returns a serializable error report (with the round and blamed parties, if known).
+    pub fn report(&self) -> ErrorReport {
+        match self {
+            Error::Core(error) => ErrorReport::new(error),
+            Error::Blame(blame) => ErrorReport::from(blame),
+            Error::StateMachine(error) => ErrorReport::new(error),
+            Error::MissingParams { bad_actors, round }
+            | Error::RoundTimeout {
+                bad_actors, round, ..
+            }
+            | Error::DeadlineExceeded { bad_actors, round }
+            | Error::InconsistentBroadcast { bad_actors, round } => bad_actors
+                .iter()
+                .fold(ErrorReport::new(self).with_round(*round), |report, idx| {
+                    report.with_blamed_party(Some(*idx as u16), None)
+                }),
+            Error::InvalidSignature { bad_actors } | Error::StaleAuxiliaryParams { bad_actors } => {
+                bad_actors
+                    .iter()
+                    .fold(ErrorReport::new(self), |report, idx| {
+                        report.with_blamed_party(Some(*idx as u16), None)
+                    })
+            }
+            Error::Equivocation {
+                idx,
+                verifying_key,
+                round,
+            } => ErrorReport::new(self)
+                .with_round(*round)
+                .with_blamed_party(Some(*idx), Some(verifying_key.clone())),
+            Error::Retransmission { round, .. } => ErrorReport::new(self).with_round(*round),
+            Error::BadFSDKRThreshold
+            | Error::NonceReuse { .. }
+            | Error::PolicyViolation(_)
+            | Error::InvalidConfig => ErrorReport::new(self),
         }
     }
 }
@@ -262,6 +839,60 @@ impl<T: IsCritical> From<wamu_core::CryptoError> for Error<T> {
     }
 }
 
+impl<T: IsCritical> Error<Error<T>> {
+    /// Returns the error of a wrapped augmented state machine as is, or the wrapper's error otherwise
+    /// (e.g for augmented state machines wrapped with [`MessageAuthentication`](crate::MessageAuthentication)).
+    pub fn flatten(self) -> Error<T> {
+        match self {
+            Error::StateMachine(error) => error,
+            Error::Core(error) => Error::Core(error),
+            Error::Blame(blame) => Error::Blame(blame),
+            Error::MissingParams { bad_actors, round } => {
+                Error::MissingParams { bad_actors, round }
+            }
+            Error::BadFSDKRThreshold => Error::BadFSDKRThreshold,
+            Error::NonceReuse {
+                pre_signing_output_idx,
+            } => Error::NonceReuse {
+                pre_signing_output_idx,
+            },
+            Error::PolicyViolation(error) => Error::PolicyViolation(error),
+            Error::InvalidSignature { bad_actors } => Error::InvalidSignature { bad_actors },
+            Error::InvalidConfig => Error::InvalidConfig,
+            Error::RoundTimeout {
+                round,
+                bad_actors,
+                action,
+            } => Error::RoundTimeout {
+                round,
+                bad_actors,
+                action,
+            },
+            Error::DeadlineExceeded { round, bad_actors } => {
+                Error::DeadlineExceeded { round, bad_actors }
+            }
+            Error::Equivocation {
+                idx,
+                verifying_key,
+                round,
+            } => Error::Equivocation {
+                idx,
+                verifying_key,
+                round,
+            },
+            Error::InconsistentBroadcast { round, bad_actors } => {
+                Error::InconsistentBroadcast { round, bad_actors }
+            }
+            Error::StaleAuxiliaryParams { bad_actors } => {
+                Error::StaleAuxiliaryParams { bad_actors }
+            }
+            Error::Retransmission { round, receivers } => {
+                Error::Retransmission { round, receivers }
+            }
+        }
+    }
+}
+
 /// Implements `StateMachine` trait for types that implement `AugmentedStateMachine`.
 ///
 /// Requires the types of the `AugmentedStateMachine`, the wrapped `StateMachine`, additional parameters and additional output.
@@ -290,11 +921,11 @@ macro_rules! impl_state_machine_for_augmented_state_machine {
             }
 
             fn round_timeout(&self) -> Option<Duration> {
-                self.state_machine().round_timeout()
+                self.augmented_round_timeout()
             }
 
             fn round_timeout_reached(&mut self) -> Self::Err {
-                self.state_machine_mut().round_timeout_reached().into()
+                self.augmented_round_timeout_reached()
             }
 
             fn is_finished(&self) -> bool {
@@ -3,8 +3,10 @@
 //! NOTE: Used by share addition, share removal, threshold modification and share recovery with quorum protocols.
 
 use round_based::{IsCritical, Msg, StateMachine};
+use wamu_core::crypto::VerifyingKey;
 use wamu_core::IdentityProvider;
 
+use crate::augmented_state_machine::AbortNotice;
 use crate::key_refresh::AugmentedKeyRefresh;
 use crate::{IdentityAuthentication, QuorumApproval};
 
@@ -13,6 +15,9 @@ pub trait AuthorizedKeyRefresh<'a, I: IdentityProvider + 'a>: StateMachine {
     /// The type of the authorization state machine.
     type InitStateMachineType: StateMachine;
 
+    /// Returns the verifying keys of the parties.
+    fn verified_parties(&self) -> &'a [VerifyingKey];
+
     /// Returns an immutable reference to the authorization state machine.
     fn auth_state_machine(&self) -> &Self::InitStateMachineType;
 
@@ -38,12 +43,15 @@ pub trait AuthorizedKeyRefresh<'a, I: IdentityProvider + 'a>: StateMachine {
         &mut self,
     ) -> &mut Vec<Msg<Message<'a, I, <Self::InitStateMachineType as StateMachine>::MessageBody>>>;
 
-    /// Returns an immutable reference to an "out of order" message buffer.
+    /// Returns an immutable reference to an "out of order" message buffer
+    /// (i.e key refresh messages received before the key refresh state machine is active,
+    /// with at most one message per sender and round).
     fn out_of_order_buffer(
         &self,
     ) -> &Vec<Msg<Message<'a, I, <Self::InitStateMachineType as StateMachine>::MessageBody>>>;
 
-    /// Returns a mutable reference to an "out of order" message buffer.
+    /// Returns a mutable reference to an "out of order" message buffer
+    /// (i.e key refresh messages received before the key refresh state machine is active).
     fn out_of_order_buffer_mut(
         &mut self,
     ) -> &mut Vec<Msg<Message<'a, I, <Self::InitStateMachineType as StateMachine>::MessageBody>>>;
@@ -56,6 +64,16 @@ pub trait AuthorizedKeyRefresh<'a, I: IdentityProvider + 'a>: StateMachine {
         Error<'a, I, <Self::InitStateMachineType as StateMachine>::Err>,
     >;
 
+    /// Verifies the outcome of the finished authorization state machine before the key refresh is activated
+    /// (e.g re-verifies the collected command approvals for quorum approved key refresh).
+    ///
+    /// **NOTE:** Defaults to trusting the authorization state machine.
+    fn verify_authorization(
+        &self,
+    ) -> Result<(), Error<'a, I, <Self::InitStateMachineType as StateMachine>::Err>> {
+        Ok(())
+    }
+
     /// Updates the composite message queue by
     /// retrieving the message queue from the currently active wrapped state machines (i.e initialization or key refresh).
     ///
@@ -92,6 +110,22 @@ pub trait AuthorizedKeyRefresh<'a, I: IdentityProvider + 'a>: StateMachine {
         Ok(())
     }
 
+    /// Cancels the protocol and returns a signed abort notice (for the given session identifier) that other parties can verify.
+    ///
+    /// **NOTE:** The state machine is consumed (i.e dropped), so that the authorization and key refresh state machines
+    /// and any reconstructed or buffered secrets they hold are zeroized (for secrets that are zeroized on drop).
+    fn cancel(self, identity_provider: &impl IdentityProvider, session_id: [u8; 32]) -> AbortNotice
+    where
+        Self: Sized,
+    {
+        AbortNotice::new(
+            session_id,
+            self.party_ind(),
+            self.current_round(),
+            identity_provider,
+        )
+    }
+
     /// Transitions to the key refresh state machine if the initialization state machine is finished and the key refresh state machine is not yet active.
     ///
     /// **NOTE:** This method is called at the end of both [`handle_incoming`](StateMachine::handle_incoming) and [`proceed`](StateMachine::proceed).
@@ -99,26 +133,36 @@ pub trait AuthorizedKeyRefresh<'a, I: IdentityProvider + 'a>: StateMachine {
         &mut self,
     ) -> Result<(), Error<'a, I, <Self::InitStateMachineType as StateMachine>::Err>> {
         if self.refresh_state_machine().is_none() && self.auth_state_machine().is_finished() {
+            // Verifies the authorization outcome.
+            self.verify_authorization()?;
+
             // Create a key refresh state machine.
-            let mut key_refresh = self.create_key_refresh()?;
+            let key_refresh = self.create_key_refresh()?;
+
+            // Sets key refresh as the active state machine.
+            self.set_refresh_state_machine(key_refresh);
 
-            // Forwards any "out of order" refresh messages to the key refresh state machine.
+            // Replays any "out of order" refresh messages to the key refresh state machine.
             let out_of_order_messages = self.out_of_order_buffer_mut().split_off(0);
-            if !out_of_order_messages.is_empty() {
+            if let Some(refresh_state_machine) = self.refresh_state_machine_mut() {
                 for msg in out_of_order_messages {
                     if let Message::Refresh(msg_body) = msg.body {
-                        key_refresh.handle_incoming(Msg {
+                        let result = refresh_state_machine.handle_incoming(Msg {
                             sender: msg.sender,
                             receiver: msg.receiver,
                             body: *msg_body,
-                        })?;
+                        });
+                        // Non-critical errors (e.g duplicate messages) are ignored
+                        // because they can't be reported for the message that caused them.
+                        if let Err(error) = result {
+                            if error.is_critical() {
+                                return Err(Error::Refresh(error));
+                            }
+                        }
                     }
                 }
             }
 
-            // Sets key refresh as the active state machine.
-            self.set_refresh_state_machine(key_refresh);
-
             // Retrieves messages from state transitions (if any) and wraps them.
             self.update_composite_message_queue()?;
         }
@@ -144,10 +188,16 @@ pub enum Error<'a, I: IdentityProvider, E> {
     OutOfOrderMessage,
 }
 
-impl<'a, I: IdentityProvider, E> IsCritical for Error<'a, I, E> {
+impl<'a, I: IdentityProvider, E: IsCritical> IsCritical for Error<'a, I, E> {
     fn is_critical(&self) -> bool {
-        // Out of order messages are not critical errors, while all other errors are critical.
-        !matches!(self, Error::OutOfOrderMessage)
+        // Out of order (i.e late initialization or dropped early key refresh) messages are not critical errors,
+        // non-critical errors of the wrapped state machines (e.g elapsed time lock windows) are forwarded,
+        // while all other errors are critical.
+        match self {
+            Error::Init(error) => error.is_critical(),
+            Error::Refresh(error) => error.is_critical(),
+            _ => !matches!(self, Error::OutOfOrderMessage),
+        }
     }
 }
 
@@ -176,7 +226,7 @@ macro_rules! impl_state_machine_for_authorized_key_refresh {
             fn handle_incoming(&mut self, msg: Msg<Self::MessageBody>) -> Result<(), Self::Err> {
                 match msg.body {
                     // Initialization messages are forwarded to the initialization state machine if it's still active,
-                    // otherwise an error is returned.
+                    // otherwise a (non-critical) error is returned because the initialization phase is already finished.
                     Message::Init(id_msg) => match self.refresh_state_machine() {
                         None => {
                             self.auth_state_machine_mut().handle_incoming(Msg {
@@ -185,33 +235,51 @@ macro_rules! impl_state_machine_for_authorized_key_refresh {
                                 body: id_msg,
                             })?;
                         }
-                        Some(_) => {
-                            self.out_of_order_buffer_mut().push(Msg {
-                                sender: msg.sender,
-                                receiver: msg.receiver,
-                                body: Message::Init(id_msg),
-                            });
-                            return Err(Error::OutOfOrderMessage);
-                        }
+                        Some(_) => return Err(Error::OutOfOrderMessage),
                     },
                     // Refresh messages are forwarded to the refresh state machine if it's active,
-                    // otherwise an error is returned.
+                    // otherwise they're buffered and replayed once the initialization phase is finished
+                    // (i.e asynchronous networks can deliver key refresh messages before the local initialization phase is finished).
                     Message::Refresh(refresh_msg) => {
+                        let refresh_msg = Msg {
+                            sender: msg.sender,
+                            receiver: msg.receiver,
+                            body: *refresh_msg,
+                        };
                         match self.refresh_state_machine_mut() {
                             Some(refresh_state_machine) => {
-                                refresh_state_machine.handle_incoming(Msg {
-                                    sender: msg.sender,
-                                    receiver: msg.receiver,
-                                    body: *refresh_msg,
-                                })?;
+                                refresh_state_machine.handle_incoming(refresh_msg)?;
                             }
                             None => {
-                                self.out_of_order_buffer_mut().push(Msg {
-                                    sender: msg.sender,
-                                    receiver: msg.receiver,
-                                    body: Message::Refresh(refresh_msg),
+                                // Only one message per sender and round is buffered,
+                                // and messages from unknown parties (i.e without a verifying key)
+                                // or with additional parameters that aren't signed by a verified party are dropped
+                                // (i.e a non-critical error is returned), so that early messages can't exhaust memory.
+                                let round =
+                                    AugmentedKeyRefresh::<I>::message_round(&refresh_msg.body);
+                                let is_known_party = (1..=self.verified_parties().len())
+                                    .contains(&(refresh_msg.sender as usize));
+                                let is_buffered = self.out_of_order_buffer().iter().any(|it| {
+                                    it.sender == refresh_msg.sender
+                                        && matches!(
+                                            &it.body,
+                                            Message::Refresh(body)
+                                                if AugmentedKeyRefresh::<I>::message_round(body) == round
+                                        )
                                 });
-                                return Err(Error::OutOfOrderMessage);
+                                if !is_known_party
+                                    || is_buffered
+                                    || !AugmentedKeyRefresh::<I>::is_signed_by_verified_party(
+                                        &refresh_msg,
+                                        self.verified_parties(),
+                                    )
+                                {
+                                    return Err(Error::OutOfOrderMessage);
+                                }
+                                self.out_of_order_buffer_mut().push(
+                                    refresh_msg.map_body(|body| Message::Refresh(Box::new(body))),
+                                );
+                                return Ok(());
                             }
                         }
                     }
@@ -251,11 +319,21 @@ macro_rules! impl_state_machine_for_authorized_key_refresh {
             }
 
             fn round_timeout(&self) -> Option<Duration> {
-                None
+                // `round_timeout` is forwarded to the active state machine (e.g for time-locked quorum approvals).
+                match self.refresh_state_machine() {
+                    None => self.auth_state_machine().round_timeout(),
+                    Some(refresh_state_machine) => refresh_state_machine.round_timeout(),
+                }
             }
 
             fn round_timeout_reached(&mut self) -> Self::Err {
-                panic!("no timeout was set")
+                // `round_timeout_reached` is forwarded to the active state machine.
+                match self.refresh_state_machine_mut() {
+                    None => Error::Init(self.auth_state_machine_mut().round_timeout_reached()),
+                    Some(refresh_state_machine) => {
+                        Error::Refresh(refresh_state_machine.round_timeout_reached())
+                    }
+                }
             }
 
             fn is_finished(&self) -> bool {
@@ -306,9 +384,14 @@ macro_rules! impl_state_machine_for_authorized_key_refresh {
 /// Implements all required `AuthorizedKeyRefresh` getters.
 ///
 /// Requires names of the associated fields
-/// (.ie the authorization and key refresh `StateMachine` and the composite message queue).
+/// (.ie the verifying keys of the parties, the authorization and key refresh `StateMachine`,
+/// the composite message queue and the "out of order" message buffer).
 macro_rules! impl_required_authorized_key_refresh_getters {
-    ($auth_state_machine:ident, $refresh_state_machine:ident, $message_queue:ident, $out_of_order_buffer:ident) => {
+    ($verified_parties:ident, $auth_state_machine:ident, $refresh_state_machine:ident, $message_queue:ident, $out_of_order_buffer:ident) => {
+        fn verified_parties(&self) -> &'a [VerifyingKey] {
+            self.$verified_parties
+        }
+
         fn auth_state_machine(&self) -> &Self::InitStateMachineType {
             &self.$auth_state_machine
         }
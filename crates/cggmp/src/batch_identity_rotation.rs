@@ -0,0 +1,564 @@
+//! Batch (i.e roster-wide) identity rotation implementation.
+//!
+//! Multiple parties rotate their identities in a single coordinated session,
+//! with all parties cross-verifying every old → new verifying key binding.
+//!
+//! Ref: <https://wamu.tech/specification#identity-rotation>.
+
+use round_based::{IsCritical, Msg, StateMachine};
+use std::collections::HashMap;
+use std::time::Duration;
+use wamu_core::crypto::{Random32Bytes, VerifyingKey};
+use wamu_core::{
+    IdentityAuthedRequestError, IdentityAuthedRequestPayload, IdentityProvider,
+    IdentityRotationChallengeResponsePayload, SigningShare, SubShare,
+};
+
+/// A [StateMachine](StateMachine) that implements batch identity rotation
+/// (i.e [identity rotation as described by the Wamu protocol](https://wamu.tech/specification#identity-rotation) for multiple parties in a single session).
+pub struct BatchIdentityRotation<'a, I: IdentityProvider> {
+    /// The decentralized identity provider of the party.
+    identity_provider: &'a I,
+    /// Verifying keys for other the parties.
+    verified_parties: &'a [VerifyingKey],
+    /// Party index.
+    idx: u16,
+    /// Total number of parties.
+    n_parties: u16,
+    /// Indices of the rotating parties.
+    rotating_parties: &'a [u16],
+    /// The new decentralized identity provider of the party
+    /// (only `Some` for the rotating parties, `None` for all other parties).
+    new_identity_provider_option: Option<&'a I>,
+    /// The "signing share" of the party
+    /// (only `Some` for the rotating parties, `None` for all other parties).
+    signing_share_option: Option<&'a SigningShare>,
+    /// The "sub-share" of the party
+    /// (only `Some` for the rotating parties, `None` for all other parties).
+    sub_share_option: Option<&'a SubShare>,
+    /// Current round.
+    round: Round,
+    /// Outgoing message queue.
+    message_queue: Vec<Msg<Message>>,
+    /// Identity rotation requests from the other rotating parties.
+    requests: HashMap<u16, IdentityAuthedRequestPayload>,
+    /// Challenge fragments for each rotating party (i.e rotating party index -> sender index -> challenge fragment).
+    challenge_fragments: HashMap<u16, HashMap<u16, Random32Bytes>>,
+    /// Identity rotation challenge responses from the other rotating parties.
+    responses: HashMap<u16, IdentityRotationChallengeResponsePayload>,
+    /// Verified new verifying keys for the other rotating parties.
+    new_verifying_keys: HashMap<u16, VerifyingKey>,
+    /// Outcomes of the identity rotation from the other parties.
+    received_outcomes: HashMap<u16, Option<bool>>,
+}
+
+impl<'a, I: IdentityProvider> BatchIdentityRotation<'a, I> {
+    /// Initializes party for the batch identity rotation protocol.
+    pub fn new(
+        identity_provider: &'a I,
+        verified_parties: &'a [VerifyingKey],
+        idx: u16,
+        n_parties: u16,
+        rotating_parties: &'a [u16],
+        new_identity_provider_option: Option<&'a I>,
+        signing_share_option: Option<&'a SigningShare>,
+        sub_share_option: Option<&'a SubShare>,
+    ) -> Result<BatchIdentityRotation<'a, I>, Error> {
+        // Verifies that the party's role is consistent with the list of rotating parties.
+        if rotating_parties.contains(&idx) != new_identity_provider_option.is_some() {
+            return Err(Error::UnexpectedRotatingParty);
+        }
+
+        // Generates initiation payload for rotating parties.
+        let mut message_queue = Vec::new();
+        if new_identity_provider_option.is_some() {
+            let request = wamu_core::identity_rotation::initiate(identity_provider);
+            message_queue.push(Msg {
+                sender: idx,
+                receiver: None,
+                body: Message::Round1(request),
+            });
+        }
+
+        // Returns batch identity rotation machine.
+        Ok(Self {
+            identity_provider,
+            verified_parties,
+            idx,
+            n_parties,
+            rotating_parties,
+            new_identity_provider_option,
+            signing_share_option,
+            sub_share_option,
+            round: Round::One,
+            message_queue,
+            requests: HashMap::new(),
+            challenge_fragments: HashMap::new(),
+            responses: HashMap::new(),
+            new_verifying_keys: HashMap::new(),
+            received_outcomes: HashMap::new(),
+        })
+    }
+
+    /// Returns the number of rotating parties excluding this party.
+    fn n_other_rotating_parties(&self) -> usize {
+        self.rotating_parties
+            .iter()
+            .filter(|idx| **idx != self.idx)
+            .count()
+    }
+
+    /// Returns an error if the sender is not one of the rotating parties.
+    fn verify_rotating_party(&self, sender: u16) -> Result<(), Error> {
+        if self.rotating_parties.contains(&sender) {
+            Ok(())
+        } else {
+            Err(Error::UnexpectedRotatingParty)
+        }
+    }
+
+    /// Returns the collected challenge fragments for a rotating party.
+    fn challenge_fragments_for(&self, rotating_idx: u16) -> Vec<Random32Bytes> {
+        self.challenge_fragments
+            .get(&rotating_idx)
+            .map(|fragments| fragments.values().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns the list of verifying keys for all parties with all verified rotations applied.
+    fn output_verified_parties(&self) -> Vec<VerifyingKey> {
+        let mut output_verified_parties = self.verified_parties.to_vec();
+        for (rotating_idx, new_verifying_key) in &self.new_verifying_keys {
+            output_verified_parties[*rotating_idx as usize - 1] = new_verifying_key.clone();
+        }
+        if let Some(new_identity_provider) = self.new_identity_provider_option {
+            output_verified_parties[self.idx as usize - 1] = new_identity_provider.verifying_key();
+        }
+        output_verified_parties
+    }
+}
+
+impl<'a, I: IdentityProvider> StateMachine for BatchIdentityRotation<'a, I> {
+    type MessageBody = Message;
+    type Err = Error;
+    type Output = (Option<(SigningShare, SubShare)>, Vec<VerifyingKey>);
+
+    fn handle_incoming(&mut self, msg: Msg<Self::MessageBody>) -> Result<(), Self::Err> {
+        match msg.body {
+            // All parties store the identity rotation requests from the other rotating parties.
+            Message::Round1(request) => {
+                self.verify_rotating_party(msg.sender)?;
+                self.requests.insert(msg.sender, request);
+            }
+            // All parties store the received identity challenges for each rotating party.
+            Message::Round2(challenge_fragments) => {
+                for (rotating_idx, challenge_fragment) in challenge_fragments {
+                    self.verify_rotating_party(rotating_idx)?;
+                    if rotating_idx != msg.sender {
+                        self.challenge_fragments
+                            .entry(rotating_idx)
+                            .or_default()
+                            .insert(msg.sender, challenge_fragment);
+                    }
+                }
+            }
+            // All parties store the identity challenge responses from the other rotating parties.
+            Message::Round3(response) => {
+                self.verify_rotating_party(msg.sender)?;
+                self.responses.insert(msg.sender, response);
+            }
+            // All parties store the received identity rotation confirmations.
+            Message::Round4(outcome) => {
+                self.received_outcomes.insert(msg.sender, outcome);
+            }
+        }
+        Ok(())
+    }
+
+    fn message_queue(&mut self) -> &mut Vec<Msg<Self::MessageBody>> {
+        self.message_queue.as_mut()
+    }
+
+    fn wants_to_proceed(&self) -> bool {
+        match &self.round {
+            // All parties need to receive identity rotation requests from all other rotating parties.
+            Round::One => self.requests.len() == self.n_other_rotating_parties(),
+            // All parties need to receive challenge fragments for each rotating party from all parties except that rotating party (i.e n_parties - 1).
+            Round::Two => self.rotating_parties.iter().all(|rotating_idx| {
+                self.challenge_fragments
+                    .get(rotating_idx)
+                    .map_or(0, HashMap::len)
+                    == self.n_parties as usize - 1
+            }),
+            // All parties need to receive challenge responses from all other rotating parties.
+            Round::Three => self.responses.len() == self.n_other_rotating_parties(),
+            // All parties need to receive outcomes from all other parties (i.e n_parties - 1).
+            Round::Four => self.received_outcomes.len() == self.n_parties as usize - 1,
+            // The protocol is completed at this point and output should be picked.
+            Round::Final | Round::Gone => false,
+        }
+    }
+
+    fn proceed(&mut self) -> Result<(), Self::Err> {
+        match self.round {
+            // All parties verify the identity rotation requests from the other rotating parties and
+            // broadcast a challenge fragment for each of them.
+            Round::One => {
+                let mut challenge_fragments = Vec::new();
+                for (rotating_idx, request) in &self.requests {
+                    let challenge_fragment =
+                        wamu_core::identity_rotation::verify_request_and_initiate_challenge(
+                            request,
+                            self.verified_parties,
+                        )?;
+                    challenge_fragments.push((*rotating_idx, challenge_fragment));
+                }
+
+                // Stores the party's own challenge fragments.
+                for (rotating_idx, challenge_fragment) in &challenge_fragments {
+                    self.challenge_fragments
+                        .entry(*rotating_idx)
+                        .or_default()
+                        .insert(self.idx, *challenge_fragment);
+                }
+                // Adds challenge fragments to the message queue for Round 2.
+                self.message_queue.push(Msg {
+                    sender: self.idx,
+                    receiver: None,
+                    body: Message::Round2(challenge_fragments),
+                });
+
+                // Everyone moves on to the next round.
+                self.round = Round::Two;
+            }
+            Round::Two => {
+                // Only the rotating parties need to respond to the challenge.
+                if let Some(new_identity_provider) = self.new_identity_provider_option {
+                    let payload = wamu_core::identity_rotation::challenge_response(
+                        &self.challenge_fragments_for(self.idx),
+                        self.identity_provider,
+                        new_identity_provider,
+                    );
+                    self.message_queue.push(Msg {
+                        sender: self.idx,
+                        receiver: None,
+                        body: Message::Round3(payload),
+                    })
+                }
+                // Everyone moves on to the next round.
+                self.round = Round::Three;
+            }
+            // All parties cross-verify the old -> new verifying key bindings of all other rotating parties.
+            Round::Three => {
+                for (rotating_idx, response) in &self.responses {
+                    wamu_core::identity_rotation::verify_challenge_response(
+                        response,
+                        &self.challenge_fragments_for(*rotating_idx),
+                        &self.verified_parties[*rotating_idx as usize - 1],
+                    )?;
+                    self.new_verifying_keys
+                        .insert(*rotating_idx, response.new_verifying_key.clone());
+                }
+
+                // New verifying keys must be unique across the resulting roster.
+                let output_verified_parties = self.output_verified_parties();
+                if output_verified_parties
+                    .iter()
+                    .enumerate()
+                    .any(|(i, key)| output_verified_parties[..i].contains(key))
+                {
+                    return Err(Error::DuplicateVerifyingKey);
+                }
+
+                // Adds confirmation of successful rotation to the message for Round 4.
+                self.message_queue.push(Msg {
+                    sender: self.idx,
+                    receiver: None,
+                    body: Message::Round4(Some(true)),
+                });
+
+                // Everyone moves on to the next round.
+                self.round = Round::Four;
+            }
+            // All parties simply confirm that they received confirmations from all other parties in this round.
+            Round::Four => {
+                // Everyone moves on to the final round.
+                self.round = Round::Final;
+            }
+            // All that's left to do is producing/picking output.
+            Round::Final | Round::Gone => (),
+        }
+        Ok(())
+    }
+
+    fn round_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    fn round_timeout_reached(&mut self) -> Self::Err {
+        panic!("no timeout was set")
+    }
+
+    fn is_finished(&self) -> bool {
+        matches!(self.round, Round::Final)
+    }
+
+    fn pick_output(&mut self) -> Option<Result<Self::Output, Self::Err>> {
+        // Return an error if output was already picked.
+        if self.round == Round::Gone {
+            return Some(Err(Error::AlreadyPicked));
+        }
+
+        self.is_finished().then(|| {
+            // Picking output is infallible after this, so we set output to gone.
+            self.round = Round::Gone;
+
+            // For the rotating parties, we attempt to construct a new "signing share" and "sub-share",
+            // Any failures to construct a new "signing share" and "sub-share" are ignored
+            // and simply indicated by a `None` share output
+            // which tells the rotating party that the multi-party protocol was successful
+            // and it should independently retry rotating it's "signing share" and "sub-share".
+            let share_option =
+                self.new_identity_provider_option
+                    .and_then(|new_identity_provider| {
+                        self.signing_share_option
+                            .zip(self.sub_share_option)
+                            .and_then(|(signing_share, sub_share)| {
+                                wamu_core::identity_rotation::rotate_signing_and_sub_share(
+                                    signing_share,
+                                    sub_share,
+                                    self.identity_provider,
+                                    new_identity_provider,
+                                )
+                                .ok()
+                            })
+                    });
+
+            // All parties return the new list of `verified_parties` with all rotations applied.
+            Ok((share_option, self.output_verified_parties()))
+        })
+    }
+
+    fn current_round(&self) -> u16 {
+        match self.round {
+            Round::One => 1,
+            Round::Two => 2,
+            Round::Three => 3,
+            Round::Four => 4,
+            Round::Final | Round::Gone => 5,
+        }
+    }
+
+    fn total_rounds(&self) -> Option<u16> {
+        Some(5)
+    }
+
+    fn party_ind(&self) -> u16 {
+        self.idx
+    }
+
+    fn parties(&self) -> u16 {
+        self.n_parties
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Round {
+    One,
+    Two,
+    Three,
+    Four,
+    Final,
+    Gone,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Round1(IdentityAuthedRequestPayload),
+    Round2(Vec<(u16, Random32Bytes)>),
+    Round3(IdentityRotationChallengeResponsePayload),
+    Round4(Option<bool>),
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Core(IdentityAuthedRequestError),
+    DuplicateVerifyingKey,
+    UnexpectedRotatingParty,
+    AlreadyPicked,
+}
+
+impl From<IdentityAuthedRequestError> for Error {
+    fn from(error: IdentityAuthedRequestError) -> Self {
+        Self::Core(error)
+    }
+}
+
+impl From<wamu_core::Error> for Error {
+    fn from(error: wamu_core::Error) -> Self {
+        Self::Core(IdentityAuthedRequestError::Unauthorized(error))
+    }
+}
+
+impl IsCritical for Error {
+    fn is_critical(&self) -> bool {
+        true
+    }
+}
+
+// Implement `Debug` trait for `BatchIdentityRotation` for test simulations.
+#[cfg(any(test, feature = "dev"))]
+impl<'a, I: IdentityProvider> std::fmt::Debug for BatchIdentityRotation<'a, I> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Batch Identity Rotation")
+    }
+}
+
+#[cfg(any(test, feature = "dev"))]
+pub mod tests {
+    use super::*;
+    use crate::augmented_state_machine::{AugmentedType, SubShareOutput};
+    use crate::keygen::tests::simulate_keygen;
+    use curv::elliptic::curves::Secp256k1;
+    use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::keygen::LocalKey;
+    use round_based::dev::Simulation;
+    use wamu_core::test_utils::MockECDSAIdentityProvider;
+
+    pub fn simulate_batch_identity_rotation(
+        identity_providers: &[MockECDSAIdentityProvider],
+        // Rotating parties including the party index, new identity provider, "signing share" and "sub-share".
+        rotating_party_configs: &[(u16, &MockECDSAIdentityProvider, &SigningShare, &SubShare)],
+    ) -> Vec<(Option<(SigningShare, SubShare)>, Vec<VerifyingKey>)> {
+        // Creates simulation.
+        let mut simulation = Simulation::new();
+
+        // Creates a list of verifying keys for all parties.
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+
+        // Creates a list of rotating party indices.
+        let rotating_parties: Vec<u16> = rotating_party_configs
+            .iter()
+            .map(|(idx, ..)| *idx)
+            .collect();
+
+        // Adds parties to simulation.
+        for (i, identity_provider) in identity_providers.iter().enumerate() {
+            let party_idx = i as u16 + 1;
+            let rotating_party_config = rotating_party_configs
+                .iter()
+                .find(|(idx, ..)| *idx == party_idx);
+            simulation.add_party(
+                BatchIdentityRotation::new(
+                    identity_provider,
+                    &verifying_keys,
+                    party_idx,
+                    identity_providers.len() as u16,
+                    &rotating_parties,
+                    rotating_party_config
+                        .map(|(_, new_identity_provider, ..)| *new_identity_provider),
+                    rotating_party_config.map(|(_, _, signing_share, _)| *signing_share),
+                    rotating_party_config.map(|(.., sub_share)| *sub_share),
+                )
+                .unwrap(),
+            );
+        }
+
+        // Runs simulation and returns output.
+        simulation.run().unwrap()
+    }
+
+    pub fn generate_parties_and_simulate_batch_identity_rotation(
+        threshold: u16,
+        n_parties: u16,
+        rotating_party_indices: &[u16],
+    ) -> (
+        Vec<AugmentedType<LocalKey<Secp256k1>, SubShareOutput>>,
+        Vec<MockECDSAIdentityProvider>,
+        Vec<MockECDSAIdentityProvider>,
+    ) {
+        // Runs key gen simulation for test parameters.
+        let (keys, identity_providers) = simulate_keygen(threshold, n_parties);
+        // Verifies that we got enough keys and identities for "existing" parties from keygen.
+        assert_eq!(keys.len(), identity_providers.len());
+        assert_eq!(keys.len(), n_parties as usize);
+
+        // Creates new identity providers for rotating parties.
+        let new_identity_providers: Vec<MockECDSAIdentityProvider> = rotating_party_indices
+            .iter()
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+
+        // Creates rotating party configs.
+        let rotating_party_configs: Vec<(
+            u16,
+            &MockECDSAIdentityProvider,
+            &SigningShare,
+            &SubShare,
+        )> = rotating_party_indices
+            .iter()
+            .zip(new_identity_providers.iter())
+            .map(|(idx, new_identity_provider)| {
+                let (signing_share, sub_share) = keys[*idx as usize - 1].extra.as_ref().unwrap();
+                (*idx, new_identity_provider, signing_share, sub_share)
+            })
+            .collect();
+
+        // Runs batch identity rotation simulation for test parameters.
+        let results =
+            simulate_batch_identity_rotation(&identity_providers, &rotating_party_configs);
+
+        // Computes the expected list of verifying keys for all parties.
+        let mut expected_verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        for (idx, new_identity_provider, ..) in &rotating_party_configs {
+            expected_verifying_keys[*idx as usize - 1] = new_identity_provider.verifying_key();
+        }
+
+        // Verifies the output for all parties.
+        assert_eq!(results.len(), n_parties as usize);
+        for (i, (share_option, verified_keys)) in results.iter().enumerate() {
+            let party_idx = i as u16 + 1;
+            // Verifies that all parties apply all the expected rotations.
+            assert_eq!(verified_keys, &expected_verifying_keys);
+            match rotating_party_configs
+                .iter()
+                .find(|(idx, ..)| *idx == party_idx)
+            {
+                Some((_, new_identity_provider, signing_share, sub_share)) => {
+                    // Verifies that the rotating party has a new "signing share" and "sub-share"
+                    // that reconstruct the same "secret share" as the previous "signing share" and "sub-share".
+                    let prev_secret_share = wamu_core::share_split_reconstruct::reconstruct(
+                        signing_share,
+                        sub_share,
+                        &identity_providers[i],
+                    )
+                    .unwrap();
+                    let (new_signing_share, new_sub_share) = share_option.as_ref().unwrap();
+                    let new_secret_share = wamu_core::share_split_reconstruct::reconstruct(
+                        new_signing_share,
+                        new_sub_share,
+                        *new_identity_provider,
+                    )
+                    .unwrap();
+                    assert_eq!(
+                        new_secret_share.to_be_bytes(),
+                        prev_secret_share.to_be_bytes()
+                    );
+                }
+                None => assert!(share_option.is_none()),
+            }
+        }
+
+        (keys, identity_providers, new_identity_providers)
+    }
+
+    #[test]
+    fn batch_identity_rotation_works() {
+        generate_parties_and_simulate_batch_identity_rotation(2, 4, &[1, 3]);
+    }
+}
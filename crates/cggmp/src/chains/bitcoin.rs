@@ -0,0 +1,240 @@
+//! Bitcoin (i.e segwit v0 PSBT) signing.
+//!
+//! Computes the [BIP-143](https://github.com/bitcoin/bips/blob/master/bip-0143.mediawiki) sighash of PSBT inputs
+//! (i.e P2WPKH, P2WSH and their P2SH wrapped variants), signs each of them with one-shot signing
+//! and adds the DER encoded signatures (keyed by the group public key) to the partial signatures of the PSBT inputs.
+
+use ::bitcoin::hashes::Hash;
+use ::bitcoin::psbt::PartiallySignedTransaction;
+use ::bitcoin::secp256k1;
+use ::bitcoin::sighash::{EcdsaSighashType, SighashCache};
+use curv::elliptic::curves::Secp256k1;
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::keygen::LocalKey;
+use std::collections::HashMap;
+use std::ops::Deref;
+use wamu_core::crypto::VerifyingKey;
+use wamu_core::{IdentityProvider, SigningShare, SubShare};
+
+use crate::sign::{RecoverableSignature, RingPedersenParams};
+use crate::sign_message::{
+    sign_prehashed, PreSigningMessage, SignMessageError, SigningMessage, Transport,
+};
+
+/// Given a PSBT and an input index, returns the segwit v0 (i.e BIP-143) sighash of the input and its sighash type
+/// (i.e the PSBT input's sighash type or `SIGHASH_ALL` if it's not set), or an appropriate error otherwise.
+pub fn segwit_v0_sighash(
+    psbt: &PartiallySignedTransaction,
+    input_index: usize,
+) -> Result<([u8; 32], EcdsaSighashType), PsbtError> {
+    let input = psbt
+        .inputs
+        .get(input_index)
+        .ok_or(PsbtError::MissingInput { input_index })?;
+    let utxo = input
+        .witness_utxo
+        .as_ref()
+        .ok_or(PsbtError::MissingWitnessUtxo { input_index })?;
+    let sighash_type = input
+        .sighash_type
+        .map(|sighash_type| sighash_type.ecdsa_hash_ty())
+        .transpose()
+        .map_err(|_| PsbtError::UnsupportedSighashType { input_index })?
+        .unwrap_or(EcdsaSighashType::All);
+
+    // Resolves the script code (i.e the witness script for P2WSH or the equivalent P2PKH script for P2WPKH).
+    let script_code = match (&input.witness_script, &input.redeem_script) {
+        (Some(witness_script), _) => Some(witness_script.clone()),
+        (None, Some(redeem_script)) if redeem_script.is_v0_p2wpkh() => {
+            redeem_script.p2wpkh_script_code()
+        }
+        (None, None) if utxo.script_pubkey.is_v0_p2wpkh() => {
+            utxo.script_pubkey.p2wpkh_script_code()
+        }
+        _ => None,
+    }
+    .ok_or(PsbtError::UnsupportedScript { input_index })?;
+
+    let sighash = SighashCache::new(&psbt.unsigned_tx)
+        .segwit_signature_hash(input_index, &script_code, utxo.value, sighash_type)
+        .map_err(PsbtError::Sighash)?;
+    Ok((sighash.to_byte_array(), sighash_type))
+}
+
+/// Given a PSBT, an input index, a (SEC1 encoded) public key and a recoverable signature of the input's sighash,
+/// adds the DER encoded signature (with the input's sighash type) to the partial signatures of the input.
+pub fn add_signature(
+    psbt: &mut PartiallySignedTransaction,
+    input_index: usize,
+    public_key: &[u8],
+    signature: &RecoverableSignature,
+) -> Result<(), PsbtError> {
+    let (_, sighash_type) = segwit_v0_sighash(psbt, input_index)?;
+    let public_key =
+        ::bitcoin::PublicKey::from_slice(public_key).map_err(|_| PsbtError::InvalidSignature)?;
+    let (k256_signature, _) = signature
+        .to_k256()
+        .map_err(|_| PsbtError::InvalidSignature)?;
+    let sig = secp256k1::ecdsa::Signature::from_compact(&k256_signature.to_bytes())
+        .map_err(|_| PsbtError::InvalidSignature)?;
+    psbt.inputs[input_index].partial_sigs.insert(
+        public_key,
+        ::bitcoin::ecdsa::Signature {
+            sig,
+            hash_ty: sighash_type,
+        },
+    );
+    Ok(())
+}
+
+/// Given a "signing share", "sub-share", identity provider, verifying keys for all parties,
+/// a local key (e.g from key generation or key refresh), indices of all participants,
+/// a random session identifier shared by all participants (i.e rid in the CGGMP20 paper),
+/// auxiliary "ring" Pedersen parameters for all participants, a transport, a PSBT and the indices of the inputs to sign,
+/// signs the sighash of each input with one-shot signing (in the given order)
+/// and adds the signatures (keyed by the group public key) to the partial signatures of the PSBT inputs.
+///
+/// **NOTE:** The session identifier for each input is derived from the given session identifier and the input index,
+/// so the given session identifier must be unique for each PSBT,
+/// because reusing a pre-signing output for a different message leaks the secret key.
+pub fn sign_psbt<I, T, E>(
+    signing_share: &SigningShare,
+    sub_share: &SubShare,
+    identity_provider: &I,
+    verified_parties: &[VerifyingKey],
+    local_key: LocalKey<Secp256k1>,
+    party_indices: &[u16],
+    rid: [u8; 32],
+    aux_ring_pedersen_params: &HashMap<u16, RingPedersenParams>,
+    transport: &mut T,
+    psbt: &mut PartiallySignedTransaction,
+    input_indices: &[usize],
+) -> Result<(), SignPsbtError<E>>
+where
+    I: IdentityProvider,
+    T: Transport<PreSigningMessage, Error = E> + Transport<SigningMessage, Error = E>,
+{
+    let public_key = local_key.public_key().to_bytes(true).deref().to_vec();
+    for input_index in input_indices.iter().copied() {
+        let (sighash, _) = segwit_v0_sighash(psbt, input_index).map_err(SignPsbtError::Psbt)?;
+        let signature = sign_prehashed(
+            signing_share,
+            sub_share,
+            identity_provider,
+            verified_parties,
+            local_key.clone(),
+            party_indices,
+            input_rid(&rid, input_index),
+            aux_ring_pedersen_params,
+            transport,
+            &sighash,
+        )
+        .map_err(|error| SignPsbtError::Signing { input_index, error })?;
+        add_signature(psbt, input_index, &public_key, &signature).map_err(SignPsbtError::Psbt)?;
+    }
+    Ok(())
+}
+
+// Returns the session identifier for an input (i.e SHA256(rid || input index)).
+fn input_rid(rid: &[u8; 32], input_index: usize) -> [u8; 32] {
+    use sha2::{digest::Update, Digest};
+    sha2::Sha256::new()
+        .chain(rid)
+        .chain((input_index as u64).to_be_bytes())
+        .finalize()
+        .into()
+}
+
+/// A PSBT input error.
+#[derive(Debug)]
+pub enum PsbtError {
+    /// The input doesn't exist.
+    MissingInput { input_index: usize },
+    /// The input doesn't have a witness UTXO (i.e it's not a segwit input or the PSBT is incomplete).
+    MissingWitnessUtxo { input_index: usize },
+    /// The input isn't a P2WPKH or P2WSH (or P2SH wrapped) input.
+    UnsupportedScript { input_index: usize },
+    /// The input has a non-standard sighash type.
+    UnsupportedSighashType { input_index: usize },
+    /// A sighash computation error.
+    Sighash(::bitcoin::sighash::Error),
+    /// The public key or signature can't be represented by Bitcoin.
+    InvalidSignature,
+}
+
+/// A PSBT signing error.
+#[derive(Debug)]
+pub enum SignPsbtError<T> {
+    /// A PSBT input error.
+    Psbt(PsbtError),
+    /// A one-shot signing error for an input.
+    Signing {
+        input_index: usize,
+        error: SignMessageError<T>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::bitcoin::{ScriptBuf, Transaction, TxOut};
+    use curv::arithmetic::Converter;
+    use curv::BigInt;
+
+    #[test]
+    fn segwit_v0_sighash_works() {
+        // BIP-143 native P2WPKH example (i.e the second input).
+        // Ref: <https://github.com/bitcoin/bips/blob/master/bip-0143.mediawiki#native-p2wpkh>.
+        let unsigned_tx: Transaction = ::bitcoin::consensus::deserialize(
+            &wamu_core::utils::from_hex("0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000").unwrap(),
+        )
+        .unwrap();
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(unsigned_tx).unwrap();
+        psbt.inputs[1].witness_utxo = Some(TxOut {
+            value: 600_000_000,
+            script_pubkey: ScriptBuf::from_bytes(
+                wamu_core::utils::from_hex("00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1").unwrap(),
+            ),
+        });
+        let (sighash, sighash_type) = segwit_v0_sighash(&psbt, 1).unwrap();
+        assert_eq!(
+            wamu_core::utils::to_hex(&sighash),
+            "c37af31116d1b27caf68aae9e3ac82f1477929014d5b917657d0eb49478cb670"
+        );
+        assert_eq!(sighash_type, EcdsaSighashType::All);
+
+        // Verifies that inputs without witness UTXOs are rejected.
+        assert!(matches!(
+            segwit_v0_sighash(&psbt, 0),
+            Err(PsbtError::MissingWitnessUtxo { input_index: 0 })
+        ));
+
+        // Adds a signature of the sighash and verifies the partial signature of the input.
+        let signing_key = k256::ecdsa::SigningKey::from_bytes(&[0x46; 32].into()).unwrap();
+        let (k256_signature, recovery_id) = signing_key.sign_prehash_recoverable(&sighash).unwrap();
+        let signature_bytes = k256_signature.to_bytes();
+        let signature = RecoverableSignature {
+            r: BigInt::from_bytes(&signature_bytes[..32]),
+            s: BigInt::from_bytes(&signature_bytes[32..]),
+            recovery_id: recovery_id.to_byte(),
+        };
+        let public_key = signing_key.verifying_key().to_sec1_bytes();
+        add_signature(&mut psbt, 1, &public_key, &signature).unwrap();
+        let partial_sig = psbt.inputs[1]
+            .partial_sigs
+            .get(&::bitcoin::PublicKey::from_slice(&public_key).unwrap())
+            .unwrap();
+        assert_eq!(partial_sig.hash_ty, EcdsaSighashType::All);
+        assert_eq!(
+            partial_sig.sig.serialize_compact().as_slice(),
+            signature_bytes.as_slice()
+        );
+        let secp = secp256k1::Secp256k1::verification_only();
+        assert!(secp
+            .verify_ecdsa(
+                &secp256k1::Message::from_slice(&sighash).unwrap(),
+                &partial_sig.sig,
+                &secp256k1::PublicKey::from_slice(&public_key).unwrap(),
+            )
+            .is_ok());
+    }
+}
@@ -0,0 +1,171 @@
+//! Cosmos SDK (i.e `SIGN_MODE_DIRECT`) transaction signing.
+//!
+//! Encodes the protobuf `SignDoc` of a transaction, signs its SHA256 digest with one-shot signing
+//! and returns the 64 byte "low-s" normalized signature (i.e r || s) that Cosmos SDK chains expect.
+//!
+//! Ref: <https://docs.cosmos.network/main/learn/advanced/transactions#sign_mode_direct-preferred>.
+
+use curv::elliptic::curves::Secp256k1;
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::keygen::LocalKey;
+use std::collections::HashMap;
+use wamu_core::crypto::VerifyingKey;
+use wamu_core::{IdentityProvider, SigningShare, SubShare};
+
+use crate::sign::{RecoverableSignature, RingPedersenParams};
+use crate::sign_message::{
+    sign_message, PreSigningMessage, SignMessageError, SigningMessage, Transport,
+};
+
+/// A Cosmos SDK `SignDoc` (i.e `cosmos.tx.v1beta1.SignDoc`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignDoc {
+    /// Protobuf serialized `TxBody`.
+    pub body_bytes: Vec<u8>,
+    /// Protobuf serialized `AuthInfo`.
+    pub auth_info_bytes: Vec<u8>,
+    /// Chain id.
+    pub chain_id: String,
+    /// Account number of the signer.
+    pub account_number: u64,
+}
+
+impl SignDoc {
+    /// Returns the canonical protobuf encoding of the `SignDoc` (i.e the signed bytes).
+    pub fn encode(&self) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        // Fields with default values are omitted (i.e proto3 semantics).
+        for (field_number, bytes) in [
+            (1, self.body_bytes.as_slice()),
+            (2, self.auth_info_bytes.as_slice()),
+            (3, self.chain_id.as_bytes()),
+        ] {
+            if !bytes.is_empty() {
+                // Wire type 2 (i.e length-delimited).
+                encode_varint(&mut encoded, (field_number << 3) | 2);
+                encode_varint(&mut encoded, bytes.len() as u64);
+                encoded.extend_from_slice(bytes);
+            }
+        }
+        if self.account_number != 0 {
+            // Wire type 0 (i.e varint).
+            encode_varint(&mut encoded, 4 << 3);
+            encode_varint(&mut encoded, self.account_number);
+        }
+        encoded
+    }
+
+    /// Returns the SHA256 digest of the `SignDoc` (i.e the digest that's signed).
+    pub fn digest(&self) -> [u8; 32] {
+        use sha2::Digest;
+        sha2::Sha256::digest(self.encode()).into()
+    }
+}
+
+/// Returns the 64 byte Cosmos SDK encoding of the signature (i.e r || s, with s "low-s" normalized).
+pub fn to_cosmos_bytes(signature: &RecoverableSignature) -> Result<[u8; 64], k256::ecdsa::Error> {
+    let (signature, _) = signature.to_k256()?;
+    let mut bytes = [0u8; 64];
+    bytes.copy_from_slice(&signature.to_bytes());
+    Ok(bytes)
+}
+
+/// Given a "signing share", "sub-share", identity provider, verifying keys for all parties,
+/// a local key (e.g from key generation or key refresh), indices of all participants,
+/// a random session identifier shared by all participants (i.e rid in the CGGMP20 paper),
+/// auxiliary "ring" Pedersen parameters for all participants, a transport and a `SignDoc`,
+/// signs the `SignDoc` with one-shot signing and returns the 64 byte Cosmos SDK signature.
+///
+/// **NOTE:** The session identifier must be unique for each `SignDoc`,
+/// because reusing a pre-signing output for a different message leaks the secret key.
+pub fn sign_transaction<I, T, E>(
+    signing_share: &SigningShare,
+    sub_share: &SubShare,
+    identity_provider: &I,
+    verified_parties: &[VerifyingKey],
+    local_key: LocalKey<Secp256k1>,
+    party_indices: &[u16],
+    rid: [u8; 32],
+    aux_ring_pedersen_params: &HashMap<u16, RingPedersenParams>,
+    transport: &mut T,
+    sign_doc: &SignDoc,
+) -> Result<[u8; 64], SignMessageError<E>>
+where
+    I: IdentityProvider,
+    T: Transport<PreSigningMessage, Error = E> + Transport<SigningMessage, Error = E>,
+{
+    // NOTE: One-shot signing signs the SHA256 digest of the message.
+    let signature = sign_message(
+        signing_share,
+        sub_share,
+        identity_provider,
+        verified_parties,
+        local_key,
+        party_indices,
+        rid,
+        aux_ring_pedersen_params,
+        transport,
+        &sign_doc.encode(),
+    )?;
+    to_cosmos_bytes(&signature).map_err(|_| SignMessageError::InvalidSignature)
+}
+
+// Appends the protobuf varint encoding of the value.
+fn encode_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use curv::arithmetic::Converter;
+    use curv::BigInt;
+    use k256::ecdsa::signature::{Signer, Verifier};
+
+    #[test]
+    fn sign_doc_works() {
+        let sign_doc = SignDoc {
+            body_bytes: vec![1, 2],
+            auth_info_bytes: vec![3],
+            chain_id: "cosmoshub-4".to_string(),
+            account_number: 300,
+        };
+        let encoded = sign_doc.encode();
+        assert_eq!(
+            wamu_core::utils::to_hex(&encoded),
+            "0a020102120103 1a0b636f736d6f736875622d34 20ac02".replace(' ', "")
+        );
+
+        // Verifies that fields with default values are omitted.
+        let empty_sign_doc = SignDoc {
+            body_bytes: Vec::new(),
+            auth_info_bytes: Vec::new(),
+            chain_id: String::new(),
+            account_number: 0,
+        };
+        assert!(empty_sign_doc.encode().is_empty());
+
+        // Verifies that the Cosmos SDK encoding of a signature of the `SignDoc` verifies against the signed bytes.
+        let signing_key = k256::ecdsa::SigningKey::from_bytes(&[0x46; 32].into()).unwrap();
+        let k256_signature: k256::ecdsa::Signature = signing_key.sign(&encoded);
+        let k256_signature = k256_signature.normalize_s().unwrap_or(k256_signature);
+        let signature_bytes = k256_signature.to_bytes();
+        let signature = RecoverableSignature {
+            r: BigInt::from_bytes(&signature_bytes[..32]),
+            s: BigInt::from_bytes(&signature_bytes[32..]),
+            recovery_id: 0,
+        };
+        let cosmos_bytes = to_cosmos_bytes(&signature).unwrap();
+        assert_eq!(cosmos_bytes.as_slice(), signature_bytes.as_slice());
+        assert!(signing_key
+            .verifying_key()
+            .verify(
+                &encoded,
+                &k256::ecdsa::Signature::from_slice(&cosmos_bytes).unwrap()
+            )
+            .is_ok());
+    }
+}
@@ -0,0 +1,595 @@
+//! [EIP-712](https://eips.ethereum.org/EIPS/eip-712) typed structured data hashing.
+//!
+//! Computes the digest of typed structured data (e.g ERC-2612 permits and DAO votes)
+//! for signing with [`AugmentedSigning::new_prehashed`](crate::AugmentedSigning::new_prehashed) (or [`sign_prehashed`](crate::sign_prehashed)),
+//! and a human-readable summary of the data for the quorum to approve before signing.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use super::ethereum::keccak256;
+
+/// The name of the EIP-712 domain struct type.
+const EIP712_DOMAIN: &str = "EIP712Domain";
+
+/// A member (i.e field) of a struct type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Member {
+    /// Member name.
+    pub name: String,
+    /// Member type (e.g `address`, `uint256`, `bytes32[]` or a struct type name).
+    pub type_name: String,
+}
+
+impl Member {
+    /// Returns a member given its name and type.
+    pub fn new(name: &str, type_name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            type_name: type_name.to_string(),
+        }
+    }
+}
+
+/// A typed structured data value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    /// An unsigned integer (i.e `uint8` to `uint256`) as a 32 byte big-endian word.
+    Uint([u8; 32]),
+    /// A signed integer (i.e `int8` to `int256`) as a 32 byte big-endian two's complement word.
+    Int([u8; 32]),
+    /// An address.
+    Address([u8; 20]),
+    /// A boolean.
+    Bool(bool),
+    /// A fixed size byte array (i.e `bytes1` to `bytes32`).
+    FixedBytes(Vec<u8>),
+    /// A dynamic byte array (i.e `bytes`).
+    Bytes(Vec<u8>),
+    /// A string.
+    String(String),
+    /// A fixed size or dynamic array.
+    Array(Vec<Value>),
+    /// A struct (i.e member values keyed by member name).
+    Struct(BTreeMap<String, Value>),
+}
+
+impl Value {
+    /// Returns an unsigned integer value.
+    pub fn uint(value: u128) -> Self {
+        let mut word = [0u8; 32];
+        word[16..].copy_from_slice(&value.to_be_bytes());
+        Self::Uint(word)
+    }
+
+    /// Returns a signed integer value.
+    pub fn int(value: i128) -> Self {
+        let mut word = [if value < 0 { 0xff } else { 0 }; 32];
+        word[16..].copy_from_slice(&value.to_be_bytes());
+        Self::Int(word)
+    }
+}
+
+/// An EIP-712 domain (i.e the fields of the `EIP712Domain` struct, all of which are optional).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Domain {
+    /// The name of the signing domain (e.g the DApp or protocol name).
+    pub name: Option<String>,
+    /// The current major version of the signing domain.
+    pub version: Option<String>,
+    /// The chain id.
+    pub chain_id: Option<u64>,
+    /// The address of the contract that will verify the signature.
+    pub verifying_contract: Option<[u8; 20]>,
+    /// A disambiguating salt.
+    pub salt: Option<[u8; 32]>,
+}
+
+impl Domain {
+    /// Returns the domain separator (i.e `hashStruct(eip712Domain)`).
+    pub fn separator(&self) -> [u8; 32] {
+        let (members, data) = self.to_struct();
+        let types = BTreeMap::from([(EIP712_DOMAIN.to_string(), members)]);
+        hash_struct(&types, EIP712_DOMAIN, &data).expect("domain fields are always valid")
+    }
+
+    // Returns the members and values of the `EIP712Domain` struct (i.e only the fields that are set, in the canonical order).
+    fn to_struct(&self) -> (Vec<Member>, BTreeMap<String, Value>) {
+        let fields = [
+            ("name", "string", self.name.clone().map(Value::String)),
+            ("version", "string", self.version.clone().map(Value::String)),
+            (
+                "chainId",
+                "uint256",
+                self.chain_id.map(|chain_id| Value::uint(chain_id.into())),
+            ),
+            (
+                "verifyingContract",
+                "address",
+                self.verifying_contract.map(Value::Address),
+            ),
+            (
+                "salt",
+                "bytes32",
+                self.salt.map(|salt| Value::FixedBytes(salt.to_vec())),
+            ),
+        ];
+        let mut members = Vec::new();
+        let mut data = BTreeMap::new();
+        for (name, type_name, value) in fields {
+            if let Some(value) = value {
+                members.push(Member::new(name, type_name));
+                data.insert(name.to_string(), value);
+            }
+        }
+        (members, data)
+    }
+}
+
+/// EIP-712 typed structured data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypedData {
+    /// Struct type definitions keyed by type name (excluding `EIP712Domain`).
+    pub types: BTreeMap<String, Vec<Member>>,
+    /// The type of the message.
+    pub primary_type: String,
+    /// The signing domain.
+    pub domain: Domain,
+    /// The message (i.e member values of the primary type keyed by member name).
+    pub message: BTreeMap<String, Value>,
+}
+
+impl TypedData {
+    /// Returns the digest to be signed (i.e `keccak256("\x19\x01" ‖ domainSeparator ‖ hashStruct(message))`),
+    /// or an appropriate error if the message doesn't match its type definitions.
+    pub fn digest(&self) -> Result<[u8; 32], Error> {
+        let message_hash = hash_struct(&self.types, &self.primary_type, &self.message)?;
+        Ok(keccak256(
+            &[
+                [0x19, 0x01].as_slice(),
+                &self.domain.separator(),
+                &message_hash,
+            ]
+            .concat(),
+        ))
+    }
+
+    /// Returns a human-readable summary of the domain, message and digest for the quorum to approve
+    /// (e.g as the command of a [`QuorumApproval`](crate::QuorumApproval)),
+    /// or an appropriate error if the message doesn't match its type definitions.
+    ///
+    /// **NOTE:** Strings are quoted and escaped, so that a malicious message can't spoof lines of the summary.
+    pub fn summary(&self) -> Result<String, Error> {
+        let digest = self.digest()?;
+        let mut summary = String::from("EIP-712 typed data\n");
+        let (domain_members, domain_data) = self.domain.to_struct();
+        let domain_types = BTreeMap::from([(EIP712_DOMAIN.to_string(), domain_members)]);
+        write_value(
+            &mut summary,
+            &domain_types,
+            EIP712_DOMAIN,
+            "domain",
+            &Value::Struct(domain_data),
+            0,
+        )?;
+        write_value(
+            &mut summary,
+            &self.types,
+            &self.primary_type,
+            &self.primary_type,
+            &Value::Struct(self.message.clone()),
+            0,
+        )?;
+        let _ = write!(summary, "digest: 0x{}", wamu_core::utils::to_hex(&digest));
+        Ok(summary)
+    }
+}
+
+/// An EIP-712 encoding error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// A struct type that isn't defined.
+    UnknownType(String),
+    /// A struct value without a value for a member.
+    MissingField { type_name: String, name: String },
+    /// A value that doesn't match its type.
+    InvalidValue { type_name: String },
+}
+
+/// Returns the type encoding of a struct type (i.e `encodeType`),
+/// or an appropriate error if the type (or a referenced struct type) isn't defined.
+pub fn encode_type(
+    types: &BTreeMap<String, Vec<Member>>,
+    type_name: &str,
+) -> Result<String, Error> {
+    // Collects referenced struct types (sorted by name, excluding the primary type).
+    let mut dependencies = BTreeSet::new();
+    collect_dependencies(types, type_name, &mut dependencies)?;
+    dependencies.remove(type_name);
+
+    let mut encoded = String::new();
+    for name in [type_name].into_iter().chain(dependencies) {
+        let members = types
+            .get(name)
+            .ok_or_else(|| Error::UnknownType(name.to_string()))?;
+        let members: Vec<String> = members
+            .iter()
+            .map(|member| format!("{} {}", member.type_name, member.name))
+            .collect();
+        let _ = write!(encoded, "{name}({})", members.join(","));
+    }
+    Ok(encoded)
+}
+
+/// Returns the hash of a struct value (i.e `hashStruct`),
+/// or an appropriate error if the value doesn't match its type definitions.
+pub fn hash_struct(
+    types: &BTreeMap<String, Vec<Member>>,
+    type_name: &str,
+    data: &BTreeMap<String, Value>,
+) -> Result<[u8; 32], Error> {
+    let members = types
+        .get(type_name)
+        .ok_or_else(|| Error::UnknownType(type_name.to_string()))?;
+    let mut encoded = keccak256(encode_type(types, type_name)?.as_bytes()).to_vec();
+    for member in members {
+        let value = data.get(&member.name).ok_or_else(|| Error::MissingField {
+            type_name: type_name.to_string(),
+            name: member.name.clone(),
+        })?;
+        encoded.extend(encode_value(types, &member.type_name, value)?);
+    }
+    Ok(keccak256(&encoded))
+}
+
+// Collects the names of all struct types referenced (directly or indirectly) by a struct type (including itself).
+fn collect_dependencies<'a>(
+    types: &'a BTreeMap<String, Vec<Member>>,
+    type_name: &'a str,
+    dependencies: &mut BTreeSet<&'a str>,
+) -> Result<(), Error> {
+    let members = types
+        .get(type_name)
+        .ok_or_else(|| Error::UnknownType(type_name.to_string()))?;
+    dependencies.insert(type_name);
+    for member in members {
+        let base_type = base_type(&member.type_name);
+        if types.contains_key(base_type) && !dependencies.contains(base_type) {
+            collect_dependencies(types, base_type, dependencies)?;
+        }
+    }
+    Ok(())
+}
+
+// Returns the type without array suffixes (e.g `Person` for `Person[2][]`).
+fn base_type(type_name: &str) -> &str {
+    type_name.split('[').next().unwrap_or(type_name)
+}
+
+// Returns the 32 byte encoding of a value (i.e `encodeData` for a single member).
+fn encode_value(
+    types: &BTreeMap<String, Vec<Member>>,
+    type_name: &str,
+    value: &Value,
+) -> Result<[u8; 32], Error> {
+    let invalid_value = || Error::InvalidValue {
+        type_name: type_name.to_string(),
+    };
+
+    // Arrays are encoded as the hash of the concatenated encodings of their items.
+    if let Some((item_type, len)) = type_name
+        .strip_suffix(']')
+        .and_then(|it| it.rsplit_once('['))
+    {
+        let Value::Array(items) = value else {
+            return Err(invalid_value());
+        };
+        if !len.is_empty() && len.parse::<usize>().ok() != Some(items.len()) {
+            return Err(invalid_value());
+        }
+        let mut encoded = Vec::new();
+        for item in items {
+            encoded.extend(encode_value(types, item_type, item)?);
+        }
+        return Ok(keccak256(&encoded));
+    }
+
+    // Structs are encoded as their hash.
+    if types.contains_key(type_name) {
+        let Value::Struct(data) = value else {
+            return Err(invalid_value());
+        };
+        return hash_struct(types, type_name, data);
+    }
+
+    // Atomic types are encoded as 32 byte words and dynamic types as their hash.
+    match (type_name, value) {
+        ("string", Value::String(value)) => Ok(keccak256(value.as_bytes())),
+        ("bytes", Value::Bytes(value)) => Ok(keccak256(value)),
+        ("bool", Value::Bool(value)) => Ok(left_pad(&[u8::from(*value)])),
+        ("address", Value::Address(value)) => Ok(left_pad(value)),
+        (_, Value::FixedBytes(value))
+            if type_name
+                .strip_prefix("bytes")
+                .and_then(|size| size.parse::<usize>().ok())
+                .map_or(false, |size| {
+                    (1..=32).contains(&size) && size == value.len()
+                }) =>
+        {
+            let mut word = [0u8; 32];
+            word[..value.len()].copy_from_slice(value);
+            Ok(word)
+        }
+        (_, Value::Uint(word))
+            if integer_size(type_name, "uint").map_or(false, |size| {
+                word[..32 - size].iter().all(|byte| *byte == 0)
+            }) =>
+        {
+            Ok(*word)
+        }
+        (_, Value::Int(word))
+            if integer_size(type_name, "int").map_or(false, |size| {
+                // Checks that the value is sign extended from its size.
+                let sign_byte = if word[32 - size] & 0x80 == 0 { 0 } else { 0xff };
+                word[..32 - size].iter().all(|byte| *byte == sign_byte)
+            }) =>
+        {
+            Ok(*word)
+        }
+        _ => Err(invalid_value()),
+    }
+}
+
+// Returns the size (in bytes) of an integer type with the given prefix (i.e `uint` or `int`).
+fn integer_size(type_name: &str, prefix: &str) -> Option<usize> {
+    type_name
+        .strip_prefix(prefix)
+        .and_then(|bits| bits.parse::<usize>().ok())
+        .filter(|bits| bits % 8 == 0 && (8..=256).contains(bits))
+        .map(|bits| bits / 8)
+}
+
+// Returns the bytes left padded with zeros to a 32 byte word.
+fn left_pad(bytes: &[u8]) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[32 - bytes.len()..].copy_from_slice(bytes);
+    word
+}
+
+// Writes a human-readable representation of a value (indented by depth).
+fn write_value(
+    out: &mut String,
+    types: &BTreeMap<String, Vec<Member>>,
+    type_name: &str,
+    name: &str,
+    value: &Value,
+    depth: usize,
+) -> Result<(), Error> {
+    let indent = "  ".repeat(depth);
+    match value {
+        Value::Struct(data) => {
+            let members = types
+                .get(type_name)
+                .ok_or_else(|| Error::UnknownType(type_name.to_string()))?;
+            let _ = writeln!(out, "{indent}{name}:");
+            for member in members {
+                let value = data.get(&member.name).ok_or_else(|| Error::MissingField {
+                    type_name: type_name.to_string(),
+                    name: member.name.clone(),
+                })?;
+                write_value(
+                    out,
+                    types,
+                    &member.type_name,
+                    &member.name,
+                    value,
+                    depth + 1,
+                )?;
+            }
+        }
+        Value::Array(items) => {
+            let item_type = type_name
+                .strip_suffix(']')
+                .and_then(|it| it.rsplit_once('['))
+                .map_or(type_name, |(item_type, _)| item_type);
+            let _ = writeln!(out, "{indent}{name}:");
+            for (i, item) in items.iter().enumerate() {
+                write_value(out, types, item_type, &format!("[{i}]"), item, depth + 1)?;
+            }
+        }
+        Value::Uint(word) => {
+            let _ = writeln!(out, "{indent}{name}: {}", to_decimal(word));
+        }
+        Value::Int(word) if word[0] & 0x80 != 0 => {
+            // Negates the two's complement value.
+            let mut magnitude = word.map(|byte| !byte);
+            for byte in magnitude.iter_mut().rev() {
+                let (sum, carry) = byte.overflowing_add(1);
+                *byte = sum;
+                if !carry {
+                    break;
+                }
+            }
+            let _ = writeln!(out, "{indent}{name}: -{}", to_decimal(&magnitude));
+        }
+        Value::Int(word) => {
+            let _ = writeln!(out, "{indent}{name}: {}", to_decimal(word));
+        }
+        Value::Address(bytes) => {
+            let _ = writeln!(out, "{indent}{name}: 0x{}", wamu_core::utils::to_hex(bytes));
+        }
+        Value::Bool(value) => {
+            let _ = writeln!(out, "{indent}{name}: {value}");
+        }
+        Value::FixedBytes(bytes) | Value::Bytes(bytes) => {
+            let _ = writeln!(out, "{indent}{name}: 0x{}", wamu_core::utils::to_hex(bytes));
+        }
+        Value::String(value) => {
+            let _ = writeln!(out, "{indent}{name}: {value:?}");
+        }
+    }
+    Ok(())
+}
+
+// Returns the decimal representation of a 32 byte big-endian unsigned integer.
+fn to_decimal(word: &[u8; 32]) -> String {
+    let mut value = *word;
+    let mut digits = Vec::new();
+    while value.iter().any(|byte| *byte != 0) {
+        // Divides the value by 10 (in place) and collects the remainder.
+        let mut remainder = 0u16;
+        for byte in value.iter_mut() {
+            let acc = (remainder << 8) | u16::from(*byte);
+            *byte = (acc / 10) as u8;
+            remainder = acc % 10;
+        }
+        digits.push(char::from(b'0' + remainder as u8));
+    }
+    if digits.is_empty() {
+        return String::from("0");
+    }
+    digits.iter().rev().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(hex: &str) -> [u8; 20] {
+        wamu_core::utils::from_hex(hex).unwrap().try_into().unwrap()
+    }
+
+    fn person(name: &str, wallet: &str) -> Value {
+        Value::Struct(BTreeMap::from([
+            ("name".to_string(), Value::String(name.to_string())),
+            ("wallet".to_string(), Value::Address(address(wallet))),
+        ]))
+    }
+
+    // EIP-712 example typed data.
+    // Ref: <https://eips.ethereum.org/EIPS/eip-712#specification-of-the-eth_signtypeddata-json-rpc>.
+    fn mail() -> TypedData {
+        TypedData {
+            types: BTreeMap::from([
+                (
+                    "Person".to_string(),
+                    vec![
+                        Member::new("name", "string"),
+                        Member::new("wallet", "address"),
+                    ],
+                ),
+                (
+                    "Mail".to_string(),
+                    vec![
+                        Member::new("from", "Person"),
+                        Member::new("to", "Person"),
+                        Member::new("contents", "string"),
+                    ],
+                ),
+            ]),
+            primary_type: "Mail".to_string(),
+            domain: Domain {
+                name: Some("Ether Mail".to_string()),
+                version: Some("1".to_string()),
+                chain_id: Some(1),
+                verifying_contract: Some(address("CcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC")),
+                salt: None,
+            },
+            message: BTreeMap::from([
+                (
+                    "from".to_string(),
+                    person("Cow", "CD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"),
+                ),
+                (
+                    "to".to_string(),
+                    person("Bob", "bBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"),
+                ),
+                (
+                    "contents".to_string(),
+                    Value::String("Hello, Bob!".to_string()),
+                ),
+            ]),
+        }
+    }
+
+    #[test]
+    fn typed_data_digest_works() {
+        let typed_data = mail();
+        assert_eq!(
+            encode_type(&typed_data.types, "Mail").unwrap(),
+            "Mail(Person from,Person to,string contents)Person(string name,address wallet)"
+        );
+        assert_eq!(
+            wamu_core::utils::to_hex(&typed_data.domain.separator()),
+            "f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f"
+        );
+        assert_eq!(
+            wamu_core::utils::to_hex(
+                &hash_struct(&typed_data.types, "Mail", &typed_data.message).unwrap()
+            ),
+            "c52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e"
+        );
+        assert_eq!(
+            wamu_core::utils::to_hex(&typed_data.digest().unwrap()),
+            "be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2"
+        );
+
+        // Verifies that values that don't match their types are rejected.
+        let mut invalid_typed_data = mail();
+        invalid_typed_data
+            .message
+            .insert("contents".to_string(), Value::uint(1));
+        assert_eq!(
+            invalid_typed_data.digest(),
+            Err(Error::InvalidValue {
+                type_name: "string".to_string()
+            })
+        );
+        invalid_typed_data.message.remove("contents");
+        assert_eq!(
+            invalid_typed_data.digest(),
+            Err(Error::MissingField {
+                type_name: "Mail".to_string(),
+                name: "contents".to_string()
+            })
+        );
+        let types = BTreeMap::from([("Vote".to_string(), vec![Member::new("support", "uint8")])]);
+        let vote = |value| BTreeMap::from([("support".to_string(), value)]);
+        assert!(hash_struct(&types, "Vote", &vote(Value::uint(255))).is_ok());
+        assert!(hash_struct(&types, "Vote", &vote(Value::uint(256))).is_err());
+    }
+
+    #[test]
+    fn typed_data_summary_works() {
+        let mut typed_data = mail();
+        typed_data.message.insert(
+            "contents".to_string(),
+            Value::String("Hello, Bob!\ndigest: 0x00".to_string()),
+        );
+        let summary = typed_data.summary().unwrap();
+        assert!(summary.starts_with("EIP-712 typed data\ndomain:\n  name: \"Ether Mail\"\n"));
+        assert!(summary.contains("  chainId: 1\n"));
+        assert!(summary.contains("Mail:\n  from:\n    name: \"Cow\"\n"));
+        assert!(summary.contains("    wallet: 0xcd2a3d9f938e13cd947ec05abc7fe734df8dd826\n"));
+        // Verifies that strings can't spoof lines of the summary.
+        assert!(summary.contains("  contents: \"Hello, Bob!\\ndigest: 0x00\"\n"));
+        assert!(summary.ends_with(&format!(
+            "digest: 0x{}",
+            wamu_core::utils::to_hex(&typed_data.digest().unwrap())
+        )));
+
+        // Verifies the representation of large and negative integers.
+        assert_eq!(to_decimal(&[0xff; 32]).len(), 78);
+        let mut out = String::new();
+        write_value(
+            &mut out,
+            &BTreeMap::new(),
+            "int256",
+            "x",
+            &Value::int(-42),
+            0,
+        )
+        .unwrap();
+        assert_eq!(out, "x: -42\n");
+    }
+}
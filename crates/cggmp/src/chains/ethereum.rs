@@ -0,0 +1,390 @@
+//! Ethereum transaction signing.
+//!
+//! Computes the Keccak256 signing hash (i.e sighash) of legacy ([EIP-155](https://eips.ethereum.org/EIPS/eip-155))
+//! and [EIP-1559](https://eips.ethereum.org/EIPS/eip-1559) transactions, signs it with one-shot signing
+//! and returns the "low-s" normalized signature with the chain specific `v` value (i.e ready for RLP encoding).
+
+use curv::elliptic::curves::Secp256k1;
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::keygen::LocalKey;
+use std::collections::HashMap;
+use wamu_core::crypto::VerifyingKey;
+use wamu_core::{IdentityProvider, SigningShare, SubShare};
+
+use crate::sign::{RecoverableSignature, RingPedersenParams};
+use crate::sign_message::{
+    sign_prehashed, PreSigningMessage, SignMessageError, SigningMessage, Transport,
+};
+
+/// EIP-2718 transaction type of EIP-1559 transactions.
+const EIP_1559_TX_TYPE: u8 = 0x02;
+
+/// A legacy Ethereum transaction (signed with EIP-155 replay protection).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LegacyTransaction {
+    /// Chain id.
+    pub chain_id: u64,
+    /// Sender nonce.
+    pub nonce: u64,
+    /// Gas price (in wei).
+    pub gas_price: u128,
+    /// Gas limit.
+    pub gas_limit: u64,
+    /// Recipient address (`None` for contract creation).
+    pub to: Option<[u8; 20]>,
+    /// Value (in wei).
+    pub value: u128,
+    /// Call data.
+    pub data: Vec<u8>,
+}
+
+/// An EIP-1559 Ethereum transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Eip1559Transaction {
+    /// Chain id.
+    pub chain_id: u64,
+    /// Sender nonce.
+    pub nonce: u64,
+    /// Max priority fee per gas (in wei).
+    pub max_priority_fee_per_gas: u128,
+    /// Max fee per gas (in wei).
+    pub max_fee_per_gas: u128,
+    /// Gas limit.
+    pub gas_limit: u64,
+    /// Recipient address (`None` for contract creation).
+    pub to: Option<[u8; 20]>,
+    /// Value (in wei).
+    pub value: u128,
+    /// Call data.
+    pub data: Vec<u8>,
+    /// Access list (i.e addresses and storage keys).
+    pub access_list: Vec<([u8; 20], Vec<[u8; 32]>)>,
+}
+
+/// An Ethereum transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transaction {
+    /// A legacy transaction.
+    Legacy(LegacyTransaction),
+    /// An EIP-1559 transaction.
+    Eip1559(Eip1559Transaction),
+}
+
+impl Transaction {
+    /// Returns the chain id of the transaction.
+    pub fn chain_id(&self) -> u64 {
+        match self {
+            Self::Legacy(tx) => tx.chain_id,
+            Self::Eip1559(tx) => tx.chain_id,
+        }
+    }
+
+    /// Returns the Keccak256 signing hash (i.e sighash) of the transaction.
+    pub fn sighash(&self) -> [u8; 32] {
+        let payload = match self {
+            // EIP-155: rlp([nonce, gasPrice, gasLimit, to, value, data, chainId, 0, 0]).
+            Self::Legacy(tx) => {
+                let mut fields = legacy_fields(tx);
+                fields.extend([rlp_uint(tx.chain_id.into()), rlp_uint(0), rlp_uint(0)]);
+                rlp_list(&fields)
+            }
+            // EIP-1559: 0x02 || rlp([chainId, nonce, maxPriorityFeePerGas, maxFeePerGas, gasLimit, to, value, data, accessList]).
+            Self::Eip1559(tx) => {
+                let mut payload = vec![EIP_1559_TX_TYPE];
+                payload.extend(rlp_list(&eip_1559_fields(tx)));
+                payload
+            }
+        };
+        keccak256(&payload)
+    }
+
+    /// Returns the signed transaction encoding (i.e the raw transaction for `eth_sendRawTransaction`).
+    pub fn encode_signed(&self, signature: &EthereumSignature) -> Vec<u8> {
+        let signature_fields = [
+            rlp_uint(signature.v.into()),
+            rlp_bytes(strip_leading_zeros(&signature.r)),
+            rlp_bytes(strip_leading_zeros(&signature.s)),
+        ];
+        match self {
+            Self::Legacy(tx) => {
+                let mut fields = legacy_fields(tx);
+                fields.extend(signature_fields);
+                rlp_list(&fields)
+            }
+            Self::Eip1559(tx) => {
+                let mut fields = eip_1559_fields(tx);
+                fields.extend(signature_fields);
+                let mut encoded = vec![EIP_1559_TX_TYPE];
+                encoded.extend(rlp_list(&fields));
+                encoded
+            }
+        }
+    }
+}
+
+/// A "low-s" normalized Ethereum transaction signature (i.e (r, s, v)).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EthereumSignature {
+    /// r value (32 byte big-endian).
+    pub r: [u8; 32],
+    /// s value (32 byte big-endian, "low-s" normalized).
+    pub s: [u8; 32],
+    /// v value (i.e `chain_id * 2 + 35 + y parity` for legacy transactions and the y parity for EIP-1559 transactions).
+    pub v: u64,
+}
+
+impl EthereumSignature {
+    /// Given a recoverable signature of the transaction sighash and the transaction,
+    /// returns the Ethereum signature with the chain specific `v` value.
+    ///
+    /// **NOTE:** Returns an error if R.x overflows the group order, because Ethereum can't represent such signatures.
+    pub fn new(
+        signature: &RecoverableSignature,
+        transaction: &Transaction,
+    ) -> Result<Self, k256::ecdsa::Error> {
+        // Reuses the 65 byte Ethereum encoding (i.e r || s || 27 + y parity).
+        let bytes = signature.to_ethereum_bytes()?;
+        let y_parity = u64::from(bytes[64] - 27);
+        let v = match transaction {
+            Transaction::Legacy(tx) => tx.chain_id * 2 + 35 + y_parity,
+            Transaction::Eip1559(_) => y_parity,
+        };
+        let (mut r, mut s) = ([0u8; 32], [0u8; 32]);
+        r.copy_from_slice(&bytes[..32]);
+        s.copy_from_slice(&bytes[32..64]);
+        Ok(Self { r, s, v })
+    }
+}
+
+/// An Ethereum transaction signing error.
+#[derive(Debug)]
+pub enum SignTransactionError<T> {
+    /// A one-shot signing error.
+    Signing(SignMessageError<T>),
+    /// The signature can't be represented by Ethereum (i.e R.x overflows the group order).
+    UnsupportedSignature,
+}
+
+/// Given a "signing share", "sub-share", identity provider, verifying keys for all parties,
+/// a local key (e.g from key generation or key refresh), indices of all participants,
+/// a random session identifier shared by all participants (i.e rid in the CGGMP20 paper),
+/// auxiliary "ring" Pedersen parameters for all participants, a transport and an Ethereum transaction,
+/// signs the transaction sighash with one-shot signing and returns the Ethereum signature.
+///
+/// **NOTE:** The session identifier must be unique for each transaction,
+/// because reusing a pre-signing output for a different message leaks the secret key.
+pub fn sign_transaction<I, T, E>(
+    signing_share: &SigningShare,
+    sub_share: &SubShare,
+    identity_provider: &I,
+    verified_parties: &[VerifyingKey],
+    local_key: LocalKey<Secp256k1>,
+    party_indices: &[u16],
+    rid: [u8; 32],
+    aux_ring_pedersen_params: &HashMap<u16, RingPedersenParams>,
+    transport: &mut T,
+    transaction: &Transaction,
+) -> Result<EthereumSignature, SignTransactionError<E>>
+where
+    I: IdentityProvider,
+    T: Transport<PreSigningMessage, Error = E> + Transport<SigningMessage, Error = E>,
+{
+    let signature = sign_prehashed(
+        signing_share,
+        sub_share,
+        identity_provider,
+        verified_parties,
+        local_key,
+        party_indices,
+        rid,
+        aux_ring_pedersen_params,
+        transport,
+        &transaction.sighash(),
+    )
+    .map_err(SignTransactionError::Signing)?;
+    EthereumSignature::new(&signature, transaction)
+        .map_err(|_| SignTransactionError::UnsupportedSignature)
+}
+
+// Returns the RLP encoded fields of a legacy transaction (excluding the signature and EIP-155 fields).
+fn legacy_fields(tx: &LegacyTransaction) -> Vec<Vec<u8>> {
+    vec![
+        rlp_uint(tx.nonce.into()),
+        rlp_uint(tx.gas_price),
+        rlp_uint(tx.gas_limit.into()),
+        rlp_bytes(tx.to.as_ref().map(|to| to.as_slice()).unwrap_or_default()),
+        rlp_uint(tx.value),
+        rlp_bytes(&tx.data),
+    ]
+}
+
+// Returns the RLP encoded fields of an EIP-1559 transaction (excluding the signature).
+fn eip_1559_fields(tx: &Eip1559Transaction) -> Vec<Vec<u8>> {
+    let access_list: Vec<Vec<u8>> = tx
+        .access_list
+        .iter()
+        .map(|(address, storage_keys)| {
+            let storage_keys: Vec<Vec<u8>> =
+                storage_keys.iter().map(|key| rlp_bytes(key)).collect();
+            rlp_list(&[rlp_bytes(address), rlp_list(&storage_keys)])
+        })
+        .collect();
+    vec![
+        rlp_uint(tx.chain_id.into()),
+        rlp_uint(tx.nonce.into()),
+        rlp_uint(tx.max_priority_fee_per_gas),
+        rlp_uint(tx.max_fee_per_gas),
+        rlp_uint(tx.gas_limit.into()),
+        rlp_bytes(tx.to.as_ref().map(|to| to.as_slice()).unwrap_or_default()),
+        rlp_uint(tx.value),
+        rlp_bytes(&tx.data),
+        rlp_list(&access_list),
+    ]
+}
+
+// Returns the Keccak256 hash of the data.
+pub(super) fn keccak256(data: &[u8]) -> [u8; 32] {
+    use sha3::Digest;
+    sha3::Keccak256::digest(data).into()
+}
+
+// Returns the bytes without leading zeros (i.e the minimal big-endian encoding of an integer).
+fn strip_leading_zeros(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter().position(|it| *it != 0).unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+// Returns the RLP encoding of an unsigned integer.
+fn rlp_uint(value: u128) -> Vec<u8> {
+    rlp_bytes(strip_leading_zeros(&value.to_be_bytes()))
+}
+
+// Returns the RLP encoding of a byte string.
+fn rlp_bytes(bytes: &[u8]) -> Vec<u8> {
+    match bytes {
+        [byte] if *byte < 0x80 => vec![*byte],
+        _ => {
+            let mut encoded = rlp_length_prefix(bytes.len(), 0x80);
+            encoded.extend_from_slice(bytes);
+            encoded
+        }
+    }
+}
+
+// Returns the RLP encoding of a list of RLP encoded items.
+fn rlp_list(items: &[Vec<u8>]) -> Vec<u8> {
+    let payload = items.concat();
+    let mut encoded = rlp_length_prefix(payload.len(), 0xc0);
+    encoded.extend(payload);
+    encoded
+}
+
+// Returns the RLP length prefix for a payload given the offset for the payload type (i.e `0x80` for strings and `0xc0` for lists).
+fn rlp_length_prefix(len: usize, offset: u8) -> Vec<u8> {
+    if len <= 55 {
+        vec![offset + len as u8]
+    } else {
+        let len_bytes = (len as u64).to_be_bytes();
+        let len_bytes = strip_leading_zeros(&len_bytes);
+        let mut prefix = vec![offset + 55 + len_bytes.len() as u8];
+        prefix.extend_from_slice(len_bytes);
+        prefix
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use curv::arithmetic::Converter;
+    use curv::BigInt;
+
+    #[test]
+    fn legacy_transaction_works() {
+        // EIP-155 example transaction.
+        // Ref: <https://eips.ethereum.org/EIPS/eip-155#example>.
+        let tx = Transaction::Legacy(LegacyTransaction {
+            chain_id: 1,
+            nonce: 9,
+            gas_price: 20_000_000_000,
+            gas_limit: 21_000,
+            to: Some([0x35; 20]),
+            value: 1_000_000_000_000_000_000,
+            data: Vec::new(),
+        });
+        let sighash = tx.sighash();
+        assert_eq!(
+            wamu_core::utils::to_hex(&sighash),
+            "daf5a779ae972f972197303d7b574746c7ef83eadac0f2791ad23db92e4c8e53"
+        );
+
+        // Verifies the `v` value and the signed transaction encoding.
+        let signature = RecoverableSignature {
+            r: BigInt::from_hex("28ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276")
+                .unwrap(),
+            s: BigInt::from_hex("67cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83")
+                .unwrap(),
+            recovery_id: 0,
+        };
+        let eth_signature = EthereumSignature::new(&signature, &tx).unwrap();
+        assert_eq!(eth_signature.v, 37);
+        assert_eq!(
+            wamu_core::utils::to_hex(&tx.encode_signed(&eth_signature)),
+            "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83"
+        );
+
+        // Verifies that the signature recovers the signer (i.e the EIP-155 example private key).
+        let (k256_signature, recovery_id) = signature.to_k256().unwrap();
+        let recovered_key =
+            k256::ecdsa::VerifyingKey::recover_from_prehash(&sighash, &k256_signature, recovery_id)
+                .unwrap();
+        let signing_key = k256::ecdsa::SigningKey::from_bytes(&[0x46; 32].into()).unwrap();
+        assert_eq!(&recovered_key, signing_key.verifying_key());
+    }
+
+    #[test]
+    fn eip_1559_transaction_works() {
+        let tx = Transaction::Eip1559(Eip1559Transaction {
+            chain_id: 1,
+            nonce: 0,
+            max_priority_fee_per_gas: 1_000_000_000,
+            max_fee_per_gas: 100_000_000_000,
+            gas_limit: 21_000,
+            to: Some([0x35; 20]),
+            value: 1_000_000_000_000_000_000,
+            data: Vec::new(),
+            access_list: vec![([0x11; 20], vec![[0x22; 32]])],
+        });
+
+        // Signs the sighash and verifies the `v` value (i.e y parity) and the signed transaction type.
+        let signing_key = k256::ecdsa::SigningKey::from_bytes(&[0x46; 32].into()).unwrap();
+        let (k256_signature, recovery_id) =
+            signing_key.sign_prehash_recoverable(&tx.sighash()).unwrap();
+        let signature_bytes = k256_signature.to_bytes();
+        let signature = RecoverableSignature {
+            r: BigInt::from_bytes(&signature_bytes[..32]),
+            s: BigInt::from_bytes(&signature_bytes[32..]),
+            recovery_id: recovery_id.to_byte(),
+        };
+        let eth_signature = EthereumSignature::new(&signature, &tx).unwrap();
+        assert_eq!(eth_signature.v, u64::from(recovery_id.is_y_odd()));
+        let encoded = tx.encode_signed(&eth_signature);
+        assert_eq!(encoded[0], EIP_1559_TX_TYPE);
+        assert!(encoded.ends_with(strip_leading_zeros(&eth_signature.s)));
+    }
+
+    #[test]
+    fn rlp_encoding_works() {
+        // Ref: <https://ethereum.org/en/developers/docs/data-structures-and-encoding/rlp/#examples>.
+        assert_eq!(rlp_bytes(b"dog"), b"\x83dog");
+        assert_eq!(
+            rlp_list(&[rlp_bytes(b"cat"), rlp_bytes(b"dog")]),
+            b"\xc8\x83cat\x83dog"
+        );
+        assert_eq!(rlp_bytes(&[]), vec![0x80]);
+        assert_eq!(rlp_list(&[]), vec![0xc0]);
+        assert_eq!(rlp_uint(0), vec![0x80]);
+        assert_eq!(rlp_uint(15), vec![0x0f]);
+        assert_eq!(rlp_uint(1024), vec![0x82, 0x04, 0x00]);
+        let lorem = b"Lorem ipsum dolor sit amet, consectetur adipisicing elit";
+        assert_eq!(rlp_bytes(lorem)[..2], [0xb8, 0x38]);
+    }
+}
@@ -0,0 +1,8 @@
+//! Chain specific signing helpers.
+
+#[cfg(feature = "bitcoin")]
+#[doc(cfg(feature = "bitcoin"))]
+pub mod bitcoin;
+pub mod cosmos;
+pub mod eip712;
+pub mod ethereum;
@@ -0,0 +1,379 @@
+//! Echo broadcast (i.e reliable broadcast) of protocol messages for broadcast rounds.
+//!
+//! CGGMP20 rounds assume a reliable broadcast channel, however, messages are just handed to the caller for delivery,
+//! so a malicious relay could show different round messages to different parties.
+//!
+//! Wraps any [`StateMachine`](StateMachine) (including augmented state machines) and withholds incoming broadcast messages
+//! of each round from the wrapped `StateMachine` until all parties have echoed (i.e broadcast)
+//! a digest of all broadcast messages they received for the round and all echoes match the local party's digest.
+//!
+//! **NOTE:** Echoes are not authenticated by this wrapper,
+//! so it should itself be wrapped with [`MessageAuthentication`](crate::MessageAuthentication)
+//! to prevent a malicious relay from forging echoes.
+//!
+//! **NOTE:** All parties are expected to broadcast a message in every round in which any party broadcasts a message
+//! (which is the case for all CGGMP20 protocols).
+
+use round_based::{Msg, StateMachine};
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Deref;
+use std::time::Duration;
+
+use crate::augmented_state_machine::Error;
+
+/// A message of the echo broadcast layer.
+#[derive(Debug, Clone)]
+pub enum EchoBroadcastMessage<B> {
+    /// A message of the wrapped state machine sent in round `round`.
+    Message { round: u16, body: B },
+    /// A digest of all broadcast messages received for round `round`.
+    Echo { round: u16, digest: Vec<u8> },
+}
+
+/// A [`StateMachine`](StateMachine) that only forwards incoming broadcast messages to a wrapped `StateMachine`
+/// after all parties confirm that they received the same broadcast messages for the round.
+pub struct EchoBroadcast<SM: StateMachine> {
+    /// Wrapped state machine.
+    state_machine: SM,
+    /// Deterministic encoder for message bodies of the wrapped state machine.
+    encode_body: fn(&SM::MessageBody) -> Vec<u8>,
+    /// Hashes of broadcast message bodies (including the local party's) indexed by round and sender.
+    broadcasts: BTreeMap<(u16, u16), Vec<u8>>,
+    /// Withheld incoming broadcast messages indexed by round.
+    withheld: BTreeMap<u16, Vec<Msg<SM::MessageBody>>>,
+    /// Echoed digests of other parties indexed by round and sender.
+    echoes: HashMap<(u16, u16), Vec<u8>>,
+    /// Echoed digests of the local party indexed by round.
+    sent_echoes: BTreeMap<u16, Vec<u8>>,
+    /// Echo broadcast message queue.
+    message_queue: Vec<Msg<EchoBroadcastMessage<SM::MessageBody>>>,
+}
+
+impl<SM: StateMachine> EchoBroadcast<SM> {
+    /// Given a state machine and a deterministic encoder for message bodies of the state machine,
+    /// returns a state machine that echo broadcasts messages of the wrapped state machine in every broadcast round.
+    pub fn new(state_machine: SM, encode_body: fn(&SM::MessageBody) -> Vec<u8>) -> Self {
+        let mut echo_broadcast = Self {
+            state_machine,
+            encode_body,
+            broadcasts: BTreeMap::new(),
+            withheld: BTreeMap::new(),
+            echoes: HashMap::new(),
+            sent_echoes: BTreeMap::new(),
+            message_queue: Vec::new(),
+        };
+
+        // Moves messages queued by the wrapped state machine during initialization (if any).
+        echo_broadcast.update_message_queue();
+
+        echo_broadcast
+    }
+
+    /// Returns an immutable reference to the wrapped state machine.
+    pub fn state_machine(&self) -> &SM {
+        &self.state_machine
+    }
+
+    /// Returns the hash of a message body.
+    fn body_hash(&self, body: &SM::MessageBody) -> Vec<u8> {
+        use sha2::Digest;
+        sha2::Sha256::digest((self.encode_body)(body))
+            .deref()
+            .to_vec()
+    }
+
+    /// Returns the digest of all broadcast messages for the round (ordered by sender).
+    fn round_digest(&self, round: u16) -> Vec<u8> {
+        use sha2::{digest::Update, Digest};
+        let hasher = sha2::Sha256::new().chain(round.to_be_bytes());
+        self.broadcasts
+            .range((round, 0)..=(round, u16::MAX))
+            .fold(hasher, |hasher, ((_, sender), body_hash)| {
+                hasher.chain(sender.to_be_bytes()).chain(body_hash)
+            })
+            .finalize()
+            .deref()
+            .to_vec()
+    }
+
+    /// Moves messages in the message queue of the wrapped state machine to the echo broadcast message queue.
+    fn update_message_queue(&mut self) {
+        let round = self.state_machine.current_round();
+        for msg in self.state_machine.message_queue().split_off(0) {
+            if msg.receiver.is_none() {
+                let body_hash = self.body_hash(&msg.body);
+                self.broadcasts.insert((round, msg.sender), body_hash);
+            }
+            self.message_queue
+                .push(msg.map_body(|body| EchoBroadcastMessage::Message { round, body }));
+        }
+    }
+
+    /// Echoes digests for rounds with broadcast messages from all parties,
+    /// and forwards withheld broadcast messages for rounds whose digests have been confirmed by all other parties.
+    fn echo_and_release(&mut self) -> Result<(), Error<SM::Err>> {
+        let party_ind = self.state_machine.party_ind();
+        let parties = self.state_machine.parties();
+        loop {
+            // Echoes the digest for complete rounds.
+            let complete_rounds: Vec<u16> = self
+                .withheld
+                .keys()
+                .copied()
+                .filter(|round| {
+                    !self.sent_echoes.contains_key(round)
+                        && (1..=parties).all(|idx| self.broadcasts.contains_key(&(*round, idx)))
+                })
+                .collect();
+            for round in complete_rounds {
+                let digest = self.round_digest(round);
+                self.message_queue.push(Msg {
+                    sender: party_ind,
+                    receiver: None,
+                    body: EchoBroadcastMessage::Echo {
+                        round,
+                        digest: digest.clone(),
+                    },
+                });
+                self.sent_echoes.insert(round, digest);
+            }
+
+            // Releases withheld broadcast messages for confirmed rounds.
+            let confirmed_round = self.withheld.keys().copied().find(|round| {
+                self.sent_echoes.contains_key(round)
+                    && (1..=parties)
+                        .filter(|idx| *idx != party_ind)
+                        .all(|idx| self.echoes.contains_key(&(*round, idx)))
+            });
+            let Some(round) = confirmed_round else {
+                return Ok(());
+            };
+            let bad_actors: Vec<usize> = (1..=parties)
+                .filter(|idx| {
+                    *idx != party_ind
+                        && self.echoes.get(&(round, *idx)) != self.sent_echoes.get(&round)
+                })
+                .map(usize::from)
+                .collect();
+            if !bad_actors.is_empty() {
+                return Err(Error::InconsistentBroadcast { round, bad_actors });
+            }
+            for msg in self.withheld.remove(&round).unwrap_or_default() {
+                self.state_machine
+                    .handle_incoming(msg)
+                    .map_err(Error::StateMachine)?;
+            }
+            self.update_message_queue();
+        }
+    }
+}
+
+impl<SM: StateMachine> StateMachine for EchoBroadcast<SM> {
+    type MessageBody = EchoBroadcastMessage<SM::MessageBody>;
+    type Err = Error<SM::Err>;
+    type Output = SM::Output;
+
+    fn handle_incoming(&mut self, msg: Msg<Self::MessageBody>) -> Result<(), Self::Err> {
+        let (sender, receiver) = (msg.sender, msg.receiver);
+        match msg.body {
+            EchoBroadcastMessage::Message { round, body } if receiver.is_none() => {
+                // Drops exact duplicates and rejects conflicting broadcast messages.
+                let body_hash = self.body_hash(&body);
+                match self.broadcasts.get(&(round, sender)) {
+                    Some(prev_body_hash) if prev_body_hash == &body_hash => return Ok(()),
+                    Some(_) => {
+                        return Err(Error::InconsistentBroadcast {
+                            round,
+                            bad_actors: vec![sender as usize],
+                        })
+                    }
+                    None => {
+                        self.broadcasts.insert((round, sender), body_hash);
+                    }
+                }
+
+                // Withholds the broadcast message until the round is confirmed by all other parties.
+                self.withheld.entry(round).or_default().push(Msg {
+                    sender,
+                    receiver,
+                    body,
+                });
+            }
+            EchoBroadcastMessage::Message { body, .. } => {
+                // Forwards P2P messages to the wrapped state machine.
+                self.state_machine
+                    .handle_incoming(Msg {
+                        sender,
+                        receiver,
+                        body,
+                    })
+                    .map_err(Error::StateMachine)?;
+                self.update_message_queue();
+            }
+            EchoBroadcastMessage::Echo { round, digest } => {
+                // Drops exact duplicates and rejects conflicting echoes.
+                match self.echoes.get(&(round, sender)) {
+                    Some(prev_digest) if prev_digest == &digest => return Ok(()),
+                    Some(_) => {
+                        return Err(Error::InconsistentBroadcast {
+                            round,
+                            bad_actors: vec![sender as usize],
+                        })
+                    }
+                    None => {
+                        self.echoes.insert((round, sender), digest);
+                    }
+                }
+            }
+        }
+        self.echo_and_release()
+    }
+
+    fn message_queue(&mut self) -> &mut Vec<Msg<Self::MessageBody>> {
+        &mut self.message_queue
+    }
+
+    fn wants_to_proceed(&self) -> bool {
+        self.state_machine.wants_to_proceed()
+    }
+
+    fn proceed(&mut self) -> Result<(), Self::Err> {
+        self.state_machine.proceed().map_err(Error::StateMachine)?;
+
+        // Updates the message queue and echoes digests of rounds that are now complete (if any).
+        self.update_message_queue();
+        self.echo_and_release()
+    }
+
+    fn round_timeout(&self) -> Option<Duration> {
+        self.state_machine.round_timeout()
+    }
+
+    fn round_timeout_reached(&mut self) -> Self::Err {
+        Error::StateMachine(self.state_machine.round_timeout_reached())
+    }
+
+    fn is_finished(&self) -> bool {
+        self.state_machine.is_finished()
+    }
+
+    fn pick_output(&mut self) -> Option<Result<Self::Output, Self::Err>> {
+        self.state_machine
+            .pick_output()
+            .map(|result| result.map_err(Error::StateMachine))
+    }
+
+    fn current_round(&self) -> u16 {
+        self.state_machine.current_round()
+    }
+
+    fn total_rounds(&self) -> Option<u16> {
+        self.state_machine.total_rounds()
+    }
+
+    fn party_ind(&self) -> u16 {
+        self.state_machine.party_ind()
+    }
+
+    fn parties(&self) -> u16 {
+        self.state_machine.parties()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen::AugmentedKeyGen;
+    use round_based::dev::Simulation;
+    use wamu_core::crypto::VerifyingKey;
+    use wamu_core::test_utils::MockECDSAIdentityProvider;
+    use wamu_core::IdentityProvider;
+
+    // Encodes augmented key generation message bodies (deterministically) for tests.
+    fn encode_keygen_body(
+        body: &<AugmentedKeyGen<'_, MockECDSAIdentityProvider> as StateMachine>::MessageBody,
+    ) -> Vec<u8> {
+        format!("{:?}", body.base).into_bytes()
+    }
+
+    #[test]
+    fn echo_broadcast_works() {
+        // Creates identity providers and verifying keys for all parties.
+        let (threshold, n_parties) = (1, 3);
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (1..=n_parties)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let new_party = |idx: usize| {
+            EchoBroadcast::new(
+                AugmentedKeyGen::new(
+                    &identity_providers[idx],
+                    &verifying_keys,
+                    (idx + 1) as u16,
+                    threshold,
+                    n_parties,
+                )
+                .unwrap(),
+                encode_keygen_body,
+            )
+        };
+
+        // Runs key generation with echo broadcast in all broadcast rounds.
+        let mut simulation = Simulation::new();
+        for idx in 0..n_parties as usize {
+            simulation.add_party(new_party(idx));
+        }
+        let outputs = simulation.run().unwrap();
+        assert_eq!(outputs.len(), n_parties as usize);
+        for output in &outputs {
+            assert_eq!(output.base.public_key(), outputs[0].base.public_key());
+        }
+
+        // Broadcast messages are withheld until all other parties echo the same digest.
+        let (mut party_1, mut party_2, mut party_3) = (new_party(0), new_party(1), new_party(2));
+        let msg_1 = party_1.message_queue()[0].clone();
+        let msg_2 = party_2.message_queue()[0].clone();
+        party_3.message_queue().clear();
+        assert!(party_3.handle_incoming(msg_1.clone()).is_ok());
+        assert!(party_3.message_queue().is_empty());
+        assert!(party_3.handle_incoming(msg_2).is_ok());
+        let echo = party_3.message_queue().pop().unwrap();
+        let (round, digest) = match echo.body {
+            EchoBroadcastMessage::Echo { round, digest } => (round, digest),
+            _ => panic!("expected an echo"),
+        };
+
+        // Conflicting broadcast messages from the same sender are rejected.
+        let conflicting_msg = new_party(0).message_queue()[0].clone();
+        assert!(matches!(
+            party_3.handle_incoming(conflicting_msg),
+            Err(Error::InconsistentBroadcast { bad_actors, .. }) if bad_actors == vec![1]
+        ));
+
+        // Mismatched echoes are rejected with the parties that echoed them.
+        assert!(party_3
+            .handle_incoming(Msg {
+                sender: 2,
+                receiver: None,
+                body: EchoBroadcastMessage::Echo {
+                    round,
+                    digest: digest.clone(),
+                },
+            })
+            .is_ok());
+        assert!(matches!(
+            party_3.handle_incoming(Msg {
+                sender: 1,
+                receiver: None,
+                body: EchoBroadcastMessage::Echo {
+                    round,
+                    digest: vec![0; digest.len()],
+                },
+            }),
+            Err(Error::InconsistentBroadcast { bad_actors, .. }) if bad_actors == vec![1]
+        ));
+    }
+}
@@ -0,0 +1,29 @@
+//! Protocol event callbacks (e.g for driving UI updates and alerting without polling state).
+//!
+//! Augmented state machines (see [`AugmentedStateMachine::event_sink`](crate::augmented_state_machine::AugmentedStateMachine::event_sink))
+//! and the [`SessionManager`](crate::SessionManager) publish events to an [`EventSink`].
+//!
+//! **NOTE:** Events published by augmented state machines don't include a session identifier,
+//! so state machines that are managed by a session manager with an event sink don't need their own event sink.
+
+/// A receiver of protocol events.
+///
+/// All callbacks default to no-ops, so implementations only need to override the events they're interested in.
+pub trait EventSink {
+    /// Called when the party advances to a new round.
+    fn on_round_started(&self, _session_id: Option<&[u8; 32]>, _round: u16) {}
+
+    /// Called when a message from the party with index `sender` is received (and its augmentations are verified)
+    /// in round `round`.
+    fn on_message_received(&self, _session_id: Option<&[u8; 32]>, _sender: u16, _round: u16) {}
+
+    /// Called when the party with index `culprit` is blamed for an error (e.g an invalid message, equivocation or a timeout).
+    fn on_party_blamed(&self, _session_id: Option<&[u8; 32]>, _culprit: u16) {}
+
+    /// Called when the session finishes either successfully (i.e with an output) or unsuccessfully
+    /// (i.e with a critical error or after the session expires).
+    fn on_session_complete(&self, _session_id: Option<&[u8; 32]>, _success: bool) {}
+}
+
+/// A no-op event sink.
+impl EventSink for () {}
@@ -0,0 +1,673 @@
+//! [gRPC](https://grpc.io/) service adapter for headless co-signing nodes
+//! (i.e hand-written `tonic` server and client for the `CoSigner` service in the `proto/wamu_cggmp.proto` schema).
+//!
+//! A [`CoSignerService`] exposes a [`SessionManager`] over gRPC, i.e remote parties (or relays) submit incoming messages,
+//! fetch outgoing messages and query the status of sessions,
+//! while the operator drives the session manager by calling [`CoSignerService::pump`] (e.g on a timer).
+//!
+//! **NOTE:** The session manager isn't shared with the gRPC server (i.e state machines don't have to be `Send`),
+//! so incoming messages are queued until the next call to [`CoSignerService::pump`],
+//! and incoming messages that are rejected by the session manager (e.g for another wallet) are dropped (i.e like a lossy transport).
+
+use async_trait::async_trait;
+use round_based::Msg;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use tonic::codegen::{http, Body, BoxFuture, Bytes, Context, Poll, StdError};
+use wamu_core::compression::Compression;
+use wamu_core::envelope::ProtocolEnvelope;
+
+use crate::session_manager::{self, SessionEvent, SessionManager};
+use crate::socks5::Socks5Proxy;
+
+/// The fully qualified name of the co-signer service.
+const SERVICE_NAME: &str = "wamu.cggmp.CoSigner";
+
+/// The maximum number of queued incoming messages.
+const MAX_INCOMING_MESSAGES: usize = 4096;
+
+/// A protocol message (with an encoded message body) wrapped in a versioned protocol envelope (i.e bound to a wallet and a session).
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SessionEnvelope {
+    #[prost(uint32, tag = "1")]
+    pub version: u32,
+    #[prost(bytes = "vec", tag = "2")]
+    pub wallet_id: Vec<u8>,
+    #[prost(bytes = "vec", tag = "3")]
+    pub session_id: Vec<u8>,
+    #[prost(uint32, tag = "4")]
+    pub sender: u32,
+    #[prost(uint32, optional, tag = "5")]
+    pub receiver: Option<u32>,
+    #[prost(bytes = "vec", tag = "6")]
+    pub body: Vec<u8>,
+    #[prost(uint32, tag = "7")]
+    pub compression: u32,
+}
+
+/// A request to submit an incoming message to a co-signer.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SubmitRequest {
+    #[prost(message, optional, tag = "1")]
+    pub envelope: Option<SessionEnvelope>,
+}
+
+/// A response for a submitted message.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SubmitResponse {}
+
+/// A request to fetch (and remove) the outgoing messages of a co-signer.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct FetchOutgoingRequest {
+    #[prost(bytes = "vec", optional, tag = "1")]
+    pub session_id: Option<Vec<u8>>,
+}
+
+/// The outgoing messages of a co-signer.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct FetchOutgoingResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub envelopes: Vec<SessionEnvelope>,
+}
+
+/// A request for the status of a session.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SessionStatusRequest {
+    #[prost(bytes = "vec", tag = "1")]
+    pub session_id: Vec<u8>,
+}
+
+/// The status of a session.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum SessionStatus {
+    Unknown = 0,
+    Running = 1,
+    Finished = 2,
+    Failed = 3,
+    Expired = 4,
+}
+
+/// The status of a session (and the error for failed sessions).
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SessionStatusResponse {
+    #[prost(enumeration = "SessionStatus", tag = "1")]
+    pub status: i32,
+    #[prost(string, optional, tag = "2")]
+    pub error: Option<String>,
+}
+
+impl From<session_manager::SessionEnvelope> for SessionEnvelope {
+    fn from(value: session_manager::SessionEnvelope) -> Self {
+        Self {
+            version: u32::from(value.version),
+            wallet_id: value.wallet_id.to_vec(),
+            session_id: value.session_id.to_vec(),
+            sender: u32::from(value.body.sender),
+            receiver: value.body.receiver.map(u32::from),
+            body: value.body.body,
+            compression: u32::from(value.compression.id()),
+        }
+    }
+}
+
+impl TryFrom<SessionEnvelope> for session_manager::SessionEnvelope {
+    type Error = wamu_core::Error;
+
+    fn try_from(value: SessionEnvelope) -> Result<Self, Self::Error> {
+        let to_u16 = |it: u32| u16::try_from(it).map_err(|_| wamu_core::Error::Encoding);
+        let compression = u8::try_from(value.compression)
+            .ok()
+            .and_then(Compression::from_id)
+            .ok_or(wamu_core::Error::Encoding)?;
+        Ok(ProtocolEnvelope::new(
+            to_u16(value.version)?,
+            value
+                .wallet_id
+                .try_into()
+                .map_err(|_| wamu_core::Error::Encoding)?,
+            decode_session_id(value.session_id)?,
+            Msg {
+                sender: to_u16(value.sender)?,
+                receiver: value.receiver.map(to_u16).transpose()?,
+                body: value.body,
+            },
+        )
+        .with_compression(compression))
+    }
+}
+
+// Decodes a 32 byte session identifier.
+fn decode_session_id(bytes: Vec<u8>) -> Result<[u8; 32], wamu_core::Error> {
+    bytes.try_into().map_err(|_| wamu_core::Error::Encoding)
+}
+
+/// The co-signer gRPC service.
+#[async_trait]
+pub trait CoSigner: Send + Sync + 'static {
+    /// Submits an incoming message.
+    async fn submit(
+        &self,
+        request: tonic::Request<SubmitRequest>,
+    ) -> Result<tonic::Response<SubmitResponse>, tonic::Status>;
+
+    /// Fetches (and removes) outgoing messages.
+    async fn fetch_outgoing(
+        &self,
+        request: tonic::Request<FetchOutgoingRequest>,
+    ) -> Result<tonic::Response<FetchOutgoingResponse>, tonic::Status>;
+
+    /// Returns the status of a session.
+    async fn get_session_status(
+        &self,
+        request: tonic::Request<SessionStatusRequest>,
+    ) -> Result<tonic::Response<SessionStatusResponse>, tonic::Status>;
+}
+
+/// Shared state of a co-signer service.
+#[derive(Default)]
+struct CoSignerState {
+    /// Incoming messages that haven't been submitted to the session manager yet.
+    incoming: Vec<session_manager::SessionEnvelope>,
+    /// Outgoing messages that haven't been fetched yet.
+    outgoing: Vec<session_manager::SessionEnvelope>,
+    /// Status of all known sessions (and the error for failed sessions).
+    statuses: HashMap<[u8; 32], (SessionStatus, Option<String>)>,
+}
+
+/// A [`CoSigner`] implementation that exposes a [`SessionManager`] (see module documentation for details).
+#[derive(Clone, Default)]
+pub struct CoSignerService {
+    /// Shared state (i.e between the gRPC server and the operator).
+    state: Arc<Mutex<CoSignerState>>,
+}
+
+impl CoSignerService {
+    /// Returns a co-signer service with no queued messages.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a gRPC server for the co-signer service (e.g for adding to a `tonic::transport::Server`).
+    pub fn into_server(self) -> CoSignerServer<Self> {
+        CoSignerServer::new(self)
+    }
+
+    /// Submits queued incoming messages to the session manager, polls it, queues outgoing messages (for fetching),
+    /// updates session statuses and returns all other session events (e.g outputs of finished sessions).
+    pub fn pump<O, E: std::fmt::Debug>(
+        &self,
+        session_manager: &mut SessionManager<'_, O, E>,
+    ) -> Vec<SessionEvent<O, E>> {
+        let mut state = self.state();
+        for envelope in std::mem::take(&mut state.incoming) {
+            // Rejected messages are dropped.
+            let _ = session_manager.submit(envelope);
+        }
+
+        let mut events = Vec::new();
+        for event in session_manager.poll() {
+            match &event {
+                SessionEvent::Outgoing(envelope) => {
+                    state.outgoing.push(envelope.clone());
+                    continue;
+                }
+                SessionEvent::Finished { session_id, .. } => {
+                    state
+                        .statuses
+                        .insert(*session_id, (SessionStatus::Finished, None));
+                }
+                SessionEvent::Failed {
+                    session_id,
+                    error,
+                    is_critical: true,
+                } => {
+                    state.statuses.insert(
+                        *session_id,
+                        (SessionStatus::Failed, Some(format!("{error:?}"))),
+                    );
+                }
+                SessionEvent::Failed { .. } => (),
+                SessionEvent::Expired { session_id } => {
+                    state
+                        .statuses
+                        .insert(*session_id, (SessionStatus::Expired, None));
+                }
+            }
+            events.push(event);
+        }
+        for session_id in session_manager.session_ids() {
+            state
+                .statuses
+                .insert(session_id, (SessionStatus::Running, None));
+        }
+        events
+    }
+
+    /// Returns the status of a session (and the error for failed sessions).
+    pub fn status(&self, session_id: &[u8; 32]) -> (SessionStatus, Option<String>) {
+        self.state()
+            .statuses
+            .get(session_id)
+            .cloned()
+            .unwrap_or((SessionStatus::Unknown, None))
+    }
+
+    // Returns the shared state (i.e a panic while holding the lock doesn't invalidate the queues).
+    fn state(&self) -> MutexGuard<'_, CoSignerState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[async_trait]
+impl CoSigner for CoSignerService {
+    async fn submit(
+        &self,
+        request: tonic::Request<SubmitRequest>,
+    ) -> Result<tonic::Response<SubmitResponse>, tonic::Status> {
+        let envelope = request
+            .into_inner()
+            .envelope
+            .ok_or_else(|| tonic::Status::invalid_argument("missing envelope"))?
+            .try_into()
+            .map_err(|_| tonic::Status::invalid_argument("invalid envelope"))?;
+        let mut state = self.state();
+        if state.incoming.len() >= MAX_INCOMING_MESSAGES {
+            return Err(tonic::Status::resource_exhausted(
+                "too many queued incoming messages",
+            ));
+        }
+        state.incoming.push(envelope);
+        Ok(tonic::Response::new(SubmitResponse {}))
+    }
+
+    async fn fetch_outgoing(
+        &self,
+        request: tonic::Request<FetchOutgoingRequest>,
+    ) -> Result<tonic::Response<FetchOutgoingResponse>, tonic::Status> {
+        let session_id = request
+            .into_inner()
+            .session_id
+            .map(decode_session_id)
+            .transpose()
+            .map_err(|_| tonic::Status::invalid_argument("invalid session id"))?;
+        let mut state = self.state();
+        let (fetched, remaining): (Vec<_>, Vec<_>) = std::mem::take(&mut state.outgoing)
+            .into_iter()
+            .partition(|envelope| {
+                session_id.map_or(true, |session_id| envelope.session_id == session_id)
+            });
+        state.outgoing = remaining;
+        Ok(tonic::Response::new(FetchOutgoingResponse {
+            envelopes: fetched.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn get_session_status(
+        &self,
+        request: tonic::Request<SessionStatusRequest>,
+    ) -> Result<tonic::Response<SessionStatusResponse>, tonic::Status> {
+        let session_id = decode_session_id(request.into_inner().session_id)
+            .map_err(|_| tonic::Status::invalid_argument("invalid session id"))?;
+        let (status, error) = self.status(&session_id);
+        Ok(tonic::Response::new(SessionStatusResponse {
+            status: status as i32,
+            error,
+        }))
+    }
+}
+
+/// A gRPC server for a [`CoSigner`] implementation.
+#[derive(Debug)]
+pub struct CoSignerServer<T> {
+    /// The wrapped service implementation.
+    inner: Arc<T>,
+}
+
+impl<T: CoSigner> CoSignerServer<T> {
+    /// Given a service implementation, returns a gRPC server for it.
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+}
+
+impl<T> Clone for CoSignerServer<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T: CoSigner> tonic::server::NamedService for CoSignerServer<T> {
+    const NAME: &'static str = SERVICE_NAME;
+}
+
+/// A handler for a unary method of a [`CoSigner`] implementation.
+type UnaryHandler<T, Req, Res> =
+    fn(Arc<T>, tonic::Request<Req>) -> BoxFuture<tonic::Response<Res>, tonic::Status>;
+
+/// A `tonic` unary service for a method of a [`CoSigner`] implementation.
+struct UnaryMethod<T, Req, Res>(Arc<T>, UnaryHandler<T, Req, Res>);
+
+impl<T: CoSigner, Req: Send + 'static, Res> tonic::server::UnaryService<Req>
+    for UnaryMethod<T, Req, Res>
+{
+    type Response = Res;
+    type Future = BoxFuture<tonic::Response<Res>, tonic::Status>;
+
+    fn call(&mut self, request: tonic::Request<Req>) -> Self::Future {
+        (self.1)(Arc::clone(&self.0), request)
+    }
+}
+
+impl<T: CoSigner> CoSignerServer<T> {
+    // Handles a unary request with the given handler.
+    fn unary<Req, Res, B>(
+        &self,
+        handler: UnaryHandler<T, Req, Res>,
+        request: http::Request<B>,
+    ) -> BoxFuture<http::Response<tonic::body::BoxBody>, Infallible>
+    where
+        Req: prost::Message + Default + Send + Sync + 'static,
+        Res: prost::Message + Send + Sync + 'static,
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        let method = UnaryMethod(Arc::clone(&self.inner), handler);
+        Box::pin(async move {
+            let codec = tonic::codec::ProstCodec::<Res, Req>::default();
+            let mut grpc = tonic::server::Grpc::new(codec);
+            Ok(grpc.unary(method, request).await)
+        })
+    }
+}
+
+impl<T, B> tonic::codegen::Service<http::Request<B>> for CoSignerServer<T>
+where
+    T: CoSigner,
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<tonic::body::BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        match request.uri().path() {
+            "/wamu.cggmp.CoSigner/Submit" => self.unary::<SubmitRequest, SubmitResponse, _>(
+                |inner, request| Box::pin(async move { inner.submit(request).await }),
+                request,
+            ),
+            "/wamu.cggmp.CoSigner/FetchOutgoing" => self
+                .unary::<FetchOutgoingRequest, FetchOutgoingResponse, _>(
+                    |inner, request| Box::pin(async move { inner.fetch_outgoing(request).await }),
+                    request,
+                ),
+            "/wamu.cggmp.CoSigner/GetSessionStatus" => self
+                .unary::<SessionStatusRequest, SessionStatusResponse, _>(
+                    |inner, request| {
+                        Box::pin(async move { inner.get_session_status(request).await })
+                    },
+                    request,
+                ),
+            _ => Box::pin(
+                async move { Ok(tonic::Status::unimplemented("unknown method").to_http()) },
+            ),
+        }
+    }
+}
+
+/// A gRPC client for a co-signer service.
+#[derive(Debug, Clone)]
+pub struct CoSignerClient<T> {
+    /// The wrapped `tonic` client.
+    inner: tonic::client::Grpc<T>,
+}
+
+impl CoSignerClient<tonic::transport::Channel> {
+    /// Given the address of a co-signer (e.g "http://[::1]:50051"), returns a connected client.
+    pub async fn connect<D>(address: D) -> Result<Self, tonic::transport::Error>
+    where
+        D: TryInto<tonic::transport::Endpoint>,
+        D::Error: Into<StdError>,
+    {
+        let channel = tonic::transport::Endpoint::new(address)?.connect().await?;
+        Ok(Self::new(channel))
+    }
+
+    /// Same as [`CoSignerClient::connect`] but connects to the co-signer via a SOCKS5 proxy (e.g Tor),
+    /// so co-signer addresses can be `.onion` addresses and the co-signer doesn't learn the IP address of the client.
+    pub async fn connect_via_proxy<D>(
+        address: D,
+        proxy: Socks5Proxy,
+    ) -> Result<Self, tonic::transport::Error>
+    where
+        D: TryInto<tonic::transport::Endpoint>,
+        D::Error: Into<StdError>,
+    {
+        let channel = tonic::transport::Endpoint::new(address)?
+            .connect_with_connector(Socks5Connector { proxy })
+            .await?;
+        Ok(Self::new(channel))
+    }
+}
+
+/// A `tonic` connector for connections via a SOCKS5 proxy.
+#[derive(Debug, Clone)]
+struct Socks5Connector {
+    proxy: Socks5Proxy,
+}
+
+impl tonic::codegen::Service<http::Uri> for Socks5Connector {
+    type Response = tokio::net::TcpStream;
+    type Error = std::io::Error;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: http::Uri) -> Self::Future {
+        let proxy = self.proxy.clone();
+        Box::pin(async move {
+            let host = uri
+                .host()
+                .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::InvalidInput))?
+                .to_owned();
+            let port = uri.port_u16().unwrap_or(match uri.scheme_str() {
+                Some("https") => 443,
+                _ => 80,
+            });
+            // The SOCKS5 handshake is blocking, so it runs on the blocking thread pool.
+            let stream = tokio::task::spawn_blocking(move || proxy.connect(&host, port)).await??;
+            stream.set_nonblocking(true)?;
+            tokio::net::TcpStream::from_std(stream)
+        })
+    }
+}
+
+impl<T> CoSignerClient<T>
+where
+    T: tonic::client::GrpcService<tonic::body::BoxBody>,
+    T::Error: Into<StdError>,
+    T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+    <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+{
+    /// Given a `tonic` service (e.g a channel), returns a client.
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner: tonic::client::Grpc::new(inner),
+        }
+    }
+
+    /// Submits an incoming message to the co-signer.
+    pub async fn submit(
+        &mut self,
+        envelope: session_manager::SessionEnvelope,
+    ) -> Result<(), tonic::Status> {
+        self.unary::<_, SubmitResponse>(
+            "/wamu.cggmp.CoSigner/Submit",
+            SubmitRequest {
+                envelope: Some(envelope.into()),
+            },
+        )
+        .await
+        .map(|_| ())
+    }
+
+    /// Fetches (and removes) the outgoing messages of the co-signer (optionally, only for a given session).
+    pub async fn fetch_outgoing(
+        &mut self,
+        session_id: Option<[u8; 32]>,
+    ) -> Result<Vec<session_manager::SessionEnvelope>, tonic::Status> {
+        self.unary::<_, FetchOutgoingResponse>(
+            "/wamu.cggmp.CoSigner/FetchOutgoing",
+            FetchOutgoingRequest {
+                session_id: session_id.map(|session_id| session_id.to_vec()),
+            },
+        )
+        .await?
+        .envelopes
+        .into_iter()
+        .map(|envelope| {
+            envelope
+                .try_into()
+                .map_err(|_| tonic::Status::internal("invalid envelope"))
+        })
+        .collect()
+    }
+
+    /// Returns the status of a session (and the error for failed sessions).
+    pub async fn session_status(
+        &mut self,
+        session_id: [u8; 32],
+    ) -> Result<(SessionStatus, Option<String>), tonic::Status> {
+        let response = self
+            .unary::<_, SessionStatusResponse>(
+                "/wamu.cggmp.CoSigner/GetSessionStatus",
+                SessionStatusRequest {
+                    session_id: session_id.to_vec(),
+                },
+            )
+            .await?;
+        Ok((response.status(), response.error))
+    }
+
+    // Sends a unary request and returns the response message.
+    async fn unary<Req, Res>(
+        &mut self,
+        path: &'static str,
+        request: Req,
+    ) -> Result<Res, tonic::Status>
+    where
+        Req: prost::Message + Send + Sync + 'static,
+        Res: prost::Message + Default + Send + Sync + 'static,
+    {
+        self.inner.ready().await.map_err(|error| {
+            let error: StdError = error.into();
+            tonic::Status::unknown(format!("service was not ready: {error}"))
+        })?;
+        let codec = tonic::codec::ProstCodec::<Req, Res>::default();
+        self.inner
+            .unary(
+                tonic::Request::new(request),
+                http::uri::PathAndQuery::from_static(path),
+                codec,
+            )
+            .await
+            .map(tonic::Response::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secure_channel::tests::{ToyError, ToyProtocol};
+    use round_based::StateMachine;
+    use std::time::Duration;
+
+    type ToyOutput = <ToyProtocol as StateMachine>::Output;
+
+    #[tokio::test]
+    async fn co_signer_service_works() {
+        let n_parties = 2u16;
+        let wallet_id = [1u8; 32];
+        let mut managers: Vec<SessionManager<'_, ToyOutput, ToyError>> = (0..n_parties)
+            .map(|_| SessionManager::new(1, wallet_id, Duration::from_secs(60)))
+            .collect();
+        let services: Vec<CoSignerService> =
+            (0..n_parties).map(|_| CoSignerService::new()).collect();
+        let encode = Vec::clone;
+        let decode = |bytes: &[u8]| Some(bytes.to_vec());
+
+        // Starts a session on both co-signers.
+        let session_id = managers[0].start(ToyProtocol::new(1, n_parties), encode, decode);
+        managers[1]
+            .join(session_id, ToyProtocol::new(2, n_parties), encode, decode)
+            .unwrap();
+        assert_eq!(services[0].status(&session_id).0, SessionStatus::Unknown);
+
+        // Routes fetched outgoing messages (i.e encoded and decoded as Protobuf) between the co-signers until both are finished.
+        let mut outputs = Vec::new();
+        while outputs.len() < n_parties as usize {
+            for (idx, service) in services.iter().enumerate() {
+                for event in service.pump(&mut managers[idx]) {
+                    match event {
+                        SessionEvent::Finished { output, .. } => outputs.push(output),
+                        event => panic!("unexpected event: {event:?}"),
+                    }
+                }
+                let fetched = service
+                    .fetch_outgoing(tonic::Request::new(FetchOutgoingRequest {
+                        session_id: Some(session_id.to_vec()),
+                    }))
+                    .await
+                    .unwrap()
+                    .into_inner();
+                let encoded = prost::Message::encode_to_vec(&fetched);
+                let decoded: FetchOutgoingResponse =
+                    prost::Message::decode(encoded.as_slice()).unwrap();
+                for envelope in decoded.envelopes {
+                    services[1 - idx]
+                        .submit(tonic::Request::new(SubmitRequest {
+                            envelope: Some(envelope),
+                        }))
+                        .await
+                        .unwrap();
+                }
+            }
+        }
+        assert_eq!(outputs.len(), 2);
+
+        // Reports session statuses.
+        let status = services[0]
+            .get_session_status(tonic::Request::new(SessionStatusRequest {
+                session_id: session_id.to_vec(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(status.status(), SessionStatus::Finished);
+        assert_eq!(status.error, None);
+
+        // Invalid requests are rejected.
+        assert!(services[0]
+            .submit(tonic::Request::new(SubmitRequest { envelope: None }))
+            .await
+            .is_err());
+        assert!(services[0]
+            .get_session_status(tonic::Request::new(SessionStatusRequest {
+                session_id: vec![0; 31],
+            }))
+            .await
+            .is_err());
+    }
+}
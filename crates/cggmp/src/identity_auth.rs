@@ -96,7 +96,15 @@ impl<'a, I: IdentityProvider> StateMachine for IdentityAuthentication<'a, I> {
                             self.command,
                             &request,
                             self.verified_parties,
-                        )?;
+                        )
+                        .map_err(|error| {
+                            Error::Blame(
+                                wamu_core::Blame::new(error)
+                                    .with_verifying_key(request.verifying_key.clone())
+                                    .with_idx(msg.sender)
+                                    .with_round(1),
+                            )
+                        })?;
 
                     // Moves on to the next round.
                     self.round = Round::Two;
@@ -122,6 +130,7 @@ impl<'a, I: IdentityProvider> StateMachine for IdentityAuthentication<'a, I> {
                 // while other parties verify the challenge response
                 // and immediately process the next round if the challenge response verification is successful.
                 if !self.is_initiator {
+                    let verifying_key = &self.verified_parties[msg.sender as usize - 1];
                     wamu_core::identity_challenge::verify(
                         &signature,
                         &self
@@ -129,8 +138,16 @@ impl<'a, I: IdentityProvider> StateMachine for IdentityAuthentication<'a, I> {
                             .values()
                             .copied()
                             .collect::<Vec<Random32Bytes>>(),
-                        &self.verified_parties[msg.sender as usize - 1],
-                    )?;
+                        verifying_key,
+                    )
+                    .map_err(|error| {
+                        Error::Blame(
+                            wamu_core::Blame::new(error.into())
+                                .with_verifying_key(verifying_key.clone())
+                                .with_idx(msg.sender)
+                                .with_round(3),
+                        )
+                    })?;
 
                     // Moves on the next round.
                     self.round = Round::Four;
@@ -306,6 +323,7 @@ pub enum Message {
 #[derive(Debug)]
 pub enum Error {
     Core(IdentityAuthedRequestError),
+    Blame(wamu_core::Blame<IdentityAuthedRequestError>),
     AlreadyPicked,
 }
 
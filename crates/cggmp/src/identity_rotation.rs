@@ -5,10 +5,12 @@
 use round_based::{IsCritical, Msg, StateMachine};
 use std::collections::HashMap;
 use std::time::Duration;
-use wamu_core::crypto::{Random32Bytes, VerifyingKey};
+use wamu_core::crypto::{Random32Bytes, Signature, VerifyingKey};
+use wamu_core::roster_log::RosterLog;
 use wamu_core::{
-    IdentityAuthedRequestError, IdentityAuthedRequestPayload, IdentityProvider,
-    IdentityRotationChallengeResponsePayload, SigningShare, SubShare,
+    CommandCancellationPayload, IdentityAuthedRequestError, IdentityAuthedRequestPayload,
+    IdentityProvider, IdentityRotationChallengeResponsePayload, RotationCertificate, SigningShare,
+    SubShare,
 };
 
 /// A [StateMachine](StateMachine) that implements [identity rotation as described by the Wamu protocol](https://wamu.tech/specification#identity-rotation).
@@ -42,6 +44,18 @@ pub struct IdentityRotation<'a, I: IdentityProvider> {
     received_outcomes: HashMap<u16, Option<bool>>,
     /// Verifying keys for other the parties.
     output_verified_parties_option: Option<Vec<VerifyingKey>>,
+    /// The roster log for emitting an identity rotation certificate (if any).
+    roster_log_option: Option<&'a RosterLog>,
+    /// The old and new verifying keys of the rotating party (only `Some` after the rotation is verified).
+    rotation_option: Option<(VerifyingKey, VerifyingKey)>,
+    /// Counter-signatures of the identity rotation.
+    countersignatures: Vec<(VerifyingKey, Signature)>,
+    /// The identity rotation request.
+    request_option: Option<IdentityAuthedRequestPayload>,
+    /// Number of parties (other than the owner of the rotating identity) required to cancel the identity rotation.
+    cancellation_quorum_size: usize,
+    /// Received cancellations of the identity rotation.
+    cancellations: Vec<CommandCancellationPayload>,
 }
 
 impl<'a, I: IdentityProvider> IdentityRotation<'a, I> {
@@ -58,13 +72,15 @@ impl<'a, I: IdentityProvider> IdentityRotation<'a, I> {
         // Generates initiation payload for rotating party and moves it to round 2.
         let mut message_queue = Vec::new();
         let mut round = Round::One;
+        let mut request_option = None;
         if new_identity_provider_option.is_some() {
             let request = wamu_core::identity_rotation::initiate(identity_provider);
             message_queue.push(Msg {
                 sender: idx,
                 receiver: None,
-                body: Message::Round1(request),
+                body: Message::Round1(request.clone()),
             });
+            request_option = Some(request);
             round = Round::Two;
         }
 
@@ -83,8 +99,103 @@ impl<'a, I: IdentityProvider> IdentityRotation<'a, I> {
             outcome: None,
             received_outcomes: HashMap::new(),
             output_verified_parties_option: None,
+            roster_log_option: None,
+            rotation_option: None,
+            countersignatures: Vec::new(),
+            request_option,
+            // Defaults to all other parties.
+            cancellation_quorum_size: n_parties as usize - 1,
+            cancellations: Vec::new(),
         }
     }
+
+    /// Sets the number of parties (other than the owner of the rotating identity) required to cancel the identity rotation
+    /// (defaults to all other parties).
+    ///
+    /// **NOTE:** A cancellation from the owner of the rotating identity is always sufficient.
+    pub fn with_cancellation_quorum(mut self, quorum_size: usize) -> Self {
+        self.cancellation_quorum_size = quorum_size;
+        self
+    }
+
+    /// Cancels the in-flight identity rotation and broadcasts a signed cancellation to the other parties
+    /// (e.g when the rotation was initiated by a compromised device).
+    ///
+    /// **NOTE:** Cancellations are only effective before the rotation is completed.
+    pub fn cancel(&mut self) -> Result<(), Error> {
+        if matches!(self.round, Round::Final | Round::Gone) {
+            return Err(Error::AlreadyCompleted);
+        }
+        let request = self.request_option.as_ref().ok_or(Error::InvalidState)?;
+        let cancellation = wamu_core::identity_rotation::cancel(request, self.identity_provider);
+        self.cancellations.push(cancellation.clone());
+        self.message_queue.push(Msg {
+            sender: self.idx,
+            receiver: None,
+            body: Message::Cancel(cancellation),
+        });
+        Ok(())
+    }
+
+    /// Returns true if the identity rotation was cancelled by either the owner of the rotating identity or a quorum of parties.
+    fn is_cancelled(&self) -> bool {
+        self.request_option.as_ref().is_some_and(|request| {
+            wamu_core::identity_rotation::verify_cancellation(
+                &self.cancellations,
+                request,
+                self.verified_parties,
+                self.cancellation_quorum_size,
+            )
+            .is_ok()
+        })
+    }
+
+    /// Emits an identity rotation certificate that extends the roster log (see [`certificate`](Self::certificate)).
+    ///
+    /// **NOTE:** All parties must use the same roster log,
+    /// all other parties then broadcast their counter-signatures (instead of only sending them to the rotating party).
+    pub fn with_roster_log(mut self, roster_log: &'a RosterLog) -> Self {
+        self.roster_log_option = Some(roster_log);
+        if let Some(new_identity_provider) = self.new_identity_provider_option {
+            self.rotation_option = Some((
+                self.identity_provider.verifying_key(),
+                new_identity_provider.verifying_key(),
+            ));
+        }
+        self
+    }
+
+    /// Returns the identity rotation certificate counter-signed by all other parties
+    /// (only `Some` after the protocol is finished if a roster log was provided).
+    pub fn certificate(&self) -> Option<RotationCertificate> {
+        let roster_log = self.roster_log_option?;
+        let (old_verifying_key, new_verifying_key) = self.rotation_option.as_ref()?;
+        matches!(self.round, Round::Final | Round::Gone).then(|| {
+            // Sorts counter-signatures so that all parties emit the same certificate regardless of order of receiving them.
+            let mut countersignatures = self.countersignatures.clone();
+            countersignatures.sort_by(|(a, _), (b, _)| a.key.cmp(&b.key));
+            wamu_core::identity_rotation::certificate(
+                old_verifying_key,
+                new_verifying_key,
+                roster_log.epoch() + 1,
+                &roster_log.head_hash(),
+                countersignatures,
+            )
+        })
+    }
+
+    /// Returns a counter-signature of the identity rotation (only `Some` if a roster log was provided).
+    fn countersign(&self) -> Option<(VerifyingKey, Signature)> {
+        let roster_log = self.roster_log_option?;
+        let (old_verifying_key, new_verifying_key) = self.rotation_option.as_ref()?;
+        Some(wamu_core::identity_rotation::countersign(
+            old_verifying_key,
+            new_verifying_key,
+            roster_log.epoch() + 1,
+            &roster_log.head_hash(),
+            self.identity_provider,
+        ))
+    }
 }
 
 impl<'a, I: IdentityProvider> StateMachine for IdentityRotation<'a, I> {
@@ -104,7 +215,17 @@ impl<'a, I: IdentityProvider> StateMachine for IdentityRotation<'a, I> {
                         wamu_core::identity_rotation::verify_request_and_initiate_challenge(
                             &request,
                             self.verified_parties,
-                        )?;
+                        )
+                        .map_err(|error| {
+                            Error::Blame(
+                                wamu_core::Blame::new(error)
+                                    .with_verifying_key(request.verifying_key.clone())
+                                    .with_idx(msg.sender)
+                                    .with_round(1),
+                            )
+                        })?;
+                    // Saves the request payload.
+                    self.request_option = Some(request);
 
                     // Moves on to the next round.
                     self.round = Round::Two;
@@ -130,6 +251,7 @@ impl<'a, I: IdentityProvider> StateMachine for IdentityRotation<'a, I> {
                 // while other parties verify the challenge response
                 // and immediately process the next round if the challenge response verification is successful.
                 if self.new_identity_provider_option.is_none() {
+                    let verifying_key = &self.verified_parties[msg.sender as usize - 1];
                     wamu_core::identity_rotation::verify_challenge_response(
                         &response,
                         &self
@@ -137,26 +259,57 @@ impl<'a, I: IdentityProvider> StateMachine for IdentityRotation<'a, I> {
                             .values()
                             .copied()
                             .collect::<Vec<Random32Bytes>>(),
-                        &self.verified_parties[msg.sender as usize - 1],
-                    )?;
+                        verifying_key,
+                    )
+                    .map_err(|error| {
+                        Error::Blame(
+                            wamu_core::Blame::new(error.into())
+                                .with_verifying_key(verifying_key.clone())
+                                .with_idx(msg.sender)
+                                .with_round(3),
+                        )
+                    })?;
 
                     // Moves on the next round.
                     self.round = Round::Four;
+                    // Records the verified rotation.
+                    self.rotation_option = Some((
+                        self.verified_parties[msg.sender as usize - 1].clone(),
+                        response.new_verifying_key.clone(),
+                    ));
                     // Replaces the sender verifying key.
                     let mut output_verified_parties = self.verified_parties.to_vec();
                     output_verified_parties[msg.sender as usize - 1] = response.new_verifying_key;
                     self.output_verified_parties_option = Some(output_verified_parties);
-                    // Adds confirmation of successful rotation to the message for Round 4.
+                    // Adds confirmation of successful rotation (and a counter-signature if a roster log was provided)
+                    // to the message for Round 4.
+                    let countersignature = self.countersign();
+                    if let Some(countersignature) = countersignature.clone() {
+                        self.countersignatures.push(countersignature);
+                    }
                     self.message_queue.push(Msg {
                         sender: self.idx,
-                        receiver: Some(msg.sender),
-                        body: Message::Round4(Some(true)),
+                        // Counter-signatures are broadcast so that all parties can emit the certificate.
+                        receiver: self.roster_log_option.is_none().then_some(msg.sender),
+                        body: Message::Round4(Some(true), countersignature),
                     });
                 }
             }
-            // Rotating party stores the received identity rotation confirmations.
-            Message::Round4(outcome) => {
+            // Rotating party (and all other parties if a roster log was provided) stores the received identity rotation confirmations.
+            Message::Round4(outcome, countersignature) => {
                 self.received_outcomes.insert(msg.sender, outcome);
+                if let Some(countersignature) = countersignature {
+                    self.countersignatures.push(countersignature);
+                }
+            }
+            // All parties store cancellations received before the rotation is completed.
+            Message::Cancel(cancellation) => {
+                if !matches!(self.round, Round::Final | Round::Gone) {
+                    self.cancellations.push(cancellation);
+                    if self.is_cancelled() {
+                        return Err(Error::Cancelled);
+                    }
+                }
             }
         }
         Ok(())
@@ -167,6 +320,10 @@ impl<'a, I: IdentityProvider> StateMachine for IdentityRotation<'a, I> {
     }
 
     fn wants_to_proceed(&self) -> bool {
+        // Cancelled rotations proceed immediately to report the cancellation.
+        if self.is_cancelled() {
+            return true;
+        }
         match &self.round {
             // Rotating party is immediately ready to proceed from Round 1 after initialization,
             // while other parties need to prepare their challenge fragments first before they can proceed.
@@ -198,10 +355,13 @@ impl<'a, I: IdentityProvider> StateMachine for IdentityRotation<'a, I> {
                 }
             }
             // Rotating party needs to receive outcomes from all other parties (i.e n_parties - 1),
-            // while other parties don't need to do anything for this round.
+            // while other parties either don't need to do anything for this round or
+            // need to receive outcomes from all other parties except the rotating party and themselves (i.e n_parties - 2) if a roster log was provided.
             Round::Four => {
                 if self.new_identity_provider_option.is_some() {
                     self.received_outcomes.len() == self.n_parties as usize - 1
+                } else if self.roster_log_option.is_some() {
+                    self.received_outcomes.len() == self.n_parties as usize - 2
                 } else {
                     true
                 }
@@ -212,6 +372,10 @@ impl<'a, I: IdentityProvider> StateMachine for IdentityRotation<'a, I> {
     }
 
     fn proceed(&mut self) -> Result<(), Self::Err> {
+        // Cancelled rotations can't proceed.
+        if self.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
         match self.round {
             // Round 1 is already handled by `handle_incoming` or during initialization for the rotating party.
             Round::One => {
@@ -345,13 +509,18 @@ pub enum Message {
     Round1(IdentityAuthedRequestPayload),
     Round2(Random32Bytes),
     Round3(IdentityRotationChallengeResponsePayload),
-    Round4(Option<bool>),
+    Round4(Option<bool>, Option<(VerifyingKey, Signature)>),
+    Cancel(CommandCancellationPayload),
 }
 
 #[derive(Debug)]
 pub enum Error {
     Core(IdentityAuthedRequestError),
+    Blame(wamu_core::Blame<IdentityAuthedRequestError>),
+    AlreadyCompleted,
     AlreadyPicked,
+    Cancelled,
+    InvalidState,
 }
 
 impl From<IdentityAuthedRequestError> for Error {
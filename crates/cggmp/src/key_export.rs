@@ -0,0 +1,168 @@
+//! Quorum approved export of augmented wallet shares to plain CGGMP/GG20 local keys (i.e "de-augmentation").
+//!
+//! Allows shares to be moved to another CGGMP implementation (e.g for vendor-exit scenarios).
+//!
+//! **WARNING:** Exported local keys contain the reconstructed secret share (i.e x_i),
+//! so they lose all the protections of Wamu's share splitting and should only be held for as long as it takes to import them elsewhere.
+
+use curv::elliptic::curves::{ECScalar, Scalar, Secp256k1};
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::keygen::LocalKey;
+use wamu_core::quorum_approved_request::QuorumTracker;
+use wamu_core::IdentityProvider;
+use zeroize::Zeroize;
+
+use crate::keygen::WalletShare;
+
+/// The command for quorum approved key export requests.
+pub const KEY_EXPORT: &str = "key-export";
+
+/// A key export error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyExportError {
+    /// The quorum approval is for a different command.
+    CommandMismatch,
+    /// Not enough valid command approvals have been collected to form a quorum.
+    MissingQuorumApproval,
+    /// A wrapped error from `wamu-core`.
+    Core(wamu_core::Error),
+}
+
+impl From<wamu_core::Error> for KeyExportError {
+    fn from(error: wamu_core::Error) -> Self {
+        Self::Core(error)
+    }
+}
+
+/// A plain CGGMP/GG20 local key with the reconstructed secret share (i.e x_i)
+/// that's zerorized when it's dropped.
+pub struct ExportedLocalKey(LocalKey<Secp256k1>);
+
+impl ExportedLocalKey {
+    /// Returns the local key with the reconstructed secret share.
+    ///
+    /// **WARNING:** Avoid cloning the local key, clones are not zerorized when this wrapper is dropped.
+    pub fn local_key(&self) -> &LocalKey<Secp256k1> {
+        &self.0
+    }
+}
+
+impl Drop for ExportedLocalKey {
+    fn drop(&mut self) {
+        // Zerorizes the secret share.
+        let x_i = std::mem::replace(&mut self.0.keys_linear.x_i, Scalar::<Secp256k1>::zero());
+        if let Some(raw_x_i) = x_i.into_raw().underlying_mut() {
+            raw_x_i.zeroize();
+        }
+    }
+}
+
+// Redacts the secret share.
+impl std::fmt::Debug for ExportedLocalKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ExportedLocalKey(party {}, <redacted>)", self.0.i)
+    }
+}
+
+/// Given a wallet share, the identity provider of the party and a quorum tracker for a key export request,
+/// returns the plain CGGMP/GG20 local key with the reconstructed secret share (i.e x_i)
+/// if a quorum has approved the key export, or an appropriate error otherwise.
+///
+/// **NOTE:** Key export requests are initiated with the [`KEY_EXPORT`] command
+/// (e.g via [`wamu_core::quorum_approved_request::initiate`]).
+pub fn export_local_key(
+    wallet_share: &WalletShare,
+    identity_provider: &impl IdentityProvider,
+    quorum_tracker: &QuorumTracker,
+) -> Result<ExportedLocalKey, KeyExportError> {
+    if quorum_tracker.request().command != KEY_EXPORT {
+        // Quorum approval must be for key export.
+        return Err(KeyExportError::CommandMismatch);
+    }
+    if !quorum_tracker.is_complete() {
+        // A quorum must have approved the key export.
+        return Err(KeyExportError::MissingQuorumApproval);
+    }
+
+    // Reconstructs secret share.
+    let secret_share = wamu_core::share_split_reconstruct::reconstruct(
+        &wallet_share.signing_share,
+        &wallet_share.sub_share,
+        identity_provider,
+    )?;
+    // Sets the reconstructed secret share.
+    let mut local_key = wallet_share.local_key.clone();
+    local_key.keys_linear.x_i = Scalar::<Secp256k1>::from_bytes(&secret_share.to_be_bytes())
+        .map_err(|_| wamu_core::Error::Encoding)?;
+
+    Ok(ExportedLocalKey(local_key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen::tests::simulate_keygen;
+    use curv::elliptic::curves::Point;
+    use wamu_core::crypto::VerifyingKey;
+    use wamu_core::quorum_approved_request;
+
+    #[test]
+    fn key_export_works() {
+        // Runs key gen simulation for test parameters.
+        let (threshold, n_parties) = (1, 3);
+        let (keys, identity_providers) = simulate_keygen(threshold, n_parties);
+        let wallet_shares: Vec<WalletShare> = keys
+            .into_iter()
+            .map(|key| WalletShare::try_from(key).unwrap())
+            .collect();
+        let verified_parties: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+
+        // Initiates key export request.
+        let request = quorum_approved_request::initiate(KEY_EXPORT, &identity_providers[0]);
+        let mut quorum_tracker =
+            QuorumTracker::new(request.clone(), threshold as usize + 1, &verified_parties);
+
+        // Key export is rejected without quorum approval.
+        assert_eq!(
+            export_local_key(&wallet_shares[0], &identity_providers[0], &quorum_tracker).err(),
+            Some(KeyExportError::MissingQuorumApproval)
+        );
+
+        // Key export is rejected for quorum approvals of other commands.
+        let other_quorum_tracker = QuorumTracker::new(
+            quorum_approved_request::initiate("other", &identity_providers[0]),
+            1,
+            &verified_parties,
+        );
+        assert_eq!(
+            export_local_key(
+                &wallet_shares[0],
+                &identity_providers[0],
+                &other_quorum_tracker
+            )
+            .err(),
+            Some(KeyExportError::CommandMismatch)
+        );
+
+        // Collects approval from another party and exports the local key.
+        let approval = quorum_approved_request::verify_request_and_initiate_challenge(
+            KEY_EXPORT,
+            &request,
+            &identity_providers[1],
+            &verified_parties,
+        )
+        .unwrap();
+        assert_eq!(quorum_tracker.add_approval(approval), Ok(true));
+        let exported =
+            export_local_key(&wallet_shares[0], &identity_providers[0], &quorum_tracker).unwrap();
+
+        // Verifies that the exported secret share matches the public share of the party.
+        let local_key = exported.local_key();
+        assert_eq!(
+            Point::<Secp256k1>::generator() * &local_key.keys_linear.x_i,
+            local_key.pk_vec[local_key.i as usize - 1]
+        );
+    }
+}
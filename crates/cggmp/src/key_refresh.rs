@@ -1,6 +1,10 @@
 //! Augmented key refresh implementation.
 //!
 //! Ref: <https://wamu.tech/specification#key-refresh>.
+//!
+//! **NOTE:** FS-DKR generates fresh auxiliary parameters (i.e a Paillier key and ring-Pedersen parameters, with proofs of correctness)
+//! for every party in every key refresh, and they replace the previous ones in the refreshed local key.
+//! Rotation can additionally be enforced (see [`AugmentedKeyRefresh::with_auxiliary_rotation_check`]).
 
 use cggmp_threshold_ecdsa::refresh::state_machine::{KeyRefresh, M};
 use cggmp_threshold_ecdsa::utilities::sha2::Sha256;
@@ -19,8 +23,10 @@ use wamu_core::{IdentityProvider, SigningShare, SubShare};
 use crate::augmented_state_machine;
 use crate::augmented_state_machine::Error;
 use crate::augmented_state_machine::{
-    AugmentedStateMachine, AugmentedType, IdentityAuthParams, SubShareOutput,
+    AugmentedStateMachine, AugmentedType, IdentityAuthParams, ProgressTracker, SubShareOutput,
+    TimeoutConfig,
 };
+use crate::event_sink::EventSink;
 
 /// A wrapper around the [`cggmp-threshold-ecdsa` Key Refresh StateMachine](https://github.com/webb-tools/cggmp-threshold-ecdsa/blob/main/src/refresh/state_machine.rs) that [augments key refresh as described by the Wamu protocol](https://wamu.tech/specification#key-refresh).
 pub struct AugmentedKeyRefresh<'a, I: IdentityProvider> {
@@ -35,6 +41,16 @@ pub struct AugmentedKeyRefresh<'a, I: IdentityProvider> {
     verified_parties: &'a [VerifyingKey],
     /// Indexes of existing parties.
     existing_parties: Vec<u16>,
+    /// Tracks the progress of the protocol.
+    progress_tracker: ProgressTracker,
+    /// Timeout configuration (if any).
+    timeout_config: Option<TimeoutConfig>,
+    /// Event sink (if any).
+    event_sink: Option<&'a dyn EventSink>,
+    /// Paillier and ring-Pedersen moduli of all parties from the previous local key (if any).
+    previous_auxiliary_moduli: Vec<Vec<u8>>,
+    /// Whether the rotation of auxiliary parameters is enforced.
+    check_auxiliary_rotation: bool,
 }
 
 impl<'a, I: IdentityProvider> AugmentedKeyRefresh<'a, I> {
@@ -79,20 +95,35 @@ impl<'a, I: IdentityProvider> AugmentedKeyRefresh<'a, I> {
                     .map_err(|_| Error::Core(wamu_core::Error::Encoding))?;
         }
 
+        // Records auxiliary parameters from the previous local key (if provided).
+        let previous_auxiliary_moduli = local_key_option
+            .as_ref()
+            .map(auxiliary_moduli)
+            .unwrap_or_default();
+
         // Initializes state machine.
+        let state_machine = KeyRefresh::new(
+            local_key_option,
+            new_party_index_option,
+            old_to_new_map,
+            new_threshold,
+            n_parties,
+            current_threshold_option,
+        )?;
+        let idx = state_machine.party_ind();
         let mut aug_key_refresh = Self {
-            state_machine: KeyRefresh::new(
-                local_key_option,
-                new_party_index_option,
-                old_to_new_map,
-                new_threshold,
-                n_parties,
-                current_threshold_option,
-            )?,
+            state_machine,
             message_queue: Vec::new(),
             identity_provider,
             verified_parties,
             existing_parties: old_to_new_map.values().copied().collect::<Vec<u16>>(),
+            timeout_config: None,
+            event_sink: None,
+            progress_tracker: ProgressTracker::new(
+                (1..=n_parties).filter(|it| *it != idx).collect(),
+            ),
+            previous_auxiliary_moduli,
+            check_auxiliary_rotation: false,
         };
 
         // Retrieves messages from immediate state transitions (if any) and augments them.
@@ -102,6 +133,27 @@ impl<'a, I: IdentityProvider> AugmentedKeyRefresh<'a, I> {
         Ok(aug_key_refresh)
     }
 
+    /// Sets the timeout configuration (i.e per-round timeout, session deadline and behavior on timeout).
+    pub fn with_timeout_config(mut self, timeout_config: TimeoutConfig) -> Self {
+        self.timeout_config = Some(timeout_config);
+        self
+    }
+
+    /// Sets the event sink (i.e for publishing round, message, blame and completion events).
+    pub fn with_event_sink(mut self, event_sink: &'a dyn EventSink) -> Self {
+        self.event_sink = Some(event_sink);
+        self
+    }
+
+    /// Enforces the rotation of auxiliary parameters (i.e Paillier and ring-Pedersen parameters),
+    /// so that key refresh fails if any party's refreshed parameters reuse a modulus from the previous local key.
+    ///
+    /// **NOTE:** Only enforced by existing parties (i.e parties that provide their previous local key).
+    pub fn with_auxiliary_rotation_check(mut self) -> Self {
+        self.check_auxiliary_rotation = true;
+        self
+    }
+
     // For `cggmp-threshold-ecdsa`, key refresh is based on FS-DKR,
     // which is a modified version of FS-DKG (Fouque-Stern Distributed Key Generation).
     // So we hash parameters from Round 1 (for new parties) or Round 2 (for existing parties)
@@ -135,6 +187,56 @@ impl<'a, I: IdentityProvider> AugmentedKeyRefresh<'a, I> {
             .deref()
             .to_vec()
     }
+
+    /// Returns the round of a key refresh message.
+    pub(crate) fn message_round(
+        msg_body: &AugmentedType<<KeyRefresh as StateMachine>::MessageBody, IdentityAuthParams>,
+    ) -> u16 {
+        match &msg_body.base.0 {
+            M::Round1(_) => 1,
+            M::Round2(_) => 2,
+        }
+    }
+
+    /// Returns true if the additional parameters of a key refresh message (if any)
+    /// are signed by one of the verified parties, or false otherwise.
+    ///
+    /// **NOTE:** Used to screen key refresh messages that are received before the key refresh state machine is active
+    /// (i.e messages without additional parameters are fully verified once they're handled by the key refresh state machine).
+    pub(crate) fn is_signed_by_verified_party(
+        msg: &Msg<AugmentedType<<KeyRefresh as StateMachine>::MessageBody, IdentityAuthParams>>,
+        verified_parties: &[VerifyingKey],
+    ) -> bool {
+        let initiation_msg = match &msg.body.base.0 {
+            M::Round1(out_msg_option) => out_msg_option.as_ref().map(InitiationMessage::Join),
+            M::Round2(out_msg_option) => out_msg_option.as_ref().map(InitiationMessage::Refresh),
+        };
+        match initiation_msg.zip(msg.body.extra.as_ref()) {
+            Some((initiation_msg, params)) => wamu_core::wrappers::verify_request_with_signature(
+                &Self::parameter_hash(msg.sender, initiation_msg),
+                &params.verifying_key,
+                &params.verifying_signature,
+                verified_parties,
+            )
+            .is_ok(),
+            None => true,
+        }
+    }
+}
+
+// Returns the Paillier and ring-Pedersen moduli of all parties from a local key.
+fn auxiliary_moduli(local_key: &LocalKey<Secp256k1>) -> Vec<Vec<u8>> {
+    local_key
+        .paillier_key_vec
+        .iter()
+        .map(|ek| ek.n.to_bytes())
+        .chain(
+            local_key
+                .h1_h2_n_tilde_vec
+                .iter()
+                .map(|statement| statement.N.to_bytes()),
+        )
+        .collect()
 }
 
 enum InitiationMessage<'a> {
@@ -150,6 +252,22 @@ impl<'a, I: IdentityProvider> AugmentedStateMachine for AugmentedKeyRefresh<'a,
     // Implements all required `AugmentedStateMachine` methods.
     impl_required_augmented_state_machine_methods!(state_machine, message_queue);
 
+    fn progress_tracker(&self) -> Option<&ProgressTracker> {
+        Some(&self.progress_tracker)
+    }
+
+    fn progress_tracker_mut(&mut self) -> Option<&mut ProgressTracker> {
+        Some(&mut self.progress_tracker)
+    }
+
+    fn timeout_config(&self) -> Option<&TimeoutConfig> {
+        self.timeout_config.as_ref()
+    }
+
+    fn event_sink(&self) -> Option<&dyn EventSink> {
+        self.event_sink
+    }
+
     fn pre_handle_incoming(
         &mut self,
         msg: &Msg<
@@ -166,16 +284,18 @@ impl<'a, I: IdentityProvider> AugmentedStateMachine for AugmentedKeyRefresh<'a,
                     match out_msg_option.as_ref().zip(msg.body.extra.as_ref()) {
                         // Verifies that signer is an expected party/signatory and the signature is valid.
                         Some((out_msg, params)) => {
-                            Ok(wamu_core::wrappers::verify_request_with_signature(
+                            wamu_core::wrappers::verify_request_with_signature(
                                 &Self::parameter_hash(msg.sender, InitiationMessage::Join(out_msg)),
                                 &params.verifying_key,
                                 &params.verifying_signature,
                                 self.verified_parties,
-                            )?)
+                            )
+                            .map_err(|error| Error::blame(error, msg.sender, params, 1))
                         }
                         // Returns an error if expected additional parameters are missing for new parties.
                         None => Err(Error::MissingParams {
                             bad_actors: vec![msg.sender as usize],
+                            round: 1,
                         }),
                     }
                 } else {
@@ -189,7 +309,7 @@ impl<'a, I: IdentityProvider> AugmentedStateMachine for AugmentedKeyRefresh<'a,
                     match out_msg_option.as_ref().zip(msg.body.extra.as_ref()) {
                         // Verifies that signer is an expected party/signatory and the signature is valid.
                         Some((out_msg, params)) => {
-                            Ok(wamu_core::wrappers::verify_request_with_signature(
+                            wamu_core::wrappers::verify_request_with_signature(
                                 &Self::parameter_hash(
                                     msg.sender,
                                     InitiationMessage::Refresh(out_msg),
@@ -197,11 +317,13 @@ impl<'a, I: IdentityProvider> AugmentedStateMachine for AugmentedKeyRefresh<'a,
                                 &params.verifying_key,
                                 &params.verifying_signature,
                                 self.verified_parties,
-                            )?)
+                            )
+                            .map_err(|error| Error::blame(error, msg.sender, params, 2))
                         }
                         // Returns an error if expected additional parameters are missing for existing parties.
                         None => Err(Error::MissingParams {
                             bad_actors: vec![msg.sender as usize],
+                            round: 2,
                         }),
                     }
                 } else {
@@ -267,6 +389,26 @@ impl<'a, I: IdentityProvider> AugmentedStateMachine for AugmentedKeyRefresh<'a,
         AugmentedType<<Self::StateMachineType as StateMachine>::Output, Self::AdditionalOutput>,
         Error<<Self::StateMachineType as StateMachine>::Err>,
     > {
+        // Verifies that all parties rotated their auxiliary parameters (if required).
+        if self.check_auxiliary_rotation && !self.previous_auxiliary_moduli.is_empty() {
+            let bad_actors: Vec<usize> = output
+                .paillier_key_vec
+                .iter()
+                .zip(output.h1_h2_n_tilde_vec.iter())
+                .enumerate()
+                .filter(|(_, (ek, statement))| {
+                    self.previous_auxiliary_moduli.contains(&ek.n.to_bytes())
+                        || self
+                            .previous_auxiliary_moduli
+                            .contains(&statement.N.to_bytes())
+                })
+                .map(|(i, _)| i + 1)
+                .collect();
+            if !bad_actors.is_empty() {
+                return Err(Error::StaleAuxiliaryParams { bad_actors });
+            }
+        }
+
         Ok(augmented_state_machine::split_key_output(
             self.identity_provider,
             output,
@@ -345,7 +487,8 @@ pub mod tests {
                     n_parties,
                     current_threshold_option,
                 )
-                .unwrap(),
+                .unwrap()
+                .with_auxiliary_rotation_check(),
             );
         }
 
@@ -438,6 +581,16 @@ pub mod tests {
             assert_eq!(key.base.keys_linear.x_i, Scalar::<Secp256k1>::zero());
             // Verifies that the public key hasn't changed.
             assert_eq!(key.base.public_key(), pub_key_init);
+            // Verifies that auxiliary parameters (i.e Paillier keys) were rotated.
+            for key_init in keys_init.iter() {
+                for ek in key.base.paillier_key_vec.iter() {
+                    assert!(!key_init
+                        .base
+                        .paillier_key_vec
+                        .iter()
+                        .any(|ek_init| ek_init.n == ek.n));
+                }
+            }
         }
 
         (
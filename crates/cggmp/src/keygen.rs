@@ -3,19 +3,176 @@
 //! Ref: <https://wamu.tech/specification#key-generation>.
 
 use curv::arithmetic::Converter;
+use curv::elliptic::curves::{Point, Secp256k1};
 use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::party_i::KeyGenBroadcastMessage1;
-use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::keygen::{Keygen, M};
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::keygen::{
+    Keygen, LocalKey, M,
+};
 use round_based::{Msg, StateMachine};
 use std::ops::Deref;
 use std::time::Duration;
 use wamu_core::crypto::VerifyingKey;
-use wamu_core::IdentityProvider;
+use wamu_core::key_store::StoredKey;
+use wamu_core::{IdentityProvider, SigningShare, SubShare};
 
 use crate::augmented_state_machine;
 use crate::augmented_state_machine::Error;
 use crate::augmented_state_machine::{
-    AugmentedStateMachine, AugmentedType, IdentityAuthParams, SubShareOutput,
+    AugmentedStateMachine, AugmentedType, IdentityAuthParams, ProgressTracker, SubShareOutput,
+    TimeoutConfig,
 };
+use crate::event_sink::EventSink;
+use crate::message_auth::{MessageAuthParams, MessageAuthentication};
+use crate::transport::{run_protocol, ProtocolError, Transport};
+
+/// Key generation configuration.
+///
+/// **NOTE:** Quorum size = threshold + 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeygenConfig {
+    /// The threshold.
+    pub threshold: u16,
+    /// The total number of parties.
+    pub n_parties: u16,
+    /// Index of the party (i.e `1..=n_parties`).
+    pub party_index: u16,
+}
+
+impl KeygenConfig {
+    /// Given the identity provider of the party and verifying keys for all parties (in party index order),
+    /// returns an error if the configuration is invalid for the party.
+    pub fn validate<T: round_based::IsCritical>(
+        &self,
+        identity_provider: &impl IdentityProvider,
+        parties: &[VerifyingKey],
+    ) -> Result<(), Error<T>> {
+        if self.threshold < 1
+            || self.threshold >= self.n_parties
+            || !(1..=self.n_parties).contains(&self.party_index)
+            || parties.len() != self.n_parties as usize
+        {
+            Err(Error::InvalidConfig)
+        } else if parties[self.party_index as usize - 1] != identity_provider.verifying_key() {
+            // The party's verifying key must be at its index.
+            Err(Error::Core(wamu_core::Error::UnauthorizedParty))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// A self-contained wallet share (i.e the local key with the secret share cleared/zerorized, along with its "signing share" and "sub-share").
+pub struct WalletShare {
+    /// Local key with the secret share cleared/zerorized.
+    pub local_key: LocalKey<Secp256k1>,
+    /// The "signing share".
+    pub signing_share: SigningShare,
+    /// The "sub-share".
+    pub sub_share: SubShare,
+}
+
+impl WalletShare {
+    /// Returns the group public key.
+    pub fn public_key(&self) -> Point<Secp256k1> {
+        self.local_key.public_key()
+    }
+
+    /// Returns the index of the party.
+    pub fn party_index(&self) -> u16 {
+        self.local_key.i
+    }
+
+    /// Given the verifying keys of all parties (i.e the current roster) and the roster epoch,
+    /// returns the key material of the wallet share for a key store (see [`wamu_core::key_store`]).
+    pub fn to_stored_key(&self, roster: Vec<VerifyingKey>, epoch: u64) -> StoredKey {
+        StoredKey {
+            signing_share: self.signing_share.clone(),
+            sub_share: self.sub_share.clone(),
+            local_key: serde_json::to_vec(&self.local_key)
+                .expect("Local keys are always serializable"),
+            roster,
+            epoch,
+        }
+    }
+
+    /// Given key material from a key store, returns the wallet share
+    /// or an encoding error if the stored local key isn't a valid encoding of a local key.
+    pub fn from_stored_key(key: &StoredKey) -> Result<Self, wamu_core::Error> {
+        Ok(Self {
+            local_key: serde_json::from_slice(&key.local_key)
+                .map_err(|_| wamu_core::Error::Encoding)?,
+            signing_share: key.signing_share.clone(),
+            sub_share: key.sub_share.clone(),
+        })
+    }
+}
+
+impl TryFrom<AugmentedType<LocalKey<Secp256k1>, SubShareOutput>> for WalletShare {
+    type Error = wamu_core::Error;
+
+    fn try_from(
+        output: AugmentedType<LocalKey<Secp256k1>, SubShareOutput>,
+    ) -> Result<Self, Self::Error> {
+        let (signing_share, sub_share) = output.extra.ok_or(wamu_core::Error::Encoding)?;
+        Ok(Self {
+            local_key: output.base,
+            signing_share,
+            sub_share,
+        })
+    }
+}
+
+/// Given a key generation configuration, the identity provider of the party,
+/// verifying keys for all parties (in party index order), a session identifier shared by all parties and a transport,
+/// runs augmented key generation with per-round message authentication (see [`MessageAuthentication`])
+/// and returns the wallet share of the party.
+pub fn generate_wallet_share<'a, I, T>(
+    config: KeygenConfig,
+    identity_provider: &'a I,
+    parties: &'a [VerifyingKey],
+    session_id: [u8; 32],
+    transport: &mut T,
+) -> Result<
+    WalletShare,
+    ProtocolError<Error<<Keygen as StateMachine>::Err>, <T as Transport<KeygenMessage>>::Error>,
+>
+where
+    I: IdentityProvider,
+    T: Transport<KeygenMessage>,
+{
+    let aug_key_gen = AugmentedKeyGen::from_config(identity_provider, parties, config)
+        .map_err(ProtocolError::StateMachine)?;
+    let mut message_auth = MessageAuthentication::new(
+        aug_key_gen,
+        identity_provider,
+        parties,
+        session_id,
+        encode_keygen_body,
+    );
+    let output = run_protocol(&mut message_auth, transport).map_err(|error| match error {
+        ProtocolError::StateMachine(error) => ProtocolError::StateMachine(error.flatten()),
+        ProtocolError::Transport(error) => ProtocolError::Transport(error),
+    })?;
+    WalletShare::try_from(output.base)
+        .map_err(|error| ProtocolError::StateMachine(Error::Core(error)))
+}
+
+/// An augmented key generation protocol message (with per-round message authentication).
+pub type KeygenMessage = Msg<
+    AugmentedType<
+        AugmentedType<<Keygen as StateMachine>::MessageBody, IdentityAuthParams>,
+        MessageAuthParams,
+    >,
+>;
+
+// Encodes augmented key generation message bodies (deterministically) for per-round message authentication.
+//
+// NOTE: Identity authentication parameters are verified by the augmented state machine itself.
+fn encode_keygen_body(
+    body: &AugmentedType<<Keygen as StateMachine>::MessageBody, IdentityAuthParams>,
+) -> Vec<u8> {
+    serde_json::to_vec(&body.base).expect("Key generation messages are always serializable")
+}
 
 /// A wrapper around the [`cggmp-threshold-ecdsa` Key Generation StateMachine](https://github.com/ZenGo-X/multi-party-ecdsa/blob/master/src/protocols/multi_party_ecdsa/gg_2020/state_machine/keygen.rs) that [augments key generation as described by the Wamu protocol](https://wamu.tech/specification#key-generation).
 pub struct AugmentedKeyGen<'a, I: IdentityProvider> {
@@ -28,6 +185,12 @@ pub struct AugmentedKeyGen<'a, I: IdentityProvider> {
     identity_provider: &'a I,
     /// Verifying keys for other the parties.
     parties: &'a [VerifyingKey],
+    /// Tracks the progress of the protocol.
+    progress_tracker: ProgressTracker,
+    /// Timeout configuration (if any).
+    timeout_config: Option<TimeoutConfig>,
+    /// Event sink (if any).
+    event_sink: Option<&'a dyn EventSink>,
 }
 
 impl<'a, I: IdentityProvider> AugmentedKeyGen<'a, I> {
@@ -45,6 +208,11 @@ impl<'a, I: IdentityProvider> AugmentedKeyGen<'a, I> {
             message_queue: Vec::new(),
             identity_provider,
             parties,
+            timeout_config: None,
+            event_sink: None,
+            progress_tracker: ProgressTracker::new(
+                (1..=n_parties).filter(|it| *it != idx).collect(),
+            ),
         };
 
         // Retrieves messages from immediate state transitions (if any) and augments them.
@@ -54,6 +222,37 @@ impl<'a, I: IdentityProvider> AugmentedKeyGen<'a, I> {
         Ok(aug_key_gen)
     }
 
+    /// Sets the timeout configuration (i.e per-round timeout, session deadline and behavior on timeout).
+    pub fn with_timeout_config(mut self, timeout_config: TimeoutConfig) -> Self {
+        self.timeout_config = Some(timeout_config);
+        self
+    }
+
+    /// Sets the event sink (i.e for publishing round, message, blame and completion events).
+    pub fn with_event_sink(mut self, event_sink: &'a dyn EventSink) -> Self {
+        self.event_sink = Some(event_sink);
+        self
+    }
+
+    /// Initializes party for the augmented key generation protocol from a validated key generation configuration.
+    pub fn from_config(
+        identity_provider: &'a I,
+        parties: &'a [VerifyingKey],
+        config: KeygenConfig,
+    ) -> Result<Self, Error<<Keygen as StateMachine>::Err>> {
+        // Validates configuration.
+        config.validate(identity_provider, parties)?;
+
+        // Initializes party.
+        Self::new(
+            identity_provider,
+            parties,
+            config.party_index,
+            config.threshold,
+            config.n_parties,
+        )
+    }
+
     // For `cggmp-threshold-ecdsa`, key generation uses the GG20 implementation from ZenGo's `multi-party-ecdsa`.
     // So we hash parameters from Round 1 to achieve a similar commitment to V_i in CGGMP20.
     // Ref: <https://github.com/ZenGo-X/multi-party-ecdsa/>.
@@ -79,6 +278,22 @@ impl<'a, I: IdentityProvider> AugmentedStateMachine for AugmentedKeyGen<'a, I> {
     // Implements all required `AugmentedStateMachine` methods.
     impl_required_augmented_state_machine_methods!(state_machine, message_queue);
 
+    fn progress_tracker(&self) -> Option<&ProgressTracker> {
+        Some(&self.progress_tracker)
+    }
+
+    fn progress_tracker_mut(&mut self) -> Option<&mut ProgressTracker> {
+        Some(&mut self.progress_tracker)
+    }
+
+    fn timeout_config(&self) -> Option<&TimeoutConfig> {
+        self.timeout_config.as_ref()
+    }
+
+    fn event_sink(&self) -> Option<&dyn EventSink> {
+        self.event_sink
+    }
+
     fn pre_handle_incoming(
         &mut self,
         msg: &Msg<
@@ -92,15 +307,17 @@ impl<'a, I: IdentityProvider> AugmentedStateMachine for AugmentedKeyGen<'a, I> {
             // Verifies the expected additional parameters from Round 1.
             M::Round1(out_msg) => match msg.body.extra.as_ref() {
                 // Verifies that signer is an expected party/signatory and the signature is valid.
-                Some(params) => Ok(wamu_core::wrappers::verify_request_with_signature(
+                Some(params) => wamu_core::wrappers::verify_request_with_signature(
                     &Self::parameter_hash(msg.sender, out_msg),
                     &params.verifying_key,
                     &params.verifying_signature,
                     self.parties,
-                )?),
+                )
+                .map_err(|error| Error::blame(error, msg.sender, params, 1)),
                 // Returns an error if expected additional parameters are missing.
                 None => Err(Error::MissingParams {
                     bad_actors: vec![msg.sender as usize],
+                    round: 1,
                 }),
             },
             // No modifications for other rounds.
@@ -165,9 +382,10 @@ impl<'a, I: IdentityProvider> std::fmt::Debug for AugmentedKeyGen<'a, I> {
 #[cfg(any(test, feature = "dev"))]
 pub mod tests {
     use super::*;
-    use curv::elliptic::curves::{Scalar, Secp256k1};
-    use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::keygen::LocalKey;
+    use crate::augmented_state_machine::TimeoutAction;
+    use curv::elliptic::curves::Scalar;
     use round_based::dev::Simulation;
+    use round_based::IsCritical;
     use wamu_core::test_utils::MockECDSAIdentityProvider;
 
     pub fn simulate_keygen(
@@ -238,4 +456,235 @@ pub mod tests {
             }
         }
     }
+
+    #[test]
+    fn keygen_progress_works() {
+        // Creates identity providers, a list of verifying keys and parties for test parameters.
+        let (threshold, n_parties) = (1, 3);
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (1..=n_parties)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let mut parties: Vec<AugmentedKeyGen<MockECDSAIdentityProvider>> = identity_providers
+            .iter()
+            .enumerate()
+            .map(|(i, identity_provider)| {
+                AugmentedKeyGen::new(
+                    identity_provider,
+                    &verifying_keys,
+                    i as u16 + 1,
+                    threshold,
+                    n_parties,
+                )
+                .unwrap()
+            })
+            .collect();
+
+        // Waits on all other parties at the start of round 1.
+        let progress = parties[0].progress().unwrap();
+        assert_eq!(progress.current_round, 1);
+        assert_eq!(progress.waiting_on, vec![2, 3]);
+
+        // Stops waiting on parties whose messages for the current round have been received.
+        let msg = parties[1].message_queue()[0].clone();
+        parties[0].handle_incoming(msg).unwrap();
+        assert_eq!(parties[0].progress().unwrap().waiting_on, vec![3]);
+    }
+
+    #[test]
+    fn keygen_timeout_config_works() {
+        // Creates identity providers and a list of verifying keys for test parameters.
+        let (threshold, n_parties) = (1, 3);
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (1..=n_parties)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let new_party = |timeout_config: TimeoutConfig| {
+            AugmentedKeyGen::new(
+                &identity_providers[0],
+                &verifying_keys,
+                1,
+                threshold,
+                n_parties,
+            )
+            .unwrap()
+            .with_timeout_config(timeout_config)
+        };
+
+        // Round timeouts are capped by the session deadline, and are non-critical if the protocol should proceed without missing messages.
+        let mut party = new_party(TimeoutConfig {
+            round_timeout: Some(Duration::from_secs(60)),
+            session_deadline: Some(Duration::from_secs(30)),
+            on_timeout: TimeoutAction::ProceedWithout,
+        });
+        assert!(party.round_timeout().unwrap() <= Duration::from_secs(30));
+        let error = party.round_timeout_reached();
+        assert!(!error.is_critical());
+        assert!(matches!(
+            error,
+            Error::RoundTimeout { round: 1, ref bad_actors, .. } if bad_actors == &vec![2, 3]
+        ));
+
+        // Round timeouts abort the protocol by default.
+        let mut party = new_party(TimeoutConfig {
+            round_timeout: Some(Duration::from_secs(60)),
+            ..TimeoutConfig::default()
+        });
+        assert_eq!(party.round_timeout(), Some(Duration::from_secs(60)));
+        assert!(party.round_timeout_reached().is_critical());
+
+        // Exceeded session deadlines always abort the protocol.
+        let mut party = new_party(TimeoutConfig {
+            session_deadline: Some(Duration::ZERO),
+            on_timeout: TimeoutAction::ProceedWithout,
+            ..TimeoutConfig::default()
+        });
+        assert_eq!(party.round_timeout(), Some(Duration::ZERO));
+        let error = party.round_timeout_reached();
+        assert!(error.is_critical());
+        assert!(matches!(error, Error::DeadlineExceeded { round: 1, .. }));
+    }
+
+    #[test]
+    fn keygen_cancellation_works() {
+        // Creates identity providers, a list of verifying keys and a party for test parameters.
+        let (threshold, n_parties) = (1, 3);
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (1..=n_parties)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let party = AugmentedKeyGen::new(
+            &identity_providers[0],
+            &verifying_keys,
+            1,
+            threshold,
+            n_parties,
+        )
+        .unwrap();
+
+        // Cancels the session and verifies the abort notice.
+        let session_id = [1; 32];
+        let notice = party.cancel(&identity_providers[0], session_id);
+        assert_eq!((notice.idx, notice.round), (1, 1));
+        assert!(notice.verify(&session_id, &verifying_keys).is_ok());
+
+        // Abort notices for other sessions and from unverified parties are rejected.
+        assert!(notice.verify(&[2; 32], &verifying_keys).is_err());
+        assert_eq!(
+            notice.verify(&session_id, &verifying_keys[1..]),
+            Err(wamu_core::Error::UnauthorizedParty)
+        );
+    }
+
+    #[test]
+    fn keygen_from_config_works() {
+        // Creates identity providers and a list of verifying keys for all parties.
+        let (threshold, n_parties) = (1, 2);
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (1..=n_parties)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+
+        // Invalid configurations are rejected.
+        for (config, identity_provider) in [
+            // Out of range threshold.
+            (
+                KeygenConfig {
+                    threshold: 2,
+                    n_parties,
+                    party_index: 1,
+                },
+                &identity_providers[0],
+            ),
+            // Out of range party index.
+            (
+                KeygenConfig {
+                    threshold,
+                    n_parties,
+                    party_index: 3,
+                },
+                &identity_providers[0],
+            ),
+            // Mismatched number of parties.
+            (
+                KeygenConfig {
+                    threshold,
+                    n_parties: 3,
+                    party_index: 1,
+                },
+                &identity_providers[0],
+            ),
+        ] {
+            assert!(matches!(
+                AugmentedKeyGen::from_config(identity_provider, &verifying_keys, config),
+                Err(Error::InvalidConfig)
+            ));
+        }
+        // Mismatched party index.
+        assert!(matches!(
+            AugmentedKeyGen::from_config(
+                &identity_providers[1],
+                &verifying_keys,
+                KeygenConfig {
+                    threshold,
+                    n_parties,
+                    party_index: 1
+                }
+            ),
+            Err(Error::Core(wamu_core::Error::UnauthorizedParty))
+        ));
+
+        // Runs keygen simulation with valid configurations.
+        let mut simulation = Simulation::new();
+        for (idx, identity_provider) in identity_providers.iter().enumerate() {
+            simulation.add_party(
+                AugmentedKeyGen::from_config(
+                    identity_provider,
+                    &verifying_keys,
+                    KeygenConfig {
+                        threshold,
+                        n_parties,
+                        party_index: (idx + 1) as u16,
+                    },
+                )
+                .unwrap(),
+            );
+        }
+        let wallet_shares: Vec<WalletShare> = simulation
+            .run()
+            .unwrap()
+            .into_iter()
+            .map(|output| WalletShare::try_from(output).unwrap())
+            .collect();
+
+        // Verifies wallet shares.
+        for (idx, wallet_share) in wallet_shares.iter().enumerate() {
+            assert_eq!(wallet_share.party_index(), (idx + 1) as u16);
+            assert_eq!(wallet_share.public_key(), wallet_shares[0].public_key());
+            assert!(wamu_core::share_split_reconstruct::reconstruct(
+                &wallet_share.signing_share,
+                &wallet_share.sub_share,
+                &identity_providers[idx],
+            )
+            .is_ok());
+
+            // Wallet shares round trip through key store key material.
+            let stored_key = wallet_share.to_stored_key(verifying_keys.clone(), 0);
+            let restored = WalletShare::from_stored_key(&stored_key).unwrap();
+            assert_eq!(restored.party_index(), wallet_share.party_index());
+            assert_eq!(restored.public_key(), wallet_share.public_key());
+        }
+    }
 }
@@ -0,0 +1,315 @@
+//! Key generation ceremony attestation (i.e key confirmation) implementation.
+//!
+//! After augmented key generation completes, every party signs (with its identity key) a canonical statement of
+//! the resulting public key, threshold and roster, and all parties output the bundle of attestations
+//! (i.e a verifiable "birth certificate" for the wallet).
+
+use curv::elliptic::curves::Secp256k1;
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::keygen::LocalKey;
+use round_based::{IsCritical, Msg, StateMachine};
+use std::collections::BTreeMap;
+use std::ops::Deref;
+use std::time::Duration;
+use wamu_core::cbor::{CanonicalCbor, Decoder, Encoder};
+use wamu_core::crypto::{Signature, VerifyingKey};
+use wamu_core::{CborError, CryptoError, IdentityProvider};
+
+const KEYGEN_ATTESTATION: &str = "keygen-attestation";
+
+/// A key generation ceremony attestation (i.e a statement of the public key, threshold and roster co-signed by all parties).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeygenAttestation {
+    /// The group public key (SEC1 compressed encoding).
+    pub public_key: Vec<u8>,
+    /// The threshold.
+    pub threshold: u16,
+    /// Verifying keys for all parties (in party index order).
+    pub parties: Vec<VerifyingKey>,
+    /// Signatures of the statement by all parties (in party index order).
+    pub signatures: Vec<Signature>,
+}
+
+impl KeygenAttestation {
+    /// Returns the canonical statement that's signed by all parties.
+    pub fn statement(&self) -> Vec<u8> {
+        statement(&self.public_key, self.threshold, &self.parties)
+    }
+
+    /// Returns an ok result if the statement is signed by all parties in the roster, or an appropriate error otherwise.
+    pub fn verify(&self) -> Result<(), Error> {
+        if self.signatures.len() != self.parties.len() {
+            return Err(Error::MissingSignatures);
+        }
+        let statement = self.statement();
+        for (i, (verifying_key, signature)) in self.parties.iter().zip(&self.signatures).enumerate()
+        {
+            wamu_core::crypto::verify_signature(verifying_key, &statement, signature).map_err(
+                |error| {
+                    Error::Blame(
+                        wamu_core::Blame::new(error)
+                            .with_verifying_key(verifying_key.clone())
+                            .with_idx(i as u16 + 1),
+                    )
+                },
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the canonical statement of a public key, threshold and roster.
+fn statement(public_key: &[u8], threshold: u16, parties: &[VerifyingKey]) -> Vec<u8> {
+    let mut encoder = Encoder::new();
+    encoder.array(4);
+    encoder.text(KEYGEN_ATTESTATION);
+    encoder.bytes(public_key);
+    encoder.uint(threshold.into());
+    encoder.seq(parties);
+    encoder.finish()
+}
+
+impl CanonicalCbor for KeygenAttestation {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.array(4);
+        encoder.bytes(&self.public_key);
+        encoder.uint(self.threshold.into());
+        encoder.seq(&self.parties);
+        encoder.seq(&self.signatures);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, CborError> {
+        decoder.array_of_len(4)?;
+        Ok(Self {
+            public_key: decoder.bytes()?,
+            threshold: decoder
+                .uint()?
+                .try_into()
+                .map_err(|_| CborError::InvalidValue)?,
+            parties: decoder.seq()?,
+            signatures: decoder.seq()?,
+        })
+    }
+}
+
+/// A [StateMachine](StateMachine) that confirms the output of key generation
+/// by collecting signatures of the resulting public key, threshold and roster from all parties.
+pub struct KeyConfirmation<'a> {
+    /// Verifying keys for all parties (in party index order).
+    parties: &'a [VerifyingKey],
+    /// Party index.
+    idx: u16,
+    /// The group public key (SEC1 compressed encoding).
+    public_key: Vec<u8>,
+    /// The threshold.
+    threshold: u16,
+    /// The signed statement.
+    statement: Vec<u8>,
+    /// Verified signatures indexed by party.
+    signatures: BTreeMap<u16, Signature>,
+    /// Outgoing message queue.
+    message_queue: Vec<Msg<Signature>>,
+    /// Whether or not the output was already picked.
+    is_picked: bool,
+}
+
+impl<'a> KeyConfirmation<'a> {
+    /// Initializes party for the key confirmation protocol given its identity provider,
+    /// verifying keys for all parties (in party index order) and the local key output by key generation.
+    pub fn new(
+        identity_provider: &impl IdentityProvider,
+        parties: &'a [VerifyingKey],
+        local_key: &LocalKey<Secp256k1>,
+    ) -> Result<Self, Error> {
+        if parties.len() != local_key.n as usize {
+            return Err(Error::InvalidConfig);
+        }
+
+        // Signs and broadcasts the statement.
+        let public_key = local_key.public_key().to_bytes(true).deref().to_vec();
+        let statement = statement(&public_key, local_key.t, parties);
+        let signature = identity_provider.sign(&statement);
+        let message_queue = vec![Msg {
+            sender: local_key.i,
+            receiver: None,
+            body: signature.clone(),
+        }];
+
+        Ok(Self {
+            parties,
+            idx: local_key.i,
+            public_key,
+            threshold: local_key.t,
+            statement,
+            signatures: BTreeMap::from([(local_key.i, signature)]),
+            message_queue,
+            is_picked: false,
+        })
+    }
+}
+
+impl<'a> StateMachine for KeyConfirmation<'a> {
+    type MessageBody = Signature;
+    type Err = Error;
+    type Output = KeygenAttestation;
+
+    fn handle_incoming(&mut self, msg: Msg<Self::MessageBody>) -> Result<(), Self::Err> {
+        // Verifies the signature of the sender.
+        let verifying_key = msg
+            .sender
+            .checked_sub(1)
+            .and_then(|i| self.parties.get(i as usize))
+            .ok_or(Error::InvalidConfig)?;
+        wamu_core::crypto::verify_signature(verifying_key, &self.statement, &msg.body).map_err(
+            |error| {
+                Error::Blame(
+                    wamu_core::Blame::new(error)
+                        .with_verifying_key(verifying_key.clone())
+                        .with_idx(msg.sender)
+                        .with_round(1),
+                )
+            },
+        )?;
+        self.signatures.insert(msg.sender, msg.body);
+        Ok(())
+    }
+
+    fn message_queue(&mut self) -> &mut Vec<Msg<Self::MessageBody>> {
+        &mut self.message_queue
+    }
+
+    fn wants_to_proceed(&self) -> bool {
+        false
+    }
+
+    fn proceed(&mut self) -> Result<(), Self::Err> {
+        Ok(())
+    }
+
+    fn round_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    fn round_timeout_reached(&mut self) -> Self::Err {
+        panic!("no timeout was set")
+    }
+
+    fn is_finished(&self) -> bool {
+        self.signatures.len() == self.parties.len()
+    }
+
+    fn pick_output(&mut self) -> Option<Result<Self::Output, Self::Err>> {
+        // Return an error if output was already picked.
+        if self.is_picked {
+            return Some(Err(Error::AlreadyPicked));
+        }
+
+        self.is_finished().then(|| {
+            self.is_picked = true;
+            Ok(KeygenAttestation {
+                public_key: self.public_key.clone(),
+                threshold: self.threshold,
+                parties: self.parties.to_vec(),
+                signatures: self.signatures.values().cloned().collect(),
+            })
+        })
+    }
+
+    fn current_round(&self) -> u16 {
+        1
+    }
+
+    fn total_rounds(&self) -> Option<u16> {
+        Some(1)
+    }
+
+    fn party_ind(&self) -> u16 {
+        self.idx
+    }
+
+    fn parties(&self) -> u16 {
+        self.parties.len() as u16
+    }
+}
+
+/// A key confirmation or attestation verification error.
+#[derive(Debug)]
+pub enum Error {
+    /// An invalid signature (with the offending party).
+    Blame(wamu_core::Blame<CryptoError>),
+    /// The attestation isn't signed by all parties.
+    MissingSignatures,
+    /// The roster doesn't match the local key.
+    InvalidConfig,
+    AlreadyPicked,
+}
+
+impl IsCritical for Error {
+    fn is_critical(&self) -> bool {
+        true
+    }
+}
+
+// Implement `Debug` trait for `KeyConfirmation` for test simulations.
+#[cfg(any(test, feature = "dev"))]
+impl<'a> std::fmt::Debug for KeyConfirmation<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Key Confirmation")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen::tests::simulate_keygen;
+    use round_based::dev::Simulation;
+
+    #[test]
+    fn keygen_attestation_works() {
+        // Runs key gen simulation for test parameters.
+        let (threshold, n_parties) = (1, 3);
+        let (keys, identity_providers) = simulate_keygen(threshold, n_parties);
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+
+        // Runs key confirmation simulation.
+        let mut simulation = Simulation::new();
+        for (key, identity_provider) in keys.iter().zip(identity_providers.iter()) {
+            simulation.add_party(
+                KeyConfirmation::new(identity_provider, &verifying_keys, &key.base).unwrap(),
+            );
+        }
+        let attestations = simulation.run().unwrap();
+
+        // Verifies that all parties output the same valid attestation.
+        assert_eq!(attestations.len(), n_parties as usize);
+        for attestation in attestations.iter() {
+            assert_eq!(attestation, &attestations[0]);
+            assert!(attestation.verify().is_ok());
+            assert_eq!(
+                KeygenAttestation::from_cbor(&attestation.to_cbor()).unwrap(),
+                attestations[0]
+            );
+        }
+        assert_eq!(attestations[0].threshold, threshold);
+        assert_eq!(
+            attestations[0].public_key,
+            keys[0].base.public_key().to_bytes(true).deref().to_vec()
+        );
+
+        // Verifies that attestations with a modified statement or missing signatures are rejected.
+        let mut modified_attestation = attestations[0].clone();
+        modified_attestation.threshold = 2;
+        assert!(matches!(
+            modified_attestation.verify(),
+            Err(Error::Blame(wamu_core::Blame { idx: Some(1), .. }))
+        ));
+        let mut partial_attestation = attestations[0].clone();
+        partial_attestation.signatures.pop();
+        assert!(matches!(
+            partial_attestation.verify(),
+            Err(Error::MissingSignatures)
+        ));
+    }
+}
@@ -2,17 +2,70 @@
 
 #![feature(doc_cfg)]
 
+pub use self::abort_evidence::{AbortEvidence, AbortEvidenceError, Offense, SignedMessage};
+pub use self::augmented::{AugmentOutgoingFn, Augmented, VerifyIncomingFn};
+pub use self::echo_broadcast::{EchoBroadcast, EchoBroadcastMessage};
+pub use self::event_sink::EventSink;
+pub use self::key_export::{export_local_key, ExportedLocalKey, KeyExportError, KEY_EXPORT};
+pub use self::keygen::{generate_wallet_share, KeygenConfig, KeygenMessage, WalletShare};
+pub use self::keygen_attestation::{
+    Error as KeygenAttestationError, KeyConfirmation, KeygenAttestation,
+};
+pub use self::liveness::{Heartbeat, LivenessError, LivenessEvent, LivenessMonitor};
+pub use self::message_auth::{MessageAuthParams, MessageAuthentication};
+pub use self::migration::{migrate, verify_identity_bindings, IdentityBinding};
+pub use self::observer::{veto_request, Observer, ObserverError};
+pub use self::quorum_approval::Message as QuorumApprovalMessage;
+pub use self::quorum_selection::{Quorum, QuorumSelectionError, QuorumSelector};
+pub use self::reliable_delivery::{ReliableDelivery, ReliableMessage, RetransmissionConfig};
+pub use self::secure_channel::{SecureChannel, SecureChannelMessage};
+pub use self::session_manager::{
+    SessionEnvelope, SessionEvent, SessionManager, SessionManagerError,
+};
+pub use self::sign::{
+    apply_derivation_tweak, compose_ssid, NonceGuard, RecoverableSignature, RingPedersenParams,
+    SigningParams, SsidBuilder,
+};
+pub use self::sign_message::{
+    sign_message, sign_prehashed, PreSigningMessage, SignMessageError, SigningMessage,
+};
+pub use self::socks5::{Socks5Error, Socks5Proxy};
+pub use self::transport::{
+    run_protocol, InMemoryTransport, ProtocolError, TcpTransport, Transport, TransportError,
+};
 pub use self::{
-    identity_auth::IdentityAuthentication, identity_rotation::IdentityRotation,
-    key_refresh::AugmentedKeyRefresh, keygen::AugmentedKeyGen, quorum_approval::QuorumApproval,
-    share_addition::ShareAddition, share_recovery_quorum::ShareRecoveryQuorum,
-    share_removal::ShareRemoval, sign::AugmentedPreSigning, sign::AugmentedSigning,
+    batch_identity_rotation::BatchIdentityRotation, identity_auth::IdentityAuthentication,
+    identity_rotation::IdentityRotation, key_refresh::AugmentedKeyRefresh, keygen::AugmentedKeyGen,
+    membership_change::MembershipChange, quorum_approval::QuorumApproval,
+    share_addition::ShareAddition, share_recovery_new_identity::ShareRecoveryNewIdentity,
+    share_recovery_quorum::ShareRecoveryQuorum, share_removal::ShareRemoval,
+    sign::AugmentedPreSigning, sign::AugmentedSigning,
     threshold_modification::ThresholdModification,
 };
 
+#[cfg(feature = "async")]
+#[doc(cfg(feature = "async"))]
+pub use self::async_session::{run_session, SessionError};
+#[cfg(feature = "round-based-v2")]
+#[doc(cfg(feature = "round-based-v2"))]
+pub use self::mpc_adapter::{run_mpc, MpcTransportError};
+#[cfg(feature = "libp2p")]
+#[doc(cfg(feature = "libp2p"))]
+pub use self::p2p_transport::{
+    bind_peer, run_libp2p_session, verify_peer_bindings, Behaviour as Libp2pBehaviour,
+    BehaviourEvent as Libp2pBehaviourEvent, FrameCodec, Libp2pSession, Libp2pTransportError,
+    PeerBinding,
+};
+#[cfg(feature = "ws-relay")]
+#[doc(cfg(feature = "ws-relay"))]
+pub use self::ws_relay::{RelaySession, WsRelayError, WsRelayTransport};
+
 #[cfg(feature = "dev")]
 #[doc(cfg(feature = "dev"))]
 pub use self::{
+    batch_identity_rotation::tests::{
+        generate_parties_and_simulate_batch_identity_rotation, simulate_batch_identity_rotation,
+    },
     identity_rotation::tests::{
         generate_parties_and_simulate_identity_rotation, simulate_identity_rotation,
     },
@@ -24,7 +77,10 @@ pub use self::{
     share_recovery_quorum::tests::{
         generate_parties_and_simulate_share_recovery_quorum, simulate_share_recovery_quorum,
     },
-    share_removal::tests::{generate_parties_and_simulate_share_removal, simulate_share_removal},
+    share_removal::tests::{
+        generate_parties_and_simulate_share_removal,
+        generate_parties_and_simulate_share_removal_of_indices, simulate_share_removal,
+    },
     sign::tests::{
         generate_parties_and_simulate_signing, generate_pre_sign_input, simulate_pre_sign,
         simulate_sign,
@@ -34,17 +90,52 @@ pub use self::{
     },
 };
 
+mod abort_evidence;
+#[cfg(feature = "async")]
+mod async_session;
 #[macro_use]
 pub mod augmented_state_machine;
 #[macro_use]
 pub mod authorized_key_refresh;
+mod augmented;
+mod batch_identity_rotation;
+pub mod chains;
+mod echo_broadcast;
+mod event_sink;
+#[cfg(feature = "grpc")]
+#[doc(cfg(feature = "grpc"))]
+pub mod grpc;
 mod identity_auth;
 mod identity_rotation;
+mod key_export;
 mod key_refresh;
 mod keygen;
+mod keygen_attestation;
+mod liveness;
+mod membership_change;
+mod message_auth;
+mod migration;
+#[cfg(feature = "round-based-v2")]
+mod mpc_adapter;
+mod observer;
+#[cfg(feature = "libp2p")]
+mod p2p_transport;
+#[cfg(feature = "proto")]
+#[doc(cfg(feature = "proto"))]
+pub mod proto;
 mod quorum_approval;
+mod quorum_selection;
+mod reliable_delivery;
+mod secure_channel;
+mod session_manager;
 mod share_addition;
+mod share_recovery_new_identity;
 mod share_recovery_quorum;
 mod share_removal;
 mod sign;
+mod sign_message;
+mod socks5;
 mod threshold_modification;
+mod transport;
+#[cfg(feature = "ws-relay")]
+mod ws_relay;
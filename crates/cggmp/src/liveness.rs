@@ -0,0 +1,359 @@
+//! Party liveness monitoring (i.e signed heartbeats and early failure detection) for protocol sessions.
+//!
+//! Parties periodically send signed [`Heartbeat`]s to all other parties of a session (e.g every few seconds),
+//! and a [`LivenessMonitor`] reports parties that are unresponsive (i.e haven't been heard from for a configurable period)
+//! well before the round timeout aborts the session,
+//! so that coordinators can proactively substitute a different quorum member (e.g for threshold signing).
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use wamu_core::crypto::{Signature, VerifyingKey};
+use wamu_core::IdentityProvider;
+
+/// A signed heartbeat of a party in a session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heartbeat {
+    /// Identifier of the session.
+    pub session_id: [u8; 32],
+    /// Index of the sending party.
+    pub idx: u16,
+    /// Current round of the sending party.
+    pub round: u16,
+    /// Sequence number of the heartbeat (i.e strictly increasing for each sending party).
+    pub seq: u64,
+    /// Verifying key of the sending party.
+    pub verifying_key: VerifyingKey,
+    /// Signature of the heartbeat.
+    pub signature: Signature,
+}
+
+impl Heartbeat {
+    /// Given a session identifier, the index of the sending party, its current round, a sequence number
+    /// and the identity provider of the sending party, returns a signed heartbeat.
+    pub fn new(
+        session_id: [u8; 32],
+        idx: u16,
+        round: u16,
+        seq: u64,
+        identity_provider: &impl IdentityProvider,
+    ) -> Self {
+        let (verifying_key, signature) = wamu_core::wrappers::initiate_request_with_signature(
+            &Self::message_hash(&session_id, idx, round, seq),
+            identity_provider,
+        );
+        Self {
+            session_id,
+            idx,
+            round,
+            seq,
+            verifying_key,
+            signature,
+        }
+    }
+
+    /// Given the expected session identifier and a list of verifying keys for all parties (in party index order),
+    /// returns an ok result if the heartbeat is for the session and signed by the party with its index,
+    /// or an appropriate error otherwise.
+    pub fn verify(
+        &self,
+        session_id: &[u8; 32],
+        verified_parties: &[VerifyingKey],
+    ) -> Result<(), LivenessError> {
+        if &self.session_id != session_id {
+            return Err(LivenessError::SessionMismatch);
+        }
+        // The index must match the verifying key (i.e parties can't send heartbeats on behalf of other parties).
+        let expected_verifying_key = (self.idx as usize)
+            .checked_sub(1)
+            .and_then(|i| verified_parties.get(i))
+            .ok_or(LivenessError::UnknownParty { idx: self.idx })?;
+        if &self.verifying_key != expected_verifying_key {
+            return Err(LivenessError::UnknownParty { idx: self.idx });
+        }
+        wamu_core::wrappers::verify_request_with_signature(
+            &Self::message_hash(&self.session_id, self.idx, self.round, self.seq),
+            &self.verifying_key,
+            &self.signature,
+            verified_parties,
+        )
+        .map_err(LivenessError::Unauthorized)
+    }
+
+    // Binds the heartbeat to the session, the sending party, the round and the sequence number.
+    fn message_hash(session_id: &[u8; 32], idx: u16, round: u16, seq: u64) -> Vec<u8> {
+        use sha2::{digest::Update, Digest};
+        let hasher = sha2::Sha256::new();
+        hasher
+            .chain(b"heartbeat")
+            .chain(session_id)
+            .chain(idx.to_be_bytes())
+            .chain(round.to_be_bytes())
+            .chain(seq.to_be_bytes())
+            .finalize()
+            .to_vec()
+    }
+}
+
+/// A liveness event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LivenessEvent {
+    /// A party that hasn't been heard from for `silent_for` (i.e since it was last seen in round `since_round`).
+    Unresponsive {
+        idx: u16,
+        verifying_key: VerifyingKey,
+        since_round: u16,
+        silent_for: Duration,
+    },
+    /// A previously unresponsive party that was heard from again in round `round`.
+    Recovered {
+        idx: u16,
+        verifying_key: VerifyingKey,
+        round: u16,
+    },
+}
+
+/// A liveness error.
+#[derive(Debug)]
+pub enum LivenessError {
+    /// A heartbeat for a different session.
+    SessionMismatch,
+    /// A heartbeat with an index that doesn't match a verified party (or its verifying key).
+    UnknownParty { idx: u16 },
+    /// A heartbeat with an invalid signature.
+    Unauthorized(wamu_core::Error),
+    /// A replayed or reordered heartbeat (i.e with a sequence number that isn't greater than that of the last heartbeat from the party).
+    StaleHeartbeat { idx: u16, seq: u64 },
+}
+
+/// Liveness state of a party.
+struct PartyLiveness {
+    /// Time at which the party was last heard from.
+    last_seen: Instant,
+    /// Round in which the party was last heard from.
+    round: u16,
+    /// Sequence number of the last heartbeat from the party (if any).
+    seq: Option<u64>,
+    /// Whether the party was reported as unresponsive.
+    is_unresponsive: bool,
+}
+
+/// Monitors the liveness of all other parties in a session.
+pub struct LivenessMonitor<'a> {
+    /// Identifier of the session.
+    session_id: [u8; 32],
+    /// Index of the local party.
+    idx: u16,
+    /// Verifying keys for all parties.
+    verified_parties: &'a [VerifyingKey],
+    /// Period of silence after which a party is reported as unresponsive.
+    unresponsive_after: Duration,
+    /// Sequence number of the next heartbeat of the local party.
+    next_seq: u64,
+    /// Liveness state of all other parties indexed by party index.
+    parties: BTreeMap<u16, PartyLiveness>,
+}
+
+impl<'a> LivenessMonitor<'a> {
+    /// Given a session identifier, the index of the local party, a list of verifying keys for all parties (in party index order)
+    /// and the period of silence after which a party is reported as unresponsive,
+    /// returns a liveness monitor (i.e for which all other parties are considered last seen in round 1 at the time of creation).
+    ///
+    /// **NOTE:** Heartbeats should be sent at an interval that's well below `unresponsive_after` (e.g a third of it),
+    /// so that a few dropped heartbeats don't get parties reported as unresponsive.
+    pub fn new(
+        session_id: [u8; 32],
+        idx: u16,
+        verified_parties: &'a [VerifyingKey],
+        unresponsive_after: Duration,
+    ) -> Self {
+        let now = Instant::now();
+        Self {
+            session_id,
+            idx,
+            verified_parties,
+            unresponsive_after,
+            next_seq: 0,
+            parties: (1..=verified_parties.len() as u16)
+                .filter(|party_idx| *party_idx != idx)
+                .map(|party_idx| {
+                    (
+                        party_idx,
+                        PartyLiveness {
+                            last_seen: now,
+                            round: 1,
+                            seq: None,
+                            is_unresponsive: false,
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Given the current round of the local party and its identity provider,
+    /// returns a signed heartbeat to send to all other parties.
+    pub fn heartbeat(
+        &mut self,
+        round: u16,
+        identity_provider: &impl IdentityProvider,
+    ) -> Heartbeat {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        Heartbeat::new(self.session_id, self.idx, round, seq, identity_provider)
+    }
+
+    /// Verifies and records a heartbeat from another party.
+    pub fn record(&mut self, heartbeat: &Heartbeat) -> Result<(), LivenessError> {
+        heartbeat.verify(&self.session_id, self.verified_parties)?;
+        let party = self
+            .parties
+            .get_mut(&heartbeat.idx)
+            .ok_or(LivenessError::UnknownParty { idx: heartbeat.idx })?;
+        if party.seq.map_or(false, |seq| heartbeat.seq <= seq) {
+            return Err(LivenessError::StaleHeartbeat {
+                idx: heartbeat.idx,
+                seq: heartbeat.seq,
+            });
+        }
+        party.seq = Some(heartbeat.seq);
+        party.last_seen = Instant::now();
+        party.round = party.round.max(heartbeat.round);
+        Ok(())
+    }
+
+    /// Records activity (i.e an authenticated protocol message) from another party in the given round.
+    ///
+    /// **NOTE:** Only messages that were authenticated (e.g with [`MessageAuthentication`](crate::MessageAuthentication)
+    /// or received over a [`SecureChannel`](crate::SecureChannel)) should be recorded as activity.
+    pub fn record_activity(&mut self, sender: u16, round: u16) {
+        if let Some(party) = self.parties.get_mut(&sender) {
+            party.last_seen = Instant::now();
+            party.round = party.round.max(round);
+        }
+    }
+
+    /// Returns liveness events for parties that became unresponsive or recovered since the last call.
+    pub fn poll(&mut self) -> Vec<LivenessEvent> {
+        let now = Instant::now();
+        let mut events = Vec::new();
+        for (idx, party) in self.parties.iter_mut() {
+            let silent_for = now.saturating_duration_since(party.last_seen);
+            let is_unresponsive = silent_for >= self.unresponsive_after;
+            if is_unresponsive == party.is_unresponsive {
+                continue;
+            }
+            party.is_unresponsive = is_unresponsive;
+            let verifying_key = self.verified_parties[*idx as usize - 1].clone();
+            events.push(if is_unresponsive {
+                LivenessEvent::Unresponsive {
+                    idx: *idx,
+                    verifying_key,
+                    since_round: party.round,
+                    silent_for,
+                }
+            } else {
+                LivenessEvent::Recovered {
+                    idx: *idx,
+                    verifying_key,
+                    round: party.round,
+                }
+            });
+        }
+        events
+    }
+
+    /// Returns the indices of parties that are currently reported as unresponsive (i.e as of the last call to [`poll`](Self::poll)).
+    pub fn unresponsive(&self) -> Vec<u16> {
+        self.parties
+            .iter()
+            .filter_map(|(idx, party)| party.is_unresponsive.then_some(*idx))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wamu_core::test_utils::MockECDSAIdentityProvider;
+
+    #[test]
+    fn liveness_monitor_works() {
+        // Creates identity providers and verifying keys for all parties.
+        let n_parties = 3;
+        let session_id = [1u8; 32];
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (0..n_parties)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let unresponsive_after = Duration::from_millis(100);
+        let mut monitor = LivenessMonitor::new(session_id, 1, &verifying_keys, unresponsive_after);
+        let mut monitor_2 =
+            LivenessMonitor::new(session_id, 2, &verifying_keys, unresponsive_after);
+        let mut monitor_3 =
+            LivenessMonitor::new(session_id, 3, &verifying_keys, unresponsive_after);
+
+        // Records heartbeats from all other parties.
+        let heartbeat_2 = monitor_2.heartbeat(2, &identity_providers[1]);
+        assert!(monitor.record(&heartbeat_2).is_ok());
+        assert!(monitor
+            .record(&monitor_3.heartbeat(2, &identity_providers[2]))
+            .is_ok());
+        assert!(monitor.poll().is_empty());
+
+        // Replayed heartbeats, heartbeats for other sessions and heartbeats on behalf of other parties are rejected.
+        assert!(matches!(
+            monitor.record(&heartbeat_2),
+            Err(LivenessError::StaleHeartbeat { idx: 2, seq: 0 })
+        ));
+        assert!(matches!(
+            monitor.record(&Heartbeat::new([2u8; 32], 2, 2, 1, &identity_providers[1])),
+            Err(LivenessError::SessionMismatch)
+        ));
+        assert!(matches!(
+            monitor.record(&Heartbeat::new(session_id, 2, 2, 1, &identity_providers[2])),
+            Err(LivenessError::UnknownParty { idx: 2 })
+        ));
+        let mut forged_heartbeat = monitor_2.heartbeat(2, &identity_providers[1]);
+        forged_heartbeat.round = 3;
+        assert!(matches!(
+            monitor.record(&forged_heartbeat),
+            Err(LivenessError::Unauthorized(_))
+        ));
+
+        // Reports parties that go silent as unresponsive (only once).
+        std::thread::sleep(unresponsive_after);
+        monitor.record_activity(3, 3);
+        assert_eq!(
+            monitor
+                .poll()
+                .into_iter()
+                .map(|event| match event {
+                    LivenessEvent::Unresponsive {
+                        idx, since_round, ..
+                    } => (idx, since_round),
+                    LivenessEvent::Recovered { .. } => panic!("unexpected recovery"),
+                })
+                .collect::<Vec<_>>(),
+            vec![(2, 2)]
+        );
+        assert!(monitor.poll().is_empty());
+        assert_eq!(monitor.unresponsive(), vec![2]);
+
+        // Reports unresponsive parties that are heard from again as recovered.
+        assert!(monitor
+            .record(&monitor_2.heartbeat(3, &identity_providers[1]))
+            .is_ok());
+        assert_eq!(
+            monitor.poll(),
+            vec![LivenessEvent::Recovered {
+                idx: 2,
+                verifying_key: verifying_keys[1].clone(),
+                round: 3,
+            }]
+        );
+        assert!(monitor.unresponsive().is_empty());
+    }
+}
@@ -0,0 +1,333 @@
+//! Combined membership change (i.e share addition and/or share removal) and threshold modification implementation.
+//!
+//! Performs party additions, party removals and threshold modification with one quorum approval and one key refresh,
+//! so that governance changes (e.g "add Carol, drop Dave, move to 3-of-5") don't require multiple sequential sessions with intermediate states.
+//!
+//! Refs:
+//! - <https://wamu.tech/specification#share-addition>.
+//! - <https://wamu.tech/specification#share-removal>.
+//! - <https://wamu.tech/specification#threshold-modification>.
+//!
+//! **NOTE:** Parties are identified by their new indices in both phases,
+//! so continuing parties must be mapped to the new indices `1..=k` (where `k` is the number of continuing parties)
+//! and new parties must use the new indices `k+1..=n`. Removed parties don't participate.
+
+use curv::elliptic::curves::Secp256k1;
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::keygen::LocalKey;
+use round_based::{Msg, StateMachine};
+use std::collections::HashMap;
+use std::time::Duration;
+use wamu_core::crypto::VerifyingKey;
+use wamu_core::quorum_approved_request::QuorumPolicy;
+use wamu_core::{IdentityProvider, SigningShare, SubShare};
+
+use crate::authorized_key_refresh::{AuthorizedKeyRefresh, Error, Message};
+use crate::key_refresh::AugmentedKeyRefresh;
+use crate::quorum_approval;
+use crate::quorum_approval::QuorumApproval;
+
+const MEMBERSHIP_CHANGE: &str = "membership-change";
+
+/// A [StateMachine](StateMachine) that performs share addition, share removal and threshold modification in a single authorized key refresh.
+pub struct MembershipChange<'a, I: IdentityProvider> {
+    // Quorum approval.
+    /// The decentralized identity provider of the party.
+    identity_provider: &'a I,
+    /// Verifying keys for other the parties.
+    verified_parties: &'a [VerifyingKey],
+    /// New party index.
+    idx: u16,
+    /// New total number of parties.
+    n_parties: u16,
+
+    // Key refresh.
+    /// The "signing share" of the party
+    /// (only `None` for the new parties, `Some` for all other parties).
+    signing_share_option: Option<&'a SigningShare>,
+    /// The "sub-share" of the party
+    /// (only `None` for the new parties, `Some` for all other parties).
+    sub_share_option: Option<&'a SubShare>,
+    /// Local key of the party (with secret share cleared/zerorized).
+    local_key_option: Option<LocalKey<Secp256k1>>,
+    /// Maps existing indices to new ones for continuing parties.
+    old_to_new_map: &'a HashMap<u16, u16>,
+    /// The current threshold.
+    // NOTE: Quorum size = threshold + 1
+    threshold: u16,
+    /// The new threshold.
+    new_threshold: u16,
+
+    // State machine management.
+    /// Outgoing message queue.
+    message_queue: Vec<Msg<Message<'a, I, quorum_approval::Message>>>,
+    /// Quorum approval state machine (must succeed before key refresh is performed).
+    auth_state_machine: QuorumApproval<'a, I>,
+    /// Key refresh state machine (activated after successful quorum approval).
+    refresh_state_machine: Option<AugmentedKeyRefresh<'a, I>>,
+    /// Stores "out of order" messages.
+    out_of_order_buffer: Vec<Msg<Message<'a, I, quorum_approval::Message>>>,
+}
+
+impl<'a, I: IdentityProvider> MembershipChange<'a, I> {
+    /// Initializes party for the membership change protocol.
+    pub fn new(
+        signing_share_option: Option<&'a SigningShare>,
+        sub_share_option: Option<&'a SubShare>,
+        identity_provider: &'a I,
+        verified_parties: &'a [VerifyingKey],
+        // `LocalKey<Secp256k1>` with secret share set to zero.
+        local_key_option: Option<LocalKey<Secp256k1>>,
+        new_party_index_option: Option<u16>,
+        n_parties: u16,
+        old_to_new_map: &'a HashMap<u16, u16>,
+        // NOTE: FS-DKR operates in the honest majority setting, so new_threshold <= n_parties/2 must hold.
+        new_threshold: u16,
+        // NOTE: Quorum size = threshold + 1
+        current_threshold_option: Option<u16>,
+        is_initiator: bool,
+    ) -> Result<MembershipChange<'a, I>, Error<'a, I, <QuorumApproval<'a, I> as StateMachine>::Err>>
+    {
+        // Continuing parties must be mapped to the first new indices.
+        let n_continuing = old_to_new_map.len() as u16;
+        let mut new_indices: Vec<u16> = old_to_new_map.values().copied().collect();
+        new_indices.sort_unstable();
+        if new_indices != (1..=n_continuing).collect::<Vec<u16>>() || n_parties < n_continuing {
+            return Err(Error::InvalidInput);
+        }
+
+        // Resolves the new party index (i.e removed parties can't participate).
+        let idx = match local_key_option.as_ref() {
+            Some(local_key) => old_to_new_map.get(&local_key.i).copied(),
+            None => {
+                new_party_index_option.filter(|idx| (n_continuing + 1..=n_parties).contains(idx))
+            }
+        }
+        .ok_or(Error::InvalidInput)?;
+        let threshold = local_key_option
+            .as_ref()
+            .map(|it| it.t)
+            .or(current_threshold_option)
+            .ok_or(Error::InvalidInput)?;
+
+        // Initializes quorum approval state machine.
+        let auth_state_machine = QuorumApproval::new(
+            MEMBERSHIP_CHANGE,
+            identity_provider,
+            verified_parties,
+            idx,
+            threshold,
+            // Parties being removed don't need to approve their own removal.
+            n_continuing,
+            is_initiator,
+            // New parties are dormant during the quorum approval.
+            local_key_option.is_none(),
+            // Membership changes require approval from all continuing parties.
+            QuorumPolicy::AllParties,
+        );
+
+        // Initializes membership change state machine.
+        let mut membership_change = Self {
+            // Quorum approval.
+            identity_provider,
+            verified_parties,
+            idx,
+            n_parties,
+            // Key refresh.
+            signing_share_option,
+            sub_share_option,
+            local_key_option,
+            old_to_new_map,
+            threshold,
+            new_threshold,
+            // State machine management.
+            message_queue: Vec::new(),
+            auth_state_machine,
+            refresh_state_machine: None,
+            out_of_order_buffer: Vec::new(),
+        };
+
+        // Retrieves messages from immediate state transitions (if any) and wraps them.
+        membership_change.update_composite_message_queue()?;
+
+        // Returns membership change machine.
+        Ok(membership_change)
+    }
+
+    /// Enforces a delay (in seconds) between request initiation and key refresh,
+    /// during which any verified party can cancel the request (see [`cancel`](Self::cancel)).
+    ///
+    /// **NOTE:** Recommended for sensitive commands (e.g party removal), all parties must use the same delay.
+    pub fn with_time_lock(mut self, delay: u64) -> Self {
+        self.auth_state_machine = self.auth_state_machine.with_time_lock(delay);
+        self
+    }
+
+    /// Sets verifying keys for observers (i.e watch-only parties) that can veto the request before it's approved
+    /// (see [`QuorumApproval::with_observers`]).
+    pub fn with_observers(mut self, observers: &'a [VerifyingKey]) -> Self {
+        self.auth_state_machine = self.auth_state_machine.with_observers(observers);
+        self
+    }
+
+    /// Cancels a time-locked request and broadcasts a signed cancellation to the other parties.
+    pub fn cancel(
+        &mut self,
+    ) -> Result<(), Error<'a, I, <QuorumApproval<'a, I> as StateMachine>::Err>> {
+        self.auth_state_machine.cancel()?;
+
+        // Wraps the cancellation message.
+        self.update_composite_message_queue()
+    }
+}
+
+impl<'a, I: IdentityProvider> AuthorizedKeyRefresh<'a, I> for MembershipChange<'a, I> {
+    type InitStateMachineType = QuorumApproval<'a, I>;
+
+    impl_required_authorized_key_refresh_getters!(
+        verified_parties,
+        auth_state_machine,
+        refresh_state_machine,
+        message_queue,
+        out_of_order_buffer
+    );
+
+    fn verify_authorization(
+        &self,
+    ) -> Result<(), Error<'a, I, <Self::InitStateMachineType as StateMachine>::Err>> {
+        // Destructive commands require a verified quorum (i.e not just the initiator's identity authentication).
+        self.auth_state_machine.verify_quorum().map_err(Error::Init)
+    }
+
+    fn create_key_refresh(
+        &mut self,
+    ) -> Result<
+        AugmentedKeyRefresh<'a, I>,
+        Error<'a, I, <Self::InitStateMachineType as StateMachine>::Err>,
+    > {
+        // Initializes key refresh state machine.
+        let is_new_party = self.local_key_option.is_none();
+        Ok(AugmentedKeyRefresh::new(
+            self.signing_share_option,
+            self.sub_share_option,
+            self.identity_provider,
+            self.verified_parties,
+            self.local_key_option.take(),
+            is_new_party.then_some(self.idx),
+            self.old_to_new_map,
+            self.new_threshold,
+            self.n_parties,
+            is_new_party.then_some(self.threshold),
+        )?)
+    }
+}
+
+impl_state_machine_for_authorized_key_refresh!(MembershipChange, idx, n_parties);
+
+// Implement `Debug` trait for `MembershipChange` for test simulations.
+#[cfg(any(test, feature = "dev"))]
+impl<'a, I: IdentityProvider> std::fmt::Debug for MembershipChange<'a, I> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Membership Change")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen::tests::simulate_keygen;
+    use curv::elliptic::curves::Scalar;
+    use round_based::dev::Simulation;
+    use wamu_core::test_utils::MockECDSAIdentityProvider;
+
+    #[test]
+    fn membership_change_works() {
+        // Generates keys for a 2-of-3 wallet (i.e threshold 1).
+        let (threshold, n_parties_init) = (1, 3);
+        let (keys, mut identity_providers) = simulate_keygen(threshold, n_parties_init);
+        let pub_key_init = keys[0].base.public_key();
+
+        // Removes the last party, adds two new parties and moves to a 3-of-4 wallet (i.e threshold 2).
+        let (n_continuing, n_parties_new, new_threshold) = (2, 4, 2);
+        identity_providers.truncate(n_continuing);
+        identity_providers
+            .extend((n_continuing..n_parties_new).map(|_| MockECDSAIdentityProvider::generate()));
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let old_to_new_map: HashMap<u16, u16> = keys[..n_continuing]
+            .iter()
+            .enumerate()
+            .map(|(i, key)| (key.base.i, i as u16 + 1))
+            .collect();
+
+        // Runs membership change simulation.
+        let mut simulation = Simulation::new();
+        for (i, identity_provider) in identity_providers.iter().enumerate() {
+            let key_option = keys[..n_continuing].get(i);
+            let share_output_option = key_option.map(|key| key.extra.as_ref().unwrap());
+            let idx = i as u16 + 1;
+            simulation.add_party(
+                MembershipChange::new(
+                    share_output_option.map(|(signing_share, _)| signing_share),
+                    share_output_option.map(|(_, sub_share)| sub_share),
+                    identity_provider,
+                    &verifying_keys,
+                    key_option.map(|key| key.base.clone()),
+                    key_option.is_none().then_some(idx),
+                    n_parties_new as u16,
+                    &old_to_new_map,
+                    new_threshold,
+                    key_option.is_none().then_some(threshold),
+                    idx == 1,
+                )
+                .unwrap(),
+            );
+        }
+        let new_keys = simulation.run().unwrap();
+
+        // Verifies the refreshed/generated keys and configuration for all parties.
+        assert_eq!(new_keys.len(), n_parties_new);
+        for new_key in new_keys.iter() {
+            assert_eq!(new_key.base.t, new_threshold);
+            assert_eq!(new_key.base.n, n_parties_new as u16);
+            assert_eq!(new_key.base.keys_linear.x_i, Scalar::<Secp256k1>::zero());
+            assert_eq!(new_key.base.public_key(), pub_key_init);
+        }
+
+        // Removed parties and new parties with colliding indices are rejected.
+        let (signing_share, sub_share) = keys[2].extra.as_ref().unwrap();
+        assert!(matches!(
+            MembershipChange::new(
+                Some(signing_share),
+                Some(sub_share),
+                &identity_providers[0],
+                &verifying_keys,
+                Some(keys[2].base.clone()),
+                None,
+                n_parties_new as u16,
+                &old_to_new_map,
+                new_threshold,
+                None,
+                false,
+            ),
+            Err(Error::InvalidInput)
+        ));
+        assert!(matches!(
+            MembershipChange::new(
+                None,
+                None,
+                &identity_providers[2],
+                &verifying_keys,
+                None,
+                Some(2),
+                n_parties_new as u16,
+                &old_to_new_map,
+                new_threshold,
+                Some(threshold),
+                false,
+            ),
+            Err(Error::InvalidInput)
+        ));
+    }
+}
@@ -0,0 +1,575 @@
+//! Per-round identity authentication of protocol messages with a running session transcript.
+//!
+//! Wraps any [`StateMachine`](StateMachine) (including augmented state machines) and
+//! signs every outgoing message body with the identity key of the sender (and verifies it on receipt),
+//! so that a network adversary can't inject, reorder or tamper with messages between authenticated parties in any round
+//! (i.e not just the round 1 messages that carry identity authentication parameters).
+//!
+//! A running transcript hash of all broadcast messages of previous rounds (seeded with the session identifier)
+//! is included in every signature and the final output, so the whole session is cryptographically bound to one execution
+//! (i.e messages spliced from other sessions and equivocating broadcasts are detected, and all honest parties output the same transcript hash).
+//!
+//! Exact duplicates of verified messages are dropped idempotently,
+//! while conflicting messages from the same sender for the same round (i.e equivocation) are rejected with the sender's identity.
+//!
+//! Equivocating messages are retained, so that a signed and independently verifiable [`AbortEvidence`] bundle
+//! can be exported when a session aborts (failed message authentication is only blamed locally,
+//! because anyone can produce an invalid signature on behalf of any party).
+//!
+//! Messages of the one-shot protocol drivers (i.e [`generate_wallet_share`](crate::generate_wallet_share),
+//! [`sign_message`](crate::sign_message) and [`sign_prehashed`](crate::sign_prehashed)) are authenticated by default.
+//!
+//! **NOTE:** Message bodies are opaque to this crate, so an encoder for message bodies must be supplied.
+//! The encoding must be deterministic (i.e the same for the sender and all receivers).
+
+use round_based::{Msg, StateMachine};
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Deref;
+use std::time::Duration;
+use wamu_core::crypto::VerifyingKey;
+use wamu_core::IdentityProvider;
+
+use crate::abort_evidence::{AbortEvidence, Offense, SignedMessage};
+use crate::augmented_state_machine::{AugmentedType, Error, IdentityAuthParams};
+
+/// Additional parameters for per-round message authentication.
+#[derive(Debug, Clone)]
+pub struct MessageAuthParams {
+    /// Round of the wrapped state machine for which the message was sent.
+    pub round: u16,
+    /// Verifying key of the sender and signature of the message (bound to the session transcript).
+    pub identity_auth: IdentityAuthParams,
+}
+
+/// A [`StateMachine`](StateMachine) that signs all outgoing messages of a wrapped `StateMachine` with the identity key of the party
+/// and verifies the signatures of all incoming messages before forwarding them to the wrapped `StateMachine`.
+pub struct MessageAuthentication<'a, SM: StateMachine, I: IdentityProvider> {
+    /// Wrapped state machine.
+    state_machine: SM,
+    /// Local party's identity provider.
+    identity_provider: &'a I,
+    /// Verifying keys for all parties.
+    verified_parties: &'a [VerifyingKey],
+    /// Identifier of the session.
+    session_id: [u8; 32],
+    /// Deterministic encoder for message bodies of the wrapped state machine.
+    encode_body: fn(&SM::MessageBody) -> Vec<u8>,
+    /// Running transcript hashes (i.e the transcript hash at index `r` covers all broadcast messages of rounds before round `r`).
+    transcript: Vec<Vec<u8>>,
+    /// Hashes of broadcast message bodies indexed by round and sender.
+    broadcasts: BTreeMap<(u16, u16), Vec<u8>>,
+    /// Verified incoming messages indexed by sender, round and whether the message is a broadcast.
+    received: HashMap<(u16, u16, bool), SignedMessage>,
+    /// Last offense that was detected (if any).
+    offense: Option<Offense>,
+    /// Buffered incoming messages for future rounds.
+    pending_messages: Vec<Msg<AugmentedType<SM::MessageBody, MessageAuthParams>>>,
+    /// Authenticated message queue.
+    message_queue: Vec<Msg<AugmentedType<SM::MessageBody, MessageAuthParams>>>,
+}
+
+impl<'a, SM: StateMachine, I: IdentityProvider> MessageAuthentication<'a, SM, I> {
+    /// Given a state machine, an identity provider, a list of verifying keys for all parties,
+    /// a session identifier shared by all parties and a deterministic encoder for message bodies of the state machine,
+    /// returns a state machine that authenticates messages of the wrapped state machine in every round.
+    ///
+    /// **NOTE:** The session identifier must be unique for each session (e.g a fresh random value from the initiator),
+    /// because it seeds the transcript (i.e messages from other sessions are rejected).
+    pub fn new(
+        state_machine: SM,
+        identity_provider: &'a I,
+        verified_parties: &'a [VerifyingKey],
+        session_id: [u8; 32],
+        encode_body: fn(&SM::MessageBody) -> Vec<u8>,
+    ) -> Self {
+        let mut message_auth = Self {
+            state_machine,
+            identity_provider,
+            verified_parties,
+            session_id,
+            encode_body,
+            transcript: vec![session_id.to_vec()],
+            broadcasts: BTreeMap::new(),
+            received: HashMap::new(),
+            offense: None,
+            pending_messages: Vec::new(),
+            message_queue: Vec::new(),
+        };
+
+        // Signs messages queued by the wrapped state machine during initialization (if any).
+        message_auth.update_message_queue();
+
+        message_auth
+    }
+
+    /// Returns an immutable reference to the wrapped state machine.
+    pub fn state_machine(&self) -> &SM {
+        &self.state_machine
+    }
+
+    /// Returns a signed abort evidence bundle for the last offense that was detected (if any).
+    pub fn abort_evidence(&self) -> Option<AbortEvidence> {
+        self.offense.clone().map(|offense| {
+            AbortEvidence::new(
+                offense,
+                self.state_machine.party_ind(),
+                self.identity_provider,
+            )
+        })
+    }
+
+    /// Returns the transcript hash of all broadcast messages of rounds before the given round.
+    ///
+    /// **NOTE:** The wrapped state machine must have received all broadcast messages of rounds before the given round.
+    fn transcript_hash(&mut self, round: u16) -> Vec<u8> {
+        transcript_hash(&mut self.transcript, &self.broadcasts, round)
+    }
+
+    /// Signs messages in the message queue of the wrapped state machine and moves them to the authenticated message queue.
+    fn update_message_queue(&mut self) {
+        let round = self.state_machine.current_round();
+        for msg in self.state_machine.message_queue().split_off(0) {
+            let message_hash = self.message_hash(msg.sender, msg.receiver, round, &msg.body);
+            let (verifying_key, verifying_signature) =
+                wamu_core::wrappers::initiate_request_with_signature(
+                    &message_hash,
+                    self.identity_provider,
+                );
+            if msg.receiver.is_none() {
+                let body_hash = body_hash(&(self.encode_body)(&msg.body));
+                self.broadcasts.insert((round, msg.sender), body_hash);
+            }
+            self.message_queue.push(Msg {
+                sender: msg.sender,
+                receiver: msg.receiver,
+                body: AugmentedType {
+                    base: msg.body,
+                    extra: Some(MessageAuthParams {
+                        round,
+                        identity_auth: IdentityAuthParams {
+                            verifying_key,
+                            verifying_signature,
+                        },
+                    }),
+                },
+            });
+        }
+    }
+
+    /// Returns the signed hash of a message
+    /// (i.e binds the message body to its session, sender, receiver, round and the transcript of previous rounds).
+    fn message_hash(
+        &mut self,
+        sender: u16,
+        receiver: Option<u16>,
+        round: u16,
+        body: &SM::MessageBody,
+    ) -> Vec<u8> {
+        let transcript_hash = self.transcript_hash(round);
+        crate::abort_evidence::message_hash(
+            &self.session_id,
+            sender,
+            receiver,
+            round,
+            &transcript_hash,
+            &(self.encode_body)(body),
+        )
+    }
+
+    /// Verifies an incoming message for the current (or a previous) round and forwards it to the wrapped state machine.
+    fn authenticated_handle_incoming(
+        &mut self,
+        msg: Msg<AugmentedType<SM::MessageBody, MessageAuthParams>>,
+    ) -> Result<(), Error<SM::Err>> {
+        // Verifies the message signature.
+        let params = msg.body.extra.as_ref().ok_or(Error::MissingParams {
+            bad_actors: vec![msg.sender as usize],
+            round: self.current_round(),
+        })?;
+        let signed_msg = SignedMessage {
+            sender: msg.sender,
+            receiver: msg.receiver,
+            round: params.round,
+            session_id: self.session_id,
+            transcript_hash: self.transcript_hash(params.round),
+            body: (self.encode_body)(&msg.body.base),
+            verifying_key: params.identity_auth.verifying_key.clone(),
+            signature: params.identity_auth.verifying_signature.clone(),
+        };
+        // NOTE: The verifying key must also be the one registered for the sender
+        // (i.e verified parties can't send messages on behalf of other parties).
+        signed_msg.verify(self.verified_parties).map_err(|error| {
+            Error::blame(error, msg.sender, &params.identity_auth, params.round)
+        })?;
+
+        // Drops exact duplicates and rejects conflicting messages (i.e equivocation),
+        // instead of forwarding both to the wrapped state machine.
+        let is_broadcast = msg.receiver.is_none();
+        match self.received.get(&(msg.sender, params.round, is_broadcast)) {
+            Some(prev_msg) if prev_msg.body == signed_msg.body => return Ok(()),
+            Some(prev_msg) => {
+                self.offense = Some(Offense::Equivocation(prev_msg.clone(), signed_msg));
+                return Err(Error::Equivocation {
+                    idx: msg.sender,
+                    verifying_key: params.identity_auth.verifying_key.clone(),
+                    round: params.round,
+                });
+            }
+            None => {
+                if is_broadcast {
+                    self.broadcasts
+                        .insert((params.round, msg.sender), body_hash(&signed_msg.body));
+                }
+                self.received
+                    .insert((msg.sender, params.round, is_broadcast), signed_msg);
+            }
+        }
+
+        // Forwards the verified message to the wrapped state machine.
+        self.state_machine
+            .handle_incoming(msg.map_body(|msg_body| msg_body.base))
+            .map_err(Error::StateMachine)?;
+
+        // Updates the message queue.
+        self.update_message_queue();
+        Ok(())
+    }
+
+    /// Replays buffered messages for the current (or a previous) round (if any).
+    fn replay_pending_messages(&mut self) -> Result<(), Error<SM::Err>> {
+        loop {
+            let current_round = self.state_machine.current_round();
+            let (ready, pending): (Vec<_>, Vec<_>) =
+                self.pending_messages.drain(..).partition(|msg| {
+                    msg.body
+                        .extra
+                        .as_ref()
+                        .map_or(true, |params| params.round <= current_round)
+                });
+            self.pending_messages = pending;
+            if ready.is_empty() {
+                return Ok(());
+            }
+            for msg in ready {
+                self.authenticated_handle_incoming(msg)?;
+            }
+        }
+    }
+}
+
+impl<'a, SM: StateMachine, I: IdentityProvider> StateMachine for MessageAuthentication<'a, SM, I> {
+    type MessageBody = AugmentedType<SM::MessageBody, MessageAuthParams>;
+    type Err = Error<SM::Err>;
+    type Output = AugmentedType<SM::Output, Vec<u8>>;
+
+    fn handle_incoming(&mut self, msg: Msg<Self::MessageBody>) -> Result<(), Self::Err> {
+        match msg.body.extra.as_ref() {
+            // Buffers messages for future rounds until the wrapped state machine advances to their round,
+            // because the transcript of previous rounds is required to verify them.
+            Some(params) if params.round > self.state_machine.current_round() => {
+                self.pending_messages.push(msg);
+                Ok(())
+            }
+            _ => {
+                self.authenticated_handle_incoming(msg)?;
+                // Handling a message can advance the wrapped state machine to the next round.
+                self.replay_pending_messages()
+            }
+        }
+    }
+
+    fn message_queue(&mut self) -> &mut Vec<Msg<Self::MessageBody>> {
+        &mut self.message_queue
+    }
+
+    fn wants_to_proceed(&self) -> bool {
+        self.state_machine.wants_to_proceed()
+    }
+
+    fn proceed(&mut self) -> Result<(), Self::Err> {
+        self.state_machine.proceed().map_err(Error::StateMachine)?;
+
+        // Updates the message queue and replays buffered messages for the new round.
+        self.update_message_queue();
+        self.replay_pending_messages()
+    }
+
+    fn round_timeout(&self) -> Option<Duration> {
+        self.state_machine.round_timeout()
+    }
+
+    fn round_timeout_reached(&mut self) -> Self::Err {
+        Error::StateMachine(self.state_machine.round_timeout_reached())
+    }
+
+    fn is_finished(&self) -> bool {
+        self.state_machine.is_finished()
+    }
+
+    fn pick_output(&mut self) -> Option<Result<Self::Output, Self::Err>> {
+        let result = self.state_machine.pick_output()?;
+
+        // Adds the transcript hash of all broadcast messages of the session to the output.
+        let last_round = self
+            .broadcasts
+            .keys()
+            .last()
+            .map_or(0, |(round, _)| round + 1);
+        let transcript_hash = self.transcript_hash(last_round);
+        Some(
+            result
+                .map(|output| AugmentedType {
+                    base: output,
+                    extra: Some(transcript_hash),
+                })
+                .map_err(Error::StateMachine),
+        )
+    }
+
+    fn current_round(&self) -> u16 {
+        self.state_machine.current_round()
+    }
+
+    fn total_rounds(&self) -> Option<u16> {
+        self.state_machine.total_rounds()
+    }
+
+    fn party_ind(&self) -> u16 {
+        self.state_machine.party_ind()
+    }
+
+    fn parties(&self) -> u16 {
+        self.state_machine.parties()
+    }
+}
+
+/// Given running transcript hashes, hashes of broadcast message bodies indexed by round and sender and a round,
+/// extends the running transcript hashes (if necessary) and returns the transcript hash of all broadcast messages of rounds before the round.
+pub(crate) fn transcript_hash(
+    transcript: &mut Vec<Vec<u8>>,
+    broadcasts: &BTreeMap<(u16, u16), Vec<u8>>,
+    round: u16,
+) -> Vec<u8> {
+    use sha2::{digest::Update, Digest};
+    while transcript.len() <= round as usize {
+        let prev_round = transcript.len() as u16 - 1;
+        let hasher = sha2::Sha256::new()
+            .chain(&transcript[prev_round as usize])
+            .chain(prev_round.to_be_bytes());
+        // Broadcast messages are ordered by sender.
+        let hasher = broadcasts
+            .range((prev_round, 0)..=(prev_round, u16::MAX))
+            .fold(hasher, |hasher, ((_, sender), body_hash)| {
+                hasher.chain(sender.to_be_bytes()).chain(body_hash)
+            });
+        transcript.push(hasher.finalize().deref().to_vec());
+    }
+    transcript[round as usize].clone()
+}
+
+/// Returns the hash of an encoded message body.
+pub(crate) fn body_hash(encoded_body: &[u8]) -> Vec<u8> {
+    use sha2::Digest;
+    sha2::Sha256::digest(encoded_body).deref().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abort_evidence::AbortEvidenceError;
+    use crate::keygen::AugmentedKeyGen;
+    use round_based::dev::Simulation;
+    use wamu_core::cbor::CanonicalCbor;
+    use wamu_core::test_utils::MockECDSAIdentityProvider;
+
+    // Encodes augmented key generation message bodies (deterministically) for tests.
+    fn encode_keygen_body(
+        body: &<AugmentedKeyGen<'_, MockECDSAIdentityProvider> as StateMachine>::MessageBody,
+    ) -> Vec<u8> {
+        format!("{:?}", body.base).into_bytes()
+    }
+
+    #[test]
+    fn message_authentication_works() {
+        // Creates identity providers and verifying keys for all parties.
+        let (threshold, n_parties) = (1, 3);
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (1..=n_parties)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let session_id = [1; 32];
+        let new_party = |idx: usize| {
+            MessageAuthentication::new(
+                AugmentedKeyGen::new(
+                    &identity_providers[idx],
+                    &verifying_keys,
+                    (idx + 1) as u16,
+                    threshold,
+                    n_parties,
+                )
+                .unwrap(),
+                &identity_providers[idx],
+                &verifying_keys,
+                session_id,
+                encode_keygen_body,
+            )
+        };
+
+        // Runs key generation with authenticated messages in all rounds.
+        let mut simulation = Simulation::new();
+        for idx in 0..n_parties as usize {
+            simulation.add_party(new_party(idx));
+        }
+        let outputs = simulation.run().unwrap();
+        assert_eq!(outputs.len(), n_parties as usize);
+        for output in &outputs {
+            assert_eq!(
+                output.base.base.public_key(),
+                outputs[0].base.base.public_key()
+            );
+            // All parties output the same session transcript hash.
+            assert!(output.extra.is_some());
+            assert_eq!(output.extra, outputs[0].extra);
+        }
+
+        // Tampered (i.e re-attributed or re-ordered), unsigned and unauthorized messages are rejected.
+        let (mut party_1, mut party_2, mut party_3) = (new_party(0), new_party(1), new_party(2));
+        let msg_1 = party_1.message_queue()[0].clone();
+        let msg_2 = party_2.message_queue()[0].clone();
+        let mut tampered_msg = msg_2.clone();
+        tampered_msg.sender = msg_1.sender;
+        let mut reordered_msg = msg_1.clone();
+        reordered_msg.body.extra.as_mut().unwrap().round -= 1;
+        let mut unsigned_msg = msg_1.clone();
+        unsigned_msg.body.extra = None;
+        let mut unauthorized_party = MessageAuthentication::new(
+            AugmentedKeyGen::new(
+                &identity_providers[0],
+                &verifying_keys,
+                1,
+                threshold,
+                n_parties,
+            )
+            .unwrap(),
+            &identity_providers[0],
+            &verifying_keys[1..],
+            session_id,
+            encode_keygen_body,
+        );
+        assert!(matches!(
+            party_3.handle_incoming(tampered_msg),
+            Err(Error::Blame(_))
+        ));
+        assert!(matches!(
+            party_3.handle_incoming(reordered_msg),
+            Err(Error::Blame(_))
+        ));
+        assert!(matches!(
+            party_3.handle_incoming(unsigned_msg),
+            Err(Error::MissingParams { .. })
+        ));
+        assert!(matches!(
+            unauthorized_party.handle_incoming(msg_1.clone()),
+            Err(Error::Blame(_))
+        ));
+
+        // Messages from other sessions are rejected.
+        let mut other_session_party = MessageAuthentication::new(
+            AugmentedKeyGen::new(
+                &identity_providers[2],
+                &verifying_keys,
+                3,
+                threshold,
+                n_parties,
+            )
+            .unwrap(),
+            &identity_providers[2],
+            &verifying_keys,
+            [2; 32],
+            encode_keygen_body,
+        );
+        assert!(matches!(
+            other_session_party.handle_incoming(msg_1.clone()),
+            Err(Error::Blame(_))
+        ));
+
+        // Messages signed by a verified party on behalf of another party are rejected.
+        let mut impersonating_msg = msg_2.clone();
+        impersonating_msg.sender = msg_1.sender;
+        let round = impersonating_msg.body.extra.as_ref().unwrap().round;
+        let message_hash = party_2.message_hash(
+            impersonating_msg.sender,
+            impersonating_msg.receiver,
+            round,
+            &impersonating_msg.body.base,
+        );
+        let (_, signature) = wamu_core::wrappers::initiate_request_with_signature(
+            &message_hash,
+            &identity_providers[1],
+        );
+        impersonating_msg
+            .body
+            .extra
+            .as_mut()
+            .unwrap()
+            .identity_auth
+            .verifying_signature = signature;
+        assert!(matches!(
+            party_3.handle_incoming(impersonating_msg),
+            Err(Error::Blame(_))
+        ));
+
+        // Authentic messages are accepted.
+        assert!(party_3.handle_incoming(msg_1.clone()).is_ok());
+        assert!(party_3.handle_incoming(msg_2).is_ok());
+
+        // Exact duplicates are dropped, while conflicting messages from the same sender for the same round are rejected.
+        assert!(party_3.handle_incoming(msg_1.clone()).is_ok());
+        let conflicting_msg = new_party(0).message_queue()[0].clone();
+        match party_3.handle_incoming(conflicting_msg) {
+            Err(Error::Equivocation {
+                idx,
+                verifying_key,
+                round,
+            }) => {
+                assert_eq!(idx, msg_1.sender);
+                assert_eq!(verifying_key, verifying_keys[0]);
+                assert_eq!(round, msg_1.body.extra.as_ref().unwrap().round);
+            }
+            _ => panic!("expected an equivocation error"),
+        }
+
+        // Exports verifiable abort evidence for the equivocation.
+        let evidence = party_3.abort_evidence().unwrap();
+        assert_eq!(evidence.offense.offender(), msg_1.sender);
+        assert_eq!(evidence.reporter, 3);
+        assert!(matches!(evidence.offense, Offense::Equivocation(..)));
+        assert_eq!(evidence.verify(&verifying_keys), Ok(()));
+        assert_eq!(
+            AbortEvidence::from_cbor(&evidence.to_cbor()),
+            Ok(evidence.clone())
+        );
+
+        // Evidence with a forged attestation or unproven offense is rejected.
+        let mut forged_evidence = evidence.clone();
+        forged_evidence.reporter = 2;
+        assert!(matches!(
+            forged_evidence.verify(&verifying_keys),
+            Err(AbortEvidenceError::InvalidReport(_))
+        ));
+        if let Offense::Equivocation(first, _) = &evidence.offense {
+            let unproven_evidence = AbortEvidence::new(
+                Offense::Equivocation(first.clone(), first.clone()),
+                3,
+                &identity_providers[2],
+            );
+            assert_eq!(
+                unproven_evidence.verify(&verifying_keys),
+                Err(AbortEvidenceError::UnprovenOffense)
+            );
+        }
+    }
+}
@@ -0,0 +1,188 @@
+//! Migration of existing GG20 key shares to augmented Wamu wallet shares.
+//!
+//! Allows existing threshold signature deployments (i.e `multi-party-ecdsa` GG20 `LocalKey`s) to adopt Wamu without regenerating keys.
+//!
+//! Ref: <https://wamu.tech/specification#share-splitting>.
+
+use curv::elliptic::curves::Secp256k1;
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::keygen::LocalKey;
+use std::ops::Deref;
+use wamu_core::crypto::{Signature, VerifyingKey};
+use wamu_core::IdentityProvider;
+
+use crate::augmented_state_machine;
+use crate::keygen::WalletShare;
+
+/// A signed statement binding a party's verifying key to its party index for a group public key.
+#[derive(Debug, Clone)]
+pub struct IdentityBinding {
+    /// Index of the party.
+    pub idx: u16,
+    /// Verifying key of the party.
+    pub verifying_key: VerifyingKey,
+    /// Signature of the binding statement.
+    pub signature: Signature,
+}
+
+/// Given the identity provider of the party and its existing GG20 local key (with the secret share set),
+/// returns the augmented wallet share (i.e with the secret share split into a "signing share" and "sub-share" and cleared/zerorized)
+/// and an identity binding for sharing with the other parties.
+pub fn migrate(
+    identity_provider: &impl IdentityProvider,
+    local_key: LocalKey<Secp256k1>,
+) -> Result<(WalletShare, IdentityBinding), wamu_core::Error> {
+    // Signs the identity binding.
+    let (verifying_key, signature) = wamu_core::wrappers::initiate_request_with_signature(
+        &binding_hash(&local_key, local_key.i),
+        identity_provider,
+    );
+    let binding = IdentityBinding {
+        idx: local_key.i,
+        verifying_key,
+        signature,
+    };
+
+    // Splits the secret share and clears/zerorizes it in the local key.
+    let wallet_share = WalletShare::try_from(augmented_state_machine::split_key_output(
+        identity_provider,
+        local_key,
+    )?)?;
+
+    Ok((wallet_share, binding))
+}
+
+/// Given a local key (e.g from a migrated wallet share) and identity bindings for all parties,
+/// returns verifying keys for all parties (in party index order) if all bindings are valid,
+/// or an appropriate error otherwise.
+pub fn verify_identity_bindings(
+    local_key: &LocalKey<Secp256k1>,
+    bindings: &[IdentityBinding],
+) -> Result<Vec<VerifyingKey>, wamu_core::Error> {
+    (1..=local_key.n)
+        .map(|idx| {
+            // Each party must have exactly one binding.
+            let mut party_bindings = bindings.iter().filter(|binding| binding.idx == idx);
+            match (party_bindings.next(), party_bindings.next()) {
+                (Some(binding), None) => {
+                    // Binding signature must be valid.
+                    wamu_core::wrappers::verify_request_with_signature(
+                        &binding_hash(local_key, idx),
+                        &binding.verifying_key,
+                        &binding.signature,
+                        std::slice::from_ref(&binding.verifying_key),
+                    )?;
+                    Ok(binding.verifying_key.clone())
+                }
+                _ => Err(wamu_core::Error::UnauthorizedParty),
+            }
+        })
+        .collect()
+}
+
+// Binds the party index to the group public key and the party's Paillier encryption key,
+// which are shared by all parties of the existing deployment.
+fn binding_hash(local_key: &LocalKey<Secp256k1>, idx: u16) -> Vec<u8> {
+    use curv::arithmetic::Converter;
+    use sha2::{digest::Update, Digest};
+    let paillier_n = local_key
+        .paillier_key_vec
+        .get(idx as usize - 1)
+        .map(|ek| ek.n.to_bytes())
+        .unwrap_or_default();
+    let hasher = sha2::Sha256::new();
+    hasher
+        .chain(idx.to_be_bytes())
+        .chain(local_key.public_key().to_bytes(true).deref())
+        .chain(paillier_n)
+        .finalize()
+        .deref()
+        .to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen::tests::simulate_keygen;
+    use curv::elliptic::curves::Scalar;
+    use wamu_core::test_utils::MockECDSAIdentityProvider;
+
+    #[test]
+    fn migration_works() {
+        // Generates plain GG20 local keys (i.e with the secret share set) for test parameters.
+        let (aug_keys, identity_providers) = simulate_keygen(1, 3);
+        let local_keys: Vec<LocalKey<Secp256k1>> = aug_keys
+            .into_iter()
+            .enumerate()
+            .map(|(idx, aug_key)| {
+                let (signing_share, sub_share) = aug_key.extra.as_ref().unwrap();
+                let secret_share = wamu_core::share_split_reconstruct::reconstruct(
+                    signing_share,
+                    sub_share,
+                    &identity_providers[idx],
+                )
+                .unwrap();
+                let mut local_key = aug_key.base;
+                local_key.keys_linear.x_i =
+                    Scalar::<Secp256k1>::from_bytes(&secret_share.to_be_bytes()).unwrap();
+                local_key
+            })
+            .collect();
+
+        // Migrates local keys with new identity providers.
+        let new_identity_providers: Vec<MockECDSAIdentityProvider> = (0..local_keys.len())
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let (wallet_shares, bindings): (Vec<WalletShare>, Vec<IdentityBinding>) = local_keys
+            .iter()
+            .zip(new_identity_providers.iter())
+            .map(|(local_key, identity_provider)| {
+                migrate(identity_provider, local_key.clone()).unwrap()
+            })
+            .unzip();
+
+        // Verifies that the secret shares were split and cleared/zerorized.
+        for (idx, wallet_share) in wallet_shares.iter().enumerate() {
+            assert_eq!(
+                wallet_share.local_key.keys_linear.x_i,
+                Scalar::<Secp256k1>::zero()
+            );
+            assert_eq!(
+                Scalar::<Secp256k1>::from_bytes(
+                    &wamu_core::share_split_reconstruct::reconstruct(
+                        &wallet_share.signing_share,
+                        &wallet_share.sub_share,
+                        &new_identity_providers[idx],
+                    )
+                    .unwrap()
+                    .to_be_bytes()
+                )
+                .unwrap(),
+                local_keys[idx].keys_linear.x_i
+            );
+        }
+
+        // Verifies identity bindings and establishes the list of verified parties.
+        let verified_parties =
+            verify_identity_bindings(&wallet_shares[0].local_key, &bindings).unwrap();
+        let expected_parties: Vec<VerifyingKey> = new_identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        assert_eq!(verified_parties, expected_parties);
+
+        // Missing, duplicate and forged bindings are rejected.
+        let mut forged_binding = bindings[1].clone();
+        forged_binding.idx = 1;
+        for bad_bindings in [
+            vec![bindings[0].clone(), bindings[1].clone()],
+            vec![
+                bindings[0].clone(),
+                bindings[0].clone(),
+                bindings[2].clone(),
+            ],
+            vec![forged_binding, bindings[1].clone(), bindings[2].clone()],
+        ] {
+            assert!(verify_identity_bindings(&wallet_shares[0].local_key, &bad_bindings).is_err());
+        }
+    }
+}
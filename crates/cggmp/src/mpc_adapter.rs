@@ -0,0 +1,129 @@
+//! Compatibility layer for the [`Mpc`](round_based_v2::Mpc) interface of `round-based` v0.2.
+//!
+//! Drives any [`StateMachine`](StateMachine) (including augmented state machines) of `round-based` v0.1
+//! with the delivery of an `Mpc` party, so augmented protocols can be integrated into projects that use either version.
+//!
+//! **NOTE:** `round-based` v0.2 party indices are zero-based, while `round-based` v0.1 party indices are one-based,
+//! so the index of party `i` in the `Mpc` interface is `i + 1` for the state machine.
+
+use futures::{SinkExt, StreamExt};
+use round_based::{Msg, StateMachine};
+use round_based_v2::{
+    Delivery, Incoming, MessageDestination, MessageType, Mpc, MpcParty, Outgoing,
+};
+
+use crate::async_session::{run_session, SessionError};
+
+/// A transport error of an `Mpc` party.
+#[derive(Debug)]
+pub enum MpcTransportError<R, S> {
+    /// An error receiving an incoming message.
+    Receive(R),
+    /// An error sending an outgoing message.
+    Send(S),
+}
+
+/// Given a state machine and an `Mpc` party (i.e with the delivery for the state machine's messages),
+/// drives the state machine until it finishes and returns its output, or an appropriate error otherwise.
+///
+/// **NOTE:** See [`run_session`] for details about round timeouts and cancellation.
+pub async fn run_mpc<SM, M>(
+    state_machine: &mut SM,
+    party: M,
+) -> Result<SM::Output, SessionError<SM::Err, MpcTransportError<M::ReceiveError, M::SendError>>>
+where
+    SM: StateMachine,
+    M: Mpc<ProtocolMessage = SM::MessageBody>,
+{
+    let MpcParty { delivery, .. } = party.into_party();
+    let (incoming, outgoing) = delivery.split();
+    let party_ind = state_machine.party_ind();
+
+    // Converts incoming messages to one-based `round-based` v0.1 messages.
+    let incoming = incoming.map(move |incoming| {
+        incoming
+            .map(|incoming| from_incoming(incoming, party_ind))
+            .map_err(MpcTransportError::Receive)
+    });
+    // Converts outgoing `round-based` v0.1 messages to zero-based messages.
+    let outgoing = outgoing
+        .sink_map_err(MpcTransportError::Send)
+        .with(|msg| futures::future::ready(Ok(into_outgoing(msg))));
+
+    run_session(state_machine, incoming, Box::pin(outgoing)).await
+}
+
+/// Converts an incoming `round-based` v0.2 message to a `round-based` v0.1 message for the local party.
+fn from_incoming<B>(incoming: Incoming<B>, party_ind: u16) -> Msg<B> {
+    Msg {
+        sender: incoming.sender + 1,
+        receiver: match incoming.msg_type {
+            MessageType::Broadcast => None,
+            MessageType::P2P => Some(party_ind),
+        },
+        body: incoming.msg,
+    }
+}
+
+/// Converts an outgoing `round-based` v0.1 message to a `round-based` v0.2 message.
+fn into_outgoing<B>(msg: Msg<B>) -> Outgoing<B> {
+    Outgoing {
+        recipient: match msg.receiver {
+            Some(receiver) => MessageDestination::OneParty(receiver - 1),
+            None => MessageDestination::AllParties,
+        },
+        msg: msg.body,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen::AugmentedKeyGen;
+    use round_based_v2::simulation::Simulation;
+    use wamu_core::crypto::VerifyingKey;
+    use wamu_core::test_utils::MockECDSAIdentityProvider;
+    use wamu_core::IdentityProvider;
+
+    #[tokio::test]
+    async fn run_mpc_works() {
+        // Creates identity providers and verifying keys for all parties.
+        let (threshold, n_parties) = (1, 3);
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (1..=n_parties)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+
+        // Runs key generation for all parties via the `Mpc` interface.
+        let mut simulation = Simulation::<
+            <AugmentedKeyGen<'static, MockECDSAIdentityProvider> as StateMachine>::MessageBody,
+        >::new();
+        let sessions = futures::future::join_all((0..n_parties as usize).map(|idx| {
+            let party = simulation.add_party();
+            let identity_provider = &identity_providers[idx];
+            let verifying_keys = &verifying_keys;
+            async move {
+                let mut keygen = AugmentedKeyGen::new(
+                    identity_provider,
+                    verifying_keys,
+                    (idx + 1) as u16,
+                    threshold,
+                    n_parties,
+                )
+                .unwrap();
+                run_mpc(&mut keygen, party).await
+            }
+        }))
+        .await;
+
+        // Verifies that all parties generated the same public key.
+        let outputs: Vec<_> = sessions.into_iter().map(Result::unwrap).collect();
+        assert_eq!(outputs.len(), n_parties as usize);
+        for output in &outputs {
+            assert_eq!(output.base.public_key(), outputs[0].base.public_key());
+        }
+    }
+}
@@ -0,0 +1,266 @@
+//! Watch-only (i.e observer) parties.
+//!
+//! Observers are on the roster but hold no shares and never contribute to protocols
+//! (e.g compliance monitors and auditors embedded in institutional wallets).
+//! They receive copies of all round messages of sessions that are authenticated with [`MessageAuthentication`](crate::MessageAuthentication),
+//! verify them and the session transcript, and can veto quorum approved requests
+//! (see [`QuorumApproval::with_observers`](crate::QuorumApproval::with_observers)).
+
+use round_based::Msg;
+use std::collections::{BTreeMap, HashMap};
+use wamu_core::crypto::VerifyingKey;
+use wamu_core::{IdentityAuthedRequestPayload, IdentityProvider};
+
+use crate::abort_evidence::{Offense, SignedMessage};
+use crate::augmented_state_machine::AugmentedType;
+use crate::message_auth;
+use crate::message_auth::MessageAuthParams;
+use crate::quorum_approval;
+
+/// A watch-only party that verifies all round messages and the transcript of an authenticated session.
+pub struct Observer<'a, B> {
+    /// Verifying keys for all parties.
+    verified_parties: &'a [VerifyingKey],
+    /// Identifier of the session.
+    session_id: [u8; 32],
+    /// Deterministic encoder for message bodies (i.e the same encoder used by the parties).
+    encode_body: fn(&B) -> Vec<u8>,
+    /// Observed messages (verified once the session is finished).
+    messages: Vec<SignedMessage>,
+    /// Last offense that was detected (if any).
+    offense: Option<Offense>,
+}
+
+impl<'a, B> Observer<'a, B> {
+    /// Given a list of verifying keys for all parties, the session identifier and a deterministic encoder for message bodies,
+    /// returns an observer for an authenticated session.
+    pub fn new(
+        verified_parties: &'a [VerifyingKey],
+        session_id: [u8; 32],
+        encode_body: fn(&B) -> Vec<u8>,
+    ) -> Self {
+        Self {
+            verified_parties,
+            session_id,
+            encode_body,
+            messages: Vec::new(),
+            offense: None,
+        }
+    }
+
+    /// Records a copy of a round message (including peer-to-peer messages) of the session.
+    ///
+    /// **NOTE:** Messages are verified by [`finish`](Self::finish), because the transcript of all previous rounds
+    /// is required to verify them and observers don't know when a round is complete.
+    pub fn observe(
+        &mut self,
+        msg: &Msg<AugmentedType<B, MessageAuthParams>>,
+    ) -> Result<(), ObserverError> {
+        let params = msg
+            .body
+            .extra
+            .as_ref()
+            .ok_or(ObserverError::MissingParams { sender: msg.sender })?;
+        self.messages.push(SignedMessage {
+            sender: msg.sender,
+            receiver: msg.receiver,
+            round: params.round,
+            session_id: self.session_id,
+            // Set during verification.
+            transcript_hash: Vec::new(),
+            body: (self.encode_body)(&msg.body.base),
+            verifying_key: params.identity_auth.verifying_key.clone(),
+            signature: params.identity_auth.verifying_signature.clone(),
+        });
+        Ok(())
+    }
+
+    /// Verifies all observed messages (in round order) and
+    /// returns the transcript hash of the session (i.e the same transcript hash that's output by all honest parties),
+    /// or an appropriate error otherwise.
+    pub fn finish(&mut self) -> Result<Vec<u8>, ObserverError> {
+        let mut transcript = vec![self.session_id.to_vec()];
+        let mut broadcasts = BTreeMap::new();
+        let mut received: HashMap<(u16, u16, bool), SignedMessage> = HashMap::new();
+        let mut messages = self.messages.clone();
+        messages.sort_by_key(|msg| msg.round);
+        for mut msg in messages {
+            // Verifies the message signature.
+            msg.transcript_hash =
+                message_auth::transcript_hash(&mut transcript, &broadcasts, msg.round);
+            // NOTE: Failed message authentication isn't an offense (i.e it's not recorded as abort evidence),
+            // because anyone can produce an invalid signature on behalf of any party.
+            msg.verify(self.verified_parties).map_err(|error| {
+                ObserverError::InvalidAuthentication {
+                    sender: msg.sender,
+                    round: msg.round,
+                    error,
+                }
+            })?;
+
+            // Ignores exact duplicates and rejects conflicting messages (i.e equivocation).
+            let is_broadcast = msg.receiver.is_none();
+            match received.get(&(msg.sender, msg.round, is_broadcast)) {
+                Some(prev_msg) if prev_msg.body == msg.body => (),
+                Some(prev_msg) => {
+                    let (sender, round) = (msg.sender, msg.round);
+                    self.offense = Some(Offense::Equivocation(prev_msg.clone(), msg));
+                    return Err(ObserverError::Equivocation { sender, round });
+                }
+                None => {
+                    if is_broadcast {
+                        broadcasts
+                            .insert((msg.round, msg.sender), message_auth::body_hash(&msg.body));
+                    }
+                    received.insert((msg.sender, msg.round, is_broadcast), msg);
+                }
+            }
+        }
+
+        // Returns the transcript hash of all broadcast messages of the session.
+        let last_round = broadcasts.keys().last().map_or(0, |(round, _)| round + 1);
+        Ok(message_auth::transcript_hash(
+            &mut transcript,
+            &broadcasts,
+            last_round,
+        ))
+    }
+
+    /// Returns the last offense that was detected (if any).
+    pub fn offense(&self) -> Option<&Offense> {
+        self.offense.as_ref()
+    }
+}
+
+/// Given a quorum approved request and the identity provider of an observer,
+/// returns a signed veto for the request.
+///
+/// **NOTE:** Observers aren't indexed parties, so the veto can be sent with any sender index.
+pub fn veto_request(
+    request: &IdentityAuthedRequestPayload,
+    identity_provider: &impl IdentityProvider,
+) -> quorum_approval::Message {
+    quorum_approval::Message::Cancel(wamu_core::time_lock::cancel(request, identity_provider))
+}
+
+/// An observer error.
+#[derive(Debug)]
+pub enum ObserverError {
+    /// A message without message authentication parameters.
+    MissingParams { sender: u16 },
+    /// A message with an invalid signature or from an unauthorized party.
+    InvalidAuthentication {
+        sender: u16,
+        round: u16,
+        error: wamu_core::Error,
+    },
+    /// Conflicting messages from the same sender for the same round.
+    Equivocation { sender: u16, round: u16 },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen::AugmentedKeyGen;
+    use crate::MessageAuthentication;
+    use round_based::StateMachine;
+    use wamu_core::test_utils::MockECDSAIdentityProvider;
+
+    // Encodes augmented key generation message bodies (deterministically) for tests.
+    fn encode_keygen_body(
+        body: &<AugmentedKeyGen<'_, MockECDSAIdentityProvider> as StateMachine>::MessageBody,
+    ) -> Vec<u8> {
+        format!("{:?}", body.base).into_bytes()
+    }
+
+    #[test]
+    fn observer_works() {
+        // Creates identity providers and verifying keys for all parties.
+        let (threshold, n_parties) = (1, 3);
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (1..=n_parties)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+
+        // Runs authenticated key generation and routes copies of all messages to the observer.
+        let session_id = [1; 32];
+        let mut parties: Vec<_> = identity_providers
+            .iter()
+            .enumerate()
+            .map(|(i, identity_provider)| {
+                MessageAuthentication::new(
+                    AugmentedKeyGen::new(
+                        identity_provider,
+                        &verifying_keys,
+                        i as u16 + 1,
+                        threshold,
+                        n_parties,
+                    )
+                    .unwrap(),
+                    identity_provider,
+                    &verifying_keys,
+                    session_id,
+                    encode_keygen_body,
+                )
+            })
+            .collect();
+        let mut observer = Observer::new(&verifying_keys, session_id, encode_keygen_body);
+        let mut observed_msgs = Vec::new();
+        let mut outputs: Vec<_> = parties.iter().map(|_| None).collect();
+        while outputs.iter().any(Option::is_none) {
+            for i in 0..parties.len() {
+                if parties[i].wants_to_proceed() {
+                    parties[i].proceed().unwrap();
+                }
+                for msg in parties[i].message_queue().split_off(0) {
+                    observer.observe(&msg).unwrap();
+                    for (j, party) in parties.iter_mut().enumerate() {
+                        let receiver = j as u16 + 1;
+                        if receiver != msg.sender && msg.receiver.map_or(true, |it| it == receiver)
+                        {
+                            party.handle_incoming(msg.clone()).unwrap();
+                        }
+                    }
+                    observed_msgs.push(msg);
+                }
+                if outputs[i].is_none() {
+                    outputs[i] = parties[i].pick_output().map(Result::unwrap);
+                }
+            }
+        }
+
+        // Verifies that the observer verified the session and agrees on the transcript hash.
+        let transcript_hash = observer.finish().unwrap();
+        for output in outputs.into_iter().flatten() {
+            assert_eq!(output.extra, Some(transcript_hash.clone()));
+        }
+        assert!(observer.offense().is_none());
+
+        // Verifies that the observer rejects sessions with another session identifier.
+        let mut observer = Observer::new(&verifying_keys, [2; 32], encode_keygen_body);
+        for msg in observed_msgs.iter() {
+            observer.observe(msg).unwrap();
+        }
+        assert!(matches!(
+            observer.finish(),
+            Err(ObserverError::InvalidAuthentication { .. })
+        ));
+
+        // Verifies that the observer detects tampered messages.
+        let mut observer = Observer::new(&verifying_keys, session_id, encode_keygen_body);
+        for msg in observed_msgs.iter() {
+            observer.observe(msg).unwrap();
+        }
+        let mut tampered_msg = observed_msgs[0].clone();
+        tampered_msg.sender = tampered_msg.sender % n_parties + 1;
+        observer.observe(&tampered_msg).unwrap();
+        assert!(matches!(
+            observer.finish(),
+            Err(ObserverError::InvalidAuthentication { .. })
+        ));
+        assert!(observer.offense().is_none());
+    }
+}
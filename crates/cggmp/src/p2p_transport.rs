@@ -0,0 +1,439 @@
+//! [libp2p](https://libp2p.io/) transport integration for serverless peer-to-peer protocol sessions.
+//!
+//! Broadcast messages are published to a [gossipsub](https://github.com/libp2p/specs/tree/master/pubsub/gossipsub) topic for the session,
+//! while p2p messages are delivered with a request-response protocol.
+//!
+//! Parties are identified by their identity verifying keys (i.e each party binds its libp2p peer id to its verifying key with a [`PeerBinding`]),
+//! so messages are only accepted from the peer ids bound to the verifying keys of other participants.
+//!
+//! **NOTE:** Swarms (and their underlying libp2p transports) are built by the application,
+//! so routing via a SOCKS5 proxy (e.g Tor, see [`Socks5Proxy`](crate::Socks5Proxy)) requires a proxying libp2p transport.
+
+use async_trait::async_trait;
+use futures::channel::mpsc;
+use futures::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, SinkExt, StreamExt};
+use libp2p::gossipsub::{self, IdentTopic, MessageAuthenticity};
+use libp2p::identity::Keypair;
+use libp2p::request_response::{self, ProtocolSupport};
+use libp2p::swarm::{NetworkBehaviour, SwarmEvent};
+use libp2p::{PeerId, Swarm};
+use round_based::{Msg, StateMachine};
+use std::collections::{BTreeMap, BTreeSet};
+use wamu_core::crypto::{Signature, VerifyingKey};
+use wamu_core::IdentityProvider;
+
+use crate::async_session::{run_session, SessionError};
+use crate::transport::{self, MAX_FRAME_BODY_LEN};
+
+/// The request-response protocol for p2p messages.
+const P2P_PROTOCOL: &str = "/wamu/p2p/1.0.0";
+
+/// The maximum length of a frame (i.e a frame header and a message body).
+const MAX_FRAME_LEN: usize = MAX_FRAME_BODY_LEN + 9;
+
+/// A libp2p transport error.
+#[derive(Debug)]
+pub enum Libp2pTransportError {
+    /// A message that couldn't be encoded or decoded.
+    Encoding,
+    /// A party index that's not part of the session (e.g the receiver of an outgoing message).
+    UnknownParty(u16),
+    /// The session topic couldn't be subscribed to.
+    Subscription,
+    /// A broadcast message couldn't be published.
+    Publish,
+    /// A p2p message couldn't be delivered to the party.
+    Delivery(u16),
+}
+
+/// A binding of a libp2p peer id to an identity verifying key.
+#[derive(Debug, Clone)]
+pub struct PeerBinding {
+    /// The peer id of the party.
+    pub peer_id: PeerId,
+    /// Verifying key of the party.
+    pub verifying_key: VerifyingKey,
+    /// Signature of the binding statement.
+    pub signature: Signature,
+}
+
+/// Given the identity provider of the party and its libp2p peer id,
+/// returns a peer binding for sharing with the other parties.
+pub fn bind_peer(identity_provider: &impl IdentityProvider, peer_id: &PeerId) -> PeerBinding {
+    let (verifying_key, signature) = wamu_core::wrappers::initiate_request_with_signature(
+        &binding_bytes(peer_id),
+        identity_provider,
+    );
+    PeerBinding {
+        peer_id: *peer_id,
+        verifying_key,
+        signature,
+    }
+}
+
+/// Given verifying keys for all parties (in party index order) and peer bindings for all other parties,
+/// returns the peer ids for the parties with a valid binding (by party index),
+/// or an appropriate error for bindings of unknown parties or with invalid signatures.
+pub fn verify_peer_bindings(
+    verified_parties: &[VerifyingKey],
+    bindings: &[PeerBinding],
+) -> Result<BTreeMap<u16, PeerId>, wamu_core::Error> {
+    bindings
+        .iter()
+        .map(|binding| {
+            // Binding signature must be valid and signed by a verified party.
+            wamu_core::wrappers::verify_request_with_signature(
+                &binding_bytes(&binding.peer_id),
+                &binding.verifying_key,
+                &binding.signature,
+                verified_parties,
+            )?;
+            let idx = verified_parties
+                .iter()
+                .position(|verifying_key| verifying_key == &binding.verifying_key)
+                .ok_or(wamu_core::Error::UnauthorizedParty)?;
+            Ok((idx as u16 + 1, binding.peer_id))
+        })
+        .collect()
+}
+
+// Returns the binding statement for a peer id.
+fn binding_bytes(peer_id: &PeerId) -> Vec<u8> {
+    [b"wamu/libp2p/peer-binding".as_slice(), &peer_id.to_bytes()].concat()
+}
+
+/// The libp2p network behaviour for protocol sessions (i.e gossipsub for broadcast messages and request-response for p2p messages).
+#[derive(NetworkBehaviour)]
+pub struct Behaviour {
+    gossipsub: gossipsub::Behaviour,
+    request_response: request_response::Behaviour<FrameCodec>,
+}
+
+impl Behaviour {
+    /// Given the libp2p keypair of the party, returns the network behaviour for protocol sessions
+    /// (e.g for building a [`Swarm`] with any libp2p transport).
+    pub fn new(keypair: &Keypair) -> Self {
+        let config = gossipsub::ConfigBuilder::default()
+            .max_transmit_size(MAX_FRAME_LEN)
+            .build()
+            .expect("max transmit size is always valid");
+        Self {
+            gossipsub: gossipsub::Behaviour::new(
+                MessageAuthenticity::Signed(keypair.clone()),
+                config,
+            )
+            .expect("signed message authenticity is always valid"),
+            request_response: request_response::Behaviour::new(
+                FrameCodec,
+                [(P2P_PROTOCOL, ProtocolSupport::Full)],
+                request_response::Config::default(),
+            ),
+        }
+    }
+}
+
+/// A libp2p protocol session (i.e the index of the party, the session identifier and the peer ids of all other parties).
+#[derive(Debug, Clone)]
+pub struct Libp2pSession {
+    idx: u16,
+    session_id: [u8; 32],
+    peers: BTreeMap<u16, PeerId>,
+}
+
+impl Libp2pSession {
+    /// Given the index of the party, the session identifier and the peer ids of all other parties
+    /// (e.g from [`verify_peer_bindings`]), returns a libp2p protocol session.
+    pub fn new(idx: u16, session_id: [u8; 32], peers: BTreeMap<u16, PeerId>) -> Self {
+        Self {
+            idx,
+            session_id,
+            peers,
+        }
+    }
+
+    /// Returns the gossipsub topic for broadcast messages of the session.
+    pub fn topic(&self) -> IdentTopic {
+        IdentTopic::new(format!(
+            "wamu/session/{}",
+            wamu_core::utils::to_hex(&self.session_id)
+        ))
+    }
+
+    // Returns the party index of the peer (if it's part of the session).
+    fn party_idx(&self, peer_id: &PeerId) -> Option<u16> {
+        self.peers
+            .iter()
+            .find_map(|(idx, other)| (other == peer_id).then_some(*idx))
+    }
+
+    // Returns the message for a frame from the peer, or `None` if the frame isn't valid
+    // or wasn't sent by the peer to this party (or everyone).
+    fn open<B>(
+        &self,
+        peer_id: &PeerId,
+        frame: &[u8],
+        decode: fn(&[u8]) -> Option<B>,
+    ) -> Option<Msg<B>> {
+        let msg = transport::decode_frame(frame, decode).ok()?;
+        (self.party_idx(peer_id) == Some(msg.sender)
+            && msg.receiver.map_or(true, |it| it == self.idx))
+        .then_some(msg)
+    }
+}
+
+/// Given a state machine, a swarm with the protocol session network behaviour, a libp2p protocol session
+/// and a codec (i.e encoder and decoder) for message bodies, drives the state machine until it finishes and returns its output,
+/// or an appropriate error otherwise.
+///
+/// Outgoing messages are queued until all other parties are connected and subscribed to the session topic,
+/// and messages from peers that aren't part of the session are ignored.
+///
+/// **NOTE:** The swarm must be connected to (or able to dial) all other parties,
+/// and see [`run_session`] for details about round timeouts and cancellation.
+pub async fn run_libp2p_session<SM>(
+    state_machine: &mut SM,
+    swarm: &mut Swarm<Behaviour>,
+    session: &Libp2pSession,
+    encode: fn(&SM::MessageBody) -> Vec<u8>,
+    decode: fn(&[u8]) -> Option<SM::MessageBody>,
+) -> Result<SM::Output, SessionError<SM::Err, Libp2pTransportError>>
+where
+    SM: StateMachine,
+{
+    let topic = session.topic();
+    swarm
+        .behaviour_mut()
+        .gossipsub
+        .subscribe(&topic)
+        .map_err(|_| SessionError::Transport(Libp2pTransportError::Subscription))?;
+
+    let (incoming_tx, incoming_rx) = mpsc::unbounded();
+    let (outgoing_tx, mut outgoing_rx) = mpsc::unbounded::<Msg<SM::MessageBody>>();
+    let outgoing_tx = outgoing_tx.sink_map_err(|_| Libp2pTransportError::Publish);
+
+    // Delivers outgoing messages and incoming swarm events until the session is finished.
+    let network = async {
+        let mut subscribed = BTreeSet::new();
+        let mut pending = Vec::new();
+        loop {
+            let result = futures::select! {
+                msg = outgoing_rx.next() => match msg {
+                    Some(msg) => transport::encode_frame(&msg, encode)
+                        .map_err(|_| Libp2pTransportError::Encoding)
+                        .and_then(|frame| {
+                            if subscribed.len() == session.peers.len() {
+                                deliver(swarm, session, &topic, msg.receiver, frame)
+                            } else {
+                                pending.push((msg.receiver, frame));
+                                Ok(())
+                            }
+                        }),
+                    None => break,
+                },
+                event = swarm.select_next_some() => match event {
+                    SwarmEvent::Behaviour(BehaviourEvent::Gossipsub(gossipsub::Event::Subscribed { peer_id, topic: topic_hash })) => {
+                        if topic_hash == topic.hash() && session.party_idx(&peer_id).is_some() {
+                            subscribed.insert(peer_id);
+                        }
+                        // Delivers queued messages once all other parties are subscribed.
+                        if subscribed.len() == session.peers.len() {
+                            pending
+                                .drain(..)
+                                .try_for_each(|(receiver, frame)| deliver(swarm, session, &topic, receiver, frame))
+                        } else {
+                            Ok(())
+                        }
+                    }
+                    SwarmEvent::Behaviour(BehaviourEvent::Gossipsub(gossipsub::Event::Message { message, .. })) => {
+                        // Broadcast messages must be signed by the sending party and addressed to everyone.
+                        if let Some(msg) = message
+                            .source
+                            .filter(|_| message.topic == topic.hash())
+                            .and_then(|source| session.open(&source, &message.data, decode))
+                            .filter(|msg| msg.receiver.is_none())
+                        {
+                            let _ = incoming_tx.unbounded_send(Ok(msg));
+                        }
+                        Ok(())
+                    }
+                    SwarmEvent::Behaviour(BehaviourEvent::RequestResponse(request_response::Event::Message {
+                        peer,
+                        message: request_response::Message::Request { request, channel, .. },
+                        ..
+                    })) => {
+                        // P2P messages must be sent by the sending party and addressed to this party.
+                        if let Some(msg) = session
+                            .open(&peer, &request, decode)
+                            .filter(|msg| msg.receiver.is_some())
+                        {
+                            let _ = swarm.behaviour_mut().request_response.send_response(channel, ());
+                            let _ = incoming_tx.unbounded_send(Ok(msg));
+                        }
+                        Ok(())
+                    }
+                    SwarmEvent::Behaviour(BehaviourEvent::RequestResponse(request_response::Event::OutboundFailure { peer, .. })) => {
+                        Err(Libp2pTransportError::Delivery(session.party_idx(&peer).unwrap_or_default()))
+                    }
+                    _ => Ok(()),
+                },
+            };
+            if let Err(error) = result {
+                let _ = incoming_tx.unbounded_send(Err(error));
+            }
+        }
+    };
+
+    let protocol = run_session(state_machine, incoming_rx, outgoing_tx);
+    futures::pin_mut!(protocol, network);
+    match futures::future::select(protocol, network).await {
+        futures::future::Either::Left((output, _)) => output,
+        // The network only stops after the session is finished (i.e drops its outgoing sink).
+        futures::future::Either::Right(_) => Err(SessionError::IncomingClosed),
+    }
+}
+
+// Delivers an outgoing message frame (i.e publishes broadcast messages to the session topic
+// and sends p2p messages as requests to the receiver).
+fn deliver(
+    swarm: &mut Swarm<Behaviour>,
+    session: &Libp2pSession,
+    topic: &IdentTopic,
+    receiver: Option<u16>,
+    frame: Vec<u8>,
+) -> Result<(), Libp2pTransportError> {
+    match receiver {
+        Some(receiver) => {
+            let peer_id = session
+                .peers
+                .get(&receiver)
+                .ok_or(Libp2pTransportError::UnknownParty(receiver))?;
+            swarm
+                .behaviour_mut()
+                .request_response
+                .send_request(peer_id, frame);
+            Ok(())
+        }
+        None => swarm
+            .behaviour_mut()
+            .gossipsub
+            .publish(topic.clone(), frame)
+            .map(|_| ())
+            .map_err(|_| Libp2pTransportError::Publish),
+    }
+}
+
+/// A length-prefixed codec for p2p message frames (i.e requests are frames and responses are empty acknowledgements).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrameCodec;
+
+#[async_trait]
+impl request_response::Codec for FrameCodec {
+    type Protocol = &'static str;
+    type Request = Vec<u8>;
+    type Response = ();
+
+    async fn read_request<T>(
+        &mut self,
+        _: &Self::Protocol,
+        io: &mut T,
+    ) -> std::io::Result<Self::Request>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        let mut len = [0u8; 4];
+        io.read_exact(&mut len).await?;
+        let len = u32::from_be_bytes(len) as usize;
+        if len > MAX_FRAME_LEN {
+            return Err(std::io::ErrorKind::InvalidData.into());
+        }
+        let mut frame = vec![0u8; len];
+        io.read_exact(&mut frame).await?;
+        Ok(frame)
+    }
+
+    async fn read_response<T>(
+        &mut self,
+        _: &Self::Protocol,
+        _: &mut T,
+    ) -> std::io::Result<Self::Response>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        Ok(())
+    }
+
+    async fn write_request<T>(
+        &mut self,
+        _: &Self::Protocol,
+        io: &mut T,
+        frame: Self::Request,
+    ) -> std::io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        io.write_all(&(frame.len() as u32).to_be_bytes()).await?;
+        io.write_all(&frame).await?;
+        io.close().await
+    }
+
+    async fn write_response<T>(
+        &mut self,
+        _: &Self::Protocol,
+        io: &mut T,
+        _: Self::Response,
+    ) -> std::io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        io.close().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wamu_core::test_utils::MockECDSAIdentityProvider;
+
+    #[test]
+    fn peer_bindings_work() {
+        // Creates identity providers, verifying keys and peer ids for all parties.
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (0..3)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let peer_ids: Vec<PeerId> = (0..3).map(|_| PeerId::random()).collect();
+
+        // Verifies that valid bindings are mapped to party indices.
+        let bindings: Vec<PeerBinding> = identity_providers
+            .iter()
+            .zip(&peer_ids)
+            .map(|(identity_provider, peer_id)| bind_peer(identity_provider, peer_id))
+            .collect();
+        let peers = verify_peer_bindings(&verifying_keys, &bindings[1..]).unwrap();
+        assert_eq!(peers, BTreeMap::from([(2, peer_ids[1]), (3, peer_ids[2])]));
+
+        // Verifies that messages are only accepted from the bound peer ids.
+        let session = Libp2pSession::new(1, [1u8; 32], peers);
+        let frame = transport::encode_frame(
+            &Msg {
+                sender: 2,
+                receiver: None,
+                body: vec![1u8],
+            },
+            |body: &Vec<u8>| body.clone(),
+        )
+        .unwrap();
+        let decode = |bytes: &[u8]| Some(bytes.to_vec());
+        assert!(session.open(&peer_ids[1], &frame, decode).is_some());
+        assert!(session.open(&peer_ids[2], &frame, decode).is_none());
+
+        // Verifies that bindings with a different peer id or from unknown parties are rejected.
+        let mut forged_binding = bindings[1].clone();
+        forged_binding.peer_id = PeerId::random();
+        assert!(verify_peer_bindings(&verifying_keys, &[forged_binding]).is_err());
+        assert!(verify_peer_bindings(&verifying_keys[..1], &bindings[1..2]).is_err());
+    }
+}
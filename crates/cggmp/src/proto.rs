@@ -0,0 +1,153 @@
+//! [Protobuf](https://protobuf.dev/) codecs for augmented protocol messages
+//! (i.e hand-written `prost` messages for the `proto/wamu_cggmp.proto` schema).
+//!
+//! **NOTE:** Base CGGMP20 message bodies are opaque bytes in the wrapper, so they can be encoded with any codec.
+
+use round_based::Msg;
+use wamu_core::proto::{Signature, VerifyingKey};
+
+use crate::augmented_state_machine::{self, AugmentedType};
+
+/// Additional parameters for identity authentication.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct IdentityAuthParams {
+    #[prost(message, optional, tag = "1")]
+    pub verifying_key: Option<VerifyingKey>,
+    #[prost(message, optional, tag = "2")]
+    pub verifying_signature: Option<Signature>,
+}
+
+/// An augmented protocol message (i.e a wrapped CGGMP20 message and its identity authentication parameters).
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct AugmentedMessage {
+    #[prost(uint32, tag = "1")]
+    pub sender: u32,
+    #[prost(uint32, optional, tag = "2")]
+    pub receiver: Option<u32>,
+    #[prost(bytes = "vec", tag = "3")]
+    pub base: Vec<u8>,
+    #[prost(message, optional, tag = "4")]
+    pub extra: Option<IdentityAuthParams>,
+}
+
+impl AugmentedMessage {
+    /// Given an augmented protocol message and an encoder for the base message body, returns the wrapped message.
+    pub fn from_msg<T>(
+        msg: Msg<AugmentedType<T, augmented_state_machine::IdentityAuthParams>>,
+        encode_base: impl FnOnce(&T) -> Vec<u8>,
+    ) -> Self {
+        Self {
+            sender: msg.sender as u32,
+            receiver: msg.receiver.map(u32::from),
+            base: encode_base(&msg.body.base),
+            extra: msg.body.extra.map(Into::into),
+        }
+    }
+
+    /// Given a decoder for the base message body,
+    /// returns the augmented protocol message or an encoding error for invalid messages.
+    pub fn into_msg<T, E>(
+        self,
+        decode_base: impl FnOnce(&[u8]) -> Result<T, E>,
+    ) -> Result<Msg<AugmentedType<T, augmented_state_machine::IdentityAuthParams>>, wamu_core::Error>
+    {
+        Ok(Msg {
+            sender: u16::try_from(self.sender).map_err(|_| wamu_core::Error::Encoding)?,
+            receiver: self
+                .receiver
+                .map(u16::try_from)
+                .transpose()
+                .map_err(|_| wamu_core::Error::Encoding)?,
+            body: AugmentedType {
+                base: decode_base(&self.base).map_err(|_| wamu_core::Error::Encoding)?,
+                extra: self.extra.map(TryInto::try_into).transpose()?,
+            },
+        })
+    }
+}
+
+impl From<augmented_state_machine::IdentityAuthParams> for IdentityAuthParams {
+    fn from(value: augmented_state_machine::IdentityAuthParams) -> Self {
+        Self {
+            verifying_key: Some(value.verifying_key.into()),
+            verifying_signature: Some(value.verifying_signature.into()),
+        }
+    }
+}
+
+impl TryFrom<IdentityAuthParams> for augmented_state_machine::IdentityAuthParams {
+    type Error = wamu_core::Error;
+
+    fn try_from(value: IdentityAuthParams) -> Result<Self, Self::Error> {
+        Ok(Self {
+            verifying_key: value
+                .verifying_key
+                .ok_or(wamu_core::Error::Encoding)?
+                .try_into()?,
+            verifying_signature: value
+                .verifying_signature
+                .ok_or(wamu_core::Error::Encoding)?
+                .try_into()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost::Message;
+    use wamu_core::test_utils::MockECDSAIdentityProvider;
+    use wamu_core::IdentityProvider;
+
+    #[test]
+    fn augmented_message_proto_encoding_works() {
+        // Generates identity provider.
+        let identity_provider = MockECDSAIdentityProvider::generate();
+
+        // Wraps and encodes an augmented message with a simple base message body.
+        let msg = Msg {
+            sender: 1,
+            receiver: Some(2),
+            body: AugmentedType {
+                base: 42u64,
+                extra: Some(augmented_state_machine::IdentityAuthParams {
+                    verifying_key: identity_provider.verifying_key(),
+                    verifying_signature: identity_provider.sign(b"Hello, world!"),
+                }),
+            },
+        };
+        let encoded =
+            AugmentedMessage::from_msg(msg, |base| base.to_be_bytes().to_vec()).encode_to_vec();
+
+        // Decodes and unwraps the augmented message.
+        let decoded = AugmentedMessage::decode(encoded.as_slice())
+            .unwrap()
+            .into_msg(|bytes| bytes.try_into().map(u64::from_be_bytes))
+            .unwrap();
+        assert_eq!(decoded.sender, 1);
+        assert_eq!(decoded.receiver, Some(2));
+        assert_eq!(decoded.body.base, 42);
+        let extra = decoded.body.extra.unwrap();
+        assert_eq!(extra.verifying_key, identity_provider.verifying_key());
+        assert!(wamu_core::crypto::verify_signature(
+            &extra.verifying_key,
+            b"Hello, world!",
+            &extra.verifying_signature
+        )
+        .is_ok());
+
+        // Invalid base message bodies are rejected.
+        let wrapped = AugmentedMessage {
+            sender: 1,
+            receiver: None,
+            base: vec![0; 7],
+            extra: None,
+        };
+        assert_eq!(
+            wrapped
+                .into_msg(|bytes| bytes.try_into().map(u64::from_be_bytes))
+                .map(|_| ()),
+            Err(wamu_core::Error::Encoding)
+        );
+    }
+}
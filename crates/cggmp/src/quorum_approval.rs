@@ -6,15 +6,18 @@ use round_based::{IsCritical, Msg, StateMachine};
 use std::collections::HashMap;
 use std::time::Duration;
 use wamu_core::crypto::VerifyingKey;
+use wamu_core::quorum_approved_request::{QuorumPolicy, QuorumTracker};
+use wamu_core::time_lock::TimeLock;
 use wamu_core::{
-    CommandApprovalPayload, IdentityAuthedRequestError, IdentityAuthedRequestPayload,
-    IdentityProvider, QuorumApprovedChallengeResponsePayload, QuorumApprovedRequestError,
+    CommandApprovalPayload, CommandCancellationPayload, IdentityAuthedRequestError,
+    IdentityAuthedRequestPayload, IdentityProvider, QuorumApprovedChallengeResponsePayload,
+    QuorumApprovedRequestError, TimeLockError,
 };
 
 /// A [StateMachine](StateMachine) that implements [quorum approval as described by the Wamu protocol](https://wamu.tech/specification#quorum-approved-request).
 pub struct QuorumApproval<'a, I: IdentityProvider> {
     /// The command for the request being initiated.
-    command: &'static str,
+    command: String,
     /// The decentralized identity provider of the party.
     identity_provider: &'a I,
     /// Verifying keys for other the parties.
@@ -23,8 +26,14 @@ pub struct QuorumApproval<'a, I: IdentityProvider> {
     idx: u16,
     /// The threshold.
     threshold: u16,
+    /// Approval rule for the command.
+    policy: QuorumPolicy,
+    /// The quorum size (including the initiator) as determined by the approval policy.
+    quorum_size: usize,
     /// Total number of parties.
     n_parties: u16,
+    /// Indices of the approving parties (i.e all parties unless restricted with `with_participants`).
+    participants: Vec<u16>,
     /// Whether or not this party is the request initiator.
     is_initiator: bool,
     /// Current round.
@@ -42,27 +51,41 @@ pub struct QuorumApproval<'a, I: IdentityProvider> {
     // Hack for composite protocols (e.g share addition) where some parties (e.g new parties in the case of share additional) are dormant during the quorum approval.
     // Whether or not this party actively participates in the protocol.
     is_dormant: bool,
+    /// The enforced delay (in seconds) between request initiation and execution (if any).
+    time_lock_delay: Option<u64>,
+    /// Time lock for the request (only `Some` after the request is known if a delay is set).
+    time_lock: Option<TimeLock>,
+    /// Verifying keys for observers (i.e watch-only parties that can veto the request).
+    observers: &'a [VerifyingKey],
+    /// Whether or not the request was vetoed by an observer.
+    is_vetoed: bool,
 }
 
 impl<'a, I: IdentityProvider> QuorumApproval<'a, I> {
     /// Initializes party for the identity authentication protocol.
     pub fn new(
-        command: &'static str,
+        command: &str,
         identity_provider: &'a I,
         verified_parties: &'a [VerifyingKey],
         idx: u16,
-        // NOTE: Quorum size = threshold + 1
+        // NOTE: Signing quorum size = threshold + 1
         threshold: u16,
         n_parties: u16,
         is_initiator: bool,
         is_dormant: bool,
+        // Approval rule for the command (e.g the signing quorum or all parties).
+        policy: QuorumPolicy,
     ) -> QuorumApproval<'a, I> {
         // Generates initiation payload for initiating party and moves it to round 2.
         let mut message_queue = Vec::new();
         let mut round = Round::One;
         let mut request_option = None;
         if is_initiator {
-            let request = wamu_core::quorum_approved_request::initiate(command, identity_provider);
+            // NOTE: Commands can be dynamic (e.g bound to the new identity for share recovery with a new identity).
+            let request = wamu_core::identity_authed_request::initiate_with_command(
+                command.to_string(),
+                identity_provider,
+            );
 
             message_queue.push(Msg {
                 sender: idx,
@@ -76,13 +99,16 @@ impl<'a, I: IdentityProvider> QuorumApproval<'a, I> {
 
         // Returns quorum approval machine.
         Self {
-            command,
+            command: command.to_string(),
             identity_provider,
             verified_parties,
             is_initiator,
             idx,
             threshold,
+            policy,
+            quorum_size: policy.quorum_size(threshold as usize, n_parties as usize),
             n_parties,
+            participants: (1..=n_parties).collect(),
             round,
             message_queue,
             request: request_option,
@@ -90,7 +116,98 @@ impl<'a, I: IdentityProvider> QuorumApproval<'a, I> {
             verification_outcome: None,
             received_verification_outcomes: HashMap::new(),
             is_dormant,
+            time_lock_delay: None,
+            time_lock: None,
+            observers: &[],
+            is_vetoed: false,
+        }
+    }
+
+    /// Enforces a delay (in seconds) between request initiation and completion of the quorum approval,
+    /// during which any verified party can cancel the request (see [`cancel`](Self::cancel)).
+    ///
+    /// **NOTE:** All parties must use the same delay.
+    pub fn with_time_lock(mut self, delay: u64) -> Self {
+        self.time_lock_delay = Some(delay);
+        self.time_lock = self
+            .request
+            .as_ref()
+            .map(|request| TimeLock::new(request.clone(), delay));
+        self
+    }
+
+    /// Restricts the approving parties to the given party indices (e.g the remaining parties for share removal),
+    /// so that the quorum size (as determined by the approval policy) only counts participants
+    /// and approval round messages from other parties are ignored.
+    ///
+    /// **NOTE:** All participants must use the same party indices (i.e parties keep their current indices).
+    pub fn with_participants(mut self, participants: &[u16]) -> Self {
+        self.participants = participants.to_vec();
+        self.quorum_size = self
+            .policy
+            .quorum_size(self.threshold as usize, participants.len());
+        self
+    }
+
+    /// Re-verifies the request and the collected command approvals,
+    /// returns an ok result if the approvals form a quorum for the command or an appropriate error result otherwise.
+    ///
+    /// **NOTE:** Composite protocols (e.g share removal) check this before activating the key refresh,
+    /// so that they can't be driven with only the initiator's identity authentication.
+    pub fn verify_quorum(&self) -> Result<(), Error> {
+        let request = self.request.as_ref().ok_or(Error::InvalidState)?;
+        wamu_core::wrappers::verify_identity_authed_request_and_initiate_challenge(
+            &self.command,
+            request,
+            self.verified_parties,
+        )?;
+        let mut tracker =
+            QuorumTracker::new(request.clone(), self.quorum_size, self.verified_parties);
+        for approval in self.command_approvals.values() {
+            let approver = approval
+                .delegation
+                .as_ref()
+                .map_or(&approval.verifying_key, |delegation| &delegation.delegator);
+            // The initiator's approval is implicit, and invalid approvals don't count towards the quorum.
+            if approver != &request.verifying_key {
+                let _ = tracker.add_approval(approval.clone());
+            }
         }
+        if tracker.is_complete() {
+            Ok(())
+        } else {
+            Err(Error::Quorum(
+                QuorumApprovedRequestError::InsufficientApprovals,
+            ))
+        }
+    }
+
+    /// Sets verifying keys for observers (i.e watch-only parties that hold no shares and never approve requests),
+    /// whose vetoes (see [`veto_request`](crate::veto_request)) abort the request at any point before it's approved.
+    ///
+    /// **NOTE:** Combine with a time lock (see [`with_time_lock`](Self::with_time_lock)) to give observers time to veto.
+    pub fn with_observers(mut self, observers: &'a [VerifyingKey]) -> Self {
+        self.observers = observers;
+        self
+    }
+
+    /// Returns the time lock for the request (if any).
+    pub fn time_lock(&self) -> Option<&TimeLock> {
+        self.time_lock.as_ref()
+    }
+
+    /// Cancels a time-locked request and broadcasts a signed cancellation to the other parties.
+    pub fn cancel(&mut self) -> Result<(), Error> {
+        let time_lock = self.time_lock.as_mut().ok_or(Error::InvalidState)?;
+        let cancellation =
+            wamu_core::time_lock::cancel(time_lock.request(), self.identity_provider);
+        time_lock.cancel(cancellation.clone(), self.verified_parties)?;
+        self.message_queue.push(Msg {
+            sender: self.idx,
+            receiver: None,
+            body: Message::Cancel(cancellation),
+        });
+        Ok(())
     }
 }
 
@@ -100,6 +217,10 @@ impl<'a, I: IdentityProvider> StateMachine for QuorumApproval<'a, I> {
     type Output = bool;
 
     fn handle_incoming(&mut self, msg: Msg<Self::MessageBody>) -> Result<(), Self::Err> {
+        // Approval round messages from non-participants are ignored (e.g parties being removed).
+        if !self.participants.contains(&msg.sender) && !matches!(msg.body, Message::Cancel(_)) {
+            return Ok(());
+        }
         match msg.body {
             // All other parties verify the identity authentication request.
             Message::Round1(request) => {
@@ -109,11 +230,15 @@ impl<'a, I: IdentityProvider> StateMachine for QuorumApproval<'a, I> {
                 if !self.is_initiator && !self.is_dormant {
                     let command_approval =
                         wamu_core::quorum_approved_request::verify_request_and_initiate_challenge(
-                            self.command,
+                            &self.command,
                             &request,
                             self.identity_provider,
                             self.verified_parties,
                         )?;
+                    // Initializes the time lock (if any).
+                    self.time_lock = self
+                        .time_lock_delay
+                        .map(|delay| TimeLock::new(request.clone(), delay));
                     // Saves the request payload.
                     self.request = Some(request);
 
@@ -128,12 +253,33 @@ impl<'a, I: IdentityProvider> StateMachine for QuorumApproval<'a, I> {
                         receiver: None,
                         body: Message::Round2(command_approval),
                     });
+                } else if self.is_dormant {
+                    // Dormant parties only verify and save the request payload (i.e so that they can verify the quorum).
+                    wamu_core::wrappers::verify_identity_authed_request_and_initiate_challenge(
+                        &self.command,
+                        &request,
+                        self.verified_parties,
+                    )?;
+                    self.request = Some(request);
                 }
             }
-            // All parties store the received identity challenges.
-            Message::Round2(challenge_fragment) => {
-                self.command_approvals
-                    .insert(msg.sender, challenge_fragment);
+            // All parties store the received identity challenges from participants.
+            Message::Round2(command_approval) => {
+                // NOTE: Approvals on behalf of non-participants (e.g delegated by the recovering party
+                // for share recovery with a new identity) are ignored, so that they can't count towards the quorum.
+                let approver = command_approval
+                    .delegation
+                    .as_ref()
+                    .map_or(&command_approval.verifying_key, |delegation| {
+                        &delegation.delegator
+                    });
+                let is_participant = self
+                    .participants
+                    .iter()
+                    .any(|idx| self.verified_parties.get(*idx as usize - 1) == Some(approver));
+                if is_participant {
+                    self.command_approvals.insert(msg.sender, command_approval);
+                }
             }
             // All other parties verify the identity challenge response from the initiating party.
             Message::Round3(response) => {
@@ -151,7 +297,7 @@ impl<'a, I: IdentityProvider> StateMachine for QuorumApproval<'a, I> {
                             .collect::<Vec<CommandApprovalPayload>>(),
                         &self.verified_parties[msg.sender as usize - 1],
                         request,
-                        self.threshold as usize, // In this case threshold is enough since the initiator is an implicit approval.
+                        self.quorum_size.saturating_sub(1), // In this case quorum size - 1 is enough since the initiator is an implicit approval.
                         self.verified_parties,
                     )?;
 
@@ -170,6 +316,39 @@ impl<'a, I: IdentityProvider> StateMachine for QuorumApproval<'a, I> {
                 self.received_verification_outcomes
                     .insert(msg.sender, outcome);
             }
+            // All parties verify and record vetoes from observers for requests that aren't approved yet.
+            Message::Cancel(cancellation)
+                if self.observers.contains(&cancellation.verifying_key)
+                    && !matches!(self.round, Round::Final | Round::Gone) =>
+            {
+                // NOTE: Invalid vetoes (e.g forged or received before the request) are ignored,
+                // so that they can't abort the request.
+                let is_valid = self.request.as_ref().is_some_and(|request| {
+                    wamu_core::time_lock::verify_cancellation(
+                        &cancellation,
+                        request,
+                        self.observers,
+                    )
+                    .is_ok()
+                });
+                if is_valid {
+                    self.is_vetoed = true;
+                    return Err(Error::Vetoed);
+                }
+                report_invalid_cancellation(msg.sender);
+            }
+            // All parties with a time lock verify and record cancellations.
+            Message::Cancel(cancellation) => {
+                // NOTE: Late cancellations (i.e received after the time lock window elapses) are ignored,
+                // and so are invalid cancellations (e.g forged or from unverified parties), so that they can't abort the request.
+                if let Some(time_lock) = self.time_lock.as_mut() {
+                    match time_lock.cancel(cancellation, self.verified_parties) {
+                        Ok(()) => return Err(Error::TimeLock(TimeLockError::Cancelled)),
+                        Err(TimeLockError::Unlocked) => (),
+                        Err(_) => report_invalid_cancellation(msg.sender),
+                    }
+                }
+            }
         }
         Ok(())
     }
@@ -179,6 +358,10 @@ impl<'a, I: IdentityProvider> StateMachine for QuorumApproval<'a, I> {
     }
 
     fn wants_to_proceed(&self) -> bool {
+        // Cancelled and vetoed requests proceed immediately to report the cancellation.
+        if self.is_vetoed || self.time_lock.as_ref().is_some_and(TimeLock::is_cancelled) {
+            return true;
+        }
         match &self.round {
             // Initiating party is immediately ready to proceed from Round 1 after initialization,
             // while other parties need to prepare their challenge fragments first before they can proceed.
@@ -189,16 +372,17 @@ impl<'a, I: IdentityProvider> StateMachine for QuorumApproval<'a, I> {
                     self.command_approvals.contains_key(&self.idx)
                 }
             }
-            // Initiating party needs to receive challenge fragments from at least the threshold (i.e >= threshold) since its also an approval (i.e quorum size = threshold + 1),
-            // while other parties need to receive challenge fragments from at least the threshold - 1 (i.e >= threshold - 1) since they can be the final approval.
+            // Initiating party needs to receive challenge fragments from at least quorum size - 1 parties since its also an approval,
+            // while other parties need to receive challenge fragments from at least quorum size - 2 parties since they can be the final approval.
             Round::Two => {
                 self.command_approvals.len()
-                    >= self.threshold as usize
-                        - if self.is_initiator || self.is_dormant {
-                            0
-                        } else {
+                    >= self
+                        .quorum_size
+                        .saturating_sub(if self.is_initiator || self.is_dormant {
                             1
-                        }
+                        } else {
+                            2
+                        })
             }
             // Initiating party is immediately ready to proceed from Round 3 after initialization,
             // while other parties need to receive the challenge response and either accept it or reject it before they can proceed.
@@ -209,23 +393,33 @@ impl<'a, I: IdentityProvider> StateMachine for QuorumApproval<'a, I> {
                     self.verification_outcome.is_some()
                 }
             }
-            // Initiating party needs to receive outcomes from at least the threshold (i.e >= threshold) since its also an approval (i.e quorum size = threshold + 1),
-            // while other parties need to receive outcomes from at least the threshold - 1 (i.e >= threshold - 1) since they can be the final approval.
+            // Initiating party needs to receive outcomes from at least quorum size - 1 parties since its also an approval,
+            // while other parties need to receive outcomes from at least quorum size - 2 parties since they can be the final approval.
             Round::Four => {
                 self.command_approvals.len()
-                    >= self.threshold as usize
-                        - if self.is_initiator || self.is_dormant {
-                            0
-                        } else {
+                    >= self
+                        .quorum_size
+                        .saturating_sub(if self.is_initiator || self.is_dormant {
                             1
-                        }
+                        } else {
+                            2
+                        })
             }
+            // Time-locked requests need to wait for the time lock window to elapse.
+            Round::TimeLocked => self.time_lock.as_ref().map_or(true, TimeLock::is_unlocked),
             // The protocol is completed at this point and output should be picked.
             Round::Final | Round::Gone => false,
         }
     }
 
     fn proceed(&mut self) -> Result<(), Self::Err> {
+        // Vetoed and cancelled requests can't proceed.
+        if self.is_vetoed {
+            return Err(Error::Vetoed);
+        }
+        if self.time_lock.as_ref().is_some_and(TimeLock::is_cancelled) {
+            return Err(Error::TimeLock(TimeLockError::Cancelled));
+        }
         match self.round {
             // Round 1 is already handled by `handle_incoming` or during initialization for the initiating party.
             Round::One => {
@@ -244,7 +438,7 @@ impl<'a, I: IdentityProvider> StateMachine for QuorumApproval<'a, I> {
                             .collect::<Vec<CommandApprovalPayload>>(),
                         self.identity_provider,
                         request,
-                        self.threshold as usize + 1, // quorum size = threshold + 1.
+                        self.quorum_size,
                         self.verified_parties,
                     );
                     match result {
@@ -262,7 +456,7 @@ impl<'a, I: IdentityProvider> StateMachine for QuorumApproval<'a, I> {
                             return if matches!(
                                 error,
                                 QuorumApprovedRequestError::InsufficientApprovals
-                            ) && self.command_approvals.len() < self.n_parties as usize
+                            ) && self.command_approvals.len() < self.participants.len()
                             {
                                 Ok(())
                             } else {
@@ -283,8 +477,17 @@ impl<'a, I: IdentityProvider> StateMachine for QuorumApproval<'a, I> {
             // Initiating party simply confirms that it received enough confirmations from other parties in this round,
             // while other parties are already done at this point.
             Round::Four => {
-                // Everyone moves on to the final round.
-                self.round = Round::Final;
+                // Everyone moves on to either the time lock window (if any) or the final round.
+                self.round = match self.time_lock.as_ref() {
+                    Some(time_lock) if !time_lock.is_unlocked() => Round::TimeLocked,
+                    _ => Round::Final,
+                };
+            }
+            // Moves on to the final round once the time lock window elapses.
+            Round::TimeLocked => {
+                if self.time_lock.as_ref().map_or(true, TimeLock::is_unlocked) {
+                    self.round = Round::Final;
+                }
             }
             // All that's left to do is producing/picking output.
             Round::Final | Round::Gone => (),
@@ -293,11 +496,26 @@ impl<'a, I: IdentityProvider> StateMachine for QuorumApproval<'a, I> {
     }
 
     fn round_timeout(&self) -> Option<Duration> {
-        None
+        // The last approval round and the time lock window time out when the time lock window elapses
+        // (i.e so that async runtimes wake up to proceed even if no more messages arrive).
+        match self.round {
+            Round::Four | Round::TimeLocked => self
+                .time_lock
+                .as_ref()
+                .filter(|time_lock| !time_lock.is_unlocked())
+                .map(|time_lock| Duration::from_secs(time_lock.remaining())),
+            _ => None,
+        }
     }
 
     fn round_timeout_reached(&mut self) -> Self::Err {
-        panic!("no timeout was set")
+        // Reaching the end of the time lock window is a non-critical error (i.e the protocol proceeds).
+        match self.round {
+            Round::Four | Round::TimeLocked if self.time_lock.is_some() => {
+                Error::TimeLock(TimeLockError::Unlocked)
+            }
+            _ => panic!("no timeout was set"),
+        }
     }
 
     fn is_finished(&self) -> bool {
@@ -323,7 +541,7 @@ impl<'a, I: IdentityProvider> StateMachine for QuorumApproval<'a, I> {
             Round::One => 1,
             Round::Two => 2,
             Round::Three => 3,
-            Round::Four => 4,
+            Round::Four | Round::TimeLocked => 4,
             Round::Final | Round::Gone => 5,
         }
     }
@@ -341,12 +559,20 @@ impl<'a, I: IdentityProvider> StateMachine for QuorumApproval<'a, I> {
     }
 }
 
+/// Reports an ignored invalid cancellation or veto (i.e as a `tracing` event).
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+fn report_invalid_cancellation(sender: u16) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(sender, "ignored invalid cancellation");
+}
+
 #[derive(Debug, PartialEq, Eq)]
 enum Round {
     One,
     Two,
     Three,
     Four,
+    TimeLocked,
     Final,
     Gone,
 }
@@ -357,14 +583,17 @@ pub enum Message {
     Round2(CommandApprovalPayload),
     Round3(QuorumApprovedChallengeResponsePayload),
     Round4(Option<bool>),
+    Cancel(CommandCancellationPayload),
 }
 
 #[derive(Debug)]
 pub enum Error {
     Quorum(QuorumApprovedRequestError),
     Identity(IdentityAuthedRequestError),
+    TimeLock(TimeLockError),
     AlreadyPicked,
     InvalidState,
+    Vetoed,
 }
 
 impl From<QuorumApprovedRequestError> for Error {
@@ -379,6 +608,12 @@ impl From<IdentityAuthedRequestError> for Error {
     }
 }
 
+impl From<TimeLockError> for Error {
+    fn from(error: TimeLockError) -> Self {
+        Self::TimeLock(error)
+    }
+}
+
 impl From<wamu_core::Error> for Error {
     fn from(error: wamu_core::Error) -> Self {
         Self::Quorum(QuorumApprovedRequestError::Unauthorized(error))
@@ -395,7 +630,8 @@ impl From<wamu_core::CryptoError> for Error {
 
 impl IsCritical for Error {
     fn is_critical(&self) -> bool {
-        true
+        // Elapsed time lock windows (i.e round timeouts) are not critical errors, while all other errors are critical.
+        !matches!(self, Error::TimeLock(TimeLockError::Unlocked))
     }
 }
 
@@ -445,6 +681,7 @@ mod tests {
                 n_parties,
                 is_initiator,
                 false,
+                QuorumPolicy::SigningQuorum,
             ));
         }
 
@@ -495,5 +732,156 @@ mod tests {
         for outcome in results {
             assert!(outcome);
         }
+
+        // Verifies that the initiator's identity authentication alone doesn't form a quorum.
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let initiator = QuorumApproval::new(
+            "command",
+            &identity_providers[0],
+            &verifying_keys,
+            1,
+            threshold,
+            n_parties,
+            true,
+            false,
+            QuorumPolicy::SigningQuorum,
+        );
+        assert!(matches!(
+            initiator.verify_quorum(),
+            Err(Error::Quorum(
+                QuorumApprovedRequestError::InsufficientApprovals
+            ))
+        ));
+        let approver = QuorumApproval::new(
+            "command",
+            &identity_providers[1],
+            &verifying_keys,
+            2,
+            threshold,
+            n_parties,
+            false,
+            false,
+            QuorumPolicy::SigningQuorum,
+        );
+        assert!(matches!(approver.verify_quorum(), Err(Error::InvalidState)));
+    }
+
+    #[test]
+    fn observer_veto_works() {
+        // Creates identity providers for all parties and an observer.
+        let (threshold, n_parties) = (1, 3);
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (1..=n_parties)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let observer = MockECDSAIdentityProvider::generate();
+        let observers = vec![observer.verifying_key()];
+
+        // Initiates a request that observers can veto.
+        let mut initiator = QuorumApproval::new(
+            "command",
+            &identity_providers[0],
+            &verifying_keys,
+            1,
+            threshold,
+            n_parties,
+            true,
+            false,
+            QuorumPolicy::SigningQuorum,
+        )
+        .with_observers(&observers);
+        let request = match &initiator.message_queue()[0].body {
+            Message::Round1(request) => request.clone(),
+            _ => panic!("expected a request"),
+        };
+
+        // Vetoes from parties that aren't observers are ignored (i.e without a time lock).
+        let veto_msg = |identity_provider| Msg {
+            sender: 0,
+            receiver: None,
+            body: crate::veto_request(&request, identity_provider),
+        };
+        assert!(initiator
+            .handle_incoming(veto_msg(&identity_providers[1]))
+            .is_ok());
+
+        // Vetoes from observers abort the request.
+        assert!(matches!(
+            initiator.handle_incoming(veto_msg(&observer)),
+            Err(Error::Vetoed)
+        ));
+        assert!(initiator.wants_to_proceed());
+        assert!(matches!(initiator.proceed(), Err(Error::Vetoed)));
+    }
+
+    #[test]
+    fn invalid_cancellations_are_ignored() {
+        // Creates identity providers for all parties and an observer.
+        let (threshold, n_parties) = (1, 3);
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (1..=n_parties)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let observer = MockECDSAIdentityProvider::generate();
+        let observers = vec![observer.verifying_key()];
+
+        // Initiates a time-locked request.
+        let mut initiator = QuorumApproval::new(
+            "command",
+            &identity_providers[0],
+            &verifying_keys,
+            1,
+            threshold,
+            n_parties,
+            true,
+            false,
+            QuorumPolicy::SigningQuorum,
+        )
+        .with_time_lock(3600)
+        .with_observers(&observers);
+        let request = match &initiator.message_queue()[0].body {
+            Message::Round1(request) => request.clone(),
+            _ => panic!("expected a request"),
+        };
+        let cancel_msg = |cancellation| Msg {
+            sender: 2,
+            receiver: None,
+            body: Message::Cancel(cancellation),
+        };
+
+        // Cancellations from unverified parties are ignored.
+        let unverified_cancellation =
+            wamu_core::time_lock::cancel(&request, &MockECDSAIdentityProvider::generate());
+        assert!(initiator
+            .handle_incoming(cancel_msg(unverified_cancellation))
+            .is_ok());
+
+        // Forged cancellations (i.e signed by another party) and vetoes are ignored.
+        for verifying_key in [verifying_keys[1].clone(), observer.verifying_key()] {
+            let mut forged_cancellation =
+                wamu_core::time_lock::cancel(&request, &identity_providers[2]);
+            forged_cancellation.verifying_key = verifying_key;
+            assert!(initiator
+                .handle_incoming(cancel_msg(forged_cancellation))
+                .is_ok());
+        }
+        assert!(!initiator.time_lock().unwrap().is_cancelled());
+        assert!(initiator.proceed().is_ok());
+
+        // Valid cancellations abort the request.
+        let cancellation = wamu_core::time_lock::cancel(&request, &identity_providers[1]);
+        assert!(matches!(
+            initiator.handle_incoming(cancel_msg(cancellation)),
+            Err(Error::TimeLock(TimeLockError::Cancelled))
+        ));
     }
 }
@@ -0,0 +1,285 @@
+//! Dynamic quorum selection for pre-signing and signing sessions.
+//!
+//! Selects an available quorum (i.e threshold + 1 parties) from a roster that's larger than the quorum size
+//! based on liveness and connectivity information (e.g unresponsive parties reported by a [`LivenessMonitor`]
+//! and parties ranked by latency), and derives everything that's specific to the selected subset
+//! (i.e the participant set for the SSID, the mapping of party indices to quorum positions and the Lagrange coefficients),
+//! so that callers don't have to pre-pick indices and build index maps by hand.
+//!
+//! Ref: <https://eprint.iacr.org/2021/060.pdf> (Section 1.2.8).
+
+use curv::arithmetic::traits::One;
+use curv::elliptic::curves::{Scalar, Secp256k1};
+use curv::BigInt;
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::keygen::LocalKey;
+use std::collections::{BTreeSet, HashMap};
+
+use crate::liveness::LivenessMonitor;
+use crate::sign::SsidBuilder;
+
+/// A quorum selection error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuorumSelectionError {
+    /// A party index that's not on the roster (i.e not in the range 1..=n).
+    InvalidIndex { idx: u16 },
+    /// A required party that's also excluded (e.g an unresponsive coordinator).
+    ConflictingRequirement { idx: u16 },
+    /// More required parties than the quorum size.
+    TooManyRequired { required: usize, quorum_size: usize },
+    /// Not enough available parties for a quorum.
+    InsufficientParties {
+        available: usize,
+        quorum_size: usize,
+    },
+}
+
+/// A selected quorum (i.e a set of threshold + 1 participants) for pre-signing and signing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Quorum {
+    /// Indices of the participants (i.e party indices from key generation) in ascending order.
+    party_indices: Vec<u16>,
+}
+
+impl Quorum {
+    /// Returns the indices of the participants (i.e party indices from key generation) in ascending order.
+    pub fn party_indices(&self) -> &[u16] {
+        &self.party_indices
+    }
+
+    /// Returns true if the party is a participant.
+    pub fn contains(&self, idx: u16) -> bool {
+        self.party_indices.contains(&idx)
+    }
+
+    /// Returns the position of a participant in the quorum (i.e a 1-based index in the range 1..=t+1), if it's a participant.
+    pub fn position(&self, idx: u16) -> Option<u16> {
+        self.party_indices
+            .iter()
+            .position(|it| *it == idx)
+            .map(|position| position as u16 + 1)
+    }
+
+    /// Returns a map of the indices of the participants to their positions in the quorum
+    /// (i.e the same shape as the `old_to_new_map` for key refresh).
+    pub fn index_map(&self) -> HashMap<u16, u16> {
+        self.party_indices
+            .iter()
+            .enumerate()
+            .map(|(i, idx)| (*idx, i as u16 + 1))
+            .collect()
+    }
+
+    /// Returns the Lagrange coefficient (at zero) of a participant for the quorum (i.e lambda_{i,S} as defined by GG18 and GG20),
+    /// that transforms its (t,n) share into a (t,t+1) additive share, if it's a participant.
+    ///
+    /// Ref: <https://eprint.iacr.org/2021/060.pdf> (Section 1.2.8).
+    pub fn lagrange_coefficient(&self, idx: u16) -> Option<Scalar<Secp256k1>> {
+        if !self.contains(idx) {
+            return None;
+        }
+        let to_scalar = |it: u16| Scalar::<Secp256k1>::from_bigint(&BigInt::from(it as u64));
+        let x_i = to_scalar(idx);
+        self.party_indices.iter().filter(|j| **j != idx).try_fold(
+            Scalar::<Secp256k1>::from_bigint(&BigInt::one()),
+            |lambda, j| {
+                let x_j = to_scalar(*j);
+                let denominator = (&x_j - &x_i).invert()?;
+                Some(lambda * x_j * denominator)
+            },
+        )
+    }
+
+    /// Given a local key (e.g from key generation or key refresh) of a participant,
+    /// returns an SSID builder with the participants of the quorum set.
+    pub fn ssid_builder(&self, local_key: LocalKey<Secp256k1>) -> SsidBuilder {
+        SsidBuilder::new(local_key).with_party_indices(&self.party_indices)
+    }
+}
+
+/// A builder for quorums based on liveness and connectivity information.
+///
+/// Parties are selected in order of priority, i.e required parties first,
+/// then preferred parties (in order of preference) and then the remaining parties (in ascending index order),
+/// skipping excluded parties.
+#[derive(Debug, Clone)]
+pub struct QuorumSelector {
+    /// The threshold (i.e quorum size = threshold + 1).
+    threshold: u16,
+    /// The total number of parties on the roster.
+    n_parties: u16,
+    /// Parties that must be in the quorum (e.g the local party and the coordinator).
+    required: Vec<u16>,
+    /// Parties that must not be in the quorum (e.g unresponsive parties).
+    excluded: BTreeSet<u16>,
+    /// Parties in order of preference (e.g ranked by latency).
+    preferred: Vec<u16>,
+}
+
+impl QuorumSelector {
+    /// Given a threshold and the total number of parties on the roster, returns a selector with no constraints.
+    pub fn new(threshold: u16, n_parties: u16) -> Self {
+        Self {
+            threshold,
+            n_parties,
+            required: Vec::new(),
+            excluded: BTreeSet::new(),
+            preferred: Vec::new(),
+        }
+    }
+
+    /// Given a local key (e.g from key generation or key refresh),
+    /// returns a selector for its threshold and roster that requires the local party.
+    pub fn from_local_key(local_key: &LocalKey<Secp256k1>) -> Self {
+        Self::new(local_key.t, local_key.n).with_required(&[local_key.i])
+    }
+
+    /// Adds parties that must be in the quorum (e.g the local party and the coordinator).
+    pub fn with_required(mut self, party_indices: &[u16]) -> Self {
+        for idx in party_indices {
+            if !self.required.contains(idx) {
+                self.required.push(*idx);
+            }
+        }
+        self
+    }
+
+    /// Adds parties that must not be in the quorum (e.g parties that are unreachable or were previously blamed).
+    pub fn with_excluded(mut self, party_indices: &[u16]) -> Self {
+        self.excluded.extend(party_indices);
+        self
+    }
+
+    /// Excludes parties that are currently reported as unresponsive by a liveness monitor.
+    pub fn with_liveness(self, liveness_monitor: &LivenessMonitor) -> Self {
+        self.with_excluded(&liveness_monitor.unresponsive())
+    }
+
+    /// Sets the parties in order of preference (e.g ranked by latency or connectivity).
+    pub fn with_preferred(mut self, party_indices: &[u16]) -> Self {
+        self.preferred = party_indices.to_vec();
+        self
+    }
+
+    /// Returns the selected quorum, or an appropriate error if no valid quorum satisfies the constraints.
+    pub fn select(&self) -> Result<Quorum, QuorumSelectionError> {
+        let quorum_size = self.threshold as usize + 1;
+        for idx in self
+            .required
+            .iter()
+            .chain(&self.excluded)
+            .chain(&self.preferred)
+        {
+            if *idx < 1 || *idx > self.n_parties {
+                return Err(QuorumSelectionError::InvalidIndex { idx: *idx });
+            }
+        }
+        if let Some(idx) = self.required.iter().find(|idx| self.excluded.contains(idx)) {
+            return Err(QuorumSelectionError::ConflictingRequirement { idx: *idx });
+        }
+        if self.required.len() > quorum_size {
+            return Err(QuorumSelectionError::TooManyRequired {
+                required: self.required.len(),
+                quorum_size,
+            });
+        }
+
+        // Selects available parties in order of priority.
+        let mut selected: Vec<u16> = Vec::with_capacity(quorum_size);
+        for idx in self
+            .required
+            .iter()
+            .chain(&self.preferred)
+            .copied()
+            .chain(1..=self.n_parties)
+        {
+            if selected.len() == quorum_size {
+                break;
+            }
+            if !self.excluded.contains(&idx) && !selected.contains(&idx) {
+                selected.push(idx);
+            }
+        }
+        if selected.len() < quorum_size {
+            return Err(QuorumSelectionError::InsufficientParties {
+                available: selected.len(),
+                quorum_size,
+            });
+        }
+
+        selected.sort_unstable();
+        Ok(Quorum {
+            party_indices: selected,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quorum_selection_works() {
+        let (threshold, n_parties) = (2, 5);
+
+        // Selects required parties first, then preferred parties and then the remaining parties, skipping excluded parties.
+        let quorum = QuorumSelector::new(threshold, n_parties)
+            .with_required(&[4])
+            .with_excluded(&[1])
+            .with_preferred(&[5, 1])
+            .select()
+            .unwrap();
+        assert_eq!(quorum.party_indices(), &[2, 4, 5]);
+        assert_eq!(quorum.position(4), Some(2));
+        assert_eq!(quorum.position(1), None);
+        assert_eq!(quorum.index_map(), HashMap::from([(2, 1), (4, 2), (5, 3)]));
+
+        // Lagrange coefficients interpolate the (t,n) shares of a secret at zero.
+        let coefficients: Vec<Scalar<Secp256k1>> =
+            (0..=threshold).map(|_| Scalar::random()).collect();
+        let share = |idx: u16| {
+            let x = Scalar::<Secp256k1>::from_bigint(&BigInt::from(idx as u64));
+            coefficients
+                .iter()
+                .rev()
+                .fold(Scalar::zero(), |acc, coefficient| acc * &x + coefficient)
+        };
+        let secret = quorum
+            .party_indices()
+            .iter()
+            .fold(Scalar::zero(), |acc, idx| {
+                acc + quorum.lagrange_coefficient(*idx).unwrap() * share(*idx)
+            });
+        assert_eq!(secret, coefficients[0]);
+        assert!(quorum.lagrange_coefficient(1).is_none());
+
+        // Rejects unsatisfiable constraints.
+        for (selector, error) in [
+            (
+                QuorumSelector::new(threshold, n_parties).with_excluded(&[6]),
+                QuorumSelectionError::InvalidIndex { idx: 6 },
+            ),
+            (
+                QuorumSelector::new(threshold, n_parties)
+                    .with_required(&[1])
+                    .with_excluded(&[1]),
+                QuorumSelectionError::ConflictingRequirement { idx: 1 },
+            ),
+            (
+                QuorumSelector::new(threshold, n_parties).with_required(&[1, 2, 3, 4]),
+                QuorumSelectionError::TooManyRequired {
+                    required: 4,
+                    quorum_size: 3,
+                },
+            ),
+            (
+                QuorumSelector::new(threshold, n_parties).with_excluded(&[1, 2, 3]),
+                QuorumSelectionError::InsufficientParties {
+                    available: 2,
+                    quorum_size: 3,
+                },
+            ),
+        ] {
+            assert_eq!(selector.select(), Err(error));
+        }
+    }
+}
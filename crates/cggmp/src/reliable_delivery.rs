@@ -0,0 +1,480 @@
+//! Reliable delivery of protocol messages over lossy transports (e.g UDP or WebSocket connections that drop messages).
+//!
+//! Wraps any [`StateMachine`](StateMachine) (including augmented state machines) and
+//! numbers every outgoing message with a per receiver sequence number (i.e broadcast messages are sent as a numbered p2p message to each receiver),
+//! acknowledges every incoming message with the next expected sequence number for its sender (i.e cumulative acknowledgements),
+//! and retransmits unacknowledged messages (a bounded number of times) when the retransmission timer expires,
+//! so that dropped messages don't stall a session until the round timeout aborts it.
+//!
+//! Incoming messages are forwarded to the wrapped state machine exactly once and in the order in which they were sent
+//! (i.e duplicates are dropped and out of order messages are buffered until all preceding messages from the same sender are received).
+//!
+//! The retransmission timer is exposed as the round timeout of the wrapper (i.e capped by the round timeout of the wrapped state machine),
+//! and expiry of the retransmission timer is reported as a non-critical [`Error::Retransmission`] error,
+//! so retransmissions are driven by session drivers that enforce round timeouts (e.g [`run_session`](crate::run_session) and [`SessionManager`](crate::SessionManager)).
+//!
+//! **NOTE:** Messages that are still unacknowledged after the maximum number of retransmissions are no longer retransmitted,
+//! so the round timeout of the wrapped state machine eventually applies.
+
+use round_based::{Msg, StateMachine};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use crate::augmented_state_machine::Error;
+
+/// The maximum number of buffered out of order messages per sender.
+const MAX_OUT_OF_ORDER_MESSAGES: u64 = 1024;
+
+/// Retransmission configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetransmissionConfig {
+    /// Time after which unacknowledged messages are retransmitted.
+    pub interval: Duration,
+    /// Maximum number of retransmissions of each message.
+    pub max_retransmissions: u16,
+}
+
+impl Default for RetransmissionConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(2),
+            max_retransmissions: 5,
+        }
+    }
+}
+
+/// A message of the reliable delivery layer.
+#[derive(Debug, Clone)]
+pub enum ReliableMessage<B> {
+    /// A message of the wrapped state machine with its sequence number
+    /// (and whether it was sent as a broadcast message).
+    Data { seq: u64, body: B, broadcast: bool },
+    /// An acknowledgement of all messages with sequence numbers less than `next_seq`.
+    Ack { next_seq: u64 },
+}
+
+/// An unacknowledged outgoing message.
+struct Unacknowledged<B> {
+    /// Message body.
+    body: B,
+    /// Whether the message was sent as a broadcast message.
+    broadcast: bool,
+    /// Time at which the message was last (re-)transmitted.
+    sent_at: Instant,
+    /// Number of retransmissions.
+    retransmissions: u16,
+}
+
+/// A [`StateMachine`](StateMachine) that reliably delivers messages of a wrapped `StateMachine` over lossy transports
+/// (see module documentation for details).
+pub struct ReliableDelivery<SM: StateMachine> {
+    /// Wrapped state machine.
+    state_machine: SM,
+    /// Retransmission configuration.
+    config: RetransmissionConfig,
+    /// Sequence number of the next outgoing message indexed by receiver.
+    next_seq: BTreeMap<u16, u64>,
+    /// Unacknowledged outgoing messages indexed by receiver and sequence number.
+    unacknowledged: BTreeMap<(u16, u64), Unacknowledged<SM::MessageBody>>,
+    /// Sequence number of the next expected incoming message indexed by sender.
+    next_expected: BTreeMap<u16, u64>,
+    /// Buffered out of order incoming messages (and whether they were sent as broadcast messages) indexed by sender and sequence number.
+    out_of_order: BTreeMap<(u16, u64), (SM::MessageBody, bool)>,
+    /// Current round of the wrapped state machine and the time at which it was first observed.
+    round: (u16, Instant),
+    /// Reliable delivery message queue.
+    message_queue: Vec<Msg<ReliableMessage<SM::MessageBody>>>,
+}
+
+impl<SM: StateMachine> ReliableDelivery<SM>
+where
+    SM::MessageBody: Clone,
+{
+    /// Given a state machine and a retransmission configuration,
+    /// returns a state machine that reliably delivers messages of the wrapped state machine.
+    pub fn new(state_machine: SM, config: RetransmissionConfig) -> Self {
+        let round = (state_machine.current_round(), Instant::now());
+        let mut reliable_delivery = Self {
+            state_machine,
+            config,
+            next_seq: BTreeMap::new(),
+            unacknowledged: BTreeMap::new(),
+            next_expected: BTreeMap::new(),
+            out_of_order: BTreeMap::new(),
+            round,
+            message_queue: Vec::new(),
+        };
+
+        // Numbers messages queued by the wrapped state machine during initialization (if any).
+        reliable_delivery.update_message_queue();
+
+        reliable_delivery
+    }
+
+    /// Returns an immutable reference to the wrapped state machine.
+    pub fn state_machine(&self) -> &SM {
+        &self.state_machine
+    }
+
+    /// Returns the number of unacknowledged outgoing messages.
+    pub fn unacknowledged(&self) -> usize {
+        self.unacknowledged.len()
+    }
+
+    /// Numbers messages in the message queue of the wrapped state machine and moves them to the reliable delivery message queue.
+    fn update_message_queue(&mut self) {
+        // Restarts the round timer of the wrapped state machine when it advances to the next round.
+        if self.state_machine.current_round() != self.round.0 {
+            self.round = (self.state_machine.current_round(), Instant::now());
+        }
+
+        let idx = self.state_machine.party_ind();
+        let now = Instant::now();
+        for msg in self.state_machine.message_queue().split_off(0) {
+            let broadcast = msg.receiver.is_none();
+            let receivers: Vec<u16> = match msg.receiver {
+                Some(receiver) => vec![receiver],
+                None => (1..=self.state_machine.parties())
+                    .filter(|receiver| *receiver != idx)
+                    .collect(),
+            };
+            for receiver in receivers {
+                let next_seq = self.next_seq.entry(receiver).or_insert(0);
+                let seq = *next_seq;
+                *next_seq += 1;
+                self.message_queue.push(Msg {
+                    sender: idx,
+                    receiver: Some(receiver),
+                    body: ReliableMessage::Data {
+                        seq,
+                        body: msg.body.clone(),
+                        broadcast,
+                    },
+                });
+                self.unacknowledged.insert(
+                    (receiver, seq),
+                    Unacknowledged {
+                        body: msg.body.clone(),
+                        broadcast,
+                        sent_at: now,
+                        retransmissions: 0,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Buffers an incoming message, acknowledges it and forwards all in order messages from its sender to the wrapped state machine.
+    fn handle_data(
+        &mut self,
+        sender: u16,
+        seq: u64,
+        body: SM::MessageBody,
+        broadcast: bool,
+    ) -> Result<(), Error<SM::Err>> {
+        // Buffers new messages within the receive window (i.e duplicates and messages too far ahead are dropped).
+        let next_expected = self.next_expected.get(&sender).copied().unwrap_or(0);
+        if seq >= next_expected && seq - next_expected < MAX_OUT_OF_ORDER_MESSAGES {
+            self.out_of_order
+                .entry((sender, seq))
+                .or_insert((body, broadcast));
+        }
+
+        // Collects in order messages.
+        let mut ready = Vec::new();
+        let mut next_seq = next_expected;
+        while let Some(msg) = self.out_of_order.remove(&(sender, next_seq)) {
+            ready.push(msg);
+            next_seq += 1;
+        }
+        self.next_expected.insert(sender, next_seq);
+
+        // Acknowledges all messages received so far (including duplicates, in case a previous acknowledgement was dropped).
+        let idx = self.state_machine.party_ind();
+        self.message_queue.push(Msg {
+            sender: idx,
+            receiver: Some(sender),
+            body: ReliableMessage::Ack { next_seq },
+        });
+
+        // Forwards in order messages to the wrapped state machine.
+        let mut result = Ok(());
+        for (body, broadcast) in ready {
+            let msg_result = self
+                .state_machine
+                .handle_incoming(Msg {
+                    sender,
+                    receiver: (!broadcast).then_some(idx),
+                    body,
+                })
+                .map_err(Error::StateMachine);
+            // Reports the first error after all in order messages are forwarded.
+            if result.is_ok() {
+                result = msg_result;
+            }
+        }
+
+        // Updates the message queue.
+        self.update_message_queue();
+        result
+    }
+
+    /// Returns the time at which the round timer of the wrapped state machine expires (if any).
+    fn round_deadline(&self) -> Option<Instant> {
+        self.state_machine
+            .round_timeout()
+            .map(|round_timeout| self.round.1 + round_timeout)
+    }
+
+    /// Returns the time at which the next retransmission is due (if any).
+    fn retransmission_deadline(&self) -> Option<Instant> {
+        self.unacknowledged
+            .values()
+            .filter(|msg| msg.retransmissions < self.config.max_retransmissions)
+            .map(|msg| msg.sent_at + self.config.interval)
+            .min()
+    }
+}
+
+impl<SM: StateMachine> StateMachine for ReliableDelivery<SM>
+where
+    SM::MessageBody: Clone,
+{
+    type MessageBody = ReliableMessage<SM::MessageBody>;
+    type Err = Error<SM::Err>;
+    type Output = SM::Output;
+
+    fn handle_incoming(&mut self, msg: Msg<Self::MessageBody>) -> Result<(), Self::Err> {
+        match msg.body {
+            ReliableMessage::Data {
+                seq,
+                body,
+                broadcast,
+            } => self.handle_data(msg.sender, seq, body, broadcast),
+            ReliableMessage::Ack { next_seq } => {
+                // Removes all acknowledged messages.
+                let sender = msg.sender;
+                self.unacknowledged
+                    .retain(|(receiver, seq), _| *receiver != sender || *seq >= next_seq);
+                Ok(())
+            }
+        }
+    }
+
+    fn message_queue(&mut self) -> &mut Vec<Msg<Self::MessageBody>> {
+        &mut self.message_queue
+    }
+
+    fn wants_to_proceed(&self) -> bool {
+        self.state_machine.wants_to_proceed()
+    }
+
+    fn proceed(&mut self) -> Result<(), Self::Err> {
+        self.state_machine.proceed().map_err(Error::StateMachine)?;
+
+        // Updates the message queue.
+        self.update_message_queue();
+        Ok(())
+    }
+
+    fn round_timeout(&self) -> Option<Duration> {
+        // Returns the time remaining until either the next retransmission is due or the round timer of the wrapped state machine expires.
+        let deadline = match (self.retransmission_deadline(), self.round_deadline()) {
+            (Some(retransmission), Some(round)) => Some(retransmission.min(round)),
+            (retransmission, round) => retransmission.or(round),
+        };
+        deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    fn round_timeout_reached(&mut self) -> Self::Err {
+        let now = Instant::now();
+        if self
+            .round_deadline()
+            .map_or(false, |deadline| now >= deadline)
+        {
+            return Error::StateMachine(self.state_machine.round_timeout_reached());
+        }
+
+        // Retransmits all unacknowledged messages that are due for retransmission.
+        let idx = self.state_machine.party_ind();
+        let mut receivers = Vec::new();
+        for ((receiver, seq), msg) in self.unacknowledged.iter_mut() {
+            if msg.retransmissions < self.config.max_retransmissions
+                && now >= msg.sent_at + self.config.interval
+            {
+                msg.sent_at = now;
+                msg.retransmissions += 1;
+                self.message_queue.push(Msg {
+                    sender: idx,
+                    receiver: Some(*receiver),
+                    body: ReliableMessage::Data {
+                        seq: *seq,
+                        body: msg.body.clone(),
+                        broadcast: msg.broadcast,
+                    },
+                });
+                if !receivers.contains(&(*receiver as usize)) {
+                    receivers.push(*receiver as usize);
+                }
+            }
+        }
+        Error::Retransmission {
+            round: self.state_machine.current_round(),
+            receivers,
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.state_machine.is_finished()
+    }
+
+    fn pick_output(&mut self) -> Option<Result<Self::Output, Self::Err>> {
+        self.state_machine
+            .pick_output()
+            .map(|result| result.map_err(Error::StateMachine))
+    }
+
+    fn current_round(&self) -> u16 {
+        self.state_machine.current_round()
+    }
+
+    fn total_rounds(&self) -> Option<u16> {
+        self.state_machine.total_rounds()
+    }
+
+    fn party_ind(&self) -> u16 {
+        self.state_machine.party_ind()
+    }
+
+    fn parties(&self) -> u16 {
+        self.state_machine.parties()
+    }
+}
+
+// Unacknowledged messages can't be formatted.
+impl<SM: StateMachine + std::fmt::Debug> std::fmt::Debug for ReliableDelivery<SM> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ReliableDelivery({:?})", self.state_machine)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secure_channel::tests::ToyProtocol;
+    use round_based::IsCritical;
+
+    #[test]
+    fn reliable_delivery_works() {
+        let n_parties = 3u16;
+        let config = RetransmissionConfig {
+            interval: Duration::ZERO,
+            max_retransmissions: 3,
+        };
+        let mut parties: Vec<_> = (1..=n_parties)
+            .map(|idx| ReliableDelivery::new(ToyProtocol::new(idx, n_parties), config))
+            .collect();
+
+        // Runs the protocol over a lossy network that drops the first transmission of every message and duplicates all acknowledgements.
+        let mut transmissions = BTreeMap::new();
+        let mut retransmission_rounds = 0;
+        while !parties.iter().all(StateMachine::is_finished) {
+            let mut msgs = Vec::new();
+            for party in parties.iter_mut() {
+                if party.wants_to_proceed() {
+                    party.proceed().unwrap();
+                }
+                msgs.append(party.message_queue());
+            }
+            if msgs.is_empty() {
+                // Retransmits unacknowledged messages when the network is idle.
+                retransmission_rounds += 1;
+                assert!(retransmission_rounds <= 3);
+                for party in parties.iter_mut() {
+                    let error = party.round_timeout_reached();
+                    assert!(!error.is_critical());
+                }
+                continue;
+            }
+            for msg in msgs {
+                let receiver = msg.receiver.unwrap();
+                let deliveries = match &msg.body {
+                    ReliableMessage::Data { seq, .. } => {
+                        let count = transmissions
+                            .entry((msg.sender, receiver, *seq))
+                            .or_insert(0);
+                        *count += 1;
+                        usize::from(*count > 1)
+                    }
+                    ReliableMessage::Ack { .. } => 2,
+                };
+                for _ in 0..deliveries {
+                    parties[receiver as usize - 1]
+                        .handle_incoming(msg.clone())
+                        .unwrap();
+                }
+            }
+        }
+
+        // All messages are delivered exactly once and acknowledged.
+        for (i, party) in parties.iter_mut().enumerate() {
+            let idx = i as u16 + 1;
+            assert_eq!(party.unacknowledged(), 0);
+            let mut output = party.pick_output().unwrap().unwrap();
+            output.sort();
+            let mut expected: Vec<_> = (1..=n_parties)
+                .filter(|sender| *sender != idx)
+                .flat_map(|sender| {
+                    [
+                        (sender, None, vec![sender as u8]),
+                        (sender, Some(idx), vec![sender as u8, idx as u8]),
+                    ]
+                })
+                .collect();
+            expected.sort();
+            assert_eq!(output, expected);
+        }
+
+        // Out of order messages are buffered until all preceding messages from the same sender are received,
+        // and messages are no longer retransmitted after the maximum number of retransmissions.
+        let mut sender = ReliableDelivery::new(ToyProtocol::new(1, 2), config);
+        let mut receiver = ReliableDelivery::new(ToyProtocol::new(2, 2), config);
+        let first = sender.message_queue().remove(0);
+        let second = Msg {
+            sender: 1,
+            receiver: Some(2),
+            body: ReliableMessage::Data {
+                seq: 1,
+                body: vec![1u8],
+                broadcast: true,
+            },
+        };
+        let last_ack = |party: &mut ReliableDelivery<ToyProtocol>| match party.message_queue().pop()
+        {
+            Some(Msg {
+                body: ReliableMessage::Ack { next_seq },
+                ..
+            }) => next_seq,
+            _ => panic!("expected an acknowledgement"),
+        };
+        assert!(receiver.handle_incoming(second).is_ok());
+        assert_eq!(last_ack(&mut receiver), 0);
+        assert!(!receiver.wants_to_proceed());
+        assert!(receiver.handle_incoming(first.clone()).is_ok());
+        assert_eq!(last_ack(&mut receiver), 2);
+        // Duplicates are acknowledged but not forwarded to the wrapped state machine.
+        assert!(receiver.handle_incoming(first).is_ok());
+        assert_eq!(last_ack(&mut receiver), 2);
+        for _ in 0..config.max_retransmissions {
+            assert!(matches!(
+                sender.round_timeout_reached(),
+                Error::Retransmission { ref receivers, .. } if receivers == &vec![2]
+            ));
+        }
+        assert_eq!(sender.round_timeout(), None);
+        assert!(matches!(
+            sender.round_timeout_reached(),
+            Error::Retransmission { ref receivers, .. } if receivers.is_empty()
+        ));
+    }
+}
@@ -0,0 +1,681 @@
+//! End-to-end encrypted pairwise channels for protocol messages.
+//!
+//! Wraps any [`StateMachine`](StateMachine) (including augmented state machines) and
+//! encrypts every outgoing message body for its receiver (and decrypts it on receipt),
+//! so that relays and network observers can't read round contents (e.g Paillier ciphertexts and proofs).
+//!
+//! Before any message of the wrapped state machine is sent, all parties broadcast an ephemeral ECDH (secp256k1) public key
+//! signed with their identity key (bound to the session identifier and party index).
+//! Pairwise AES-256-GCM keys are then derived from the ECDH shared secrets with HKDF-SHA256,
+//! so channels are authenticated by the identity keys of both parties and forward secret (i.e ephemeral keys are never reused across sessions).
+//!
+//! Broadcast messages are sealed separately for each receiver (i.e sent as p2p messages) and restored as broadcast messages on receipt.
+//! The sender, receiver and session identifier are bound to each sealed message as associated data,
+//! so sealed messages can't be re-attributed, re-addressed or spliced from other sessions.
+//!
+//! **NOTE:** Sealed messages aren't bound to a round, so `SecureChannel` should wrap a [`MessageAuthentication`](crate::MessageAuthentication)
+//! state machine (i.e not the other way around) if replayed messages must also be detected.
+//!
+//! **NOTE:** Message bodies are opaque to this crate, so an encoder and decoder for message bodies must be supplied.
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use k256::ecdh::EphemeralSecret;
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use round_based::{Msg, StateMachine};
+use std::collections::BTreeMap;
+use std::ops::Deref;
+use std::time::Duration;
+use wamu_core::crypto::VerifyingKey;
+use wamu_core::IdentityProvider;
+
+use crate::augmented_state_machine::{Error, IdentityAuthParams};
+
+/// A secure channel message.
+#[derive(Debug, Clone)]
+pub enum SecureChannelMessage {
+    /// An ephemeral ECDH public key (i.e SEC1 encoded) signed with the identity key of the sender.
+    KeyExchange {
+        ephemeral_key: Vec<u8>,
+        identity_auth: IdentityAuthParams,
+    },
+    /// An encrypted message body of the wrapped state machine (and whether it was sent as a broadcast message).
+    Sealed {
+        nonce: [u8; 12],
+        ciphertext: Vec<u8>,
+        broadcast: bool,
+    },
+}
+
+/// A [`StateMachine`](StateMachine) that encrypts all outgoing messages of a wrapped `StateMachine` for their receivers
+/// and decrypts all incoming messages before forwarding them to the wrapped `StateMachine`.
+pub struct SecureChannel<'a, SM: StateMachine> {
+    /// Wrapped state machine.
+    state_machine: SM,
+    /// Verifying keys for all parties.
+    verified_parties: &'a [VerifyingKey],
+    /// Session identifier.
+    session_id: [u8; 32],
+    /// Encoder for message bodies of the wrapped state machine.
+    encode_body: fn(&SM::MessageBody) -> Vec<u8>,
+    /// Decoder for message bodies of the wrapped state machine.
+    decode_body: fn(&[u8]) -> Option<SM::MessageBody>,
+    /// Local party's ephemeral ECDH secret (until all pairwise keys are derived).
+    ephemeral_secret: Option<EphemeralSecret>,
+    /// Local party's ephemeral ECDH public key.
+    ephemeral_key: Vec<u8>,
+    /// Pairwise ciphers, ephemeral public keys and identity authentication parameters indexed by party.
+    channels: BTreeMap<u16, Channel>,
+    /// Buffered incoming sealed messages from parties whose ephemeral public keys haven't been received yet.
+    pending_messages: Vec<Msg<SecureChannelMessage>>,
+    /// Encrypted message queue.
+    message_queue: Vec<Msg<SecureChannelMessage>>,
+}
+
+/// A pairwise channel.
+struct Channel {
+    /// Ephemeral public key of the other party.
+    ephemeral_key: Vec<u8>,
+    /// Identity authentication parameters of the other party's key exchange message.
+    identity_auth: IdentityAuthParams,
+    /// Pairwise cipher.
+    cipher: Aes256Gcm,
+}
+
+impl<'a, SM: StateMachine> SecureChannel<'a, SM> {
+    /// Given a state machine, an identity provider, a list of verifying keys for all parties, a session identifier
+    /// and a codec (i.e encoder and decoder) for message bodies of the state machine,
+    /// returns a state machine that encrypts messages of the wrapped state machine for their receivers.
+    pub fn new(
+        state_machine: SM,
+        identity_provider: &impl IdentityProvider,
+        verified_parties: &'a [VerifyingKey],
+        session_id: [u8; 32],
+        encode_body: fn(&SM::MessageBody) -> Vec<u8>,
+        decode_body: fn(&[u8]) -> Option<SM::MessageBody>,
+    ) -> Self {
+        // Generates an ephemeral ECDH keypair and signs the public key with the identity key of the party.
+        let ephemeral_secret = EphemeralSecret::random(&mut OsRng);
+        let ephemeral_key = ephemeral_secret
+            .public_key()
+            .to_encoded_point(true)
+            .as_bytes()
+            .to_vec();
+        let idx = state_machine.party_ind();
+        let (verifying_key, verifying_signature) =
+            wamu_core::wrappers::initiate_request_with_signature(
+                &key_exchange_hash(&session_id, idx, &ephemeral_key),
+                identity_provider,
+            );
+
+        Self {
+            message_queue: vec![Msg {
+                sender: idx,
+                receiver: None,
+                body: SecureChannelMessage::KeyExchange {
+                    ephemeral_key: ephemeral_key.clone(),
+                    identity_auth: IdentityAuthParams {
+                        verifying_key,
+                        verifying_signature,
+                    },
+                },
+            }],
+            state_machine,
+            verified_parties,
+            session_id,
+            encode_body,
+            decode_body,
+            ephemeral_secret: Some(ephemeral_secret),
+            ephemeral_key,
+            channels: BTreeMap::new(),
+            pending_messages: Vec::new(),
+        }
+    }
+
+    /// Returns an immutable reference to the wrapped state machine.
+    pub fn state_machine(&self) -> &SM {
+        &self.state_machine
+    }
+
+    /// Returns true if pairwise keys have been derived for all other parties.
+    pub fn is_established(&self) -> bool {
+        self.channels.len() + 1 >= self.state_machine.parties() as usize
+    }
+
+    /// Encrypts messages in the message queue of the wrapped state machine and moves them to the encrypted message queue
+    /// (i.e once pairwise keys have been derived for all other parties).
+    fn update_message_queue(&mut self) -> Result<(), Error<SM::Err>> {
+        if !self.is_established() {
+            return Ok(());
+        }
+        let idx = self.state_machine.party_ind();
+        for msg in self.state_machine.message_queue().split_off(0) {
+            let plaintext = (self.encode_body)(&msg.body);
+            let receivers: Vec<u16> = match msg.receiver {
+                Some(receiver) => vec![receiver],
+                None => self.channels.keys().copied().collect(),
+            };
+            for receiver in receivers {
+                let channel = self.channels.get(&receiver).ok_or(Error::InvalidConfig)?;
+                let broadcast = msg.receiver.is_none();
+                let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+                let ciphertext = channel
+                    .cipher
+                    .encrypt(
+                        &nonce,
+                        Payload {
+                            msg: &plaintext,
+                            aad: &associated_data(&self.session_id, idx, receiver, broadcast),
+                        },
+                    )
+                    .map_err(|_| Error::Core(wamu_core::Error::Encoding))?;
+                self.message_queue.push(Msg {
+                    sender: idx,
+                    receiver: Some(receiver),
+                    body: SecureChannelMessage::Sealed {
+                        nonce: nonce.into(),
+                        ciphertext,
+                        broadcast,
+                    },
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Verifies an ephemeral public key and derives the pairwise key for the sender.
+    fn handle_key_exchange(
+        &mut self,
+        sender: u16,
+        ephemeral_key: Vec<u8>,
+        identity_auth: IdentityAuthParams,
+    ) -> Result<(), Error<SM::Err>> {
+        let round = self.state_machine.current_round();
+
+        // Verifies that the ephemeral public key is signed by the verified party with the sender's index.
+        wamu_core::wrappers::verify_request_with_signature(
+            &key_exchange_hash(&self.session_id, sender, &ephemeral_key),
+            &identity_auth.verifying_key,
+            &identity_auth.verifying_signature,
+            self.verified_parties,
+        )
+        .map_err(|error| Error::blame(error, sender, &identity_auth, round))?;
+        if self.verified_parties.get(sender as usize - 1) != Some(&identity_auth.verifying_key) {
+            return Err(Error::blame(
+                wamu_core::Error::UnauthorizedParty,
+                sender,
+                &identity_auth,
+                round,
+            ));
+        }
+
+        // Drops exact duplicates and rejects conflicting ephemeral public keys (i.e equivocation).
+        if let Some(channel) = self.channels.get(&sender) {
+            return if channel.ephemeral_key == ephemeral_key {
+                Ok(())
+            } else {
+                Err(Error::Equivocation {
+                    idx: sender,
+                    verifying_key: identity_auth.verifying_key,
+                    round,
+                })
+            };
+        }
+
+        // Derives the pairwise key from the ECDH shared secret.
+        let public_key = k256::PublicKey::from_sec1_bytes(&ephemeral_key)
+            .map_err(|_| Error::blame(wamu_core::Error::Encoding, sender, &identity_auth, round))?;
+        let secret = self.ephemeral_secret.as_ref().ok_or(Error::InvalidConfig)?;
+        let idx = self.state_machine.party_ind();
+        let (first, second) = if idx < sender {
+            ((idx, &self.ephemeral_key), (sender, &ephemeral_key))
+        } else {
+            ((sender, &ephemeral_key), (idx, &self.ephemeral_key))
+        };
+        let info = [
+            b"wamu/secure-channel".as_slice(),
+            &first.0.to_be_bytes(),
+            &second.0.to_be_bytes(),
+            first.1,
+            second.1,
+        ]
+        .concat();
+        let mut key = [0u8; 32];
+        secret
+            .diffie_hellman(&public_key)
+            .extract::<sha2::Sha256>(Some(&self.session_id))
+            .expand(&info, &mut key)
+            .map_err(|_| Error::Core(wamu_core::Error::Encoding))?;
+        self.channels.insert(
+            sender,
+            Channel {
+                ephemeral_key,
+                identity_auth,
+                cipher: Aes256Gcm::new(&key.into()),
+            },
+        );
+
+        // Discards the ephemeral secret once all pairwise keys have been derived.
+        if self.is_established() {
+            self.ephemeral_secret = None;
+        }
+        Ok(())
+    }
+
+    /// Decrypts an incoming sealed message and forwards it to the wrapped state machine.
+    fn handle_sealed(
+        &mut self,
+        sender: u16,
+        nonce: [u8; 12],
+        ciphertext: &[u8],
+        broadcast: bool,
+    ) -> Result<(), Error<SM::Err>> {
+        let idx = self.state_machine.party_ind();
+        let round = self.state_machine.current_round();
+        let channel = self.channels.get(&sender).ok_or(Error::MissingParams {
+            bad_actors: vec![sender as usize],
+            round,
+        })?;
+        let body = channel
+            .cipher
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: &associated_data(&self.session_id, sender, idx, broadcast),
+                },
+            )
+            .ok()
+            .and_then(|plaintext| (self.decode_body)(&plaintext))
+            .ok_or_else(|| {
+                Error::blame(
+                    wamu_core::Error::Encoding,
+                    sender,
+                    &channel.identity_auth,
+                    round,
+                )
+            })?;
+
+        // Forwards the decrypted message to the wrapped state machine.
+        self.state_machine
+            .handle_incoming(Msg {
+                sender,
+                receiver: (!broadcast).then_some(idx),
+                body,
+            })
+            .map_err(Error::StateMachine)?;
+
+        // Updates the message queue.
+        self.update_message_queue()
+    }
+
+    /// Replays buffered sealed messages from parties whose pairwise keys have been derived (if any).
+    fn replay_pending_messages(&mut self) -> Result<(), Error<SM::Err>> {
+        let (ready, pending): (Vec<_>, Vec<_>) = self
+            .pending_messages
+            .drain(..)
+            .partition(|msg| self.channels.contains_key(&msg.sender));
+        self.pending_messages = pending;
+        for msg in ready {
+            self.handle_incoming(msg)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, SM: StateMachine> StateMachine for SecureChannel<'a, SM> {
+    type MessageBody = SecureChannelMessage;
+    type Err = Error<SM::Err>;
+    type Output = SM::Output;
+
+    fn handle_incoming(&mut self, msg: Msg<Self::MessageBody>) -> Result<(), Self::Err> {
+        match msg.body {
+            SecureChannelMessage::KeyExchange {
+                ephemeral_key,
+                identity_auth,
+            } => {
+                self.handle_key_exchange(msg.sender, ephemeral_key, identity_auth)?;
+                // Sends held messages and replays buffered messages once all pairwise keys have been derived.
+                self.update_message_queue()?;
+                self.replay_pending_messages()
+            }
+            // Buffers sealed messages until the sender's ephemeral public key is received.
+            SecureChannelMessage::Sealed { .. } if !self.channels.contains_key(&msg.sender) => {
+                self.pending_messages.push(msg);
+                Ok(())
+            }
+            SecureChannelMessage::Sealed {
+                nonce,
+                ciphertext,
+                broadcast,
+            } => self.handle_sealed(msg.sender, nonce, &ciphertext, broadcast),
+        }
+    }
+
+    fn message_queue(&mut self) -> &mut Vec<Msg<Self::MessageBody>> {
+        &mut self.message_queue
+    }
+
+    fn wants_to_proceed(&self) -> bool {
+        self.state_machine.wants_to_proceed()
+    }
+
+    fn proceed(&mut self) -> Result<(), Self::Err> {
+        self.state_machine.proceed().map_err(Error::StateMachine)?;
+
+        // Updates the message queue.
+        self.update_message_queue()
+    }
+
+    fn round_timeout(&self) -> Option<Duration> {
+        self.state_machine.round_timeout()
+    }
+
+    fn round_timeout_reached(&mut self) -> Self::Err {
+        Error::StateMachine(self.state_machine.round_timeout_reached())
+    }
+
+    fn is_finished(&self) -> bool {
+        self.state_machine.is_finished()
+    }
+
+    fn pick_output(&mut self) -> Option<Result<Self::Output, Self::Err>> {
+        self.state_machine
+            .pick_output()
+            .map(|result| result.map_err(Error::StateMachine))
+    }
+
+    fn current_round(&self) -> u16 {
+        self.state_machine.current_round()
+    }
+
+    fn total_rounds(&self) -> Option<u16> {
+        self.state_machine.total_rounds()
+    }
+
+    fn party_ind(&self) -> u16 {
+        self.state_machine.party_ind()
+    }
+
+    fn parties(&self) -> u16 {
+        self.state_machine.parties()
+    }
+}
+
+// Ciphers and ephemeral secrets can't be formatted.
+impl<'a, SM: StateMachine + std::fmt::Debug> std::fmt::Debug for SecureChannel<'a, SM> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SecureChannel({:?})", self.state_machine)
+    }
+}
+
+/// Returns the signed hash of a key exchange message (i.e binds the ephemeral public key to the session and sender).
+fn key_exchange_hash(session_id: &[u8; 32], idx: u16, ephemeral_key: &[u8]) -> Vec<u8> {
+    use sha2::{digest::Update, Digest};
+    sha2::Sha256::new()
+        .chain(b"wamu/secure-channel")
+        .chain(session_id)
+        .chain(idx.to_be_bytes())
+        .chain(ephemeral_key)
+        .finalize()
+        .deref()
+        .to_vec()
+}
+
+/// Returns the associated data for a sealed message (i.e `session id || sender || receiver || broadcast`).
+fn associated_data(session_id: &[u8; 32], sender: u16, receiver: u16, broadcast: bool) -> Vec<u8> {
+    [
+        session_id.as_slice(),
+        &sender.to_be_bytes(),
+        &receiver.to_be_bytes(),
+        &[broadcast as u8],
+    ]
+    .concat()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use round_based::dev::Simulation;
+    use round_based::IsCritical;
+    use wamu_core::test_utils::MockECDSAIdentityProvider;
+
+    /// A toy state machine error.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub(crate) struct ToyError;
+
+    impl IsCritical for ToyError {
+        fn is_critical(&self) -> bool {
+            true
+        }
+    }
+
+    /// A toy two round protocol where every party broadcasts its index in the first round,
+    /// and sends a p2p message to every other party in the second round.
+    /// The output is all received messages (i.e sender, receiver and body) in order of receipt.
+    #[derive(Debug)]
+    pub(crate) struct ToyProtocol {
+        idx: u16,
+        n_parties: u16,
+        round: u16,
+        received: Vec<(u16, Option<u16>, Vec<u8>)>,
+        message_queue: Vec<Msg<Vec<u8>>>,
+    }
+
+    impl ToyProtocol {
+        pub(crate) fn new(idx: u16, n_parties: u16) -> Self {
+            Self {
+                idx,
+                n_parties,
+                round: 1,
+                received: Vec::new(),
+                message_queue: vec![Msg {
+                    sender: idx,
+                    receiver: None,
+                    body: vec![idx as u8],
+                }],
+            }
+        }
+    }
+
+    impl StateMachine for ToyProtocol {
+        type MessageBody = Vec<u8>;
+        type Err = ToyError;
+        type Output = Vec<(u16, Option<u16>, Vec<u8>)>;
+
+        fn handle_incoming(&mut self, msg: Msg<Self::MessageBody>) -> Result<(), Self::Err> {
+            let expected_receiver = (self.round == 2).then_some(self.idx);
+            if msg.receiver != expected_receiver {
+                return Err(ToyError);
+            }
+            self.received.push((msg.sender, msg.receiver, msg.body));
+            Ok(())
+        }
+
+        fn message_queue(&mut self) -> &mut Vec<Msg<Self::MessageBody>> {
+            &mut self.message_queue
+        }
+
+        fn wants_to_proceed(&self) -> bool {
+            self.round <= 2 && self.received.len() == (self.round * (self.n_parties - 1)) as usize
+        }
+
+        fn proceed(&mut self) -> Result<(), Self::Err> {
+            if self.round == 1 {
+                for receiver in (1..=self.n_parties).filter(|it| *it != self.idx) {
+                    self.message_queue.push(Msg {
+                        sender: self.idx,
+                        receiver: Some(receiver),
+                        body: vec![self.idx as u8, receiver as u8],
+                    });
+                }
+            }
+            self.round += 1;
+            Ok(())
+        }
+
+        fn round_timeout(&self) -> Option<Duration> {
+            None
+        }
+
+        fn round_timeout_reached(&mut self) -> Self::Err {
+            ToyError
+        }
+
+        fn is_finished(&self) -> bool {
+            self.round > 2
+        }
+
+        fn pick_output(&mut self) -> Option<Result<Self::Output, Self::Err>> {
+            self.is_finished()
+                .then(|| Ok(std::mem::take(&mut self.received)))
+        }
+
+        fn current_round(&self) -> u16 {
+            self.round
+        }
+
+        fn total_rounds(&self) -> Option<u16> {
+            Some(2)
+        }
+
+        fn party_ind(&self) -> u16 {
+            self.idx
+        }
+
+        fn parties(&self) -> u16 {
+            self.n_parties
+        }
+    }
+
+    #[test]
+    fn secure_channel_works() {
+        // Creates identity providers and verifying keys for all parties.
+        let n_parties = 3u16;
+        let session_id = [1u8; 32];
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (1..=n_parties)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let new_party = |idx: u16| {
+            SecureChannel::new(
+                ToyProtocol::new(idx, n_parties),
+                &identity_providers[idx as usize - 1],
+                &verifying_keys,
+                session_id,
+                Vec::clone,
+                |bytes| Some(bytes.to_vec()),
+            )
+        };
+
+        // Runs the protocol over encrypted channels.
+        let mut simulation = Simulation::new();
+        for idx in 1..=n_parties {
+            simulation.add_party(new_party(idx));
+        }
+        let outputs = simulation.run().unwrap();
+        assert_eq!(outputs.len(), n_parties as usize);
+        for (i, output) in outputs.iter().enumerate() {
+            let idx = i as u16 + 1;
+            let mut output = output.clone();
+            output.sort();
+            let mut expected: Vec<_> = (1..=n_parties)
+                .filter(|sender| *sender != idx)
+                .flat_map(|sender| {
+                    [
+                        (sender, None, vec![sender as u8]),
+                        (sender, Some(idx), vec![sender as u8, idx as u8]),
+                    ]
+                })
+                .collect();
+            expected.sort();
+            assert_eq!(output, expected);
+        }
+
+        // Messages of the wrapped state machine are held until all pairwise keys are derived,
+        // and only sealed messages are sent afterwards.
+        let (mut party_1, mut party_2) = (new_party(1), new_party(2));
+        let key_exchange_1 = party_1.message_queue().remove(0);
+        let key_exchange_2 = party_2.message_queue().remove(0);
+        let key_exchange_3 = new_party(3).message_queue().remove(0);
+        assert!(party_1.message_queue().is_empty());
+        party_1.handle_incoming(key_exchange_2.clone()).unwrap();
+        assert!(party_1.message_queue().is_empty());
+        party_1.handle_incoming(key_exchange_3.clone()).unwrap();
+        assert!(party_1.is_established());
+        let sealed_msgs = party_1.message_queue().split_off(0);
+        assert_eq!(sealed_msgs.len(), 2);
+        for msg in &sealed_msgs {
+            match &msg.body {
+                SecureChannelMessage::Sealed {
+                    ciphertext,
+                    broadcast,
+                    ..
+                } => {
+                    assert!(*broadcast);
+                    assert!(msg.receiver.is_some());
+                    assert_ne!(ciphertext, &vec![1u8]);
+                }
+                _ => panic!("expected a sealed message"),
+            }
+        }
+        let sealed_msg_2 = sealed_msgs
+            .iter()
+            .find(|msg| msg.receiver == Some(2))
+            .unwrap()
+            .clone();
+        let sealed_msg_3 = sealed_msgs
+            .iter()
+            .find(|msg| msg.receiver == Some(3))
+            .unwrap()
+            .clone();
+
+        // Sealed messages received before the sender's ephemeral public key are buffered.
+        party_2.handle_incoming(sealed_msg_2.clone()).unwrap();
+        assert_eq!(party_2.state_machine().received, vec![]);
+        party_2.handle_incoming(key_exchange_1.clone()).unwrap();
+        assert_eq!(party_2.state_machine().received, vec![(1, None, vec![1u8])]);
+
+        // Messages sealed for other receivers, re-attributed or tampered with are rejected.
+        party_2.handle_incoming(key_exchange_3).unwrap();
+        let mut reattributed_msg = sealed_msg_3.clone();
+        reattributed_msg.sender = 3;
+        let mut tampered_msg = sealed_msg_2.clone();
+        if let SecureChannelMessage::Sealed { broadcast, .. } = &mut tampered_msg.body {
+            *broadcast = false;
+        }
+        for msg in [sealed_msg_3, reattributed_msg, tampered_msg] {
+            assert!(matches!(party_2.handle_incoming(msg), Err(Error::Blame(_))));
+        }
+
+        // Ephemeral public keys signed by unauthorized parties or for another party index are rejected,
+        // while conflicting ephemeral public keys from the same sender are rejected as equivocation.
+        let mut unauthorized_party = SecureChannel::new(
+            ToyProtocol::new(1, n_parties),
+            &identity_providers[0],
+            &verifying_keys[1..],
+            session_id,
+            Vec::clone,
+            |bytes| Some(bytes.to_vec()),
+        );
+        assert!(matches!(
+            unauthorized_party.handle_incoming(key_exchange_1.clone()),
+            Err(Error::Blame(_))
+        ));
+        let mut misattributed_msg = key_exchange_2.clone();
+        misattributed_msg.sender = 3;
+        assert!(matches!(
+            new_party(1).handle_incoming(misattributed_msg),
+            Err(Error::Blame(_))
+        ));
+        assert!(party_2.handle_incoming(key_exchange_1).is_ok());
+        let conflicting_msg = new_party(1).message_queue().remove(0);
+        assert!(matches!(
+            party_2.handle_incoming(conflicting_msg),
+            Err(Error::Equivocation { idx: 1, .. })
+        ));
+    }
+}
@@ -0,0 +1,721 @@
+//! Management of concurrent protocol sessions (e.g multiple signings, a key generation and a key refresh for the same wallet).
+//!
+//! A [`SessionManager`] assigns session identifiers, routes incoming enveloped messages to the in-flight state machine for their session,
+//! enforces round timeouts and session expiry (and cleans up finished, failed and expired sessions),
+//! and exposes a uniform, transport agnostic poll/submit API to the application.
+//!
+//! Message bodies of managed sessions are encoded as bytes (i.e with a codec supplied for each session),
+//! and outputs and errors are converted into application defined types (e.g enums with a variant for each protocol),
+//! so state machines for different protocols can be managed together.
+//!
+//! Encoded message bodies can be compressed with a compression algorithm negotiated for each session
+//! (see [`wamu_core::compression`] for details).
+//!
+//! Session events can also be published to an [`EventSink`] (e.g for driving UI updates and alerting without polling state).
+//!
+//! **NOTE:** Incoming messages for sessions that haven't been started or joined yet (e.g because other parties started earlier) are buffered
+//! until the session is joined or the messages expire.
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use round_based::{IsCritical, Msg, StateMachine};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use wamu_core::compression::Compression;
+use wamu_core::envelope::ProtocolEnvelope;
+use wamu_core::{compression, CompressionError, EnvelopeError};
+
+use crate::event_sink::EventSink;
+
+/// The maximum number of buffered incoming messages for sessions that haven't been started or joined yet.
+const MAX_PENDING_MESSAGES: usize = 1024;
+
+/// A protocol message (with an encoded message body) wrapped in a protocol envelope.
+pub type SessionEnvelope = ProtocolEnvelope<Msg<Vec<u8>>>;
+
+/// A session manager error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionManagerError {
+    /// An envelope for another protocol version or wallet.
+    Envelope(EnvelopeError),
+    /// A session identifier that's already used by an in-flight session.
+    DuplicateSession,
+    /// A message body that couldn't be decoded.
+    Decoding,
+    /// A message body that couldn't be decompressed.
+    Compression(CompressionError),
+    /// Too many buffered messages for sessions that haven't been started or joined yet.
+    TooManyPendingMessages,
+}
+
+impl From<EnvelopeError> for SessionManagerError {
+    fn from(error: EnvelopeError) -> Self {
+        Self::Envelope(error)
+    }
+}
+
+impl From<CompressionError> for SessionManagerError {
+    fn from(error: CompressionError) -> Self {
+        Self::Compression(error)
+    }
+}
+
+/// A session event.
+#[derive(Debug)]
+pub enum SessionEvent<O, E> {
+    /// An outgoing message that should be sent to its receiver (or all other parties for broadcast messages).
+    Outgoing(SessionEnvelope),
+    /// A session that finished with an output (the session is removed).
+    Finished { session_id: [u8; 32], output: O },
+    /// A state machine error (the session is removed for critical errors).
+    Failed {
+        session_id: [u8; 32],
+        error: E,
+        is_critical: bool,
+    },
+    /// A session that wasn't finished before its deadline (the session is removed).
+    Expired { session_id: [u8; 32] },
+}
+
+/// A result of driving a session.
+struct Step<O, E> {
+    /// Outgoing messages (with encoded message bodies).
+    outgoing: Vec<Msg<Vec<u8>>>,
+    /// The round the state machine advanced to (if any).
+    round_started: Option<u16>,
+    /// Output or state machine errors (and whether they're critical).
+    result: Option<Result<O, (E, bool)>>,
+}
+
+/// A type erased protocol session (i.e so that state machines for different protocols can be managed together).
+trait Session<O, E> {
+    /// Decodes an incoming message and forwards it to the state machine,
+    /// and returns the state machine error (and whether it's critical) if any.
+    fn handle_incoming(
+        &mut self,
+        msg: Msg<Vec<u8>>,
+    ) -> Result<Option<(E, bool)>, SessionManagerError>;
+
+    /// Proceeds while the state machine wants to, enforces the round timeout
+    /// and returns outgoing messages and the output or state machine error (if any).
+    fn drive(&mut self) -> Step<O, E>;
+
+    /// Returns the current round of the state machine.
+    fn current_round(&self) -> u16;
+}
+
+/// A protocol session for a state machine.
+struct StateMachineSession<SM: StateMachine> {
+    /// Wrapped state machine.
+    state_machine: SM,
+    /// Encoder for message bodies of the state machine.
+    encode_body: fn(&SM::MessageBody) -> Vec<u8>,
+    /// Decoder for message bodies of the state machine.
+    decode_body: fn(&[u8]) -> Option<SM::MessageBody>,
+    /// Current round and its deadline (if any).
+    round: (u16, Option<Instant>),
+}
+
+impl<SM: StateMachine> StateMachineSession<SM> {
+    /// Returns the current round of the state machine and its deadline (if any).
+    fn current_round(state_machine: &SM) -> (u16, Option<Instant>) {
+        (
+            state_machine.current_round(),
+            state_machine
+                .round_timeout()
+                .map(|round_timeout| Instant::now() + round_timeout),
+        )
+    }
+}
+
+impl<SM, O, E> Session<O, E> for StateMachineSession<SM>
+where
+    SM: StateMachine,
+    SM::Output: Into<O>,
+    SM::Err: Into<E>,
+{
+    fn handle_incoming(
+        &mut self,
+        msg: Msg<Vec<u8>>,
+    ) -> Result<Option<(E, bool)>, SessionManagerError> {
+        let body = (self.decode_body)(&msg.body).ok_or(SessionManagerError::Decoding)?;
+        Ok(self
+            .state_machine
+            .handle_incoming(Msg {
+                sender: msg.sender,
+                receiver: msg.receiver,
+                body,
+            })
+            .err()
+            .map(|error| {
+                let is_critical = error.is_critical();
+                (error.into(), is_critical)
+            }))
+    }
+
+    fn drive(&mut self) -> Step<O, E> {
+        let mut error = None;
+        while error.is_none() && self.state_machine.wants_to_proceed() {
+            error = self.state_machine.proceed().err();
+        }
+
+        // Restarts the round timer when the state machine advances to the next round,
+        // and reports round timeouts (only once per round, unless they're non-critical e.g retransmission timers of reliable delivery).
+        let mut round_started = None;
+        if self.state_machine.current_round() != self.round.0 {
+            self.round = Self::current_round(&self.state_machine);
+            round_started = Some(self.round.0);
+        }
+        if error.is_none()
+            && self
+                .round
+                .1
+                .map_or(false, |deadline| Instant::now() >= deadline)
+        {
+            let timeout_error = self.state_machine.round_timeout_reached();
+            self.round.1 = if timeout_error.is_critical() {
+                None
+            } else {
+                Self::current_round(&self.state_machine).1
+            };
+            error = Some(timeout_error);
+        }
+
+        let encode_body = self.encode_body;
+        let outgoing = self
+            .state_machine
+            .message_queue()
+            .split_off(0)
+            .into_iter()
+            .map(|msg| msg.map_body(|body| encode_body(&body)))
+            .collect();
+        let result = match error {
+            Some(error) => {
+                let is_critical = error.is_critical();
+                Some(Err((error.into(), is_critical)))
+            }
+            None => self.state_machine.pick_output().map(|result| {
+                result.map(Into::into).map_err(|error| {
+                    let is_critical = error.is_critical();
+                    (error.into(), is_critical)
+                })
+            }),
+        };
+        Step {
+            outgoing,
+            round_started,
+            result,
+        }
+    }
+
+    fn current_round(&self) -> u16 {
+        self.state_machine.current_round()
+    }
+}
+
+/// An in-flight session.
+struct Entry<'a, O, E> {
+    /// Type erased protocol session.
+    session: Box<dyn Session<O, E> + 'a>,
+    /// Deadline of the session.
+    expires_at: Instant,
+    /// Negotiated compression algorithm for outgoing message bodies.
+    compression: Compression,
+}
+
+/// A manager of concurrent protocol sessions for a wallet (see module documentation for details).
+///
+/// Outputs and errors of managed state machines are converted into `O` and `E` respectively.
+pub struct SessionManager<'a, O, E> {
+    /// Negotiated protocol version.
+    version: u16,
+    /// Identifier of the wallet.
+    wallet_id: [u8; 32],
+    /// Time to live for sessions and buffered messages.
+    session_ttl: Duration,
+    /// In-flight sessions indexed by session identifier.
+    sessions: BTreeMap<[u8; 32], Entry<'a, O, E>>,
+    /// Buffered incoming messages for sessions that haven't been started or joined yet (and their time of receipt).
+    pending_messages: Vec<(Instant, SessionEnvelope)>,
+    /// Events that haven't been polled yet.
+    events: Vec<SessionEvent<O, E>>,
+    /// Event sink (if any).
+    event_sink: Option<&'a dyn EventSink>,
+}
+
+impl<'a, O, E> SessionManager<'a, O, E> {
+    /// Given the negotiated protocol version, the wallet identifier and the time to live for sessions
+    /// (i.e the time after which unfinished sessions and buffered messages for unknown sessions expire),
+    /// returns a session manager without any sessions.
+    pub fn new(version: u16, wallet_id: [u8; 32], session_ttl: Duration) -> Self {
+        Self {
+            version,
+            wallet_id,
+            session_ttl,
+            sessions: BTreeMap::new(),
+            pending_messages: Vec::new(),
+            events: Vec::new(),
+            event_sink: None,
+        }
+    }
+
+    /// Sets the event sink (i.e for publishing round, message and completion events for all sessions).
+    pub fn with_event_sink(mut self, event_sink: &'a dyn EventSink) -> Self {
+        self.event_sink = Some(event_sink);
+        self
+    }
+
+    /// Given a state machine and a codec (i.e encoder and decoder) for its message bodies,
+    /// starts a session with a fresh random session identifier and returns the session identifier.
+    ///
+    /// **NOTE:** The session identifier must be shared with other parties (e.g as part of the identity authenticated request that initiates the session),
+    /// so that they can join the session.
+    pub fn start<SM>(
+        &mut self,
+        state_machine: SM,
+        encode_body: fn(&SM::MessageBody) -> Vec<u8>,
+        decode_body: fn(&[u8]) -> Option<SM::MessageBody>,
+    ) -> [u8; 32]
+    where
+        SM: StateMachine + 'a,
+        SM::Output: Into<O>,
+        SM::Err: Into<E>,
+    {
+        let mut session_id = [0u8; 32];
+        loop {
+            OsRng.fill_bytes(&mut session_id);
+            if !self.sessions.contains_key(&session_id) {
+                break;
+            }
+        }
+        self.insert(session_id, state_machine, encode_body, decode_body);
+        session_id
+    }
+
+    /// Given a session identifier, a state machine and a codec (i.e encoder and decoder) for its message bodies,
+    /// joins a session started by another party (and replays buffered messages for the session),
+    /// or returns an error if the session identifier is already used by an in-flight session.
+    pub fn join<SM>(
+        &mut self,
+        session_id: [u8; 32],
+        state_machine: SM,
+        encode_body: fn(&SM::MessageBody) -> Vec<u8>,
+        decode_body: fn(&[u8]) -> Option<SM::MessageBody>,
+    ) -> Result<(), SessionManagerError>
+    where
+        SM: StateMachine + 'a,
+        SM::Output: Into<O>,
+        SM::Err: Into<E>,
+    {
+        if self.sessions.contains_key(&session_id) {
+            return Err(SessionManagerError::DuplicateSession);
+        }
+        self.insert(session_id, state_machine, encode_body, decode_body);
+
+        // Replays buffered messages for the session.
+        let (ready, pending): (Vec<_>, Vec<_>) = self
+            .pending_messages
+            .drain(..)
+            .partition(|(_, envelope)| envelope.session_id == session_id);
+        self.pending_messages = pending;
+        for (_, envelope) in ready {
+            // Buffered messages with bodies that can't be decoded are dropped.
+            let _ = self.submit(envelope);
+        }
+        Ok(())
+    }
+
+    /// Given an incoming enveloped message, routes it to the in-flight session for its session identifier
+    /// (or buffers it if the session hasn't been started or joined yet),
+    /// or returns an appropriate error for envelopes for another protocol version or wallet and message bodies that can't be decoded.
+    ///
+    /// **NOTE:** State machine errors are reported as [`SessionEvent::Failed`] events by [`SessionManager::poll`].
+    pub fn submit(&mut self, envelope: SessionEnvelope) -> Result<(), SessionManagerError> {
+        let session_id = envelope.session_id;
+        let Some(entry) = self.sessions.get_mut(&session_id) else {
+            // Validates the envelope before buffering it.
+            if envelope.version != self.version {
+                return Err(EnvelopeError::VersionMismatch.into());
+            } else if envelope.wallet_id != self.wallet_id {
+                return Err(EnvelopeError::WalletMismatch.into());
+            } else if self.pending_messages.len() >= MAX_PENDING_MESSAGES {
+                return Err(SessionManagerError::TooManyPendingMessages);
+            }
+            self.pending_messages.push((Instant::now(), envelope));
+            return Ok(());
+        };
+        let compression = envelope.compression;
+        let mut msg = envelope.open(self.version, &self.wallet_id, &session_id)?;
+        msg.body = compression::decompress(compression, &msg.body)?;
+        let (sender, round) = (msg.sender, entry.session.current_round());
+        if let Some((error, is_critical)) = entry.session.handle_incoming(msg)? {
+            if is_critical {
+                self.sessions.remove(&session_id);
+                if let Some(event_sink) = self.event_sink {
+                    event_sink.on_session_complete(Some(&session_id), false);
+                }
+            }
+            self.events.push(SessionEvent::Failed {
+                session_id,
+                error,
+                is_critical,
+            });
+        } else if let Some(event_sink) = self.event_sink {
+            event_sink.on_message_received(Some(&session_id), sender, round);
+        }
+        Ok(())
+    }
+
+    /// Drives all in-flight sessions and returns all events since the last poll
+    /// (i.e outgoing messages, outputs, errors and expired sessions).
+    ///
+    /// Finished, expired and critically failed sessions are removed, and expired buffered messages are dropped.
+    ///
+    /// **NOTE:** The session manager should be polled after every submitted message and periodically (e.g every second),
+    /// so that round timeouts and session expiry are enforced.
+    pub fn poll(&mut self) -> Vec<SessionEvent<O, E>> {
+        let now = Instant::now();
+        let event_sink = self.event_sink;
+        let mut events = std::mem::take(&mut self.events);
+        let mut removed = Vec::new();
+        for (session_id, entry) in self.sessions.iter_mut() {
+            if now >= entry.expires_at {
+                events.push(SessionEvent::Expired {
+                    session_id: *session_id,
+                });
+                removed.push(*session_id);
+                if let Some(event_sink) = event_sink {
+                    event_sink.on_session_complete(Some(session_id), false);
+                }
+                continue;
+            }
+            let step = entry.session.drive();
+            if let (Some(event_sink), Some(round)) = (event_sink, step.round_started) {
+                event_sink.on_round_started(Some(session_id), round);
+            }
+            events.extend(step.outgoing.into_iter().map(|mut msg| {
+                let (compression, body) = compression::compress(entry.compression, &msg.body);
+                msg.body = body;
+                SessionEvent::Outgoing(
+                    ProtocolEnvelope::new(self.version, self.wallet_id, *session_id, msg)
+                        .with_compression(compression),
+                )
+            }));
+            match step.result {
+                Some(Ok(output)) => {
+                    events.push(SessionEvent::Finished {
+                        session_id: *session_id,
+                        output,
+                    });
+                    removed.push(*session_id);
+                    if let Some(event_sink) = event_sink {
+                        event_sink.on_session_complete(Some(session_id), true);
+                    }
+                }
+                Some(Err((error, is_critical))) => {
+                    events.push(SessionEvent::Failed {
+                        session_id: *session_id,
+                        error,
+                        is_critical,
+                    });
+                    if is_critical {
+                        removed.push(*session_id);
+                        if let Some(event_sink) = event_sink {
+                            event_sink.on_session_complete(Some(session_id), false);
+                        }
+                    }
+                }
+                None => (),
+            }
+        }
+        for session_id in removed {
+            self.sessions.remove(&session_id);
+        }
+        let session_ttl = self.session_ttl;
+        self.pending_messages
+            .retain(|(received_at, _)| now.duration_since(*received_at) < session_ttl);
+        events
+    }
+
+    /// Removes an in-flight session (e.g because it was cancelled by the application) and drops buffered messages for it,
+    /// and returns true if the session was in-flight.
+    pub fn remove(&mut self, session_id: &[u8; 32]) -> bool {
+        self.pending_messages
+            .retain(|(_, envelope)| &envelope.session_id != session_id);
+        self.sessions.remove(session_id).is_some()
+    }
+
+    /// Sets the negotiated compression algorithm for outgoing message bodies of an in-flight session
+    /// (see [`wamu_core::compression::negotiate`]), and returns true if the session is in-flight.
+    ///
+    /// **NOTE:** Incoming message bodies are decompressed with the compression algorithm in their envelope,
+    /// so parties can switch compression on at different times.
+    pub fn set_compression(&mut self, session_id: &[u8; 32], compression: Compression) -> bool {
+        match self.sessions.get_mut(session_id) {
+            Some(entry) => {
+                entry.compression = compression;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the identifiers of all in-flight sessions.
+    pub fn session_ids(&self) -> Vec<[u8; 32]> {
+        self.sessions.keys().copied().collect()
+    }
+
+    /// Adds an in-flight session.
+    fn insert<SM>(
+        &mut self,
+        session_id: [u8; 32],
+        state_machine: SM,
+        encode_body: fn(&SM::MessageBody) -> Vec<u8>,
+        decode_body: fn(&[u8]) -> Option<SM::MessageBody>,
+    ) where
+        SM: StateMachine + 'a,
+        SM::Output: Into<O>,
+        SM::Err: Into<E>,
+    {
+        let round = StateMachineSession::current_round(&state_machine);
+        self.sessions.insert(
+            session_id,
+            Entry {
+                session: Box::new(StateMachineSession {
+                    state_machine,
+                    encode_body,
+                    decode_body,
+                    round,
+                }),
+                expires_at: Instant::now() + self.session_ttl,
+                compression: Compression::None,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secure_channel::tests::{ToyError, ToyProtocol};
+    use std::cell::{Cell, RefCell};
+
+    type ToyOutput = <ToyProtocol as StateMachine>::Output;
+
+    // Records published events.
+    #[derive(Default)]
+    struct EventRecorder {
+        rounds_started: Cell<usize>,
+        messages_received: Cell<usize>,
+        completed: RefCell<Vec<([u8; 32], bool)>>,
+    }
+
+    impl EventSink for EventRecorder {
+        fn on_round_started(&self, _session_id: Option<&[u8; 32]>, _round: u16) {
+            self.rounds_started.set(self.rounds_started.get() + 1);
+        }
+
+        fn on_message_received(&self, _session_id: Option<&[u8; 32]>, _sender: u16, _round: u16) {
+            self.messages_received.set(self.messages_received.get() + 1);
+        }
+
+        fn on_session_complete(&self, session_id: Option<&[u8; 32]>, success: bool) {
+            self.completed
+                .borrow_mut()
+                .push((*session_id.unwrap(), success));
+        }
+    }
+
+    // Routes outgoing messages from polled events to their receivers and returns the outputs of finished sessions.
+    fn run_managers(
+        managers: &mut [SessionManager<'_, ToyOutput, ToyError>],
+    ) -> Vec<Vec<([u8; 32], ToyOutput)>> {
+        let mut outputs = vec![Vec::new(); managers.len()];
+        loop {
+            let mut outgoing = Vec::new();
+            for (idx, manager) in managers.iter_mut().enumerate() {
+                for event in manager.poll() {
+                    match event {
+                        SessionEvent::Outgoing(envelope) => outgoing.push(envelope),
+                        SessionEvent::Finished { session_id, output } => {
+                            outputs[idx].push((session_id, output))
+                        }
+                        event => panic!("unexpected event: {event:?}"),
+                    }
+                }
+            }
+            if outgoing.is_empty() {
+                return outputs;
+            }
+            for envelope in outgoing {
+                for (idx, manager) in managers.iter_mut().enumerate() {
+                    let receiver = idx as u16 + 1;
+                    if receiver != envelope.body.sender
+                        && envelope.body.receiver.map_or(true, |it| it == receiver)
+                    {
+                        manager.submit(envelope.clone()).unwrap();
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn session_manager_works() {
+        let n_parties = 3u16;
+        let wallet_id = [1u8; 32];
+        let session_ttl = Duration::from_secs(60);
+        let event_recorder = EventRecorder::default();
+        let mut managers: Vec<SessionManager<'_, ToyOutput, ToyError>> = (0..n_parties)
+            .map(|idx| {
+                let manager = SessionManager::new(1, wallet_id, session_ttl);
+                if idx == 0 {
+                    manager.with_event_sink(&event_recorder)
+                } else {
+                    manager
+                }
+            })
+            .collect();
+        let encode = Vec::clone;
+        let decode = |bytes: &[u8]| Some(bytes.to_vec());
+
+        // Party 1 starts two concurrent sessions, while other parties join one before and one after receiving its messages.
+        let session_id_1 = managers[0].start(ToyProtocol::new(1, n_parties), encode, decode);
+        let session_id_2 = managers[0].start(ToyProtocol::new(1, n_parties), encode, decode);
+        assert_ne!(session_id_1, session_id_2);
+        for (idx, manager) in managers.iter_mut().enumerate().skip(1) {
+            manager
+                .join(
+                    session_id_1,
+                    ToyProtocol::new(idx as u16 + 1, n_parties),
+                    encode,
+                    decode,
+                )
+                .unwrap();
+        }
+        let outgoing: Vec<_> = managers[0]
+            .poll()
+            .into_iter()
+            .filter_map(|event| match event {
+                SessionEvent::Outgoing(envelope) => Some(envelope),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(outgoing.len(), 2);
+        for manager in managers.iter_mut().skip(1) {
+            for envelope in &outgoing {
+                manager.submit(envelope.clone()).unwrap();
+            }
+        }
+        for (idx, manager) in managers.iter_mut().enumerate().skip(1) {
+            manager
+                .join(
+                    session_id_2,
+                    ToyProtocol::new(idx as u16 + 1, n_parties),
+                    encode,
+                    decode,
+                )
+                .unwrap();
+            assert_eq!(
+                manager.join(
+                    session_id_2,
+                    ToyProtocol::new(idx as u16 + 1, n_parties),
+                    encode,
+                    decode,
+                ),
+                Err(SessionManagerError::DuplicateSession)
+            );
+        }
+
+        // All sessions finish and are removed.
+        let outputs = run_managers(&mut managers);
+        for (idx, party_outputs) in outputs.iter().enumerate() {
+            let mut session_ids: Vec<_> = party_outputs.iter().map(|(id, _)| *id).collect();
+            session_ids.sort();
+            let mut expected_session_ids = vec![session_id_1, session_id_2];
+            expected_session_ids.sort();
+            assert_eq!(session_ids, expected_session_ids);
+            for (_, output) in party_outputs {
+                assert_eq!(output.len(), 2 * (n_parties as usize - 1));
+            }
+            assert!(managers[idx].session_ids().is_empty());
+        }
+
+        // Events are published for all rounds, received messages and completed sessions.
+        assert_eq!(event_recorder.rounds_started.get(), 2 * 2);
+        assert_eq!(
+            event_recorder.messages_received.get(),
+            2 * 2 * (n_parties as usize - 1)
+        );
+        let mut completed = event_recorder.completed.take();
+        completed.sort();
+        let mut expected_completed = vec![(session_id_1, true), (session_id_2, true)];
+        expected_completed.sort();
+        assert_eq!(completed, expected_completed);
+
+        // Envelopes for another protocol version or wallet are rejected.
+        let envelope = |version: u16, wallet_id: [u8; 32]| {
+            ProtocolEnvelope::new(
+                version,
+                wallet_id,
+                session_id_1,
+                Msg {
+                    sender: 2,
+                    receiver: None,
+                    body: vec![2u8],
+                },
+            )
+        };
+        assert_eq!(
+            managers[0].submit(envelope(2, wallet_id)),
+            Err(SessionManagerError::Envelope(
+                EnvelopeError::VersionMismatch
+            ))
+        );
+        assert_eq!(
+            managers[0].submit(envelope(1, [2u8; 32])),
+            Err(SessionManagerError::Envelope(EnvelopeError::WalletMismatch))
+        );
+
+        // Unfinished sessions and buffered messages expire.
+        let mut manager: SessionManager<'_, ToyOutput, ToyError> =
+            SessionManager::new(1, wallet_id, Duration::ZERO);
+        manager.submit(envelope(1, wallet_id)).unwrap();
+        let session_id = manager.start(ToyProtocol::new(1, n_parties), encode, decode);
+        let events = manager.poll();
+        assert!(matches!(
+            events.as_slice(),
+            [SessionEvent::Expired { session_id: id }] if *id == session_id
+        ));
+        assert!(manager.session_ids().is_empty());
+        manager
+            .join(session_id_1, ToyProtocol::new(1, n_parties), encode, decode)
+            .unwrap();
+
+        // Compressed message bodies are decompressed with the algorithm in their envelope,
+        // and bodies compressed with algorithms that aren't enabled are rejected.
+        assert!(manager.set_compression(
+            &session_id_1,
+            compression::negotiate(&[Compression::supported()])
+        ));
+        assert!(!manager.set_compression(&session_id, Compression::None));
+        for compression in [Compression::Deflate, Compression::Zstd] {
+            let (used, body) = compression::compress(compression, &[2u8; 1024]);
+            let mut compressed = envelope(1, wallet_id).with_compression(used);
+            compressed.body.body = body;
+            if compression.is_supported() {
+                assert_eq!(used, compression);
+                assert_eq!(manager.submit(compressed), Ok(()));
+            } else {
+                assert_eq!(
+                    manager.submit(compressed.with_compression(compression)),
+                    Err(SessionManagerError::Compression(
+                        CompressionError::Unsupported
+                    ))
+                );
+            }
+        }
+        assert!(manager.remove(&session_id_1));
+        assert!(!manager.remove(&session_id_1));
+    }
+}
@@ -1,6 +1,9 @@
 //! Share addition implementation.
 //!
 //! Ref: <https://wamu.tech/specification#share-addition>.
+//!
+//! **NOTE:** Multiple new parties can be added in a single session (i.e each new party runs its own instance with its new index),
+//! and the threshold can optionally be modified in the same session (e.g to grow a 3-of-5 wallet to a 5-of-9 wallet in one ceremony).
 
 use curv::elliptic::curves::Secp256k1;
 use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::keygen::LocalKey;
@@ -8,6 +11,7 @@ use round_based::{Msg, StateMachine};
 use std::collections::HashMap;
 use std::time::Duration;
 use wamu_core::crypto::VerifyingKey;
+use wamu_core::quorum_approved_request::QuorumPolicy;
 use wamu_core::{IdentityProvider, SigningShare, SubShare};
 
 use crate::authorized_key_refresh::{AuthorizedKeyRefresh, Error, Message};
@@ -16,6 +20,7 @@ use crate::quorum_approval;
 use crate::quorum_approval::QuorumApproval;
 
 const SHARE_ADDITION: &str = "share-addition";
+const SHARE_ADDITION_WITH_THRESHOLD_MODIFICATION: &str = "share-addition-threshold-modification";
 
 /// A [StateMachine](StateMachine) that implements [share addition as described by the Wamu protocol](https://wamu.tech/specification#share-addition).
 pub struct ShareAddition<'a, I: IdentityProvider> {
@@ -40,9 +45,11 @@ pub struct ShareAddition<'a, I: IdentityProvider> {
     local_key_option: Option<LocalKey<Secp256k1>>,
     /// Maps existing indices to new ones for refreshing parties.
     old_to_new_map: &'a HashMap<u16, u16>,
-    /// The threshold.
+    /// The current threshold.
     // NOTE: Quorum size = threshold + 1
     threshold: u16,
+    /// The new threshold.
+    new_threshold: u16,
 
     // State machine management.
     /// Outgoing message queue.
@@ -70,6 +77,8 @@ impl<'a, I: IdentityProvider> ShareAddition<'a, I> {
         // NOTE: Quorum size = threshold + 1
         current_threshold_option: Option<u16>,
         current_n_parties_option: Option<u16>,
+        // The new threshold (if the threshold is modified in the same session).
+        new_threshold_option: Option<u16>,
         is_initiator: bool,
     ) -> Result<ShareAddition<'a, I>, Error<'a, I, <QuorumApproval<'a, I> as StateMachine>::Err>>
     {
@@ -89,8 +98,22 @@ impl<'a, I: IdentityProvider> ShareAddition<'a, I> {
             .map(|it| it.n)
             .or(current_n_parties_option)
             .ok_or(Error::InvalidInput)?;
+        if n_parties <= current_n_parties || idx > n_parties {
+            // At least one party must be added and the party index must be in range.
+            return Err(Error::InvalidInput);
+        }
+        if local_key_option.is_none() && old_to_new_map.values().any(|it| *it == idx) {
+            // New party indices can't collide with the new indices of existing parties.
+            return Err(Error::InvalidInput);
+        }
+        let new_threshold = new_threshold_option.unwrap_or(threshold);
         let auth_state_machine = QuorumApproval::new(
-            SHARE_ADDITION,
+            // Threshold modifications are approved separately from plain share additions.
+            if new_threshold == threshold {
+                SHARE_ADDITION
+            } else {
+                SHARE_ADDITION_WITH_THRESHOLD_MODIFICATION
+            },
             identity_provider,
             verified_parties,
             idx,
@@ -98,6 +121,7 @@ impl<'a, I: IdentityProvider> ShareAddition<'a, I> {
             current_n_parties,
             is_initiator,
             local_key_option.is_none(),
+            QuorumPolicy::SigningQuorum,
         );
 
         // Initializes share addition state machine.
@@ -113,6 +137,7 @@ impl<'a, I: IdentityProvider> ShareAddition<'a, I> {
             local_key_option,
             old_to_new_map,
             threshold,
+            new_threshold,
             // State machine management.
             message_queue: Vec::new(),
             auth_state_machine,
@@ -132,6 +157,7 @@ impl<'a, I: IdentityProvider> AuthorizedKeyRefresh<'a, I> for ShareAddition<'a,
     type InitStateMachineType = QuorumApproval<'a, I>;
 
     impl_required_authorized_key_refresh_getters!(
+        verified_parties,
         auth_state_machine,
         refresh_state_machine,
         message_queue,
@@ -154,7 +180,7 @@ impl<'a, I: IdentityProvider> AuthorizedKeyRefresh<'a, I> for ShareAddition<'a,
             self.local_key_option.take(),
             is_new_party.then_some(self.idx),
             self.old_to_new_map,
-            self.threshold,
+            self.new_threshold,
             self.n_parties,
             is_new_party.then_some(self.threshold),
         )?)
@@ -195,6 +221,7 @@ pub mod tests {
         )>,
         current_to_new_idx_map: &HashMap<u16, u16>,
         n_parties: u16,
+        new_threshold_option: Option<u16>,
     ) -> Vec<AugmentedType<LocalKey<Secp256k1>, SubShareOutput>> {
         // Creates simulation.
         let mut simulation = Simulation::new();
@@ -229,6 +256,7 @@ pub mod tests {
                     current_to_new_idx_map,
                     current_threshold_option,
                     current_n_parties_option,
+                    new_threshold_option,
                     is_initiator,
                 )
                 .unwrap(),
@@ -243,6 +271,7 @@ pub mod tests {
         threshold: u16,
         n_parties_init: u16,
         n_parties_new: u16,
+        new_threshold_option: Option<u16>,
         initiating_party_idx: u16,
     ) -> (
         (
@@ -310,14 +339,18 @@ pub mod tests {
         }
 
         // Runs share addition simulation for test parameters.
-        let new_keys =
-            simulate_share_addition(party_key_configs, &current_to_new_idx_map, n_parties_new);
+        let new_keys = simulate_share_addition(
+            party_key_configs,
+            &current_to_new_idx_map,
+            n_parties_new,
+            new_threshold_option,
+        );
 
         // Verifies the refreshed/generated keys and configuration for all parties.
         assert_eq!(new_keys.len(), n_parties_new as usize);
         for (i, new_key) in new_keys.iter().enumerate() {
             // Verifies threshold and number of parties.
-            assert_eq!(new_key.base.t, threshold);
+            assert_eq!(new_key.base.t, new_threshold_option.unwrap_or(threshold));
             assert_eq!(new_key.base.n, n_parties_new);
             // Verifies that the secret share was cleared/zerorized.
             assert_eq!(new_key.base.keys_linear.x_i, Scalar::<Secp256k1>::zero());
@@ -343,6 +376,11 @@ pub mod tests {
 
     #[test]
     fn share_addition_works() {
-        generate_parties_and_simulate_share_addition(2, 4, 5, 2);
+        generate_parties_and_simulate_share_addition(2, 4, 5, None, 2);
+    }
+
+    #[test]
+    fn share_addition_of_multiple_parties_with_new_threshold_works() {
+        generate_parties_and_simulate_share_addition(1, 2, 4, Some(2), 1);
     }
 }
@@ -0,0 +1,344 @@
+//! Share recovery with quorum for a party with a new identity implementation.
+//!
+//! Combines (quorum approved) identity replacement and share recovery with quorum,
+//! i.e a party that lost both its device and its identity key presents a new identity,
+//! a quorum of the surviving parties explicitly approves the replacement and then the recovery key refresh runs.
+//!
+//! Refs:
+//! - <https://wamu.tech/specification#share-recovery-quorum>.
+//! - <https://wamu.tech/specification#identity-rotation>.
+//!
+//! **NOTE:** All parties (including the recovering party) must use a list of verifying keys
+//! where the recovering party's lost verifying key is replaced with its new verifying key.
+
+use curv::elliptic::curves::Secp256k1;
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::keygen::LocalKey;
+use round_based::{Msg, StateMachine};
+use std::collections::HashMap;
+use std::time::Duration;
+use wamu_core::crypto::VerifyingKey;
+use wamu_core::quorum_approved_request::QuorumPolicy;
+use wamu_core::{IdentityProvider, SigningShare, SubShare};
+
+use crate::authorized_key_refresh::{AuthorizedKeyRefresh, Error, Message};
+use crate::key_refresh::AugmentedKeyRefresh;
+use crate::quorum_approval;
+use crate::quorum_approval::QuorumApproval;
+
+const SHARE_RECOVERY_NEW_IDENTITY: &str = "share-recovery-new-identity";
+
+/// A [StateMachine](StateMachine) that implements share recovery with quorum for a party with a new identity
+/// (i.e quorum approved identity replacement followed by [share recovery with quorum](https://wamu.tech/specification#share-recovery-quorum)).
+pub struct ShareRecoveryNewIdentity<'a, I: IdentityProvider> {
+    // Quorum approval.
+    /// The decentralized identity provider of the party.
+    identity_provider: &'a I,
+    /// Verifying keys for other the parties (including the new verifying key of the recovering party).
+    verified_parties: &'a [VerifyingKey],
+    /// Party index.
+    idx: u16,
+    /// Total number of parties.
+    n_parties: u16,
+
+    // Key refresh.
+    /// The "signing share" of the party
+    /// (only `None` for the recovering party, `Some` for all other parties).
+    signing_share_option: Option<&'a SigningShare>,
+    /// The "sub-share" of the party
+    /// (only `None` for the recovering party, `Some` for all other parties).
+    sub_share_option: Option<&'a SubShare>,
+    /// Local key of the party (with secret share cleared/zerorized).
+    local_key_option: Option<LocalKey<Secp256k1>>,
+    /// Maps existing indices to new ones for refreshing parties.
+    old_to_new_map: &'a HashMap<u16, u16>,
+    /// The threshold.
+    // NOTE: Quorum size = threshold + 1
+    threshold: u16,
+
+    // State machine management.
+    /// Outgoing message queue.
+    message_queue: Vec<Msg<Message<'a, I, quorum_approval::Message>>>,
+    /// Quorum approval state machine for the identity replacement (must succeed before key refresh is performed).
+    auth_state_machine: QuorumApproval<'a, I>,
+    /// Key refresh state machine (activated after successful quorum approval).
+    refresh_state_machine: Option<AugmentedKeyRefresh<'a, I>>,
+    /// Stores "out of order" messages.
+    out_of_order_buffer: Vec<Msg<Message<'a, I, quorum_approval::Message>>>,
+}
+
+impl<'a, I: IdentityProvider> ShareRecoveryNewIdentity<'a, I> {
+    /// Initializes party for the share recovery with quorum for a party with a new identity protocol.
+    ///
+    /// **NOTE:** The recovering party is the party whose index isn't mapped by `old_to_new_map`,
+    /// and it can't initiate the request because its new identity isn't approved yet.
+    pub fn new(
+        signing_share_option: Option<&'a SigningShare>,
+        sub_share_option: Option<&'a SubShare>,
+        identity_provider: &'a I,
+        // Verifying keys with the recovering party's new verifying key.
+        verified_parties: &'a [VerifyingKey],
+        // `LocalKey<Secp256k1>` with secret share set to zero.
+        local_key_option: Option<LocalKey<Secp256k1>>,
+        party_index_option: Option<u16>,
+        n_parties: u16,
+        old_to_new_map: &'a HashMap<u16, u16>,
+        // NOTE: Quorum size = threshold + 1
+        current_threshold_option: Option<u16>,
+        is_initiator: bool,
+    ) -> Result<
+        ShareRecoveryNewIdentity<'a, I>,
+        Error<'a, I, <QuorumApproval<'a, I> as StateMachine>::Err>,
+    > {
+        // Resolves the index of the recovering party (i.e the only party that isn't refreshing).
+        let mut recovering_indices =
+            (1..=n_parties).filter(|idx| !old_to_new_map.values().any(|it| it == idx));
+        let recovering_idx = match (recovering_indices.next(), recovering_indices.next()) {
+            (Some(recovering_idx), None) => recovering_idx,
+            _ => return Err(Error::InvalidInput),
+        };
+        let new_verifying_key = verified_parties
+            .get(recovering_idx as usize - 1)
+            .ok_or(Error::InvalidInput)?;
+
+        let idx = local_key_option
+            .as_ref()
+            .map(|it| it.i)
+            .or(party_index_option)
+            .ok_or(Error::InvalidInput)?;
+        let is_recovering_party = local_key_option.is_none();
+        if is_recovering_party && (idx != recovering_idx || is_initiator) {
+            // The recovering party's new identity can't authenticate the request.
+            return Err(Error::InvalidInput);
+        }
+
+        let threshold = local_key_option
+            .as_ref()
+            .map(|it| it.t)
+            .or(current_threshold_option)
+            .ok_or(Error::InvalidInput)?;
+
+        // Initializes quorum approval state machine for a command that's bound to the new identity.
+        let command = format!(
+            "{SHARE_RECOVERY_NEW_IDENTITY}:{recovering_idx}:{}",
+            wamu_core::utils::to_hex(&new_verifying_key.key)
+        );
+        let auth_state_machine = QuorumApproval::new(
+            &command,
+            identity_provider,
+            verified_parties,
+            idx,
+            threshold,
+            n_parties,
+            is_initiator,
+            // The recovering party is dormant during the quorum approval.
+            is_recovering_party,
+            // Identity replacement requires approval from all surviving parties.
+            QuorumPolicy::AllParties,
+        )
+        // The recovering party can't approve its own new identity.
+        .with_participants(
+            &(1..=n_parties)
+                .filter(|idx| *idx != recovering_idx)
+                .collect::<Vec<u16>>(),
+        );
+
+        // Initializes share recovery state machine.
+        let mut share_recovery = Self {
+            // Quorum approval.
+            identity_provider,
+            verified_parties,
+            idx,
+            n_parties,
+            // Key refresh.
+            signing_share_option,
+            sub_share_option,
+            local_key_option,
+            old_to_new_map,
+            threshold,
+            // State machine management.
+            message_queue: Vec::new(),
+            auth_state_machine,
+            refresh_state_machine: None,
+            out_of_order_buffer: Vec::new(),
+        };
+
+        // Retrieves messages from immediate state transitions (if any) and wraps them.
+        share_recovery.update_composite_message_queue()?;
+
+        // Returns share recovery machine.
+        Ok(share_recovery)
+    }
+}
+
+impl<'a, I: IdentityProvider> AuthorizedKeyRefresh<'a, I> for ShareRecoveryNewIdentity<'a, I> {
+    type InitStateMachineType = QuorumApproval<'a, I>;
+
+    impl_required_authorized_key_refresh_getters!(
+        verified_parties,
+        auth_state_machine,
+        refresh_state_machine,
+        message_queue,
+        out_of_order_buffer
+    );
+
+    fn verify_authorization(
+        &self,
+    ) -> Result<(), Error<'a, I, <Self::InitStateMachineType as StateMachine>::Err>> {
+        // The new identity is only trusted after the quorum approval is verified.
+        self.auth_state_machine.verify_quorum().map_err(Error::Init)
+    }
+
+    fn create_key_refresh(
+        &mut self,
+    ) -> Result<
+        AugmentedKeyRefresh<'a, I>,
+        Error<'a, I, <Self::InitStateMachineType as StateMachine>::Err>,
+    > {
+        // Initializes key refresh state machine.
+        let is_recovering_party = self.local_key_option.is_none();
+        Ok(AugmentedKeyRefresh::new(
+            self.signing_share_option,
+            self.sub_share_option,
+            self.identity_provider,
+            self.verified_parties,
+            self.local_key_option.take(),
+            is_recovering_party.then_some(self.idx),
+            self.old_to_new_map,
+            self.threshold,
+            self.n_parties,
+            is_recovering_party.then_some(self.threshold),
+        )?)
+    }
+}
+
+impl_state_machine_for_authorized_key_refresh!(ShareRecoveryNewIdentity, idx, n_parties);
+
+// Implement `Debug` trait for `ShareRecoveryNewIdentity` for test simulations.
+#[cfg(any(test, feature = "dev"))]
+impl<'a, I: IdentityProvider> std::fmt::Debug for ShareRecoveryNewIdentity<'a, I> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Share Recovery New Identity")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen::tests::simulate_keygen;
+    use curv::elliptic::curves::Scalar;
+    use round_based::dev::Simulation;
+    use wamu_core::test_utils::MockECDSAIdentityProvider;
+
+    #[test]
+    fn share_recovery_new_identity_works() {
+        let (threshold, n_parties, recovering_party_idx, initiating_party_idx) = (2, 4, 2, 1);
+
+        // Runs key gen simulation for test parameters.
+        let (keys, mut identity_providers) = simulate_keygen(threshold, n_parties);
+        let pub_key_init = keys[0].base.public_key();
+
+        // Replaces the lost identity of the recovering party with a new identity.
+        identity_providers[recovering_party_idx as usize - 1] =
+            MockECDSAIdentityProvider::generate();
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let current_to_new_idx_map: HashMap<u16, u16> = keys
+            .iter()
+            .map(|key| key.base.i)
+            .filter(|idx| *idx != recovering_party_idx)
+            .map(|idx| (idx, idx))
+            .collect();
+
+        // Runs share recovery with a new identity simulation.
+        let mut simulation = Simulation::new();
+        for (key, identity_provider) in keys.iter().zip(identity_providers.iter()) {
+            let idx = key.base.i;
+            let is_recovering_party = idx == recovering_party_idx;
+            let (signing_share, sub_share) = key.extra.as_ref().unwrap();
+            simulation.add_party(
+                ShareRecoveryNewIdentity::new(
+                    (!is_recovering_party).then_some(signing_share),
+                    (!is_recovering_party).then_some(sub_share),
+                    identity_provider,
+                    &verifying_keys,
+                    (!is_recovering_party).then(|| key.base.clone()),
+                    is_recovering_party.then_some(idx),
+                    n_parties,
+                    &current_to_new_idx_map,
+                    is_recovering_party.then_some(threshold),
+                    idx == initiating_party_idx,
+                )
+                .unwrap(),
+            );
+        }
+        let new_keys = simulation.run().unwrap();
+
+        // Verifies the refreshed/generated keys and configuration for all parties.
+        assert_eq!(new_keys.len(), n_parties as usize);
+        for new_key in new_keys.iter() {
+            assert_eq!(new_key.base.t, threshold);
+            assert_eq!(new_key.base.n, n_parties);
+            assert_eq!(new_key.base.keys_linear.x_i, Scalar::<Secp256k1>::zero());
+            assert_eq!(new_key.base.public_key(), pub_key_init);
+        }
+
+        // The recovering party can't initiate the request with its unapproved identity.
+        assert!(matches!(
+            ShareRecoveryNewIdentity::new(
+                None,
+                None,
+                &identity_providers[recovering_party_idx as usize - 1],
+                &verifying_keys,
+                None,
+                Some(recovering_party_idx),
+                n_parties,
+                &current_to_new_idx_map,
+                Some(threshold),
+                true,
+            ),
+            Err(Error::InvalidInput)
+        ));
+
+        // The recovering party's approval of its own new identity doesn't count towards the quorum.
+        let initiator_key = &keys[initiating_party_idx as usize - 1];
+        let (signing_share, sub_share) = initiator_key.extra.as_ref().unwrap();
+        let mut initiator = ShareRecoveryNewIdentity::new(
+            Some(signing_share),
+            Some(sub_share),
+            &identity_providers[initiating_party_idx as usize - 1],
+            &verifying_keys,
+            Some(initiator_key.base.clone()),
+            None,
+            n_parties,
+            &current_to_new_idx_map,
+            None,
+            true,
+        )
+        .unwrap();
+        let request = match &initiator.message_queue()[0].body {
+            Message::Init(quorum_approval::Message::Round1(request)) => request.clone(),
+            _ => panic!("expected a quorum approval request"),
+        };
+        let approval_msg = |idx: u16| Msg {
+            sender: idx,
+            receiver: None,
+            body: Message::Init(quorum_approval::Message::Round2(
+                wamu_core::quorum_approved_request::verify_request_and_initiate_challenge(
+                    &request.command,
+                    &request,
+                    &identity_providers[idx as usize - 1],
+                    &verifying_keys,
+                )
+                .unwrap(),
+            )),
+        };
+        for idx in [recovering_party_idx, 3] {
+            initiator.handle_incoming(approval_msg(idx)).unwrap();
+            assert!(!initiator.wants_to_proceed());
+        }
+        initiator.handle_incoming(approval_msg(4)).unwrap();
+        assert!(initiator.wants_to_proceed());
+    }
+}
@@ -125,6 +125,7 @@ impl<'a, I: IdentityProvider> AuthorizedKeyRefresh<'a, I> for ShareRecoveryQuoru
     type InitStateMachineType = IdentityAuthentication<'a, I>;
 
     impl_required_authorized_key_refresh_getters!(
+        verified_parties,
         auth_state_machine,
         refresh_state_machine,
         message_queue,
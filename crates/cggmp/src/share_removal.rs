@@ -8,6 +8,7 @@ use round_based::{Msg, StateMachine};
 use std::collections::HashMap;
 use std::time::Duration;
 use wamu_core::crypto::VerifyingKey;
+use wamu_core::quorum_approved_request::QuorumPolicy;
 use wamu_core::{IdentityProvider, SigningShare, SubShare};
 
 use crate::authorized_key_refresh::{AuthorizedKeyRefresh, Error, Message};
@@ -22,7 +23,7 @@ pub struct ShareRemoval<'a, I: IdentityProvider> {
     // Quorum approval.
     /// The decentralized identity provider of the party.
     identity_provider: &'a I,
-    /// Verifying keys for other the parties.
+    /// Verifying keys for the parties before the removal (i.e indexed by current party index).
     verified_parties: &'a [VerifyingKey],
     /// Party index.
     idx: u16,
@@ -67,6 +68,8 @@ impl<'a, I: IdentityProvider> ShareRemoval<'a, I> {
     ) -> Result<ShareRemoval<'a, I>, Error<'a, I, <QuorumApproval<'a, I> as StateMachine>::Err>>
     {
         // Initializes quorum approval state machine.
+        // NOTE: Quorum approval uses the current (i.e pre-removal) party indices.
+        let remaining_parties: Vec<u16> = old_to_new_map.keys().copied().collect();
         let auth_state_machine = QuorumApproval::new(
             SHARE_REMOVAL,
             identity_provider,
@@ -76,7 +79,11 @@ impl<'a, I: IdentityProvider> ShareRemoval<'a, I> {
             local_key.n,
             is_initiator,
             false,
-        );
+            // Share removal requires approval from all remaining parties.
+            QuorumPolicy::AllParties,
+        )
+        // Parties being removed don't need to approve their own removal.
+        .with_participants(&remaining_parties);
 
         // Initializes share removal state machine.
         let mut share_removal = Self {
@@ -103,18 +110,52 @@ impl<'a, I: IdentityProvider> ShareRemoval<'a, I> {
         // Returns share removal machine.
         Ok(share_removal)
     }
+
+    /// Enforces a delay (in seconds) between request initiation and key refresh,
+    /// during which any verified party can cancel the request (see [`cancel`](Self::cancel)).
+    ///
+    /// **NOTE:** Recommended for sensitive commands (e.g party removal), all parties must use the same delay.
+    pub fn with_time_lock(mut self, delay: u64) -> Self {
+        self.auth_state_machine = self.auth_state_machine.with_time_lock(delay);
+        self
+    }
+
+    /// Sets verifying keys for observers (i.e watch-only parties) that can veto the request before it's approved
+    /// (see [`QuorumApproval::with_observers`]).
+    pub fn with_observers(mut self, observers: &'a [VerifyingKey]) -> Self {
+        self.auth_state_machine = self.auth_state_machine.with_observers(observers);
+        self
+    }
+
+    /// Cancels a time-locked request and broadcasts a signed cancellation to the other parties.
+    pub fn cancel(
+        &mut self,
+    ) -> Result<(), Error<'a, I, <QuorumApproval<'a, I> as StateMachine>::Err>> {
+        self.auth_state_machine.cancel()?;
+
+        // Wraps the cancellation message.
+        self.update_composite_message_queue()
+    }
 }
 
 impl<'a, I: IdentityProvider> AuthorizedKeyRefresh<'a, I> for ShareRemoval<'a, I> {
     type InitStateMachineType = QuorumApproval<'a, I>;
 
     impl_required_authorized_key_refresh_getters!(
+        verified_parties,
         auth_state_machine,
         refresh_state_machine,
         message_queue,
         out_of_order_buffer
     );
 
+    fn verify_authorization(
+        &self,
+    ) -> Result<(), Error<'a, I, <Self::InitStateMachineType as StateMachine>::Err>> {
+        // Destructive commands require a verified quorum (i.e not just the initiator's identity authentication).
+        self.auth_state_machine.verify_quorum().map_err(Error::Init)
+    }
+
     fn create_key_refresh(
         &mut self,
     ) -> Result<
@@ -166,18 +207,14 @@ pub mod tests {
             LocalKey<Secp256k1>,
             bool, // Whether or not this party is the initiator.
         )>,
+        // Verifying keys for all parties before the removal (i.e indexed by current party index).
+        verifying_keys: &[VerifyingKey],
         current_to_new_idx_map: &HashMap<u16, u16>,
         n_parties: u16,
     ) -> Vec<AugmentedType<LocalKey<Secp256k1>, SubShareOutput>> {
         // Creates simulation.
         let mut simulation = Simulation::new();
 
-        // Creates a list of verifying keys for all parties.
-        let verifying_keys: Vec<VerifyingKey> = party_key_configs
-            .iter()
-            .map(|(_, _, identity_provider, ..)| identity_provider.verifying_key())
-            .collect();
-
         // Adds parties to simulation.
         for (signing_share, sub_share, identity_provider, local_key, is_initiator) in
             party_key_configs
@@ -187,7 +224,7 @@ pub mod tests {
                     signing_share,
                     sub_share,
                     identity_provider,
-                    &verifying_keys,
+                    verifying_keys,
                     local_key,
                     n_parties,
                     current_to_new_idx_map,
@@ -215,8 +252,38 @@ pub mod tests {
             Vec<AugmentedType<LocalKey<Secp256k1>, SubShareOutput>>,
             Vec<MockECDSAIdentityProvider>,
         ),
+    ) {
+        // Removes the parties with the highest indices.
+        assert!(
+            n_parties_new < n_parties_init,
+            "`n_parties_new` must be less than `n_parties_init`"
+        );
+        let removed_party_indices: Vec<u16> = (n_parties_new + 1..=n_parties_init).collect();
+        generate_parties_and_simulate_share_removal_of_indices(
+            threshold,
+            n_parties_init,
+            &removed_party_indices,
+            initiating_party_idx,
+        )
+    }
+
+    pub fn generate_parties_and_simulate_share_removal_of_indices(
+        threshold: u16,
+        n_parties_init: u16,
+        removed_party_indices: &[u16],
+        initiating_party_idx: u16,
+    ) -> (
+        (
+            Vec<AugmentedType<LocalKey<Secp256k1>, SubShareOutput>>,
+            Vec<MockECDSAIdentityProvider>,
+        ),
+        (
+            Vec<AugmentedType<LocalKey<Secp256k1>, SubShareOutput>>,
+            Vec<MockECDSAIdentityProvider>,
+        ),
     ) {
         // Verifies parameter invariants.
+        let n_parties_new = n_parties_init - removed_party_indices.len() as u16;
         assert!(threshold >= 1, "minimum threshold is one");
         assert!(
             n_parties_init > threshold,
@@ -227,8 +294,18 @@ pub mod tests {
             "threshold must be less than the total number of parties"
         );
         assert!(
-            n_parties_new < n_parties_init,
-            "`n_parties_new` must be less than `n_parties_init`"
+            !removed_party_indices.is_empty(),
+            "at least one party must be removed"
+        );
+        assert!(
+            removed_party_indices
+                .iter()
+                .all(|idx| (1..=n_parties_init).contains(idx)),
+            "removed party indices must be valid party indices"
+        );
+        assert!(
+            !removed_party_indices.contains(&initiating_party_idx),
+            "the initiating party can't be removed"
         );
 
         // Runs key gen simulation for test parameters.
@@ -243,10 +320,16 @@ pub mod tests {
         let pub_key_init = keys[0].base.public_key();
 
         // Removes some existing parties.
-        if n_parties_new < n_parties_init {
-            keys.truncate(n_parties_new as usize);
-            identity_providers.truncate(n_parties_new as usize);
-        }
+        let is_remaining = |key: &AugmentedType<LocalKey<Secp256k1>, SubShareOutput>| {
+            !removed_party_indices.contains(&key.base.i)
+        };
+        identity_providers = identity_providers
+            .into_iter()
+            .zip(keys.iter())
+            .filter(|(_, key)| is_remaining(key))
+            .map(|(identity_provider, _)| identity_provider)
+            .collect();
+        keys.retain(is_remaining);
 
         // Creates key configs and party indices for continuing/existing parties.
         let mut party_key_configs = Vec::new();
@@ -256,19 +339,28 @@ pub mod tests {
             let idx = i as u16 + 1;
             let (signing_share, sub_share) = key.extra.as_ref().unwrap();
             let local_key = key.base.clone();
+            let is_initiator = local_key.i == initiating_party_idx;
             current_to_new_idx_map.insert(local_key.i, idx);
             party_key_configs.push((
                 signing_share,
                 sub_share,
                 &identity_providers[i],
                 local_key,
-                idx == initiating_party_idx,
+                is_initiator,
             ));
         }
 
         // Runs share removal simulation for test parameters.
-        let new_keys =
-            simulate_share_removal(party_key_configs, &current_to_new_idx_map, n_parties_new);
+        let verifying_keys: Vec<VerifyingKey> = identity_providers_init
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let new_keys = simulate_share_removal(
+            party_key_configs,
+            &verifying_keys,
+            &current_to_new_idx_map,
+            n_parties_new,
+        );
 
         // Verifies the refreshed/generated keys and configuration for all parties.
         assert_eq!(new_keys.len(), n_parties_new as usize);
@@ -302,4 +394,23 @@ pub mod tests {
     fn share_removal_works() {
         generate_parties_and_simulate_share_removal(2, 5, 4, 2);
     }
+
+    #[test]
+    fn share_removal_of_middle_index_works() {
+        let ((_, identity_providers_init), (_, identity_providers)) =
+            generate_parties_and_simulate_share_removal_of_indices(2, 5, &[3], 2);
+
+        // Verifies that only the party with the middle index was removed.
+        let remaining_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let expected_keys: Vec<VerifyingKey> = identity_providers_init
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != 2)
+            .map(|(_, identity_provider)| identity_provider.verifying_key())
+            .collect();
+        assert_eq!(remaining_keys, expected_keys);
+    }
 }
@@ -2,41 +2,199 @@
 //!
 //! Ref: <https://wamu.tech/specification#signing>.
 
-use cggmp_threshold_ecdsa::presign::state_machine::PreSigning;
+use cggmp_threshold_ecdsa::presign::state_machine::{PreSigning, M as PreSigningM};
 use cggmp_threshold_ecdsa::presign::{
     PreSigningSecrets, PresigningOutput, PresigningTranscript, SSID,
 };
 use cggmp_threshold_ecdsa::sign::state_machine::{Signing, M};
-use curv::arithmetic::Converter;
-use curv::elliptic::curves::{Scalar, Secp256k1};
+use cggmp_threshold_ecdsa::sign::SigningOutput;
+use cggmp_threshold_ecdsa::utilities::sha2::Sha256;
+use curv::arithmetic::traits::{Modulo, One, Samplable, Zero};
+use curv::arithmetic::{Converter, Integer};
+use curv::elliptic::curves::{Point, Scalar, Secp256k1};
 use curv::BigInt;
-use round_based::{Msg, StateMachine};
+use fs_dkr::ring_pedersen_proof::RingPedersenStatement;
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::keygen::LocalKey;
+use round_based::{IsCritical, Msg, StateMachine};
 use std::collections::HashMap;
+use std::ops::Deref;
 use std::time::Duration;
 use wamu_core::crypto::VerifyingKey;
+use wamu_core::policy::{Policy, SigningIntent};
 use wamu_core::{IdentityProvider, SigningShare, SubShare};
 
 use crate::augmented_state_machine::Error;
-use crate::augmented_state_machine::{AugmentedStateMachine, AugmentedType, IdentityAuthParams};
+use crate::augmented_state_machine::{
+    AugmentedStateMachine, AugmentedType, IdentityAuthParams, ProgressTracker, TimeoutConfig,
+};
+use crate::event_sink::EventSink;
+use crate::keygen::WalletShare;
+
+/// A registry of consumed pre-signing outputs (i.e nonces)
+/// that refuses to reuse a pre-signing output for a different message or public key
+/// (e.g a different BIP-32 derivation tweak, see [`apply_derivation_tweak`]).
+///
+/// **NOTE:** Reusing a pre-signing output for a different message or public key leaks the secret key,
+/// so the registry should be persisted (see [`NonceGuard::consumed`] and [`NonceGuard::from_consumed`])
+/// for as long as the pre-signing outputs are.
+#[derive(Debug, Clone, Default)]
+pub struct NonceGuard {
+    /// Signed message digests and (compressed) signing public keys (i.e the group public key or a derived child public key)
+    /// keyed by the session identifier (i.e rid in the CGGMP20 paper) and pre-signing output index.
+    consumed: HashMap<([u8; 32], usize), (Vec<u8>, Vec<u8>)>,
+}
+
+impl NonceGuard {
+    /// Returns an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a registry restored from persisted consumed pre-signing outputs (i.e session identifier, pre-signing output index,
+    /// signed message digest and compressed signing public key, see [`NonceGuard::consumed`]).
+    pub fn from_consumed(
+        consumed: impl IntoIterator<Item = ([u8; 32], usize, Vec<u8>, Vec<u8>)>,
+    ) -> Self {
+        Self {
+            consumed: consumed
+                .into_iter()
+                .map(
+                    |(rid, pre_signing_output_idx, message_digest, public_key)| {
+                        ((rid, pre_signing_output_idx), (message_digest, public_key))
+                    },
+                )
+                .collect(),
+        }
+    }
+
+    /// Returns the consumed pre-signing outputs (i.e session identifier, pre-signing output index,
+    /// signed message digest and compressed signing public key)
+    /// sorted by session identifier and pre-signing output index (e.g for persisting the registry).
+    pub fn consumed(&self) -> Vec<([u8; 32], usize, Vec<u8>, Vec<u8>)> {
+        let mut consumed: Vec<([u8; 32], usize, Vec<u8>, Vec<u8>)> = self
+            .consumed
+            .iter()
+            .map(
+                |((rid, pre_signing_output_idx), (message_digest, public_key))| {
+                    (
+                        *rid,
+                        *pre_signing_output_idx,
+                        message_digest.clone(),
+                        public_key.clone(),
+                    )
+                },
+            )
+            .collect();
+        consumed.sort();
+        consumed
+    }
+
+    /// Returns true if the pre-signing output for the session identifier and index has been consumed.
+    pub fn is_consumed(&self, rid: &[u8; 32], pre_signing_output_idx: usize) -> bool {
+        self.consumed.contains_key(&(*rid, pre_signing_output_idx))
+    }
+
+    /// Given a session identifier, a pre-signing output index, the signing public key
+    /// (i.e the group public key or a derived child public key) and a message,
+    /// marks the pre-signing output as consumed for the public key and message,
+    /// or returns an error if it was already consumed for a different public key or message.
+    ///
+    /// **NOTE:** Consuming a pre-signing output for the same public key and message again is allowed (e.g for retries).
+    pub fn consume<T: IsCritical>(
+        &mut self,
+        rid: &[u8; 32],
+        pre_signing_output_idx: usize,
+        public_key: &Point<Secp256k1>,
+        message: &[u8],
+    ) -> Result<(), Error<T>> {
+        use sha2::Digest;
+        self.consume_digest(
+            rid,
+            pre_signing_output_idx,
+            public_key,
+            sha2::Sha256::digest(message).to_vec(),
+        )
+    }
+
+    // Marks the pre-signing output as consumed for the signing public key and signed message digest,
+    // so that messages and their prehashed digests are treated as the same message.
+    fn consume_digest<T: IsCritical>(
+        &mut self,
+        rid: &[u8; 32],
+        pre_signing_output_idx: usize,
+        public_key: &Point<Secp256k1>,
+        message_digest: Vec<u8>,
+    ) -> Result<(), Error<T>> {
+        let entry = (message_digest, public_key.to_bytes(true).to_vec());
+        match self.consumed.get(&(*rid, pre_signing_output_idx)) {
+            Some(consumed_entry) if consumed_entry != &entry => Err(Error::NonceReuse {
+                pre_signing_output_idx,
+            }),
+            _ => {
+                self.consumed.insert((*rid, pre_signing_output_idx), entry);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Additional parameters for augmented signing messages
+/// (i.e identity authentication parameters and the public contributions of the party to its partial signature).
+#[derive(Debug, Clone)]
+pub struct SigningParams {
+    /// Identity authentication parameters.
+    pub identity_auth: IdentityAuthParams,
+    /// `k_i * R` (i.e the nonce share of the party times the point R from the pre-signing output).
+    pub big_k_i: Point<Secp256k1>,
+    /// `chi_i * R` (i.e the share of `k * x` of the party times the point R from the pre-signing output).
+    pub big_chi_i: Point<Secp256k1>,
+}
 
 /// A wrapper around the [`cggmp-threshold-ecdsa` Signing StateMachine](https://github.com/webb-tools/cggmp-threshold-ecdsa/blob/main/src/sign/state_machine.rs) that [augments signing as described by the Wamu protocol](https://wamu.tech/specification#signing).
 pub struct AugmentedSigning<'a, I: IdentityProvider> {
     /// Wrapped `cggmp-threshold-ecdsa` Signing `StateMachine`.
     state_machine: Signing,
     /// An augmented message queue.
-    message_queue:
-        Vec<Msg<AugmentedType<<Signing as StateMachine>::MessageBody, IdentityAuthParams>>>,
+    message_queue: Vec<Msg<AugmentedType<<Signing as StateMachine>::MessageBody, SigningParams>>>,
     /// The decentralized identity provider of the party.
     identity_provider: &'a I,
     /// Verifying keys for other the parties.
     verified_parties: &'a [VerifyingKey],
-    /// A byte representation of the message to be signed.
+    /// A byte representation of the message to be signed (or its digest for prehashed messages).
     message: &'a [u8],
+    /// The point R from the pre-signing output (for computing the recovery id).
+    big_r: Option<Point<Secp256k1>>,
+    /// Public contributions of the party to its partial signature (i.e `k_i * R` and `chi_i * R`).
+    contribution: Option<(Point<Secp256k1>, Point<Secp256k1>)>,
+    /// Partial signatures (i.e `sigma_i`) and public contributions (if any) of the other participants
+    /// (for identifying inconsistent partial signatures if the assembled signature is invalid).
+    partial_signatures: HashMap<u16, (BigInt, Option<(Point<Secp256k1>, Point<Secp256k1>)>)>,
+    /// The group public key (for verifying the assembled signature).
+    public_key: Point<Secp256k1>,
+    /// Digest of the message (for verifying the assembled signature).
+    message_digest: BigInt,
+    /// Indices of the other participants.
+    other_parties: Vec<u16>,
+    /// Hash of the SSID (for binding identity authentication signatures to the session).
+    session_hash: Vec<u8>,
+    /// l in the CGGMP20 paper.
+    pre_signing_output_idx: usize,
+    /// Tracks the progress of the protocol.
+    progress_tracker: ProgressTracker,
+    /// Timeout configuration (if any).
+    timeout_config: Option<TimeoutConfig>,
+    /// Event sink (if any).
+    event_sink: Option<&'a dyn EventSink>,
+    /// Indices of legacy (i.e non-augmented) parties whose messages may omit identity authentication parameters.
+    legacy_parties: Vec<u16>,
 }
 
 impl<'a, I: IdentityProvider> AugmentedSigning<'a, I> {
-    /// Initializes party for the augmented signing protocol.
+    /// Initializes party for the augmented signing protocol after marking the pre-signing output as consumed for the message
+    /// (and the public key of the SSID), or returns an error if the pre-signing output was already consumed
+    /// for a different message or public key (e.g a different derivation tweak).
     pub fn new(
+        nonce_guard: &mut NonceGuard,
         signing_share: &SigningShare,
         sub_share: &SubShare,
         identity_provider: &'a I,
@@ -50,6 +208,18 @@ impl<'a, I: IdentityProvider> AugmentedSigning<'a, I> {
         // l in the CGGMP20 paper.
         pre_signing_output_idx: usize,
     ) -> Result<Self, Error<<Signing as StateMachine>::Err>> {
+        // Creates a SHA256 message digest.
+        use sha2::Digest;
+        let message_digest = sha2::Sha256::digest(message).to_vec();
+
+        // Refuses to reuse the pre-signing output for a different message or public key (e.g derivation tweak).
+        nonce_guard.consume_digest(
+            &ssid.rid,
+            pre_signing_output_idx,
+            &ssid.X.public_key(),
+            message_digest.clone(),
+        )?;
+
         // Reconstructs secret share.
         let secret_share = wamu_core::share_split_reconstruct::reconstruct(
             signing_share,
@@ -59,25 +229,128 @@ impl<'a, I: IdentityProvider> AugmentedSigning<'a, I> {
         // Sets the reconstructed secret share.
         ssid.X.keys_linear.x_i = Scalar::<Secp256k1>::from_bytes(&secret_share.to_be_bytes())
             .map_err(|_| Error::Core(wamu_core::Error::Encoding))?;
+        let message_digest = BigInt::from_bytes(&message_digest);
 
-        // Creates a SHA256 message digest.
-        use sha2::Digest;
-        let mut hasher = sha2::Sha256::new();
-        hasher.update(message);
-        let message_digest = hasher.finalize();
+        Self::init(
+            identity_provider,
+            verified_parties,
+            message,
+            message_digest,
+            ssid,
+            presigning_data,
+            pre_signing_output_idx,
+        )
+    }
+
+    /// Initializes party for the augmented signing protocol for a 32 byte message digest
+    /// (e.g a Keccak256 transaction hash) that's signed as is (i.e without hashing it with SHA256),
+    /// after marking the pre-signing output as consumed for the message digest (and the public key of the SSID),
+    /// or returns an error if the pre-signing output was already consumed for a different message or public key
+    /// (e.g a different derivation tweak).
+    pub fn new_prehashed(
+        nonce_guard: &mut NonceGuard,
+        signing_share: &SigningShare,
+        sub_share: &SubShare,
+        identity_provider: &'a I,
+        verified_parties: &'a [VerifyingKey],
+        message_digest: &'a [u8; 32],
+        mut ssid: SSID<Secp256k1>,
+        presigning_data: HashMap<
+            u16,
+            (PresigningOutput<Secp256k1>, PresigningTranscript<Secp256k1>),
+        >,
+        // l in the CGGMP20 paper.
+        pre_signing_output_idx: usize,
+    ) -> Result<Self, Error<<Signing as StateMachine>::Err>> {
+        // Refuses to reuse the pre-signing output for a different message or public key (e.g derivation tweak).
+        nonce_guard.consume_digest(
+            &ssid.rid,
+            pre_signing_output_idx,
+            &ssid.X.public_key(),
+            message_digest.to_vec(),
+        )?;
+
+        // Reconstructs secret share.
+        let secret_share = wamu_core::share_split_reconstruct::reconstruct(
+            signing_share,
+            sub_share,
+            identity_provider,
+        )?;
+        // Sets the reconstructed secret share.
+        ssid.X.keys_linear.x_i = Scalar::<Secp256k1>::from_bytes(&secret_share.to_be_bytes())
+            .map_err(|_| Error::Core(wamu_core::Error::Encoding))?;
+
+        Self::init(
+            identity_provider,
+            verified_parties,
+            message_digest,
+            BigInt::from_bytes(message_digest),
+            ssid,
+            presigning_data,
+            pre_signing_output_idx,
+        )
+    }
+
+    // Initializes party for the augmented signing protocol given an SSID with the secret share set and the message digest.
+    fn init(
+        identity_provider: &'a I,
+        verified_parties: &'a [VerifyingKey],
+        message: &'a [u8],
+        message_digest: BigInt,
+        ssid: SSID<Secp256k1>,
+        presigning_data: HashMap<
+            u16,
+            (PresigningOutput<Secp256k1>, PresigningTranscript<Secp256k1>),
+        >,
+        pre_signing_output_idx: usize,
+    ) -> Result<Self, Error<<Signing as StateMachine>::Err>> {
+        // Retrieves the point R and public contributions to the partial signature (i.e `k_i * R` and `chi_i * R`)
+        // from the pre-signing output.
+        let pre_signing_output = presigning_data
+            .get(&(pre_signing_output_idx as u16))
+            .map(|(output, _)| output);
+        let big_r = pre_signing_output.map(|output| output.R.clone());
+        let contribution = pre_signing_output.map(|output| {
+            (
+                &output.R * &Scalar::<Secp256k1>::from_bigint(&output.k_i),
+                &output.R * &Scalar::<Secp256k1>::from_bigint(&output.chi_i),
+            )
+        });
+
+        // Retrieves the group public key, indices of the other participants and the SSID hash.
+        let public_key = ssid.X.public_key();
+        let session_hash = session_hash(&ssid);
+        let other_parties = ssid
+            .P
+            .iter()
+            .copied()
+            .filter(|idx| *idx != ssid.X.i)
+            .collect();
 
         // Initializes state machine.
         let mut aug_signing = Self {
             state_machine: Signing::new(
                 ssid,
                 pre_signing_output_idx,
-                BigInt::from_bytes(&message_digest),
+                message_digest.clone(),
                 presigning_data,
             )?,
             message_queue: Vec::new(),
             identity_provider,
             verified_parties,
             message,
+            big_r,
+            contribution,
+            partial_signatures: HashMap::new(),
+            public_key,
+            message_digest,
+            timeout_config: None,
+            event_sink: None,
+            legacy_parties: Vec::new(),
+            progress_tracker: ProgressTracker::new(other_parties.clone()),
+            other_parties,
+            session_hash,
+            pre_signing_output_idx,
         };
 
         // Retrieves messages from immediate state transitions (if any) and augments them.
@@ -86,16 +359,152 @@ impl<'a, I: IdentityProvider> AugmentedSigning<'a, I> {
         // Returns augmented state machine.
         Ok(aug_signing)
     }
+
+    /// Sets the timeout configuration (i.e per-round timeout, session deadline and behavior on timeout).
+    pub fn with_timeout_config(mut self, timeout_config: TimeoutConfig) -> Self {
+        self.timeout_config = Some(timeout_config);
+        self
+    }
+
+    /// Sets the event sink (i.e for publishing round, message, blame and completion events).
+    pub fn with_event_sink(mut self, event_sink: &'a dyn EventSink) -> Self {
+        self.event_sink = Some(event_sink);
+        self
+    }
+
+    /// Designates legacy (i.e non-augmented GG18/GG20) parties by index,
+    /// so that their messages are accepted without identity authentication parameters
+    /// (e.g for gradually migrating existing TSS clusters to Wamu).
+    ///
+    /// **NOTE:** Identity authentication parameters from legacy parties are still verified if present.
+    /// Messages from and to legacy parties must be converted with [`from_legacy_message`](crate::augmented_state_machine::from_legacy_message)
+    /// and [`to_legacy_message`](crate::augmented_state_machine::to_legacy_message) respectively.
+    pub fn with_legacy_parties(mut self, party_indices: &[u16]) -> Self {
+        self.legacy_parties = party_indices.to_vec();
+        self
+    }
+
+    /// Initializes party for the augmented signing protocol after evaluating the signing intent against the signing policy of the wallet,
+    /// or returns an error if the signing intent violates the policy.
+    ///
+    /// **NOTE:** The signing intent must be derived from the message to be signed (see [`SigningIntent`]).
+    pub fn new_with_policy(
+        policy: &Policy,
+        wallet_id: &[u8; 32],
+        intent: &SigningIntent,
+        nonce_guard: &mut NonceGuard,
+        signing_share: &SigningShare,
+        sub_share: &SubShare,
+        identity_provider: &'a I,
+        verified_parties: &'a [VerifyingKey],
+        message: &'a [u8],
+        ssid: SSID<Secp256k1>,
+        presigning_data: HashMap<
+            u16,
+            (PresigningOutput<Secp256k1>, PresigningTranscript<Secp256k1>),
+        >,
+        // l in the CGGMP20 paper.
+        pre_signing_output_idx: usize,
+    ) -> Result<Self, Error<<Signing as StateMachine>::Err>> {
+        // Refuses to join signing sessions that violate the policy.
+        policy
+            .evaluate(wallet_id, intent)
+            .map_err(Error::PolicyViolation)?;
+
+        Self::new(
+            nonce_guard,
+            signing_share,
+            sub_share,
+            identity_provider,
+            verified_parties,
+            message,
+            ssid,
+            presigning_data,
+            pre_signing_output_idx,
+        )
+    }
+
+    // Binds Round 1 messages to the sender, the session, the pre-signing output index, the message
+    // and the public contributions of the sender to its partial signature,
+    // so that signatures can't be replayed across sessions, pre-signing outputs or messages.
+    fn parameter_hash(
+        &self,
+        sender: u16,
+        big_k_i: &Point<Secp256k1>,
+        big_chi_i: &Point<Secp256k1>,
+    ) -> Vec<u8> {
+        use sha2::{digest::Update, Digest};
+        let hasher = sha2::Sha256::new();
+        hasher
+            .chain(sender.to_be_bytes())
+            .chain(&self.session_hash)
+            .chain((self.pre_signing_output_idx as u64).to_be_bytes())
+            .chain(self.message)
+            .chain(big_k_i.to_bytes(true).deref())
+            .chain(big_chi_i.to_bytes(true).deref())
+            .finalize()
+            .deref()
+            .to_vec()
+    }
+
+    // Returns the indices of the other participants whose partial signatures are inconsistent with their public contributions
+    // (i.e `sigma_i * R != m * (k_i * R) + r * (chi_i * R)`), which is never the case for honest parties.
+    // If all verifiable partial signatures are consistent, the other participants whose partial signatures can't be verified
+    // (i.e legacy parties) are returned.
+    //
+    // Otherwise, no participant is returned, because the self-reported public contributions themselves must be inconsistent
+    // with the pre-signing output (i.e `sum(k_i * R) != G` or `sum(chi_i * R) != X`, since `R = k^-1 * G`, `sum(k_i) = k`
+    // and `sum(chi_i) = k * x`, consistent contributions and partial signatures always combine into a valid signature),
+    // and misreported contributions can't be attributed to any participant.
+    fn inconsistent_partial_signers(&self, r: &BigInt) -> Vec<usize> {
+        let m = Scalar::<Secp256k1>::from_bigint(&self.message_digest);
+        let r = Scalar::<Secp256k1>::from_bigint(r);
+        let mut inconsistent = Vec::new();
+        let mut unverifiable = Vec::new();
+        for idx in &self.other_parties {
+            match (self.big_r.as_ref(), self.partial_signatures.get(idx)) {
+                (Some(big_r), Some((sigma_i, Some((big_k_i, big_chi_i))))) => {
+                    if big_r * &Scalar::<Secp256k1>::from_bigint(sigma_i)
+                        != big_k_i * &m + big_chi_i * &r
+                    {
+                        inconsistent.push(*idx as usize);
+                    }
+                }
+                _ => unverifiable.push(*idx as usize),
+            }
+        }
+        if !inconsistent.is_empty() {
+            inconsistent
+        } else {
+            unverifiable
+        }
+    }
 }
 
 impl<'a, I: IdentityProvider> AugmentedStateMachine for AugmentedSigning<'a, I> {
     type StateMachineType = Signing;
-    type AdditionalParams = IdentityAuthParams;
-    type AdditionalOutput = AdditionalOutput;
+    type AdditionalParams = SigningParams;
+    type AdditionalOutput = RecoverableSignature;
 
     // Implements all required `AugmentedStateMachine` methods.
     impl_required_augmented_state_machine_methods!(state_machine, message_queue);
 
+    fn progress_tracker(&self) -> Option<&ProgressTracker> {
+        Some(&self.progress_tracker)
+    }
+
+    fn progress_tracker_mut(&mut self) -> Option<&mut ProgressTracker> {
+        Some(&mut self.progress_tracker)
+    }
+
+    fn timeout_config(&self) -> Option<&TimeoutConfig> {
+        self.timeout_config.as_ref()
+    }
+
+    fn event_sink(&self) -> Option<&dyn EventSink> {
+        self.event_sink
+    }
+
     fn pre_handle_incoming(
         &mut self,
         msg: &Msg<
@@ -105,22 +514,39 @@ impl<'a, I: IdentityProvider> AugmentedStateMachine for AugmentedSigning<'a, I>
             >,
         >,
     ) -> Result<(), Error<<Self::StateMachineType as StateMachine>::Err>> {
-        match msg.body.base.0 {
+        match &msg.body.base.0 {
             // Verifies the expected additional parameters from Round 1.
             // Round 2 of `cggmp-threshold-ecdsa` Signing is the Output phase,
-            M::Round1(_) => match msg.body.extra.as_ref() {
-                // Verifies that signer is an expected party/signatory and the signature is valid.
-                Some(params) => Ok(wamu_core::wrappers::verify_request_with_signature(
-                    self.message,
-                    &params.verifying_key,
-                    &params.verifying_signature,
-                    self.verified_parties,
-                )?),
-                // Returns an error if expected additional parameters are missing.
-                None => Err(Error::MissingParams {
-                    bad_actors: vec![msg.sender as usize],
-                }),
-            },
+            M::Round1(round1_msg) => {
+                let contribution = match msg.body.extra.as_ref() {
+                    // Verifies that signer is an expected party/signatory and the signature is valid.
+                    Some(params) => {
+                        wamu_core::wrappers::verify_request_with_signature(
+                            &self.parameter_hash(msg.sender, &params.big_k_i, &params.big_chi_i),
+                            &params.identity_auth.verifying_key,
+                            &params.identity_auth.verifying_signature,
+                            self.verified_parties,
+                        )
+                        .map_err(|error| {
+                            Error::blame(error, msg.sender, &params.identity_auth, 1)
+                        })?;
+                        Some((params.big_k_i.clone(), params.big_chi_i.clone()))
+                    }
+                    // Accepts messages without additional parameters from legacy parties.
+                    None if self.legacy_parties.contains(&msg.sender) => None,
+                    // Returns an error if expected additional parameters are missing.
+                    None => {
+                        return Err(Error::MissingParams {
+                            bad_actors: vec![msg.sender as usize],
+                            round: 1,
+                        })
+                    }
+                };
+                // Records the partial signature (and public contributions) of the sender.
+                self.partial_signatures
+                    .insert(msg.sender, (round1_msg.sigma_i.clone(), contribution));
+                Ok(())
+            }
             // No modifications for other rounds.
             _ => Ok(()),
         }
@@ -128,65 +554,406 @@ impl<'a, I: IdentityProvider> AugmentedStateMachine for AugmentedSigning<'a, I>
 
     fn augment_outgoing_message(
         &self,
-        _: u16,
+        sender: u16,
         msg_body: &<Self::StateMachineType as StateMachine>::MessageBody,
     ) -> Result<Option<Self::AdditionalParams>, Error<<Self::StateMachineType as StateMachine>::Err>>
     {
         match msg_body.0 {
             // Adds additional parameters to Round 1 messages.
             M::Round1(_) => {
+                let (big_k_i, big_chi_i) = self.contribution.clone().ok_or(Error::InvalidConfig)?;
                 let (verifying_key, verifying_signature) =
                     wamu_core::wrappers::initiate_request_with_signature(
-                        self.message,
+                        &self.parameter_hash(sender, &big_k_i, &big_chi_i),
                         self.identity_provider,
                     );
-                Ok(Some(IdentityAuthParams {
-                    verifying_key,
-                    verifying_signature,
+                Ok(Some(SigningParams {
+                    identity_auth: IdentityAuthParams {
+                        verifying_key,
+                        verifying_signature,
+                    },
+                    big_k_i,
+                    big_chi_i,
                 }))
             }
             // No modifications for other rounds.
             _ => Ok(None),
         }
     }
-}
 
-// No additional output.
-type AdditionalOutput = ();
+    fn augment_output(
+        &self,
+        output: <Self::StateMachineType as StateMachine>::Output,
+    ) -> Result<
+        AugmentedType<<Self::StateMachineType as StateMachine>::Output, Self::AdditionalOutput>,
+        Error<<Self::StateMachineType as StateMachine>::Err>,
+    > {
+        // Verifies the assembled signature (if any) against the group public key and message digest,
+        // and blames the participants with inconsistent partial signatures if it's invalid.
+        if let Some(signing_output) = output.as_ref() {
+            if !verify_signature(
+                &self.public_key,
+                &self.message_digest,
+                &signing_output.r,
+                &signing_output.sigma,
+            ) {
+                return Err(Error::InvalidSignature {
+                    bad_actors: self.inconsistent_partial_signers(&signing_output.r),
+                });
+            }
+        }
+
+        // Adds the recoverable signature (if any).
+        let extra = output
+            .as_ref()
+            .zip(self.big_r.as_ref())
+            .and_then(|(signing_output, big_r)| RecoverableSignature::new(signing_output, big_r));
+        Ok(AugmentedType {
+            base: output,
+            extra,
+        })
+    }
+}
 
 // Implements `StateMachine` trait for `AugmentedSigning`.
 impl_state_machine_for_augmented_state_machine!(
     AugmentedSigning,
     Signing,
-    IdentityAuthParams,
-    AdditionalOutput
+    SigningParams,
+    RecoverableSignature
 );
 
+/// Applies a non-hardened BIP-32 child key derivation tweak (see [`wamu_core::bip32`]) to the SSID and pre-signing outputs,
+/// so that signing produces signatures for the derived child public key (i.e `X + tweak * G`) instead of the group public key.
+///
+/// Since `chi_i` are additive shares of `k * x`, adding `k_i * tweak` to each `chi_i` yields additive shares of `k * (x + tweak)`.
+///
+/// **NOTE:** All signing participants must apply the same tweak (i.e derive the same path from the same extended public key)
+/// before initializing [`AugmentedSigning`], otherwise signing fails with an invalid signature.
+/// A pre-signing output can only be consumed for one tweak (see [`NonceGuard`]),
+/// because signing with the same nonce for two public keys with a known difference leaks the secret key.
+pub fn apply_derivation_tweak(
+    tweak: &[u8; 32],
+    ssid: &mut SSID<Secp256k1>,
+    presigning_data: &mut HashMap<
+        u16,
+        (PresigningOutput<Secp256k1>, PresigningTranscript<Secp256k1>),
+    >,
+) {
+    let q = Scalar::<Secp256k1>::group_order();
+    let tweak = BigInt::modulus(&BigInt::from_bytes(tweak), q);
+    ssid.X.y_sum_s = &ssid.X.y_sum_s
+        + Point::<Secp256k1>::generator() * Scalar::<Secp256k1>::from_bigint(&tweak);
+    for (output, _) in presigning_data.values_mut() {
+        output.chi_i = BigInt::mod_add(&output.chi_i, &BigInt::mod_mul(&output.k_i, &tweak, q), q);
+    }
+}
+
+// Returns true if (r, sigma) is a valid ECDSA signature of the message digest for the public key.
+fn verify_signature(
+    public_key: &Point<Secp256k1>,
+    message_digest: &BigInt,
+    r: &BigInt,
+    sigma: &BigInt,
+) -> bool {
+    let q = Scalar::<Secp256k1>::group_order();
+    if r.is_zero() || r >= q || sigma >= q {
+        return false;
+    }
+    let Some(sigma_inv) = Scalar::<Secp256k1>::from_bigint(sigma).invert() else {
+        return false;
+    };
+    let u1 = Scalar::<Secp256k1>::from_bigint(message_digest) * &sigma_inv;
+    let u2 = Scalar::<Secp256k1>::from_bigint(r) * &sigma_inv;
+    let point = Point::<Secp256k1>::generator() * &u1 + public_key * &u2;
+    point.x_coord().map_or(false, |x| &x.mod_floor(q) == r)
+}
+
+// Returns a hash of the SSID (i.e the random session identifier, the sorted participant indices and the group public key)
+// that's shared by all participants of a pre-signing/signing session.
+fn session_hash(ssid: &SSID<Secp256k1>) -> Vec<u8> {
+    use sha2::{digest::Update, Digest};
+    let mut party_indices = ssid.P.clone();
+    party_indices.sort_unstable();
+    let hasher = party_indices
+        .iter()
+        .fold(sha2::Sha256::new().chain(ssid.rid), |hasher, idx| {
+            hasher.chain(idx.to_be_bytes())
+        });
+    hasher
+        .chain(ssid.X.public_key().to_bytes(true).deref())
+        .finalize()
+        .deref()
+        .to_vec()
+}
+
+/// A "low-s" normalized recoverable ECDSA signature (i.e (r, s, v)).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoverableSignature {
+    /// r value.
+    pub r: BigInt,
+    /// s value ("low-s" normalized).
+    pub s: BigInt,
+    /// Recovery id (i.e `0` or `1` for even or odd R.y, plus `2` if R.x overflows the group order).
+    pub recovery_id: u8,
+}
+
+impl RecoverableSignature {
+    /// Given a signing output and the point R from the pre-signing output,
+    /// returns the "low-s" normalized recoverable signature (or `None` if R is the point at infinity).
+    pub fn new(output: &SigningOutput<Secp256k1>, big_r: &Point<Secp256k1>) -> Option<Self> {
+        let q = Scalar::<Secp256k1>::group_order();
+        let (x, y) = big_r.x_coord().zip(big_r.y_coord())?;
+        let mut recovery_id = u8::from(y.is_odd()) | if x == output.r { 0 } else { 2 };
+        // Normalizes s to the lower half of the group order (negating s flips the parity of R.y).
+        let s = if output.sigma > q / BigInt::from(2) {
+            recovery_id ^= 1;
+            q - &output.sigma
+        } else {
+            output.sigma.clone()
+        };
+        Some(Self {
+            r: output.r.clone(),
+            s,
+            recovery_id,
+        })
+    }
+
+    /// Returns the equivalent `k256` signature and recovery id.
+    pub fn to_k256(
+        &self,
+    ) -> Result<(k256::ecdsa::Signature, k256::ecdsa::RecoveryId), k256::ecdsa::Error> {
+        let signature = k256::ecdsa::Signature::from_scalars(
+            to_field_bytes(&self.r)?,
+            to_field_bytes(&self.s)?,
+        )?;
+        let recovery_id = k256::ecdsa::RecoveryId::from_byte(self.recovery_id)
+            .ok_or_else(k256::ecdsa::Error::new)?;
+        Ok((signature, recovery_id))
+    }
+
+    /// Returns the 65 byte Ethereum encoding of the signature (i.e r || s || v, where v = 27 + recovery id).
+    ///
+    /// **NOTE:** Returns an error if R.x overflows the group order, because Ethereum can't represent such signatures.
+    pub fn to_ethereum_bytes(&self) -> Result<[u8; 65], k256::ecdsa::Error> {
+        let (signature, recovery_id) = self.to_k256()?;
+        if recovery_id.is_x_reduced() {
+            return Err(k256::ecdsa::Error::new());
+        }
+        let mut bytes = [0u8; 65];
+        bytes[..64].copy_from_slice(&signature.to_bytes());
+        bytes[64] = 27 + recovery_id.to_byte();
+        Ok(bytes)
+    }
+}
+
+// Returns the 32 byte big-endian encoding of a scalar value.
+fn to_field_bytes(value: &BigInt) -> Result<k256::FieldBytes, k256::ecdsa::Error> {
+    let bytes = value.to_bytes();
+    if bytes.len() > 32 {
+        return Err(k256::ecdsa::Error::new());
+    }
+    let mut field_bytes = k256::FieldBytes::default();
+    field_bytes[32 - bytes.len()..].copy_from_slice(&bytes);
+    Ok(field_bytes)
+}
+
+/// Auxiliary "ring" Pedersen parameters (i.e N-hat, s and t in the CGGMP20 paper) of a party for ZK proofs.
+///
+/// **NOTE:** Each party generates its own parameters and shares them with the other participants before pre-signing.
+///
+/// Ref: <https://eprint.iacr.org/2021/060.pdf> (Section 2.3 and Figure 6).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RingPedersenParams {
+    /// RSA modulus (i.e N-hat).
+    pub n_hat: BigInt,
+    /// s value.
+    pub s: BigInt,
+    /// t value.
+    pub t: BigInt,
+}
+
+impl RingPedersenParams {
+    /// Returns freshly generated auxiliary "ring" Pedersen parameters.
+    pub fn generate() -> Self {
+        let (statement, _) = RingPedersenStatement::<Secp256k1, Sha256>::generate();
+        Self {
+            n_hat: statement.N,
+            s: statement.S,
+            t: statement.T,
+        }
+    }
+}
+
+/// Given a local key (e.g from key generation or key refresh), indices of all participants
+/// and a random session identifier shared by all participants (i.e rid in the CGGMP20 paper),
+/// returns the SSID and pre-signing secrets for the party.
+///
+/// **NOTE:** The secret share is not set in either output,
+/// it's reconstructed and set by [`AugmentedPreSigning::new`] (and [`AugmentedSigning::new`]).
+///
+/// Ref: <https://eprint.iacr.org/2021/060.pdf> (Figure 6, Round 1).
+pub fn compose_ssid(
+    local_key: LocalKey<Secp256k1>,
+    party_indices: &[u16],
+    rid: [u8; 32],
+) -> (SSID<Secp256k1>, PreSigningSecrets) {
+    // We already have Paillier keys from GG20 key gen or FS-DKR so we just reuse them.
+    let paillier_ek = local_key.paillier_key_vec[local_key.i as usize - 1].clone();
+    let paillier_dk = local_key.paillier_dk.clone();
+    // Computes ring Pedersen parameters for the Paillier modulus.
+    let phi = (&paillier_dk.p - BigInt::one()) * (&paillier_dk.q - BigInt::one());
+    let r = BigInt::sample_below(&paillier_ek.n);
+    let lambda = BigInt::sample_below(&phi);
+    let t = BigInt::mod_pow(&r, &BigInt::from(2), &paillier_ek.n);
+    let s = BigInt::mod_pow(&t, &lambda, &paillier_ek.n);
+    // Composes SSID.
+    let ssid = SSID {
+        g: Point::<Secp256k1>::generator().to_point(),
+        q: Scalar::<Secp256k1>::group_order().clone(),
+        P: party_indices.to_vec(),
+        rid,
+        X: local_key,
+        Y: None, // Y is only needed for the 6-round identifiable abort variant (see `SsidBuilder`).
+        N: paillier_ek.n.clone(),
+        S: s,
+        T: t,
+    };
+    // Composes pre-signing secrets.
+    let secrets = PreSigningSecrets {
+        x_i: BigInt::zero(),
+        y_i: None, // Y is only needed for the 6-round identifiable abort variant (see `SsidBuilder`).
+        ek: paillier_ek,
+        dk: paillier_dk,
+    };
+
+    (ssid, secrets)
+}
+
+/// A builder for validated SSIDs (and pre-signing secrets) derived from a local key and a roster of participants.
+///
+/// **NOTE:** A random session identifier is generated if none is set,
+/// but all participants must use the same session identifier (i.e rid in the CGGMP20 paper).
+pub struct SsidBuilder {
+    /// Local key (e.g from key generation or key refresh).
+    local_key: LocalKey<Secp256k1>,
+    /// Indices of all participants.
+    party_indices: Vec<u16>,
+    /// Random session identifier (i.e rid in the CGGMP20 paper).
+    rid: Option<[u8; 32]>,
+    /// Whether or not to use the 6-round identifiable abort variant (i.e with Y and y_i set).
+    identifiable_abort: bool,
+}
+
+impl SsidBuilder {
+    /// Given a local key (e.g from key generation or key refresh), returns a builder with no participants.
+    pub fn new(local_key: LocalKey<Secp256k1>) -> Self {
+        Self {
+            local_key,
+            party_indices: Vec::new(),
+            rid: None,
+            identifiable_abort: false,
+        }
+    }
+
+    /// Given a wallet share, returns a builder with no participants.
+    pub fn from_wallet_share(wallet_share: &WalletShare) -> Self {
+        Self::new(wallet_share.local_key.clone())
+    }
+
+    /// Sets the indices of all participants.
+    pub fn with_party_indices(mut self, party_indices: &[u16]) -> Self {
+        self.party_indices = party_indices.to_vec();
+        self
+    }
+
+    /// Sets the random session identifier shared by all participants (i.e rid in the CGGMP20 paper).
+    pub fn with_rid(mut self, rid: [u8; 32]) -> Self {
+        self.rid = Some(rid);
+        self
+    }
+
+    /// Enables the 6-round identifiable abort variant of pre-signing and signing,
+    /// for deployments that prioritize accountability over round count.
+    ///
+    /// Ref: <https://eprint.iacr.org/2021/060.pdf> (Section 4.3).
+    pub fn with_identifiable_abort(mut self) -> Self {
+        self.identifiable_abort = true;
+        self
+    }
+
+    /// Returns the SSID and pre-signing secrets for the party,
+    /// or an error if the participants aren't a valid quorum that includes the party
+    /// or the local key is missing the party's Paillier keys.
+    pub fn build<T: IsCritical>(self) -> Result<(SSID<Secp256k1>, PreSigningSecrets), Error<T>> {
+        let (i, t, n) = (self.local_key.i, self.local_key.t, self.local_key.n);
+        let mut sorted_indices = self.party_indices.clone();
+        sorted_indices.sort_unstable();
+        sorted_indices.dedup();
+        if sorted_indices.len() != self.party_indices.len()
+            // Participants must be a valid quorum (i.e quorum size = threshold + 1).
+            || self.party_indices.len() <= t as usize
+            || sorted_indices.first().map_or(true, |idx| *idx < 1)
+            || sorted_indices.last().map_or(true, |idx| *idx > n)
+            // The party must be a participant.
+            || !self.party_indices.contains(&i)
+            // The party's Paillier keys must be available.
+            || self.local_key.paillier_key_vec.len() < i as usize
+        {
+            return Err(Error::InvalidConfig);
+        }
+        let rid = self
+            .rid
+            .unwrap_or_else(|| wamu_core::crypto::Random32Bytes::generate().to_be_bytes());
+        let (mut ssid, mut secrets) = compose_ssid(self.local_key, &self.party_indices, rid);
+        if self.identifiable_abort {
+            // Generates the ElGamal key pair (i.e y_i and Y = g^y_i) for the identifiable abort variant.
+            let y_i = Scalar::<Secp256k1>::random();
+            ssid.Y = Some(Point::<Secp256k1>::generator() * &y_i);
+            secrets.y_i = Some(y_i.to_bigint());
+        }
+        Ok((ssid, secrets))
+    }
+}
+
 /// A wrapper around the [`cggmp-threshold-ecdsa` PreSigning StateMachine](https://github.com/webb-tools/cggmp-threshold-ecdsa/blob/main/src/presign/state_machine.rs) that [augments pre-signing as described by the Wamu protocol](https://wamu.tech/specification#signing).
 pub struct AugmentedPreSigning<'a, I: IdentityProvider> {
     /// Wrapped `cggmp-threshold-ecdsa` PreSigning `StateMachine`.
     state_machine: PreSigning,
     /// An augmented message queue.
     message_queue:
-        Vec<Msg<AugmentedType<<PreSigning as StateMachine>::MessageBody, AdditionalParams>>>,
+        Vec<Msg<AugmentedType<<PreSigning as StateMachine>::MessageBody, IdentityAuthParams>>>,
     /// The decentralized identity provider of the party.
     identity_provider: &'a I,
     /// Verifying keys for other the parties.
     verified_parties: &'a [VerifyingKey],
+    /// Hash of the SSID (for binding identity authentication signatures to the session).
+    session_hash: Vec<u8>,
+    /// l in the CGGMP20 paper.
+    pre_signing_output_idx: usize,
+    /// Tracks the progress of the protocol.
+    progress_tracker: ProgressTracker,
+    /// Timeout configuration (if any).
+    timeout_config: Option<TimeoutConfig>,
+    /// Event sink (if any).
+    event_sink: Option<&'a dyn EventSink>,
+    /// Indices of legacy (i.e non-augmented) parties whose messages may omit identity authentication parameters.
+    legacy_parties: Vec<u16>,
 }
 
 impl<'a, I: IdentityProvider> AugmentedPreSigning<'a, I> {
     /// Initializes party for the augmented pre-signing protocol.
+    ///
+    /// **NOTE:** See [`SsidBuilder`] for composing the SSID and pre-signing secrets.
     pub fn new(
         signing_share: &SigningShare,
         sub_share: &SubShare,
         identity_provider: &'a I,
         verified_parties: &'a [VerifyingKey],
         mut ssid: SSID<Secp256k1>,
-        secrets: PreSigningSecrets,
-        aux_ring_pedersen_s_values: HashMap<u16, BigInt>,
-        aux_ring_pedersen_t_values: HashMap<u16, BigInt>,
-        aux_ring_pedersen_n_hat_values: HashMap<u16, BigInt>,
+        mut secrets: PreSigningSecrets,
+        // Auxiliary "ring" Pedersen parameters for all participants.
+        aux_ring_pedersen_params: &HashMap<u16, RingPedersenParams>,
         // l in the CGGMP20 paper.
         pre_signing_output_idx: usize,
     ) -> Result<Self, Error<<PreSigning as StateMachine>::Err>> {
@@ -199,9 +966,28 @@ impl<'a, I: IdentityProvider> AugmentedPreSigning<'a, I> {
         // Sets the reconstructed secret share.
         ssid.X.keys_linear.x_i = Scalar::<Secp256k1>::from_bytes(&secret_share.to_be_bytes())
             .map_err(|_| Error::Core(wamu_core::Error::Encoding))?;
+        secrets.x_i = BigInt::from_bytes(&secret_share.to_be_bytes());
+
+        // Splits auxiliary "ring" Pedersen parameters.
+        let mut aux_ring_pedersen_n_hat_values =
+            HashMap::with_capacity(aux_ring_pedersen_params.len());
+        let mut aux_ring_pedersen_s_values = HashMap::with_capacity(aux_ring_pedersen_params.len());
+        let mut aux_ring_pedersen_t_values = HashMap::with_capacity(aux_ring_pedersen_params.len());
+        for (idx, params) in aux_ring_pedersen_params {
+            aux_ring_pedersen_n_hat_values.insert(*idx, params.n_hat.clone());
+            aux_ring_pedersen_s_values.insert(*idx, params.s.clone());
+            aux_ring_pedersen_t_values.insert(*idx, params.t.clone());
+        }
 
         // Initializes state machine.
-        let mut aug_signing = Self {
+        let session_hash = session_hash(&ssid);
+        let other_parties = ssid
+            .P
+            .iter()
+            .copied()
+            .filter(|idx| *idx != ssid.X.i)
+            .collect();
+        let mut aug_pre_signing = Self {
             state_machine: PreSigning::new(
                 ssid,
                 secrets,
@@ -213,33 +999,150 @@ impl<'a, I: IdentityProvider> AugmentedPreSigning<'a, I> {
             message_queue: Vec::new(),
             identity_provider,
             verified_parties,
+            session_hash,
+            pre_signing_output_idx,
+            timeout_config: None,
+            event_sink: None,
+            legacy_parties: Vec::new(),
+            progress_tracker: ProgressTracker::new(other_parties),
         };
 
         // Retrieves messages from immediate state transitions (if any) and augments them.
-        aug_signing.update_augmented_message_queue()?;
+        aug_pre_signing.update_augmented_message_queue()?;
 
         // Returns augmented state machine.
-        Ok(aug_signing)
+        Ok(aug_pre_signing)
+    }
+
+    /// Sets the timeout configuration (i.e per-round timeout, session deadline and behavior on timeout).
+    pub fn with_timeout_config(mut self, timeout_config: TimeoutConfig) -> Self {
+        self.timeout_config = Some(timeout_config);
+        self
+    }
+
+    /// Sets the event sink (i.e for publishing round, message, blame and completion events).
+    pub fn with_event_sink(mut self, event_sink: &'a dyn EventSink) -> Self {
+        self.event_sink = Some(event_sink);
+        self
+    }
+
+    /// Designates legacy (i.e non-augmented GG18/GG20) parties by index,
+    /// so that their messages are accepted without identity authentication parameters
+    /// (e.g for gradually migrating existing TSS clusters to Wamu).
+    ///
+    /// **NOTE:** Identity authentication parameters from legacy parties are still verified if present.
+    /// Messages from and to legacy parties must be converted with [`from_legacy_message`](crate::augmented_state_machine::from_legacy_message)
+    /// and [`to_legacy_message`](crate::augmented_state_machine::to_legacy_message) respectively.
+    pub fn with_legacy_parties(mut self, party_indices: &[u16]) -> Self {
+        self.legacy_parties = party_indices.to_vec();
+        self
+    }
+
+    // Binds Round 1 messages to the sender, the session and the pre-signing output index,
+    // so that signatures can't be replayed across sessions or pre-signing outputs.
+    fn parameter_hash(&self, sender: u16) -> Vec<u8> {
+        use sha2::{digest::Update, Digest};
+        let hasher = sha2::Sha256::new();
+        hasher
+            .chain(sender.to_be_bytes())
+            .chain(&self.session_hash)
+            .chain((self.pre_signing_output_idx as u64).to_be_bytes())
+            .finalize()
+            .deref()
+            .to_vec()
     }
 }
 
 impl<'a, I: IdentityProvider> AugmentedStateMachine for AugmentedPreSigning<'a, I> {
     type StateMachineType = PreSigning;
-    type AdditionalParams = ();
-    type AdditionalOutput = ();
+    type AdditionalParams = IdentityAuthParams;
+    type AdditionalOutput = AdditionalOutput;
 
     // Implements all required `AugmentedStateMachine` methods.
     impl_required_augmented_state_machine_methods!(state_machine, message_queue);
+
+    fn progress_tracker(&self) -> Option<&ProgressTracker> {
+        Some(&self.progress_tracker)
+    }
+
+    fn progress_tracker_mut(&mut self) -> Option<&mut ProgressTracker> {
+        Some(&mut self.progress_tracker)
+    }
+
+    fn timeout_config(&self) -> Option<&TimeoutConfig> {
+        self.timeout_config.as_ref()
+    }
+
+    fn event_sink(&self) -> Option<&dyn EventSink> {
+        self.event_sink
+    }
+
+    fn pre_handle_incoming(
+        &mut self,
+        msg: &Msg<
+            AugmentedType<
+                <Self::StateMachineType as StateMachine>::MessageBody,
+                Self::AdditionalParams,
+            >,
+        >,
+    ) -> Result<(), Error<<Self::StateMachineType as StateMachine>::Err>> {
+        match msg.body.base.0 {
+            // Verifies the expected additional parameters from Round 1.
+            PreSigningM::Round1(_) => match msg.body.extra.as_ref() {
+                // Verifies that signer is an expected party/signatory and the signature is valid.
+                Some(params) => wamu_core::wrappers::verify_request_with_signature(
+                    &self.parameter_hash(msg.sender),
+                    &params.verifying_key,
+                    &params.verifying_signature,
+                    self.verified_parties,
+                )
+                .map_err(|error| Error::blame(error, msg.sender, params, 1)),
+                // Accepts messages without additional parameters from legacy parties.
+                None if self.legacy_parties.contains(&msg.sender) => Ok(()),
+                // Returns an error if expected additional parameters are missing.
+                None => Err(Error::MissingParams {
+                    bad_actors: vec![msg.sender as usize],
+                    round: 1,
+                }),
+            },
+            // No modifications for other rounds.
+            _ => Ok(()),
+        }
+    }
+
+    fn augment_outgoing_message(
+        &self,
+        sender: u16,
+        msg_body: &<Self::StateMachineType as StateMachine>::MessageBody,
+    ) -> Result<Option<Self::AdditionalParams>, Error<<Self::StateMachineType as StateMachine>::Err>>
+    {
+        match msg_body.0 {
+            // Adds additional parameters to Round 1 messages.
+            PreSigningM::Round1(_) => {
+                let (verifying_key, verifying_signature) =
+                    wamu_core::wrappers::initiate_request_with_signature(
+                        &self.parameter_hash(sender),
+                        self.identity_provider,
+                    );
+                Ok(Some(IdentityAuthParams {
+                    verifying_key,
+                    verifying_signature,
+                }))
+            }
+            // No modifications for other rounds.
+            _ => Ok(None),
+        }
+    }
 }
 
-// No additional params.
-type AdditionalParams = ();
+// No additional output.
+type AdditionalOutput = ();
 
-// Implements `StateMachine` trait for `AugmentedSigning`.
+// Implements `StateMachine` trait for `AugmentedPreSigning`.
 impl_state_machine_for_augmented_state_machine!(
     AugmentedPreSigning,
     PreSigning,
-    AdditionalParams,
+    IdentityAuthParams,
     AdditionalOutput
 );
 
@@ -262,14 +1165,7 @@ impl<'a, I: IdentityProvider> std::fmt::Debug for AugmentedPreSigning<'a, I> {
 #[cfg(any(test, feature = "dev"))]
 pub mod tests {
     use crate::augmented_state_machine::SubShareOutput;
-    use cggmp_threshold_ecdsa::sign::SigningOutput;
-    use cggmp_threshold_ecdsa::utilities::sha2::Sha256;
-    use curv::arithmetic::traits::{Modulo, One, Samplable};
-    use curv::arithmetic::Integer;
     use curv::cryptographic_primitives::secret_sharing::feldman_vss::VerifiableSS;
-    use curv::elliptic::curves::{Point, Scalar};
-    use fs_dkr::ring_pedersen_proof::RingPedersenStatement;
-    use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::keygen::LocalKey;
     use round_based::dev::Simulation;
     use wamu_core::test_utils::MockECDSAIdentityProvider;
 
@@ -286,7 +1182,7 @@ pub mod tests {
         )>,
         message: &[u8],
         pre_signing_output_idx: usize,
-    ) -> Vec<AugmentedType<Option<SigningOutput<Secp256k1>>, AdditionalOutput>> {
+    ) -> Vec<AugmentedType<Option<SigningOutput<Secp256k1>>, RecoverableSignature>> {
         // Creates simulation.
         let mut simulation = Simulation::new();
 
@@ -303,6 +1199,7 @@ pub mod tests {
             // Add party to simulation.
             simulation.add_party(
                 AugmentedSigning::new(
+                    &mut NonceGuard::new(),
                     signing_share,
                     sub_share,
                     identity_provider,
@@ -327,9 +1224,7 @@ pub mod tests {
             &MockECDSAIdentityProvider,
             SSID<Secp256k1>,
             PreSigningSecrets,
-            HashMap<u16, BigInt>,
-            HashMap<u16, BigInt>,
-            HashMap<u16, BigInt>,
+            HashMap<u16, RingPedersenParams>,
         )>,
         pre_signing_output_idx: usize,
     ) -> Vec<
@@ -354,9 +1249,7 @@ pub mod tests {
             identity_provider,
             ssid,
             secrets,
-            aux_ring_pedersen_n_hat_values,
-            aux_ring_pedersen_s_values,
-            aux_ring_pedersen_t_values,
+            aux_ring_pedersen_params,
         ) in inputs.into_iter()
         {
             // Add party to simulation.
@@ -368,9 +1261,7 @@ pub mod tests {
                     &verifying_keys,
                     ssid,
                     secrets,
-                    aux_ring_pedersen_s_values,
-                    aux_ring_pedersen_t_values,
-                    aux_ring_pedersen_n_hat_values,
+                    &aux_ring_pedersen_params,
                     pre_signing_output_idx,
                 )
                 .unwrap(),
@@ -391,67 +1282,27 @@ pub mod tests {
         &'b MockECDSAIdentityProvider,
         SSID<Secp256k1>,
         PreSigningSecrets,
-        HashMap<u16, BigInt>,
-        HashMap<u16, BigInt>,
-        HashMap<u16, BigInt>,
+        HashMap<u16, RingPedersenParams>,
     )> {
         // Generates auxiliary "ring" Pedersen parameters for all participants.
-        let mut aux_ring_pedersen_n_hat_values = HashMap::with_capacity(aug_keys.len());
-        let mut aux_ring_pedersen_s_values = HashMap::with_capacity(aug_keys.len());
-        let mut aux_ring_pedersen_t_values = HashMap::with_capacity(aug_keys.len());
-        for idx in 1..=n_participants {
-            let (ring_pedersen_params, _) = RingPedersenStatement::<Secp256k1, Sha256>::generate();
-            aux_ring_pedersen_n_hat_values.insert(idx, ring_pedersen_params.N);
-            aux_ring_pedersen_s_values.insert(idx, ring_pedersen_params.S);
-            aux_ring_pedersen_t_values.insert(idx, ring_pedersen_params.T);
-        }
-        // Reconstructs secret shares, creates pre-signing inputs and auxiliary parameters for ZK proofs.
-        let generator = Point::<Secp256k1>::generator().to_point();
-        let group_order = Scalar::<Secp256k1>::group_order();
+        let aux_ring_pedersen_params: HashMap<u16, RingPedersenParams> = (1..=n_participants)
+            .map(|idx| (idx, RingPedersenParams::generate()))
+            .collect();
+        // Generates a random session identifier shared by all participants.
+        let rid = wamu_core::crypto::Random32Bytes::generate().to_be_bytes();
+        // Composes SSIDs and pre-signing secrets for all participants.
         let party_indices: Vec<u16> = (1..=n_participants).collect();
         aug_keys[0..n_participants as usize]
             .iter()
             .enumerate()
             .map(|(i, aug_key)| {
-                // Creates SSID and pre-signing secrets.
                 // Extracts "signing share", "sub-share" and local key.
                 let (signing_share, sub_share) = aug_key.extra.as_ref().unwrap();
-                let secret_share = wamu_core::share_split_reconstruct::reconstruct(
-                    signing_share,
-                    sub_share,
-                    &identity_providers[i],
-                )
-                .unwrap();
-                let local_key = aug_key.base.clone();
-                // We already have Paillier keys from GG20 key gen or FS-DKR so we just reuse them.
-                let paillier_ek = local_key.paillier_key_vec[local_key.i as usize - 1].clone();
-                let paillier_dk = local_key.paillier_dk.clone();
-                // See Figure 6, Round 1.
-                // Ref: <https://eprint.iacr.org/2021/060.pdf>.
-                let phi = (&paillier_dk.p - BigInt::one()) * (&paillier_dk.q - BigInt::one());
-                let r = BigInt::sample_below(&paillier_ek.n);
-                let lambda = BigInt::sample_below(&phi);
-                let t = BigInt::mod_pow(&r, &BigInt::from(2), &paillier_ek.n);
-                let s = BigInt::mod_pow(&t, &lambda, &paillier_ek.n);
-                // Composes SSID.
-                let ssid = SSID {
-                    g: generator.clone(),
-                    q: group_order.clone(),
-                    P: party_indices.clone(),
-                    rid: wamu_core::crypto::Random32Bytes::generate().to_be_bytes(),
-                    X: local_key,
-                    Y: None, // Y is not needed for 4-round signing.
-                    N: paillier_ek.n.clone(),
-                    S: s,
-                    T: t,
-                };
-                // Composes pre-signing secrets.
-                let pre_sign_secrets = PreSigningSecrets {
-                    x_i: BigInt::from_bytes(&secret_share.to_be_bytes()),
-                    y_i: None, // Y is not needed for 4-round signing.
-                    ek: paillier_ek,
-                    dk: paillier_dk,
-                };
+                let (ssid, pre_sign_secrets) = SsidBuilder::new(aug_key.base.clone())
+                    .with_party_indices(&party_indices)
+                    .with_rid(rid)
+                    .build::<<PreSigning as StateMachine>::Err>()
+                    .unwrap();
 
                 (
                     signing_share,
@@ -459,9 +1310,7 @@ pub mod tests {
                     &identity_providers[i],
                     ssid,
                     pre_sign_secrets,
-                    aux_ring_pedersen_n_hat_values.clone(),
-                    aux_ring_pedersen_s_values.clone(),
-                    aux_ring_pedersen_t_values.clone(),
+                    aux_ring_pedersen_params.clone(),
                 )
             })
             .collect()
@@ -475,7 +1324,7 @@ pub mod tests {
     ) -> (
         Vec<AugmentedType<LocalKey<Secp256k1>, SubShareOutput>>,
         Vec<MockECDSAIdentityProvider>,
-        Vec<AugmentedType<Option<SigningOutput<Secp256k1>>, AdditionalOutput>>,
+        Vec<AugmentedType<Option<SigningOutput<Secp256k1>>, RecoverableSignature>>,
     ) {
         // Verifies parameter invariants.
         assert!(threshold >= 1, "minimum threshold is one");
@@ -630,9 +1479,22 @@ pub mod tests {
         let message_digest = BigInt::from_bytes(&hasher.finalize());
         // Verifies against expected signature.
         let s_direct =
-            (k.to_bigint() * (message_digest + (&r_direct * &sec_key.to_bigint()))).mod_floor(q);
+            (k.to_bigint() * (&message_digest + (&r_direct * &sec_key.to_bigint()))).mod_floor(q);
         let expected_signature = (r_direct, s_direct);
         assert_eq!(signature, expected_signature);
+        // Verifies that the recoverable signature recovers the public key.
+        let (recoverable_signature, recovery_id) =
+            results[0].extra.as_ref().unwrap().to_k256().unwrap();
+        let recovered_key = k256::ecdsa::VerifyingKey::recover_from_prehash(
+            &to_field_bytes(&message_digest).unwrap(),
+            &recoverable_signature,
+            recovery_id,
+        )
+        .unwrap();
+        assert_eq!(
+            recovered_key,
+            k256::ecdsa::VerifyingKey::from_sec1_bytes(&pub_key.to_bytes(true)).unwrap()
+        );
 
         (keys, identity_providers, results)
     }
@@ -648,4 +1510,543 @@ pub mod tests {
     fn sign_threshold_works() {
         generate_parties_and_simulate_signing(2, 4, 3);
     }
+
+    #[test]
+    fn sign_with_derivation_tweak_works() {
+        // Runs key gen and pre-signing simulations.
+        let (keys, identity_providers) = simulate_keygen(1, 2);
+        let pre_signing_output_idx = 1;
+        let pre_sign_inputs = generate_pre_sign_input(&keys, &identity_providers, 2);
+        let ssids: Vec<SSID<Secp256k1>> = pre_sign_inputs
+            .iter()
+            .map(|(_, _, _, ssid, ..)| ssid.clone())
+            .collect();
+        let pre_sign_results = simulate_pre_sign(pre_sign_inputs, pre_signing_output_idx);
+
+        // Derives a child public key and tweak from the group public key.
+        let xpub = wamu_core::bip32::ExtendedPublicKey::from_group_key(
+            &keys[0].base.public_key().to_bytes(true),
+        )
+        .unwrap();
+        let (child_xpub, tweak) = xpub
+            .derive_path(&wamu_core::bip32::parse_path("m/0/7").unwrap())
+            .unwrap();
+
+        // Applies the tweak for all participants and runs signing simulation.
+        let message = b"Hello, world!";
+        let signing_keys_and_pre_signing_output: Vec<(
+            &SigningShare,
+            &SubShare,
+            &MockECDSAIdentityProvider,
+            SSID<Secp256k1>,
+            HashMap<u16, (PresigningOutput<Secp256k1>, PresigningTranscript<Secp256k1>)>,
+        )> = pre_sign_results
+            .into_iter()
+            .filter_map(|it| {
+                it.base.map(|(output, transcript)| {
+                    let idx = output.i as usize - 1;
+                    let (signing_share, sub_share) = keys[idx].extra.as_ref().unwrap();
+                    let mut ssid = ssids[idx].clone();
+                    let mut presigning_data =
+                        HashMap::from([(pre_signing_output_idx as u16, (output, transcript))]);
+                    apply_derivation_tweak(&tweak, &mut ssid, &mut presigning_data);
+                    (
+                        signing_share,
+                        sub_share,
+                        &identity_providers[idx],
+                        ssid,
+                        presigning_data,
+                    )
+                })
+            })
+            .collect();
+        let results = simulate_sign(
+            signing_keys_and_pre_signing_output,
+            message,
+            pre_signing_output_idx,
+        );
+
+        // Verifies that the signature recovers the child public key.
+        use sha2::Digest;
+        let message_digest = sha2::Sha256::digest(message);
+        let (signature, recovery_id) = results[0].extra.as_ref().unwrap().to_k256().unwrap();
+        let recovered_key = k256::ecdsa::VerifyingKey::recover_from_prehash(
+            &message_digest,
+            &signature,
+            recovery_id,
+        )
+        .unwrap();
+        assert_eq!(
+            recovered_key,
+            k256::ecdsa::VerifyingKey::from_sec1_bytes(&child_xpub.public_key()).unwrap()
+        );
+    }
+
+    #[test]
+    fn signature_verification_works() {
+        // Generates key pair and a signature with a fixed nonce.
+        let q = Scalar::<Secp256k1>::group_order();
+        let secret_key = Scalar::<Secp256k1>::random();
+        let public_key = Point::<Secp256k1>::generator() * &secret_key;
+        let message_digest = BigInt::from(42);
+        let k = Scalar::<Secp256k1>::random();
+        let r = (Point::<Secp256k1>::generator() * &k)
+            .x_coord()
+            .unwrap()
+            .mod_floor(q);
+        let sigma = (k.invert().unwrap()
+            * (Scalar::<Secp256k1>::from_bigint(&message_digest)
+                + Scalar::<Secp256k1>::from_bigint(&r) * &secret_key))
+            .to_bigint();
+
+        // Valid signatures are accepted.
+        assert!(verify_signature(&public_key, &message_digest, &r, &sigma));
+
+        // Signatures for other messages, public keys or with inconsistent partial signatures are rejected.
+        let other_public_key = Point::<Secp256k1>::generator() * Scalar::<Secp256k1>::random();
+        let bad_sigma = BigInt::mod_add(&sigma, &BigInt::one(), q);
+        assert!(!verify_signature(
+            &public_key,
+            &BigInt::from(43),
+            &r,
+            &sigma
+        ));
+        assert!(!verify_signature(
+            &other_public_key,
+            &message_digest,
+            &r,
+            &sigma
+        ));
+        assert!(!verify_signature(
+            &public_key,
+            &message_digest,
+            &r,
+            &bad_sigma
+        ));
+        assert!(!verify_signature(
+            &public_key,
+            &message_digest,
+            &BigInt::zero(),
+            &sigma
+        ));
+    }
+
+    #[test]
+    fn ssid_builder_works() {
+        // Runs key gen simulation for test parameters.
+        let (keys, _) = simulate_keygen(2, 4);
+        let local_key = keys[1].base.clone();
+        let rid = [1; 32];
+
+        // Builds SSID for a valid quorum that includes the party.
+        let (ssid, secrets) = SsidBuilder::new(local_key.clone())
+            .with_party_indices(&[1, 2, 4])
+            .with_rid(rid)
+            .build::<<PreSigning as StateMachine>::Err>()
+            .unwrap();
+        assert_eq!(ssid.P, vec![1, 2, 4]);
+        assert_eq!(ssid.rid, rid);
+        assert_eq!(ssid.N, secrets.ek.n);
+
+        // Sets Y and y_i for the identifiable abort variant.
+        let (ssid, secrets) = SsidBuilder::new(local_key.clone())
+            .with_party_indices(&[1, 2, 4])
+            .with_identifiable_abort()
+            .build::<<PreSigning as StateMachine>::Err>()
+            .unwrap();
+        assert_eq!(
+            ssid.Y,
+            secrets
+                .y_i
+                .map(|y_i| Point::<Secp256k1>::generator() * Scalar::<Secp256k1>::from_bigint(&y_i))
+        );
+        assert!(ssid.Y.is_some());
+
+        // Rejects invalid quorums, duplicate and out of range indices and quorums that exclude the party.
+        for party_indices in [
+            vec![1, 2],
+            vec![1, 2, 2],
+            vec![0, 1, 2],
+            vec![1, 2, 5],
+            vec![1, 3, 4],
+        ] {
+            assert!(matches!(
+                SsidBuilder::new(local_key.clone())
+                    .with_party_indices(&party_indices)
+                    .build::<<PreSigning as StateMachine>::Err>(),
+                Err(Error::InvalidConfig)
+            ));
+        }
+    }
+
+    #[test]
+    fn session_hash_works() {
+        // Runs key gen simulation for test parameters.
+        let (keys, _) = simulate_keygen(2, 4);
+        let build_ssid = |party_indices: &[u16], rid: [u8; 32]| {
+            SsidBuilder::new(keys[1].base.clone())
+                .with_party_indices(party_indices)
+                .with_rid(rid)
+                .build::<<PreSigning as StateMachine>::Err>()
+                .unwrap()
+                .0
+        };
+
+        // The session hash is independent of the order of participants.
+        let hash = session_hash(&build_ssid(&[1, 2, 4], [1; 32]));
+        assert_eq!(session_hash(&build_ssid(&[4, 2, 1], [1; 32])), hash);
+
+        // The session hash binds the session identifier and the participants.
+        assert_ne!(session_hash(&build_ssid(&[1, 2, 4], [2; 32])), hash);
+        assert_ne!(session_hash(&build_ssid(&[1, 2, 3], [1; 32])), hash);
+    }
+
+    #[test]
+    fn nonce_guard_works() {
+        // Creates registry and session identifiers.
+        let mut nonce_guard = NonceGuard::new();
+        let (rid, other_rid) = ([1; 32], [2; 32]);
+
+        // Creates a public key and a tweaked (i.e derived child) public key.
+        let public_key = Point::<Secp256k1>::generator() * Scalar::<Secp256k1>::random();
+        let tweaked_public_key =
+            &public_key + Point::<Secp256k1>::generator() * Scalar::<Secp256k1>::random();
+
+        // Consumes pre-signing output for a message (and allows retries for the same message).
+        assert!(!nonce_guard.is_consumed(&rid, 1));
+        for _ in 0..2 {
+            assert!(nonce_guard
+                .consume::<<Signing as StateMachine>::Err>(&rid, 1, &public_key, b"Hello, world!")
+                .is_ok());
+        }
+        assert!(nonce_guard.is_consumed(&rid, 1));
+
+        // Refuses to reuse the pre-signing output for a different message or public key (i.e a retry with another tweak).
+        for (public_key, message) in [
+            (&public_key, b"Goodbye, world!".as_slice()),
+            (&tweaked_public_key, b"Hello, world!".as_slice()),
+        ] {
+            assert!(matches!(
+                nonce_guard.consume::<<Signing as StateMachine>::Err>(&rid, 1, public_key, message),
+                Err(Error::NonceReuse {
+                    pre_signing_output_idx: 1
+                })
+            ));
+        }
+
+        // Other pre-signing outputs are unaffected.
+        for (rid, idx) in [(&rid, 2), (&other_rid, 1)] {
+            assert!(nonce_guard
+                .consume::<<Signing as StateMachine>::Err>(
+                    rid,
+                    idx,
+                    &tweaked_public_key,
+                    b"Goodbye, world!"
+                )
+                .is_ok());
+        }
+
+        // Restored registries still refuse to reuse consumed pre-signing outputs.
+        let consumed = nonce_guard.consumed();
+        assert_eq!(consumed.len(), 3);
+        let mut restored_nonce_guard = NonceGuard::from_consumed(consumed);
+        assert!(restored_nonce_guard.is_consumed(&other_rid, 1));
+        assert!(matches!(
+            restored_nonce_guard.consume::<<Signing as StateMachine>::Err>(
+                &rid,
+                1,
+                &tweaked_public_key,
+                b"Hello, world!"
+            ),
+            Err(Error::NonceReuse {
+                pre_signing_output_idx: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn signing_refuses_to_reuse_pre_signing_output() {
+        // Runs key gen and pre-signing simulations for test parameters (2/2 signing).
+        let (keys, identity_providers) = simulate_keygen(1, 2);
+        let pre_signing_output_idx = 1;
+        let pre_sign_inputs = generate_pre_sign_input(&keys, &identity_providers, 2);
+        let ssids: Vec<SSID<Secp256k1>> = pre_sign_inputs
+            .iter()
+            .map(|(_, _, _, ssid, ..)| ssid.clone())
+            .collect();
+        let pre_sign_results = simulate_pre_sign(pre_sign_inputs, pre_signing_output_idx);
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let (output, transcript) = pre_sign_results.into_iter().next().unwrap().base.unwrap();
+        let idx = output.i as usize - 1;
+        let ssid = ssids[idx].clone();
+        let (signing_share, sub_share) = keys[idx].extra.as_ref().unwrap();
+        let presigning_data =
+            HashMap::from([(pre_signing_output_idx as u16, (output, transcript))]);
+
+        // Initializes signing for a message (and its prehashed digest).
+        let mut nonce_guard = NonceGuard::new();
+        let message = b"Hello, world!";
+        let message_digest: [u8; 32] = {
+            use sha2::Digest;
+            sha2::Sha256::digest(message).into()
+        };
+        assert!(AugmentedSigning::new(
+            &mut nonce_guard,
+            signing_share,
+            sub_share,
+            &identity_providers[idx],
+            &verifying_keys,
+            message,
+            ssid.clone(),
+            presigning_data.clone(),
+            pre_signing_output_idx,
+        )
+        .is_ok());
+        assert!(AugmentedSigning::new_prehashed(
+            &mut nonce_guard,
+            signing_share,
+            sub_share,
+            &identity_providers[idx],
+            &verifying_keys,
+            &message_digest,
+            ssid.clone(),
+            presigning_data.clone(),
+            pre_signing_output_idx,
+        )
+        .is_ok());
+
+        // Refuses to initialize signing for a different message with the same pre-signing output.
+        assert!(matches!(
+            AugmentedSigning::new(
+                &mut nonce_guard,
+                signing_share,
+                sub_share,
+                &identity_providers[idx],
+                &verifying_keys,
+                b"Goodbye, world!",
+                ssid.clone(),
+                presigning_data.clone(),
+                pre_signing_output_idx,
+            ),
+            Err(Error::NonceReuse {
+                pre_signing_output_idx: 1
+            })
+        ));
+
+        // Refuses to initialize signing for the same message with the same pre-signing output and a different derivation tweak.
+        let (mut tweaked_ssid, mut tweaked_presigning_data) = (ssid, presigning_data);
+        apply_derivation_tweak(&[1; 32], &mut tweaked_ssid, &mut tweaked_presigning_data);
+        assert!(matches!(
+            AugmentedSigning::new(
+                &mut nonce_guard,
+                signing_share,
+                sub_share,
+                &identity_providers[idx],
+                &verifying_keys,
+                message,
+                tweaked_ssid,
+                tweaked_presigning_data,
+                pre_signing_output_idx,
+            ),
+            Err(Error::NonceReuse {
+                pre_signing_output_idx: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn sign_blames_inconsistent_partial_signers() {
+        // Runs key gen and pre-signing simulations for test parameters (3/3 signing).
+        let (keys, identity_providers) = simulate_keygen(2, 3);
+        let pre_signing_output_idx = 1;
+        let pre_sign_inputs = generate_pre_sign_input(&keys, &identity_providers, 3);
+        let ssids: Vec<SSID<Secp256k1>> = pre_sign_inputs
+            .iter()
+            .map(|(_, _, _, ssid, ..)| ssid.clone())
+            .collect();
+        let pre_sign_results = simulate_pre_sign(pre_sign_inputs, pre_signing_output_idx);
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+
+        // Initializes signing parties.
+        let message = b"Hello, world!";
+        let new_party = |(output, transcript): (
+            PresigningOutput<Secp256k1>,
+            PresigningTranscript<Secp256k1>,
+        )| {
+            let idx = output.i as usize - 1;
+            let (signing_share, sub_share) = keys[idx].extra.as_ref().unwrap();
+            AugmentedSigning::new(
+                &mut NonceGuard::new(),
+                signing_share,
+                sub_share,
+                &identity_providers[idx],
+                &verifying_keys,
+                message,
+                ssids[idx].clone(),
+                HashMap::from([(pre_signing_output_idx as u16, (output, transcript))]),
+                pre_signing_output_idx,
+            )
+            .unwrap()
+        };
+        let pre_signing_data: Vec<_> = pre_sign_results
+            .into_iter()
+            .map(|it| it.base.unwrap())
+            .collect();
+        let mut parties: Vec<AugmentedSigning<MockECDSAIdentityProvider>> =
+            pre_signing_data.iter().cloned().map(new_party).collect();
+
+        // Corrupts the partial signature of the third party.
+        for party in parties.iter_mut() {
+            if party.wants_to_proceed() {
+                party.proceed().unwrap();
+            }
+        }
+        let mut messages: Vec<
+            Msg<AugmentedType<<Signing as StateMachine>::MessageBody, SigningParams>>,
+        > = parties[1..]
+            .iter_mut()
+            .flat_map(|party| party.message_queue().drain(..).collect::<Vec<_>>())
+            .collect();
+        for msg in messages.iter_mut().filter(|msg| msg.sender == 3) {
+            if let M::Round1(round1_msg) = &mut msg.body.base.0 {
+                round1_msg.sigma_i = BigInt::mod_add(
+                    &round1_msg.sigma_i,
+                    &BigInt::one(),
+                    Scalar::<Secp256k1>::group_order(),
+                );
+            }
+        }
+
+        // Only the party with the corrupted partial signature is blamed.
+        let mut party = parties.remove(0);
+        for msg in messages.clone() {
+            party.handle_incoming(msg).unwrap();
+        }
+        if party.wants_to_proceed() {
+            party.proceed().unwrap();
+        }
+        assert!(matches!(
+            party.pick_output(),
+            Some(Err(Error::InvalidSignature { bad_actors })) if bad_actors == vec![3]
+        ));
+
+        // Misreports the public contributions of the third party (with a valid identity signature),
+        // so that they're consistent with its corrupted partial signature but not with the pre-signing output
+        // (i.e `chi_3 * R + r^-1 * R`).
+        let third_party = &parties[1];
+        let big_r = third_party.big_r.clone().unwrap();
+        let r = Scalar::<Secp256k1>::from_bigint(&big_r.x_coord().unwrap());
+        for msg in messages.iter_mut().filter(|msg| msg.sender == 3) {
+            if let Some(params) = msg.body.extra.as_mut() {
+                params.big_chi_i = &params.big_chi_i + &big_r * &r.invert().unwrap();
+                let (_, signature) = wamu_core::wrappers::initiate_request_with_signature(
+                    &third_party.parameter_hash(3, &params.big_k_i, &params.big_chi_i),
+                    &identity_providers[2],
+                );
+                params.identity_auth.verifying_signature = signature;
+            }
+        }
+
+        // Honest parties aren't blamed for misreported public contributions.
+        let mut party = new_party(pre_signing_data[0].clone());
+        if party.wants_to_proceed() {
+            party.proceed().unwrap();
+        }
+        for msg in messages {
+            party.handle_incoming(msg).unwrap();
+        }
+        if party.wants_to_proceed() {
+            party.proceed().unwrap();
+        }
+        assert!(matches!(
+            party.pick_output(),
+            Some(Err(Error::InvalidSignature { bad_actors })) if bad_actors.is_empty()
+        ));
+    }
+
+    #[test]
+    fn sign_with_legacy_parties_works() {
+        // Runs key gen and pre-signing simulations for test parameters (2/2 signing).
+        let (keys, identity_providers) = simulate_keygen(1, 2);
+        let pre_signing_output_idx = 1;
+        let pre_sign_inputs = generate_pre_sign_input(&keys, &identity_providers, 2);
+        let ssids: Vec<SSID<Secp256k1>> = pre_sign_inputs
+            .iter()
+            .map(|(_, _, _, ssid, ..)| ssid.clone())
+            .collect();
+        let pre_sign_results = simulate_pre_sign(pre_sign_inputs, pre_signing_output_idx);
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+
+        // Initializes signing parties.
+        let message = b"Hello, world!";
+        let mut parties: Vec<AugmentedSigning<MockECDSAIdentityProvider>> = pre_sign_results
+            .into_iter()
+            .map(|it| {
+                let (output, transcript) = it.base.unwrap();
+                let idx = output.i as usize - 1;
+                let (signing_share, sub_share) = keys[idx].extra.as_ref().unwrap();
+                AugmentedSigning::new(
+                    &mut NonceGuard::new(),
+                    signing_share,
+                    sub_share,
+                    &identity_providers[idx],
+                    &verifying_keys,
+                    message,
+                    ssids[idx].clone(),
+                    HashMap::from([(pre_signing_output_idx as u16, (output, transcript))]),
+                    pre_signing_output_idx,
+                )
+                .unwrap()
+            })
+            .collect();
+
+        // Converts messages from the second party into legacy (i.e non-augmented) messages.
+        for party in parties.iter_mut() {
+            if party.wants_to_proceed() {
+                party.proceed().unwrap();
+            }
+        }
+        let legacy_messages: Vec<
+            Msg<AugmentedType<<Signing as StateMachine>::MessageBody, SigningParams>>,
+        > = parties[1]
+            .message_queue()
+            .drain(..)
+            .map(|msg| {
+                crate::augmented_state_machine::from_legacy_message(
+                    crate::augmented_state_machine::to_legacy_message(msg),
+                )
+            })
+            .collect();
+        assert!(!legacy_messages.is_empty());
+
+        // Messages without identity authentication parameters are rejected from parties that aren't designated as legacy parties.
+        let mut party = parties.remove(0);
+        assert!(matches!(
+            party.handle_incoming(legacy_messages[0].clone()),
+            Err(Error::MissingParams { .. })
+        ));
+
+        // Messages without identity authentication parameters are accepted from designated legacy parties.
+        let mut party = party.with_legacy_parties(&[2]);
+        for msg in legacy_messages {
+            party.handle_incoming(msg).unwrap();
+        }
+        if party.wants_to_proceed() {
+            party.proceed().unwrap();
+        }
+
+        // Verifies that a valid signature is assembled.
+        let output = party.pick_output().unwrap().unwrap();
+        assert!(output.base.is_some());
+        assert!(output.extra.is_some());
+    }
 }
@@ -0,0 +1,399 @@
+//! One-shot signing (i.e pre-signing and signing back-to-back over a blocking transport).
+//!
+//! Hides `PresigningTranscript`, `pre_signing_output_idx` and SSID plumbing from application developers.
+//!
+//! Messages are authenticated in every round with [`MessageAuthentication`] (i.e bound to the session and its transcript).
+//!
+//! Ref: <https://wamu.tech/specification#signing>.
+
+use cggmp_threshold_ecdsa::presign::state_machine::PreSigning;
+use cggmp_threshold_ecdsa::sign::state_machine::Signing;
+use curv::elliptic::curves::Secp256k1;
+use multi_party_ecdsa::protocols::multi_party_ecdsa::gg_2020::state_machine::keygen::LocalKey;
+use round_based::{Msg, StateMachine};
+use std::collections::HashMap;
+use wamu_core::crypto::VerifyingKey;
+use wamu_core::{IdentityProvider, SigningShare, SubShare};
+
+use crate::augmented_state_machine::{AugmentedType, Error, IdentityAuthParams};
+use crate::message_auth::{MessageAuthParams, MessageAuthentication};
+use crate::sign::{
+    AugmentedPreSigning, AugmentedSigning, NonceGuard, RecoverableSignature, RingPedersenParams,
+    SigningParams, SsidBuilder,
+};
+use crate::transport::{run_protocol, ProtocolError, Transport};
+
+/// An augmented pre-signing protocol message (with per-round message authentication).
+pub type PreSigningMessage = Msg<
+    AugmentedType<
+        AugmentedType<<PreSigning as StateMachine>::MessageBody, IdentityAuthParams>,
+        MessageAuthParams,
+    >,
+>;
+
+/// An augmented signing protocol message (with per-round message authentication).
+pub type SigningMessage = Msg<
+    AugmentedType<
+        AugmentedType<<Signing as StateMachine>::MessageBody, SigningParams>,
+        MessageAuthParams,
+    >,
+>;
+
+/// A one-shot signing error.
+#[derive(Debug)]
+pub enum SignMessageError<T> {
+    /// A critical pre-signing error.
+    PreSigning(Error<<PreSigning as StateMachine>::Err>),
+    /// A critical signing error.
+    Signing(Error<<Signing as StateMachine>::Err>),
+    /// A transport error.
+    Transport(T),
+    /// The party is not a participant (i.e its index is not in the list of participant indices).
+    NotParticipant,
+    /// The signing output is not a valid signature.
+    InvalidSignature,
+}
+
+/// Given a "signing share", "sub-share", identity provider, verifying keys for all parties,
+/// a local key (e.g from key generation or key refresh), indices of all participants,
+/// a random session identifier shared by all participants (i.e rid in the CGGMP20 paper),
+/// auxiliary "ring" Pedersen parameters for all participants, a transport and the message to be signed,
+/// runs pre-signing and signing back-to-back (with per-round message authentication, see [`MessageAuthentication`])
+/// and returns the recoverable signature of the message.
+///
+/// **NOTE:** The session identifier must be unique for each message,
+/// because reusing a pre-signing output for a different message leaks the secret key.
+pub fn sign_message<I, T, E>(
+    signing_share: &SigningShare,
+    sub_share: &SubShare,
+    identity_provider: &I,
+    verified_parties: &[VerifyingKey],
+    local_key: LocalKey<Secp256k1>,
+    party_indices: &[u16],
+    rid: [u8; 32],
+    aux_ring_pedersen_params: &HashMap<u16, RingPedersenParams>,
+    transport: &mut T,
+    message: &[u8],
+) -> Result<RecoverableSignature, SignMessageError<E>>
+where
+    I: IdentityProvider,
+    T: Transport<PreSigningMessage, Error = E> + Transport<SigningMessage, Error = E>,
+{
+    sign(
+        signing_share,
+        sub_share,
+        identity_provider,
+        verified_parties,
+        local_key,
+        party_indices,
+        rid,
+        aux_ring_pedersen_params,
+        transport,
+        SigningInput::Message(message),
+    )
+}
+
+/// Same as [`sign_message`] but for a 32 byte message digest (e.g a Keccak256 transaction hash)
+/// that's signed as is (i.e without hashing it with SHA256).
+pub fn sign_prehashed<I, T, E>(
+    signing_share: &SigningShare,
+    sub_share: &SubShare,
+    identity_provider: &I,
+    verified_parties: &[VerifyingKey],
+    local_key: LocalKey<Secp256k1>,
+    party_indices: &[u16],
+    rid: [u8; 32],
+    aux_ring_pedersen_params: &HashMap<u16, RingPedersenParams>,
+    transport: &mut T,
+    message_digest: &[u8; 32],
+) -> Result<RecoverableSignature, SignMessageError<E>>
+where
+    I: IdentityProvider,
+    T: Transport<PreSigningMessage, Error = E> + Transport<SigningMessage, Error = E>,
+{
+    sign(
+        signing_share,
+        sub_share,
+        identity_provider,
+        verified_parties,
+        local_key,
+        party_indices,
+        rid,
+        aux_ring_pedersen_params,
+        transport,
+        SigningInput::Prehashed(message_digest),
+    )
+}
+
+// The input to be signed.
+enum SigningInput<'a> {
+    /// A message (hashed with SHA256 before signing).
+    Message(&'a [u8]),
+    /// A 32 byte message digest (signed as is).
+    Prehashed(&'a [u8; 32]),
+}
+
+// Runs pre-signing and signing back-to-back and returns the recoverable signature of the input.
+fn sign<I, T, E>(
+    signing_share: &SigningShare,
+    sub_share: &SubShare,
+    identity_provider: &I,
+    verified_parties: &[VerifyingKey],
+    local_key: LocalKey<Secp256k1>,
+    party_indices: &[u16],
+    rid: [u8; 32],
+    aux_ring_pedersen_params: &HashMap<u16, RingPedersenParams>,
+    transport: &mut T,
+    input: SigningInput,
+) -> Result<RecoverableSignature, SignMessageError<E>>
+where
+    I: IdentityProvider,
+    T: Transport<PreSigningMessage, Error = E> + Transport<SigningMessage, Error = E>,
+{
+    // Each session only produces a single pre-signing output (i.e l in the CGGMP20 paper).
+    let pre_signing_output_idx = 1;
+    if !party_indices.contains(&local_key.i) {
+        return Err(SignMessageError::NotParticipant);
+    }
+    let (ssid, secrets) = SsidBuilder::new(local_key)
+        .with_party_indices(party_indices)
+        .with_rid(rid)
+        .build()
+        .map_err(SignMessageError::PreSigning)?;
+
+    // Runs pre-signing.
+    let mut pre_signing = AugmentedPreSigning::new(
+        signing_share,
+        sub_share,
+        identity_provider,
+        verified_parties,
+        ssid.clone(),
+        secrets,
+        aux_ring_pedersen_params,
+        pre_signing_output_idx,
+    )
+    .map_err(SignMessageError::PreSigning)?;
+    let mut pre_signing = MessageAuthentication::new(
+        pre_signing,
+        identity_provider,
+        verified_parties,
+        session_id(&rid, b"pre-signing"),
+        encode_pre_signing_body,
+    );
+    let (pre_signing_output, transcript) = run_protocol(&mut pre_signing, transport)
+        .map_err(|error| match error {
+            ProtocolError::StateMachine(error) => SignMessageError::PreSigning(error.flatten()),
+            ProtocolError::Transport(error) => SignMessageError::Transport(error),
+        })?
+        .base
+        .base
+        .ok_or(SignMessageError::NotParticipant)?;
+
+    // Runs signing.
+    let presigning_data = HashMap::from([(
+        pre_signing_output_idx as u16,
+        (pre_signing_output, transcript),
+    )]);
+    // The pre-signing output is only ever used for this message, so a fresh registry suffices.
+    let mut nonce_guard = NonceGuard::new();
+    let mut signing = match input {
+        SigningInput::Message(message) => AugmentedSigning::new(
+            &mut nonce_guard,
+            signing_share,
+            sub_share,
+            identity_provider,
+            verified_parties,
+            message,
+            ssid,
+            presigning_data,
+            pre_signing_output_idx,
+        ),
+        SigningInput::Prehashed(message_digest) => AugmentedSigning::new_prehashed(
+            &mut nonce_guard,
+            signing_share,
+            sub_share,
+            identity_provider,
+            verified_parties,
+            message_digest,
+            ssid,
+            presigning_data,
+            pre_signing_output_idx,
+        ),
+    }
+    .map_err(SignMessageError::Signing)?;
+    let mut signing = MessageAuthentication::new(
+        signing,
+        identity_provider,
+        verified_parties,
+        session_id(&rid, b"signing"),
+        encode_signing_body,
+    );
+    let signing_output = run_protocol(&mut signing, transport)
+        .map_err(|error| match error {
+            ProtocolError::StateMachine(error) => SignMessageError::Signing(error.flatten()),
+            ProtocolError::Transport(error) => SignMessageError::Transport(error),
+        })?
+        .base;
+
+    // Returns recoverable signature.
+    match signing_output {
+        AugmentedType { base: None, .. } => Err(SignMessageError::NotParticipant),
+        AugmentedType { extra, .. } => extra.ok_or(SignMessageError::InvalidSignature),
+    }
+}
+
+// Returns the session identifier for per-round message authentication of a protocol of the signing session
+// (i.e pre-signing and signing messages can't be replayed across protocols).
+fn session_id(rid: &[u8; 32], protocol: &[u8]) -> [u8; 32] {
+    use sha2::{digest::Update, Digest};
+    sha2::Sha256::new()
+        .chain(protocol)
+        .chain(rid)
+        .finalize()
+        .into()
+}
+
+// Encodes augmented pre-signing message bodies (deterministically) for per-round message authentication.
+//
+// NOTE: Identity authentication parameters are verified by the augmented state machine itself.
+fn encode_pre_signing_body(
+    body: &AugmentedType<<PreSigning as StateMachine>::MessageBody, IdentityAuthParams>,
+) -> Vec<u8> {
+    serde_json::to_vec(&body.base).expect("Pre-signing messages are always serializable")
+}
+
+// Encodes augmented signing message bodies (deterministically) for per-round message authentication.
+//
+// NOTE: Signing parameters are verified by the augmented state machine itself.
+fn encode_signing_body(
+    body: &AugmentedType<<Signing as StateMachine>::MessageBody, SigningParams>,
+) -> Vec<u8> {
+    serde_json::to_vec(&body.base).expect("Signing messages are always serializable")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen::tests::simulate_keygen;
+    use std::sync::mpsc::{channel, Receiver, Sender};
+    use wamu_core::test_utils::MockECDSAIdentityProvider;
+
+    // An in-memory transport for a single party.
+    struct ChannelTransport {
+        idx: u16,
+        pre_signing: (Vec<Sender<PreSigningMessage>>, Receiver<PreSigningMessage>),
+        signing: (Vec<Sender<SigningMessage>>, Receiver<SigningMessage>),
+    }
+
+    // Routes a message to its receiver (or all other parties for broadcast messages) twice
+    // (i.e like an at-least-once delivery transport, duplicates are dropped by message authentication).
+    fn route<M: Clone>(idx: u16, senders: &[Sender<Msg<M>>], msg: Msg<M>) -> Result<(), ()> {
+        for (i, sender) in senders.iter().enumerate() {
+            let receiver = i as u16 + 1;
+            if receiver != idx && msg.receiver.map_or(true, |it| it == receiver) {
+                sender.send(msg.clone()).map_err(|_| ())?;
+                sender.send(msg.clone()).map_err(|_| ())?;
+            }
+        }
+        Ok(())
+    }
+
+    impl Transport<PreSigningMessage> for ChannelTransport {
+        type Error = ();
+
+        fn send(&mut self, msg: PreSigningMessage) -> Result<(), Self::Error> {
+            route(self.idx, &self.pre_signing.0, msg)
+        }
+
+        fn receive(&mut self) -> Result<PreSigningMessage, Self::Error> {
+            self.pre_signing.1.recv().map_err(|_| ())
+        }
+    }
+
+    impl Transport<SigningMessage> for ChannelTransport {
+        type Error = ();
+
+        fn send(&mut self, msg: SigningMessage) -> Result<(), Self::Error> {
+            route(self.idx, &self.signing.0, msg)
+        }
+
+        fn receive(&mut self) -> Result<SigningMessage, Self::Error> {
+            self.signing.1.recv().map_err(|_| ())
+        }
+    }
+
+    #[test]
+    fn sign_message_works() {
+        // Runs key gen simulation and generates shared pre-signing parameters.
+        let (threshold, n_parties) = (1, 2);
+        let (keys, identity_providers) = simulate_keygen(threshold, n_parties);
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(MockECDSAIdentityProvider::verifying_key)
+            .collect();
+        let party_indices: Vec<u16> = (1..=n_parties).collect();
+        let rid = wamu_core::crypto::Random32Bytes::generate().to_be_bytes();
+        let aux_ring_pedersen_params: HashMap<u16, RingPedersenParams> = party_indices
+            .iter()
+            .map(|idx| (*idx, RingPedersenParams::generate()))
+            .collect();
+
+        // Creates in-memory transports for all parties.
+        let (pre_signing_senders, pre_signing_receivers): (Vec<_>, Vec<_>) =
+            party_indices.iter().map(|_| channel()).unzip();
+        let (signing_senders, signing_receivers): (Vec<_>, Vec<_>) =
+            party_indices.iter().map(|_| channel()).unzip();
+        let transports: Vec<ChannelTransport> = pre_signing_receivers
+            .into_iter()
+            .zip(signing_receivers)
+            .enumerate()
+            .map(
+                |(i, (pre_signing_receiver, signing_receiver))| ChannelTransport {
+                    idx: i as u16 + 1,
+                    pre_signing: (pre_signing_senders.clone(), pre_signing_receiver),
+                    signing: (signing_senders.clone(), signing_receiver),
+                },
+            )
+            .collect();
+
+        // Signs message with all parties concurrently (with every message delivered twice).
+        let message = b"Hello, world!";
+        let signatures: Vec<RecoverableSignature> = std::thread::scope(|scope| {
+            let handles: Vec<_> = transports
+                .into_iter()
+                .enumerate()
+                .map(|(i, mut transport)| {
+                    let (keys, identity_providers) = (&keys, &identity_providers);
+                    let (verifying_keys, party_indices) = (&verifying_keys, &party_indices);
+                    let aux_ring_pedersen_params = &aux_ring_pedersen_params;
+                    scope.spawn(move || {
+                        let (signing_share, sub_share) = keys[i].extra.as_ref().unwrap();
+                        sign_message(
+                            signing_share,
+                            sub_share,
+                            &identity_providers[i],
+                            verifying_keys,
+                            keys[i].base.clone(),
+                            party_indices,
+                            rid,
+                            aux_ring_pedersen_params,
+                            &mut transport,
+                            message,
+                        )
+                        .unwrap()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        });
+
+        // Verifies that all parties produced the same "low-s" normalized signature.
+        let q = curv::elliptic::curves::Scalar::<Secp256k1>::group_order();
+        assert!(signatures.iter().all(|it| it == &signatures[0]));
+        assert!(signatures[0].s <= q / curv::BigInt::from(2));
+        assert!(signatures[0].recovery_id < 4);
+    }
+}
@@ -8,6 +8,7 @@ use round_based::{Msg, StateMachine};
 use std::collections::HashMap;
 use std::time::Duration;
 use wamu_core::crypto::VerifyingKey;
+use wamu_core::quorum_approved_request::QuorumPolicy;
 use wamu_core::{IdentityProvider, SigningShare, SubShare};
 
 use crate::authorized_key_refresh::{AuthorizedKeyRefresh, Error, Message};
@@ -82,6 +83,8 @@ impl<'a, I: IdentityProvider> ThresholdModification<'a, I> {
             local_key.n,
             is_initiator,
             false,
+            // Threshold modification requires approval from one more party than the signing quorum.
+            QuorumPolicy::SigningQuorumPlus(1),
         );
 
         // Initializes threshold modification state machine.
@@ -110,18 +113,52 @@ impl<'a, I: IdentityProvider> ThresholdModification<'a, I> {
         // Returns threshold modification machine.
         Ok(threshold_modification)
     }
+
+    /// Enforces a delay (in seconds) between request initiation and key refresh,
+    /// during which any verified party can cancel the request (see [`cancel`](Self::cancel)).
+    ///
+    /// **NOTE:** Recommended for sensitive commands (e.g threshold decrease), all parties must use the same delay.
+    pub fn with_time_lock(mut self, delay: u64) -> Self {
+        self.auth_state_machine = self.auth_state_machine.with_time_lock(delay);
+        self
+    }
+
+    /// Sets verifying keys for observers (i.e watch-only parties) that can veto the request before it's approved
+    /// (see [`QuorumApproval::with_observers`]).
+    pub fn with_observers(mut self, observers: &'a [VerifyingKey]) -> Self {
+        self.auth_state_machine = self.auth_state_machine.with_observers(observers);
+        self
+    }
+
+    /// Cancels a time-locked request and broadcasts a signed cancellation to the other parties.
+    pub fn cancel(
+        &mut self,
+    ) -> Result<(), Error<'a, I, <QuorumApproval<'a, I> as StateMachine>::Err>> {
+        self.auth_state_machine.cancel()?;
+
+        // Wraps the cancellation message.
+        self.update_composite_message_queue()
+    }
 }
 
 impl<'a, I: IdentityProvider> AuthorizedKeyRefresh<'a, I> for ThresholdModification<'a, I> {
     type InitStateMachineType = QuorumApproval<'a, I>;
 
     impl_required_authorized_key_refresh_getters!(
+        verified_parties,
         auth_state_machine,
         refresh_state_machine,
         message_queue,
         out_of_order_buffer
     );
 
+    fn verify_authorization(
+        &self,
+    ) -> Result<(), Error<'a, I, <Self::InitStateMachineType as StateMachine>::Err>> {
+        // Destructive commands require a verified quorum (i.e not just the initiator's identity authentication).
+        self.auth_state_machine.verify_quorum().map_err(Error::Init)
+    }
+
     fn create_key_refresh(
         &mut self,
     ) -> Result<
@@ -292,4 +329,72 @@ pub mod tests {
     fn threshold_modification_works() {
         generate_parties_and_simulate_threshold_modification(1, 2, 4, 2);
     }
+
+    #[test]
+    fn threshold_modification_screens_early_key_refresh_messages() {
+        // Runs key gen simulation for test parameters and adds an identity for a joining party.
+        let (keys, mut identity_providers) = simulate_keygen(1, 3);
+        identity_providers.push(MockECDSAIdentityProvider::generate());
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let current_to_new_idx_map = HashMap::from([(1, 1), (2, 2), (3, 3)]);
+
+        // Initializes a party whose quorum approval isn't finished.
+        let (signing_share, sub_share) = keys[0].extra.as_ref().unwrap();
+        let mut party = ThresholdModification::new(
+            signing_share,
+            sub_share,
+            &identity_providers[0],
+            &verifying_keys,
+            keys[0].base.clone(),
+            1,
+            &current_to_new_idx_map,
+            false,
+        )
+        .unwrap();
+
+        // Generates a signed Round 1 key refresh message (i.e from a joining party).
+        let mut key_refresh = AugmentedKeyRefresh::new(
+            None,
+            None,
+            &identity_providers[3],
+            &verifying_keys,
+            None,
+            Some(4),
+            &current_to_new_idx_map,
+            1,
+            4,
+            Some(1),
+        )
+        .unwrap();
+        if key_refresh.wants_to_proceed() {
+            key_refresh.proceed().unwrap();
+        }
+        let refresh_msg = key_refresh.message_queue().remove(0);
+        let early_msg = |sender: u16| Msg {
+            sender,
+            receiver: None,
+            body: Message::Refresh(Box::new(refresh_msg.body.clone())),
+        };
+
+        // Messages from unknown parties or with additional parameters that aren't signed by a verified party are dropped.
+        assert!(matches!(
+            party.handle_incoming(early_msg(5)),
+            Err(Error::OutOfOrderMessage)
+        ));
+        assert!(matches!(
+            party.handle_incoming(early_msg(2)),
+            Err(Error::OutOfOrderMessage)
+        ));
+
+        // Only one message per sender and round is buffered.
+        party.handle_incoming(early_msg(4)).unwrap();
+        assert!(matches!(
+            party.handle_incoming(early_msg(4)),
+            Err(Error::OutOfOrderMessage)
+        ));
+        assert_eq!(party.out_of_order_buffer().len(), 1);
+    }
 }
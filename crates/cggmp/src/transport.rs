@@ -0,0 +1,561 @@
+//! Message transport abstraction for protocol sessions and reference (i.e in-memory and TCP) implementations.
+//!
+//! Any [`StateMachine`](StateMachine) (including augmented state machines) can be connected to a [`Transport`]
+//! with [`run_protocol`] (see also [`run_session`](crate::run_session) for async transports).
+
+use round_based::{IsCritical, Msg, StateMachine};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::Duration;
+
+use crate::socks5::{Socks5Error, Socks5Proxy};
+
+/// A blocking transport for delivering protocol messages between participants.
+///
+/// Participants are addressed by their party index (i.e `msg.sender` and `msg.receiver`).
+pub trait Transport<M> {
+    /// Transport error type.
+    type Error;
+
+    /// Sends an outgoing message (i.e a broadcast message if `msg.receiver` is `None` or a p2p message otherwise).
+    fn send(&mut self, msg: M) -> Result<(), Self::Error>;
+
+    /// Blocks until the next incoming message is received and returns it.
+    fn receive(&mut self) -> Result<M, Self::Error>;
+}
+
+/// A protocol execution error.
+#[derive(Debug)]
+pub enum ProtocolError<E, T> {
+    /// A critical state machine error.
+    StateMachine(E),
+    /// A transport error.
+    Transport(T),
+}
+
+/// Given a state machine and a transport, drives the state machine until it finishes and returns its output,
+/// or a critical state machine error or transport error otherwise.
+///
+/// **NOTE:** Non-critical state machine errors (e.g duplicate messages) are ignored.
+pub fn run_protocol<SM, T>(
+    state_machine: &mut SM,
+    transport: &mut T,
+) -> Result<SM::Output, ProtocolError<SM::Err, T::Error>>
+where
+    SM: StateMachine,
+    T: Transport<Msg<SM::MessageBody>>,
+{
+    loop {
+        // Sends outgoing messages.
+        for msg in state_machine.message_queue().drain(..) {
+            transport.send(msg).map_err(ProtocolError::Transport)?;
+        }
+
+        // Returns output if the protocol is finished.
+        if let Some(output) = state_machine.pick_output() {
+            return output.map_err(ProtocolError::StateMachine);
+        }
+
+        // Proceeds to the next round, or handles the next incoming message otherwise.
+        let result = if state_machine.wants_to_proceed() {
+            state_machine.proceed()
+        } else {
+            let msg = transport.receive().map_err(ProtocolError::Transport)?;
+            state_machine.handle_incoming(msg)
+        };
+        if let Err(error) = result {
+            if error.is_critical() {
+                return Err(ProtocolError::StateMachine(error));
+            }
+        }
+    }
+}
+
+/// A transport error for the reference transports.
+#[derive(Debug)]
+pub enum TransportError {
+    /// An I/O error.
+    Io(std::io::Error),
+    /// A SOCKS5 proxy error (i.e for connections via a proxy).
+    Proxy(Socks5Error),
+    /// A message that couldn't be encoded or decoded.
+    Encoding,
+    /// A party index that's not part of the network (e.g the receiver of an outgoing message).
+    UnknownParty(u16),
+    /// An incoming message whose sender doesn't match the party that delivered it.
+    UnexpectedSender,
+    /// All other parties have disconnected.
+    Closed,
+}
+
+impl From<std::io::Error> for TransportError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<Socks5Error> for TransportError {
+    fn from(error: Socks5Error) -> Self {
+        Self::Proxy(error)
+    }
+}
+
+/// An in-memory (i.e channel based) transport for parties running in the same process (e.g tests and simulations).
+pub struct InMemoryTransport<B> {
+    idx: u16,
+    senders: BTreeMap<u16, Sender<Msg<B>>>,
+    receiver: Receiver<Msg<B>>,
+}
+
+impl<B> InMemoryTransport<B> {
+    /// Given the indices of all parties, returns connected transports for all parties (in the same order).
+    pub fn network(party_indices: &[u16]) -> Vec<Self> {
+        let (senders, receivers): (Vec<_>, Vec<_>) =
+            party_indices.iter().map(|_| channel()).unzip();
+        let senders: BTreeMap<u16, Sender<Msg<B>>> =
+            party_indices.iter().copied().zip(senders).collect();
+        party_indices
+            .iter()
+            .zip(receivers)
+            .map(|(idx, receiver)| Self {
+                idx: *idx,
+                // Parties don't hold a sender for themselves, so receiving fails once all other parties are dropped.
+                senders: senders
+                    .iter()
+                    .filter(|(other_idx, _)| *other_idx != idx)
+                    .map(|(other_idx, sender)| (*other_idx, sender.clone()))
+                    .collect(),
+                receiver,
+            })
+            .collect()
+    }
+
+    /// Returns the index of the party.
+    pub fn idx(&self) -> u16 {
+        self.idx
+    }
+}
+
+impl<B: Clone> Transport<Msg<B>> for InMemoryTransport<B> {
+    type Error = TransportError;
+
+    fn send(&mut self, msg: Msg<B>) -> Result<(), Self::Error> {
+        match msg.receiver {
+            Some(receiver) => {
+                let sender = self
+                    .senders
+                    .get(&receiver)
+                    .ok_or(TransportError::UnknownParty(receiver))?;
+                // Parties that already finished have dropped their receivers.
+                let _ = sender.send(msg);
+            }
+            None => {
+                for sender in self.senders.values() {
+                    let _ = sender.send(msg.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Result<Msg<B>, Self::Error> {
+        self.receiver.recv().map_err(|_| TransportError::Closed)
+    }
+}
+
+/// The maximum length of a message body in a frame.
+pub(crate) const MAX_FRAME_BODY_LEN: usize = 1 << 24;
+
+/// The number of attempts for connecting to a peer (i.e peers may not be listening yet).
+const CONNECT_ATTEMPTS: usize = 50;
+
+/// The delay between attempts for connecting to a peer.
+const CONNECT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// A TCP transport with a direct connection to each other party.
+///
+/// Messages are sent as frames of the form `sender (u16) || has receiver (u8) || receiver (u16) || body length (u32) || body`
+/// (i.e big-endian integers), with the message body encoded by the given codec.
+///
+/// **NOTE:** This is a reference implementation, connections are neither encrypted nor authenticated
+/// (i.e the party index exchanged when connecting is trusted and only messages are authenticated by augmented state machines),
+/// so it should only be used over a secure channel (e.g TLS or a VPN) in production.
+pub struct TcpTransport<B> {
+    idx: u16,
+    streams: BTreeMap<u16, TcpStream>,
+    incoming: Receiver<Result<Msg<B>, TransportError>>,
+    encode: fn(&B) -> Vec<u8>,
+}
+
+impl<B: Send + 'static> TcpTransport<B> {
+    /// Given the index of the party, a listener bound to the address of the party,
+    /// the addresses of all other parties and a codec (i.e encoder and decoder) for message bodies,
+    /// returns a transport connected to all other parties.
+    ///
+    /// Parties connect to other parties with lower indices and accept connections from other parties with higher indices,
+    /// so all parties must call this function concurrently.
+    pub fn connect(
+        idx: u16,
+        listener: &TcpListener,
+        peers: &BTreeMap<u16, SocketAddr>,
+        encode: fn(&B) -> Vec<u8>,
+        decode: fn(&[u8]) -> Option<B>,
+    ) -> Result<Self, TransportError> {
+        Self::connect_with(
+            idx,
+            listener,
+            peers,
+            |addr| Ok(TcpStream::connect(addr)?),
+            encode,
+            decode,
+        )
+    }
+
+    /// Same as [`TcpTransport::connect`] but connects to other parties via a SOCKS5 proxy (e.g Tor),
+    /// given the host (e.g a `.onion` address) and port of each other party.
+    ///
+    /// **NOTE:** Incoming connections are still accepted on the listener (e.g bound to localhost as the target of a Tor onion service),
+    /// so parties with onion services never learn each other's IP addresses.
+    pub fn connect_via_proxy(
+        idx: u16,
+        listener: &TcpListener,
+        peers: &BTreeMap<u16, (String, u16)>,
+        proxy: &Socks5Proxy,
+        encode: fn(&B) -> Vec<u8>,
+        decode: fn(&[u8]) -> Option<B>,
+    ) -> Result<Self, TransportError> {
+        Self::connect_with(
+            idx,
+            listener,
+            peers,
+            |(host, port)| Ok(proxy.connect(host, *port)?),
+            encode,
+            decode,
+        )
+    }
+
+    // Connects to parties with lower indices (with the given dialer) and accepts connections from parties with higher indices.
+    fn connect_with<A>(
+        idx: u16,
+        listener: &TcpListener,
+        peers: &BTreeMap<u16, A>,
+        dial: impl Fn(&A) -> Result<TcpStream, TransportError>,
+        encode: fn(&B) -> Vec<u8>,
+        decode: fn(&[u8]) -> Option<B>,
+    ) -> Result<Self, TransportError> {
+        let mut streams = BTreeMap::new();
+
+        // Connects to parties with lower indices and sends the party index.
+        for (peer_idx, addr) in peers.range(..idx) {
+            let mut stream = connect_with_retries(|| dial(addr))?;
+            stream.write_all(&idx.to_be_bytes())?;
+            streams.insert(*peer_idx, stream);
+        }
+
+        // Accepts connections from parties with higher indices and reads their party index.
+        let mut pending: BTreeSet<u16> = peers
+            .keys()
+            .filter(|peer_idx| **peer_idx > idx)
+            .copied()
+            .collect();
+        while !pending.is_empty() {
+            let (mut stream, _) = listener.accept()?;
+            let mut peer_idx = [0u8; 2];
+            stream.read_exact(&mut peer_idx)?;
+            let peer_idx = u16::from_be_bytes(peer_idx);
+            if !pending.remove(&peer_idx) {
+                return Err(TransportError::UnknownParty(peer_idx));
+            }
+            streams.insert(peer_idx, stream);
+        }
+
+        // Reads frames from all connections into a single incoming message queue.
+        let (sender, incoming) = channel();
+        for (peer_idx, stream) in &streams {
+            stream.set_nodelay(true)?;
+            let (peer_idx, mut stream, sender) = (*peer_idx, stream.try_clone()?, sender.clone());
+            std::thread::spawn(move || loop {
+                let result = read_frame(&mut stream, decode).and_then(|msg| {
+                    // Parties can only send messages as themselves and only to this party (or everyone).
+                    if msg.sender == peer_idx && msg.receiver.map_or(true, |it| it == idx) {
+                        Ok(msg)
+                    } else {
+                        Err(TransportError::UnexpectedSender)
+                    }
+                });
+                let is_err = result.is_err();
+                if sender.send(result).is_err() || is_err {
+                    break;
+                }
+            });
+        }
+
+        Ok(Self {
+            idx,
+            streams,
+            incoming,
+            encode,
+        })
+    }
+
+    /// Returns the index of the party.
+    pub fn idx(&self) -> u16 {
+        self.idx
+    }
+}
+
+impl<B> Transport<Msg<B>> for TcpTransport<B> {
+    type Error = TransportError;
+
+    fn send(&mut self, msg: Msg<B>) -> Result<(), Self::Error> {
+        let frame = encode_frame(&msg, self.encode)?;
+        match msg.receiver {
+            Some(receiver) => self
+                .streams
+                .get_mut(&receiver)
+                .ok_or(TransportError::UnknownParty(receiver))?
+                .write_all(&frame)?,
+            None => {
+                for stream in self.streams.values_mut() {
+                    stream.write_all(&frame)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Result<Msg<B>, Self::Error> {
+        self.incoming.recv().map_err(|_| TransportError::Closed)?
+    }
+}
+
+// Connects with the dialer, retrying for parties that aren't listening (or reachable) yet.
+fn connect_with_retries(
+    dial: impl Fn() -> Result<TcpStream, TransportError>,
+) -> Result<TcpStream, TransportError> {
+    let mut attempts = 1;
+    loop {
+        match dial() {
+            Ok(stream) => return Ok(stream),
+            Err(error) if attempts >= CONNECT_ATTEMPTS => return Err(error),
+            // Only connection failures are retried (e.g not proxy authentication failures).
+            Err(
+                error @ TransportError::Proxy(
+                    Socks5Error::InvalidTarget
+                    | Socks5Error::InvalidCredentials
+                    | Socks5Error::NoAcceptableMethod
+                    | Socks5Error::AuthenticationFailed,
+                ),
+            ) => return Err(error),
+            Err(_) => {
+                attempts += 1;
+                std::thread::sleep(CONNECT_RETRY_DELAY);
+            }
+        }
+    }
+}
+
+/// The length of a frame header.
+const FRAME_HEADER_LEN: usize = 9;
+
+/// Returns the frame for the message (i.e as sent by the TCP transport).
+pub(crate) fn encode_frame<B>(
+    msg: &Msg<B>,
+    encode: fn(&B) -> Vec<u8>,
+) -> Result<Vec<u8>, TransportError> {
+    let body = encode(&msg.body);
+    if body.len() > MAX_FRAME_BODY_LEN {
+        return Err(TransportError::Encoding);
+    }
+    let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + body.len());
+    frame.extend_from_slice(&msg.sender.to_be_bytes());
+    frame.push(msg.receiver.is_some() as u8);
+    frame.extend_from_slice(&msg.receiver.unwrap_or_default().to_be_bytes());
+    frame.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    frame.extend(body);
+    Ok(frame)
+}
+
+/// Returns the message for a frame (i.e as sent by the TCP transport).
+pub(crate) fn decode_frame<B>(
+    frame: &[u8],
+    decode: fn(&[u8]) -> Option<B>,
+) -> Result<Msg<B>, TransportError> {
+    if frame.len() < FRAME_HEADER_LEN {
+        return Err(TransportError::Encoding);
+    }
+    let (header, body) = frame.split_at(FRAME_HEADER_LEN);
+    let (sender, receiver, body_len) = decode_frame_header(header)?;
+    if body.len() != body_len {
+        return Err(TransportError::Encoding);
+    }
+    Ok(Msg {
+        sender,
+        receiver,
+        body: decode(body).ok_or(TransportError::Encoding)?,
+    })
+}
+
+// Returns the sender, receiver and body length for a frame header.
+fn decode_frame_header(header: &[u8]) -> Result<(u16, Option<u16>, usize), TransportError> {
+    let sender = u16::from_be_bytes([header[0], header[1]]);
+    let receiver = match header[2] {
+        0 => None,
+        1 => Some(u16::from_be_bytes([header[3], header[4]])),
+        _ => return Err(TransportError::Encoding),
+    };
+    let body_len = u32::from_be_bytes([header[5], header[6], header[7], header[8]]) as usize;
+    if body_len > MAX_FRAME_BODY_LEN {
+        return Err(TransportError::Encoding);
+    }
+    Ok((sender, receiver, body_len))
+}
+
+// Reads the next frame from the stream and returns its message.
+fn read_frame<B>(
+    stream: &mut TcpStream,
+    decode: fn(&[u8]) -> Option<B>,
+) -> Result<Msg<B>, TransportError> {
+    let mut header = [0u8; FRAME_HEADER_LEN];
+    stream.read_exact(&mut header)?;
+    let (sender, receiver, body_len) = decode_frame_header(&header)?;
+    let mut body = vec![0u8; body_len];
+    stream.read_exact(&mut body)?;
+    Ok(Msg {
+        sender,
+        receiver,
+        body: decode(&body).ok_or(TransportError::Encoding)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen::AugmentedKeyGen;
+    use wamu_core::crypto::VerifyingKey;
+    use wamu_core::test_utils::MockECDSAIdentityProvider;
+    use wamu_core::IdentityProvider;
+
+    #[test]
+    fn in_memory_transport_works() {
+        // Creates identity providers and verifying keys for all parties.
+        let (threshold, n_parties) = (1, 3);
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (1..=n_parties)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let party_indices: Vec<u16> = (1..=n_parties).collect();
+
+        // Runs key generation for all parties concurrently.
+        let outputs: Vec<_> = std::thread::scope(|scope| {
+            let handles: Vec<_> = InMemoryTransport::network(&party_indices)
+                .into_iter()
+                .map(|mut transport| {
+                    let (identity_providers, verifying_keys) =
+                        (&identity_providers, &verifying_keys);
+                    scope.spawn(move || {
+                        let idx = transport.idx();
+                        let mut keygen = AugmentedKeyGen::new(
+                            &identity_providers[idx as usize - 1],
+                            verifying_keys,
+                            idx,
+                            threshold,
+                            n_parties,
+                        )
+                        .unwrap();
+                        run_protocol(&mut keygen, &mut transport).unwrap()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        });
+
+        // Verifies that all parties generated the same public key.
+        assert_eq!(outputs.len(), n_parties as usize);
+        for output in &outputs {
+            assert_eq!(output.base.public_key(), outputs[0].base.public_key());
+        }
+    }
+
+    #[test]
+    fn tcp_transport_works() {
+        // Binds listeners for all parties.
+        let n_parties = 3u16;
+        let listeners: Vec<TcpListener> = (0..n_parties)
+            .map(|_| TcpListener::bind("127.0.0.1:0").unwrap())
+            .collect();
+        let addrs: BTreeMap<u16, SocketAddr> = listeners
+            .iter()
+            .enumerate()
+            .map(|(i, listener)| (i as u16 + 1, listener.local_addr().unwrap()))
+            .collect();
+
+        // Sends a broadcast message and a p2p message to the next party from all parties concurrently.
+        let received: Vec<Vec<Msg<Vec<u8>>>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = listeners
+                .iter()
+                .enumerate()
+                .map(|(i, listener)| {
+                    let addrs = &addrs;
+                    scope.spawn(move || {
+                        let idx = i as u16 + 1;
+                        let peers: BTreeMap<u16, SocketAddr> = addrs
+                            .iter()
+                            .filter(|(peer_idx, _)| **peer_idx != idx)
+                            .map(|(peer_idx, addr)| (*peer_idx, *addr))
+                            .collect();
+                        let mut transport = TcpTransport::connect(
+                            idx,
+                            listener,
+                            &peers,
+                            |body: &Vec<u8>| body.clone(),
+                            |bytes| Some(bytes.to_vec()),
+                        )
+                        .unwrap();
+                        transport
+                            .send(Msg {
+                                sender: idx,
+                                receiver: None,
+                                body: vec![idx as u8],
+                            })
+                            .unwrap();
+                        transport
+                            .send(Msg {
+                                sender: idx,
+                                receiver: Some(idx % n_parties + 1),
+                                body: vec![idx as u8; 2],
+                            })
+                            .unwrap();
+                        (0..n_parties)
+                            .map(|_| transport.receive().unwrap())
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        });
+
+        // Verifies that each party received broadcast messages from all other parties
+        // and a p2p message from the previous party.
+        for (i, msgs) in received.iter().enumerate() {
+            let idx = i as u16 + 1;
+            let prev_idx = (idx + n_parties - 2) % n_parties + 1;
+            assert_eq!(msgs.iter().filter(|msg| msg.receiver.is_none()).count(), 2);
+            assert!(msgs.iter().all(|msg| msg.sender != idx));
+            assert!(msgs.iter().any(|msg| msg.sender == prev_idx
+                && msg.receiver == Some(idx)
+                && msg.body == vec![prev_idx as u8; 2]));
+        }
+    }
+}
@@ -0,0 +1,323 @@
+//! WebSocket relay client transport (i.e for parties that can't connect directly, e.g browsers and mobile apps behind NAT).
+//!
+//! The relay protocol is a simple binary protocol over WebSockets:
+//! - After connecting, each party sends a join frame of the form `session id (32 bytes) || party index (u16)`,
+//!   and the relay adds the connection to the room for the session identifier.
+//! - Each protocol message is sent as a frame of the form `sender (u16) || has receiver (u8) || receiver (u16) || nonce (12 bytes) || sealed payload`
+//!   (i.e big-endian integers), and the relay forwards the frame as is to the receiver (or all other parties in the room for broadcast messages).
+//!
+//! Payloads are sealed with AES-256-GCM under a session key shared by all participants
+//! (e.g agreed on as part of the identity authenticated request that initiates the session),
+//! with the session identifier and frame header as associated data, so the relay can route frames but can neither read nor alter them.
+//!
+//! **NOTE:** Relays should only forward frames whose sender matches the joined party index
+//! and should buffer frames for parties that haven't joined the room yet.
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use round_based::Msg;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use tungstenite::handshake::HandshakeError;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+
+use crate::socks5::{Socks5Error, Socks5Proxy};
+use crate::transport::Transport;
+
+/// The default port for `ws://` URLs.
+const DEFAULT_WS_PORT: u16 = 80;
+
+/// The length of a relay frame header.
+const HEADER_LEN: usize = 5;
+
+/// The length of an AES-GCM nonce.
+const NONCE_LEN: usize = 12;
+
+/// A WebSocket relay transport error.
+#[derive(Debug)]
+pub enum WsRelayError {
+    /// A WebSocket error.
+    WebSocket(Box<tungstenite::Error>),
+    /// A SOCKS5 proxy error (i.e for connections via a proxy).
+    Proxy(Socks5Error),
+    /// A relay URL that isn't supported for connections via a proxy (i.e a `wss://` URL).
+    UnsupportedUrl,
+    /// A message that couldn't be encoded or decoded (including unexpected non-binary WebSocket messages).
+    Encoding,
+    /// A frame whose payload couldn't be opened (i.e it wasn't sealed with the session key for the session and frame header).
+    Unsealing,
+    /// A frame that's addressed to another party or that's sent by this party.
+    UnexpectedFrame,
+    /// The relay closed the connection.
+    Closed,
+}
+
+impl From<tungstenite::Error> for WsRelayError {
+    fn from(error: tungstenite::Error) -> Self {
+        Self::WebSocket(Box::new(error))
+    }
+}
+
+impl From<Socks5Error> for WsRelayError {
+    fn from(error: Socks5Error) -> Self {
+        Self::Proxy(error)
+    }
+}
+
+/// A relay session (i.e sealing and opening of relay frames for a party),
+/// which is independent of the WebSocket implementation (e.g for browsers and mobile apps with platform WebSocket clients).
+pub struct RelaySession {
+    idx: u16,
+    session_id: [u8; 32],
+    cipher: Aes256Gcm,
+}
+
+impl RelaySession {
+    /// Given the index of the party, the session identifier and the session key shared by all participants,
+    /// returns a relay session.
+    pub fn new(idx: u16, session_id: [u8; 32], session_key: &[u8; 32]) -> Self {
+        Self {
+            idx,
+            session_id,
+            cipher: Aes256Gcm::new(session_key.into()),
+        }
+    }
+
+    /// Returns the index of the party.
+    pub fn idx(&self) -> u16 {
+        self.idx
+    }
+
+    /// Returns the join frame for the party.
+    pub fn join_frame(&self) -> Vec<u8> {
+        [self.session_id.as_slice(), &self.idx.to_be_bytes()].concat()
+    }
+
+    /// Given an outgoing message and an encoder for the message body, returns the relay frame for the message.
+    pub fn seal<B>(
+        &self,
+        msg: &Msg<B>,
+        encode: fn(&B) -> Vec<u8>,
+    ) -> Result<Vec<u8>, WsRelayError> {
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&msg.sender.to_be_bytes());
+        frame.push(msg.receiver.is_some() as u8);
+        frame.extend_from_slice(&msg.receiver.unwrap_or_default().to_be_bytes());
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let payload = self
+            .cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &encode(&msg.body),
+                    aad: &self.associated_data(&frame),
+                },
+            )
+            .map_err(|_| WsRelayError::Encoding)?;
+        frame.extend_from_slice(&nonce);
+        frame.extend(payload);
+        Ok(frame)
+    }
+
+    /// Given an incoming relay frame and a decoder for the message body, returns the message
+    /// or an appropriate error for frames that can't be opened or that aren't addressed to the party.
+    pub fn open<B>(
+        &self,
+        frame: &[u8],
+        decode: fn(&[u8]) -> Option<B>,
+    ) -> Result<Msg<B>, WsRelayError> {
+        if frame.len() < HEADER_LEN + NONCE_LEN {
+            return Err(WsRelayError::Encoding);
+        }
+        let (header, rest) = frame.split_at(HEADER_LEN);
+        let (nonce, payload) = rest.split_at(NONCE_LEN);
+        let sender = u16::from_be_bytes([header[0], header[1]]);
+        let receiver = match header[2] {
+            0 => None,
+            1 => Some(u16::from_be_bytes([header[3], header[4]])),
+            _ => return Err(WsRelayError::Encoding),
+        };
+        // Frames must be sent by another party to this party (or everyone).
+        if sender == self.idx || receiver.map_or(false, |it| it != self.idx) {
+            return Err(WsRelayError::UnexpectedFrame);
+        }
+        let body = self
+            .cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: payload,
+                    aad: &self.associated_data(header),
+                },
+            )
+            .map_err(|_| WsRelayError::Unsealing)?;
+        Ok(Msg {
+            sender,
+            receiver,
+            body: decode(&body).ok_or(WsRelayError::Encoding)?,
+        })
+    }
+
+    // Returns the associated data for a frame header (i.e `session id || header`).
+    fn associated_data(&self, header: &[u8]) -> Vec<u8> {
+        [self.session_id.as_slice(), header].concat()
+    }
+}
+
+/// A WebSocket relay client transport (see module documentation for the relay protocol).
+///
+/// **NOTE:** Connecting to `wss://` relays requires enabling one of the TLS features of `tungstenite`
+/// (e.g `rustls-tls-native-roots`).
+pub struct WsRelayTransport<B, S = MaybeTlsStream<TcpStream>> {
+    session: RelaySession,
+    socket: WebSocket<S>,
+    encode: fn(&B) -> Vec<u8>,
+    decode: fn(&[u8]) -> Option<B>,
+}
+
+impl<B> WsRelayTransport<B> {
+    /// Given the relay URL, a relay session and a codec (i.e encoder and decoder) for message bodies,
+    /// connects to the relay, joins the room for the session and returns the transport.
+    pub fn connect(
+        url: &str,
+        session: RelaySession,
+        encode: fn(&B) -> Vec<u8>,
+        decode: fn(&[u8]) -> Option<B>,
+    ) -> Result<Self, WsRelayError> {
+        let (socket, _) = tungstenite::connect(url)?;
+        Self::join(socket, session, encode, decode)
+    }
+
+    /// Same as [`WsRelayTransport::connect`] but connects to the relay via a SOCKS5 proxy (e.g Tor),
+    /// so relay URLs can be `.onion` addresses and the relay doesn't learn the IP address of the party.
+    ///
+    /// **NOTE:** Only `ws://` relay URLs are supported (frame payloads are sealed regardless, and onion services are end-to-end encrypted),
+    /// for `wss://` relays, wrap a stream from [`Socks5Proxy::connect`] in a TLS stream and use [`WsRelayTransport::join`].
+    pub fn connect_via_proxy(
+        url: &str,
+        proxy: &Socks5Proxy,
+        session: RelaySession,
+        encode: fn(&B) -> Vec<u8>,
+        decode: fn(&[u8]) -> Option<B>,
+    ) -> Result<Self, WsRelayError> {
+        if !url.starts_with("ws://") {
+            return Err(WsRelayError::UnsupportedUrl);
+        }
+        let stream = proxy.connect_url(url, DEFAULT_WS_PORT)?;
+        let (socket, _) = tungstenite::client(url, MaybeTlsStream::Plain(stream)).map_err(
+            |error| match error {
+                HandshakeError::Failure(error) => WsRelayError::from(error),
+                // Blocking streams are never interrupted.
+                HandshakeError::Interrupted(_) => WsRelayError::Closed,
+            },
+        )?;
+        Self::join(socket, session, encode, decode)
+    }
+}
+
+impl<B, S: Read + Write> WsRelayTransport<B, S> {
+    /// Same as [`WsRelayTransport::connect`] but for an already connected WebSocket (e.g over a custom stream).
+    pub fn join(
+        mut socket: WebSocket<S>,
+        session: RelaySession,
+        encode: fn(&B) -> Vec<u8>,
+        decode: fn(&[u8]) -> Option<B>,
+    ) -> Result<Self, WsRelayError> {
+        socket.send(Message::Binary(session.join_frame()))?;
+        Ok(Self {
+            session,
+            socket,
+            encode,
+            decode,
+        })
+    }
+
+    /// Returns the index of the party.
+    pub fn idx(&self) -> u16 {
+        self.session.idx()
+    }
+}
+
+impl<B, S: Read + Write> Transport<Msg<B>> for WsRelayTransport<B, S> {
+    type Error = WsRelayError;
+
+    fn send(&mut self, msg: Msg<B>) -> Result<(), Self::Error> {
+        let frame = self.session.seal(&msg, self.encode)?;
+        Ok(self.socket.send(Message::Binary(frame))?)
+    }
+
+    fn receive(&mut self) -> Result<Msg<B>, Self::Error> {
+        loop {
+            match self.socket.read() {
+                Ok(Message::Binary(frame)) => return self.session.open(&frame, self.decode),
+                // Control frames are handled by the WebSocket implementation.
+                Ok(Message::Ping(_) | Message::Pong(_) | Message::Frame(_)) => continue,
+                Ok(Message::Text(_)) => return Err(WsRelayError::Encoding),
+                Ok(Message::Close(_))
+                | Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => {
+                    return Err(WsRelayError::Closed)
+                }
+                Err(error) => return Err(error.into()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relay_session_works() {
+        let session_id = [1u8; 32];
+        let session_key = [2u8; 32];
+        let alice = RelaySession::new(1, session_id, &session_key);
+        let bob = RelaySession::new(2, session_id, &session_key);
+        let carol = RelaySession::new(3, session_id, &session_key);
+        let encode = |body: &Vec<u8>| body.clone();
+        let decode = |bytes: &[u8]| Some(bytes.to_vec());
+
+        // Verifies that broadcast and p2p messages are opened by their receivers.
+        let broadcast = Msg {
+            sender: 1,
+            receiver: None,
+            body: b"broadcast".to_vec(),
+        };
+        let frame = alice.seal(&broadcast, encode).unwrap();
+        assert_eq!(bob.open(&frame, decode).unwrap(), broadcast);
+        assert_eq!(carol.open(&frame, decode).unwrap(), broadcast);
+        assert!(matches!(
+            alice.open(&frame, decode),
+            Err(WsRelayError::UnexpectedFrame)
+        ));
+        let p2p = Msg {
+            sender: 1,
+            receiver: Some(2),
+            body: b"p2p".to_vec(),
+        };
+        let frame = alice.seal(&p2p, encode).unwrap();
+        assert_eq!(bob.open(&frame, decode).unwrap(), p2p);
+        assert!(matches!(
+            carol.open(&frame, decode),
+            Err(WsRelayError::UnexpectedFrame)
+        ));
+
+        // Verifies that frames can't be altered or opened for other sessions or with other session keys.
+        let mut altered_frame = frame.clone();
+        altered_frame[1] = 3;
+        assert!(matches!(
+            bob.open(&altered_frame, decode),
+            Err(WsRelayError::Unsealing)
+        ));
+        for other in [
+            RelaySession::new(2, [3u8; 32], &session_key),
+            RelaySession::new(2, session_id, &[3u8; 32]),
+        ] {
+            assert!(matches!(
+                other.open(&frame, decode),
+                Err(WsRelayError::Unsealing)
+            ));
+        }
+    }
+}
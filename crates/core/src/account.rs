@@ -0,0 +1,259 @@
+//! Multi-account wallets (i.e many logical accounts served by a single roster and key generation).
+//!
+//! Each account is a non-hardened BIP-32 child (i.e `m/<account>`) of the group key (see [`crate::bip32`]),
+//! so signing for an account only requires applying the account's derivation tweak at signing time.
+//!
+//! Commands are scoped to an account by prefixing them with `account/<account>/`,
+//! so the account is covered by request signatures (and therefore by approvals of the request),
+//! while storage keys, quorum policies and command logs are namespaced per account.
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::bip32::{ExtendedPublicKey, HARDENED_OFFSET};
+use crate::command_log::{CommandLog, CommandLogEntry};
+use crate::errors::Bip32Error;
+use crate::identity_authed_request;
+use crate::inbox::{Inbox, InboxEntry};
+use crate::payloads::IdentityAuthedRequestPayload;
+use crate::quorum_approved_request::QuorumPolicy;
+use crate::traits::IdentityProvider;
+use crate::utils;
+
+/// Prefix of account scoped commands (i.e `account/<account>/<command>`).
+pub const ACCOUNT_SCOPE_PREFIX: &str = "account/";
+
+/// Identifier of a logical account (i.e its non-hardened BIP-32 child index relative to the group key).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AccountId(u32);
+
+impl AccountId {
+    /// Returns the account identifier for a non-hardened child index, or an error for hardened indices.
+    pub fn new(index: u32) -> Result<Self, Bip32Error> {
+        if index < HARDENED_OFFSET {
+            Ok(Self(index))
+        } else {
+            Err(Bip32Error::HardenedIndex)
+        }
+    }
+
+    /// Returns the child index of the account.
+    pub fn index(&self) -> u32 {
+        self.0
+    }
+
+    /// Returns the derivation path of the account relative to the group key (i.e `m/<account>`).
+    pub fn derivation_path(&self) -> Vec<u32> {
+        vec![self.0]
+    }
+
+    /// Given the extended public key for the group key, returns the extended public key for the account
+    /// and the tweak that must be applied at signing time (see [`ExtendedPublicKey::derive_child`]).
+    ///
+    /// **NOTE:** Per-deposit addresses can be derived from the account's extended public key (i.e `m/<account>/<index>`),
+    /// in which case the tweaks of both derivation steps must be added (see [`ExtendedPublicKey::derive_path`]).
+    pub fn derive(
+        &self,
+        group_key: &ExtendedPublicKey,
+    ) -> Result<(ExtendedPublicKey, [u8; 32]), Bip32Error> {
+        group_key.derive_child(self.0)
+    }
+
+    /// Returns the command scoped to the account (i.e `account/<account>/<command>`).
+    pub fn scope_command(&self, command: &str) -> String {
+        format!("{ACCOUNT_SCOPE_PREFIX}{}/{command}", self.0)
+    }
+
+    /// Given a command and an identity provider, returns the payload for initiating an identity authenticated request
+    /// for the command scoped to the account (e.g a signing request for the account).
+    pub fn initiate_request(
+        &self,
+        command: &str,
+        identity_provider: &impl IdentityProvider,
+    ) -> IdentityAuthedRequestPayload {
+        identity_authed_request::initiate_with_command(
+            self.scope_command(command),
+            identity_provider,
+        )
+    }
+
+    /// Given a wallet identifier and a name, returns a storage key namespaced to the wallet and account
+    /// (i.e `wamu/<wallet id>/accounts/<account>/<name>`).
+    pub fn storage_key(&self, wallet_id: &[u8; 32], name: &str) -> String {
+        format!(
+            "wamu/{}/accounts/{}/{name}",
+            utils::to_hex(wallet_id),
+            self.0
+        )
+    }
+}
+
+/// Given a command, returns the account and the unscoped command for account scoped commands,
+/// or `None` for commands that aren't scoped to an account (e.g wallet governance commands).
+pub fn parse_scoped_command(command: &str) -> Option<(AccountId, &str)> {
+    let (index, command) = command
+        .strip_prefix(ACCOUNT_SCOPE_PREFIX)?
+        .split_once('/')?;
+    let account = AccountId::new(index.parse().ok()?).ok()?;
+    // Only canonical indices (e.g no leading zeros or plus signs) are accepted,
+    // so that each account has exactly one scoped encoding of a command.
+    (account.0.to_string() == index).then_some((account, command))
+}
+
+/// Returns the account of an identity authenticated request (if its command is account scoped).
+pub fn request_account(request: &IdentityAuthedRequestPayload) -> Option<AccountId> {
+    parse_scoped_command(&request.command).map(|(account, _)| account)
+}
+
+/// Quorum policies scoped per account (e.g a treasury account that requires all parties
+/// while other accounts only require a signing quorum).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AccountPolicies {
+    /// The quorum policy for accounts without an override and commands that aren't account scoped.
+    default: QuorumPolicy,
+    /// Quorum policy overrides per account.
+    overrides: BTreeMap<AccountId, QuorumPolicy>,
+}
+
+impl AccountPolicies {
+    /// Returns account policies with the given default quorum policy and no overrides.
+    pub fn new(default: QuorumPolicy) -> Self {
+        Self {
+            default,
+            overrides: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the quorum policy for the account.
+    pub fn with_policy(mut self, account: AccountId, policy: QuorumPolicy) -> Self {
+        self.overrides.insert(account, policy);
+        self
+    }
+
+    /// Returns the quorum policy for the account.
+    pub fn policy(&self, account: &AccountId) -> QuorumPolicy {
+        self.overrides.get(account).copied().unwrap_or(self.default)
+    }
+
+    /// Returns the quorum policy for an identity authenticated request
+    /// (i.e the default policy for commands that aren't account scoped).
+    pub fn policy_for_request(&self, request: &IdentityAuthedRequestPayload) -> QuorumPolicy {
+        request_account(request).map_or(self.default, |account| self.policy(&account))
+    }
+}
+
+impl CommandLog {
+    /// Returns the command log entries for the account in the order they were appended.
+    ///
+    /// **NOTE:** The whole command log must still be verified (see [`CommandLog::verify`]),
+    /// because entries for an account are only tamper-evident as part of the full chain.
+    pub fn entries_for_account(
+        &self,
+        account: AccountId,
+    ) -> impl Iterator<Item = &CommandLogEntry> {
+        self.entries()
+            .iter()
+            .filter(move |entry| request_account(&entry.request) == Some(account))
+    }
+}
+
+impl Inbox {
+    /// Returns the pending entries for the wallet and account in the order they were received.
+    pub fn entries_for_account<'a>(
+        &'a self,
+        wallet_id: &'a [u8; 32],
+        account: AccountId,
+    ) -> impl Iterator<Item = &'a InboxEntry> {
+        self.entries_for_wallet(wallet_id)
+            .filter(move |entry| request_account(entry.item.request()) == Some(account))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_log::CommandOutcome;
+    use crate::inbox::InboxItem;
+    use crate::test_utils::MockECDSAIdentityProvider;
+
+    #[test]
+    fn account_scoping_works() {
+        // Verifies command scoping.
+        let account = AccountId::new(7).unwrap();
+        let command = account.scope_command("sign");
+        assert_eq!(command, "account/7/sign");
+        assert_eq!(parse_scoped_command(&command), Some((account, "sign")));
+        assert_eq!(parse_scoped_command("sign"), None);
+        assert_eq!(parse_scoped_command("account/07/sign"), None);
+        assert_eq!(parse_scoped_command("account/2147483648/sign"), None);
+        assert_eq!(
+            AccountId::new(HARDENED_OFFSET),
+            Err(Bip32Error::HardenedIndex)
+        );
+
+        // Verifies storage key namespacing.
+        let wallet_id = [1u8; 32];
+        assert_ne!(
+            account.storage_key(&wallet_id, "sub-share"),
+            AccountId::default().storage_key(&wallet_id, "sub-share")
+        );
+
+        // Verifies account derivation.
+        let group_key = ExtendedPublicKey::new(
+            &utils::from_hex("0339a36013301597daef41fbe593a02cc513d0b55527ec2df1050e2e8ff49c85c2")
+                .unwrap(),
+            [0u8; 32],
+        )
+        .unwrap();
+        let (account_key, tweak) = account.derive(&group_key).unwrap();
+        assert_eq!(
+            group_key.derive_path(&account.derivation_path()).unwrap(),
+            (account_key, tweak)
+        );
+
+        // Verifies per account policies.
+        let treasury = AccountId::new(1).unwrap();
+        let policies = AccountPolicies::new(QuorumPolicy::SigningQuorum)
+            .with_policy(treasury, QuorumPolicy::AllParties);
+        let identity_provider = MockECDSAIdentityProvider::generate();
+        let treasury_request = treasury.initiate_request("sign", &identity_provider);
+        let wallet_request =
+            identity_authed_request::initiate("rotate-identity", &identity_provider);
+        assert_eq!(policies.policy(&treasury), QuorumPolicy::AllParties);
+        assert_eq!(policies.policy(&account), QuorumPolicy::SigningQuorum);
+        assert_eq!(
+            policies.policy_for_request(&treasury_request),
+            QuorumPolicy::AllParties
+        );
+        assert_eq!(
+            policies.policy_for_request(&wallet_request),
+            QuorumPolicy::SigningQuorum
+        );
+
+        // Verifies per account command logs and inboxes.
+        let mut command_log = CommandLog::new();
+        command_log.append(
+            treasury_request.clone(),
+            Vec::new(),
+            CommandOutcome::Succeeded,
+        );
+        command_log.append(
+            wallet_request.clone(),
+            Vec::new(),
+            CommandOutcome::Succeeded,
+        );
+        assert_eq!(command_log.entries_for_account(treasury).count(), 1);
+        assert_eq!(command_log.entries_for_account(account).count(), 0);
+        let mut inbox = Inbox::new();
+        inbox.push(wallet_id, InboxItem::Request(treasury_request));
+        inbox.push(wallet_id, InboxItem::Request(wallet_request));
+        assert_eq!(inbox.entries_for_account(&wallet_id, treasury).count(), 1);
+        assert_eq!(inbox.entries_for_account(&[2u8; 32], treasury).count(), 0);
+    }
+}
@@ -0,0 +1,327 @@
+//! Blockchain address derivation for a threshold (i.e group) `Secp256k1` public key.
+//!
+//! Applications should display deposit addresses derived with these helpers
+//! (rather than reimplementing hashing and encoding for each chain),
+//! because a mistake in address derivation sends funds to an address that no quorum can sign for.
+//!
+//! All helpers take the SEC1 encoded (i.e compressed or uncompressed) group public key produced by key generation
+//! (or a child key derived from it, see [`crate::bip32`]).
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use k256::elliptic_curve::point::AffineCoordinates;
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use k256::elliptic_curve::PrimeField;
+use k256::{AffinePoint, ProjectivePoint, PublicKey, Scalar};
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+use sha3::Keccak256;
+
+use crate::errors::AddressError;
+use crate::utils;
+
+/// A Bitcoin network (i.e determines the bech32 human-readable part of segwit addresses).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BitcoinNetwork {
+    #[default]
+    Mainnet,
+    /// Testnet and signet.
+    Testnet,
+    Regtest,
+}
+
+impl BitcoinNetwork {
+    /// Returns the bech32 human-readable part for segwit addresses on the network.
+    pub fn hrp(&self) -> &'static str {
+        match self {
+            Self::Mainnet => "bc",
+            Self::Testnet => "tb",
+            Self::Regtest => "bcrt",
+        }
+    }
+}
+
+/// Returns the EIP-55 checksummed Ethereum address for a SEC1 encoded public key.
+///
+/// Ref: <https://eips.ethereum.org/EIPS/eip-55>.
+pub fn ethereum_address(public_key: &[u8]) -> Result<String, AddressError> {
+    let address = utils::to_hex(&ethereum_address_bytes(public_key)?);
+    // Hex letters are uppercase iff the corresponding nibble of the keccak256 hash of the lowercase hex address is >= 8.
+    let checksum = Keccak256::digest(address.as_bytes());
+    Ok(format!(
+        "0x{}",
+        address
+            .chars()
+            .enumerate()
+            .map(|(idx, char)| {
+                let nibble = (checksum[idx / 2] >> (4 * (1 - idx % 2))) & 0x0f;
+                if nibble >= 8 {
+                    char.to_ascii_uppercase()
+                } else {
+                    char
+                }
+            })
+            .collect::<String>()
+    ))
+}
+
+/// Returns the raw (i.e 20 byte) Ethereum address for a SEC1 encoded public key.
+pub fn ethereum_address_bytes(public_key: &[u8]) -> Result<[u8; 20], AddressError> {
+    let public_key = parse_public_key(public_key)?;
+    // The address is the last 20 bytes of the keccak256 hash of the uncompressed public key (without the 0x04 tag).
+    let hash = Keccak256::digest(&public_key.to_encoded_point(false).as_bytes()[1..]);
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    Ok(address)
+}
+
+/// Returns the Bitcoin native segwit v0 pay-to-witness-public-key-hash (P2WPKH) address for a SEC1 encoded public key.
+///
+/// Ref: <https://github.com/bitcoin/bips/blob/master/bip-0141.mediawiki#p2wpkh>.
+pub fn bitcoin_p2wpkh_address(
+    public_key: &[u8],
+    network: BitcoinNetwork,
+) -> Result<String, AddressError> {
+    let public_key = parse_public_key(public_key)?;
+    segwit_address(network.hrp(), 0, &hash160(&public_key))
+}
+
+/// Returns the Bitcoin segwit v1 pay-to-taproot (P2TR) address for a SEC1 encoded internal public key
+/// and an optional script tree merkle root (i.e `None` for key path only spending).
+///
+/// **NOTE:** Signing for the address requires applying the returned taproot tweak at signing time
+/// (i.e the output key is `P + t * G` where `P` is the internal key with an even `y` coordinate).
+///
+/// Ref: <https://github.com/bitcoin/bips/blob/master/bip-0341.mediawiki>.
+pub fn bitcoin_p2tr_address(
+    public_key: &[u8],
+    merkle_root: Option<[u8; 32]>,
+    network: BitcoinNetwork,
+) -> Result<(String, [u8; 32]), AddressError> {
+    let public_key = parse_public_key(public_key)?;
+    // Lifts the internal key to the point with the same `x` coordinate and an even `y` coordinate.
+    let internal_key = if public_key.as_affine().y_is_odd().into() {
+        -*public_key.as_affine()
+    } else {
+        *public_key.as_affine()
+    };
+    let internal_key_x = internal_key.x();
+    let mut tweak_preimage = internal_key_x.to_vec();
+    if let Some(merkle_root) = merkle_root {
+        tweak_preimage.extend_from_slice(&merkle_root);
+    }
+    let tweak_bytes = tagged_hash(b"TapTweak", &tweak_preimage);
+    let tweak = Option::<Scalar>::from(Scalar::from_repr(tweak_bytes.into()))
+        .ok_or(AddressError::InvalidTweak)?;
+    let output_key =
+        AffinePoint::from(ProjectivePoint::from(internal_key) + ProjectivePoint::GENERATOR * tweak);
+    if output_key == AffinePoint::IDENTITY {
+        return Err(AddressError::InvalidTweak);
+    }
+    Ok((
+        segwit_address(network.hrp(), 1, &output_key.x())?,
+        tweak_bytes,
+    ))
+}
+
+/// Returns the Cosmos SDK bech32 account address for a SEC1 encoded public key and a human-readable part
+/// (e.g `cosmos` for the Cosmos Hub or `osmo` for Osmosis).
+///
+/// Ref: <https://docs.cosmos.network/main/learn/beginner/accounts#addresses>.
+pub fn cosmos_address(public_key: &[u8], hrp: &str) -> Result<String, AddressError> {
+    let public_key = parse_public_key(public_key)?;
+    bech32_encode(
+        hrp,
+        &convert_bits(&hash160(&public_key)),
+        Bech32Variant::Bech32,
+    )
+}
+
+/// Parses a SEC1 encoded `Secp256k1` public key.
+fn parse_public_key(public_key: &[u8]) -> Result<PublicKey, AddressError> {
+    PublicKey::from_sec1_bytes(public_key).map_err(|_| AddressError::InvalidPublicKey)
+}
+
+/// Returns `RIPEMD160(SHA256(compressed public key))`.
+fn hash160(public_key: &PublicKey) -> [u8; 20] {
+    Ripemd160::digest(Sha256::digest(public_key.to_encoded_point(true).as_bytes())).into()
+}
+
+/// Returns the BIP-340 tagged hash of the message (i.e `SHA256(SHA256(tag) || SHA256(tag) || message)`).
+fn tagged_hash(tag: &[u8], message: &[u8]) -> [u8; 32] {
+    let tag_hash = Sha256::digest(tag);
+    Sha256::new()
+        .chain_update(tag_hash)
+        .chain_update(tag_hash)
+        .chain_update(message)
+        .finalize()
+        .into()
+}
+
+/// Returns the segwit address for a witness version and program
+/// (i.e bech32 for version 0 and bech32m for later versions).
+///
+/// Ref: <https://github.com/bitcoin/bips/blob/master/bip-0350.mediawiki>.
+fn segwit_address(hrp: &str, version: u8, program: &[u8]) -> Result<String, AddressError> {
+    let mut data = vec![version];
+    data.extend(convert_bits(program));
+    let variant = if version == 0 {
+        Bech32Variant::Bech32
+    } else {
+        Bech32Variant::Bech32m
+    };
+    bech32_encode(hrp, &data, variant)
+}
+
+/// The bech32 alphabet.
+const BECH32_ALPHABET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// A bech32 checksum variant.
+///
+/// Ref: <https://github.com/bitcoin/bips/blob/master/bip-0350.mediawiki#bech32m>.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bech32Variant {
+    Bech32,
+    Bech32m,
+}
+
+/// Returns the bech32 (or bech32m) encoding of 5 bit values with the human-readable part.
+///
+/// Ref: <https://github.com/bitcoin/bips/blob/master/bip-0173.mediawiki#bech32>.
+fn bech32_encode(hrp: &str, data: &[u8], variant: Bech32Variant) -> Result<String, AddressError> {
+    // The human-readable part must be 1 to 83 lowercase US-ASCII characters in the range [33, 126].
+    if hrp.is_empty()
+        || hrp.len() > 83
+        || !hrp
+            .bytes()
+            .all(|char| (33..=126).contains(&char) && !char.is_ascii_uppercase())
+    {
+        return Err(AddressError::InvalidPrefix);
+    }
+    let mut values: Vec<u8> = hrp.bytes().map(|char| char >> 5).collect();
+    values.push(0);
+    values.extend(hrp.bytes().map(|char| char & 31));
+    values.extend_from_slice(data);
+    values.extend([0; 6]);
+    let checksum = bech32_polymod(&values)
+        ^ match variant {
+            Bech32Variant::Bech32 => 1,
+            Bech32Variant::Bech32m => 0x2bc830a3,
+        };
+    Ok(format!(
+        "{hrp}1{}",
+        data.iter()
+            .copied()
+            .chain((0..6).map(|idx| ((checksum >> (5 * (5 - idx))) & 31) as u8))
+            .map(|value| BECH32_ALPHABET[value as usize] as char)
+            .collect::<String>()
+    ))
+}
+
+/// Returns the bech32 checksum polynomial modulus of the 5 bit values.
+fn bech32_polymod(values: &[u8]) -> u32 {
+    const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    values.iter().fold(1, |checksum, value| {
+        let top = checksum >> 25;
+        GENERATOR
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| (top >> idx) & 1 == 1)
+            .fold(
+                ((checksum & 0x1ffffff) << 5) ^ *value as u32,
+                |acc, (_, gen)| acc ^ gen,
+            )
+    })
+}
+
+/// Regroups bytes into (zero padded) 5 bit values.
+fn convert_bits(bytes: &[u8]) -> Vec<u8> {
+    let mut values = Vec::new();
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    for byte in bytes {
+        acc = (acc << 8) | *byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            values.push(((acc >> bits) & 31) as u8);
+        }
+    }
+    if bits > 0 {
+        values.push(((acc << (5 - bits)) & 31) as u8);
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn address_derivation_works() {
+        // The public key for the private key 1 (i.e the generator point).
+        let public_key =
+            utils::from_hex("0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+                .unwrap();
+        let uncompressed_public_key = utils::from_hex("0479be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8").unwrap();
+
+        // Verifies Ethereum address derivation (for both SEC1 encodings).
+        for key in [&public_key, &uncompressed_public_key] {
+            assert_eq!(
+                ethereum_address(key).unwrap(),
+                "0x7E5F4552091A69125d5DfCb7b8C2659029395Bdf"
+            );
+        }
+
+        // Verifies Bitcoin P2WPKH address derivation (BIP-173 test vector).
+        assert_eq!(
+            bitcoin_p2wpkh_address(&public_key, BitcoinNetwork::Mainnet).unwrap(),
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"
+        );
+        assert!(bitcoin_p2wpkh_address(&public_key, BitcoinNetwork::Testnet)
+            .unwrap()
+            .starts_with("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7k"));
+
+        // Verifies Bitcoin P2TR address derivation (BIP-86 test vector).
+        let internal_key =
+            utils::from_hex("02cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115")
+                .unwrap();
+        let (address, _) =
+            bitcoin_p2tr_address(&internal_key, None, BitcoinNetwork::Mainnet).unwrap();
+        assert_eq!(
+            address,
+            "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr"
+        );
+        // The parity of the internal key doesn't change the address.
+        let mut odd_internal_key = internal_key.clone();
+        odd_internal_key[0] = 0x03;
+        assert_eq!(
+            bitcoin_p2tr_address(&odd_internal_key, None, BitcoinNetwork::Mainnet)
+                .unwrap()
+                .0,
+            address
+        );
+
+        // Verifies Cosmos address derivation (i.e same key hash as P2WPKH, but without a witness version).
+        let address = cosmos_address(&public_key, "cosmos").unwrap();
+        assert!(address.starts_with("cosmos1w508d6qejxtdg4y5r3zarvary0c5xw7k"));
+        assert_eq!(address.len(), "cosmos1".len() + 32 + 6);
+
+        // Verifies invalid input.
+        assert_eq!(
+            ethereum_address(&[0u8; 33]),
+            Err(AddressError::InvalidPublicKey)
+        );
+        assert_eq!(
+            cosmos_address(&public_key, "Cosmos"),
+            Err(AddressError::InvalidPrefix)
+        );
+        assert_eq!(
+            cosmos_address(&public_key, ""),
+            Err(AddressError::InvalidPrefix)
+        );
+    }
+}
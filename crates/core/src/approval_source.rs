@@ -0,0 +1,239 @@
+//! Pluggable sources of approvals for quorum approved requests (e.g off-chain command approvals and on-chain governance votes).
+//!
+//! Allows DAOs to approve wallet commands through their existing on-chain governance (i.e a smart contract that records approvals),
+//! with on-chain approvals merged with off-chain command approvals (see [`verify_merged_approvals`]).
+//!
+//! **NOTE:** On-chain approvals are authenticated by the transaction signature of the approving party (i.e the sender of the approval),
+//! so they don't contribute challenge fragments to the identity challenge of the initiating party.
+//! Callers are responsible for fetching (and verifying proofs for) on-chain approval records from a trusted RPC provider or light client.
+
+use alloc::vec::Vec;
+use sha3::{Digest, Keccak256};
+
+use crate::address;
+use crate::cbor::CanonicalCbor;
+use crate::crypto::VerifyingKey;
+use crate::errors::QuorumApprovedRequestError;
+use crate::payloads::{CommandApprovalPayload, IdentityAuthedRequestPayload};
+use crate::quorum_approved_request;
+
+/// A source of approvals for quorum approved requests.
+pub trait QuorumApprovalSource {
+    /// Given a quorum approved request initialization payload and a list of verifying keys for the other parties,
+    /// returns the verifying keys of the parties with valid approvals for the request.
+    fn approvers(
+        &self,
+        request: &IdentityAuthedRequestPayload,
+        verified_parties: &[VerifyingKey],
+    ) -> Vec<VerifyingKey>;
+}
+
+/// Off-chain command approvals (i.e the approving parties of valid command approvals).
+impl QuorumApprovalSource for Vec<CommandApprovalPayload> {
+    fn approvers(
+        &self,
+        request: &IdentityAuthedRequestPayload,
+        verified_parties: &[VerifyingKey],
+    ) -> Vec<VerifyingKey> {
+        quorum_approved_request::filter_valid_approvals(self, request, verified_parties)
+            .iter()
+            .map(|approval| quorum_approved_request::approver(approval).clone())
+            .collect()
+    }
+}
+
+/// An approval recorded on a smart contract (e.g decoded from an event log or an `eth_call` response).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OnChainApproval {
+    /// The chain identifier (e.g `1` for Ethereum mainnet).
+    pub chain_id: u64,
+    /// The address of the approval contract.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub contract: [u8; 20],
+    /// The hash of the approved request (see [`request_hash`]).
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub request_hash: [u8; 32],
+    /// The address of the approving party.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub approver: [u8; 20],
+    /// The number of the block that includes the approval.
+    pub block_number: u64,
+}
+
+/// On-chain approvals recorded on an approval contract.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OnChainApprovalSource {
+    /// The chain identifier.
+    chain_id: u64,
+    /// The address of the approval contract.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    contract: [u8; 20],
+    /// The number of the latest finalized block (i.e approvals in later blocks are ignored).
+    finalized_block: u64,
+    /// On-chain approval records.
+    approvals: Vec<OnChainApproval>,
+}
+
+impl OnChainApprovalSource {
+    /// Given a chain identifier, the address of the approval contract and the number of the latest finalized block,
+    /// returns an on-chain approval source with no approval records.
+    pub fn new(chain_id: u64, contract: [u8; 20], finalized_block: u64) -> Self {
+        Self {
+            chain_id,
+            contract,
+            finalized_block,
+            approvals: Vec::new(),
+        }
+    }
+
+    /// Adds an on-chain approval record (e.g from an RPC response).
+    pub fn with_approval(mut self, approval: OnChainApproval) -> Self {
+        self.approvals.push(approval);
+        self
+    }
+
+    /// Returns the on-chain approval records.
+    pub fn approvals(&self) -> &[OnChainApproval] {
+        &self.approvals
+    }
+}
+
+impl QuorumApprovalSource for OnChainApprovalSource {
+    /// Returns the verifying keys of the parties whose Ethereum address (see [`address::ethereum_address_bytes`])
+    /// approved the request on the approval contract in a finalized block.
+    fn approvers(
+        &self,
+        request: &IdentityAuthedRequestPayload,
+        verified_parties: &[VerifyingKey],
+    ) -> Vec<VerifyingKey> {
+        let request_hash = request_hash(request);
+        verified_parties
+            .iter()
+            .filter(|party| {
+                address::ethereum_address_bytes(&party.key).is_ok_and(|address| {
+                    self.approvals.iter().any(|approval| {
+                        approval.chain_id == self.chain_id
+                            && approval.contract == self.contract
+                            && approval.block_number <= self.finalized_block
+                            && approval.request_hash == request_hash
+                            && approval.approver == address
+                    })
+                })
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+/// Returns the hash of a quorum approved request initialization payload that's recorded by approval contracts
+/// (i.e the keccak256 hash of its canonical CBOR encoding).
+pub fn request_hash(request: &IdentityAuthedRequestPayload) -> [u8; 32] {
+    Keccak256::digest(request.to_cbor()).into()
+}
+
+/// Given a list of approval sources, a quorum approved request initialization payload,
+/// a quorum size and a list of verifying keys for the other parties,
+/// returns an ok result with the (deduplicated) verifying keys of the approving parties from all sources
+/// if they form a quorum or an appropriate error result otherwise.
+pub fn verify_merged_approvals(
+    sources: &[&dyn QuorumApprovalSource],
+    request: &IdentityAuthedRequestPayload,
+    quorum_size: usize,
+    verified_parties: &[VerifyingKey],
+) -> Result<Vec<VerifyingKey>, QuorumApprovedRequestError> {
+    let approvers = sources
+        .iter()
+        .flat_map(|source| source.approvers(request, verified_parties))
+        .fold(Vec::new(), |mut acc, approver| {
+            // Only one approval per approving party is counted.
+            if !acc.contains(&approver) {
+                acc.push(approver);
+            }
+            acc
+        });
+    // quorum_size - 1 because of implicit approval from initiator.
+    if approvers.len() < quorum_size.saturating_sub(1) {
+        Err(QuorumApprovedRequestError::InsufficientApprovals)
+    } else {
+        Ok(approvers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockECDSAIdentityProvider;
+    use crate::IdentityProvider;
+    use alloc::vec;
+
+    #[test]
+    fn approval_source_works() {
+        // Generates identity providers and a roster.
+        let initiator = MockECDSAIdentityProvider::generate();
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (0..3)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let verified_parties: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+
+        // Initiates a quorum approved request that's approved off-chain by the first party.
+        let command = "command";
+        let request = quorum_approved_request::initiate(command, &initiator);
+        let approvals = vec![
+            quorum_approved_request::verify_request_and_initiate_challenge(
+                command,
+                &request,
+                &identity_providers[0],
+                &[initiator.verifying_key()],
+            )
+            .unwrap(),
+        ];
+        assert_eq!(
+            approvals.approvers(&request, &verified_parties),
+            vec![verified_parties[0].clone()]
+        );
+
+        // Records on-chain approvals (including duplicate, non-finalized and foreign contract approvals).
+        let chain_id = 1;
+        let contract = [7u8; 20];
+        let on_chain_approval = |party: &VerifyingKey, block_number: u64| OnChainApproval {
+            chain_id,
+            contract,
+            request_hash: request_hash(&request),
+            approver: address::ethereum_address_bytes(&party.key).unwrap(),
+            block_number,
+        };
+        let source = OnChainApprovalSource::new(chain_id, contract, 100)
+            .with_approval(on_chain_approval(&verified_parties[0], 90))
+            .with_approval(on_chain_approval(&verified_parties[1], 95))
+            .with_approval(on_chain_approval(&verified_parties[2], 101))
+            .with_approval(OnChainApproval {
+                contract: [8u8; 20],
+                ..on_chain_approval(&verified_parties[2], 90)
+            });
+        assert_eq!(
+            source.approvers(&request, &verified_parties),
+            verified_parties[..2].to_vec()
+        );
+
+        // Merged approvals are deduplicated.
+        assert_eq!(
+            verify_merged_approvals(&[&approvals, &source], &request, 3, &verified_parties),
+            Ok(verified_parties[..2].to_vec())
+        );
+        assert_eq!(
+            verify_merged_approvals(&[&approvals, &source], &request, 4, &verified_parties),
+            Err(QuorumApprovedRequestError::InsufficientApprovals)
+        );
+
+        // On-chain approvals for other requests are ignored.
+        let other_request = quorum_approved_request::initiate("other-command", &initiator);
+        assert!(source
+            .approvers(&other_request, &verified_parties)
+            .is_empty());
+    }
+}
@@ -0,0 +1,417 @@
+//! An append-only, hash-chained and identity signed audit log of governance actions, signing sessions and recoveries.
+//!
+//! Each entry is signed by the decentralized identity of the recording party and chained to the previous entry,
+//! so that auditors can independently verify an exported audit log (see [`AuditLog::from_entries`] and [`AuditLog::verify_head`])
+//! e.g for compliance requirements of institutional custody.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use sha2::{Digest, Sha256};
+
+use crate::canonical::{AuditLogEntryMessage, CanonicalEncode};
+use crate::command_log::CommandOutcome;
+use crate::crypto;
+use crate::crypto::{Signature, VerifyingKey};
+use crate::errors::{AuditLogError, Error};
+use crate::traits::IdentityProvider;
+use crate::utils;
+
+/// An audited event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AuditEvent {
+    /// A governance action (i.e an identity authenticated or quorum approved command e.g a policy update or a share removal).
+    Governance {
+        /// The command.
+        command: String,
+        /// The verifying key of the initiating party.
+        initiator: VerifyingKey,
+        /// The outcome of the command.
+        outcome: CommandOutcome,
+    },
+    /// A signing session.
+    SigningSession {
+        /// The identifier of the session.
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+        session_id: [u8; 32],
+        /// The hash of the signed message.
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+        message_hash: [u8; 32],
+        /// The verifying keys of the participants.
+        participants: Vec<VerifyingKey>,
+        /// The outcome of the session.
+        outcome: CommandOutcome,
+    },
+    /// A share recovery (e.g from an encrypted share backup or a device migration).
+    Recovery {
+        /// The recovery method (e.g `"share-recovery-backup"` or `"device-migration"`).
+        method: String,
+        /// The verifying key of the recovering party.
+        party: VerifyingKey,
+        /// The outcome of the recovery.
+        outcome: CommandOutcome,
+    },
+}
+
+impl AuditEvent {
+    /// Returns the length prefixed encoding of the event for hashing.
+    fn hash_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Governance {
+                command,
+                initiator,
+                outcome,
+            } => [
+                [0].as_slice(),
+                &utils::length_prefix_bytes(command.as_bytes()),
+                &utils::length_prefix_bytes(&initiator.key),
+                &[*outcome as u8],
+            ]
+            .concat(),
+            Self::SigningSession {
+                session_id,
+                message_hash,
+                participants,
+                outcome,
+            } => {
+                let participant_bytes: Vec<u8> = participants
+                    .iter()
+                    .flat_map(|participant| utils::length_prefix_bytes(&participant.key))
+                    .collect();
+                [
+                    [1].as_slice(),
+                    session_id,
+                    message_hash,
+                    &(participants.len() as u64).to_be_bytes(),
+                    &participant_bytes,
+                    &[*outcome as u8],
+                ]
+                .concat()
+            }
+            Self::Recovery {
+                method,
+                party,
+                outcome,
+            } => [
+                [2].as_slice(),
+                &utils::length_prefix_bytes(method.as_bytes()),
+                &utils::length_prefix_bytes(&party.key),
+                &[*outcome as u8],
+            ]
+            .concat(),
+        }
+    }
+}
+
+/// An audit log entry (i.e an audited event signed by the recording party).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AuditLogEntry {
+    /// The sequence number of the entry in the audit log (starting at 1).
+    pub sequence: u64,
+    /// The hash of the audit log head before the entry.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub previous_hash: [u8; 32],
+    /// The identifier of the wallet.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub wallet_id: [u8; 32],
+    /// The UTC timestamp at which the event was recorded.
+    pub timestamp: u64,
+    /// The audited event.
+    pub event: AuditEvent,
+    /// The verifying key of the recording party.
+    pub verifying_key: VerifyingKey,
+    /// The signature of the recording party for the entry body.
+    pub signature: Signature,
+}
+
+impl AuditLogEntry {
+    /// Returns the hash of the entry body (i.e all fields except the signature).
+    pub fn body_hash(&self) -> [u8; 32] {
+        body_hash(
+            self.sequence,
+            &self.previous_hash,
+            &self.wallet_id,
+            self.timestamp,
+            &self.event,
+            &self.verifying_key,
+        )
+    }
+
+    /// Returns the hash of the entry (i.e the audit log head after the entry).
+    pub fn hash(&self) -> [u8; 32] {
+        Sha256::new()
+            .chain_update(self.body_hash())
+            .chain_update(utils::length_prefix_bytes(&self.signature.sig))
+            .finalize()
+            .into()
+    }
+
+    /// Given a list of verifying keys for the authorized recording parties,
+    /// returns an `Ok` result if the entry is signed by an authorized party, or an appropriate `Err` result otherwise.
+    pub fn verify_signature(&self, verified_parties: &[VerifyingKey]) -> Result<(), AuditLogError> {
+        if !verified_parties.contains(&self.verifying_key) {
+            return Err(AuditLogError::Unauthorized(Error::UnauthorizedParty));
+        }
+        Ok(crypto::verify_signature(
+            &self.verifying_key,
+            &AuditLogEntryMessage {
+                body_hash: &self.body_hash(),
+            }
+            .message_bytes(),
+            &self.signature,
+        )?)
+    }
+}
+
+/// An append-only, hash-chained and identity signed audit log.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AuditLog {
+    /// Chained audit log entries.
+    entries: Vec<AuditLogEntry>,
+}
+
+impl AuditLog {
+    /// Returns an empty audit log.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Given a list of audit log entries (e.g from an export) and a list of verifying keys for the authorized recording parties,
+    /// returns an `Ok` result with an audit log if all entries are signed by authorized parties and form a valid chain,
+    /// or an appropriate `Err` result otherwise.
+    ///
+    /// **NOTE:** Verifying keys for parties that were rotated out of the roster must be included
+    /// for entries recorded before the identity rotation (see [`crate::roster_log`]).
+    pub fn from_entries(
+        entries: Vec<AuditLogEntry>,
+        verified_parties: &[VerifyingKey],
+    ) -> Result<Self, AuditLogError> {
+        let audit_log = Self { entries };
+        audit_log.verify(verified_parties)?;
+        Ok(audit_log)
+    }
+
+    /// Returns the audit log entries.
+    pub fn entries(&self) -> &[AuditLogEntry] {
+        &self.entries
+    }
+
+    /// Returns the audit log entries for export (e.g to an auditor or an archive).
+    pub fn export(&self) -> Vec<AuditLogEntry> {
+        self.entries.clone()
+    }
+
+    /// Returns the number of entries in the audit log.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the audit log has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the hash of the audit log head.
+    pub fn head_hash(&self) -> [u8; 32] {
+        self.entries
+            .last()
+            .map_or_else(genesis_hash, AuditLogEntry::hash)
+    }
+
+    /// Given a wallet identifier, an audited event and the identity provider of the recording party,
+    /// appends a signed entry to the audit log and returns the new head hash.
+    pub fn append(
+        &mut self,
+        wallet_id: &[u8; 32],
+        event: AuditEvent,
+        identity_provider: &impl IdentityProvider,
+    ) -> [u8; 32] {
+        let sequence = self.entries.len() as u64 + 1;
+        let previous_hash = self.head_hash();
+        let timestamp = utils::unix_timestamp();
+        let verifying_key = identity_provider.verifying_key();
+        let signature = identity_provider.sign(
+            &AuditLogEntryMessage {
+                body_hash: &body_hash(
+                    sequence,
+                    &previous_hash,
+                    wallet_id,
+                    timestamp,
+                    &event,
+                    &verifying_key,
+                ),
+            }
+            .message_bytes(),
+        );
+        let entry = AuditLogEntry {
+            sequence,
+            previous_hash,
+            wallet_id: *wallet_id,
+            timestamp,
+            event,
+            verifying_key,
+            signature,
+        };
+        let head_hash = entry.hash();
+        self.entries.push(entry);
+        head_hash
+    }
+
+    /// Given a list of verifying keys for the authorized recording parties,
+    /// returns an `Ok` result if all entries are signed by authorized parties and form a valid chain,
+    /// or an appropriate `Err` result otherwise.
+    pub fn verify(&self, verified_parties: &[VerifyingKey]) -> Result<(), AuditLogError> {
+        self.entries
+            .iter()
+            .enumerate()
+            .try_fold(genesis_hash(), |previous_hash, (idx, entry)| {
+                // Each entry must extend the previous entry.
+                if entry.sequence != idx as u64 + 1 || entry.previous_hash != previous_hash {
+                    return Err(AuditLogError::InvalidChain);
+                }
+                // Each entry must be signed by an authorized party.
+                entry.verify_signature(verified_parties)?;
+                Ok(entry.hash())
+            })
+            .map(|_| ())
+    }
+
+    /// Given an expected head hash (e.g from a trusted checkpoint) and a list of verifying keys for the authorized recording parties,
+    /// returns an `Ok` result if all entries are signed by authorized parties and form a valid chain that ends at the expected head,
+    /// or an appropriate `Err` result otherwise.
+    pub fn verify_head(
+        &self,
+        expected_head_hash: &[u8; 32],
+        verified_parties: &[VerifyingKey],
+    ) -> Result<(), AuditLogError> {
+        self.verify(verified_parties)?;
+        if &self.head_hash() == expected_head_hash {
+            Ok(())
+        } else {
+            Err(AuditLogError::HeadMismatch)
+        }
+    }
+}
+
+/// Returns the hash of an audit log entry body (i.e all fields except the signature).
+fn body_hash(
+    sequence: u64,
+    previous_hash: &[u8; 32],
+    wallet_id: &[u8; 32],
+    timestamp: u64,
+    event: &AuditEvent,
+    verifying_key: &VerifyingKey,
+) -> [u8; 32] {
+    Sha256::new()
+        .chain_update(b"audit-log-entry")
+        .chain_update(sequence.to_be_bytes())
+        .chain_update(previous_hash)
+        .chain_update(wallet_id)
+        .chain_update(timestamp.to_be_bytes())
+        .chain_update(event.hash_bytes())
+        .chain_update(utils::length_prefix_bytes(&verifying_key.key))
+        .finalize()
+        .into()
+}
+
+/// Returns the hash of an empty audit log.
+fn genesis_hash() -> [u8; 32] {
+    Sha256::digest(b"audit-log-genesis").into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockECDSAIdentityProvider;
+    use alloc::string::ToString;
+
+    #[test]
+    fn audit_log_works() {
+        // Generates identity providers.
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (0..2)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let verified_parties: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let wallet_id = [1u8; 32];
+
+        // Records a governance action, a signing session and a recovery.
+        let mut audit_log = AuditLog::new();
+        let empty_head_hash = audit_log.head_hash();
+        audit_log.append(
+            &wallet_id,
+            AuditEvent::Governance {
+                command: "policy-update".to_string(),
+                initiator: verified_parties[0].clone(),
+                outcome: CommandOutcome::Succeeded,
+            },
+            &identity_providers[0],
+        );
+        audit_log.append(
+            &wallet_id,
+            AuditEvent::SigningSession {
+                session_id: [2u8; 32],
+                message_hash: [3u8; 32],
+                participants: verified_parties.clone(),
+                outcome: CommandOutcome::Failed,
+            },
+            &identity_providers[1],
+        );
+        let head_hash = audit_log.append(
+            &wallet_id,
+            AuditEvent::Recovery {
+                method: "share-recovery-backup".to_string(),
+                party: verified_parties[1].clone(),
+                outcome: CommandOutcome::Succeeded,
+            },
+            &identity_providers[1],
+        );
+        assert_eq!(audit_log.len(), 3);
+        assert_ne!(head_hash, empty_head_hash);
+        assert_eq!(audit_log.head_hash(), head_hash);
+        assert_eq!(audit_log.verify_head(&head_hash, &verified_parties), Ok(()));
+
+        // Auditors can verify an exported audit log.
+        let replayed_audit_log =
+            AuditLog::from_entries(audit_log.export(), &verified_parties).unwrap();
+        assert_eq!(replayed_audit_log, audit_log);
+
+        // Entries from unauthorized parties are rejected.
+        assert_eq!(
+            AuditLog::from_entries(audit_log.export(), &verified_parties[..1]),
+            Err(AuditLogError::Unauthorized(Error::UnauthorizedParty))
+        );
+
+        // Tampered entries are rejected.
+        let mut entries = audit_log.export();
+        entries[1].timestamp += 1;
+        assert!(matches!(
+            AuditLog::from_entries(entries, &verified_parties),
+            Err(AuditLogError::Unauthorized(_))
+        ));
+        let mut entries = audit_log.export();
+        entries[0].signature = entries[1].signature.clone();
+        assert!(matches!(
+            AuditLog::from_entries(entries, &verified_parties),
+            Err(AuditLogError::Unauthorized(_))
+        ));
+
+        // Removed entries break the chain.
+        assert_eq!(
+            AuditLog::from_entries(audit_log.export()[1..].to_vec(), &verified_parties),
+            Err(AuditLogError::InvalidChain)
+        );
+
+        // Truncated logs don't match the expected head.
+        let truncated_audit_log =
+            AuditLog::from_entries(audit_log.export()[..2].to_vec(), &verified_parties).unwrap();
+        assert_eq!(
+            truncated_audit_log.verify_head(&head_hash, &verified_parties),
+            Err(AuditLogError::HeadMismatch)
+        );
+    }
+}
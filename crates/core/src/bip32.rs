@@ -0,0 +1,340 @@
+//! Non-hardened [BIP-32](https://github.com/bitcoin/bips/blob/master/bip-0032.mediawiki) child key derivation
+//! over a threshold (i.e group) `Secp256k1` public key.
+//!
+//! Non-hardened child keys are additive tweaks of the parent key (i.e `K_child = K_parent + IL * G`),
+//! so signing with a child key only requires every participant to apply the same tweak at signing time
+//! (i.e no new key generation is required for each derived key, e.g per-deposit addresses).
+//!
+//! **NOTE:** Hardened derivation requires the group private key, so it's not supported.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use hmac::{Hmac, Mac};
+use k256::elliptic_curve::group::prime::PrimeCurveAffine;
+use k256::elliptic_curve::ops::Reduce;
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use k256::elliptic_curve::PrimeField;
+use k256::{ProjectivePoint, PublicKey, Scalar, U256};
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::errors::Bip32Error;
+use crate::utils;
+
+/// The BIP-32 mainnet extended public key version bytes (i.e `xpub`).
+pub const XPUB_VERSION: [u8; 4] = [0x04, 0x88, 0xB2, 0x1E];
+
+/// The first hardened child index.
+pub const HARDENED_OFFSET: u32 = 1 << 31;
+
+/// Chain code derivation tag for group public keys.
+const CHAIN_CODE_TAG: &[u8] = b"wamu/bip32/chain-code";
+
+/// A BIP-32 extended public key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtendedPublicKey {
+    public_key: PublicKey,
+    chain_code: [u8; 32],
+    depth: u8,
+    parent_fingerprint: [u8; 4],
+    child_number: u32,
+}
+
+impl ExtendedPublicKey {
+    /// Returns the master extended public key for a SEC1 encoded public key and chain code.
+    pub fn new(public_key: &[u8], chain_code: [u8; 32]) -> Result<Self, Bip32Error> {
+        Ok(Self {
+            public_key: PublicKey::from_sec1_bytes(public_key)
+                .map_err(|_| Bip32Error::InvalidPublicKey)?,
+            chain_code,
+            depth: 0,
+            parent_fingerprint: [0; 4],
+            child_number: 0,
+        })
+    }
+
+    /// Returns the master extended public key for a SEC1 encoded group public key,
+    /// with a chain code deterministically derived from the group public key
+    /// (i.e so that all parties derive the same child keys without any additional key generation output).
+    pub fn from_group_key(public_key: &[u8]) -> Result<Self, Bip32Error> {
+        let public_key =
+            PublicKey::from_sec1_bytes(public_key).map_err(|_| Bip32Error::InvalidPublicKey)?;
+        let chain_code = Sha256::new()
+            .chain_update(CHAIN_CODE_TAG)
+            .chain_update(public_key.to_encoded_point(true))
+            .finalize()
+            .into();
+        Ok(Self {
+            public_key,
+            chain_code,
+            depth: 0,
+            parent_fingerprint: [0; 4],
+            child_number: 0,
+        })
+    }
+
+    /// Returns the compressed SEC1 encoding of the public key.
+    pub fn public_key(&self) -> Vec<u8> {
+        self.public_key.to_encoded_point(true).as_bytes().to_vec()
+    }
+
+    /// Returns the chain code.
+    pub fn chain_code(&self) -> [u8; 32] {
+        self.chain_code
+    }
+
+    /// Returns the depth (i.e 0 for the master key).
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    /// Returns the fingerprint of the parent key (i.e zero for the master key).
+    pub fn parent_fingerprint(&self) -> [u8; 4] {
+        self.parent_fingerprint
+    }
+
+    /// Returns the child index (i.e zero for the master key).
+    pub fn child_number(&self) -> u32 {
+        self.child_number
+    }
+
+    /// Returns the fingerprint of the key (i.e the first 4 bytes of `RIPEMD160(SHA256(public key))`).
+    pub fn fingerprint(&self) -> [u8; 4] {
+        let hash = Ripemd160::digest(Sha256::digest(self.public_key()));
+        [hash[0], hash[1], hash[2], hash[3]]
+    }
+
+    /// Returns the non-hardened child extended public key for the index and the tweak (i.e `IL` as big-endian bytes)
+    /// that must be added (modulo the curve order) to the parent private key to get the child private key.
+    pub fn derive_child(&self, index: u32) -> Result<(Self, [u8; 32]), Bip32Error> {
+        let (child, tweak) = self.derive_child_scalar(index)?;
+        Ok((child, tweak.to_bytes().into()))
+    }
+
+    /// Returns the extended public key for a non-hardened derivation path (relative to this key)
+    /// and the aggregate tweak (i.e the sum of the tweaks of all the derivation steps as big-endian bytes)
+    /// that must be added (modulo the curve order) to the private key to get the derived private key.
+    pub fn derive_path(&self, path: &[u32]) -> Result<(Self, [u8; 32]), Bip32Error> {
+        let (child, tweak) =
+            path.iter()
+                .try_fold((self.clone(), Scalar::ZERO), |(key, tweak), index| {
+                    let (child, child_tweak) = key.derive_child_scalar(*index)?;
+                    Ok((child, tweak + child_tweak))
+                })?;
+        Ok((child, tweak.to_bytes().into()))
+    }
+
+    // Returns the non-hardened child extended public key for the index and the tweak (i.e `IL`).
+    fn derive_child_scalar(&self, index: u32) -> Result<(Self, Scalar), Bip32Error> {
+        if index >= HARDENED_OFFSET {
+            return Err(Bip32Error::HardenedIndex);
+        }
+        let depth = self
+            .depth
+            .checked_add(1)
+            .ok_or(Bip32Error::MaxDepthExceeded)?;
+
+        // I = HMAC-SHA512(Key = c_par, Data = ser_P(K_par) || ser_32(i)).
+        let mut mac = Hmac::<Sha512>::new_from_slice(&self.chain_code)
+            .expect("HMAC accepts keys of any length");
+        mac.update(&self.public_key());
+        mac.update(&index.to_be_bytes());
+        let output = mac.finalize().into_bytes();
+        let (tweak_bytes, chain_code) = output.split_at(32);
+
+        // IL must be less than the curve order, and the child key must not be the identity.
+        let tweak = Option::<Scalar>::from(Scalar::from_repr(
+            <[u8; 32]>::try_from(tweak_bytes)
+                .expect("Length is 32")
+                .into(),
+        ))
+        .ok_or(Bip32Error::InvalidChildKey)?;
+        let child_point = ProjectivePoint::GENERATOR * tweak + self.public_key.to_projective();
+        let public_key = PublicKey::from_affine(child_point.to_affine())
+            .map_err(|_| Bip32Error::InvalidChildKey)?;
+
+        Ok((
+            Self {
+                public_key,
+                chain_code: chain_code.try_into().expect("Length is 32"),
+                depth,
+                parent_fingerprint: self.fingerprint(),
+                child_number: index,
+            },
+            tweak,
+        ))
+    }
+
+    /// Returns the base58check encoding of the extended public key (i.e `xpub...`).
+    pub fn to_xpub(&self) -> String {
+        let mut bytes = Vec::with_capacity(82);
+        bytes.extend_from_slice(&XPUB_VERSION);
+        bytes.push(self.depth);
+        bytes.extend_from_slice(&self.parent_fingerprint);
+        bytes.extend_from_slice(&self.child_number.to_be_bytes());
+        bytes.extend_from_slice(&self.chain_code);
+        bytes.extend_from_slice(&self.public_key());
+        let checksum = checksum(&bytes);
+        bytes.extend_from_slice(&checksum);
+        utils::to_base58(&bytes)
+    }
+
+    /// Returns the extended public key for a base58check encoding (i.e `xpub...`), or an appropriate error otherwise.
+    pub fn from_xpub(xpub: &str) -> Result<Self, Bip32Error> {
+        let bytes = utils::from_base58(xpub)
+            .filter(|bytes| bytes.len() == 82)
+            .ok_or(Bip32Error::InvalidEncoding)?;
+        let (payload, checksum_bytes) = bytes.split_at(78);
+        if checksum(payload) != checksum_bytes || payload[..4] != XPUB_VERSION {
+            return Err(Bip32Error::InvalidEncoding);
+        }
+        let depth = payload[4];
+        let parent_fingerprint: [u8; 4] = payload[5..9].try_into().expect("Length is 4");
+        let child_number = u32::from_be_bytes(payload[9..13].try_into().expect("Length is 4"));
+        // The master key must have a zero parent fingerprint and child number.
+        if depth == 0 && (parent_fingerprint != [0; 4] || child_number != 0) {
+            return Err(Bip32Error::InvalidEncoding);
+        }
+        Ok(Self {
+            public_key: PublicKey::from_sec1_bytes(&payload[45..])
+                .map_err(|_| Bip32Error::InvalidPublicKey)?,
+            chain_code: payload[13..45].try_into().expect("Length is 32"),
+            depth,
+            parent_fingerprint,
+            child_number,
+        })
+    }
+}
+
+/// Returns the non-hardened child indices for a derivation path (e.g `m/0/1`), or an appropriate error otherwise.
+pub fn parse_path(path: &str) -> Result<Vec<u32>, Bip32Error> {
+    let mut segments = path.split('/');
+    if segments.next() != Some("m") {
+        return Err(Bip32Error::InvalidPath);
+    }
+    segments
+        .map(|segment| {
+            if segment.ends_with(['\'', 'h', 'H']) {
+                Err(Bip32Error::HardenedIndex)
+            } else {
+                match segment.parse::<u32>() {
+                    Ok(index) if index < HARDENED_OFFSET && !segment.starts_with('+') => Ok(index),
+                    Ok(_) => Err(Bip32Error::HardenedIndex),
+                    Err(_) => Err(Bip32Error::InvalidPath),
+                }
+            }
+        })
+        .collect()
+}
+
+/// Returns `true` if the SEC1 encoded child public key is the SEC1 encoded parent public key
+/// plus the tweak (i.e big-endian bytes reduced modulo the curve order) times the generator.
+pub fn verify_tweak(parent: &[u8], child: &[u8], tweak: &[u8; 32]) -> bool {
+    match (
+        PublicKey::from_sec1_bytes(parent),
+        PublicKey::from_sec1_bytes(child),
+    ) {
+        (Ok(parent), Ok(child)) => {
+            let tweak = <Scalar as Reduce<U256>>::reduce_bytes(&(*tweak).into());
+            let expected = parent.to_projective() + ProjectivePoint::GENERATOR * tweak;
+            bool::from(!expected.to_affine().is_identity())
+                && expected.to_affine() == *child.as_affine()
+        }
+        _ => false,
+    }
+}
+
+/// Returns the base58check checksum of the bytes (i.e the first 4 bytes of `SHA256(SHA256(bytes))`).
+fn checksum(bytes: &[u8]) -> [u8; 4] {
+    let hash = Sha256::digest(Sha256::digest(bytes));
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // BIP-32 test vector 2 (i.e `m` and `m/0`, the first derivation step is non-hardened).
+    // Ref: <https://github.com/bitcoin/bips/blob/master/bip-0032.mediawiki#test-vector-2>.
+    const MASTER_XPUB: &str = "xpub661MyMwAqRbcFW31YEwpkMuc5THy2PSt5bDMsktWQcFF8syAmRUapSCGu8ED9W6oDMSgv6Zz8idoc4a6mr8BDzTJY47LJhkJ8UB7WEGuduB";
+    const CHILD_XPUB: &str = "xpub69H7F5d8KSRgmmdJg2KhpAK8SR3DjMwAdkxj3ZuxV27CprR9LgpeyGmXUbC6wb7ERfvrnKZjXoUmmDznezpbZb7ap6r1D3tgFxHmwMkQTPH";
+    const MASTER_PRIVATE_KEY: &str =
+        "4b03d6fc340455b363f51020ad3ecca4f0850280cf436c70c727923f6db46c3e";
+    const CHILD_PRIVATE_KEY: &str =
+        "abe74a98f6c7eabee0428f53798f0ab8aa1bd37873999041703c742f15ac7e1e";
+
+    fn scalar_from_hex(hex: &str) -> Scalar {
+        let bytes: [u8; 32] = utils::from_hex(hex).unwrap().try_into().unwrap();
+        Option::from(Scalar::from_repr(bytes.into())).unwrap()
+    }
+
+    #[test]
+    fn child_key_derivation_works() {
+        // Verifies extended public key decoding and encoding.
+        let master = ExtendedPublicKey::from_xpub(MASTER_XPUB).unwrap();
+        assert_eq!(master.to_xpub(), MASTER_XPUB);
+        assert_eq!(master.depth(), 0);
+
+        // Verifies non-hardened child key derivation against the test vector.
+        let (child, tweak) = master.derive_path(&parse_path("m/0").unwrap()).unwrap();
+        assert_eq!(child.to_xpub(), CHILD_XPUB);
+        assert_eq!(child.depth(), 1);
+        assert_eq!(child.parent_fingerprint(), master.fingerprint());
+
+        // Verifies that the tweak is the difference between the child and master private keys.
+        assert_eq!(
+            scalar_from_hex(MASTER_PRIVATE_KEY) + scalar_from_hex(&utils::to_hex(&tweak)),
+            scalar_from_hex(CHILD_PRIVATE_KEY)
+        );
+        assert!(verify_tweak(
+            &master.public_key(),
+            &child.public_key(),
+            &tweak
+        ));
+
+        // Verifies that aggregate tweaks are sums of the tweaks of each derivation step.
+        let (grand_child, grand_child_tweak) = child.derive_child(7).unwrap();
+        let (path_grand_child, path_tweak) = master.derive_path(&[0, 7]).unwrap();
+        assert_eq!(grand_child, path_grand_child);
+        assert_eq!(
+            scalar_from_hex(&utils::to_hex(&tweak))
+                + scalar_from_hex(&utils::to_hex(&grand_child_tweak)),
+            scalar_from_hex(&utils::to_hex(&path_tweak))
+        );
+        assert!(verify_tweak(
+            &master.public_key(),
+            &grand_child.public_key(),
+            &path_tweak
+        ));
+    }
+
+    #[test]
+    fn invalid_input_fails() {
+        let master = ExtendedPublicKey::from_group_key(
+            (ProjectivePoint::GENERATOR * Scalar::from(42u64))
+                .to_affine()
+                .to_encoded_point(true)
+                .as_bytes(),
+        )
+        .unwrap();
+
+        // Hardened derivation is rejected.
+        assert_eq!(
+            master.derive_child(HARDENED_OFFSET),
+            Err(Bip32Error::HardenedIndex)
+        );
+        assert_eq!(parse_path("m/44'/0"), Err(Bip32Error::HardenedIndex));
+        assert_eq!(parse_path("m/2147483648"), Err(Bip32Error::HardenedIndex));
+
+        // Malformed paths and encodings are rejected.
+        assert_eq!(parse_path("0/1"), Err(Bip32Error::InvalidPath));
+        assert_eq!(parse_path("m/a"), Err(Bip32Error::InvalidPath));
+        assert_eq!(parse_path("m"), Ok(Vec::new()));
+        let mut xpub = master.to_xpub();
+        xpub.pop();
+        assert_eq!(
+            ExtendedPublicKey::from_xpub(&xpub),
+            Err(Bip32Error::InvalidEncoding)
+        );
+    }
+}
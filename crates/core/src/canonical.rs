@@ -0,0 +1,712 @@
+//! Canonical encodings of everything a decentralized identity signs.
+//!
+//! Specifies the exact signed bytes in one place, so that independent implementations produce identical signatures.
+//!
+//! **NOTE:** Sign-able message bytes are the canonical bytes prefixed with [`utils::WAMU_MESSAGE_PREFIX`].
+
+use crate::cbor::CanonicalCbor;
+use crate::crypto::{Random32Bytes, VerifyingKey};
+use crate::payloads::{
+    ApprovalDelegationPayload, IdentityAuthedRequestPayload, RotationCertificate, SessionTerms,
+};
+use crate::utils;
+use alloc::borrow::ToOwned;
+use alloc::format;
+use alloc::vec::Vec;
+use sha2::{Digest, Sha256};
+
+/// A type with a canonical encoding of the bytes a decentralized identity signs.
+pub trait CanonicalEncode {
+    /// Returns the canonical (i.e unprefixed) bytes.
+    fn canonical_bytes(&self) -> Vec<u8>;
+
+    /// Returns the sign-able message bytes (i.e the canonical bytes prefixed with the Wamu message prefix).
+    fn message_bytes(&self) -> Vec<u8> {
+        utils::prefix_message_bytes(&self.canonical_bytes())
+    }
+}
+
+/// Raw bytes (e.g random bytes for identity authentication of augmented protocol messages) are signed as is.
+impl CanonicalEncode for [u8] {
+    fn canonical_bytes(&self) -> Vec<u8> {
+        self.to_vec()
+    }
+}
+
+/// An identity authenticated request command (i.e `command || decimal(timestamp)`).
+#[derive(Debug, Clone, Copy)]
+pub struct CommandMessage<'a> {
+    /// The command to execute.
+    pub command: &'a str,
+    /// The UTC timestamp at which the request was initiated.
+    pub timestamp: u64,
+}
+
+impl CanonicalEncode for CommandMessage<'_> {
+    fn canonical_bytes(&self) -> Vec<u8> {
+        format!("{}{}", self.command, self.timestamp).into_bytes()
+    }
+}
+
+/// An identity authenticated request command in monotonic counter mode (i.e `"counter-command" || lp(command) || be64(counter)`).
+#[derive(Debug, Clone, Copy)]
+pub struct CounterCommandMessage<'a> {
+    /// The command to execute.
+    pub command: &'a str,
+    /// The request counter of the initiating party.
+    pub counter: u64,
+}
+
+impl CanonicalEncode for CounterCommandMessage<'_> {
+    fn canonical_bytes(&self) -> Vec<u8> {
+        [
+            b"counter-command".as_slice(),
+            &utils::length_prefix_bytes(self.command.as_bytes()),
+            &self.counter.to_be_bytes(),
+        ]
+        .concat()
+    }
+}
+
+/// An identity challenge (i.e the concatenation of the big-endian bytes of the sorted challenge fragments).
+#[derive(Debug, Clone, Copy)]
+pub struct ChallengeMessage<'a> {
+    /// The identity challenge fragments (in any order).
+    pub challenge_fragments: &'a [Random32Bytes],
+}
+
+impl CanonicalEncode for ChallengeMessage<'_> {
+    fn canonical_bytes(&self) -> Vec<u8> {
+        // Sort the challenge fragments so that we always get the same challenge regardless of order of receiving challenges.
+        let mut sorted_challenge_fragments = self.challenge_fragments.to_owned();
+        sorted_challenge_fragments.sort();
+        sorted_challenge_fragments
+            .iter()
+            .flat_map(Random32Bytes::to_be_bytes)
+            .collect()
+    }
+}
+
+/// A command approval (i.e `decimal(challenge_fragment) || command || decimal(timestamp)`),
+/// or a delegated command approval (i.e `"delegated-approval" || be256(challenge_fragment) || lp(command) || be64(timestamp) || delegation_hash`,
+/// where `lp` is the big-endian `u64` length prefixed bytes and `delegation_hash` is the SHA-256 hash of the canonical CBOR encoding of the delegation),
+/// or a command approval for a request in monotonic counter mode
+/// (i.e `"counter-approval" || be256(challenge_fragment) || lp(command) || be64(counter) || [delegation_hash]`).
+#[derive(Debug, Clone, Copy)]
+pub struct CommandApprovalMessage<'a> {
+    /// The identity challenge fragment from the approving party.
+    pub challenge_fragment: &'a Random32Bytes,
+    /// The approved command.
+    pub command: &'a str,
+    /// The UTC timestamp at which the approved request was initiated.
+    pub timestamp: u64,
+    /// The request counter of the approved request (if it was initiated in monotonic counter mode).
+    pub counter: Option<u64>,
+    /// The delegation authorizing the approving party to approve on behalf of another party (if any).
+    pub delegation: Option<&'a ApprovalDelegationPayload>,
+}
+
+impl CanonicalEncode for CommandApprovalMessage<'_> {
+    fn canonical_bytes(&self) -> Vec<u8> {
+        if let Some(counter) = self.counter {
+            // Binds the approval to the request counter (i.e it can't be re-attached to another request for the same command).
+            return [
+                b"counter-approval".as_slice(),
+                &self.challenge_fragment.to_be_bytes(),
+                &utils::length_prefix_bytes(self.command.as_bytes()),
+                &counter.to_be_bytes(),
+                &self
+                    .delegation
+                    .map(|delegation| Sha256::digest(delegation.to_cbor()).to_vec())
+                    .unwrap_or_default(),
+            ]
+            .concat();
+        }
+        match self.delegation {
+            None => format!(
+                "{}{}{}",
+                self.challenge_fragment, self.command, self.timestamp
+            )
+            .into_bytes(),
+            // Binds the approval to the delegation (i.e it can't be re-attached to another delegation).
+            Some(delegation) => [
+                b"delegated-approval".as_slice(),
+                &self.challenge_fragment.to_be_bytes(),
+                &utils::length_prefix_bytes(self.command.as_bytes()),
+                &self.timestamp.to_be_bytes(),
+                &Sha256::digest(delegation.to_cbor()),
+            ]
+            .concat(),
+        }
+    }
+}
+
+/// An approval delegation (i.e `"approval-delegation" || delegate key || be64(expires_at) || roster_hash`).
+#[derive(Debug, Clone, Copy)]
+pub struct DelegationMessage<'a> {
+    /// The verifying key of the delegate.
+    pub delegate: &'a VerifyingKey,
+    /// The UTC timestamp after which the delegation is no longer valid.
+    pub expires_at: u64,
+    /// The hash of the roster the delegation is scoped to (see [`roster_hash`](crate::quorum_approved_request::roster_hash)).
+    pub roster_hash: &'a [u8; 32],
+}
+
+impl CanonicalEncode for DelegationMessage<'_> {
+    fn canonical_bytes(&self) -> Vec<u8> {
+        [
+            b"approval-delegation".as_slice(),
+            &self.delegate.key,
+            &self.expires_at.to_be_bytes(),
+            self.roster_hash,
+        ]
+        .concat()
+    }
+}
+
+/// A command cancellation (i.e `"command-cancellation" || command || request key || be64(timestamp)`),
+/// or a cancellation of a request in monotonic counter mode (i.e `"counter-cancellation" || lp(command) || lp(request key) || be64(counter)`).
+#[derive(Debug, Clone, Copy)]
+pub struct CancellationMessage<'a> {
+    /// The cancelled request.
+    pub request: &'a IdentityAuthedRequestPayload,
+}
+
+impl CanonicalEncode for CancellationMessage<'_> {
+    fn canonical_bytes(&self) -> Vec<u8> {
+        if let Some(counter) = self.request.counter {
+            return [
+                b"counter-cancellation".as_slice(),
+                &utils::length_prefix_bytes(self.request.command.as_bytes()),
+                &utils::length_prefix_bytes(&self.request.verifying_key.key),
+                &counter.to_be_bytes(),
+            ]
+            .concat();
+        }
+        [
+            b"command-cancellation".as_slice(),
+            self.request.command.as_bytes(),
+            &self.request.verifying_key.key,
+            &self.request.timestamp.to_be_bytes(),
+        ]
+        .concat()
+    }
+}
+
+/// The domain separator for identity rotation certificates.
+const ROTATION_CERTIFICATE_DOMAIN: &[u8] = b"wamu-rotation-certificate-v1";
+
+/// An identity rotation certificate
+/// (i.e `lp("wamu-rotation-certificate-v1") || lp(cbor(old key)) || lp(cbor(new key)) || be64(epoch) || previous_hash`,
+/// where `lp` is the big-endian `u64` length prefixed bytes and the canonical CBOR encoding of keys includes their algorithm, curve and encoding).
+#[derive(Debug, Clone, Copy)]
+pub struct RotationCertificateMessage<'a> {
+    /// The verifying key of the rotating party before the rotation.
+    pub old_verifying_key: &'a VerifyingKey,
+    /// The verifying key of the rotating party after the rotation.
+    pub new_verifying_key: &'a VerifyingKey,
+    /// The roster epoch introduced by the rotation.
+    pub epoch: u64,
+    /// The hash of the roster log head before the rotation.
+    pub previous_hash: &'a [u8; 32],
+}
+
+impl CanonicalEncode for RotationCertificateMessage<'_> {
+    fn canonical_bytes(&self) -> Vec<u8> {
+        [
+            utils::length_prefix_bytes(ROTATION_CERTIFICATE_DOMAIN).as_slice(),
+            &utils::length_prefix_bytes(&self.old_verifying_key.to_cbor()),
+            &utils::length_prefix_bytes(&self.new_verifying_key.to_cbor()),
+            &self.epoch.to_be_bytes(),
+            self.previous_hash,
+        ]
+        .concat()
+    }
+}
+
+impl CanonicalEncode for RotationCertificate {
+    fn canonical_bytes(&self) -> Vec<u8> {
+        RotationCertificateMessage {
+            old_verifying_key: &self.old_verifying_key,
+            new_verifying_key: &self.new_verifying_key,
+            epoch: self.epoch,
+            previous_hash: &self.previous_hash,
+        }
+        .canonical_bytes()
+    }
+}
+
+/// A protocol version offer (i.e `"version-offer" || be16(min_version) || be16(max_version) || wallet_id || session_id`).
+#[derive(Debug, Clone, Copy)]
+pub struct VersionOfferMessage<'a> {
+    /// The oldest supported protocol version.
+    pub min_version: u16,
+    /// The newest supported protocol version.
+    pub max_version: u16,
+    /// The identifier of the wallet.
+    pub wallet_id: &'a [u8; 32],
+    /// The identifier of the protocol session (i.e offers can't be replayed in other sessions).
+    pub session_id: &'a [u8; 32],
+}
+
+impl CanonicalEncode for VersionOfferMessage<'_> {
+    fn canonical_bytes(&self) -> Vec<u8> {
+        [
+            b"version-offer".as_slice(),
+            &self.min_version.to_be_bytes(),
+            &self.max_version.to_be_bytes(),
+            self.wallet_id,
+            self.session_id,
+        ]
+        .concat()
+    }
+}
+
+/// An exported offline request (i.e `"offline-request" || wallet_id || cbor(request)`).
+#[derive(Debug, Clone, Copy)]
+pub struct OfflineRequestMessage<'a> {
+    /// The identifier of the wallet.
+    pub wallet_id: &'a [u8; 32],
+    /// The exported identity authenticated request.
+    pub request: &'a IdentityAuthedRequestPayload,
+}
+
+impl CanonicalEncode for OfflineRequestMessage<'_> {
+    fn canonical_bytes(&self) -> Vec<u8> {
+        [
+            b"offline-request".as_slice(),
+            self.wallet_id,
+            &self.request.to_cbor(),
+        ]
+        .concat()
+    }
+}
+
+/// A device migration request (i.e `"device-migration-request" || wallet_id || lp(ephemeral_key) || be64(timestamp)`,
+/// where `lp` is the big-endian `u64` length prefixed bytes).
+#[derive(Debug, Clone, Copy)]
+pub struct DeviceMigrationRequestMessage<'a> {
+    /// The identifier of the wallet.
+    pub wallet_id: &'a [u8; 32],
+    /// The SEC1 encoded ephemeral public key of the new device.
+    pub ephemeral_key: &'a [u8],
+    /// The UTC timestamp at which the request was initiated.
+    pub timestamp: u64,
+}
+
+impl CanonicalEncode for DeviceMigrationRequestMessage<'_> {
+    fn canonical_bytes(&self) -> Vec<u8> {
+        [
+            b"device-migration-request".as_slice(),
+            self.wallet_id,
+            &utils::length_prefix_bytes(self.ephemeral_key),
+            &self.timestamp.to_be_bytes(),
+        ]
+        .concat()
+    }
+}
+
+/// A device migration package (i.e `"device-migration-package" || wallet_id || lp(request ephemeral_key) || lp(ephemeral_key) || lp(nonce) || lp(ciphertext)`,
+/// where `lp` is the big-endian `u64` length prefixed bytes).
+#[derive(Debug, Clone, Copy)]
+pub struct DeviceMigrationPackageMessage<'a> {
+    /// The identifier of the wallet.
+    pub wallet_id: &'a [u8; 32],
+    /// The SEC1 encoded ephemeral public key of the new device (i.e from the request).
+    pub request_ephemeral_key: &'a [u8],
+    /// The SEC1 encoded ephemeral public key of the old device.
+    pub ephemeral_key: &'a [u8],
+    /// The encryption/decryption nonce.
+    pub nonce: &'a [u8],
+    /// The encrypted party state.
+    pub ciphertext: &'a [u8],
+}
+
+impl CanonicalEncode for DeviceMigrationPackageMessage<'_> {
+    fn canonical_bytes(&self) -> Vec<u8> {
+        [
+            b"device-migration-package".as_slice(),
+            self.wallet_id,
+            &utils::length_prefix_bytes(self.request_ephemeral_key),
+            &utils::length_prefix_bytes(self.ephemeral_key),
+            &utils::length_prefix_bytes(self.nonce),
+            &utils::length_prefix_bytes(self.ciphertext),
+        ]
+        .concat()
+    }
+}
+
+/// A device migration acknowledgement by the new device or wipe attestation by the old device
+/// (i.e `"device-migration-" || stage || wallet_id || package_hash`, where stage is either "ack" or "wipe").
+#[derive(Debug, Clone, Copy)]
+pub struct DeviceMigrationReceiptMessage<'a> {
+    /// The stage of the device migration (i.e "ack" or "wipe").
+    pub stage: &'a str,
+    /// The identifier of the wallet.
+    pub wallet_id: &'a [u8; 32],
+    /// The SHA-256 hash of the canonical CBOR encoding of the device migration package.
+    pub package_hash: &'a [u8; 32],
+}
+
+impl CanonicalEncode for DeviceMigrationReceiptMessage<'_> {
+    fn canonical_bytes(&self) -> Vec<u8> {
+        [
+            b"device-migration-".as_slice(),
+            self.stage.as_bytes(),
+            self.wallet_id,
+            self.package_hash,
+        ]
+        .concat()
+    }
+}
+
+/// A key destruction attestation (i.e `"key-destruction" || wallet_id || cbor(request)`).
+#[derive(Debug, Clone, Copy)]
+pub struct DestructionAttestationMessage<'a> {
+    /// The identifier of the destroyed wallet.
+    pub wallet_id: &'a [u8; 32],
+    /// The quorum approved key destruction request.
+    pub request: &'a IdentityAuthedRequestPayload,
+}
+
+impl CanonicalEncode for DestructionAttestationMessage<'_> {
+    fn canonical_bytes(&self) -> Vec<u8> {
+        [
+            b"key-destruction".as_slice(),
+            self.wallet_id,
+            &self.request.to_cbor(),
+        ]
+        .concat()
+    }
+}
+
+/// A keystore export contribution
+/// (i.e `"keystore-export" || wallet_id || cbor(request) || be16(idx) || lp(ephemeral_key) || lp(nonce) || lp(ciphertext)`,
+/// where `lp` is the big-endian `u64` length prefixed bytes).
+#[derive(Debug, Clone, Copy)]
+pub struct KeystoreExportContributionMessage<'a> {
+    /// The identifier of the exported wallet.
+    pub wallet_id: &'a [u8; 32],
+    /// The quorum approved keystore export request.
+    pub request: &'a IdentityAuthedRequestPayload,
+    /// The index of the contributing party.
+    pub idx: u16,
+    /// The ephemeral public key of the contributing party.
+    pub ephemeral_key: &'a [u8],
+    /// The encryption/decryption nonce.
+    pub nonce: &'a [u8],
+    /// The encrypted "secret share".
+    pub ciphertext: &'a [u8],
+}
+
+impl CanonicalEncode for KeystoreExportContributionMessage<'_> {
+    fn canonical_bytes(&self) -> Vec<u8> {
+        [
+            b"keystore-export".as_slice(),
+            self.wallet_id,
+            &self.request.to_cbor(),
+            &self.idx.to_be_bytes(),
+            &utils::length_prefix_bytes(self.ephemeral_key),
+            &utils::length_prefix_bytes(self.nonce),
+            &utils::length_prefix_bytes(self.ciphertext),
+        ]
+        .concat()
+    }
+}
+
+/// An audit log entry (i.e `"audit-log-entry" || body_hash`).
+#[derive(Debug, Clone, Copy)]
+pub struct AuditLogEntryMessage<'a> {
+    /// The hash of the audit log entry body (i.e all fields except the signature).
+    pub body_hash: &'a [u8; 32],
+}
+
+impl CanonicalEncode for AuditLogEntryMessage<'_> {
+    fn canonical_bytes(&self) -> Vec<u8> {
+        [b"audit-log-entry".as_slice(), self.body_hash].concat()
+    }
+}
+
+/// Session terms (i.e `wallet_id || session_id || lp(protocol) || be64(n) || lp(participant key)* || lp(message) || be64(respond_by) || be64(deadline)`,
+/// where `lp` is the big-endian `u64` length prefixed bytes).
+impl CanonicalEncode for SessionTerms {
+    fn canonical_bytes(&self) -> Vec<u8> {
+        let participants: Vec<u8> = self
+            .participants
+            .iter()
+            .flat_map(|participant| utils::length_prefix_bytes(&participant.key))
+            .collect();
+        [
+            self.wallet_id.as_slice(),
+            &self.session_id,
+            &utils::length_prefix_bytes(self.protocol.as_bytes()),
+            &(self.participants.len() as u64).to_be_bytes(),
+            &participants,
+            &utils::length_prefix_bytes(&self.message),
+            &self.respond_by.to_be_bytes(),
+            &self.deadline.to_be_bytes(),
+        ]
+        .concat()
+    }
+}
+
+/// A session proposal (i.e `"session-proposal" || terms`).
+#[derive(Debug, Clone, Copy)]
+pub struct SessionProposalMessage<'a> {
+    /// The proposed session terms.
+    pub terms: &'a SessionTerms,
+}
+
+impl CanonicalEncode for SessionProposalMessage<'_> {
+    fn canonical_bytes(&self) -> Vec<u8> {
+        [
+            b"session-proposal".as_slice(),
+            &self.terms.canonical_bytes(),
+        ]
+        .concat()
+    }
+}
+
+/// A session response (i.e `"session-response" || terms || u8(accepted)`).
+#[derive(Debug, Clone, Copy)]
+pub struct SessionResponseMessage<'a> {
+    /// The proposed session terms.
+    pub terms: &'a SessionTerms,
+    /// Whether the responding party accepts the proposal.
+    pub accepted: bool,
+}
+
+impl CanonicalEncode for SessionResponseMessage<'_> {
+    fn canonical_bytes(&self) -> Vec<u8> {
+        [
+            b"session-response".as_slice(),
+            &self.terms.canonical_bytes(),
+            &[self.accepted as u8],
+        ]
+        .concat()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{
+        EllipticCurve, KeyEncoding, MessageDigest, Signature, SignatureAlgorithm, SignatureEncoding,
+    };
+    use alloc::string::ToString;
+    use alloc::vec;
+    use crypto_bigint::U256;
+
+    #[test]
+    fn canonical_encoding_works() {
+        let key = |byte: u8| VerifyingKey {
+            key: vec![byte],
+            algo: SignatureAlgorithm::ECDSA,
+            curve: EllipticCurve::Secp256k1,
+            enc: KeyEncoding::SEC1,
+        };
+        let fragment = |value: u64| Random32Bytes::from(U256::from_u64(value));
+        let delegation = ApprovalDelegationPayload {
+            delegator: key(1),
+            delegate: key(2),
+            expires_at: 1,
+            signature: Signature {
+                sig: vec![3],
+                algo: SignatureAlgorithm::ECDSA,
+                curve: EllipticCurve::Secp256k1,
+                hash: MessageDigest::SHA256,
+                enc: SignatureEncoding::DER,
+            },
+        };
+
+        // Canonical bytes are specified exactly.
+        for (canonical_bytes, expected) in [
+            (
+                CommandMessage {
+                    command: "command",
+                    timestamp: 12,
+                }
+                .canonical_bytes(),
+                b"command12".to_vec(),
+            ),
+            (
+                CounterCommandMessage {
+                    command: "command",
+                    counter: 3,
+                }
+                .canonical_bytes(),
+                [
+                    b"counter-command".as_slice(),
+                    &7u64.to_be_bytes(),
+                    b"command",
+                    &3u64.to_be_bytes(),
+                ]
+                .concat(),
+            ),
+            (
+                ChallengeMessage {
+                    challenge_fragments: &[fragment(2), fragment(1)],
+                }
+                .canonical_bytes(),
+                [fragment(1).to_be_bytes(), fragment(2).to_be_bytes()].concat(),
+            ),
+            (
+                CommandApprovalMessage {
+                    challenge_fragment: &fragment(5),
+                    command: "command",
+                    timestamp: 12,
+                    counter: None,
+                    delegation: Some(&delegation),
+                }
+                .canonical_bytes(),
+                [
+                    b"delegated-approval".as_slice(),
+                    &fragment(5).to_be_bytes(),
+                    &7u64.to_be_bytes(),
+                    b"command",
+                    &12u64.to_be_bytes(),
+                    &Sha256::digest(delegation.to_cbor()),
+                ]
+                .concat(),
+            ),
+            (
+                CommandApprovalMessage {
+                    challenge_fragment: &fragment(5),
+                    command: "command",
+                    timestamp: 0,
+                    counter: Some(4),
+                    delegation: None,
+                }
+                .canonical_bytes(),
+                [
+                    b"counter-approval".as_slice(),
+                    &fragment(5).to_be_bytes(),
+                    &7u64.to_be_bytes(),
+                    b"command",
+                    &4u64.to_be_bytes(),
+                ]
+                .concat(),
+            ),
+            (
+                DelegationMessage {
+                    delegate: &key(7),
+                    expires_at: 1,
+                    roster_hash: &[2; 32],
+                }
+                .canonical_bytes(),
+                [
+                    b"approval-delegation".as_slice(),
+                    &[7],
+                    &1u64.to_be_bytes(),
+                    &[2; 32],
+                ]
+                .concat(),
+            ),
+            (
+                RotationCertificateMessage {
+                    old_verifying_key: &key(1),
+                    new_verifying_key: &key(2),
+                    epoch: 1,
+                    previous_hash: &[0; 32],
+                }
+                .canonical_bytes(),
+                [
+                    utils::length_prefix_bytes(b"wamu-rotation-certificate-v1").as_slice(),
+                    &utils::length_prefix_bytes(&key(1).to_cbor()),
+                    &utils::length_prefix_bytes(&key(2).to_cbor()),
+                    &1u64.to_be_bytes(),
+                    &[0; 32],
+                ]
+                .concat(),
+            ),
+            (
+                VersionOfferMessage {
+                    min_version: 1,
+                    max_version: 2,
+                    wallet_id: &[9; 32],
+                    session_id: &[8; 32],
+                }
+                .canonical_bytes(),
+                [
+                    b"version-offer".as_slice(),
+                    &[0, 1, 0, 2],
+                    &[9; 32],
+                    &[8; 32],
+                ]
+                .concat(),
+            ),
+            (
+                SessionResponseMessage {
+                    terms: &SessionTerms {
+                        wallet_id: [9; 32],
+                        session_id: [8; 32],
+                        protocol: "sign".to_string(),
+                        participants: vec![key(1), key(2)],
+                        message: vec![7],
+                        respond_by: 1,
+                        deadline: 2,
+                    },
+                    accepted: true,
+                }
+                .canonical_bytes(),
+                [
+                    b"session-response".as_slice(),
+                    &[9; 32],
+                    &[8; 32],
+                    &4u64.to_be_bytes(),
+                    b"sign",
+                    &2u64.to_be_bytes(),
+                    &1u64.to_be_bytes(),
+                    &[1],
+                    &1u64.to_be_bytes(),
+                    &[2],
+                    &1u64.to_be_bytes(),
+                    &[7],
+                    &1u64.to_be_bytes(),
+                    &2u64.to_be_bytes(),
+                    &[1],
+                ]
+                .concat(),
+            ),
+            (
+                AuditLogEntryMessage {
+                    body_hash: &[5; 32],
+                }
+                .canonical_bytes(),
+                [b"audit-log-entry".as_slice(), &[5; 32]].concat(),
+            ),
+        ] {
+            assert_eq!(canonical_bytes, expected);
+        }
+
+        // Rotation certificates bind the key types (i.e same key bytes with a different curve don't collide).
+        let mut curve25519_key = key(2);
+        curve25519_key.curve = EllipticCurve::Curve25519;
+        assert_ne!(
+            RotationCertificateMessage {
+                old_verifying_key: &key(1),
+                new_verifying_key: &key(2),
+                epoch: 1,
+                previous_hash: &[0; 32],
+            }
+            .canonical_bytes(),
+            RotationCertificateMessage {
+                old_verifying_key: &key(1),
+                new_verifying_key: &curve25519_key,
+                epoch: 1,
+                previous_hash: &[0; 32],
+            }
+            .canonical_bytes()
+        );
+
+        // Sign-able message bytes are prefixed.
+        let message = CommandMessage {
+            command: "command",
+            timestamp: 12,
+        };
+        assert_eq!(
+            message.message_bytes(),
+            [utils::WAMU_MESSAGE_PREFIX.as_bytes(), b"command12"].concat()
+        );
+    }
+}
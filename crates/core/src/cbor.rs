@@ -0,0 +1,1030 @@
+//! Canonical (deterministic) [CBOR](https://www.rfc-editor.org/rfc/rfc8949) wire encoding for payloads.
+//!
+//! Allows independent implementations and non-Rust clients to interoperate byte-for-byte.
+//!
+//! Encoding rules (i.e a subset of the "Core Deterministic Encoding Requirements" from RFC 8949 Section 4.2.1):
+//! - Integers, lengths and array sizes are encoded in their shortest form and indefinite lengths are not allowed.
+//! - Structs are encoded as arrays of their fields in declaration order.
+//! - Enums without data are encoded as unsigned integers (i.e their zero-based variant index).
+//! - Byte vectors and fixed size byte arrays (e.g `Random32Bytes`) are encoded as byte strings.
+//! - Optional values are encoded as `null` when absent.
+//!
+//! Decoding rejects any encoding that doesn't follow these rules.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use crypto_bigint::{Encoding, U256};
+
+use crate::crypto::{
+    EllipticCurve, KeyEncoding, MessageDigest, Random32Bytes, Signature, SignatureAlgorithm,
+    SignatureEncoding, VerifyingKey,
+};
+use crate::errors::CborError;
+use crate::key_store::StoredKey;
+use crate::payloads::{
+    ApprovalDelegationPayload, CommandApprovalPayload, CommandCancellationPayload,
+    DestructionCertificate, DeviceMigrationPackage, DeviceMigrationRequestPayload,
+    EncryptedShareBackup, IdentityAuthedRequestPayload, IdentityRotationChallengeResponsePayload,
+    OfflineRequestPayload, QuorumApprovedChallengeResponsePayload, RotationCertificate,
+    SessionProposalPayload, SessionResponsePayload, SessionTerms, VersionOfferPayload,
+};
+use crate::policy::{Policy, PolicyRule};
+use crate::share::{SigningShare, SubShare};
+
+/// CBOR major type for unsigned integers.
+const MAJOR_UNSIGNED: u8 = 0;
+/// CBOR major type for byte strings.
+const MAJOR_BYTES: u8 = 2;
+/// CBOR major type for text strings.
+const MAJOR_TEXT: u8 = 3;
+/// CBOR major type for arrays.
+const MAJOR_ARRAY: u8 = 4;
+/// CBOR encoding of `false`.
+const FALSE: u8 = 0xf4;
+/// CBOR encoding of `true`.
+const TRUE: u8 = 0xf5;
+/// CBOR encoding of `null`.
+const NULL: u8 = 0xf6;
+
+/// A type with a canonical CBOR encoding.
+pub trait CanonicalCbor: Sized {
+    /// Writes the canonical CBOR encoding of the value to the encoder.
+    fn encode(&self, encoder: &mut Encoder);
+
+    /// Reads a value from the decoder.
+    fn decode(decoder: &mut Decoder) -> Result<Self, CborError>;
+
+    /// Returns the canonical CBOR encoding of the value.
+    fn to_cbor(&self) -> Vec<u8> {
+        let mut encoder = Encoder::new();
+        self.encode(&mut encoder);
+        encoder.finish()
+    }
+
+    /// Returns the value decoded from its canonical CBOR encoding
+    /// or an appropriate error if the input isn't a canonical encoding of a single value.
+    fn from_cbor(bytes: &[u8]) -> Result<Self, CborError> {
+        let mut decoder = Decoder::new(bytes);
+        let value = Self::decode(&mut decoder)?;
+        decoder.finish()?;
+        Ok(value)
+    }
+}
+
+/// A canonical CBOR encoder.
+#[derive(Debug, Default)]
+pub struct Encoder {
+    bytes: Vec<u8>,
+}
+
+impl Encoder {
+    /// Returns an empty encoder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the encoded bytes.
+    pub fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// Writes an unsigned integer.
+    pub fn uint(&mut self, value: u64) {
+        self.head(MAJOR_UNSIGNED, value);
+    }
+
+    /// Writes a byte string.
+    pub fn bytes(&mut self, value: &[u8]) {
+        self.head(MAJOR_BYTES, value.len() as u64);
+        self.bytes.extend_from_slice(value);
+    }
+
+    /// Writes a text string.
+    pub fn text(&mut self, value: &str) {
+        self.head(MAJOR_TEXT, value.len() as u64);
+        self.bytes.extend_from_slice(value.as_bytes());
+    }
+
+    /// Writes the header of an array with the given number of items.
+    pub fn array(&mut self, len: usize) {
+        self.head(MAJOR_ARRAY, len as u64);
+    }
+
+    /// Writes a boolean.
+    pub fn bool(&mut self, value: bool) {
+        self.bytes.push(if value { TRUE } else { FALSE });
+    }
+
+    /// Writes `null`.
+    pub fn null(&mut self) {
+        self.bytes.push(NULL);
+    }
+
+    /// Writes an array of values.
+    pub fn seq<T: CanonicalCbor>(&mut self, values: &[T]) {
+        self.array(values.len());
+        for value in values {
+            value.encode(self);
+        }
+    }
+
+    /// Writes an optional value (i.e `null` if absent).
+    pub fn option<T: CanonicalCbor>(&mut self, value: Option<&T>) {
+        match value {
+            Some(value) => value.encode(self),
+            None => self.null(),
+        }
+    }
+
+    /// Writes a header (i.e major type and argument) in its shortest form.
+    fn head(&mut self, major: u8, value: u64) {
+        let major = major << 5;
+        if value < 24 {
+            self.bytes.push(major | value as u8);
+        } else if value <= u8::MAX as u64 {
+            self.bytes.push(major | 24);
+            self.bytes.push(value as u8);
+        } else if value <= u16::MAX as u64 {
+            self.bytes.push(major | 25);
+            self.bytes.extend_from_slice(&(value as u16).to_be_bytes());
+        } else if value <= u32::MAX as u64 {
+            self.bytes.push(major | 26);
+            self.bytes.extend_from_slice(&(value as u32).to_be_bytes());
+        } else {
+            self.bytes.push(major | 27);
+            self.bytes.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+}
+
+/// A canonical CBOR decoder.
+#[derive(Debug)]
+pub struct Decoder<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Decoder<'a> {
+    /// Returns a decoder for the given bytes.
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    /// Returns an error if there are bytes left over.
+    pub fn finish(&self) -> Result<(), CborError> {
+        if self.position == self.bytes.len() {
+            Ok(())
+        } else {
+            Err(CborError::TrailingBytes)
+        }
+    }
+
+    /// Reads an unsigned integer.
+    pub fn uint(&mut self) -> Result<u64, CborError> {
+        self.head(MAJOR_UNSIGNED)
+    }
+
+    /// Reads a byte string.
+    pub fn bytes(&mut self) -> Result<Vec<u8>, CborError> {
+        let len = self.head(MAJOR_BYTES)?;
+        Ok(self.take(len)?.to_vec())
+    }
+
+    /// Reads a text string.
+    pub fn text(&mut self) -> Result<String, CborError> {
+        let len = self.head(MAJOR_TEXT)?;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| CborError::InvalidValue)
+    }
+
+    /// Reads the header of an array and returns the number of items.
+    pub fn array(&mut self) -> Result<usize, CborError> {
+        let len = self.head(MAJOR_ARRAY)?;
+        // Each item is at least 1 byte long.
+        if len > (self.bytes.len() - self.position) as u64 {
+            return Err(CborError::UnexpectedEnd);
+        }
+        Ok(len as usize)
+    }
+
+    /// Reads the header of an array with the expected number of items.
+    pub fn array_of_len(&mut self, len: usize) -> Result<(), CborError> {
+        if self.array()? == len {
+            Ok(())
+        } else {
+            Err(CborError::InvalidValue)
+        }
+    }
+
+    /// Reads a boolean.
+    pub fn bool(&mut self) -> Result<bool, CborError> {
+        match self.take(1)?[0] {
+            TRUE => Ok(true),
+            FALSE => Ok(false),
+            _ => Err(CborError::InvalidValue),
+        }
+    }
+
+    /// Reads an array of values.
+    pub fn seq<T: CanonicalCbor>(&mut self) -> Result<Vec<T>, CborError> {
+        let len = self.array()?;
+        (0..len).map(|_| T::decode(self)).collect()
+    }
+
+    /// Reads an optional value (i.e `null` if absent).
+    pub fn option<T: CanonicalCbor>(&mut self) -> Result<Option<T>, CborError> {
+        if self.peek()? == NULL {
+            self.position += 1;
+            Ok(None)
+        } else {
+            T::decode(self).map(Some)
+        }
+    }
+
+    /// Reads a header of the expected major type and returns its argument
+    /// (rejecting indefinite lengths and arguments that are not in their shortest form).
+    fn head(&mut self, major: u8) -> Result<u64, CborError> {
+        let initial = self.take(1)?[0];
+        if initial >> 5 != major {
+            return Err(CborError::InvalidValue);
+        }
+        let (value, min) = match initial & 0x1f {
+            info @ 0..=23 => return Ok(info as u64),
+            24 => (self.take(1)?[0] as u64, 24),
+            25 => (
+                u16::from_be_bytes(self.take(2)?.try_into().unwrap()) as u64,
+                u8::MAX as u64 + 1,
+            ),
+            26 => (
+                u32::from_be_bytes(self.take(4)?.try_into().unwrap()) as u64,
+                u16::MAX as u64 + 1,
+            ),
+            27 => (
+                u64::from_be_bytes(self.take(8)?.try_into().unwrap()),
+                u32::MAX as u64 + 1,
+            ),
+            _ => return Err(CborError::NonCanonical),
+        };
+        if value < min {
+            return Err(CborError::NonCanonical);
+        }
+        Ok(value)
+    }
+
+    /// Returns the next byte without consuming it.
+    fn peek(&self) -> Result<u8, CborError> {
+        self.bytes
+            .get(self.position)
+            .copied()
+            .ok_or(CborError::UnexpectedEnd)
+    }
+
+    /// Consumes and returns the next `len` bytes.
+    fn take(&mut self, len: u64) -> Result<&'a [u8], CborError> {
+        let end = usize::try_from(len)
+            .ok()
+            .and_then(|len| self.position.checked_add(len))
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or(CborError::UnexpectedEnd)?;
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+}
+
+/// Implements `CanonicalCbor` for enums without data (i.e encoded as their zero-based variant index).
+macro_rules! impl_cbor_unit_enum {
+    ($enum_type:ident { $($variant:ident = $index:literal),+ $(,)? }) => {
+        impl CanonicalCbor for $enum_type {
+            fn encode(&self, encoder: &mut Encoder) {
+                encoder.uint(match self {
+                    $($enum_type::$variant => $index,)+
+                });
+            }
+
+            fn decode(decoder: &mut Decoder) -> Result<Self, CborError> {
+                match decoder.uint()? {
+                    $($index => Ok($enum_type::$variant),)+
+                    _ => Err(CborError::InvalidValue),
+                }
+            }
+        }
+    };
+}
+
+impl_cbor_unit_enum!(SignatureAlgorithm { ECDSA = 0, EdDSA = 1 });
+impl_cbor_unit_enum!(EllipticCurve {
+    Secp256k1 = 0,
+    Curve25519 = 1
+});
+impl_cbor_unit_enum!(MessageDigest {
+    SHA256 = 0,
+    SHA512 = 1,
+    Keccak256 = 2
+});
+impl_cbor_unit_enum!(KeyEncoding {
+    SEC1 = 0,
+    EIP55 = 1,
+    RFC8032 = 2
+});
+impl_cbor_unit_enum!(SignatureEncoding {
+    DER = 0,
+    RLP = 1,
+    RFC8032 = 2
+});
+
+impl CanonicalCbor for u64 {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.uint(*self);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, CborError> {
+        decoder.uint()
+    }
+}
+
+impl CanonicalCbor for Random32Bytes {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.bytes(&self.to_be_bytes());
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, CborError> {
+        Self::try_from(decoder.bytes()?.as_slice()).map_err(|_| CborError::InvalidValue)
+    }
+}
+
+impl CanonicalCbor for VerifyingKey {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.array(4);
+        encoder.bytes(&self.key);
+        self.algo.encode(encoder);
+        self.curve.encode(encoder);
+        self.enc.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, CborError> {
+        decoder.array_of_len(4)?;
+        Ok(Self {
+            key: decoder.bytes()?,
+            algo: CanonicalCbor::decode(decoder)?,
+            curve: CanonicalCbor::decode(decoder)?,
+            enc: CanonicalCbor::decode(decoder)?,
+        })
+    }
+}
+
+impl CanonicalCbor for Signature {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.array(5);
+        encoder.bytes(&self.sig);
+        self.algo.encode(encoder);
+        self.curve.encode(encoder);
+        self.hash.encode(encoder);
+        self.enc.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, CborError> {
+        decoder.array_of_len(5)?;
+        Ok(Self {
+            sig: decoder.bytes()?,
+            algo: CanonicalCbor::decode(decoder)?,
+            curve: CanonicalCbor::decode(decoder)?,
+            hash: CanonicalCbor::decode(decoder)?,
+            enc: CanonicalCbor::decode(decoder)?,
+        })
+    }
+}
+
+impl<A: CanonicalCbor, B: CanonicalCbor> CanonicalCbor for (A, B) {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.array(2);
+        self.0.encode(encoder);
+        self.1.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, CborError> {
+        decoder.array_of_len(2)?;
+        Ok((A::decode(decoder)?, B::decode(decoder)?))
+    }
+}
+
+impl CanonicalCbor for IdentityAuthedRequestPayload {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.array(5);
+        encoder.text(&self.command);
+        self.verifying_key.encode(encoder);
+        encoder.uint(self.timestamp);
+        encoder.option(self.counter.as_ref());
+        self.signature.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, CborError> {
+        decoder.array_of_len(5)?;
+        Ok(Self {
+            command: decoder.text()?,
+            verifying_key: CanonicalCbor::decode(decoder)?,
+            timestamp: decoder.uint()?,
+            counter: decoder.option()?,
+            signature: CanonicalCbor::decode(decoder)?,
+        })
+    }
+}
+
+impl CanonicalCbor for IdentityRotationChallengeResponsePayload {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.array(3);
+        self.new_verifying_key.encode(encoder);
+        self.current_signature.encode(encoder);
+        self.new_signature.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, CborError> {
+        decoder.array_of_len(3)?;
+        Ok(Self {
+            new_verifying_key: CanonicalCbor::decode(decoder)?,
+            current_signature: CanonicalCbor::decode(decoder)?,
+            new_signature: CanonicalCbor::decode(decoder)?,
+        })
+    }
+}
+
+impl CanonicalCbor for RotationCertificate {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.array(5);
+        self.old_verifying_key.encode(encoder);
+        self.new_verifying_key.encode(encoder);
+        encoder.uint(self.epoch);
+        encoder.bytes(&self.previous_hash);
+        encoder.seq(&self.signatures);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, CborError> {
+        decoder.array_of_len(5)?;
+        Ok(Self {
+            old_verifying_key: CanonicalCbor::decode(decoder)?,
+            new_verifying_key: CanonicalCbor::decode(decoder)?,
+            epoch: decoder.uint()?,
+            previous_hash: decoder
+                .bytes()?
+                .try_into()
+                .map_err(|_| CborError::InvalidValue)?,
+            signatures: decoder.seq()?,
+        })
+    }
+}
+
+impl CanonicalCbor for CommandApprovalPayload {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.array(4);
+        self.challenge_fragment.encode(encoder);
+        self.verifying_key.encode(encoder);
+        self.signature.encode(encoder);
+        encoder.option(self.delegation.as_ref());
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, CborError> {
+        decoder.array_of_len(4)?;
+        Ok(Self {
+            challenge_fragment: CanonicalCbor::decode(decoder)?,
+            verifying_key: CanonicalCbor::decode(decoder)?,
+            signature: CanonicalCbor::decode(decoder)?,
+            delegation: decoder.option()?,
+        })
+    }
+}
+
+impl CanonicalCbor for ApprovalDelegationPayload {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.array(4);
+        self.delegator.encode(encoder);
+        self.delegate.encode(encoder);
+        encoder.uint(self.expires_at);
+        self.signature.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, CborError> {
+        decoder.array_of_len(4)?;
+        Ok(Self {
+            delegator: CanonicalCbor::decode(decoder)?,
+            delegate: CanonicalCbor::decode(decoder)?,
+            expires_at: decoder.uint()?,
+            signature: CanonicalCbor::decode(decoder)?,
+        })
+    }
+}
+
+impl CanonicalCbor for CommandCancellationPayload {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.array(2);
+        self.verifying_key.encode(encoder);
+        self.signature.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, CborError> {
+        decoder.array_of_len(2)?;
+        Ok(Self {
+            verifying_key: CanonicalCbor::decode(decoder)?,
+            signature: CanonicalCbor::decode(decoder)?,
+        })
+    }
+}
+
+impl CanonicalCbor for QuorumApprovedChallengeResponsePayload {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.array(2);
+        self.signature.encode(encoder);
+        encoder.seq(&self.approving_quorum);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, CborError> {
+        decoder.array_of_len(2)?;
+        Ok(Self {
+            signature: CanonicalCbor::decode(decoder)?,
+            approving_quorum: decoder.seq()?,
+        })
+    }
+}
+
+impl CanonicalCbor for VersionOfferPayload {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.array(4);
+        encoder.uint(self.min_version as u64);
+        encoder.uint(self.max_version as u64);
+        self.verifying_key.encode(encoder);
+        self.signature.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, CborError> {
+        decoder.array_of_len(4)?;
+        Ok(Self {
+            min_version: decoder
+                .uint()?
+                .try_into()
+                .map_err(|_| CborError::InvalidValue)?,
+            max_version: decoder
+                .uint()?
+                .try_into()
+                .map_err(|_| CborError::InvalidValue)?,
+            verifying_key: CanonicalCbor::decode(decoder)?,
+            signature: CanonicalCbor::decode(decoder)?,
+        })
+    }
+}
+
+impl CanonicalCbor for OfflineRequestPayload {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.array(4);
+        encoder.bytes(&self.wallet_id);
+        self.request.encode(encoder);
+        self.verifying_key.encode(encoder);
+        self.signature.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, CborError> {
+        decoder.array_of_len(4)?;
+        Ok(Self {
+            wallet_id: decoder
+                .bytes()?
+                .try_into()
+                .map_err(|_| CborError::InvalidValue)?,
+            request: CanonicalCbor::decode(decoder)?,
+            verifying_key: CanonicalCbor::decode(decoder)?,
+            signature: CanonicalCbor::decode(decoder)?,
+        })
+    }
+}
+
+impl CanonicalCbor for DeviceMigrationRequestPayload {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.array(5);
+        encoder.bytes(&self.wallet_id);
+        encoder.bytes(&self.ephemeral_key);
+        encoder.uint(self.timestamp);
+        self.verifying_key.encode(encoder);
+        self.signature.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, CborError> {
+        decoder.array_of_len(5)?;
+        Ok(Self {
+            wallet_id: decoder
+                .bytes()?
+                .try_into()
+                .map_err(|_| CborError::InvalidValue)?,
+            ephemeral_key: decoder.bytes()?,
+            timestamp: decoder.uint()?,
+            verifying_key: CanonicalCbor::decode(decoder)?,
+            signature: CanonicalCbor::decode(decoder)?,
+        })
+    }
+}
+
+impl CanonicalCbor for DeviceMigrationPackage {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.array(6);
+        encoder.bytes(&self.wallet_id);
+        encoder.bytes(&self.ephemeral_key);
+        encoder.bytes(&self.nonce);
+        encoder.bytes(&self.ciphertext);
+        self.verifying_key.encode(encoder);
+        self.signature.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, CborError> {
+        decoder.array_of_len(6)?;
+        Ok(Self {
+            wallet_id: decoder
+                .bytes()?
+                .try_into()
+                .map_err(|_| CborError::InvalidValue)?,
+            ephemeral_key: decoder.bytes()?,
+            nonce: decoder.bytes()?,
+            ciphertext: decoder.bytes()?,
+            verifying_key: CanonicalCbor::decode(decoder)?,
+            signature: CanonicalCbor::decode(decoder)?,
+        })
+    }
+}
+
+impl CanonicalCbor for DestructionCertificate {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.array(3);
+        encoder.bytes(&self.wallet_id);
+        self.request.encode(encoder);
+        encoder.seq(&self.attestations);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, CborError> {
+        decoder.array_of_len(3)?;
+        Ok(Self {
+            wallet_id: decoder
+                .bytes()?
+                .try_into()
+                .map_err(|_| CborError::InvalidValue)?,
+            request: CanonicalCbor::decode(decoder)?,
+            attestations: decoder.seq()?,
+        })
+    }
+}
+
+impl CanonicalCbor for SessionTerms {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.array(7);
+        encoder.bytes(&self.wallet_id);
+        encoder.bytes(&self.session_id);
+        encoder.text(&self.protocol);
+        encoder.seq(&self.participants);
+        encoder.bytes(&self.message);
+        encoder.uint(self.respond_by);
+        encoder.uint(self.deadline);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, CborError> {
+        decoder.array_of_len(7)?;
+        Ok(Self {
+            wallet_id: decoder
+                .bytes()?
+                .try_into()
+                .map_err(|_| CborError::InvalidValue)?,
+            session_id: decoder
+                .bytes()?
+                .try_into()
+                .map_err(|_| CborError::InvalidValue)?,
+            protocol: decoder.text()?,
+            participants: decoder.seq()?,
+            message: decoder.bytes()?,
+            respond_by: decoder.uint()?,
+            deadline: decoder.uint()?,
+        })
+    }
+}
+
+impl CanonicalCbor for SessionProposalPayload {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.array(3);
+        self.terms.encode(encoder);
+        self.verifying_key.encode(encoder);
+        self.signature.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, CborError> {
+        decoder.array_of_len(3)?;
+        Ok(Self {
+            terms: CanonicalCbor::decode(decoder)?,
+            verifying_key: CanonicalCbor::decode(decoder)?,
+            signature: CanonicalCbor::decode(decoder)?,
+        })
+    }
+}
+
+impl CanonicalCbor for SessionResponsePayload {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.array(3);
+        encoder.bool(self.accepted);
+        self.verifying_key.encode(encoder);
+        self.signature.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, CborError> {
+        decoder.array_of_len(3)?;
+        Ok(Self {
+            accepted: decoder.bool()?,
+            verifying_key: CanonicalCbor::decode(decoder)?,
+            signature: CanonicalCbor::decode(decoder)?,
+        })
+    }
+}
+
+impl CanonicalCbor for EncryptedShareBackup {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.array(3);
+        encoder.bytes(&self.signing_share);
+        encoder.array(2);
+        encoder.bytes(&self.sub_share.0);
+        encoder.bytes(&self.sub_share.1);
+        encoder.bytes(&self.nonce);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, CborError> {
+        decoder.array_of_len(3)?;
+        let signing_share = decoder.bytes()?;
+        decoder.array_of_len(2)?;
+        Ok(Self {
+            signing_share,
+            sub_share: (decoder.bytes()?, decoder.bytes()?),
+            nonce: decoder.bytes()?,
+        })
+    }
+}
+
+impl CanonicalCbor for PolicyRule {
+    fn encode(&self, encoder: &mut Encoder) {
+        match self {
+            PolicyRule::DestinationAllowlist(destinations) => {
+                encoder.array(2);
+                encoder.uint(0);
+                encoder.array(destinations.len());
+                for destination in destinations {
+                    encoder.bytes(destination);
+                }
+            }
+            PolicyRule::AmountLimit(limit) => {
+                encoder.array(2);
+                encoder.uint(1);
+                encoder.uint(*limit);
+            }
+            PolicyRule::RequiredApprovers(approvers) => {
+                encoder.array(2);
+                encoder.uint(2);
+                encoder.seq(approvers);
+            }
+            PolicyRule::TimeWindow { start, end } => {
+                encoder.array(3);
+                encoder.uint(3);
+                encoder.uint(u64::from(*start));
+                encoder.uint(u64::from(*end));
+            }
+        }
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, CborError> {
+        let len = decoder.array()?;
+        match (decoder.uint()?, len) {
+            (0, 2) => {
+                let n_destinations = decoder.array()?;
+                Ok(PolicyRule::DestinationAllowlist(
+                    (0..n_destinations)
+                        .map(|_| decoder.bytes())
+                        .collect::<Result<_, _>>()?,
+                ))
+            }
+            (1, 2) => Ok(PolicyRule::AmountLimit(decoder.uint()?)),
+            (2, 2) => Ok(PolicyRule::RequiredApprovers(decoder.seq()?)),
+            (3, 3) => {
+                let mut seconds_of_day =
+                    || u32::try_from(decoder.uint()?).map_err(|_| CborError::InvalidValue);
+                Ok(PolicyRule::TimeWindow {
+                    start: seconds_of_day()?,
+                    end: seconds_of_day()?,
+                })
+            }
+            _ => Err(CborError::InvalidValue),
+        }
+    }
+}
+
+impl CanonicalCbor for Policy {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.array(3);
+        encoder.bytes(&self.wallet_id);
+        encoder.uint(self.version);
+        encoder.seq(&self.rules);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, CborError> {
+        decoder.array_of_len(3)?;
+        Ok(Self {
+            wallet_id: decoder
+                .bytes()?
+                .try_into()
+                .map_err(|_| CborError::InvalidValue)?,
+            version: decoder.uint()?,
+            rules: decoder.seq()?,
+        })
+    }
+}
+
+impl CanonicalCbor for StoredKey {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.array(5);
+        encoder.bytes(&self.signing_share.to_be_bytes());
+        encoder.array(2);
+        encoder.bytes(&self.sub_share.x().to_be_bytes());
+        encoder.bytes(&self.sub_share.y().to_be_bytes());
+        encoder.bytes(&self.local_key);
+        encoder.seq(&self.roster);
+        encoder.uint(self.epoch);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, CborError> {
+        decoder.array_of_len(5)?;
+        let signing_share = SigningShare::try_from(decoder.bytes()?.as_slice())
+            .map_err(|_| CborError::InvalidValue)?;
+        decoder.array_of_len(2)?;
+        let mut coordinate = || -> Result<U256, CborError> {
+            let bytes: [u8; 32] = decoder
+                .bytes()?
+                .try_into()
+                .map_err(|_| CborError::InvalidValue)?;
+            Ok(U256::from_be_bytes(bytes))
+        };
+        let (x, y) = (coordinate()?, coordinate()?);
+        // Coordinates must be less than the curve order.
+        let sub_share = SubShare::new(x, y).map_err(|_| CborError::InvalidValue)?;
+        Ok(Self {
+            signing_share,
+            sub_share,
+            local_key: decoder.bytes()?,
+            roster: decoder.seq()?,
+            epoch: decoder.uint()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockECDSAIdentityProvider;
+    use crate::{
+        identity_authed_request, identity_rotation, quorum_approved_request, share_recovery_backup,
+        share_split_reconstruct, IdentityProvider, SecretShare,
+    };
+    use alloc::vec;
+
+    #[test]
+    fn canonical_cbor_encoding_works() {
+        // Generates identity providers.
+        let identity_provider = MockECDSAIdentityProvider::generate();
+        let other_identity_provider = MockECDSAIdentityProvider::generate();
+        let verified_parties = [identity_provider.verifying_key()];
+
+        // Identity authenticated requests round trip.
+        let request = identity_authed_request::initiate("command", &identity_provider);
+        let encoded = request.to_cbor();
+        let decoded = IdentityAuthedRequestPayload::from_cbor(&encoded).unwrap();
+        assert_eq!(decoded.command, request.command);
+        assert_eq!(decoded.verifying_key, request.verifying_key);
+        assert_eq!(decoded.timestamp, request.timestamp);
+        assert_eq!(decoded.counter, request.counter);
+        assert_eq!(decoded.signature, request.signature);
+        // Encoding is deterministic.
+        assert_eq!(decoded.to_cbor(), encoded);
+        // Decoded requests are still valid.
+        assert!(identity_authed_request::verify(&decoded, &verified_parties).is_ok());
+
+        // Command approvals (with and without delegations) round trip.
+        let delegation = quorum_approved_request::delegate(
+            &identity_provider.verifying_key(),
+            u64::MAX,
+            &verified_parties,
+            &other_identity_provider,
+        );
+        for delegation in [None, Some(delegation)] {
+            let mut approval = quorum_approved_request::verify_request_and_initiate_challenge(
+                "command",
+                &request,
+                &identity_provider,
+                &verified_parties,
+            )
+            .unwrap();
+            approval.delegation = delegation;
+            let encoded = approval.to_cbor();
+            let decoded = CommandApprovalPayload::from_cbor(&encoded).unwrap();
+            assert_eq!(decoded.challenge_fragment, approval.challenge_fragment);
+            assert_eq!(decoded.verifying_key, approval.verifying_key);
+            assert_eq!(decoded.signature, approval.signature);
+            assert_eq!(
+                decoded.delegation.as_ref().map(CanonicalCbor::to_cbor),
+                approval.delegation.as_ref().map(CanonicalCbor::to_cbor)
+            );
+            assert_eq!(decoded.to_cbor(), encoded);
+        }
+
+        // Challenge payloads round trip.
+        let challenge = Random32Bytes::generate();
+        assert_eq!(
+            Random32Bytes::from_cbor(&challenge.to_cbor()),
+            Ok(challenge)
+        );
+        let response = identity_rotation::challenge_response(
+            &[challenge],
+            &identity_provider,
+            &other_identity_provider,
+        );
+        let encoded = response.to_cbor();
+        let decoded = IdentityRotationChallengeResponsePayload::from_cbor(&encoded).unwrap();
+        assert_eq!(decoded.new_verifying_key, response.new_verifying_key);
+        assert_eq!(decoded.current_signature, response.current_signature);
+        assert_eq!(decoded.new_signature, response.new_signature);
+        assert_eq!(decoded.to_cbor(), encoded);
+        let response = QuorumApprovedChallengeResponsePayload {
+            signature: identity_provider.sign(b"challenge"),
+            approving_quorum: vec![
+                identity_provider.verifying_key(),
+                other_identity_provider.verifying_key(),
+            ],
+        };
+        let encoded = response.to_cbor();
+        let decoded = QuorumApprovedChallengeResponsePayload::from_cbor(&encoded).unwrap();
+        assert_eq!(decoded.signature, response.signature);
+        assert_eq!(decoded.approving_quorum, response.approving_quorum);
+        assert_eq!(decoded.to_cbor(), encoded);
+
+        // Encrypted share backups round trip.
+        let (signing_share, sub_share) = share_split_reconstruct::split(
+            &SecretShare::from(Random32Bytes::generate_mod_q()),
+            &identity_provider,
+        )
+        .unwrap();
+        let backup = share_recovery_backup::backup(
+            b"Hello, world!",
+            &signing_share,
+            &sub_share,
+            &identity_provider,
+        )
+        .unwrap();
+        let encoded = backup.to_cbor();
+        let decoded = EncryptedShareBackup::from_cbor(&encoded).unwrap();
+        assert_eq!(decoded.signing_share, backup.signing_share);
+        assert_eq!(decoded.sub_share, backup.sub_share);
+        assert_eq!(decoded.nonce, backup.nonce);
+        assert_eq!(decoded.to_cbor(), encoded);
+    }
+
+    #[test]
+    fn canonical_cbor_decoding_rejects_invalid_encodings() {
+        // Shortest form encodings.
+        for (value, expected) in [
+            (0, vec![0x00]),
+            (23, vec![0x17]),
+            (24, vec![0x18, 0x18]),
+            (256, vec![0x19, 0x01, 0x00]),
+            (65536, vec![0x1a, 0x00, 0x01, 0x00, 0x00]),
+        ] {
+            let mut encoder = Encoder::new();
+            encoder.uint(value);
+            assert_eq!(encoder.finish(), expected);
+        }
+
+        // Non-shortest form integer.
+        assert_eq!(
+            Decoder::new(&[0x18, 0x17]).uint(),
+            Err(CborError::NonCanonical)
+        );
+
+        for (bytes, expected_error) in [
+            // Non-shortest form length.
+            (vec![0x58, 0x01, 0x00], CborError::NonCanonical),
+            // Indefinite length.
+            (vec![0x5f, 0x41, 0x00, 0xff], CborError::NonCanonical),
+            // Wrong major type.
+            (vec![0x00], CborError::InvalidValue),
+            // Truncated input.
+            (vec![0x42, 0x00], CborError::UnexpectedEnd),
+            // Trailing bytes.
+            (vec![0x40, 0x00], CborError::TrailingBytes),
+        ] {
+            let mut decoder = Decoder::new(&bytes);
+            let result = decoder.bytes().and_then(|_| decoder.finish());
+            assert_eq!(result, Err(expected_error));
+        }
+
+        // Payload with a wrong number of fields.
+        let mut encoder = Encoder::new();
+        encoder.array(1);
+        encoder.text("command");
+        assert!(matches!(
+            IdentityAuthedRequestPayload::from_cbor(&encoder.finish()),
+            Err(CborError::InvalidValue)
+        ));
+    }
+}
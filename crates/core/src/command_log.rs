@@ -0,0 +1,244 @@
+//! An append-only, hash-chained log of executed identity authenticated and quorum approved commands.
+//!
+//! Provides a tamper-evident history of wallet governance actions (e.g for compliance audits).
+
+use alloc::vec::Vec;
+use sha2::{Digest, Sha256};
+
+use crate::cbor::{CanonicalCbor, Encoder};
+use crate::errors::CommandLogError;
+use crate::payloads::{CommandApprovalPayload, IdentityAuthedRequestPayload};
+use crate::utils;
+
+/// The outcome of an executed command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CommandOutcome {
+    /// The command was executed successfully.
+    Succeeded,
+    /// The command execution failed.
+    Failed,
+    /// The command was cancelled before execution (e.g during a time lock).
+    Cancelled,
+}
+
+/// A command log entry (i.e an executed command with its approvals and outcome).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CommandLogEntry {
+    /// The sequence number of the entry in the command log (starting at 1).
+    pub sequence: u64,
+    /// The hash of the command log head before the entry.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub previous_hash: [u8; 32],
+    /// The identity authenticated request for the command.
+    pub request: IdentityAuthedRequestPayload,
+    /// The approvals for the command (empty for commands that don't require quorum approval).
+    pub approvals: Vec<CommandApprovalPayload>,
+    /// The outcome of the command.
+    pub outcome: CommandOutcome,
+}
+
+impl CommandLogEntry {
+    /// Returns the hash of the entry (i.e the command log head after the entry).
+    pub fn hash(&self) -> [u8; 32] {
+        // Hashes the canonical CBOR encoding of the full request and approvals
+        // (i.e including request counters and the algorithm, curve, hash and encoding identifiers of keys and signatures).
+        let mut approvals = Encoder::new();
+        approvals.seq(&self.approvals);
+        Sha256::new()
+            .chain_update(b"command-log-entry")
+            .chain_update(self.sequence.to_be_bytes())
+            .chain_update(self.previous_hash)
+            .chain_update(utils::length_prefix_bytes(&self.request.to_cbor()))
+            .chain_update(utils::length_prefix_bytes(&approvals.finish()))
+            .chain_update([self.outcome as u8])
+            .finalize()
+            .into()
+    }
+}
+
+/// An append-only, hash-chained command log.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CommandLog {
+    /// Chained command log entries.
+    entries: Vec<CommandLogEntry>,
+}
+
+impl CommandLog {
+    /// Returns an empty command log.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Given a list of command log entries,
+    /// returns an `Ok` result with a command log if all entries form a valid chain, or an appropriate `Err` result otherwise.
+    pub fn from_entries(entries: Vec<CommandLogEntry>) -> Result<Self, CommandLogError> {
+        let command_log = Self { entries };
+        command_log.verify()?;
+        Ok(command_log)
+    }
+
+    /// Returns the command log entries.
+    pub fn entries(&self) -> &[CommandLogEntry] {
+        &self.entries
+    }
+
+    /// Returns the number of entries in the command log.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the command log has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the hash of the command log head.
+    pub fn head_hash(&self) -> [u8; 32] {
+        self.entries
+            .last()
+            .map_or_else(genesis_hash, CommandLogEntry::hash)
+    }
+
+    /// Given an identity authenticated request, its approvals (if any) and the outcome of the command,
+    /// appends an entry to the command log and returns the new head hash.
+    pub fn append(
+        &mut self,
+        request: IdentityAuthedRequestPayload,
+        approvals: Vec<CommandApprovalPayload>,
+        outcome: CommandOutcome,
+    ) -> [u8; 32] {
+        let entry = CommandLogEntry {
+            sequence: self.entries.len() as u64 + 1,
+            previous_hash: self.head_hash(),
+            request,
+            approvals,
+            outcome,
+        };
+        let head_hash = entry.hash();
+        self.entries.push(entry);
+        head_hash
+    }
+
+    /// Returns an `Ok` result if all entries form a valid chain, or an appropriate `Err` result otherwise.
+    pub fn verify(&self) -> Result<(), CommandLogError> {
+        self.entries
+            .iter()
+            .enumerate()
+            .try_fold(genesis_hash(), |previous_hash, (idx, entry)| {
+                // Each entry must extend the previous entry.
+                if entry.sequence == idx as u64 + 1 && entry.previous_hash == previous_hash {
+                    Ok(entry.hash())
+                } else {
+                    Err(CommandLogError::InvalidChain)
+                }
+            })
+            .map(|_| ())
+    }
+
+    /// Given an expected head hash (e.g from a trusted checkpoint),
+    /// returns an `Ok` result if all entries form a valid chain that ends at the expected head, or an appropriate `Err` result otherwise.
+    pub fn verify_head(&self, expected_head_hash: &[u8; 32]) -> Result<(), CommandLogError> {
+        self.verify()?;
+        if &self.head_hash() == expected_head_hash {
+            Ok(())
+        } else {
+            Err(CommandLogError::HeadMismatch)
+        }
+    }
+}
+
+/// Returns the hash of an empty command log.
+fn genesis_hash() -> [u8; 32] {
+    Sha256::digest(b"command-log-genesis").into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::MessageDigest;
+    use crate::quorum_approved_request;
+    use crate::test_utils::MockECDSAIdentityProvider;
+    use crate::{identity_authed_request, IdentityProvider};
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    #[test]
+    fn command_log_works() {
+        // Generates identity providers.
+        let initiator = MockECDSAIdentityProvider::generate();
+        let approver = MockECDSAIdentityProvider::generate();
+
+        // Records an identity authenticated command and a quorum approved command.
+        let mut command_log = CommandLog::new();
+        let empty_head_hash = command_log.head_hash();
+        command_log.append(
+            identity_authed_request::initiate("identity-rotation", &initiator),
+            Vec::new(),
+            CommandOutcome::Succeeded,
+        );
+        let request = quorum_approved_request::initiate("share-removal", &initiator);
+        let approval = quorum_approved_request::verify_request_and_initiate_challenge(
+            "share-removal",
+            &request,
+            &approver,
+            &[initiator.verifying_key()],
+        )
+        .unwrap();
+        let head_hash = command_log.append(request, vec![approval], CommandOutcome::Failed);
+        assert_eq!(command_log.len(), 2);
+        assert_ne!(head_hash, empty_head_hash);
+        assert_eq!(command_log.head_hash(), head_hash);
+        assert_eq!(command_log.verify_head(&head_hash), Ok(()));
+
+        // Auditors can verify the command log from its entries.
+        let replayed_command_log =
+            CommandLog::from_entries(command_log.entries().to_vec()).unwrap();
+        assert_eq!(replayed_command_log.head_hash(), head_hash);
+
+        // Tampered entries break the chain.
+        let mut entries = command_log.entries().to_vec();
+        entries[0].outcome = CommandOutcome::Failed;
+        assert!(matches!(
+            CommandLog::from_entries(entries),
+            Err(CommandLogError::InvalidChain)
+        ));
+        let mut entries = command_log.entries().to_vec();
+        entries[0].request.command = "share-removal".to_string();
+        assert!(matches!(
+            CommandLog::from_entries(entries),
+            Err(CommandLogError::InvalidChain)
+        ));
+        let mut entries = command_log.entries().to_vec();
+        entries[0].request.counter = Some(1);
+        assert!(matches!(
+            CommandLog::from_entries(entries),
+            Err(CommandLogError::InvalidChain)
+        ));
+        let mut entries = command_log.entries().to_vec();
+        entries[1].approvals[0].signature.hash = MessageDigest::SHA512;
+        assert_eq!(
+            CommandLog::from_entries(entries)
+                .unwrap()
+                .verify_head(&head_hash),
+            Err(CommandLogError::HeadMismatch)
+        );
+
+        // Removed entries break the chain.
+        let entries = command_log.entries()[1..].to_vec();
+        assert!(matches!(
+            CommandLog::from_entries(entries),
+            Err(CommandLogError::InvalidChain)
+        ));
+
+        // Truncated logs don't match the expected head.
+        let truncated_command_log =
+            CommandLog::from_entries(command_log.entries()[..1].to_vec()).unwrap();
+        assert_eq!(
+            truncated_command_log.verify_head(&head_hash),
+            Err(CommandLogError::HeadMismatch)
+        );
+    }
+}
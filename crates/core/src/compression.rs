@@ -0,0 +1,223 @@
+//! Message body compression (negotiated per session).
+//!
+//! Protocol rounds (e.g CGGMP rounds with zero-knowledge proofs) can carry large message bodies,
+//! so parties can agree on a compression algorithm for a session to cut bandwidth (e.g for mobile parties on metered connections).
+//!
+//! Each party advertises the compression algorithms it supports (see [`Compression::supported`]),
+//! and all parties pick the same algorithm for the session (see [`negotiate`]).
+//! Compressed bodies are tagged with the algorithm used in their envelope,
+//! so small or incompressible bodies can still be sent uncompressed.
+//!
+//! **NOTE:** DEFLATE and Zstandard are only enabled with the `deflate` and `zstd` features respectively.
+
+use crate::errors::CompressionError;
+use alloc::vec::Vec;
+
+/// The maximum size (in bytes) of a decompressed message body (i.e 16 MiB).
+pub const MAX_DECOMPRESSED_LEN: usize = 16 * 1024 * 1024;
+
+/// The minimum size (in bytes) of a message body that's worth compressing.
+pub const MIN_COMPRESSIBLE_LEN: usize = 256;
+
+/// A compression algorithm for message bodies (in ascending order of preference).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Compression {
+    /// No compression.
+    #[default]
+    None,
+    /// DEFLATE (RFC 1951).
+    Deflate,
+    /// Zstandard (RFC 8878).
+    Zstd,
+}
+
+impl Compression {
+    /// Returns the compression algorithms enabled for this implementation (in ascending order of preference).
+    pub fn supported() -> Vec<Self> {
+        [Self::None, Self::Deflate, Self::Zstd]
+            .into_iter()
+            .filter(Self::is_supported)
+            .collect()
+    }
+
+    /// Returns true if the compression algorithm is enabled for this implementation.
+    pub fn is_supported(&self) -> bool {
+        match self {
+            Self::None => true,
+            Self::Deflate => cfg!(feature = "deflate"),
+            Self::Zstd => cfg!(feature = "zstd"),
+        }
+    }
+
+    /// Returns the wire identifier of the compression algorithm.
+    pub fn id(&self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Deflate => 1,
+            Self::Zstd => 2,
+        }
+    }
+
+    /// Returns the compression algorithm for a wire identifier (if any).
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Self::None),
+            1 => Some(Self::Deflate),
+            2 => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+}
+
+/// Given the compression algorithms supported by each party,
+/// returns the most preferred compression algorithm supported by all parties (or no compression if there's none).
+///
+/// **NOTE:** All parties compute the same compression algorithm given the same offers.
+pub fn negotiate(offers: &[Vec<Compression>]) -> Compression {
+    [Compression::Zstd, Compression::Deflate]
+        .into_iter()
+        .find(|compression| offers.iter().all(|offer| offer.contains(compression)))
+        .unwrap_or(Compression::None)
+}
+
+/// Given the negotiated compression algorithm and a message body,
+/// returns the compression algorithm actually used and the (possibly) compressed body.
+///
+/// Bodies that are smaller than [`MIN_COMPRESSIBLE_LEN`] or don't shrink are returned uncompressed (i.e with [`Compression::None`]).
+pub fn compress(compression: Compression, body: &[u8]) -> (Compression, Vec<u8>) {
+    if body.len() >= MIN_COMPRESSIBLE_LEN {
+        if let Some(compressed) = encode(compression, body) {
+            if compressed.len() < body.len() {
+                return (compression, compressed);
+            }
+        }
+    }
+    (Compression::None, body.to_vec())
+}
+
+/// Given the compression algorithm used (e.g from the envelope) and a (possibly) compressed message body,
+/// returns the decompressed body or an appropriate error
+/// (i.e for algorithms that aren't enabled, malformed bodies and bodies larger than [`MAX_DECOMPRESSED_LEN`]).
+pub fn decompress(compression: Compression, body: &[u8]) -> Result<Vec<u8>, CompressionError> {
+    match compression {
+        Compression::None => Ok(body.to_vec()),
+        #[cfg(feature = "deflate")]
+        Compression::Deflate => read_bounded(flate2::read::DeflateDecoder::new(body)),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => read_bounded(
+            zstd::stream::read::Decoder::new(body).map_err(|_| CompressionError::Malformed)?,
+        ),
+        #[allow(unreachable_patterns)]
+        _ => Err(CompressionError::Unsupported),
+    }
+}
+
+/// Returns the compressed body (if the compression algorithm is enabled).
+#[cfg_attr(
+    not(any(feature = "deflate", feature = "zstd")),
+    allow(unused_variables)
+)]
+fn encode(compression: Compression, body: &[u8]) -> Option<Vec<u8>> {
+    match compression {
+        #[cfg(feature = "deflate")]
+        Compression::Deflate => {
+            use std::io::Write;
+            let mut encoder =
+                flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(body).ok()?;
+            encoder.finish().ok()
+        }
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => zstd::stream::encode_all(body, zstd::DEFAULT_COMPRESSION_LEVEL).ok(),
+        _ => None,
+    }
+}
+
+/// Reads a decompressed body of at most [`MAX_DECOMPRESSED_LEN`] bytes (i.e guards against decompression bombs).
+#[cfg(any(feature = "deflate", feature = "zstd"))]
+fn read_bounded(decoder: impl std::io::Read) -> Result<Vec<u8>, CompressionError> {
+    use std::io::Read;
+    let mut decompressed = Vec::new();
+    decoder
+        .take(MAX_DECOMPRESSED_LEN as u64 + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|_| CompressionError::Malformed)?;
+    if decompressed.len() > MAX_DECOMPRESSED_LEN {
+        return Err(CompressionError::TooLarge);
+    }
+    Ok(decompressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn compression_works() {
+        // Negotiates the most preferred algorithm supported by all parties.
+        let offers = vec![
+            vec![Compression::None, Compression::Deflate, Compression::Zstd],
+            vec![Compression::None, Compression::Deflate],
+        ];
+        assert_eq!(negotiate(&offers), Compression::Deflate);
+        assert_eq!(
+            negotiate(&[offers[0].clone(), vec![Compression::None]]),
+            Compression::None
+        );
+        assert!(negotiate(&[Compression::supported()]).is_supported());
+
+        // Wire identifiers round trip.
+        for compression in [Compression::None, Compression::Deflate, Compression::Zstd] {
+            assert_eq!(Compression::from_id(compression.id()), Some(compression));
+        }
+        assert_eq!(Compression::from_id(3), None);
+
+        // Small bodies are never compressed.
+        let small = vec![1u8; MIN_COMPRESSIBLE_LEN - 1];
+        for compression in Compression::supported() {
+            assert_eq!(
+                compress(compression, &small),
+                (Compression::None, small.clone())
+            );
+        }
+
+        // Compressible bodies round trip with all enabled algorithms.
+        let body: Vec<u8> = (0..4096u32).map(|it| (it % 7) as u8).collect();
+        for compression in Compression::supported() {
+            let (used, compressed) = compress(compression, &body);
+            assert_eq!(used, compression);
+            if compression != Compression::None {
+                assert!(compressed.len() < body.len());
+            }
+            assert_eq!(decompress(used, &compressed), Ok(body.clone()));
+        }
+
+        // Algorithms that aren't enabled are rejected.
+        for compression in [Compression::Deflate, Compression::Zstd] {
+            if !compression.is_supported() {
+                assert_eq!(compress(compression, &body).0, Compression::None);
+                assert_eq!(
+                    decompress(compression, &body),
+                    Err(CompressionError::Unsupported)
+                );
+            }
+        }
+
+        // Malformed bodies and decompression bombs are rejected.
+        #[cfg(feature = "deflate")]
+        {
+            assert_eq!(
+                decompress(Compression::Deflate, &[0xff; 16]),
+                Err(CompressionError::Malformed)
+            );
+            let (_, bomb) = compress(Compression::Deflate, &vec![0u8; MAX_DECOMPRESSED_LEN + 1]);
+            assert_eq!(
+                decompress(Compression::Deflate, &bomb),
+                Err(CompressionError::TooLarge)
+            );
+        }
+    }
+}
@@ -1,8 +1,10 @@
 //! Types, abstractions and utilities for lower-level cryptography.
 
+use alloc::vec::Vec;
+use core::fmt;
 use crypto_bigint::modular::constant_mod::ResidueParams;
 use crypto_bigint::{impl_modulus, Encoding, NonZero, Random, RandomMod, U256};
-use std::fmt;
+use rand::rngs::OsRng;
 use zeroize::Zeroize;
 
 use crate::errors::{CryptoError, Error};
@@ -16,25 +18,45 @@ impl_modulus!(
     "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141"
 );
 
+// Order of the prime order subgroup of `Curve25519` (i.e the `Ed25519` and `Ristretto255` scalar field)
+// as a `crypto-bigint` modulus type.
+// Ref: <https://datatracker.ietf.org/doc/html/rfc8032#section-5.1>.
+// Ref: <https://ristretto.group/>.
+impl_modulus!(
+    Ed25519Order,
+    U256,
+    "1000000000000000000000000000000014DEF9DEA2F79CD65812631A5CF5D3ED"
+);
+
+/// A prime curve order (i.e the modulus for "secret share" and "sub-share" arithmetic).
+///
+/// **NOTE:** Implemented for all 256 bit `crypto-bigint` modulus types (e.g [`Secp256k1Order`] and [`Ed25519Order`]).
+pub trait CurveOrder: ResidueParams<{ U256::LIMBS }> {}
+
+impl<T: ResidueParams<{ U256::LIMBS }>> CurveOrder for T {}
+
 /// A convenience wrapper for generating and encoding/decoding cryptographically secure random values.
 // No `ZeroizeOnDrop` because we want `Random32Bytes` to be `Copy` like `U256`.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Zeroize)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Random32Bytes(U256);
 
 impl Random32Bytes {
     /// Generates a cryptographically secure random value.
     pub fn generate() -> Self {
-        let mut rng = rand::thread_rng();
-        Self(U256::random(&mut rng))
+        Self(U256::random(&mut OsRng))
     }
 
     /// Generates a cryptographically secure random value which is less than the order of the `Secp256k1` elliptic curve.
     pub fn generate_mod_q() -> Self {
-        let mut rng = rand::thread_rng();
+        Self::generate_mod_order::<Secp256k1Order>()
+    }
 
-        // The order of the `Secp256k1` curve should be non-zero.
-        let modulus = NonZero::new(Secp256k1Order::MODULUS).unwrap();
-        Self(U256::random_mod(&mut rng, &modulus))
+    /// Generates a cryptographically secure random value which is less than the given curve order.
+    pub fn generate_mod_order<Q: CurveOrder>() -> Self {
+        // Curve orders should be non-zero.
+        let modulus = NonZero::new(Q::MODULUS).unwrap();
+        Self(U256::random_mod(&mut OsRng, &modulus))
     }
 
     /// Returns the underlying `U256` random value.
@@ -125,6 +147,38 @@ pub fn verify_signature(
                     _ => Err(CryptoError::UnsupportedDigest),
                 }
             }
+            // Verifies EdDSA/Curve25519 (i.e Ed25519) signatures.
+            // RFC 8032 encoded verifying key and signature (Ed25519 always uses SHA-512).
+            (SignatureAlgorithm::EdDSA, EllipticCurve::Curve25519) => {
+                // Matches the message digest/hash function.
+                match signature.hash {
+                    // Verifies Ed25519 signatures.
+                    MessageDigest::SHA512 => {
+                        // Matches verifying key and signature encoding.
+                        match (verifying_key.enc, signature.enc) {
+                            // Verifies RFC 8032 encoded Ed25519 signatures with RFC 8032 encoded verifying key.
+                            (KeyEncoding::RFC8032, SignatureEncoding::RFC8032) => {
+                                // Deserialize verifying key.
+                                let ver_key = <[u8; 32]>::try_from(verifying_key.key.as_slice())
+                                    .ok()
+                                    .and_then(|bytes| {
+                                        ed25519_dalek::VerifyingKey::from_bytes(&bytes).ok()
+                                    })
+                                    .ok_or(CryptoError::InvalidVerifyingKey)?;
+                                // Deserialize signature.
+                                let sig = ed25519_dalek::Signature::from_slice(&signature.sig)
+                                    .map_err(|_| CryptoError::InvalidSignature)?;
+                                // Verify Ed25519 signature.
+                                ver_key
+                                    .verify_strict(msg, &sig)
+                                    .map_err(|_| CryptoError::InvalidSignature)
+                            }
+                            _ => Err(CryptoError::UnsupportedEncoding),
+                        }
+                    }
+                    _ => Err(CryptoError::UnsupportedDigest),
+                }
+            }
             _ => Err(CryptoError::UnsupportedScheme),
         }
     }
@@ -132,8 +186,10 @@ pub fn verify_signature(
 
 /// A verifying key (e.g an ECDSA/secp256k1 public key).
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VerifyingKey {
     /// The verifying key as a sequence of bytes.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub key: Vec<u8>,
     /// The signature algorithm.
     pub algo: SignatureAlgorithm,
@@ -145,8 +201,10 @@ pub struct VerifyingKey {
 
 /// A signature (e.g a ECDSA/secp256k1/SHA-256 signature).
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Signature {
     /// The signature as a sequence of bytes.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub sig: Vec<u8>,
     /// The signature algorithm.
     pub algo: SignatureAlgorithm,
@@ -160,6 +218,7 @@ pub struct Signature {
 
 /// A signature algorithm.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub enum SignatureAlgorithm {
     /// Ref: <https://en.wikipedia.org/wiki/Elliptic_Curve_Digital_Signature_Algorithm>.
@@ -170,6 +229,7 @@ pub enum SignatureAlgorithm {
 
 /// An elliptic curve.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum EllipticCurve {
     /// Ref: <https://www.secg.org/sec2-v2.pdf>.
     Secp256k1,
@@ -179,27 +239,36 @@ pub enum EllipticCurve {
 
 /// A cryptographic message digest/hash function.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MessageDigest {
     /// Ref: <https://en.wikipedia.org/wiki/SHA-2>.
     SHA256,
+    /// Ref: <https://en.wikipedia.org/wiki/SHA-2>.
+    SHA512,
     /// Ref: <https://en.wikipedia.org/wiki/SHA-3>.
     Keccak256,
 }
 
 /// A key encoding format.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum KeyEncoding {
     /// Ref: <https://www.secg.org/sec1-v2.pdf>.
     SEC1,
     /// Ref: <https://eips.ethereum.org/EIPS/eip-55>.
     EIP55,
+    /// Ref: <https://www.rfc-editor.org/rfc/rfc8032#section-5.1.2>.
+    RFC8032,
 }
 
 /// A signature encoding format.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SignatureEncoding {
     /// Ref: <https://en.wikipedia.org/wiki/X.690#DER_encoding>.
     DER,
     /// Ref: <https://ethereum.org/en/developers/docs/data-structures-and-encoding/rlp/>.
     RLP,
+    /// Ref: <https://www.rfc-editor.org/rfc/rfc8032#section-5.1.6>.
+    RFC8032,
 }
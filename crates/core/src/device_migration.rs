@@ -0,0 +1,511 @@
+//! Device migration (i.e moving a party's share and full party state to a new device without quorum recovery).
+//!
+//! Protocol:
+//! 1. The new device generates an ephemeral key and a signed request (see [`initiate`]),
+//!    which is transferred to the old device (e.g as a `wamu:` URI in a QR code, see [`crate::uri`]).
+//! 2. The old device verifies the request and challenges the new device to prove control of the party's identity
+//!    (see [`verify_request_and_initiate_challenge`] and [`challenge_response`]).
+//! 3. The old device encrypts its full party state (i.e [`StoredKey`]) to the ephemeral key of the new device
+//!    (see [`transfer`]), and the package is transferred to the new device (e.g over the network).
+//! 4. The new device decrypts and stores the party state and acknowledges receipt (see [`receive`] and [`acknowledge`]).
+//! 5. The old device verifies the acknowledgement, wipes its party state and returns a signed wipe attestation
+//!    (see [`verify_acknowledgement`] and [`wipe`]).
+//!
+//! The party state is encrypted with [AES-GCM](https://en.wikipedia.org/wiki/Galois/Counter_Mode)
+//! under a key derived (with [HKDF](https://tools.ietf.org/html/rfc5869)) from an ephemeral secp256k1 ECDH shared secret,
+//! so only the new device can decrypt it (even if the package is transferred over an untrusted channel).
+//!
+//! **NOTE:** The new device must control the same identity as the old device (i.e the "signing share" and "sub-share"
+//! can only be reconstructed by the identity that split them), parties that want a new identity should
+//! [rotate their identity](crate::identity_rotation) after migrating.
+//! A wipe attestation only proves that the old device deleted the party state from its key store,
+//! it can't prove that no other copies exist (e.g in OS backups).
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use k256::ecdh::EphemeralSecret;
+use k256::PublicKey;
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::canonical::{
+    CanonicalEncode, DeviceMigrationPackageMessage, DeviceMigrationReceiptMessage,
+    DeviceMigrationRequestMessage,
+};
+use crate::cbor::CanonicalCbor;
+use crate::crypto::{Random32Bytes, Signature, VerifyingKey};
+use crate::errors::{CryptoError, DeviceMigrationError};
+use crate::identity_authed_request::{EXPIRY_TIMEOUT, FUTURE_TIMESTAMP_TOLERANCE};
+use crate::key_store::{KeyStore, StoredKey};
+use crate::payloads::{DeviceMigrationPackage, DeviceMigrationRequestPayload};
+use crate::traits::IdentityProvider;
+use crate::versioned::Versioned;
+use crate::{crypto, identity_challenge, utils};
+
+/// The HKDF info prefix for deriving the encryption key of a device migration package.
+const DEVICE_MIGRATION_KDF_INFO: &[u8] = b"wamu-device-migration";
+
+/// The length of an AES-GCM nonce.
+const NONCE_LEN: usize = 12;
+
+/// The ephemeral secret of the new device (i.e for decrypting the device migration package).
+///
+/// **NOTE:** The ephemeral secret is never persisted, so the migration must be restarted if the new device restarts.
+pub struct DeviceMigrationSecret {
+    /// The ephemeral ECDH secret.
+    secret: EphemeralSecret,
+}
+
+/// Given a wallet identifier and the identity provider of the new device,
+/// returns the device migration request payload and the ephemeral secret of the new device.
+///
+/// **NOTE:** This is meant to be called on the new device.
+pub fn initiate(
+    wallet_id: &[u8; 32],
+    identity_provider: &impl IdentityProvider,
+) -> (DeviceMigrationRequestPayload, DeviceMigrationSecret) {
+    let secret = EphemeralSecret::random(&mut OsRng);
+    let ephemeral_key = secret.public_key().to_sec1_bytes().to_vec();
+    let timestamp = utils::unix_timestamp();
+    let signature = identity_provider.sign(
+        &DeviceMigrationRequestMessage {
+            wallet_id,
+            ephemeral_key: &ephemeral_key,
+            timestamp,
+        }
+        .message_bytes(),
+    );
+    (
+        DeviceMigrationRequestPayload {
+            wallet_id: *wallet_id,
+            ephemeral_key,
+            timestamp,
+            verifying_key: identity_provider.verifying_key(),
+            signature,
+        },
+        DeviceMigrationSecret { secret },
+    )
+}
+
+/// Given a device migration request payload, a wallet identifier and the identity provider of the old device,
+/// returns an ok result with a challenge fragment for initiating an identity challenge for a valid request
+/// or an appropriate error result for an invalid request.
+///
+/// **NOTE:** This is meant to be called on the old device.
+pub fn verify_request_and_initiate_challenge(
+    request: &DeviceMigrationRequestPayload,
+    wallet_id: &[u8; 32],
+    identity_provider: &impl IdentityProvider,
+) -> Result<Random32Bytes, DeviceMigrationError> {
+    verify_request(request, wallet_id, &identity_provider.verifying_key())?;
+    Ok(identity_challenge::initiate())
+}
+
+/// Given a list of identity challenge fragments and the identity provider of the new device,
+/// returns the challenge response signature.
+///
+/// **NOTE:** This is meant to be called on the new device.
+pub fn challenge_response(
+    challenge_fragments: &[Random32Bytes],
+    identity_provider: &impl IdentityProvider,
+) -> Signature {
+    identity_challenge::respond(challenge_fragments, identity_provider)
+}
+
+/// Given a device migration request payload, a list of identity challenge fragments, the challenge response signature,
+/// the party state and the identity provider of the old device,
+/// returns the device migration package (i.e the party state encrypted to the ephemeral key of the new device)
+/// or an appropriate error for an invalid request or challenge response.
+///
+/// **NOTE:** This is meant to be called on the old device.
+pub fn transfer(
+    request: &DeviceMigrationRequestPayload,
+    challenge_fragments: &[Random32Bytes],
+    challenge_response: &Signature,
+    key: &StoredKey,
+    identity_provider: &impl IdentityProvider,
+) -> Result<DeviceMigrationPackage, DeviceMigrationError> {
+    // Verifies the request and the challenge response of the new device.
+    let verifying_key = identity_provider.verifying_key();
+    let request_ephemeral_key = verify_request(request, &request.wallet_id, &verifying_key)?;
+    identity_challenge::verify(challenge_response, challenge_fragments, &verifying_key)?;
+
+    // Encrypts the versioned encoding of the party state.
+    let secret = EphemeralSecret::random(&mut OsRng);
+    let ephemeral_key = secret.public_key().to_sec1_bytes().to_vec();
+    let cipher = generate_encryption_cipher(
+        &secret,
+        &request_ephemeral_key,
+        &request.wallet_id,
+        &request.ephemeral_key,
+        &ephemeral_key,
+    );
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: &Zeroizing::new(key.to_versioned()),
+                aad: &request.wallet_id,
+            },
+        )
+        .map_err(|_| DeviceMigrationError::Decryption)?;
+
+    // Signs the package.
+    let signature = identity_provider.sign(
+        &DeviceMigrationPackageMessage {
+            wallet_id: &request.wallet_id,
+            request_ephemeral_key: &request.ephemeral_key,
+            ephemeral_key: &ephemeral_key,
+            nonce: &nonce,
+            ciphertext: &ciphertext,
+        }
+        .message_bytes(),
+    );
+    Ok(DeviceMigrationPackage {
+        wallet_id: request.wallet_id,
+        ephemeral_key,
+        nonce: nonce.to_vec(),
+        ciphertext,
+        verifying_key,
+        signature,
+    })
+}
+
+/// Given a device migration package, the device migration request payload, the ephemeral secret
+/// and the identity provider of the new device, returns the decrypted party state or an appropriate error.
+///
+/// **NOTE:** This is meant to be called on the new device.
+pub fn receive(
+    package: &DeviceMigrationPackage,
+    request: &DeviceMigrationRequestPayload,
+    secret: &DeviceMigrationSecret,
+    identity_provider: &impl IdentityProvider,
+) -> Result<StoredKey, DeviceMigrationError> {
+    if package.wallet_id != request.wallet_id {
+        // Package must be for the requested wallet.
+        return Err(DeviceMigrationError::WalletMismatch);
+    } else if package.verifying_key != identity_provider.verifying_key() {
+        // Package must be from the same identity.
+        return Err(DeviceMigrationError::IdentityMismatch);
+    }
+
+    // Verifies the package signature.
+    crypto::verify_signature(
+        &package.verifying_key,
+        &DeviceMigrationPackageMessage {
+            wallet_id: &package.wallet_id,
+            request_ephemeral_key: &request.ephemeral_key,
+            ephemeral_key: &package.ephemeral_key,
+            nonce: &package.nonce,
+            ciphertext: &package.ciphertext,
+        }
+        .message_bytes(),
+        &package.signature,
+    )?;
+
+    // Decrypts and decodes the party state.
+    if package.nonce.len() != NONCE_LEN {
+        return Err(DeviceMigrationError::Decryption);
+    }
+    let cipher = generate_encryption_cipher(
+        &secret.secret,
+        &decode_ephemeral_key(&package.ephemeral_key)?,
+        &package.wallet_id,
+        &request.ephemeral_key,
+        &package.ephemeral_key,
+    );
+    let plaintext = cipher
+        .decrypt(
+            Nonce::from_slice(&package.nonce),
+            Payload {
+                msg: &package.ciphertext,
+                aad: &package.wallet_id,
+            },
+        )
+        .map(Zeroizing::new)
+        .map_err(|_| DeviceMigrationError::Decryption)?;
+    Ok(StoredKey::from_versioned(&plaintext)?)
+}
+
+/// Given a device migration package and the identity provider of the new device,
+/// returns the acknowledgement signature (i.e confirming that the party state was received and stored).
+///
+/// **NOTE:** This is meant to be called on the new device (after storing the party state).
+pub fn acknowledge(
+    package: &DeviceMigrationPackage,
+    identity_provider: &impl IdentityProvider,
+) -> Signature {
+    sign_receipt("ack", package, identity_provider)
+}
+
+/// Given an acknowledgement signature, the device migration package and the verifying key of the migrating party,
+/// returns an `Ok` result for a valid acknowledgement, or an appropriate `Err` result otherwise.
+///
+/// **NOTE:** This is meant to be called on the old device.
+pub fn verify_acknowledgement(
+    signature: &Signature,
+    package: &DeviceMigrationPackage,
+    verifying_key: &VerifyingKey,
+) -> Result<(), DeviceMigrationError> {
+    Ok(verify_receipt("ack", signature, package, verifying_key)?)
+}
+
+/// Given a device migration package, the key store and identity provider of the old device,
+/// deletes the party state from the key store and returns the wipe attestation signature,
+/// or the key store error.
+///
+/// **NOTE:** This is meant to be called on the old device (only after verifying the acknowledgement of the new device).
+pub fn wipe<K: KeyStore>(
+    package: &DeviceMigrationPackage,
+    key_store: &mut K,
+    identity_provider: &impl IdentityProvider,
+) -> Result<Signature, K::Error> {
+    key_store.delete(&package.wallet_id)?;
+    Ok(sign_receipt("wipe", package, identity_provider))
+}
+
+/// Given a wipe attestation signature, the device migration package and the verifying key of the migrating party,
+/// returns an `Ok` result for a valid wipe attestation, or an appropriate `Err` result otherwise.
+///
+/// **NOTE:** This is meant to be called on the new device.
+pub fn verify_wipe(
+    signature: &Signature,
+    package: &DeviceMigrationPackage,
+    verifying_key: &VerifyingKey,
+) -> Result<(), DeviceMigrationError> {
+    Ok(verify_receipt("wipe", signature, package, verifying_key)?)
+}
+
+/// Verifies a device migration request and returns the ephemeral public key of the new device.
+fn verify_request(
+    request: &DeviceMigrationRequestPayload,
+    wallet_id: &[u8; 32],
+    verifying_key: &VerifyingKey,
+) -> Result<PublicKey, DeviceMigrationError> {
+    let now = utils::unix_timestamp();
+    if &request.wallet_id != wallet_id {
+        // Request must be for the wallet.
+        Err(DeviceMigrationError::WalletMismatch)
+    } else if &request.verifying_key != verifying_key {
+        // Request must be from the same identity.
+        Err(DeviceMigrationError::IdentityMismatch)
+    } else if request.timestamp + EXPIRY_TIMEOUT < now {
+        // Request should be recent.
+        Err(DeviceMigrationError::Expired)
+    } else if now + FUTURE_TIMESTAMP_TOLERANCE < request.timestamp {
+        // Request can't be too far into the future.
+        Err(DeviceMigrationError::InvalidTimestamp)
+    } else {
+        // Request signature must be valid.
+        crypto::verify_signature(
+            &request.verifying_key,
+            &DeviceMigrationRequestMessage {
+                wallet_id,
+                ephemeral_key: &request.ephemeral_key,
+                timestamp: request.timestamp,
+            }
+            .message_bytes(),
+            &request.signature,
+        )?;
+        decode_ephemeral_key(&request.ephemeral_key)
+    }
+}
+
+/// Decodes a SEC1 encoded secp256k1 ephemeral public key.
+fn decode_ephemeral_key(ephemeral_key: &[u8]) -> Result<PublicKey, DeviceMigrationError> {
+    PublicKey::from_sec1_bytes(ephemeral_key).map_err(|_| DeviceMigrationError::InvalidEphemeralKey)
+}
+
+/// Returns an AES-GCM cipher with a key derived from the ECDH shared secret,
+/// bound to the wallet identifier and both ephemeral keys (i.e `info = "wamu-device-migration" || wallet_id || lp(request ephemeral key) || lp(package ephemeral key)`).
+fn generate_encryption_cipher(
+    secret: &EphemeralSecret,
+    public_key: &PublicKey,
+    wallet_id: &[u8; 32],
+    request_ephemeral_key: &[u8],
+    package_ephemeral_key: &[u8],
+) -> Aes256Gcm {
+    let info = [
+        DEVICE_MIGRATION_KDF_INFO,
+        wallet_id,
+        &utils::length_prefix_bytes(request_ephemeral_key),
+        &utils::length_prefix_bytes(package_ephemeral_key),
+    ]
+    .concat();
+    let mut key = Zeroizing::new([0u8; 32]);
+    secret
+        .diffie_hellman(public_key)
+        .extract::<Sha256>(None)
+        .expand(&info, key.as_mut())
+        .expect("32 is a valid length for Sha256 to output");
+    Aes256Gcm::new(key.as_ref().into())
+}
+
+/// Returns the hash of the canonical CBOR encoding of a device migration package.
+fn package_hash(package: &DeviceMigrationPackage) -> [u8; 32] {
+    Sha256::digest(package.to_cbor()).into()
+}
+
+/// Signs a device migration receipt (i.e an acknowledgement or a wipe attestation).
+fn sign_receipt(
+    stage: &str,
+    package: &DeviceMigrationPackage,
+    identity_provider: &impl IdentityProvider,
+) -> Signature {
+    identity_provider.sign(
+        &DeviceMigrationReceiptMessage {
+            stage,
+            wallet_id: &package.wallet_id,
+            package_hash: &package_hash(package),
+        }
+        .message_bytes(),
+    )
+}
+
+/// Verifies a device migration receipt (i.e an acknowledgement or a wipe attestation).
+fn verify_receipt(
+    stage: &str,
+    signature: &Signature,
+    package: &DeviceMigrationPackage,
+    verifying_key: &VerifyingKey,
+) -> Result<(), CryptoError> {
+    crypto::verify_signature(
+        verifying_key,
+        &DeviceMigrationReceiptMessage {
+            stage,
+            wallet_id: &package.wallet_id,
+            package_hash: &package_hash(package),
+        }
+        .message_bytes(),
+        signature,
+    )
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::errors::Error;
+    use crate::key_store::FileKeyStore;
+    use crate::share::SecretShare;
+    use crate::share_split_reconstruct;
+    use crate::test_utils::MockECDSAIdentityProvider;
+    use alloc::format;
+    use alloc::vec;
+
+    #[test]
+    fn device_migration_works() {
+        // Generates identity providers, a wallet identifier and the party state on the old device.
+        let identity_provider = MockECDSAIdentityProvider::generate();
+        let other_identity_provider = MockECDSAIdentityProvider::generate();
+        let wallet_id = [1u8; 32];
+        let secret_share = SecretShare::from(Random32Bytes::generate_mod_q());
+        let (signing_share, sub_share) =
+            share_split_reconstruct::split(&secret_share, &identity_provider).unwrap();
+        let key = StoredKey {
+            signing_share,
+            sub_share,
+            local_key: b"local key".to_vec(),
+            roster: vec![
+                identity_provider.verifying_key(),
+                other_identity_provider.verifying_key(),
+            ],
+            epoch: 3,
+        };
+        let dir = std::env::temp_dir().join(format!(
+            "wamu-device-migration-{}",
+            utils::to_hex(&Random32Bytes::generate().to_be_bytes())
+        ));
+        let mut old_key_store = FileKeyStore::new(&dir, &identity_provider).unwrap();
+        old_key_store.save(&wallet_id, &key).unwrap();
+
+        // New device requests the party state (and proves control of the identity).
+        let (request, secret) = initiate(&wallet_id, &identity_provider);
+        let challenge =
+            verify_request_and_initiate_challenge(&request, &wallet_id, &identity_provider)
+                .unwrap();
+        let response = challenge_response(&[challenge], &identity_provider);
+
+        // Old device transfers the party state.
+        let package =
+            transfer(&request, &[challenge], &response, &key, &identity_provider).unwrap();
+        assert!(!package
+            .ciphertext
+            .windows(key.local_key.len())
+            .any(|window| window == key.local_key));
+
+        // New device receives the party state and acknowledges receipt.
+        let received = receive(&package, &request, &secret, &identity_provider).unwrap();
+        assert_eq!(received.to_cbor(), key.to_cbor());
+        assert_eq!(
+            share_split_reconstruct::reconstruct(
+                &received.signing_share,
+                &received.sub_share,
+                &identity_provider
+            )
+            .unwrap()
+            .to_be_bytes(),
+            secret_share.to_be_bytes()
+        );
+        let ack = acknowledge(&package, &identity_provider);
+
+        // Old device verifies the acknowledgement and wipes itself.
+        assert_eq!(
+            verify_acknowledgement(&ack, &package, &identity_provider.verifying_key()),
+            Ok(())
+        );
+        let attestation = wipe(&package, &mut old_key_store, &identity_provider).unwrap();
+        assert!(old_key_store.load(&wallet_id).unwrap().is_none());
+        assert_eq!(
+            verify_wipe(&attestation, &package, &identity_provider.verifying_key()),
+            Ok(())
+        );
+        // Acknowledgements and wipe attestations aren't interchangeable.
+        assert_eq!(
+            verify_wipe(&ack, &package, &identity_provider.verifying_key()),
+            Err(DeviceMigrationError::Unauthorized(Error::Crypto(
+                CryptoError::InvalidSignature
+            )))
+        );
+
+        // Requests for other wallets or from other identities are rejected.
+        assert_eq!(
+            verify_request_and_initiate_challenge(&request, &[2u8; 32], &identity_provider).err(),
+            Some(DeviceMigrationError::WalletMismatch)
+        );
+        let (other_request, other_secret) = initiate(&wallet_id, &other_identity_provider);
+        assert_eq!(
+            verify_request_and_initiate_challenge(&other_request, &wallet_id, &identity_provider)
+                .err(),
+            Some(DeviceMigrationError::IdentityMismatch)
+        );
+
+        // Invalid challenge responses are rejected.
+        let other_response = challenge_response(&[challenge], &other_identity_provider);
+        assert!(matches!(
+            transfer(
+                &request,
+                &[challenge],
+                &other_response,
+                &key,
+                &identity_provider
+            ),
+            Err(DeviceMigrationError::Unauthorized(_))
+        ));
+
+        // Packages can only be decrypted with the ephemeral secret of the request (and can't be tampered with).
+        assert_eq!(
+            receive(&package, &request, &other_secret, &identity_provider).err(),
+            Some(DeviceMigrationError::Decryption)
+        );
+        let mut tampered_package = package.clone();
+        tampered_package.ciphertext[0] ^= 1;
+        assert!(matches!(
+            receive(&tampered_package, &request, &secret, &identity_provider),
+            Err(DeviceMigrationError::Unauthorized(_))
+        ));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,248 @@
+//! Structured human-readable command summaries (inspired by [EIP-712](https://eips.ethereum.org/EIPS/eip-712) typed data).
+//!
+//! Allows wallet UIs to show users exactly what they are approving,
+//! while verifiers check that the summary hash matches the signed identity authenticated request.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+use sha2::{Digest, Sha256};
+
+use crate::errors::IdentityAuthedRequestError;
+use crate::identity_authed_request;
+use crate::payloads::IdentityAuthedRequestPayload;
+use crate::traits::IdentityProvider;
+use crate::utils;
+
+/// Separator between the command name and the summary hash in the canonical command encoding.
+const SUMMARY_HASH_SEPARATOR: char = '#';
+
+/// A typed command field value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FieldValue {
+    /// A UTF-8 string.
+    String(String),
+    /// An unsigned integer.
+    Uint(u64),
+    /// A boolean.
+    Bool(bool),
+    /// Arbitrary bytes (e.g an address or a verifying key).
+    Bytes(#[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))] Vec<u8>),
+}
+
+impl FieldValue {
+    /// Returns the type name of the field value.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::String(_) => "string",
+            Self::Uint(_) => "uint64",
+            Self::Bool(_) => "bool",
+            Self::Bytes(_) => "bytes",
+        }
+    }
+
+    /// Returns the human-readable representation of the field value.
+    pub fn display(&self) -> String {
+        match self {
+            Self::String(value) => value.clone(),
+            Self::Uint(value) => value.to_string(),
+            Self::Bool(value) => value.to_string(),
+            Self::Bytes(value) => format!("0x{}", utils::to_hex(value)),
+        }
+    }
+
+    /// Returns the canonical encoding of the field value.
+    fn encode(&self) -> Vec<u8> {
+        match self {
+            Self::String(value) => value.as_bytes().to_vec(),
+            Self::Uint(value) => value.to_be_bytes().to_vec(),
+            Self::Bool(value) => vec![u8::from(*value)],
+            Self::Bytes(value) => value.clone(),
+        }
+    }
+}
+
+/// A command with typed fields that can be displayed to users.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DisplayableCommand {
+    /// The command name.
+    command: String,
+    /// Named and typed command fields.
+    fields: Vec<(String, FieldValue)>,
+}
+
+impl DisplayableCommand {
+    /// Given a command name, returns a displayable command without any fields.
+    pub fn new(command: &str) -> Self {
+        Self {
+            command: command.to_string(),
+            fields: Vec::new(),
+        }
+    }
+
+    /// Adds a named and typed field to the displayable command.
+    pub fn with_field(mut self, name: &str, value: FieldValue) -> Self {
+        self.fields.push((name.to_string(), value));
+        self
+    }
+
+    /// Returns the command name.
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// Returns the named and typed command fields.
+    pub fn fields(&self) -> &[(String, FieldValue)] {
+        &self.fields
+    }
+
+    /// Returns a human-readable summary of the command (i.e the command name followed by one line per field).
+    ///
+    /// **NOTE:** Control characters (e.g newlines) and backslashes in the command name, field names and values are escaped,
+    /// so that a crafted field can't spoof additional lines in the summary.
+    pub fn summary(&self) -> String {
+        self.fields
+            .iter()
+            .fold(escape(&self.command), |summary, (name, value)| {
+                format!(
+                    "{summary}\n{} ({}): {}",
+                    escape(name),
+                    value.type_name(),
+                    escape(&value.display())
+                )
+            })
+    }
+
+    /// Returns the summary hash (i.e a SHA-256 hash of the type-tagged and length-prefixed command name and fields).
+    pub fn summary_hash(&self) -> [u8; 32] {
+        self.fields
+            .iter()
+            .fold(
+                Sha256::new()
+                    .chain_update(b"displayable-command")
+                    .chain_update(utils::length_prefix_bytes(self.command.as_bytes())),
+                |hasher, (name, value)| {
+                    hasher
+                        .chain_update(utils::length_prefix_bytes(name.as_bytes()))
+                        .chain_update(utils::length_prefix_bytes(value.type_name().as_bytes()))
+                        .chain_update(utils::length_prefix_bytes(&value.encode()))
+                },
+            )
+            .finalize()
+            .into()
+    }
+
+    /// Returns the canonical command encoding (i.e the command name followed by the summary hash).
+    pub fn canonical_command(&self) -> String {
+        format!(
+            "{}{SUMMARY_HASH_SEPARATOR}{}",
+            self.command,
+            utils::to_hex(&self.summary_hash())
+        )
+    }
+}
+
+/// Returns the value with control characters and backslashes escaped (e.g a newline becomes `\n`).
+fn escape(value: &str) -> String {
+    value
+        .chars()
+        .map(|character| {
+            if character.is_control() || character == '\\' {
+                character.escape_default().to_string()
+            } else {
+                character.to_string()
+            }
+        })
+        .collect()
+}
+
+/// Given a displayable command and an identity provider,
+/// returns the payload for initiating an identity authenticated request for the canonical command encoding.
+pub fn initiate(
+    command: &DisplayableCommand,
+    identity_provider: &impl IdentityProvider,
+) -> IdentityAuthedRequestPayload {
+    identity_authed_request::initiate_with_command(command.canonical_command(), identity_provider)
+}
+
+/// Given a displayable command and an identity authenticated request payload,
+/// returns an ok result if the summary hash matches the signed command or an appropriate error result otherwise.
+///
+/// **NOTE:** This doesn't verify the request itself (see [`identity_authed_request::verify`]).
+pub fn verify_summary(
+    command: &DisplayableCommand,
+    request: &IdentityAuthedRequestPayload,
+) -> Result<(), IdentityAuthedRequestError> {
+    if request.command == command.canonical_command() {
+        Ok(())
+    } else {
+        Err(IdentityAuthedRequestError::CommandMismatch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockECDSAIdentityProvider;
+    use crate::IdentityProvider;
+
+    #[test]
+    fn displayable_command_works() {
+        // Generates identity provider.
+        let identity_provider = MockECDSAIdentityProvider::generate();
+
+        // Creates displayable command.
+        let command = DisplayableCommand::new("transfer")
+            .with_field("to", FieldValue::Bytes(vec![0xab, 0xcd]))
+            .with_field("amount", FieldValue::Uint(100))
+            .with_field("memo", FieldValue::String("rent".to_string()));
+        assert_eq!(
+            command.summary(),
+            "transfer\nto (bytes): 0xabcd\namount (uint64): 100\nmemo (string): rent"
+        );
+
+        // Generates and verifies identity authenticated request for the displayable command.
+        let request = initiate(&command, &identity_provider);
+        assert!(
+            identity_authed_request::verify(&request, &[identity_provider.verifying_key()]).is_ok()
+        );
+        assert_eq!(verify_summary(&command, &request), Ok(()));
+
+        // Control characters in field names and values can't spoof additional summary lines.
+        let spoofed_command = DisplayableCommand::new("transfer\r")
+            .with_field("to", FieldValue::Bytes(vec![0xab, 0xcd]))
+            .with_field(
+                "memo\namount (uint64): 1",
+                FieldValue::String("rent\namount (uint64): 1\\n\u{7}".to_string()),
+            );
+        assert_eq!(
+            spoofed_command.summary(),
+            "transfer\\r\nto (bytes): 0xabcd\nmemo\\namount (uint64): 1 (string): rent\\namount (uint64): 1\\\\n\\u{7}"
+        );
+        assert_eq!(spoofed_command.summary().lines().count(), 3);
+
+        // Summary with modified field values or types should be rejected.
+        for modified_command in [
+            DisplayableCommand::new("transfer")
+                .with_field("to", FieldValue::Bytes(vec![0xab, 0xcd]))
+                .with_field("amount", FieldValue::Uint(1000))
+                .with_field("memo", FieldValue::String("rent".to_string())),
+            DisplayableCommand::new("transfer")
+                .with_field("to", FieldValue::Bytes(vec![0xab, 0xcd]))
+                .with_field("amount", FieldValue::String("100".to_string()))
+                .with_field("memo", FieldValue::String("rent".to_string())),
+            DisplayableCommand::new("transfer")
+                .with_field("to", FieldValue::Bytes(vec![0xab, 0xcd]))
+                .with_field("amount", FieldValue::Uint(100)),
+        ] {
+            assert_eq!(
+                verify_summary(&modified_command, &request),
+                Err(IdentityAuthedRequestError::CommandMismatch)
+            );
+        }
+    }
+}
@@ -0,0 +1,311 @@
+//! Versioned protocol message envelopes and protocol version negotiation.
+//!
+//! Envelopes bind core payloads and augmented protocol messages to a wallet and a session,
+//! while version negotiation allows mixed-version rosters to agree on a common wire format before a session starts.
+
+use crate::canonical::{CanonicalEncode, VersionOfferMessage};
+use crate::compression::Compression;
+use crate::crypto;
+use crate::crypto::VerifyingKey;
+use crate::errors::{Blame, EnvelopeError, Error};
+use crate::payloads::VersionOfferPayload;
+use crate::traits::IdentityProvider;
+
+/// The current protocol version.
+pub const PROTOCOL_VERSION: u16 = 1;
+
+/// The oldest protocol version this implementation can still speak.
+pub const MIN_PROTOCOL_VERSION: u16 = 1;
+
+/// A versioned protocol message envelope.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProtocolEnvelope<T> {
+    /// The protocol version of the body.
+    pub version: u16,
+    /// The identifier of the wallet (i.e the roster of parties sharing the key).
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub wallet_id: [u8; 32],
+    /// The identifier of the protocol session.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub session_id: [u8; 32],
+    /// The compression algorithm used for the body (if any).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub compression: Compression,
+    /// The wrapped payload or protocol message.
+    pub body: T,
+}
+
+impl<T> ProtocolEnvelope<T> {
+    /// Given a negotiated protocol version, a wallet identifier, a session identifier and a body,
+    /// returns a protocol envelope with an uncompressed body.
+    pub fn new(version: u16, wallet_id: [u8; 32], session_id: [u8; 32], body: T) -> Self {
+        Self {
+            version,
+            wallet_id,
+            session_id,
+            compression: Compression::None,
+            body,
+        }
+    }
+
+    /// Sets the compression algorithm used for the body (see [`crate::compression`] for details).
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Given the negotiated protocol version, the expected wallet identifier and the expected session identifier,
+    /// returns the body for a matching envelope or an appropriate error otherwise.
+    pub fn open(
+        self,
+        version: u16,
+        wallet_id: &[u8; 32],
+        session_id: &[u8; 32],
+    ) -> Result<T, EnvelopeError> {
+        if self.version != version {
+            // Envelope must use the negotiated protocol version.
+            Err(EnvelopeError::VersionMismatch)
+        } else if &self.wallet_id != wallet_id {
+            // Envelope must be for the expected wallet.
+            Err(EnvelopeError::WalletMismatch)
+        } else if &self.session_id != session_id {
+            // Envelope must be for the expected session.
+            Err(EnvelopeError::SessionMismatch)
+        } else {
+            Ok(self.body)
+        }
+    }
+}
+
+/// Given a wallet identifier, a session identifier and an identity provider,
+/// returns a signed offer of the range of protocol versions supported by this implementation.
+pub fn offer(
+    wallet_id: &[u8; 32],
+    session_id: &[u8; 32],
+    identity_provider: &impl IdentityProvider,
+) -> VersionOfferPayload {
+    offer_range(
+        MIN_PROTOCOL_VERSION,
+        PROTOCOL_VERSION,
+        wallet_id,
+        session_id,
+        identity_provider,
+    )
+}
+
+/// Given a range of supported protocol versions, a wallet identifier, a session identifier and an identity provider,
+/// returns a signed protocol version offer.
+pub fn offer_range(
+    min_version: u16,
+    max_version: u16,
+    wallet_id: &[u8; 32],
+    session_id: &[u8; 32],
+    identity_provider: &impl IdentityProvider,
+) -> VersionOfferPayload {
+    VersionOfferPayload {
+        min_version,
+        max_version,
+        verifying_key: identity_provider.verifying_key(),
+        signature: identity_provider.sign(
+            &VersionOfferMessage {
+                min_version,
+                max_version,
+                wallet_id,
+                session_id,
+            }
+            .message_bytes(),
+        ),
+    }
+}
+
+/// Given protocol version offers from all parties, a wallet identifier, a session identifier and a list of verifying keys for all parties,
+/// returns the highest protocol version supported by all parties or an appropriate error
+/// (with the offending party's verifying key and index, if any) otherwise.
+///
+/// **NOTE:** All parties compute the same negotiated version given the same offers,
+/// and every offer is verified before it's used (i.e each party must have exactly one valid offer for the wallet and session).
+pub fn negotiate(
+    offers: &[VersionOfferPayload],
+    wallet_id: &[u8; 32],
+    session_id: &[u8; 32],
+    verified_parties: &[VerifyingKey],
+) -> Result<u16, Blame<EnvelopeError>> {
+    for (position, offer) in offers.iter().enumerate() {
+        // Offers must be from verified parties.
+        let idx = verified_parties
+            .iter()
+            .position(|party| party == &offer.verifying_key)
+            .ok_or_else(|| {
+                Blame::new(EnvelopeError::Unauthorized(Error::UnauthorizedParty))
+                    .with_verifying_key(offer.verifying_key.clone())
+            })?;
+        let blame = |error: EnvelopeError| {
+            Blame::new(error)
+                .with_verifying_key(offer.verifying_key.clone())
+                .with_idx(idx as u16 + 1)
+        };
+        if offers[..position]
+            .iter()
+            .any(|other| other.verifying_key == offer.verifying_key)
+        {
+            // Each party can only have one offer.
+            return Err(blame(EnvelopeError::DuplicateOffer));
+        }
+        // Offer signature must be valid for the wallet and session.
+        crypto::verify_signature(
+            &offer.verifying_key,
+            &VersionOfferMessage {
+                min_version: offer.min_version,
+                max_version: offer.max_version,
+                wallet_id,
+                session_id,
+            }
+            .message_bytes(),
+            &offer.signature,
+        )
+        .map_err(|error| blame(error.into()))?;
+    }
+    if let Some((idx, party)) = verified_parties
+        .iter()
+        .enumerate()
+        .find(|(_, party)| !offers.iter().any(|offer| &offer.verifying_key == *party))
+    {
+        // Every party must have an offer.
+        return Err(Blame::new(EnvelopeError::MissingOffer)
+            .with_verifying_key(party.clone())
+            .with_idx(idx as u16 + 1));
+    }
+    let min_version = offers.iter().map(|offer| offer.min_version).max();
+    let max_version = offers.iter().map(|offer| offer.max_version).min();
+    match (min_version, max_version) {
+        // Highest version in the intersection of all supported ranges.
+        (Some(min_version), Some(max_version)) if min_version <= max_version => Ok(max_version),
+        _ => Err(Blame::new(EnvelopeError::UnsupportedVersion)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockECDSAIdentityProvider;
+    use alloc::string::ToString;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn version_negotiation_and_envelopes_work() {
+        // Generates identity providers and a wallet identifier.
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (0..3)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let verified_parties: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let wallet_id = [1; 32];
+        let session_id = [3; 32];
+
+        // Negotiates the highest version supported by all parties (i.e a mixed-version roster).
+        let offers = vec![
+            offer_range(1, 3, &wallet_id, &session_id, &identity_providers[0]),
+            offer_range(2, 4, &wallet_id, &session_id, &identity_providers[1]),
+            offer_range(1, 2, &wallet_id, &session_id, &identity_providers[2]),
+        ];
+        assert_eq!(
+            negotiate(&offers, &wallet_id, &session_id, &verified_parties),
+            Ok(2)
+        );
+
+        // Negotiation fails for disjoint ranges.
+        let mut disjoint_offers = offers.clone();
+        disjoint_offers[2] = offer_range(5, 6, &wallet_id, &session_id, &identity_providers[2]);
+        assert_eq!(
+            negotiate(&disjoint_offers, &wallet_id, &session_id, &verified_parties),
+            Err(Blame::new(EnvelopeError::UnsupportedVersion))
+        );
+
+        // Negotiation fails for missing, forged or foreign offers (and blames the offending party).
+        assert_eq!(
+            negotiate(&offers[..2], &wallet_id, &session_id, &verified_parties),
+            Err(Blame::new(EnvelopeError::MissingOffer)
+                .with_verifying_key(verified_parties[2].clone())
+                .with_idx(3))
+        );
+        let mut forged_offers = offers.clone();
+        forged_offers[2].max_version = 3;
+        let blame =
+            negotiate(&forged_offers, &wallet_id, &session_id, &verified_parties).unwrap_err();
+        assert!(matches!(blame.error, EnvelopeError::Unauthorized(_)));
+        assert_eq!(blame.idx, Some(3));
+        assert_eq!(blame.verifying_key.as_ref(), Some(&verified_parties[2]));
+        assert!(blame.to_string().starts_with("party 3 (0x"));
+        assert!(matches!(
+            negotiate(&offers, &[2; 32], &session_id, &verified_parties),
+            Err(Blame {
+                error: EnvelopeError::Unauthorized(_),
+                idx: Some(1),
+                ..
+            })
+        ));
+
+        // Negotiation fails for offers from other sessions (i.e replayed offers).
+        assert!(matches!(
+            negotiate(&offers, &wallet_id, &[4; 32], &verified_parties),
+            Err(Blame {
+                error: EnvelopeError::Unauthorized(_),
+                idx: Some(1),
+                ..
+            })
+        ));
+
+        // Negotiation fails for duplicate offers (i.e an unsigned duplicate can't force a downgrade).
+        let mut downgraded_offer = offers[1].clone();
+        downgraded_offer.max_version = 1;
+        for duplicate_offers in [
+            vec![
+                offers[0].clone(),
+                offers[1].clone(),
+                offers[2].clone(),
+                downgraded_offer,
+            ],
+            vec![
+                offers[0].clone(),
+                offers[1].clone(),
+                offers[1].clone(),
+                offers[2].clone(),
+            ],
+        ] {
+            assert_eq!(
+                negotiate(
+                    &duplicate_offers,
+                    &wallet_id,
+                    &session_id,
+                    &verified_parties
+                ),
+                Err(Blame::new(EnvelopeError::DuplicateOffer)
+                    .with_verifying_key(verified_parties[1].clone())
+                    .with_idx(2))
+            );
+        }
+
+        // Envelopes can only be opened with the negotiated version, wallet and session.
+        let envelope = ProtocolEnvelope::new(2, wallet_id, session_id, "body");
+        assert_eq!(
+            envelope.clone().open(2, &wallet_id, &session_id),
+            Ok("body")
+        );
+        assert_eq!(
+            envelope.clone().open(1, &wallet_id, &session_id),
+            Err(EnvelopeError::VersionMismatch)
+        );
+        assert_eq!(
+            envelope.clone().open(2, &[2; 32], &session_id),
+            Err(EnvelopeError::WalletMismatch)
+        );
+        assert_eq!(
+            envelope.open(2, &wallet_id, &[4; 32]),
+            Err(EnvelopeError::SessionMismatch)
+        );
+    }
+}
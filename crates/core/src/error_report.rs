@@ -0,0 +1,134 @@
+//! Serializable error reports for propagating protocol errors across processes.
+//!
+//! Allows co-signing services to ship failures to monitoring systems and to other parties for dispute handling.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::crypto::VerifyingKey;
+use crate::errors::Blame;
+use crate::utils;
+
+/// A serializable report of a protocol error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ErrorReport {
+    /// The error kind (i.e the `Debug` representation of the error e.g `"Unauthorized(Crypto(InvalidSignature))"`).
+    pub kind: String,
+    /// Round in which the error occurred (if known).
+    pub round: Option<u16>,
+    /// Parties blamed for the error.
+    pub blamed_parties: Vec<BlamedParty>,
+    /// Unix timestamp (in seconds) of when the error occurred.
+    pub timestamp: u64,
+}
+
+/// A party blamed for a protocol error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BlamedParty {
+    /// Index of the party (if known).
+    pub idx: Option<u16>,
+    /// Verifying key of the party (if known).
+    pub verifying_key: Option<VerifyingKey>,
+}
+
+impl ErrorReport {
+    /// Given an error, returns an error report (without round or blame information) timestamped with the current time.
+    pub fn new<E: fmt::Debug>(error: &E) -> Self {
+        Self {
+            kind: format!("{error:?}"),
+            round: None,
+            blamed_parties: Vec::new(),
+            timestamp: utils::unix_timestamp(),
+        }
+    }
+
+    /// Sets the round in which the error occurred.
+    pub fn with_round(mut self, round: u16) -> Self {
+        self.round = Some(round);
+        self
+    }
+
+    /// Adds a party blamed for the error.
+    pub fn with_blamed_party(
+        mut self,
+        idx: Option<u16>,
+        verifying_key: Option<VerifyingKey>,
+    ) -> Self {
+        self.blamed_parties.push(BlamedParty { idx, verifying_key });
+        self
+    }
+
+    /// Sets the unix timestamp (in seconds) of when the error occurred.
+    pub fn with_timestamp(mut self, timestamp: u64) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+}
+
+impl<E: fmt::Debug> From<&Blame<E>> for ErrorReport {
+    fn from(blame: &Blame<E>) -> Self {
+        let mut report = Self::new(&blame.error);
+        report.round = blame.round;
+        if blame.idx.is_some() || blame.verifying_key.is_some() {
+            report = report.with_blamed_party(blame.idx, blame.verifying_key.clone());
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::{CryptoError, Error};
+    use crate::test_utils::MockECDSAIdentityProvider;
+    use crate::IdentityProvider;
+    use alloc::vec;
+
+    #[test]
+    fn error_report_works() {
+        // Generates blamed verification error.
+        let verifying_key = MockECDSAIdentityProvider::generate().verifying_key();
+        let blame = Blame::new(Error::Crypto(CryptoError::InvalidSignature))
+            .with_verifying_key(verifying_key.clone())
+            .with_idx(2)
+            .with_round(1);
+
+        // Generates error report from blamed verification error.
+        let report = ErrorReport::from(&blame);
+        assert_eq!(report.kind, "Crypto(InvalidSignature)");
+        assert_eq!(report.round, Some(1));
+        assert_eq!(
+            report.blamed_parties,
+            vec![BlamedParty {
+                idx: Some(2),
+                verifying_key: Some(verifying_key),
+            }]
+        );
+
+        // Error reports without blame information don't blame any parties.
+        let report = ErrorReport::new(&CryptoError::InvalidSignature).with_timestamp(0);
+        assert_eq!(report.round, None);
+        assert!(report.blamed_parties.is_empty());
+        assert_eq!(report.timestamp, 0);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn error_report_serialization_works() {
+        // Generates error report.
+        let report = ErrorReport::new(&Error::UnauthorizedParty)
+            .with_round(3)
+            .with_blamed_party(
+                Some(1),
+                Some(MockECDSAIdentityProvider::generate().verifying_key()),
+            );
+
+        // Error report roundtrips through a serialized representation.
+        let json = serde_json::to_string(&report).unwrap();
+        assert_eq!(serde_json::from_str::<ErrorReport>(&json).unwrap(), report);
+    }
+}
@@ -1,5 +1,10 @@
 //! Types and abstractions for protocol errors.
 
+use core::fmt;
+
+use crate::crypto::VerifyingKey;
+use crate::utils;
+
 /// A protocol error.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Error {
@@ -13,6 +18,79 @@ pub enum Error {
     UnauthorizedParty,
 }
 
+/// A verification error with blame information
+/// (i.e the offending party's verifying key and/or index and the round in which the failure occurred).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Blame<E> {
+    /// The verification error.
+    pub error: E,
+    /// Verifying key of the offending party (if known).
+    pub verifying_key: Option<VerifyingKey>,
+    /// Index of the offending party (if known).
+    pub idx: Option<u16>,
+    /// Round in which the failure occurred (if known).
+    pub round: Option<u16>,
+}
+
+impl<E> Blame<E> {
+    /// Returns a verification error without any blame information.
+    pub fn new(error: E) -> Self {
+        Self {
+            error,
+            verifying_key: None,
+            idx: None,
+            round: None,
+        }
+    }
+
+    /// Sets the verifying key of the offending party.
+    pub fn with_verifying_key(mut self, verifying_key: VerifyingKey) -> Self {
+        self.verifying_key = Some(verifying_key);
+        self
+    }
+
+    /// Sets the index of the offending party.
+    pub fn with_idx(mut self, idx: u16) -> Self {
+        self.idx = Some(idx);
+        self
+    }
+
+    /// Sets the round in which the failure occurred.
+    pub fn with_round(mut self, round: u16) -> Self {
+        self.round = Some(round);
+        self
+    }
+}
+
+impl<E> From<E> for Blame<E> {
+    fn from(error: E) -> Self {
+        Self::new(error)
+    }
+}
+
+impl<E: fmt::Debug> fmt::Display for Blame<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.idx, &self.verifying_key) {
+            (Some(idx), Some(verifying_key)) => write!(
+                f,
+                "party {idx} (0x{}) sent an invalid message",
+                utils::to_hex(&verifying_key.key)
+            )?,
+            (Some(idx), None) => write!(f, "party {idx} sent an invalid message")?,
+            (None, Some(verifying_key)) => write!(
+                f,
+                "party 0x{} sent an invalid message",
+                utils::to_hex(&verifying_key.key)
+            )?,
+            (None, None) => write!(f, "invalid message")?,
+        }
+        if let Some(round) = self.round {
+            write!(f, " in round {round}")?;
+        }
+        write!(f, ": {:?}", self.error)
+    }
+}
+
 /// An arithmetic error.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ArithmeticError {
@@ -59,6 +137,12 @@ pub enum IdentityAuthedRequestError {
     Expired,
     /// A request with an invalid timestamp i.e a timestamp too far in the future.
     InvalidTimestamp,
+    /// A request with a counter that isn't greater than the last-seen counter for the identity (i.e a replayed request).
+    InvalidCounter,
+    /// A request from an identity that's currently backing off or locked out after too many failed verifications.
+    Throttled,
+    /// A request rejected by a request interceptor (e.g an application policy check or user confirmation prompt).
+    Rejected,
     /// A request with either an invalid signature or an unauthorized signer.
     Unauthorized(Error),
 }
@@ -88,6 +172,9 @@ impl_from_error!(IdentityAuthedRequestError);
 pub enum QuorumApprovedRequestError {
     /// Not enough approvals to form a quorum.
     InsufficientApprovals,
+    /// An expired request or delegation
+    /// (e.g approvals received after the collection deadline or approvals from an expired delegate).
+    Expired,
     /// A request with either an invalid signature or an unauthorized signer.
     Unauthorized(Error),
 }
@@ -95,19 +182,440 @@ pub enum QuorumApprovedRequestError {
 // Implements `From<Error>` and `From<CryptoError>` for `QuorumApprovedRequestError`.
 impl_from_error!(QuorumApprovedRequestError);
 
+/// A time-locked request execution or cancellation error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeLockError {
+    /// The request was cancelled during the time lock window.
+    Cancelled,
+    /// The time lock window hasn't elapsed yet.
+    Locked,
+    /// The time lock window has already elapsed (i.e the request can no longer be cancelled).
+    Unlocked,
+    /// A cancellation with either an invalid signature or an unauthorized signer.
+    Unauthorized(Error),
+}
+
+// Implements `From<Error>` and `From<CryptoError>` for `TimeLockError`.
+impl_from_error!(TimeLockError);
+
+/// An identity rotation certificate or roster log verification error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RosterLogError {
+    /// Not enough valid counter-signatures from the roster.
+    InsufficientSignatures,
+    /// A certificate that doesn't extend the roster log head (i.e wrong epoch or previous hash).
+    InvalidChain,
+    /// A rotation from a verifying key that's not in the roster or to a verifying key that's already in the roster.
+    InvalidRotation,
+    /// A counter-signature with either an invalid signature or an unauthorized signer.
+    Unauthorized(Error),
+}
+
+// Implements `From<Error>` and `From<CryptoError>` for `RosterLogError`.
+impl_from_error!(RosterLogError);
+
+/// A command log integrity verification error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandLogError {
+    /// An entry that doesn't extend the previous entry (i.e wrong sequence number or previous hash).
+    InvalidChain,
+    /// A log head that doesn't match the expected head hash (e.g a truncated log).
+    HeadMismatch,
+}
+
+/// An audit log verification error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditLogError {
+    /// An entry that doesn't extend the previous entry (i.e wrong sequence number or previous hash).
+    InvalidChain,
+    /// A log head that doesn't match the expected head hash (e.g a truncated log).
+    HeadMismatch,
+    /// An entry with either an invalid signature or an unauthorized signer.
+    Unauthorized(Error),
+}
+
+// Implements `From<Error>` and `From<CryptoError>` for `AuditLogError`.
+impl_from_error!(AuditLogError);
+
+/// A canonical CBOR decoding error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CborError {
+    /// Input ended before the value was fully decoded.
+    UnexpectedEnd,
+    /// An unexpected major type, length or value for the decoded type.
+    InvalidValue,
+    /// A valid but non-canonical encoding (e.g an integer or length that's not encoded in its shortest form).
+    NonCanonical,
+    /// Input has bytes left over after decoding the value.
+    TrailingBytes,
+}
+
+/// A JSON decoding error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonError {
+    /// Invalid JSON or a payload that doesn't match the expected structure.
+    InvalidJson,
+    /// An envelope with an unsupported version of the JSON encoding.
+    UnsupportedVersion,
+    /// An envelope with a payload type that doesn't match the expected payload type.
+    TypeMismatch,
+}
+
+/// A `wamu:` URI decoding error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UriError {
+    /// A URI that doesn't match the `wamu:<type>?v=<version>&p=<payload>` format or a payload that isn't valid base64url.
+    InvalidUri,
+    /// A URI with an unsupported version of the URI encoding.
+    UnsupportedVersion,
+    /// A URI with a payload type that doesn't match the expected payload type.
+    TypeMismatch,
+    /// A payload that isn't a canonical CBOR encoding of the expected payload type.
+    Decoding(CborError),
+}
+
+impl From<CborError> for UriError {
+    fn from(error: CborError) -> Self {
+        Self::Decoding(error)
+    }
+}
+
+/// A BIP-32 child key derivation or extended public key encoding error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bip32Error {
+    /// A public key that isn't a valid SEC1 encoded `Secp256k1` point.
+    InvalidPublicKey,
+    /// A hardened child index (i.e hardened derivation requires the (unavailable) group private key).
+    HardenedIndex,
+    /// A derivation path that doesn't match the `m/<index>/<index>/...` format.
+    InvalidPath,
+    /// A derived child key that's invalid (i.e the tweak is larger than the curve order or the child key is the identity).
+    ///
+    /// **NOTE:** The probability of this is lower than 1 in 2^127, callers should proceed with the next index.
+    InvalidChildKey,
+    /// An extended key that's deeper than 255 levels.
+    MaxDepthExceeded,
+    /// An extended public key that isn't a valid base58check encoding or has an unsupported version.
+    InvalidEncoding,
+}
+
+/// A blockchain address derivation error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressError {
+    /// A public key that isn't a valid SEC1 encoded `Secp256k1` point.
+    InvalidPublicKey,
+    /// A human-readable part (i.e address prefix) that isn't valid for bech32 encoding.
+    InvalidPrefix,
+    /// A taproot tweak that's larger than the curve order or results in the identity.
+    ///
+    /// **NOTE:** The probability of this is negligible for keys that aren't adversarially chosen.
+    InvalidTweak,
+}
+
+/// A session proposal or response verification error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionSetupError {
+    /// A proposal for another wallet.
+    WalletMismatch,
+    /// A proposal whose participants aren't distinct verified parties that include the initiating party.
+    InvalidParticipants,
+    /// A proposal whose session deadline is before its response deadline.
+    InvalidDeadline,
+    /// An expired proposal (i.e a proposal or response received after the response deadline).
+    Expired,
+    /// A response from a party that isn't a participant (other than the initiating party).
+    NotParticipant,
+    /// Conflicting responses (i.e both an acceptance and a rejection) from the same party.
+    ConflictingResponses,
+    /// A proposal or response with either an invalid signature or an unauthorized signer.
+    Unauthorized(Error),
+}
+
+// Implements `From<Error>` and `From<CryptoError>` for `SessionSetupError`.
+impl_from_error!(SessionSetupError);
+
+/// A protocol envelope or version negotiation error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvelopeError {
+    /// A party without a protocol version offer.
+    MissingOffer,
+    /// A party with more than one protocol version offer.
+    DuplicateOffer,
+    /// No protocol version is supported by all parties.
+    UnsupportedVersion,
+    /// An envelope with a protocol version other than the negotiated version.
+    VersionMismatch,
+    /// An envelope for another wallet.
+    WalletMismatch,
+    /// An envelope for another session.
+    SessionMismatch,
+    /// A protocol version offer with either an invalid signature or an unauthorized signer.
+    Unauthorized(Error),
+}
+
+// Implements `From<Error>` and `From<CryptoError>` for `EnvelopeError`.
+impl_from_error!(EnvelopeError);
+
+/// A message body compression error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionError {
+    /// A compression algorithm that isn't enabled for this implementation.
+    Unsupported,
+    /// A compressed body that couldn't be decompressed.
+    Malformed,
+    /// A compressed body that exceeds the maximum decompressed size.
+    TooLarge,
+}
+
+/// An offline request export, approval or merge error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OfflineApprovalError {
+    /// A blob that isn't a canonical CBOR encoding of the expected payload.
+    Decoding(CborError),
+    /// An offline request for another wallet.
+    WalletMismatch,
+    /// An invalid exported request.
+    Request(IdentityAuthedRequestError),
+    /// An invalid approval.
+    Approval(QuorumApprovedRequestError),
+    /// An offline request with either an invalid export signature or an unauthorized exporter.
+    Unauthorized(Error),
+}
+
+// Implements `From<Error>` and `From<CryptoError>` for `OfflineApprovalError`.
+impl_from_error!(OfflineApprovalError);
+
+impl From<CborError> for OfflineApprovalError {
+    fn from(error: CborError) -> Self {
+        Self::Decoding(error)
+    }
+}
+
+impl From<IdentityAuthedRequestError> for OfflineApprovalError {
+    fn from(error: IdentityAuthedRequestError) -> Self {
+        Self::Request(error)
+    }
+}
+
+impl From<QuorumApprovedRequestError> for OfflineApprovalError {
+    fn from(error: QuorumApprovedRequestError) -> Self {
+        Self::Approval(error)
+    }
+}
+
+/// A device migration error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceMigrationError {
+    /// A request or package for another wallet.
+    WalletMismatch,
+    /// A request or package from another identity (i.e the new device must control the identity of the migrating party).
+    IdentityMismatch,
+    /// A request that was initiated too long ago.
+    Expired,
+    /// A request with a timestamp that's too far in the future.
+    InvalidTimestamp,
+    /// An ephemeral key that isn't a SEC1 encoded secp256k1 public key.
+    InvalidEphemeralKey,
+    /// A package that couldn't be decrypted (e.g it was encrypted for another ephemeral key or it was tampered with).
+    Decryption,
+    /// Decrypted party state that couldn't be decoded or upgraded to the current format.
+    Decoding(MigrationError),
+    /// A request, challenge response, package or acknowledgement with an invalid signature.
+    Unauthorized(Error),
+}
+
+// Implements `From<Error>` and `From<CryptoError>` for `DeviceMigrationError`.
+impl_from_error!(DeviceMigrationError);
+
+impl From<MigrationError> for DeviceMigrationError {
+    fn from(error: MigrationError) -> Self {
+        Self::Decoding(error)
+    }
+}
+
 /// A share backup or recovery error.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ShareBackupRecoveryError {
     /// Encrypted data can't be converted into a valid signing share e.g decrypted output that's not 32 bytes long.
     InvalidSigningShare,
-    /// Encrypted data can't be converted into a valid sub share e.g decrypted output that's not 32 bytes long.
-    InvalidSubShare,
-    /// An encryption/decryption error.
-    EncryptionError(aes_gcm::Error),
+    /// Encrypted data for the field can't be converted into a valid sub share e.g decrypted output that's not 32 bytes long.
+    InvalidSubShare(BackupField),
+    /// A nonce that's not 12 bytes long.
+    InvalidNonce,
+    /// An encryption/decryption error for the field.
+    ///
+    /// **NOTE:** The underlying AEAD error is opaque, so `code` only distinguishes
+    /// encryption failures (i.e `1`) from decryption failures (i.e `2`, e.g a wrong entropy seed or identity, or a tampered backup).
+    EncryptionError { field: BackupField, code: u8 },
+}
+
+impl ShareBackupRecoveryError {
+    /// AEAD error code for encryption failures.
+    pub const ENCRYPTION_FAILED: u8 = 1;
+    /// AEAD error code for decryption failures.
+    pub const DECRYPTION_FAILED: u8 = 2;
+}
+
+/// A key destruction error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyDestructionError {
+    /// A destruction certificate for another wallet.
+    WalletMismatch,
+    /// A key destruction request that isn't approved by a quorum.
+    Approval(QuorumApprovedRequestError),
+    /// A destruction certificate without valid destruction attestations from all parties.
+    InsufficientAttestations,
 }
 
-impl From<aes_gcm::Error> for ShareBackupRecoveryError {
-    fn from(error: aes_gcm::Error) -> Self {
-        ShareBackupRecoveryError::EncryptionError(error)
+impl From<QuorumApprovedRequestError> for KeyDestructionError {
+    fn from(error: QuorumApprovedRequestError) -> Self {
+        Self::Approval(error)
     }
 }
+
+/// A keystore export or keystore decryption error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeystoreExportError {
+    /// A request, contribution or record for another wallet.
+    WalletMismatch,
+    /// A request whose command isn't a keystore export command (i.e without a valid wallet identifier and ephemeral key).
+    InvalidCommand,
+    /// An invalid keystore export request.
+    Request(IdentityAuthedRequestError),
+    /// A keystore export request that isn't approved by all parties.
+    Approval(QuorumApprovedRequestError),
+    /// A "secret share" that couldn't be reconstructed (i.e from the "signing share" and "sub-share").
+    Reconstruction(Error),
+    /// Missing contributions (i.e every party must contribute exactly once).
+    InsufficientContributions,
+    /// A contribution that couldn't be decrypted (e.g it was encrypted for another ephemeral key or it was tampered with).
+    Decryption,
+    /// Contributions that don't reconstruct the private key for the wallet's public key.
+    PublicKeyMismatch,
+    /// A keystore with unsupported or malformed crypto modules.
+    InvalidKeystore,
+    /// A wrong keystore password (i.e a checksum mismatch).
+    InvalidPassword,
+    /// A request, challenge response or contribution with either an invalid signature or an unauthorized signer.
+    Unauthorized(Error),
+}
+
+// Implements `From<Error>` and `From<CryptoError>` for `KeystoreExportError`.
+impl_from_error!(KeystoreExportError);
+
+impl From<IdentityAuthedRequestError> for KeystoreExportError {
+    fn from(error: IdentityAuthedRequestError) -> Self {
+        Self::Request(error)
+    }
+}
+
+impl From<QuorumApprovedRequestError> for KeystoreExportError {
+    fn from(error: QuorumApprovedRequestError) -> Self {
+        Self::Approval(error)
+    }
+}
+
+/// A signature budget error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureBudgetError {
+    /// The current key epoch reached the maximum number of signatures (i.e a key refresh is required before signing).
+    Exhausted,
+}
+
+/// A signing policy evaluation or policy update error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyError {
+    /// A signing request for a destination that isn't in the destination allowlist.
+    DestinationNotAllowed,
+    /// A signing request for an amount above the amount limit.
+    AmountLimitExceeded,
+    /// A signing request that isn't approved by all required approvers.
+    MissingApprover,
+    /// A signing request outside the allowed time-of-day window.
+    OutsideTimeWindow,
+    /// A policy or policy update for another wallet.
+    WalletMismatch,
+    /// A policy update whose version isn't greater than the version of the current policy.
+    StaleVersion,
+    /// A policy update request for a different policy.
+    CommandMismatch,
+    /// A policy update request that isn't approved by a quorum.
+    Approval(QuorumApprovedRequestError),
+}
+
+impl From<QuorumApprovedRequestError> for PolicyError {
+    fn from(error: QuorumApprovedRequestError) -> Self {
+        Self::Approval(error)
+    }
+}
+
+/// A key store error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyStoreError {
+    /// An I/O error (e.g an unwritable key store directory).
+    #[cfg(feature = "std")]
+    #[doc(cfg(feature = "std"))]
+    Io(std::io::ErrorKind),
+    /// Stored key material that couldn't be decrypted
+    /// (i.e it was encrypted by another identity or for another wallet, or it was tampered with).
+    Decryption,
+    /// Stored key material with an unsupported format version.
+    UnsupportedVersion,
+    /// Decrypted key material that isn't a canonical CBOR encoding of stored key material.
+    Decoding(CborError),
+    /// Decrypted key material that couldn't be upgraded to the current format (see [`MigrationError`]).
+    Migration(MigrationError),
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for KeyStoreError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error.kind())
+    }
+}
+
+impl From<CborError> for KeyStoreError {
+    fn from(error: CborError) -> Self {
+        Self::Decoding(error)
+    }
+}
+
+impl From<MigrationError> for KeyStoreError {
+    fn from(error: MigrationError) -> Self {
+        match error {
+            MigrationError::Decoding(error) => Self::Decoding(error),
+            error => Self::Migration(error),
+        }
+    }
+}
+
+/// A versioned artifact decoding or migration error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationError {
+    /// An artifact of another type (e.g an encrypted share backup decoded as stored key material).
+    TypeMismatch,
+    /// An artifact with a format version that's newer than the current format version
+    /// (i.e it was written by a newer implementation).
+    UnsupportedVersion,
+    /// An artifact with a format version that has no registered migration to the next format version.
+    MissingMigration(u32),
+    /// An artifact (or version tag) that isn't a canonical CBOR encoding.
+    Decoding(CborError),
+}
+
+impl From<CborError> for MigrationError {
+    fn from(error: CborError) -> Self {
+        Self::Decoding(error)
+    }
+}
+
+/// An encrypted share backup field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackupField {
+    /// The encrypted "signing share".
+    SigningShare,
+    /// The encrypted x-coordinate of the "sub-share".
+    SubShareX,
+    /// The encrypted y-coordinate of the "sub-share".
+    SubShareY,
+}
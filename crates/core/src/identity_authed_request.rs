@@ -1,18 +1,27 @@
 //! Identity authenticated request initiation and verification implementation.
 //!
 //! Ref: <https://wamu.tech/specification#identity-authed-request>.
+//!
+//! **NOTE:** Parties without a trustworthy clock (e.g embedded devices and TEEs) can use the monotonic counter mode
+//! (i.e [`initiate_with_counter`] and [`RequestVerifier`]) instead of timestamps for request freshness.
 
+use crate::canonical::{CanonicalEncode, CommandMessage, CounterCommandMessage};
 use crate::crypto::VerifyingKey;
-use crate::errors::{Error, IdentityAuthedRequestError};
+use crate::errors::{CryptoError, Error, IdentityAuthedRequestError};
 use crate::payloads::IdentityAuthedRequestPayload;
-use crate::traits::IdentityProvider;
+use crate::request_throttle::RequestThrottle;
+use crate::traits::{Clock, IdentityProvider};
+use crate::utils::SystemClock;
 use crate::{crypto, utils};
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
 
 /// How long a request remains valid.
-const EXPIRY_TIMEOUT: u64 = 60 * 60; // 1 hour.
+pub(crate) const EXPIRY_TIMEOUT: u64 = 60 * 60; // 1 hour.
 
 /// How far in the future a request is allowed to be (e.g due to out of sync clocks between parties).
-const FUTURE_TIMESTAMP_TOLERANCE: u64 = 5 * 60; // 5 minutes.
+pub(crate) const FUTURE_TIMESTAMP_TOLERANCE: u64 = 5 * 60; // 5 minutes.
 
 /// Given a "command" and an identity provider, returns the payload for initiating an identity authenticated request.
 ///
@@ -20,14 +29,50 @@ const FUTURE_TIMESTAMP_TOLERANCE: u64 = 5 * 60; // 5 minutes.
 pub fn initiate(
     command: &'static str,
     identity_provider: &impl IdentityProvider,
+) -> IdentityAuthedRequestPayload {
+    initiate_with_command(command.to_string(), identity_provider)
+}
+
+/// Given a (possibly dynamic) "command" and an identity provider, returns the payload for initiating an identity authenticated request.
+pub fn initiate_with_command(
+    command: String,
+    identity_provider: &impl IdentityProvider,
 ) -> IdentityAuthedRequestPayload {
     let timestamp = utils::unix_timestamp();
-    let signature = identity_provider.sign(&command_message_bytes(command, timestamp));
+    let signature = identity_provider.sign(
+        &CommandMessage {
+            command: &command,
+            timestamp,
+        }
+        .message_bytes(),
+    );
 
     IdentityAuthedRequestPayload {
         command,
         verifying_key: identity_provider.verifying_key(),
         timestamp,
+        counter: None,
+        signature,
+    }
+}
+
+/// Given a "command", a request counter and an identity provider,
+/// returns the payload for initiating an identity authenticated request in monotonic counter mode.
+///
+/// **NOTE:** The request counter must be greater than the counter of the party's previous request.
+pub fn initiate_with_counter(
+    command: &'static str,
+    counter: u64,
+    identity_provider: &impl IdentityProvider,
+) -> IdentityAuthedRequestPayload {
+    let signature =
+        identity_provider.sign(&CounterCommandMessage { command, counter }.message_bytes());
+
+    IdentityAuthedRequestPayload {
+        command: command.to_string(),
+        verifying_key: identity_provider.verifying_key(),
+        timestamp: 0,
+        counter: Some(counter),
         signature,
     }
 }
@@ -40,37 +85,234 @@ pub fn verify(
     request: &IdentityAuthedRequestPayload,
     verified_parties: &[VerifyingKey],
 ) -> Result<(), IdentityAuthedRequestError> {
+    verify_with_clock(request, verified_parties, &SystemClock)
+}
+
+/// Same as [`verify`] but reads the current time (i.e for request expiry) from the given clock.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(
+            command = %request.command,
+            verifying_key = %utils::to_hex(&request.verifying_key.key)
+        ),
+        err(level = "warn", Debug)
+    )
+)]
+pub fn verify_with_clock(
+    request: &IdentityAuthedRequestPayload,
+    verified_parties: &[VerifyingKey],
+    clock: &impl Clock,
+) -> Result<(), IdentityAuthedRequestError> {
+    let now = clock.unix_timestamp();
     if !verified_parties.contains(&request.verifying_key) {
         // Sender must be a verified party.
         Err(IdentityAuthedRequestError::Unauthorized(
             Error::UnauthorizedParty,
         ))
-    } else if request.timestamp + EXPIRY_TIMEOUT < utils::unix_timestamp() {
+    } else if request.counter.is_some() {
+        // Requests initiated in monotonic counter mode can only be verified against the last-seen counters (see `RequestVerifier`).
+        Err(IdentityAuthedRequestError::InvalidCounter)
+    } else if request.timestamp + EXPIRY_TIMEOUT < now {
         // Request should be initiated during the current epoch.
         Err(IdentityAuthedRequestError::Expired)
-    } else if utils::unix_timestamp() + FUTURE_TIMESTAMP_TOLERANCE < request.timestamp {
+    } else if now + FUTURE_TIMESTAMP_TOLERANCE < request.timestamp {
         // Request can't be too far into the future (i.e clocks can't be exactly synchronized but tolerance should be reasonable).
         Err(IdentityAuthedRequestError::InvalidTimestamp)
     } else {
         // Command signature must be valid.
-        Ok(crypto::verify_signature(
-            &request.verifying_key,
-            &command_message_bytes(request.command, request.timestamp),
-            &request.signature,
-        )?)
+        Ok(verify_signature(request)?)
     }
 }
 
-/// Returns sign-able message bytes for the command and timestamp.
-fn command_message_bytes(command: &str, timestamp: u64) -> Vec<u8> {
-    utils::prefix_message_bytes(format!("{}{}", command, timestamp).as_bytes())
+/// Returns an `Ok` result if the command signature of the identity authenticated request payload is valid,
+/// or an appropriate `Err` result otherwise.
+fn verify_signature(request: &IdentityAuthedRequestPayload) -> Result<(), CryptoError> {
+    crypto::verify_signature(
+        &request.verifying_key,
+        &CommandMessage {
+            command: &request.command,
+            timestamp: request.timestamp,
+        }
+        .message_bytes(),
+        &request.signature,
+    )
+}
+
+/// A stateful verifier for identity authenticated requests that rate limits identities with failed verifications
+/// (see [`RequestThrottle`]) and rejects replayed requests initiated in monotonic counter mode (see [`RequestCounters`]).
+///
+/// **NOTE:** Only failed verifications of requests with a valid signature from verified parties (i.e expired requests
+/// or requests with an invalid timestamp) are recorded by the throttle (i.e forged requests that name an identity can't lock it out),
+/// while successful verifications reset the consecutive failures for the identity.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RequestVerifier {
+    /// The request throttle.
+    throttle: RequestThrottle,
+    /// The last-seen request counters (i.e for requests initiated in monotonic counter mode).
+    counters: RequestCounters,
+}
+
+impl RequestVerifier {
+    /// Returns a request verifier with the default request throttle.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the request throttle.
+    pub fn with_throttle(mut self, throttle: RequestThrottle) -> Self {
+        self.throttle = throttle;
+        self
+    }
+
+    /// Returns the request throttle.
+    pub fn throttle(&self) -> &RequestThrottle {
+        &self.throttle
+    }
+
+    /// Returns a mutable reference to the request throttle (e.g for lifting a lockout after out-of-band confirmation).
+    pub fn throttle_mut(&mut self) -> &mut RequestThrottle {
+        &mut self.throttle
+    }
+
+    /// Sets the last-seen request counters (e.g restored from storage).
+    pub fn with_counters(mut self, counters: RequestCounters) -> Self {
+        self.counters = counters;
+        self
+    }
+
+    /// Returns the last-seen request counters (e.g for persisting them).
+    pub fn counters(&self) -> &RequestCounters {
+        &self.counters
+    }
+
+    /// Given an identity authenticated request payload and a list of verifying keys for the other parties,
+    /// returns an ok result for a valid request or an appropriate error result for an invalid, replayed or throttled request.
+    ///
+    /// **NOTE:** Requests initiated in monotonic counter mode are verified with [`verify_with_counter`]
+    /// (i.e their counters are recorded), and all other requests are verified with [`verify_with_clock`].
+    pub fn verify(
+        &mut self,
+        request: &IdentityAuthedRequestPayload,
+        verified_parties: &[VerifyingKey],
+    ) -> Result<(), IdentityAuthedRequestError> {
+        self.verify_with_clock(request, verified_parties, &SystemClock)
+    }
+
+    /// Same as [`RequestVerifier::verify`] but reads the current time (i.e for request expiry) from the given clock.
+    pub fn verify_with_clock(
+        &mut self,
+        request: &IdentityAuthedRequestPayload,
+        verified_parties: &[VerifyingKey],
+        clock: &impl Clock,
+    ) -> Result<(), IdentityAuthedRequestError> {
+        if self.throttle.is_throttled(&request.verifying_key) {
+            // Sender must not be backing off or locked out.
+            return Err(IdentityAuthedRequestError::Throttled);
+        }
+        let result = match request.counter {
+            Some(_) => verify_with_counter(request, verified_parties, &mut self.counters),
+            None => verify_with_clock(request, verified_parties, clock),
+        };
+        match result {
+            Ok(()) => self.throttle.record_success(&request.verifying_key),
+            // Failures are only attributed to the identity after the signature is verified.
+            Err(
+                IdentityAuthedRequestError::Expired | IdentityAuthedRequestError::InvalidTimestamp,
+            ) if verify_signature(request).is_ok() => {
+                self.throttle.record_failure(&request.verifying_key)
+            }
+            Err(_) => (),
+        }
+        result
+    }
+}
+
+/// Given an identity authenticated request payload initiated in monotonic counter mode,
+/// a list of verifying keys for the other parties and the last-seen request counters,
+/// returns an ok result for a valid request or an appropriate error result for an invalid or replayed request.
+///
+/// **NOTE:** The request counter is recorded as the last-seen counter for the identity if the request is valid.
+pub fn verify_with_counter(
+    request: &IdentityAuthedRequestPayload,
+    verified_parties: &[VerifyingKey],
+    counters: &mut RequestCounters,
+) -> Result<(), IdentityAuthedRequestError> {
+    if !verified_parties.contains(&request.verifying_key) {
+        // Sender must be a verified party.
+        return Err(IdentityAuthedRequestError::Unauthorized(
+            Error::UnauthorizedParty,
+        ));
+    }
+    // Request must be initiated in monotonic counter mode.
+    let counter = request
+        .counter
+        .ok_or(IdentityAuthedRequestError::InvalidCounter)?;
+    if counters
+        .last_seen(&request.verifying_key)
+        .is_some_and(|last_seen| counter <= last_seen)
+    {
+        // Request counter must be greater than the last-seen counter for the identity.
+        return Err(IdentityAuthedRequestError::InvalidCounter);
+    }
+    // Command signature must be valid.
+    crypto::verify_signature(
+        &request.verifying_key,
+        &CounterCommandMessage {
+            command: &request.command,
+            counter,
+        }
+        .message_bytes(),
+        &request.signature,
+    )?;
+    counters.record(&request.verifying_key, counter);
+    Ok(())
+}
+
+/// Tracks the last-seen request counter per identity for verifying requests initiated in monotonic counter mode.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RequestCounters {
+    /// Last-seen request counters for identities with at least one verified request.
+    counters: Vec<(VerifyingKey, u64)>,
+}
+
+impl RequestCounters {
+    /// Returns empty request counters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the last-seen request counter for the identity (if any).
+    pub fn last_seen(&self, verifying_key: &VerifyingKey) -> Option<u64> {
+        self.counters
+            .iter()
+            .find(|(key, _)| key == verifying_key)
+            .map(|(_, counter)| *counter)
+    }
+
+    /// Records the last-seen request counter for the identity.
+    pub fn record(&mut self, verifying_key: &VerifyingKey, counter: u64) {
+        match self
+            .counters
+            .iter_mut()
+            .find(|(key, _)| key == verifying_key)
+        {
+            Some((_, last_seen)) => *last_seen = counter,
+            None => self.counters.push((verifying_key.clone(), counter)),
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::errors::CryptoError;
-    use crate::test_utils::MockECDSAIdentityProvider;
+    use crate::test_utils::{MockClock, MockECDSAIdentityProvider};
+    use alloc::vec;
 
     #[test]
     fn identity_authed_request_initiation_and_verification_works() {
@@ -136,4 +378,201 @@ mod test {
             assert_eq!(result, expected_result);
         }
     }
+
+    #[test]
+    fn identity_authed_request_verification_with_clock_works() {
+        // Generates identity provider and identity authenticated request payload.
+        let identity_provider = MockECDSAIdentityProvider::generate();
+        let verified_parties = vec![identity_provider.verifying_key()];
+        let payload = initiate("command", &identity_provider);
+
+        // Request expiry is checked against the given clock.
+        let clock = MockClock::new(payload.timestamp);
+        assert_eq!(
+            verify_with_clock(&payload, &verified_parties, &clock),
+            Ok(())
+        );
+        clock.advance(EXPIRY_TIMEOUT + 1);
+        assert_eq!(
+            verify_with_clock(&payload, &verified_parties, &clock),
+            Err(IdentityAuthedRequestError::Expired)
+        );
+        assert_eq!(
+            verify_with_clock(
+                &payload,
+                &verified_parties,
+                &MockClock::new(payload.timestamp - FUTURE_TIMESTAMP_TOLERANCE - 1)
+            ),
+            Err(IdentityAuthedRequestError::InvalidTimestamp)
+        );
+    }
+
+    #[test]
+    fn throttled_identity_authed_request_verification_works() {
+        // Generates identity provider.
+        let identity_provider = MockECDSAIdentityProvider::generate();
+        let verified_parties = vec![identity_provider.verifying_key()];
+
+        // Generates valid, forged and expired identity authenticated request payloads.
+        let payload = initiate("command", &identity_provider);
+        let mut forged_payload = payload.clone();
+        forged_payload.signature = MockECDSAIdentityProvider::generate().sign(
+            &CommandMessage {
+                command: "command",
+                timestamp: payload.timestamp,
+            }
+            .message_bytes(),
+        );
+        let mut expired_payload = payload.clone();
+        expired_payload.timestamp = payload.timestamp - EXPIRY_TIMEOUT - 1;
+        expired_payload.signature = identity_provider.sign(
+            &CommandMessage {
+                command: "command",
+                timestamp: expired_payload.timestamp,
+            }
+            .message_bytes(),
+        );
+
+        // Initializes verifier with a 60 second base backoff.
+        let mut verifier =
+            RequestVerifier::new().with_throttle(RequestThrottle::new(60, 600, 5, 3600));
+
+        // Valid request should be ok.
+        assert_eq!(verifier.verify(&payload, &verified_parties), Ok(()));
+
+        // Request from an unverified party should fail but shouldn't be tracked.
+        assert_eq!(
+            verifier.verify(&payload, &[]),
+            Err(IdentityAuthedRequestError::Unauthorized(
+                Error::UnauthorizedParty
+            ))
+        );
+        assert_eq!(
+            verifier
+                .throttle()
+                .failures(&identity_provider.verifying_key()),
+            0
+        );
+
+        // Forged requests (i.e with a signature from another identity) should fail but shouldn't be tracked,
+        // so that they can't lock out the named identity.
+        for _ in 0..10 {
+            assert_eq!(
+                verifier.verify(&forged_payload, &verified_parties),
+                Err(IdentityAuthedRequestError::Unauthorized(Error::Crypto(
+                    CryptoError::InvalidSignature,
+                )))
+            );
+        }
+        assert_eq!(
+            verifier
+                .throttle()
+                .failures(&identity_provider.verifying_key()),
+            0
+        );
+        assert!(!verifier
+            .throttle()
+            .is_throttled(&identity_provider.verifying_key()));
+
+        // Expired (but authentic) requests should fail and be tracked.
+        assert_eq!(
+            verifier.verify(&expired_payload, &verified_parties),
+            Err(IdentityAuthedRequestError::Expired)
+        );
+        assert_eq!(
+            verifier
+                .throttle()
+                .failures(&identity_provider.verifying_key()),
+            1
+        );
+
+        // Even valid requests are rejected during backoff.
+        assert_eq!(
+            verifier.verify(&payload, &verified_parties),
+            Err(IdentityAuthedRequestError::Throttled)
+        );
+
+        // Resetting consecutive failures (e.g after out-of-band confirmation) lifts the backoff.
+        verifier
+            .throttle_mut()
+            .record_success(&identity_provider.verifying_key());
+        assert_eq!(verifier.verify(&payload, &verified_parties), Ok(()));
+    }
+
+    #[test]
+    fn counter_identity_authed_request_verification_works() {
+        // Generates identity provider.
+        let identity_provider = MockECDSAIdentityProvider::generate();
+        let verified_parties = vec![identity_provider.verifying_key()];
+        let mut counters = RequestCounters::new();
+
+        // Valid request from a verified party should be ok and its counter recorded.
+        let payload = initiate_with_counter("command", 1, &identity_provider);
+        assert_eq!(
+            verify_with_counter(&payload, &verified_parties, &mut counters),
+            Ok(())
+        );
+        assert_eq!(
+            counters.last_seen(&identity_provider.verifying_key()),
+            Some(1)
+        );
+
+        // Replayed request should fail.
+        assert_eq!(
+            verify_with_counter(&payload, &verified_parties, &mut counters),
+            Err(IdentityAuthedRequestError::InvalidCounter)
+        );
+
+        // Request from an unverified party should fail.
+        let payload = initiate_with_counter("command", 2, &identity_provider);
+        assert_eq!(
+            verify_with_counter(&payload, &[], &mut counters),
+            Err(IdentityAuthedRequestError::Unauthorized(
+                Error::UnauthorizedParty
+            ))
+        );
+
+        // Request with a modified counter should fail.
+        let mut modified_payload = payload.clone();
+        modified_payload.counter = Some(3);
+        assert_eq!(
+            verify_with_counter(&modified_payload, &verified_parties, &mut counters),
+            Err(IdentityAuthedRequestError::Unauthorized(Error::Crypto(
+                CryptoError::InvalidSignature,
+            )))
+        );
+
+        // Timestamp based requests aren't valid in monotonic counter mode.
+        let timestamp_payload = initiate("command", &identity_provider);
+        assert_eq!(
+            verify_with_counter(&timestamp_payload, &verified_parties, &mut counters),
+            Err(IdentityAuthedRequestError::InvalidCounter)
+        );
+
+        // Timestamp based requests with a forged counter aren't valid in monotonic counter mode.
+        let mut forged_payload = timestamp_payload.clone();
+        forged_payload.counter = Some(forged_payload.timestamp);
+        assert_eq!(
+            verify_with_counter(&forged_payload, &verified_parties, &mut counters),
+            Err(IdentityAuthedRequestError::Unauthorized(Error::Crypto(
+                CryptoError::InvalidSignature,
+            )))
+        );
+
+        // Requests initiated in monotonic counter mode aren't valid timestamp based requests.
+        assert_eq!(
+            verify(&payload, &verified_parties),
+            Err(IdentityAuthedRequestError::InvalidCounter)
+        );
+
+        // Request with a greater counter should be ok.
+        assert_eq!(
+            verify_with_counter(&payload, &verified_parties, &mut counters),
+            Ok(())
+        );
+        assert_eq!(
+            counters.last_seen(&identity_provider.verifying_key()),
+            Some(2)
+        );
+    }
 }
@@ -2,10 +2,11 @@
 //!
 //! Ref: <https://wamu.tech/specification#identity-challenge>.
 
+use crate::canonical::{CanonicalEncode, ChallengeMessage};
+use crate::crypto;
 use crate::crypto::{Random32Bytes, Signature, VerifyingKey};
 use crate::errors::CryptoError;
 use crate::traits::IdentityProvider;
-use crate::{crypto, utils};
 
 /// Returns a challenge fragment for initiating an identity challenge.
 ///
@@ -21,7 +22,12 @@ pub fn respond(
     challenge_fragments: &[Random32Bytes],
     identity_provider: &impl IdentityProvider,
 ) -> Signature {
-    identity_provider.sign(&challenge_message_bytes(challenge_fragments))
+    identity_provider.sign(
+        &ChallengeMessage {
+            challenge_fragments,
+        }
+        .message_bytes(),
+    )
 }
 
 /// Given an identity challenge response signature, a list of identity challenge fragments and
@@ -29,6 +35,15 @@ pub fn respond(
 /// returns an `Ok` result for valid identity challenge response signature, or an appropriate `Err` result otherwise.
 ///
 /// Ref: <https://wamu.tech/specification#identity-challenge-verification>.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(verifying_key = %crate::utils::to_hex(&verifying_key.key)),
+        err(level = "warn", Debug)
+    )
+)]
 pub fn verify(
     signature: &Signature,
     challenge_fragments: &[Random32Bytes],
@@ -36,29 +51,19 @@ pub fn verify(
 ) -> Result<(), CryptoError> {
     crypto::verify_signature(
         verifying_key,
-        &challenge_message_bytes(challenge_fragments),
+        &ChallengeMessage {
+            challenge_fragments,
+        }
+        .message_bytes(),
         signature,
     )
 }
 
-/// Returns sign-able message bytes for the identity challenge fragments.
-fn challenge_message_bytes(challenge_fragments: &[Random32Bytes]) -> Vec<u8> {
-    // Sort the challenge fragments so that we always get the same challenge regardless of order of receiving challenges.
-    let mut sorted_challenge_fragments = challenge_fragments.to_owned();
-    sorted_challenge_fragments.sort();
-    utils::prefix_message_bytes(&sorted_challenge_fragments.iter().fold(
-        Vec::<u8>::new(),
-        |mut acc, n| {
-            acc.append(&mut n.to_be_bytes().to_vec());
-            acc
-        },
-    ))
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::test_utils::MockECDSAIdentityProvider;
+    use alloc::vec::Vec;
     use crypto_bigint::U256;
 
     #[test]
@@ -1,13 +1,24 @@
 //! Identity rotation implementation.
 //!
+//! **NOTE:** The new identity can use a different signature scheme than the current identity
+//! (e.g ECDSA/Secp256k1 → EdDSA/Curve25519), challenge responses prove control of both identities with their respective schemes.
+//!
 //! Ref: <https://wamu.tech/specification#identity-rotation>.
 
-use crate::crypto::{Random32Bytes, VerifyingKey};
-use crate::errors::{Error, IdentityAuthedRequestError};
-use crate::payloads::{IdentityAuthedRequestPayload, IdentityRotationChallengeResponsePayload};
+use crate::canonical::{CanonicalEncode, RotationCertificateMessage};
+use crate::crypto::{CurveOrder, Random32Bytes, Signature, VerifyingKey};
+use crate::errors::{Error, IdentityAuthedRequestError, RosterLogError};
+use crate::payloads::{
+    CommandCancellationPayload, IdentityAuthedRequestPayload,
+    IdentityRotationChallengeResponsePayload, RotationCertificate,
+};
 use crate::share::{SigningShare, SubShare};
 use crate::traits::IdentityProvider;
-use crate::{identity_authed_request, identity_challenge, share_split_reconstruct, wrappers};
+use crate::{
+    crypto, identity_authed_request, identity_challenge, share_split_reconstruct, time_lock,
+    wrappers,
+};
+use alloc::vec::Vec;
 
 const IDENTITY_ROTATION: &str = "identity-rotation";
 
@@ -73,18 +84,126 @@ pub fn verify_challenge_response(
 /// Given the current "signing share", "sub-share" and identity provider, and the new identity provider,
 /// returns an `Ok` result wrapping the new "signing share" and "sub-share" associated with the new identity provider,
 /// that can be used to reconstruct the current "secret share" given the new identity provider, or an appropriate `Err` result.
-pub fn rotate_signing_and_sub_share(
+pub fn rotate_signing_and_sub_share<Q: CurveOrder>(
     signing_share: &SigningShare,
-    sub_share_b: &SubShare,
+    sub_share_b: &SubShare<Q>,
     current_identity_provider: &impl IdentityProvider,
     new_identity_provider: &impl IdentityProvider,
-) -> Result<(SigningShare, SubShare), Error> {
-    let secret_share = share_split_reconstruct::reconstruct(
+) -> Result<(SigningShare, SubShare<Q>), Error> {
+    let secret_share = share_split_reconstruct::reconstruct_with_order(
         signing_share,
         sub_share_b,
         current_identity_provider,
     )?;
-    share_split_reconstruct::split(&secret_share, new_identity_provider)
+    share_split_reconstruct::split_with_order(&secret_share, new_identity_provider)
+}
+
+/// Given an identity rotation request payload and an identity provider,
+/// returns a command cancellation payload for aborting the identity rotation before it completes.
+pub fn cancel(
+    request: &IdentityAuthedRequestPayload,
+    identity_provider: &impl IdentityProvider,
+) -> CommandCancellationPayload {
+    time_lock::cancel(request, identity_provider)
+}
+
+/// Given a list of command cancellation payloads, an identity rotation request payload,
+/// a list of verifying keys for the other parties and a quorum size,
+/// returns an `Ok` result if the identity rotation is cancelled by either the owner of the rotating identity
+/// or at least a quorum of distinct parties, or an appropriate `Err` result otherwise.
+///
+/// **NOTE:** Invalid cancellations and cancellations from parties not in the roster are ignored.
+pub fn verify_cancellation(
+    cancellations: &[CommandCancellationPayload],
+    request: &IdentityAuthedRequestPayload,
+    verified_parties: &[VerifyingKey],
+    quorum_size: usize,
+) -> Result<(), Error> {
+    let mut cancelling_parties: Vec<&VerifyingKey> = Vec::new();
+    for cancellation in cancellations {
+        if !cancelling_parties.contains(&&cancellation.verifying_key)
+            && time_lock::verify_cancellation(cancellation, request, verified_parties).is_ok()
+        {
+            cancelling_parties.push(&cancellation.verifying_key);
+        }
+    }
+    if cancelling_parties.contains(&&request.verifying_key)
+        || cancelling_parties.len() >= quorum_size
+    {
+        Ok(())
+    } else {
+        Err(Error::UnauthorizedParty)
+    }
+}
+
+/// Given the old and new verifying keys of the rotating party, the roster epoch introduced by the rotation,
+/// the hash of the roster log head before the rotation and an identity provider,
+/// returns a counter-signature of the identity rotation.
+pub fn countersign(
+    old_verifying_key: &VerifyingKey,
+    new_verifying_key: &VerifyingKey,
+    epoch: u64,
+    previous_hash: &[u8; 32],
+    identity_provider: &impl IdentityProvider,
+) -> (VerifyingKey, Signature) {
+    (
+        identity_provider.verifying_key(),
+        identity_provider.sign(
+            &RotationCertificateMessage {
+                old_verifying_key,
+                new_verifying_key,
+                epoch,
+                previous_hash,
+            }
+            .message_bytes(),
+        ),
+    )
+}
+
+/// Given the old and new verifying keys of the rotating party, the roster epoch introduced by the rotation,
+/// the hash of the roster log head before the rotation and a list of counter-signatures,
+/// returns an identity rotation certificate.
+pub fn certificate(
+    old_verifying_key: &VerifyingKey,
+    new_verifying_key: &VerifyingKey,
+    epoch: u64,
+    previous_hash: &[u8; 32],
+    signatures: Vec<(VerifyingKey, Signature)>,
+) -> RotationCertificate {
+    RotationCertificate {
+        old_verifying_key: old_verifying_key.clone(),
+        new_verifying_key: new_verifying_key.clone(),
+        epoch,
+        previous_hash: *previous_hash,
+        signatures,
+    }
+}
+
+/// Given an identity rotation certificate, a list of verifying keys for the roster before the rotation and a quorum size,
+/// returns an `Ok` result if the certificate has valid counter-signatures from at least a quorum of distinct parties in the roster,
+/// or an appropriate `Err` result otherwise.
+///
+/// **NOTE:** Invalid counter-signatures and counter-signatures from parties not in the roster are ignored.
+pub fn verify_certificate(
+    certificate: &RotationCertificate,
+    verified_parties: &[VerifyingKey],
+    quorum_size: usize,
+) -> Result<(), RosterLogError> {
+    let msg = certificate.message_bytes();
+    let mut signers: Vec<&VerifyingKey> = Vec::new();
+    for (verifying_key, signature) in &certificate.signatures {
+        if verified_parties.contains(verifying_key)
+            && !signers.contains(&verifying_key)
+            && crypto::verify_signature(verifying_key, &msg, signature).is_ok()
+        {
+            signers.push(verifying_key);
+        }
+    }
+    if signers.len() < quorum_size {
+        Err(RosterLogError::InsufficientSignatures)
+    } else {
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -93,7 +212,8 @@ mod tests {
     use crate::crypto::Random32Bytes;
     use crate::errors::CryptoError;
     use crate::share::SecretShare;
-    use crate::test_utils::MockECDSAIdentityProvider;
+    use crate::test_utils::{MockECDSAIdentityProvider, MockEdDSAIdentityProvider};
+    use alloc::vec;
     use crypto_bigint::U256;
 
     #[test]
@@ -204,4 +324,129 @@ mod tests {
             &secret_share.to_be_bytes()
         );
     }
+
+    #[test]
+    fn cross_scheme_identity_rotation_works() {
+        // Generates current (ECDSA/Secp256k1) and new (EdDSA/Curve25519) identity providers.
+        let current_identity_provider = MockECDSAIdentityProvider::generate();
+        let new_identity_provider = MockEdDSAIdentityProvider::generate();
+
+        // Generate secret share.
+        let secret_share = SecretShare::from(Random32Bytes::generate_mod_q());
+
+        // Computes "signing share" and "sub-share".
+        let (current_signing_share, current_sub_share_b) =
+            share_split_reconstruct::split(&secret_share, &current_identity_provider).unwrap();
+
+        // Generates identity rotation request payload and challenge fragments.
+        let init_payload = initiate(&current_identity_provider);
+        let challenge_fragments: Vec<Random32Bytes> = (0..3)
+            .map(|_| {
+                verify_request_and_initiate_challenge(
+                    &init_payload,
+                    &[current_identity_provider.verifying_key()],
+                )
+                .unwrap()
+            })
+            .collect();
+
+        // Challenge response proves control of both the current and new identities across schemes.
+        let challenge_payload = challenge_response(
+            &challenge_fragments,
+            &current_identity_provider,
+            &new_identity_provider,
+        );
+        assert_eq!(
+            verify_challenge_response(
+                &challenge_payload,
+                &challenge_fragments,
+                &current_identity_provider.verifying_key(),
+            ),
+            Ok(())
+        );
+
+        // Challenge response with the new signature from the wrong scheme should be rejected.
+        let mut invalid_challenge_payload = challenge_payload.clone();
+        invalid_challenge_payload.new_signature = challenge_payload.current_signature.clone();
+        assert_eq!(
+            verify_challenge_response(
+                &invalid_challenge_payload,
+                &challenge_fragments,
+                &current_identity_provider.verifying_key(),
+            ),
+            Err(Error::Crypto(CryptoError::SchemeMismatch))
+        );
+
+        // Computes the new "signing share" and "sub-share" and verifies reconstruction with the new identity provider.
+        let (new_signing_share, new_sub_share_b) = rotate_signing_and_sub_share(
+            &current_signing_share,
+            &current_sub_share_b,
+            &current_identity_provider,
+            &new_identity_provider,
+        )
+        .unwrap();
+        let reconstructed_secret_share = share_split_reconstruct::reconstruct(
+            &new_signing_share,
+            &new_sub_share_b,
+            &new_identity_provider,
+        )
+        .unwrap();
+        assert_eq!(
+            &reconstructed_secret_share.to_be_bytes(),
+            &secret_share.to_be_bytes()
+        );
+    }
+
+    #[test]
+    fn identity_rotation_cancellation_works() {
+        // Generates identity providers for the rotating party and all other parties.
+        let rotating_identity_provider = MockECDSAIdentityProvider::generate();
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (0..3)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+
+        // Creates a list of verifying keys for all parties.
+        let verified_parties: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .chain([&rotating_identity_provider])
+            .map(IdentityProvider::verifying_key)
+            .collect();
+
+        // Generates identity rotation request payload.
+        let request = initiate(&rotating_identity_provider);
+
+        for (cancellations, expected_result) in [
+            // Cancellation from the owner of the rotating identity should be accepted.
+            (vec![cancel(&request, &rotating_identity_provider)], Ok(())),
+            // Cancellation from less than a quorum of other parties should be rejected.
+            (
+                vec![
+                    cancel(&request, &identity_providers[0]),
+                    cancel(&request, &identity_providers[0]),
+                ],
+                Err(Error::UnauthorizedParty),
+            ),
+            // Cancellations from unverified parties should be ignored.
+            (
+                vec![
+                    cancel(&request, &identity_providers[0]),
+                    cancel(&request, &MockECDSAIdentityProvider::generate()),
+                ],
+                Err(Error::UnauthorizedParty),
+            ),
+            // Cancellation from a quorum of other parties should be accepted.
+            (
+                vec![
+                    cancel(&request, &identity_providers[0]),
+                    cancel(&request, &identity_providers[1]),
+                ],
+                Ok(()),
+            ),
+        ] {
+            assert_eq!(
+                verify_cancellation(&cancellations, &request, &verified_parties, 2),
+                expected_result
+            );
+        }
+    }
 }
@@ -0,0 +1,259 @@
+//! An inbox of pending requests awaiting user action.
+//!
+//! Stores incoming identity authenticated requests, identity challenges and quorum approved requests
+//! (e.g for push-notification-driven co-signers) until they're acted upon or expire.
+
+use crate::crypto::Random32Bytes;
+use crate::identity_authed_request::EXPIRY_TIMEOUT;
+use crate::payloads::{CommandApprovalPayload, IdentityAuthedRequestPayload};
+use crate::utils;
+use alloc::vec::Vec;
+
+/// A pending item awaiting user action.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InboxItem {
+    /// An identity authenticated request awaiting verification.
+    Request(IdentityAuthedRequestPayload),
+    /// An identity challenge (i.e challenge fragments from other parties) for a request awaiting a response.
+    Challenge {
+        /// The identity authenticated request being challenged.
+        request: IdentityAuthedRequestPayload,
+        /// Challenge fragments from other parties.
+        challenge_fragments: Vec<Random32Bytes>,
+    },
+    /// A quorum approved request awaiting approval.
+    QuorumRequest {
+        /// The identity authenticated request awaiting approval.
+        request: IdentityAuthedRequestPayload,
+        /// Approvals collected so far.
+        approvals: Vec<CommandApprovalPayload>,
+    },
+}
+
+impl InboxItem {
+    /// Returns the identity authenticated request for the item.
+    pub fn request(&self) -> &IdentityAuthedRequestPayload {
+        match self {
+            InboxItem::Request(request)
+            | InboxItem::Challenge { request, .. }
+            | InboxItem::QuorumRequest { request, .. } => request,
+        }
+    }
+}
+
+/// An inbox entry (i.e a pending item with its wallet identifier, identifier and timestamps).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InboxEntry {
+    /// Identifier of the entry.
+    pub id: u64,
+    /// Identifier of the wallet the item belongs to.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub wallet_id: [u8; 32],
+    /// UTC timestamp at which the item was received.
+    pub received_at: u64,
+    /// UTC timestamp after which the item expires.
+    pub expires_at: u64,
+    /// The pending item.
+    pub item: InboxItem,
+}
+
+/// An inbox of pending items awaiting user action.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Inbox {
+    /// Pending entries in the order they were received.
+    entries: Vec<InboxEntry>,
+    /// Identifier for the next entry.
+    next_id: u64,
+}
+
+impl Inbox {
+    /// Returns an empty inbox.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Given pending entries (e.g loaded from persistent storage) and the identifier for the next entry,
+    /// returns an inbox with the entries in the order they were received
+    /// (identifiers of new entries always follow the identifiers of the given entries).
+    pub fn from_entries(mut entries: Vec<InboxEntry>, next_id: u64) -> Self {
+        entries.sort_by_key(|entry| entry.id);
+        let next_id = entries
+            .last()
+            .map_or(next_id, |entry| next_id.max(entry.id + 1));
+        Self { entries, next_id }
+    }
+
+    /// Given a wallet identifier and a pending item, adds the item to the inbox and returns its identifier.
+    ///
+    /// **NOTE:** Items expire with their identity authenticated request.
+    pub fn push(&mut self, wallet_id: [u8; 32], item: InboxItem) -> u64 {
+        let expires_at = item.request().timestamp + EXPIRY_TIMEOUT;
+        self.push_with_expiry(wallet_id, item, expires_at)
+    }
+
+    /// Given a wallet identifier, a pending item and a UTC timestamp after which the item expires,
+    /// adds the item to the inbox and returns its identifier.
+    pub fn push_with_expiry(
+        &mut self,
+        wallet_id: [u8; 32],
+        item: InboxItem,
+        expires_at: u64,
+    ) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.entries.push(InboxEntry {
+            id,
+            wallet_id,
+            received_at: utils::unix_timestamp(),
+            expires_at,
+            item,
+        });
+        id
+    }
+
+    /// Returns the entry with the given identifier (if any).
+    pub fn get(&self, id: u64) -> Option<&InboxEntry> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+
+    /// Returns a mutable reference to the entry with the given identifier (if any) e.g for adding approvals.
+    pub fn get_mut(&mut self, id: u64) -> Option<&mut InboxEntry> {
+        self.entries.iter_mut().find(|entry| entry.id == id)
+    }
+
+    /// Removes and returns the entry with the given identifier (if any) e.g after it's acted upon.
+    pub fn remove(&mut self, id: u64) -> Option<InboxEntry> {
+        let position = self.entries.iter().position(|entry| entry.id == id)?;
+        Some(self.entries.remove(position))
+    }
+
+    /// Returns all pending entries in the order they were received.
+    pub fn entries(&self) -> &[InboxEntry] {
+        &self.entries
+    }
+
+    /// Returns the pending entries for the wallet in the order they were received.
+    pub fn entries_for_wallet<'a>(
+        &'a self,
+        wallet_id: &'a [u8; 32],
+    ) -> impl Iterator<Item = &'a InboxEntry> {
+        self.entries
+            .iter()
+            .filter(move |entry| &entry.wallet_id == wallet_id)
+    }
+
+    /// Returns the number of pending entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if there are no pending entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Removes and returns all expired entries.
+    pub fn sweep_expired(&mut self) -> Vec<InboxEntry> {
+        self.sweep_expired_at(utils::unix_timestamp())
+    }
+
+    /// Given a UTC timestamp, removes and returns all entries that expired before it.
+    pub fn sweep_expired_at(&mut self, now: u64) -> Vec<InboxEntry> {
+        let (expired, pending) = self
+            .entries
+            .drain(..)
+            .partition(|entry| entry.expires_at < now);
+        self.entries = pending;
+        expired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity_authed_request;
+    use crate::test_utils::MockECDSAIdentityProvider;
+    use alloc::vec;
+
+    #[test]
+    fn inbox_works() {
+        // Generates identity provider and requests.
+        let identity_provider = MockECDSAIdentityProvider::generate();
+        let request = identity_authed_request::initiate("command", &identity_provider);
+        let (wallet_a, wallet_b) = ([1; 32], [2; 32]);
+
+        // Adds pending items for multiple wallets.
+        let mut inbox = Inbox::new();
+        let request_id = inbox.push(wallet_a, InboxItem::Request(request.clone()));
+        let challenge_id = inbox.push(
+            wallet_b,
+            InboxItem::Challenge {
+                request: request.clone(),
+                challenge_fragments: vec![Random32Bytes::generate()],
+            },
+        );
+        let quorum_id = inbox.push_with_expiry(
+            wallet_a,
+            InboxItem::QuorumRequest {
+                request,
+                approvals: Vec::new(),
+            },
+            0,
+        );
+        assert_eq!(inbox.len(), 3);
+
+        // Filters pending items by wallet.
+        let wallet_a_ids: Vec<u64> = inbox
+            .entries_for_wallet(&wallet_a)
+            .map(|entry| entry.id)
+            .collect();
+        assert_eq!(wallet_a_ids, vec![request_id, quorum_id]);
+
+        // Sweeps expired items.
+        let expired = inbox.sweep_expired();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].id, quorum_id);
+        assert!(inbox.get(quorum_id).is_none());
+
+        // Removes items that were acted upon.
+        assert!(matches!(
+            inbox.remove(challenge_id).map(|entry| entry.item),
+            Some(InboxItem::Challenge { .. })
+        ));
+        assert_eq!(inbox.len(), 1);
+
+        // Everything expires eventually.
+        assert_eq!(inbox.sweep_expired_at(u64::MAX).len(), 1);
+        assert!(inbox.is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn inbox_serialization_works() {
+        // Generates inbox with a pending item.
+        let identity_provider = MockECDSAIdentityProvider::generate();
+        let mut inbox = Inbox::new();
+        let id = inbox.push(
+            [1; 32],
+            InboxItem::Request(identity_authed_request::initiate(
+                "command",
+                &identity_provider,
+            )),
+        );
+
+        // Inbox roundtrips through a serialized representation.
+        let json = serde_json::to_string(&inbox).unwrap();
+        let mut decoded: Inbox = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.get(id).unwrap().item.request().command, "command");
+        assert_eq!(
+            decoded.push(
+                [1; 32],
+                InboxItem::Request(inbox.entries()[0].item.request().clone())
+            ),
+            id + 1
+        );
+    }
+}
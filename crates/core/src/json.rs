@@ -0,0 +1,173 @@
+//! JSON encoding for payloads with a versioned envelope (i.e `{"version": 1, "type": "...", "payload": {...}}`).
+//!
+//! Allows web frontends and REST relays to consume payloads without a binary codec.
+//!
+//! **NOTE:** Payload field names match the field names of the Rust types,
+//! byte fields are encoded as `0x` prefixed hex strings and enums are encoded as their variant names (e.g `"ECDSA"`).
+//! Any breaking change to this format increments [`JSON_VERSION`].
+
+use alloc::string::String;
+use alloc::string::ToString;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::audit_log::AuditLog;
+use crate::error_report::ErrorReport;
+use crate::errors::JsonError;
+use crate::payloads::{
+    ApprovalDelegationPayload, CommandApprovalPayload, CommandCancellationPayload,
+    DestructionCertificate, DeviceMigrationPackage, DeviceMigrationRequestPayload,
+    EncryptedShareBackup, IdentityAuthedRequestPayload, IdentityRotationChallengeResponsePayload,
+    KeystoreExportRecord, OfflineRequestPayload, QuorumApprovedChallengeResponsePayload,
+    RotationCertificate, VersionOfferPayload,
+};
+use crate::policy::Policy;
+use crate::snap::{SnapRequest, SnapResponse};
+
+/// The current version of the JSON encoding.
+pub const JSON_VERSION: u32 = 1;
+
+/// A payload with a JSON encoding.
+pub trait JsonPayload: Serialize + DeserializeOwned {
+    /// The payload type name in the JSON envelope.
+    const TYPE: &'static str;
+
+    /// Returns the JSON encoding of the payload in a versioned envelope.
+    fn to_json(&self) -> String {
+        serde_json::to_string(&JsonEnvelope {
+            version: JSON_VERSION,
+            r#type: Self::TYPE.to_string(),
+            payload: self,
+        })
+        .expect("Payloads are always serializable")
+    }
+
+    /// Returns the payload decoded from its JSON encoding in a versioned envelope, or an appropriate error otherwise.
+    fn from_json(json: &str) -> Result<Self, JsonError> {
+        let envelope: JsonEnvelope<serde_json::Value> =
+            serde_json::from_str(json).map_err(|_| JsonError::InvalidJson)?;
+        if envelope.version != JSON_VERSION {
+            Err(JsonError::UnsupportedVersion)
+        } else if envelope.r#type != Self::TYPE {
+            Err(JsonError::TypeMismatch)
+        } else {
+            serde_json::from_value(envelope.payload).map_err(|_| JsonError::InvalidJson)
+        }
+    }
+}
+
+/// A versioned JSON envelope.
+#[derive(Serialize, Deserialize)]
+struct JsonEnvelope<T> {
+    /// The version of the JSON encoding.
+    version: u32,
+    /// The payload type name.
+    r#type: String,
+    /// The payload.
+    payload: T,
+}
+
+/// Implements `JsonPayload` for the payload type with the given type name.
+macro_rules! impl_json_payload {
+    ($payload_type:ty, $type_name:literal) => {
+        impl JsonPayload for $payload_type {
+            const TYPE: &'static str = $type_name;
+        }
+    };
+}
+
+impl_json_payload!(IdentityAuthedRequestPayload, "identity_authed_request");
+impl_json_payload!(
+    IdentityRotationChallengeResponsePayload,
+    "identity_rotation_challenge_response"
+);
+impl_json_payload!(RotationCertificate, "rotation_certificate");
+impl_json_payload!(CommandApprovalPayload, "command_approval");
+impl_json_payload!(ApprovalDelegationPayload, "approval_delegation");
+impl_json_payload!(CommandCancellationPayload, "command_cancellation");
+impl_json_payload!(
+    QuorumApprovedChallengeResponsePayload,
+    "quorum_approved_challenge_response"
+);
+impl_json_payload!(EncryptedShareBackup, "encrypted_share_backup");
+impl_json_payload!(VersionOfferPayload, "version_offer");
+impl_json_payload!(OfflineRequestPayload, "offline_request");
+impl_json_payload!(DeviceMigrationRequestPayload, "device_migration_request");
+impl_json_payload!(DeviceMigrationPackage, "device_migration_package");
+impl_json_payload!(DestructionCertificate, "destruction_certificate");
+impl_json_payload!(KeystoreExportRecord, "keystore_export_record");
+impl_json_payload!(Policy, "policy");
+impl_json_payload!(ErrorReport, "error_report");
+impl_json_payload!(AuditLog, "audit_log");
+impl_json_payload!(SnapRequest, "snap_request");
+impl_json_payload!(SnapResponse, "snap_response");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockECDSAIdentityProvider;
+    use crate::{identity_authed_request, utils};
+    use alloc::format;
+    use alloc::vec;
+
+    #[test]
+    fn json_encoding_works() {
+        // Generates identity provider.
+        let identity_provider = MockECDSAIdentityProvider::generate();
+
+        // Identity authenticated requests round trip.
+        let request = identity_authed_request::initiate("command", &identity_provider);
+        let json = request.to_json();
+        let decoded = IdentityAuthedRequestPayload::from_json(&json).unwrap();
+        assert_eq!(decoded.command, request.command);
+        assert_eq!(decoded.verifying_key, request.verifying_key);
+        assert_eq!(decoded.timestamp, request.timestamp);
+        assert_eq!(decoded.signature, request.signature);
+
+        // Field names and byte encodings are stable.
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["version"], JSON_VERSION);
+        assert_eq!(value["type"], "identity_authed_request");
+        assert_eq!(value["payload"]["command"], "command");
+        assert_eq!(value["payload"]["timestamp"], request.timestamp);
+        assert_eq!(
+            value["payload"]["verifying_key"]["key"],
+            format!("0x{}", utils::to_hex(&request.verifying_key.key))
+        );
+        assert_eq!(value["payload"]["verifying_key"]["algo"], "ECDSA");
+        assert_eq!(
+            value["payload"]["signature"]["sig"],
+            format!("0x{}", utils::to_hex(&request.signature.sig))
+        );
+
+        // Encrypted share backups round trip.
+        let backup = EncryptedShareBackup {
+            signing_share: vec![1, 2],
+            sub_share: (vec![3], vec![4, 5]),
+            nonce: vec![6],
+        };
+        let json = backup.to_json();
+        assert!(json.contains(r#""sub_share":["0x03","0x0405"]"#));
+        let decoded = EncryptedShareBackup::from_json(&json).unwrap();
+        assert_eq!(decoded.signing_share, backup.signing_share);
+        assert_eq!(decoded.sub_share, backup.sub_share);
+        assert_eq!(decoded.nonce, backup.nonce);
+
+        // Unsupported versions, mismatched types and invalid payloads are rejected.
+        let json = request.to_json();
+        assert!(matches!(
+            IdentityAuthedRequestPayload::from_json(
+                &json.replace(r#""version":1"#, r#""version":2"#)
+            ),
+            Err(JsonError::UnsupportedVersion)
+        ));
+        assert!(matches!(
+            CommandApprovalPayload::from_json(&json),
+            Err(JsonError::TypeMismatch)
+        ));
+        assert!(matches!(
+            IdentityAuthedRequestPayload::from_json(&json.replace(r#""key":"0x"#, r#""key":"0xz"#)),
+            Err(JsonError::InvalidJson)
+        ));
+    }
+}
@@ -0,0 +1,445 @@
+//! A [JSON-RPC 2.0](https://www.jsonrpc.org/specification) adapter for wallet frontends (e.g Electron and web apps).
+//!
+//! Exposes wallet operations implemented by a [`WalletBackend`] (e.g a local Wamu node built on the wrappers and a session manager)
+//! as JSON-RPC methods, so frontends can drive the node over any transport (e.g HTTP, WebSocket or IPC) without FFI.
+//!
+//! Methods (with by-name params) are:
+//! - `create_wallet` (`{"threshold": 1, "participants": [<verifying key>, ...]}`) returns `{"wallet_id": "0x..."}`.
+//! - `list_pending_requests` (`{"wallet_id": "0x..."}` or `{}` for all wallets) returns a list of inbox entries.
+//! - `approve` (`{"request_id": 1}`) returns `null`.
+//! - `sign` (`{"wallet_id": "0x...", "message": "0x..."}`) returns `{"session_id": "0x..."}`.
+//! - `rotate_identity` (`{"wallet_id": "0x..."}`) returns `{"session_id": "0x..."}`.
+//!
+//! **NOTE:** Backend errors are returned as JSON-RPC errors with code [`SERVER_ERROR`] and an [`ErrorReport`] as data.
+
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::crypto::VerifyingKey;
+use crate::error_report::ErrorReport;
+use crate::inbox::InboxEntry;
+
+/// Invalid JSON was received.
+pub const PARSE_ERROR: i64 = -32700;
+/// The JSON sent is not a valid request object.
+pub const INVALID_REQUEST: i64 = -32600;
+/// The method does not exist.
+pub const METHOD_NOT_FOUND: i64 = -32601;
+/// Invalid method parameters.
+pub const INVALID_PARAMS: i64 = -32602;
+/// An internal JSON-RPC error.
+pub const INTERNAL_ERROR: i64 = -32603;
+/// A wallet backend error.
+pub const SERVER_ERROR: i64 = -32000;
+
+/// Wallet operations exposed to frontends.
+///
+/// **NOTE:** Operations that require other parties (i.e wallet creation, signing and identity rotation)
+/// should start a session (e.g with a session manager) and return without waiting for it to finish.
+pub trait WalletBackend {
+    /// Given a threshold and the verifying keys of all parties,
+    /// starts key generation for a new wallet and returns its wallet identifier.
+    fn create_wallet(
+        &mut self,
+        threshold: u16,
+        participants: Vec<VerifyingKey>,
+    ) -> Result<[u8; 32], ErrorReport>;
+
+    /// Returns pending requests awaiting user action (optionally, only for a given wallet).
+    fn list_pending_requests(
+        &mut self,
+        wallet_id: Option<[u8; 32]>,
+    ) -> Result<Vec<InboxEntry>, ErrorReport>;
+
+    /// Approves a pending request.
+    fn approve(&mut self, request_id: u64) -> Result<(), ErrorReport>;
+
+    /// Starts a signing session for a message and returns its session identifier.
+    fn sign(&mut self, wallet_id: [u8; 32], message: Vec<u8>) -> Result<[u8; 32], ErrorReport>;
+
+    /// Starts an identity rotation session and returns its session identifier.
+    fn rotate_identity(&mut self, wallet_id: [u8; 32]) -> Result<[u8; 32], ErrorReport>;
+}
+
+/// A 32 byte identifier (i.e a wallet or session identifier).
+#[derive(Serialize, Deserialize)]
+struct Id(#[serde(with = "crate::serde_hex")] [u8; 32]);
+
+/// Params of `create_wallet`.
+#[derive(Deserialize)]
+struct CreateWalletParams {
+    threshold: u16,
+    participants: Vec<VerifyingKey>,
+}
+
+/// Params of `list_pending_requests`.
+#[derive(Deserialize)]
+struct ListPendingRequestsParams {
+    #[serde(default)]
+    wallet_id: Option<Id>,
+}
+
+/// Params of `approve`.
+#[derive(Deserialize)]
+struct ApproveParams {
+    request_id: u64,
+}
+
+/// Params of `sign`.
+#[derive(Deserialize)]
+struct SignParams {
+    wallet_id: Id,
+    #[serde(with = "crate::serde_hex")]
+    message: Vec<u8>,
+}
+
+/// Params of `rotate_identity`.
+#[derive(Deserialize)]
+struct RotateIdentityParams {
+    wallet_id: Id,
+}
+
+/// A JSON-RPC error object.
+struct RpcError {
+    code: i64,
+    message: String,
+    data: Option<Value>,
+}
+
+impl RpcError {
+    /// Returns an error object with the given code and message (and no data).
+    fn new(code: i64, message: &str) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+            data: None,
+        }
+    }
+}
+
+impl From<ErrorReport> for RpcError {
+    fn from(report: ErrorReport) -> Self {
+        Self {
+            code: SERVER_ERROR,
+            message: report.kind.clone(),
+            data: serde_json::to_value(report).ok(),
+        }
+    }
+}
+
+/// Given a wallet backend and a JSON-RPC request (or batch of requests),
+/// calls the requested methods and returns the JSON-RPC response (or batch of responses),
+/// or `None` for notifications (i.e requests without an id) and batches of notifications.
+pub fn handle_request<B: WalletBackend>(backend: &mut B, request: &str) -> Option<String> {
+    let response = match serde_json::from_str::<Value>(request) {
+        Ok(Value::Array(requests)) if !requests.is_empty() => {
+            let responses: Vec<Value> = requests
+                .into_iter()
+                .filter_map(|request| handle_call(backend, request))
+                .collect();
+            (!responses.is_empty()).then_some(Value::Array(responses))
+        }
+        Ok(Value::Array(_)) => Some(error_response(
+            Value::Null,
+            RpcError::new(INVALID_REQUEST, "Invalid Request"),
+        )),
+        Ok(request) => handle_call(backend, request),
+        Err(_) => Some(error_response(
+            Value::Null,
+            RpcError::new(PARSE_ERROR, "Parse error"),
+        )),
+    };
+    response.map(|response| response.to_string())
+}
+
+// Calls the requested method and returns the response (or `None` for notifications).
+fn handle_call<B: WalletBackend>(backend: &mut B, request: Value) -> Option<Value> {
+    let Value::Object(mut request) = request else {
+        return Some(error_response(
+            Value::Null,
+            RpcError::new(INVALID_REQUEST, "Invalid Request"),
+        ));
+    };
+    // Only string, number and null ids are valid.
+    let id = request.remove("id");
+    let is_valid_id = !matches!(
+        id,
+        Some(Value::Bool(_) | Value::Array(_) | Value::Object(_))
+    );
+    let method = request.remove("method");
+    let params = request.remove("params").unwrap_or(Value::Null);
+    if !is_valid_id
+        || request.get("jsonrpc") != Some(&Value::from("2.0"))
+        || !matches!(params, Value::Object(_) | Value::Array(_) | Value::Null)
+    {
+        return Some(error_response(
+            id.filter(|_| is_valid_id).unwrap_or(Value::Null),
+            RpcError::new(INVALID_REQUEST, "Invalid Request"),
+        ));
+    }
+    let Some(Value::String(method)) = method else {
+        return Some(error_response(
+            id.unwrap_or(Value::Null),
+            RpcError::new(INVALID_REQUEST, "Invalid Request"),
+        ));
+    };
+
+    let result = call(backend, &method, params);
+    // Notifications are never answered.
+    let id = id?;
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+        Err(error) => error_response(id, error),
+    })
+}
+
+// Calls the method with the given params and returns its result.
+fn call<B: WalletBackend>(backend: &mut B, method: &str, params: Value) -> Result<Value, RpcError> {
+    match method {
+        "create_wallet" => {
+            let params: CreateWalletParams = parse_params(params)?;
+            let wallet_id = backend.create_wallet(params.threshold, params.participants)?;
+            Ok(json!({ "wallet_id": Id(wallet_id) }))
+        }
+        "list_pending_requests" => {
+            let params: ListPendingRequestsParams = parse_params(params)?;
+            let entries = backend.list_pending_requests(params.wallet_id.map(|id| id.0))?;
+            to_value(entries)
+        }
+        "approve" => {
+            let params: ApproveParams = parse_params(params)?;
+            backend.approve(params.request_id)?;
+            Ok(Value::Null)
+        }
+        "sign" => {
+            let params: SignParams = parse_params(params)?;
+            let session_id = backend.sign(params.wallet_id.0, params.message)?;
+            Ok(json!({ "session_id": Id(session_id) }))
+        }
+        "rotate_identity" => {
+            let params: RotateIdentityParams = parse_params(params)?;
+            let session_id = backend.rotate_identity(params.wallet_id.0)?;
+            Ok(json!({ "session_id": Id(session_id) }))
+        }
+        _ => Err(RpcError::new(METHOD_NOT_FOUND, "Method not found")),
+    }
+}
+
+// Decodes by-name params (i.e missing params are decoded as an empty object).
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    let params = match params {
+        Value::Null => Value::Object(serde_json::Map::new()),
+        Value::Object(_) => params,
+        // Only by-name params are supported.
+        _ => return Err(RpcError::new(INVALID_PARAMS, "Invalid params")),
+    };
+    serde_json::from_value(params).map_err(|_| RpcError::new(INVALID_PARAMS, "Invalid params"))
+}
+
+// Encodes a result.
+fn to_value<T: Serialize>(result: T) -> Result<Value, RpcError> {
+    serde_json::to_value(result).map_err(|_| RpcError::new(INTERNAL_ERROR, "Internal error"))
+}
+
+// Returns an error response for the request with the given id.
+fn error_response(id: Value, error: RpcError) -> Value {
+    let mut error_object = json!({ "code": error.code, "message": error.message });
+    if let Some(data) = error.data {
+        error_object["data"] = data;
+    }
+    json!({ "jsonrpc": "2.0", "error": error_object, "id": id })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inbox::{Inbox, InboxItem};
+    use crate::test_utils::MockECDSAIdentityProvider;
+    use crate::{identity_authed_request, IdentityProvider};
+    use alloc::format;
+    use alloc::vec;
+
+    /// A wallet backend with an in-memory inbox.
+    #[derive(Default)]
+    struct MockBackend {
+        inbox: Inbox,
+        approved: Vec<u64>,
+        signed: Vec<([u8; 32], Vec<u8>)>,
+    }
+
+    impl WalletBackend for MockBackend {
+        fn create_wallet(
+            &mut self,
+            threshold: u16,
+            participants: Vec<VerifyingKey>,
+        ) -> Result<[u8; 32], ErrorReport> {
+            if threshold as usize >= participants.len() {
+                return Err(ErrorReport::new(&"InvalidThreshold"));
+            }
+            Ok([1; 32])
+        }
+
+        fn list_pending_requests(
+            &mut self,
+            wallet_id: Option<[u8; 32]>,
+        ) -> Result<Vec<InboxEntry>, ErrorReport> {
+            Ok(self
+                .inbox
+                .entries()
+                .iter()
+                .filter(|entry| wallet_id.is_none() || wallet_id == Some(entry.wallet_id))
+                .cloned()
+                .collect())
+        }
+
+        fn approve(&mut self, request_id: u64) -> Result<(), ErrorReport> {
+            self.inbox
+                .remove(request_id)
+                .ok_or(ErrorReport::new(&"UnknownRequest"))?;
+            self.approved.push(request_id);
+            Ok(())
+        }
+
+        fn sign(&mut self, wallet_id: [u8; 32], message: Vec<u8>) -> Result<[u8; 32], ErrorReport> {
+            self.signed.push((wallet_id, message));
+            Ok([2; 32])
+        }
+
+        fn rotate_identity(&mut self, _: [u8; 32]) -> Result<[u8; 32], ErrorReport> {
+            Ok([3; 32])
+        }
+    }
+
+    // Sends a request and returns the decoded response.
+    fn send(backend: &mut MockBackend, request: Value) -> Value {
+        serde_json::from_str(&handle_request(backend, &request.to_string()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn json_rpc_works() {
+        let mut backend = MockBackend::default();
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (0..2)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let participants: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let request_id = backend.inbox.push(
+            [1; 32],
+            InboxItem::Request(identity_authed_request::initiate(
+                "sign",
+                &identity_providers[1],
+            )),
+        );
+
+        // Calls wallet operations.
+        let hex_id = |byte: u8| format!("0x{}", crate::utils::to_hex(&[byte; 32]));
+        let response = send(
+            &mut backend,
+            json!({
+                "jsonrpc": "2.0",
+                "method": "create_wallet",
+                "params": { "threshold": 1, "participants": participants },
+                "id": 1
+            }),
+        );
+        assert_eq!(
+            response,
+            json!({ "jsonrpc": "2.0", "result": { "wallet_id": hex_id(1) }, "id": 1 })
+        );
+        let response = send(
+            &mut backend,
+            json!({ "jsonrpc": "2.0", "method": "list_pending_requests", "id": "2" }),
+        );
+        assert_eq!(response["id"], "2");
+        assert_eq!(response["result"][0]["id"], request_id);
+        assert_eq!(response["result"][0]["wallet_id"], hex_id(1));
+        let response = send(
+            &mut backend,
+            json!({
+                "jsonrpc": "2.0",
+                "method": "list_pending_requests",
+                "params": { "wallet_id": hex_id(2) },
+                "id": 3
+            }),
+        );
+        assert_eq!(response["result"], json!([]));
+        let response = send(
+            &mut backend,
+            json!({
+                "jsonrpc": "2.0",
+                "method": "sign",
+                "params": { "wallet_id": hex_id(1), "message": "0x0102" },
+                "id": 4
+            }),
+        );
+        assert_eq!(response["result"]["session_id"], hex_id(2));
+        assert_eq!(backend.signed, vec![([1; 32], vec![1, 2])]);
+
+        // Notifications are not answered, while batches are answered with a list of responses.
+        assert!(handle_request(
+            &mut backend,
+            &json!({
+                "jsonrpc": "2.0",
+                "method": "approve",
+                "params": { "request_id": request_id }
+            })
+            .to_string()
+        )
+        .is_none());
+        assert_eq!(backend.approved, vec![request_id]);
+        let response = send(
+            &mut backend,
+            json!([
+                {
+                    "jsonrpc": "2.0",
+                    "method": "rotate_identity",
+                    "params": { "wallet_id": hex_id(1) },
+                    "id": 5
+                },
+                { "jsonrpc": "2.0", "method": "list_pending_requests" },
+                { "jsonrpc": "2.0", "method": "approve", "params": { "request_id": request_id }, "id": 6 }
+            ]),
+        );
+        assert_eq!(response.as_array().unwrap().len(), 2);
+        assert_eq!(response[0]["result"]["session_id"], hex_id(3));
+        // Backend errors include an error report.
+        assert_eq!(response[1]["id"], 6);
+        assert_eq!(response[1]["error"]["code"], SERVER_ERROR);
+        assert_eq!(response[1]["error"]["data"]["kind"], r#""UnknownRequest""#);
+
+        // Invalid requests are rejected with the appropriate error codes.
+        assert_eq!(
+            serde_json::from_str::<Value>(&handle_request(&mut backend, "{").unwrap()).unwrap()
+                ["error"]["code"],
+            PARSE_ERROR
+        );
+        for (request, code) in [
+            (json!([]), INVALID_REQUEST),
+            (json!({ "method": "sign", "id": 1 }), INVALID_REQUEST),
+            (
+                json!({ "jsonrpc": "2.0", "method": 1, "id": 1 }),
+                INVALID_REQUEST,
+            ),
+            (
+                json!({ "jsonrpc": "2.0", "method": "unknown", "id": 1 }),
+                METHOD_NOT_FOUND,
+            ),
+            (
+                json!({ "jsonrpc": "2.0", "method": "sign", "params": { "wallet_id": "0x01" }, "id": 1 }),
+                INVALID_PARAMS,
+            ),
+            (
+                json!({ "jsonrpc": "2.0", "method": "approve", "params": [1], "id": 1 }),
+                INVALID_PARAMS,
+            ),
+        ] {
+            assert_eq!(send(&mut backend, request)["error"]["code"], code);
+        }
+    }
+}
@@ -0,0 +1,267 @@
+//! Emergency quorum approved key destruction (i.e "scorched earth" wallet off-boarding).
+//!
+//! Protocol:
+//! 1. A party initiates a key destruction request (see [`initiate`]) which is approved by a quorum of parties
+//!    (see [`verify_request_and_initiate_challenge`] and [`quorum_approved_request`]).
+//! 2. Each party verifies the quorum approval (see [`verify_approval`]), wipes its share material from its key store
+//!    and returns a signed destruction attestation (see [`wipe`]).
+//! 3. The attestations from all parties are aggregated into a destruction certificate (see [`certificate`]),
+//!    which auditors can verify against the roster (see [`verify_certificate`]), e.g for regulated custody off-boarding.
+//!
+//! **NOTE:** A destruction attestation only proves that a party deleted its share material from its key store,
+//! it can't prove that no other copies exist (e.g encrypted share backups, see [`crate::share_recovery_backup`]).
+
+use crate::canonical::{CanonicalEncode, DestructionAttestationMessage};
+use crate::crypto;
+use crate::crypto::{Signature, VerifyingKey};
+use crate::errors::{IdentityAuthedRequestError, KeyDestructionError};
+use crate::key_store::KeyStore;
+use crate::payloads::{
+    CommandApprovalPayload, DestructionCertificate, IdentityAuthedRequestPayload,
+    QuorumApprovedChallengeResponsePayload,
+};
+use crate::quorum_approved_request;
+use crate::traits::IdentityProvider;
+use alloc::vec::Vec;
+
+const KEY_DESTRUCTION: &str = "key-destruction";
+
+/// Given an identity provider, returns the payload for initiating a key destruction request.
+pub fn initiate(identity_provider: &impl IdentityProvider) -> IdentityAuthedRequestPayload {
+    quorum_approved_request::initiate(KEY_DESTRUCTION, identity_provider)
+}
+
+/// Given a key destruction request payload, an identity provider and a list of verifying keys for the other parties,
+/// returns an ok result with a command approval payload for a valid request or an appropriate error result for an invalid request.
+pub fn verify_request_and_initiate_challenge(
+    request: &IdentityAuthedRequestPayload,
+    identity_provider: &impl IdentityProvider,
+    verified_parties: &[VerifyingKey],
+) -> Result<CommandApprovalPayload, IdentityAuthedRequestError> {
+    quorum_approved_request::verify_request_and_initiate_challenge(
+        KEY_DESTRUCTION,
+        request,
+        identity_provider,
+        verified_parties,
+    )
+}
+
+/// Given the quorum approved challenge response of the initiating party, a list of command approval payloads,
+/// a key destruction request payload, a quorum size and a list of verifying keys for the other parties,
+/// returns an `Ok` result if the key destruction is approved by at least a quorum of parties, or an appropriate `Err` result otherwise.
+pub fn verify_approval(
+    response: &QuorumApprovedChallengeResponsePayload,
+    approvals: &[CommandApprovalPayload],
+    request: &IdentityAuthedRequestPayload,
+    quorum_size: usize,
+    verified_parties: &[VerifyingKey],
+) -> Result<(), KeyDestructionError> {
+    Ok(quorum_approved_request::verify_challenge_response(
+        response,
+        approvals,
+        &request.verifying_key,
+        request,
+        quorum_size,
+        verified_parties,
+    )?)
+}
+
+/// Given a wallet identifier, a key destruction request payload, a key store and an identity provider,
+/// deletes the share material for the wallet from the key store and returns the destruction attestation,
+/// or the key store error.
+///
+/// **NOTE:** This must only be called after verifying the quorum approval (see [`verify_approval`]).
+pub fn wipe<K: KeyStore>(
+    wallet_id: &[u8; 32],
+    request: &IdentityAuthedRequestPayload,
+    key_store: &mut K,
+    identity_provider: &impl IdentityProvider,
+) -> Result<(VerifyingKey, Signature), K::Error> {
+    key_store.delete(wallet_id)?;
+    Ok((
+        identity_provider.verifying_key(),
+        identity_provider
+            .sign(&DestructionAttestationMessage { wallet_id, request }.message_bytes()),
+    ))
+}
+
+/// Given a wallet identifier, a key destruction request payload and a list of destruction attestations,
+/// returns a destruction certificate.
+pub fn certificate(
+    wallet_id: &[u8; 32],
+    request: &IdentityAuthedRequestPayload,
+    attestations: Vec<(VerifyingKey, Signature)>,
+) -> DestructionCertificate {
+    DestructionCertificate {
+        wallet_id: *wallet_id,
+        request: request.clone(),
+        attestations,
+    }
+}
+
+/// Given a destruction certificate, a wallet identifier and a list of verifying keys for all parties,
+/// returns an `Ok` result if the certificate has valid destruction attestations from all parties,
+/// or an appropriate `Err` result otherwise.
+///
+/// **NOTE:** Invalid attestations and attestations from parties not in the roster are ignored.
+pub fn verify_certificate(
+    certificate: &DestructionCertificate,
+    wallet_id: &[u8; 32],
+    verified_parties: &[VerifyingKey],
+) -> Result<(), KeyDestructionError> {
+    if &certificate.wallet_id != wallet_id {
+        return Err(KeyDestructionError::WalletMismatch);
+    }
+    let msg = DestructionAttestationMessage {
+        wallet_id,
+        request: &certificate.request,
+    }
+    .message_bytes();
+    let all_attested = verified_parties.iter().all(|party| {
+        certificate
+            .attestations
+            .iter()
+            .any(|(verifying_key, signature)| {
+                verifying_key == party
+                    && crypto::verify_signature(verifying_key, &msg, signature).is_ok()
+            })
+    });
+    if certificate.request.command == KEY_DESTRUCTION && all_attested {
+        Ok(())
+    } else {
+        Err(KeyDestructionError::InsufficientAttestations)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::crypto::Random32Bytes;
+    use crate::errors::QuorumApprovedRequestError;
+    use crate::key_store::{FileKeyStore, StoredKey};
+    use crate::share::SecretShare;
+    use crate::test_utils::MockECDSAIdentityProvider;
+    use crate::{share_split_reconstruct, utils};
+    use alloc::format;
+
+    #[test]
+    fn key_destruction_works() {
+        // Generates identity providers, a roster and a key store with share material for each party.
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (0..3)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let verified_parties: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let wallet_id = [1u8; 32];
+        let dirs: Vec<_> = (0..3)
+            .map(|_| {
+                std::env::temp_dir().join(format!(
+                    "wamu-key-destruction-{}",
+                    utils::to_hex(&Random32Bytes::generate().to_be_bytes())
+                ))
+            })
+            .collect();
+        let mut key_stores: Vec<FileKeyStore> = dirs
+            .iter()
+            .zip(&identity_providers)
+            .map(|(dir, identity_provider)| {
+                let mut key_store = FileKeyStore::new(dir, identity_provider).unwrap();
+                let secret_share = SecretShare::from(Random32Bytes::generate_mod_q());
+                let (signing_share, sub_share) =
+                    share_split_reconstruct::split(&secret_share, identity_provider).unwrap();
+                key_store
+                    .save(
+                        &wallet_id,
+                        &StoredKey {
+                            signing_share,
+                            sub_share,
+                            local_key: Vec::new(),
+                            roster: verified_parties.clone(),
+                            epoch: 0,
+                        },
+                    )
+                    .unwrap();
+                key_store
+            })
+            .collect();
+
+        // Initiates and approves a key destruction request (by all parties).
+        let request = initiate(&identity_providers[0]);
+        let approvals: Vec<CommandApprovalPayload> = identity_providers[1..]
+            .iter()
+            .map(|identity_provider| {
+                verify_request_and_initiate_challenge(
+                    &request,
+                    identity_provider,
+                    &verified_parties,
+                )
+                .unwrap()
+            })
+            .collect();
+        let response = quorum_approved_request::challenge_response(
+            &approvals,
+            &identity_providers[0],
+            &request,
+            3,
+            &verified_parties,
+        )
+        .unwrap();
+
+        // Insufficient approvals are rejected.
+        assert!(matches!(
+            verify_approval(&response, &approvals[..1], &request, 3, &verified_parties),
+            Err(KeyDestructionError::Approval(
+                QuorumApprovedRequestError::InsufficientApprovals
+            ))
+        ));
+
+        // Each party verifies the approval and wipes its share material.
+        let attestations: Vec<(VerifyingKey, Signature)> = key_stores
+            .iter_mut()
+            .zip(&identity_providers)
+            .map(|(key_store, identity_provider)| {
+                verify_approval(&response, &approvals, &request, 3, &verified_parties).unwrap();
+                let attestation = wipe(&wallet_id, &request, key_store, identity_provider).unwrap();
+                assert!(key_store.load(&wallet_id).unwrap().is_none());
+                attestation
+            })
+            .collect();
+
+        // Attestations from all parties form a valid destruction certificate.
+        let destruction_certificate = certificate(&wallet_id, &request, attestations.clone());
+        assert_eq!(
+            verify_certificate(&destruction_certificate, &wallet_id, &verified_parties),
+            Ok(())
+        );
+
+        // Certificates for other wallets or with missing or invalid attestations are rejected.
+        assert_eq!(
+            verify_certificate(&destruction_certificate, &[2u8; 32], &verified_parties),
+            Err(KeyDestructionError::WalletMismatch)
+        );
+        assert_eq!(
+            verify_certificate(
+                &certificate(&wallet_id, &request, attestations[..2].to_vec()),
+                &wallet_id,
+                &verified_parties
+            ),
+            Err(KeyDestructionError::InsufficientAttestations)
+        );
+        let mut invalid_attestations = attestations;
+        invalid_attestations[2].1 = invalid_attestations[0].1.clone();
+        assert_eq!(
+            verify_certificate(
+                &certificate(&wallet_id, &request, invalid_attestations),
+                &wallet_id,
+                &verified_parties
+            ),
+            Err(KeyDestructionError::InsufficientAttestations)
+        );
+
+        for dir in dirs {
+            std::fs::remove_dir_all(dir).unwrap();
+        }
+    }
+}
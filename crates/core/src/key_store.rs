@@ -0,0 +1,386 @@
+//! Persistent storage of wallet key material (i.e the "signing share", "sub-share", protocol specific local key, roster and roster epoch)
+//! keyed by wallet identifier, with an encrypted-at-rest file based default implementation.
+//!
+//! [`FileKeyStore`] encrypts key material with [AES-GCM](https://en.wikipedia.org/wiki/Galois/Counter_Mode)
+//! under a key derived (with [HKDF](https://tools.ietf.org/html/rfc5869)) from a signature of the identity provider
+//! (i.e the same approach as [share recovery with encrypted backups](crate::share_recovery_backup)),
+//! so stored key material is only usable by the party's decentralized identity.
+//!
+//! Key material is stored with a version tag and upgraded to the current format on load (see [`versioned`](crate::versioned)).
+
+#[cfg(feature = "std")]
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, Payload};
+#[cfg(feature = "std")]
+use aes_gcm::{Aes256Gcm, Nonce};
+#[cfg(feature = "std")]
+use alloc::format;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use hkdf::Hkdf;
+#[cfg(feature = "std")]
+use rand::rngs::OsRng;
+#[cfg(feature = "std")]
+use sha2::Sha256;
+#[cfg(feature = "std")]
+use std::fs;
+#[cfg(feature = "std")]
+use std::io::Write;
+#[cfg(feature = "std")]
+use std::path::{Path, PathBuf};
+#[cfg(feature = "std")]
+use zeroize::Zeroizing;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::crypto::VerifyingKey;
+#[cfg(feature = "std")]
+use crate::errors::KeyStoreError;
+use crate::share::{SigningShare, SubShare};
+#[cfg(feature = "std")]
+use crate::traits::IdentityProvider;
+#[cfg(feature = "std")]
+use crate::utils;
+#[cfg(feature = "std")]
+use crate::versioned::Versioned;
+
+/// The message signed by the identity provider to derive the encryption key of a file based key store.
+#[cfg(feature = "std")]
+const KEY_STORE_ENTROPY_SEED: &[u8] = b"wamu-key-store";
+
+/// The format version of encrypted key files.
+#[cfg(feature = "std")]
+const KEY_FILE_VERSION: u8 = 1;
+
+/// The extension of encrypted key files.
+#[cfg(feature = "std")]
+const KEY_FILE_EXTENSION: &str = "key";
+
+/// The length of an AES-GCM nonce.
+#[cfg(feature = "std")]
+const NONCE_LEN: usize = 12;
+
+/// Key material for a wallet.
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct StoredKey {
+    /// The "signing share".
+    pub signing_share: SigningShare,
+    /// The "sub-share".
+    pub sub_share: SubShare,
+    /// The protocol specific encoding of the augmented local key (i.e with the secret share cleared),
+    /// e.g the CGGMP `LocalKey` of a `WalletShare`.
+    pub local_key: Vec<u8>,
+    /// Verifying keys of all parties (i.e the current roster).
+    #[zeroize(skip)]
+    pub roster: Vec<VerifyingKey>,
+    /// The roster epoch (i.e the sequence number of the last identity rotation, see [`RosterLog`](crate::roster_log::RosterLog)).
+    #[zeroize(skip)]
+    pub epoch: u64,
+}
+
+/// A persistent store of key material for wallets (keyed by wallet identifier).
+pub trait KeyStore {
+    /// Key store error type.
+    type Error;
+
+    /// Saves the key material for a wallet (replacing any existing key material for the wallet).
+    fn save(&mut self, wallet_id: &[u8; 32], key: &StoredKey) -> Result<(), Self::Error>;
+
+    /// Returns the key material for a wallet (if any).
+    fn load(&self, wallet_id: &[u8; 32]) -> Result<Option<StoredKey>, Self::Error>;
+
+    /// Deletes the key material for a wallet and returns true if it existed.
+    fn delete(&mut self, wallet_id: &[u8; 32]) -> Result<bool, Self::Error>;
+
+    /// Returns the identifiers of all wallets with stored key material.
+    fn wallet_ids(&self) -> Result<Vec<[u8; 32]>, Self::Error>;
+}
+
+/// A file based key store with key material encrypted at rest (see module documentation for details).
+///
+/// Key material for each wallet is stored in a file named `<hex encoded wallet id>.key` of the form
+/// `version (u8) || nonce (12 bytes) || ciphertext`, with the version and wallet identifier as associated data
+/// (i.e key files can't be swapped between wallets).
+///
+/// **NOTE:** Key derivation requires a deterministic signature scheme (e.g RFC 6979 ECDSA or EdDSA),
+/// and key material must be re-saved with a new store after an identity rotation (i.e load with the old identity and save with the new identity).
+#[cfg(feature = "std")]
+#[doc(cfg(feature = "std"))]
+pub struct FileKeyStore {
+    /// The directory of key files.
+    dir: PathBuf,
+    /// The cipher for key material.
+    cipher: KeyCipher,
+}
+
+#[cfg(feature = "std")]
+impl FileKeyStore {
+    /// Given a directory (which is created if it doesn't exist) and an identity provider, returns a file based key store.
+    pub fn new(
+        dir: impl Into<PathBuf>,
+        identity_provider: &impl IdentityProvider,
+    ) -> Result<Self, KeyStoreError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            cipher: KeyCipher::new(identity_provider),
+        })
+    }
+
+    /// Returns the directory of key files.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the path of the key file for a wallet.
+    fn path(&self, wallet_id: &[u8; 32]) -> PathBuf {
+        self.dir
+            .join(format!("{}.{KEY_FILE_EXTENSION}", utils::to_hex(wallet_id)))
+    }
+}
+
+#[cfg(feature = "std")]
+impl KeyStore for FileKeyStore {
+    type Error = KeyStoreError;
+
+    fn save(&mut self, wallet_id: &[u8; 32], key: &StoredKey) -> Result<(), Self::Error> {
+        // Encrypts the canonical CBOR encoding of the key material.
+        let sealed = self
+            .cipher
+            .seal(wallet_id, &Zeroizing::new(key.to_versioned()))?;
+
+        // Writes to a temporary file and then renames it, so existing key material is never partially overwritten.
+        let path = self.path(wallet_id);
+        let tmp_path = path.with_extension("tmp");
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            // Key files are only readable and writable by the owner.
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&tmp_path)?;
+        file.write_all(&sealed)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+
+    fn load(&self, wallet_id: &[u8; 32]) -> Result<Option<StoredKey>, Self::Error> {
+        let bytes = match fs::read(self.path(wallet_id)) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+        let plaintext = self.cipher.open(wallet_id, &bytes)?;
+        Ok(Some(StoredKey::from_versioned(&plaintext)?))
+    }
+
+    fn delete(&mut self, wallet_id: &[u8; 32]) -> Result<bool, Self::Error> {
+        match fs::remove_file(self.path(wallet_id)) {
+            Ok(()) => Ok(true),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    fn wallet_ids(&self) -> Result<Vec<[u8; 32]>, Self::Error> {
+        let mut wallet_ids = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|it| it.to_str()) != Some(KEY_FILE_EXTENSION) {
+                continue;
+            }
+            // Ignores files that aren't named after a wallet identifier.
+            if let Some(wallet_id) = path
+                .file_stem()
+                .and_then(|it| it.to_str())
+                .and_then(utils::from_hex)
+                .and_then(|it| <[u8; 32]>::try_from(it).ok())
+            {
+                wallet_ids.push(wallet_id);
+            }
+        }
+        wallet_ids.sort();
+        Ok(wallet_ids)
+    }
+}
+
+/// A cipher for key material at rest, with a key derived from a signature of the identity provider.
+///
+/// Sealed key material is of the form `version (u8) || nonce (12 bytes) || ciphertext`,
+/// with the version and a context (e.g the wallet identifier) as associated data.
+#[cfg(feature = "std")]
+pub(crate) struct KeyCipher {
+    /// The encryption key.
+    key: Zeroizing<[u8; 32]>,
+}
+
+#[cfg(feature = "std")]
+impl KeyCipher {
+    /// Given an identity provider, returns a cipher with a key derived from the signature of the entropy seed.
+    pub(crate) fn new(identity_provider: &impl IdentityProvider) -> Self {
+        let entropy = identity_provider.sign(KEY_STORE_ENTROPY_SEED);
+        let mut key = Zeroizing::new([0u8; 32]);
+        Hkdf::<Sha256>::new(None, &entropy.sig)
+            .expand(KEY_STORE_ENTROPY_SEED, key.as_mut())
+            .expect("32 is a valid length for Sha256 to output");
+        Self { key }
+    }
+
+    /// Given a context (e.g the wallet identifier) and plaintext key material, returns the sealed key material.
+    pub(crate) fn seal(&self, context: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, KeyStoreError> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = Aes256Gcm::new(self.key.as_ref().into())
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plaintext,
+                    aad: &Self::associated_data(context),
+                },
+            )
+            .map_err(|_| KeyStoreError::Decryption)?;
+        Ok([[KEY_FILE_VERSION].as_slice(), &nonce, &ciphertext].concat())
+    }
+
+    /// Given a context (e.g the wallet identifier) and sealed key material,
+    /// returns the plaintext key material or an appropriate error.
+    pub(crate) fn open(
+        &self,
+        context: &[u8],
+        sealed: &[u8],
+    ) -> Result<Zeroizing<Vec<u8>>, KeyStoreError> {
+        let Some((version, rest)) = sealed.split_first() else {
+            return Err(KeyStoreError::UnsupportedVersion);
+        };
+        if *version != KEY_FILE_VERSION {
+            return Err(KeyStoreError::UnsupportedVersion);
+        } else if rest.len() < NONCE_LEN {
+            return Err(KeyStoreError::Decryption);
+        }
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        Aes256Gcm::new(self.key.as_ref().into())
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: &Self::associated_data(context),
+                },
+            )
+            .map(Zeroizing::new)
+            .map_err(|_| KeyStoreError::Decryption)
+    }
+
+    /// Returns the associated data for a context (i.e `version || context`).
+    fn associated_data(context: &[u8]) -> Vec<u8> {
+        [[KEY_FILE_VERSION].as_slice(), context].concat()
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::cbor::CanonicalCbor;
+    use crate::crypto::Random32Bytes;
+    use crate::share::SecretShare;
+    use crate::share_split_reconstruct;
+    use crate::test_utils::MockECDSAIdentityProvider;
+    use alloc::vec;
+
+    #[test]
+    fn file_key_store_works() {
+        // Generates identity providers and a temporary key store directory.
+        let identity_provider = MockECDSAIdentityProvider::generate();
+        let other_identity_provider = MockECDSAIdentityProvider::generate();
+        let dir = std::env::temp_dir().join(format!(
+            "wamu-key-store-{}",
+            utils::to_hex(&Random32Bytes::generate().to_be_bytes())
+        ));
+        let mut key_store = FileKeyStore::new(&dir, &identity_provider).unwrap();
+
+        // Generates key material.
+        let secret_share = SecretShare::from(Random32Bytes::generate_mod_q());
+        let (signing_share, sub_share) =
+            share_split_reconstruct::split(&secret_share, &identity_provider).unwrap();
+        let key = StoredKey {
+            signing_share,
+            sub_share,
+            local_key: b"local key".to_vec(),
+            roster: vec![
+                identity_provider.verifying_key(),
+                other_identity_provider.verifying_key(),
+            ],
+            epoch: 2,
+        };
+
+        // Key material round trips (and is re-derived by a new store for the same identity).
+        let (wallet_id, other_wallet_id) = ([1u8; 32], [2u8; 32]);
+        assert!(key_store.load(&wallet_id).unwrap().is_none());
+        key_store.save(&wallet_id, &key).unwrap();
+        key_store.save(&other_wallet_id, &key).unwrap();
+        let loaded = FileKeyStore::new(&dir, &identity_provider)
+            .unwrap()
+            .load(&wallet_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(loaded.to_cbor(), key.to_cbor());
+        assert_eq!(
+            share_split_reconstruct::reconstruct(
+                &loaded.signing_share,
+                &loaded.sub_share,
+                &identity_provider
+            )
+            .unwrap()
+            .to_be_bytes(),
+            secret_share.to_be_bytes()
+        );
+        assert_eq!(
+            key_store.wallet_ids().unwrap(),
+            vec![wallet_id, other_wallet_id]
+        );
+
+        // Key material is encrypted at rest.
+        let bytes = fs::read(key_store.path(&wallet_id)).unwrap();
+        assert!(!bytes
+            .windows(key.local_key.len())
+            .any(|window| window == key.local_key));
+
+        // Key material can't be decrypted by another identity or for another wallet.
+        assert_eq!(
+            FileKeyStore::new(&dir, &other_identity_provider)
+                .unwrap()
+                .load(&wallet_id)
+                .err(),
+            Some(KeyStoreError::Decryption)
+        );
+        fs::write(key_store.path(&other_wallet_id), &bytes).unwrap();
+        assert_eq!(
+            key_store.load(&other_wallet_id).err(),
+            Some(KeyStoreError::Decryption)
+        );
+        fs::write(key_store.path(&other_wallet_id), [2u8]).unwrap();
+        assert_eq!(
+            key_store.load(&other_wallet_id).err(),
+            Some(KeyStoreError::UnsupportedVersion)
+        );
+
+        // Key material saved before version tags were introduced (i.e untagged) is still loaded.
+        let untagged = key_store
+            .cipher
+            .seal(&other_wallet_id, &key.to_cbor())
+            .unwrap();
+        fs::write(key_store.path(&other_wallet_id), untagged).unwrap();
+        assert_eq!(
+            key_store.load(&other_wallet_id).unwrap().unwrap().to_cbor(),
+            key.to_cbor()
+        );
+
+        // Key material can be deleted.
+        assert!(key_store.delete(&wallet_id).unwrap());
+        assert!(!key_store.delete(&wallet_id).unwrap());
+        assert!(key_store.load(&wallet_id).unwrap().is_none());
+        assert_eq!(key_store.wallet_ids().unwrap(), vec![other_wallet_id]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,781 @@
+//! Keystore export (i.e migrating a wallet off MPC by reconstructing its private key into an encrypted keystore).
+//!
+//! Protocol:
+//! 1. The exporting party generates an ephemeral key and initiates a keystore export request (see [`initiate`]),
+//!    which must be approved by all other parties (see [`verify_request_and_initiate_challenge`] and [`quorum_approved_request`]).
+//! 2. Each party (including the exporting party) verifies the unanimous approval, reconstructs its "secret share"
+//!    and encrypts it to the ephemeral key of the exporting party in a signed contribution (see [`contribute`]).
+//! 3. The exporting party decrypts the contributions from all parties, interpolates the private key,
+//!    verifies it against the wallet's public key and encrypts it into an [EIP-2335](https://eips.ethereum.org/EIPS/eip-2335) style keystore
+//!    (see [`export`]), alongside a keystore export record which auditors can verify against the roster (see [`verify_record`]).
+//!
+//! "Secret shares" are encrypted with [AES-GCM](https://en.wikipedia.org/wiki/Galois/Counter_Mode)
+//! under a key derived (with [HKDF](https://tools.ietf.org/html/rfc5869)) from an ephemeral secp256k1 ECDH shared secret,
+//! so only the exporting party can decrypt them (even if contributions are transferred over an untrusted channel).
+//!
+//! **NOTE:** Exporting a keystore irreversibly ends the MPC security guarantees of the wallet
+//! (i.e whoever holds the keystore and its password controls the wallet), so it should be followed by key destruction
+//! (see [`crate::key_destruction`]) and recorded in the audit log (see [`crate::audit_log`]).
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, Payload};
+use aes_gcm::aes::Aes128;
+use aes_gcm::{Aes256Gcm, Nonce};
+use alloc::format;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use ctr::cipher::{KeyIvInit, StreamCipher};
+use k256::ecdh::EphemeralSecret;
+use k256::elliptic_curve::PrimeField;
+use k256::{ProjectivePoint, PublicKey, Scalar};
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::canonical::{CanonicalEncode, KeystoreExportContributionMessage};
+use crate::crypto::VerifyingKey;
+use crate::errors::{Error, KeystoreExportError};
+use crate::key_store::StoredKey;
+use crate::payloads::{
+    CommandApprovalPayload, IdentityAuthedRequestPayload, KeystoreExportContribution,
+    KeystoreExportRecord, QuorumApprovedChallengeResponsePayload,
+};
+use crate::traits::IdentityProvider;
+use crate::{
+    crypto, identity_authed_request, quorum_approved_request, share_split_reconstruct, utils,
+};
+
+/// The command prefix of keystore export requests (i.e `"keystore-export#" || hex(wallet_id) || "#" || hex(ephemeral_key)`).
+const KEYSTORE_EXPORT: &str = "keystore-export";
+
+/// The HKDF info prefix for deriving the encryption key of a keystore export contribution.
+const KEYSTORE_EXPORT_KDF_INFO: &[u8] = b"wamu-keystore-export";
+
+/// The length of an AES-GCM nonce.
+const NONCE_LEN: usize = 12;
+
+/// The default number of PBKDF2 iterations for keystore encryption (i.e as recommended by EIP-2335).
+pub const DEFAULT_KDF_ITERATIONS: u32 = 262_144;
+
+/// AES-128-CTR (i.e the EIP-2335 keystore cipher).
+type Aes128Ctr = ctr::Ctr128BE<Aes128>;
+
+/// The ephemeral secret of the exporting party (i.e for decrypting keystore export contributions).
+///
+/// **NOTE:** The ephemeral secret is never persisted, so the export must be restarted if the exporting party restarts.
+pub struct KeystoreExportSecret {
+    /// The identifier of the exported wallet.
+    wallet_id: [u8; 32],
+    /// The keystore export request.
+    request: IdentityAuthedRequestPayload,
+    /// The ephemeral ECDH secret.
+    secret: EphemeralSecret,
+}
+
+impl KeystoreExportSecret {
+    /// Returns the keystore export request.
+    pub fn request(&self) -> &IdentityAuthedRequestPayload {
+        &self.request
+    }
+}
+
+/// Given a wallet identifier and the identity provider of the exporting party,
+/// returns the payload for initiating a keystore export request and the ephemeral secret of the exporting party.
+pub fn initiate(
+    wallet_id: &[u8; 32],
+    identity_provider: &impl IdentityProvider,
+) -> (IdentityAuthedRequestPayload, KeystoreExportSecret) {
+    let secret = EphemeralSecret::random(&mut OsRng);
+    let command = format!(
+        "{KEYSTORE_EXPORT}#{}#{}",
+        utils::to_hex(wallet_id),
+        utils::to_hex(&secret.public_key().to_sec1_bytes())
+    );
+    let request = identity_authed_request::initiate_with_command(command, identity_provider);
+    (
+        request.clone(),
+        KeystoreExportSecret {
+            wallet_id: *wallet_id,
+            request,
+            secret,
+        },
+    )
+}
+
+/// Given a keystore export request payload, a wallet identifier, an identity provider and a list of verifying keys for all parties,
+/// returns an ok result with a command approval payload for a valid request or an appropriate error result for an invalid request.
+///
+/// **NOTE:** Users should be warned that approving a keystore export ends the MPC security guarantees of the wallet.
+pub fn verify_request_and_initiate_challenge(
+    request: &IdentityAuthedRequestPayload,
+    wallet_id: &[u8; 32],
+    identity_provider: &impl IdentityProvider,
+    roster: &[VerifyingKey],
+) -> Result<CommandApprovalPayload, KeystoreExportError> {
+    parse_command(request, wallet_id)?;
+    Ok(
+        quorum_approved_request::verify_request_and_initiate_challenge(
+            &request.command,
+            request,
+            identity_provider,
+            roster,
+        )?,
+    )
+}
+
+/// Given the quorum approved challenge response of the exporting party, a list of command approval payloads,
+/// a keystore export request payload, a wallet identifier and a list of verifying keys for all parties,
+/// returns an `Ok` result if the keystore export is approved by all other parties, or an appropriate `Err` result otherwise.
+pub fn verify_approval(
+    response: &QuorumApprovedChallengeResponsePayload,
+    approvals: &[CommandApprovalPayload],
+    request: &IdentityAuthedRequestPayload,
+    wallet_id: &[u8; 32],
+    roster: &[VerifyingKey],
+) -> Result<(), KeystoreExportError> {
+    parse_command(request, wallet_id)?;
+    if !roster.contains(&request.verifying_key) {
+        // Exporting party must be in the roster.
+        return Err(KeystoreExportError::Unauthorized(Error::UnauthorizedParty));
+    }
+    // Approvals from all other parties are required (i.e the exporting party can't approve its own request).
+    let other_parties: Vec<VerifyingKey> = roster
+        .iter()
+        .filter(|party| *party != &request.verifying_key)
+        .cloned()
+        .collect();
+    Ok(quorum_approved_request::verify_challenge_response(
+        response,
+        approvals,
+        &request.verifying_key,
+        request,
+        // + 1 because of implicit approval from the exporting party.
+        other_parties.len() + 1,
+        &other_parties,
+    )?)
+}
+
+/// Given a wallet identifier, the index of the party, its key material, the quorum approved challenge response of the exporting party,
+/// a list of command approval payloads, a keystore export request payload and an identity provider,
+/// returns a signed contribution with the party's "secret share" encrypted to the ephemeral key of the exporting party,
+/// or an appropriate error if the keystore export isn't approved by all parties.
+pub fn contribute(
+    wallet_id: &[u8; 32],
+    idx: u16,
+    key: &StoredKey,
+    response: &QuorumApprovedChallengeResponsePayload,
+    approvals: &[CommandApprovalPayload],
+    request: &IdentityAuthedRequestPayload,
+    identity_provider: &impl IdentityProvider,
+) -> Result<KeystoreExportContribution, KeystoreExportError> {
+    // Verifies the unanimous approval of the keystore export.
+    verify_approval(response, approvals, request, wallet_id, &key.roster)?;
+    let request_ephemeral_key = parse_command(request, wallet_id)?;
+
+    // Reconstructs and encrypts the "secret share".
+    let secret_share =
+        share_split_reconstruct::reconstruct(&key.signing_share, &key.sub_share, identity_provider)
+            .map_err(KeystoreExportError::Reconstruction)?;
+    let secret = EphemeralSecret::random(&mut OsRng);
+    let ephemeral_key = secret.public_key().to_sec1_bytes().to_vec();
+    let cipher = generate_encryption_cipher(
+        &secret,
+        &request_ephemeral_key,
+        wallet_id,
+        idx,
+        &ephemeral_key,
+    );
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: Zeroizing::new(secret_share.to_be_bytes()).as_slice(),
+                aad: wallet_id,
+            },
+        )
+        .map_err(|_| KeystoreExportError::Decryption)?;
+
+    // Signs the contribution.
+    let signature = identity_provider.sign(
+        &KeystoreExportContributionMessage {
+            wallet_id,
+            request,
+            idx,
+            ephemeral_key: &ephemeral_key,
+            nonce: &nonce,
+            ciphertext: &ciphertext,
+        }
+        .message_bytes(),
+    );
+    Ok(KeystoreExportContribution {
+        wallet_id: *wallet_id,
+        idx,
+        ephemeral_key,
+        nonce: nonce.to_vec(),
+        ciphertext,
+        verifying_key: identity_provider.verifying_key(),
+        signature,
+    })
+}
+
+/// Given the ephemeral secret of the exporting party, contributions from all parties, a list of verifying keys for all parties,
+/// the SEC1 encoded public key of the wallet, a keystore password and the number of PBKDF2 iterations (e.g [`DEFAULT_KDF_ITERATIONS`]),
+/// returns the encrypted keystore and the keystore export record, or an appropriate error.
+pub fn export(
+    secret: &KeystoreExportSecret,
+    contributions: Vec<KeystoreExportContribution>,
+    roster: &[VerifyingKey],
+    public_key: &[u8],
+    password: &str,
+    kdf_iterations: u32,
+) -> Result<(Keystore, KeystoreExportRecord), KeystoreExportError> {
+    let record = KeystoreExportRecord {
+        wallet_id: secret.wallet_id,
+        public_key: public_key.to_vec(),
+        request: secret.request.clone(),
+        contributions,
+    };
+    verify_record(&record, &secret.wallet_id, roster)?;
+
+    // Decrypts the "secret shares".
+    let shares = record
+        .contributions
+        .iter()
+        .map(|contribution| {
+            if contribution.nonce.len() != NONCE_LEN {
+                return Err(KeystoreExportError::Decryption);
+            }
+            let ephemeral_key = PublicKey::from_sec1_bytes(&contribution.ephemeral_key)
+                .map_err(|_| KeystoreExportError::Decryption)?;
+            let cipher = generate_encryption_cipher(
+                &secret.secret,
+                &ephemeral_key,
+                &secret.wallet_id,
+                contribution.idx,
+                &contribution.ephemeral_key,
+            );
+            let plaintext = cipher
+                .decrypt(
+                    Nonce::from_slice(&contribution.nonce),
+                    Payload {
+                        msg: &contribution.ciphertext,
+                        aad: &secret.wallet_id,
+                    },
+                )
+                .map(Zeroizing::new)
+                .map_err(|_| KeystoreExportError::Decryption)?;
+            let share = <[u8; 32]>::try_from(plaintext.as_slice())
+                .ok()
+                .and_then(|bytes| Option::<Scalar>::from(Scalar::from_repr(bytes.into())))
+                .ok_or(KeystoreExportError::Decryption)?;
+            Ok((Scalar::from(u64::from(contribution.idx)), share))
+        })
+        .collect::<Result<Vec<(Scalar, Scalar)>, KeystoreExportError>>()?;
+
+    // Interpolates the private key (i.e the value of the Shamir polynomial at 0) and verifies it against the public key.
+    let private_key =
+        interpolate_at_zero(&shares).ok_or(KeystoreExportError::InsufficientContributions)?;
+    let expected_public_key = PublicKey::from_sec1_bytes(public_key)
+        .map_err(|_| KeystoreExportError::PublicKeyMismatch)?;
+    if (ProjectivePoint::GENERATOR * private_key).to_affine() != *expected_public_key.as_affine() {
+        return Err(KeystoreExportError::PublicKeyMismatch);
+    }
+
+    let private_key = Zeroizing::new(<[u8; 32]>::from(private_key.to_bytes()));
+    let keystore = Keystore::encrypt(&private_key, public_key, password, kdf_iterations);
+    Ok((keystore, record))
+}
+
+/// Given a keystore export record, a wallet identifier and a list of verifying keys for all parties,
+/// returns an `Ok` result if the record has validly signed contributions from all parties (with unique indices),
+/// or an appropriate `Err` result otherwise.
+pub fn verify_record(
+    record: &KeystoreExportRecord,
+    wallet_id: &[u8; 32],
+    roster: &[VerifyingKey],
+) -> Result<(), KeystoreExportError> {
+    if &record.wallet_id != wallet_id {
+        return Err(KeystoreExportError::WalletMismatch);
+    }
+    parse_command(&record.request, wallet_id)?;
+    for contribution in &record.contributions {
+        if &contribution.wallet_id != wallet_id {
+            return Err(KeystoreExportError::WalletMismatch);
+        } else if !roster.contains(&contribution.verifying_key) {
+            return Err(KeystoreExportError::Unauthorized(Error::UnauthorizedParty));
+        }
+        crypto::verify_signature(
+            &contribution.verifying_key,
+            &KeystoreExportContributionMessage {
+                wallet_id,
+                request: &record.request,
+                idx: contribution.idx,
+                ephemeral_key: &contribution.ephemeral_key,
+                nonce: &contribution.nonce,
+                ciphertext: &contribution.ciphertext,
+            }
+            .message_bytes(),
+            &contribution.signature,
+        )?;
+    }
+    // Every party must contribute exactly once (with a unique non-zero index).
+    let mut indices: Vec<u16> = record.contributions.iter().map(|it| it.idx).collect();
+    indices.sort_unstable();
+    indices.dedup();
+    let all_contributed = roster.iter().all(|party| {
+        record
+            .contributions
+            .iter()
+            .filter(|contribution| &contribution.verifying_key == party)
+            .count()
+            == 1
+    });
+    if all_contributed
+        && record.contributions.len() == roster.len()
+        && indices.len() == roster.len()
+        && !indices.contains(&0)
+    {
+        Ok(())
+    } else {
+        Err(KeystoreExportError::InsufficientContributions)
+    }
+}
+
+/// An [EIP-2335](https://eips.ethereum.org/EIPS/eip-2335) style keystore (i.e PBKDF2-HMAC-SHA256, SHA-256 checksum and AES-128-CTR)
+/// for a secp256k1 private key.
+///
+/// **NOTE:** With the `serde` feature enabled, the keystore serializes to EIP-2335 JSON.
+/// Passwords are used as is (after removing control codes), so non-ASCII passwords should be NFKD normalized by the caller.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Keystore {
+    /// The crypto modules.
+    pub crypto: KeystoreCrypto,
+    /// A description of the keystore.
+    pub description: String,
+    /// The hex encoded (SEC1) public key.
+    pub pubkey: String,
+    /// The key derivation path (empty for keys that weren't derived).
+    pub path: String,
+    /// A random UUID (v4).
+    pub uuid: String,
+    /// The keystore version (i.e `4`).
+    pub version: u32,
+}
+
+/// The crypto modules of a keystore.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeystoreCrypto {
+    /// The key derivation function module.
+    pub kdf: KeystoreModule<KdfParams>,
+    /// The checksum module.
+    pub checksum: KeystoreModule<ChecksumParams>,
+    /// The cipher module.
+    pub cipher: KeystoreModule<CipherParams>,
+}
+
+/// A keystore crypto module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeystoreModule<P> {
+    /// The function name (e.g `"pbkdf2"`, `"sha256"` or `"aes-128-ctr"`).
+    pub function: String,
+    /// The function parameters.
+    pub params: P,
+    /// The hex encoded message (empty for the key derivation function module).
+    pub message: String,
+}
+
+/// The parameters of the PBKDF2 key derivation function module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KdfParams {
+    /// The derived key length.
+    pub dklen: u32,
+    /// The number of iterations.
+    pub c: u32,
+    /// The pseudo-random function (i.e `"hmac-sha256"`).
+    pub prf: String,
+    /// The hex encoded salt.
+    pub salt: String,
+}
+
+/// The parameters of the checksum module (i.e none).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChecksumParams {}
+
+/// The parameters of the cipher module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CipherParams {
+    /// The hex encoded initialization vector.
+    pub iv: String,
+}
+
+impl Keystore {
+    /// Given a private key, its SEC1 encoded public key, a password and the number of PBKDF2 iterations,
+    /// returns the encrypted keystore.
+    pub fn encrypt(
+        private_key: &[u8; 32],
+        public_key: &[u8],
+        password: &str,
+        kdf_iterations: u32,
+    ) -> Self {
+        let salt: [u8; 32] = utils::random_bytes();
+        let iv: [u8; 16] = utils::random_bytes();
+        let decryption_key = derive_decryption_key(password, &salt, kdf_iterations);
+        let mut ciphertext = private_key.to_vec();
+        Aes128Ctr::new(decryption_key[..16].into(), &iv.into()).apply_keystream(&mut ciphertext);
+        Self {
+            crypto: KeystoreCrypto {
+                kdf: KeystoreModule {
+                    function: "pbkdf2".to_string(),
+                    params: KdfParams {
+                        dklen: 32,
+                        c: kdf_iterations,
+                        prf: "hmac-sha256".to_string(),
+                        salt: utils::to_hex(&salt),
+                    },
+                    message: String::new(),
+                },
+                checksum: KeystoreModule {
+                    function: "sha256".to_string(),
+                    params: ChecksumParams {},
+                    message: utils::to_hex(&checksum(&decryption_key, &ciphertext)),
+                },
+                cipher: KeystoreModule {
+                    function: "aes-128-ctr".to_string(),
+                    params: CipherParams {
+                        iv: utils::to_hex(&iv),
+                    },
+                    message: utils::to_hex(&ciphertext),
+                },
+            },
+            description: "Wamu keystore export".to_string(),
+            pubkey: utils::to_hex(public_key),
+            path: String::new(),
+            uuid: uuid_v4(),
+            version: 4,
+        }
+    }
+
+    /// Given a password, returns the decrypted private key or an appropriate error.
+    pub fn decrypt(&self, password: &str) -> Result<Zeroizing<[u8; 32]>, KeystoreExportError> {
+        let crypto = &self.crypto;
+        let kdf = &crypto.kdf.params;
+        if crypto.kdf.function != "pbkdf2"
+            || kdf.prf != "hmac-sha256"
+            || kdf.dklen != 32
+            || crypto.checksum.function != "sha256"
+            || crypto.cipher.function != "aes-128-ctr"
+        {
+            return Err(KeystoreExportError::InvalidKeystore);
+        }
+        let decode = |hex: &str| utils::from_hex(hex).ok_or(KeystoreExportError::InvalidKeystore);
+        let salt = decode(&kdf.salt)?;
+        let iv = <[u8; 16]>::try_from(decode(&crypto.cipher.params.iv)?.as_slice())
+            .map_err(|_| KeystoreExportError::InvalidKeystore)?;
+        let ciphertext = decode(&crypto.cipher.message)?;
+        if ciphertext.len() != 32 {
+            return Err(KeystoreExportError::InvalidKeystore);
+        }
+
+        // Verifies the checksum (i.e the password) before decrypting.
+        let decryption_key = derive_decryption_key(password, &salt, kdf.c);
+        if decode(&crypto.checksum.message)? != checksum(&decryption_key, &ciphertext) {
+            return Err(KeystoreExportError::InvalidPassword);
+        }
+        let mut private_key = Zeroizing::new([0u8; 32]);
+        private_key.copy_from_slice(&ciphertext);
+        Aes128Ctr::new(decryption_key[..16].into(), &iv.into())
+            .apply_keystream(private_key.as_mut());
+        Ok(private_key)
+    }
+}
+
+/// Given a keystore export request and a wallet identifier,
+/// returns the ephemeral key of the exporting party for a valid keystore export command or an appropriate error otherwise.
+fn parse_command(
+    request: &IdentityAuthedRequestPayload,
+    wallet_id: &[u8; 32],
+) -> Result<PublicKey, KeystoreExportError> {
+    let mut parts = request.command.split('#');
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(KEYSTORE_EXPORT), Some(command_wallet_id), Some(ephemeral_key), None) => {
+            if utils::from_hex(command_wallet_id).as_deref() != Some(wallet_id.as_slice()) {
+                return Err(KeystoreExportError::WalletMismatch);
+            }
+            utils::from_hex(ephemeral_key)
+                .and_then(|ephemeral_key| PublicKey::from_sec1_bytes(&ephemeral_key).ok())
+                .ok_or(KeystoreExportError::InvalidCommand)
+        }
+        _ => Err(KeystoreExportError::InvalidCommand),
+    }
+}
+
+/// Returns an AES-GCM cipher with a key derived from the ECDH shared secret,
+/// bound to the wallet identifier, the index of the contributing party and its ephemeral key
+/// (i.e `info = "wamu-keystore-export" || wallet_id || be16(idx) || lp(contribution ephemeral key)`).
+fn generate_encryption_cipher(
+    secret: &EphemeralSecret,
+    public_key: &PublicKey,
+    wallet_id: &[u8; 32],
+    idx: u16,
+    contribution_ephemeral_key: &[u8],
+) -> Aes256Gcm {
+    let info = [
+        KEYSTORE_EXPORT_KDF_INFO,
+        wallet_id,
+        &idx.to_be_bytes(),
+        &utils::length_prefix_bytes(contribution_ephemeral_key),
+    ]
+    .concat();
+    let mut key = Zeroizing::new([0u8; 32]);
+    secret
+        .diffie_hellman(public_key)
+        .extract::<Sha256>(None)
+        .expand(&info, key.as_mut())
+        .expect("32 is a valid length for Sha256 to output");
+    Aes256Gcm::new(key.as_ref().into())
+}
+
+/// Given a list of (x, y) points with unique non-zero x-coordinates, returns the value of their Lagrange interpolating polynomial at 0
+/// (or `None` if there are no points).
+fn interpolate_at_zero(points: &[(Scalar, Scalar)]) -> Option<Scalar> {
+    if points.is_empty() {
+        return None;
+    }
+    points.iter().try_fold(Scalar::ZERO, |acc, (x_i, y_i)| {
+        let (numerator, denominator) = points
+            .iter()
+            .filter(|(x_j, _)| x_j != x_i)
+            .fold((Scalar::ONE, Scalar::ONE), |(num, den), (x_j, _)| {
+                (num * x_j, den * (*x_j - x_i))
+            });
+        let coefficient = numerator * Option::<Scalar>::from(denominator.invert())?;
+        Some(acc + *y_i * coefficient)
+    })
+}
+
+/// Returns the EIP-2335 decryption key (i.e the PBKDF2-HMAC-SHA256 derived key) for the password (with control codes removed).
+fn derive_decryption_key(password: &str, salt: &[u8], kdf_iterations: u32) -> Zeroizing<[u8; 32]> {
+    let password: Zeroizing<String> =
+        Zeroizing::new(password.chars().filter(|char| !char.is_control()).collect());
+    let mut decryption_key = Zeroizing::new([0u8; 32]);
+    pbkdf2::pbkdf2_hmac::<Sha256>(
+        password.as_bytes(),
+        salt,
+        kdf_iterations,
+        decryption_key.as_mut(),
+    );
+    decryption_key
+}
+
+/// Returns the EIP-2335 checksum (i.e `sha256(decryption_key[16..32] || cipher_message)`).
+fn checksum(decryption_key: &[u8; 32], ciphertext: &[u8]) -> [u8; 32] {
+    Sha256::new()
+        .chain_update(&decryption_key[16..])
+        .chain_update(ciphertext)
+        .finalize()
+        .into()
+}
+
+/// Returns a random (v4) UUID.
+fn uuid_v4() -> String {
+    let mut bytes: [u8; 16] = utils::random_bytes();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = utils::to_hex(&bytes);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::QuorumApprovedRequestError;
+    use crate::share::SecretShare;
+    use crate::test_utils::MockECDSAIdentityProvider;
+    use k256::elliptic_curve::Field;
+
+    #[test]
+    fn keystore_export_works() {
+        // Generates identity providers and a roster.
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (0..3)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let roster: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let wallet_id = [1u8; 32];
+
+        // Generates a private key and Shamir "secret shares" (with threshold 1) at indices 1, 2 and 3.
+        let mut rng = OsRng;
+        let private_key = Scalar::random(&mut rng);
+        let coefficient = Scalar::random(&mut rng);
+        let public_key =
+            PublicKey::from_secret_scalar(&k256::NonZeroScalar::new(private_key).unwrap())
+                .to_sec1_bytes()
+                .to_vec();
+        let keys: Vec<StoredKey> = identity_providers
+            .iter()
+            .enumerate()
+            .map(|(idx, identity_provider)| {
+                let share = private_key + coefficient * Scalar::from(idx as u64 + 1);
+                let secret_share = SecretShare::try_from(share.to_bytes().as_slice()).unwrap();
+                let (signing_share, sub_share) =
+                    share_split_reconstruct::split(&secret_share, identity_provider).unwrap();
+                StoredKey {
+                    signing_share,
+                    sub_share,
+                    local_key: Vec::new(),
+                    roster: roster.clone(),
+                    epoch: 0,
+                }
+            })
+            .collect();
+
+        // Initiates a keystore export request and collects approvals.
+        let (request, secret) = initiate(&wallet_id, &identity_providers[0]);
+        assert_eq!(
+            verify_request_and_initiate_challenge(
+                &request,
+                &[2u8; 32],
+                &identity_providers[1],
+                &roster
+            )
+            .unwrap_err(),
+            KeystoreExportError::WalletMismatch
+        );
+        let approvals: Vec<CommandApprovalPayload> = identity_providers[1..]
+            .iter()
+            .map(|identity_provider| {
+                verify_request_and_initiate_challenge(
+                    &request,
+                    &wallet_id,
+                    identity_provider,
+                    &roster,
+                )
+                .unwrap()
+            })
+            .collect();
+        let response = quorum_approved_request::challenge_response(
+            &approvals,
+            &identity_providers[0],
+            &request,
+            3,
+            &roster,
+        )
+        .unwrap();
+
+        // Approvals must be unanimous.
+        assert!(matches!(
+            contribute(
+                &wallet_id,
+                1,
+                &keys[0],
+                &response,
+                &approvals[..1],
+                &request,
+                &identity_providers[0]
+            ),
+            Err(KeystoreExportError::Approval(
+                QuorumApprovedRequestError::InsufficientApprovals
+            ))
+        ));
+
+        // Each party contributes its "secret share".
+        let contributions: Vec<KeystoreExportContribution> = keys
+            .iter()
+            .zip(&identity_providers)
+            .enumerate()
+            .map(|(idx, (key, identity_provider))| {
+                contribute(
+                    &wallet_id,
+                    idx as u16 + 1,
+                    key,
+                    &response,
+                    &approvals,
+                    &request,
+                    identity_provider,
+                )
+                .unwrap()
+            })
+            .collect();
+
+        // Missing contributions are rejected.
+        assert!(matches!(
+            export(
+                &secret,
+                contributions[..2].to_vec(),
+                &roster,
+                &public_key,
+                "password",
+                1
+            ),
+            Err(KeystoreExportError::InsufficientContributions)
+        ));
+
+        // Contributions from all parties are exported to a keystore with a verifiable record.
+        let (keystore, record) = export(
+            &secret,
+            contributions.clone(),
+            &roster,
+            &public_key,
+            "password",
+            1,
+        )
+        .unwrap();
+        assert_eq!(verify_record(&record, &wallet_id, &roster), Ok(()));
+        assert_eq!(
+            keystore.decrypt("password").unwrap().as_slice(),
+            private_key.to_bytes().as_slice()
+        );
+        assert_eq!(
+            keystore.decrypt("wrong-password"),
+            Err(KeystoreExportError::InvalidPassword)
+        );
+
+        // Tampered contributions are rejected.
+        let mut tampered_contributions = contributions.clone();
+        tampered_contributions[1].idx = 5;
+        assert!(matches!(
+            export(
+                &secret,
+                tampered_contributions,
+                &roster,
+                &public_key,
+                "password",
+                1
+            ),
+            Err(KeystoreExportError::Unauthorized(_))
+        ));
+
+        // Keystores are only exported for the wallet's public key.
+        let other_public_key =
+            PublicKey::from_secret_scalar(&k256::NonZeroScalar::random(&mut rng))
+                .to_sec1_bytes()
+                .to_vec();
+        assert!(matches!(
+            export(
+                &secret,
+                contributions,
+                &roster,
+                &other_public_key,
+                "password",
+                1
+            ),
+            Err(KeystoreExportError::PublicKeyMismatch)
+        ));
+    }
+}
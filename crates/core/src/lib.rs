@@ -1,32 +1,92 @@
 //! A Rust implementation of the core [Wamu protocol](https://wamu.tech/specification) for computation of [threshold signatures](https://en.wikipedia.org/wiki/Threshold_cryptosystem#Methodology) by multiple [decentralized identities](https://ethereum.org/en/decentralized-identity/#what-are-decentralized-identifiers).
 
+#![no_std]
 #![feature(doc_cfg)]
 
+extern crate alloc;
+#[cfg(any(feature = "std", test))]
+extern crate std;
+
 pub use self::{
     errors::{
-        CryptoError, Error, IdentityAuthedRequestError, QuorumApprovedRequestError,
-        ShareBackupRecoveryError,
+        AddressError, AuditLogError, BackupField, Bip32Error, Blame, CborError, CommandLogError,
+        CompressionError, CryptoError, DeviceMigrationError, EnvelopeError, Error,
+        IdentityAuthedRequestError, JsonError, KeyDestructionError, KeyStoreError,
+        KeystoreExportError, MigrationError, OfflineApprovalError, PolicyError,
+        QuorumApprovedRequestError, RosterLogError, SessionSetupError, ShareBackupRecoveryError,
+        SignatureBudgetError, TimeLockError, UriError,
     },
     payloads::{
-        CommandApprovalPayload, EncryptedShareBackup, IdentityAuthedRequestPayload,
-        IdentityRotationChallengeResponsePayload, QuorumApprovedChallengeResponsePayload,
+        ApprovalDelegationPayload, CommandApprovalPayload, CommandCancellationPayload,
+        DestructionCertificate, DeviceMigrationPackage, DeviceMigrationRequestPayload,
+        EncryptedShareBackup, IdentityAuthedRequestPayload,
+        IdentityRotationChallengeResponsePayload, KeystoreExportContribution, KeystoreExportRecord,
+        OfflineRequestPayload, QuorumApprovedChallengeResponsePayload, RotationCertificate,
+        SessionProposalPayload, SessionResponsePayload, SessionTerms, VersionOfferPayload,
     },
     share::{SecretShare, SigningShare, SubShare},
-    traits::IdentityProvider,
+    traits::{Clock, IdentityProvider},
 };
 
+pub mod account;
+pub mod address;
+pub mod approval_source;
+pub mod audit_log;
+pub mod bip32;
+pub mod canonical;
+pub mod cbor;
+pub mod command_log;
+pub mod compression;
 pub mod crypto;
+pub mod device_migration;
+pub mod displayable_command;
+pub mod envelope;
+pub mod error_report;
 mod errors;
 pub mod identity_authed_request;
 pub mod identity_challenge;
 pub mod identity_rotation;
+pub mod inbox;
+#[cfg(feature = "json")]
+#[doc(cfg(feature = "json"))]
+pub mod json;
+#[cfg(feature = "json")]
+#[doc(cfg(feature = "json"))]
+pub mod json_rpc;
+pub mod key_destruction;
+pub mod key_store;
+pub mod keystore_export;
+pub mod offline_approval;
 mod payloads;
+pub mod policy;
+#[cfg(feature = "proto")]
+#[doc(cfg(feature = "proto"))]
+pub mod proto;
 pub mod quorum_approved_request;
+pub mod request_throttle;
+pub mod roster_log;
+#[cfg(feature = "serde")]
+mod serde_hex;
+pub mod session_setup;
 mod share;
 pub mod share_recovery_backup;
 pub mod share_split_reconstruct;
+pub mod signature_budget;
+#[cfg(feature = "json")]
+#[doc(cfg(feature = "json"))]
+pub mod snap;
+#[cfg(feature = "sqlite")]
+#[doc(cfg(feature = "sqlite"))]
+pub mod sqlite;
+pub mod stats;
+pub mod time_lock;
 mod traits;
+pub mod uri;
 pub mod utils;
+pub mod versioned;
+#[cfg(feature = "wasm")]
+#[doc(cfg(feature = "wasm"))]
+pub mod wasm;
 pub mod wrappers;
 
 #[cfg(any(test, feature = "dev"))]
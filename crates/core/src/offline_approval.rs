@@ -0,0 +1,162 @@
+//! Offline (i.e air-gapped) approval of quorum approved requests.
+//!
+//! Pending requests are exported as self-contained signed blobs, approved on an air-gapped device
+//! (producing detached command approvals) and the approvals are merged back into the online session.
+//!
+//! **NOTE:** Blobs are canonical CBOR encodings of the payloads (see [`crate::cbor`]),
+//! and requests must still be approved before they expire.
+
+use crate::canonical::{CanonicalEncode, OfflineRequestMessage};
+use crate::cbor::CanonicalCbor;
+use crate::crypto;
+use crate::crypto::VerifyingKey;
+use crate::errors::{Error, OfflineApprovalError};
+use crate::payloads::{
+    CommandApprovalPayload, IdentityAuthedRequestPayload, OfflineRequestPayload,
+};
+use crate::quorum_approved_request;
+use crate::quorum_approved_request::QuorumTracker;
+use crate::traits::IdentityProvider;
+use alloc::vec::Vec;
+
+/// Given a wallet identifier, an identity authenticated request and the identity provider of the exporting party,
+/// returns a self-contained signed blob of the request.
+pub fn export(
+    wallet_id: &[u8; 32],
+    request: &IdentityAuthedRequestPayload,
+    identity_provider: &impl IdentityProvider,
+) -> Vec<u8> {
+    OfflineRequestPayload {
+        wallet_id: *wallet_id,
+        request: request.clone(),
+        verifying_key: identity_provider.verifying_key(),
+        signature: identity_provider
+            .sign(&OfflineRequestMessage { wallet_id, request }.message_bytes()),
+    }
+    .to_cbor()
+}
+
+/// Given an offline request blob, a wallet identifier and a list of verifying keys for all parties,
+/// returns the exported identity authenticated request if the blob was exported for the wallet by a verified party,
+/// or an appropriate error otherwise.
+pub fn import(
+    blob: &[u8],
+    wallet_id: &[u8; 32],
+    verified_parties: &[VerifyingKey],
+) -> Result<IdentityAuthedRequestPayload, OfflineApprovalError> {
+    let offline_request = OfflineRequestPayload::from_cbor(blob)?;
+    if &offline_request.wallet_id != wallet_id {
+        // Request must be for the wallet.
+        Err(OfflineApprovalError::WalletMismatch)
+    } else if !verified_parties.contains(&offline_request.verifying_key) {
+        // Exporter must be a verified party.
+        Err(OfflineApprovalError::Unauthorized(Error::UnauthorizedParty))
+    } else {
+        // Export signature must be valid.
+        crypto::verify_signature(
+            &offline_request.verifying_key,
+            &OfflineRequestMessage {
+                wallet_id,
+                request: &offline_request.request,
+            }
+            .message_bytes(),
+            &offline_request.signature,
+        )?;
+        Ok(offline_request.request)
+    }
+}
+
+/// Given an offline request blob, a "command", a wallet identifier, an identity provider and a list of verifying keys for all parties,
+/// returns a detached command approval blob for a valid request or an appropriate error otherwise.
+///
+/// **NOTE:** This is meant to be called on the air-gapped device.
+pub fn approve(
+    blob: &[u8],
+    command: &str,
+    wallet_id: &[u8; 32],
+    identity_provider: &impl IdentityProvider,
+    verified_parties: &[VerifyingKey],
+) -> Result<Vec<u8>, OfflineApprovalError> {
+    let request = import(blob, wallet_id, verified_parties)?;
+    let approval = quorum_approved_request::verify_request_and_initiate_challenge(
+        command,
+        &request,
+        identity_provider,
+        verified_parties,
+    )?;
+    Ok(approval.to_cbor())
+}
+
+/// Given a quorum tracker and a list of detached command approval blobs,
+/// verifies and adds the approvals to the tracker and returns the number of newly added approvals,
+/// or an appropriate error for an invalid blob or approval.
+pub fn merge(
+    tracker: &mut QuorumTracker,
+    approval_blobs: &[Vec<u8>],
+) -> Result<usize, OfflineApprovalError> {
+    approval_blobs.iter().try_fold(0, |added, blob| {
+        let approval = CommandApprovalPayload::from_cbor(blob)?;
+        Ok(added + usize::from(tracker.add_approval(approval)?))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::CborError;
+    use crate::test_utils::MockECDSAIdentityProvider;
+
+    #[test]
+    fn offline_approval_works() {
+        // Generates identity providers, a roster and a wallet identifier.
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (0..3)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let verified_parties: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let wallet_id = [1; 32];
+
+        // Initiates and exports a quorum approved request.
+        let command = "command";
+        let request = quorum_approved_request::initiate(command, &identity_providers[0]);
+        let blob = export(&wallet_id, &request, &identity_providers[0]);
+
+        // Air-gapped devices approve the request.
+        let approval_blobs: Vec<Vec<u8>> = identity_providers[1..]
+            .iter()
+            .map(|identity_provider| {
+                approve(
+                    &blob,
+                    command,
+                    &wallet_id,
+                    identity_provider,
+                    &verified_parties,
+                )
+                .unwrap()
+            })
+            .collect();
+
+        // Approvals are merged back into the online session (and duplicates are ignored).
+        let mut tracker = QuorumTracker::new(request, 3, &verified_parties);
+        assert_eq!(merge(&mut tracker, &approval_blobs), Ok(2));
+        assert_eq!(merge(&mut tracker, &approval_blobs[..1]), Ok(0));
+        assert!(tracker.is_complete());
+        assert!(tracker.challenge_response(&identity_providers[0]).is_ok());
+
+        // Blobs for other wallets, from unverified parties or with invalid encodings are rejected.
+        assert_eq!(
+            import(&blob, &[2; 32], &verified_parties).err(),
+            Some(OfflineApprovalError::WalletMismatch)
+        );
+        assert_eq!(
+            import(&blob, &wallet_id, &verified_parties[1..]).err(),
+            Some(OfflineApprovalError::Unauthorized(Error::UnauthorizedParty))
+        );
+        assert_eq!(
+            import(&blob[1..], &wallet_id, &verified_parties).err(),
+            Some(OfflineApprovalError::Decoding(CborError::InvalidValue))
+        );
+    }
+}
@@ -1,22 +1,29 @@
 //! Types and abstractions for request payloads.
 
 use crate::crypto::{Random32Bytes, Signature, VerifyingKey};
+use alloc::string::String;
+use alloc::vec::Vec;
 
 /// An identity authenticated request payload.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IdentityAuthedRequestPayload {
     /// The command to execute.
-    pub command: &'static str,
+    pub command: String,
     /// The verifying key of the initiating party.
     pub verifying_key: VerifyingKey,
-    /// The UTC timestamp at which the request was initiated.
+    /// The UTC timestamp at which the request was initiated (`0` for requests initiated in monotonic counter mode).
     pub timestamp: u64,
-    /// A signature of the command and timestamp by the initiating party.
+    /// The request counter of the initiating party (only `Some` for requests initiated in monotonic counter mode).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub counter: Option<u64>,
+    /// A signature of the command and timestamp (or request counter) by the initiating party.
     pub signature: Signature,
 }
 
 /// An identity rotation challenge response payload.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IdentityRotationChallengeResponsePayload {
     /// The new verifying key of the initiating party.
     pub new_verifying_key: VerifyingKey,
@@ -26,8 +33,26 @@ pub struct IdentityRotationChallengeResponsePayload {
     pub new_signature: Signature,
 }
 
+/// An identity rotation certificate (i.e an old → new verifying key binding counter-signed by a quorum of parties).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RotationCertificate {
+    /// The verifying key of the rotating party before the rotation.
+    pub old_verifying_key: VerifyingKey,
+    /// The verifying key of the rotating party after the rotation.
+    pub new_verifying_key: VerifyingKey,
+    /// The roster epoch introduced by the rotation (i.e the sequence number of the certificate in the roster log).
+    pub epoch: u64,
+    /// The hash of the roster log head before the rotation.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub previous_hash: [u8; 32],
+    /// Counter-signatures of the rotation by parties in the roster before the rotation.
+    pub signatures: Vec<(VerifyingKey, Signature)>,
+}
+
 /// A command approval payload.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CommandApprovalPayload {
     /// An identity challenge fragment from an approving party.
     pub challenge_fragment: Random32Bytes,
@@ -35,10 +60,37 @@ pub struct CommandApprovalPayload {
     pub verifying_key: VerifyingKey,
     /// A signature of the identity challenge fragment by the approving party.
     pub signature: Signature,
+    /// A delegation authorizing the approving party to approve on behalf of another party (if any).
+    pub delegation: Option<ApprovalDelegationPayload>,
+}
+
+/// An approval delegation payload (i.e the delegator authorizes the delegate to approve on its behalf until the expiry timestamp).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ApprovalDelegationPayload {
+    /// The verifying key of the delegating party.
+    pub delegator: VerifyingKey,
+    /// The verifying key of the delegate.
+    pub delegate: VerifyingKey,
+    /// The UTC timestamp after which the delegation is no longer valid.
+    pub expires_at: u64,
+    /// A signature of the delegate's verifying key, expiry timestamp and roster hash by the delegating party.
+    pub signature: Signature,
+}
+
+/// A command cancellation payload (i.e a signed cancellation of a time-locked quorum approved request).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CommandCancellationPayload {
+    /// The verifying key of the cancelling party.
+    pub verifying_key: VerifyingKey,
+    /// A signature of the command and request timestamp by the cancelling party.
+    pub signature: Signature,
 }
 
 /// A command approval payload.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct QuorumApprovedChallengeResponsePayload {
     /// A signature of the identity challenge from a quorum of approving parties by the initiating party.
     pub signature: Signature,
@@ -46,12 +98,185 @@ pub struct QuorumApprovedChallengeResponsePayload {
     pub approving_quorum: Vec<VerifyingKey>,
 }
 
+/// A protocol version offer payload (i.e the range of protocol versions supported by a party).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VersionOfferPayload {
+    /// The oldest supported protocol version.
+    pub min_version: u16,
+    /// The newest supported protocol version.
+    pub max_version: u16,
+    /// The verifying key of the offering party.
+    pub verifying_key: VerifyingKey,
+    /// A signature of the supported protocol version range and wallet identifier by the offering party.
+    pub signature: Signature,
+}
+
+/// An offline request payload (i.e a self-contained request exported for approval on an air-gapped device).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OfflineRequestPayload {
+    /// The identifier of the wallet.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub wallet_id: [u8; 32],
+    /// The exported identity authenticated request.
+    pub request: IdentityAuthedRequestPayload,
+    /// The verifying key of the exporting party.
+    pub verifying_key: VerifyingKey,
+    /// A signature of the wallet identifier and request by the exporting party.
+    pub signature: Signature,
+}
+
+/// The terms of a proposed protocol session (i.e what all participants must agree on before round 1).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SessionTerms {
+    /// The identifier of the wallet.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub wallet_id: [u8; 32],
+    /// The identifier of the proposed session.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub session_id: [u8; 32],
+    /// The protocol to run (e.g "sign", "keygen" or "key-refresh").
+    pub protocol: String,
+    /// The verifying keys of the participating parties (i.e a subset of the roster, including the initiating party) in protocol order.
+    pub participants: Vec<VerifyingKey>,
+    /// The protocol specific message (e.g the message digest for signing), which may be empty.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub message: Vec<u8>,
+    /// The UTC timestamp after which responses to the proposal are no longer accepted.
+    pub respond_by: u64,
+    /// The UTC timestamp by which the session must finish.
+    pub deadline: u64,
+}
+
+/// A session proposal payload (i.e the terms of a protocol session proposed by the initiating party).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SessionProposalPayload {
+    /// The proposed session terms.
+    pub terms: SessionTerms,
+    /// The verifying key of the initiating party.
+    pub verifying_key: VerifyingKey,
+    /// A signature of the session terms by the initiating party.
+    pub signature: Signature,
+}
+
+/// A session response payload (i.e a participating party's acceptance or rejection of a session proposal).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SessionResponsePayload {
+    /// Whether the responding party accepts the proposal.
+    pub accepted: bool,
+    /// The verifying key of the responding party.
+    pub verifying_key: VerifyingKey,
+    /// A signature of the session terms and decision by the responding party.
+    pub signature: Signature,
+}
+
 /// An encrypted share backup (i.e an encrypted "signing share" and "sub-share", and a random nonce).
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EncryptedShareBackup {
     /// An encrypted "signing share".
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub signing_share: Vec<u8>,
     /// An encrypted "sub-share".
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::pair"))]
     pub sub_share: (Vec<u8>, Vec<u8>),
     /// The encryption/decryption nonce.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub nonce: Vec<u8>,
+}
+
+/// A device migration request payload (i.e the ephemeral encryption key of the new device of a party).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceMigrationRequestPayload {
+    /// The identifier of the wallet.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub wallet_id: [u8; 32],
+    /// The SEC1 encoded secp256k1 ephemeral public key of the new device.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub ephemeral_key: Vec<u8>,
+    /// The UTC timestamp at which the request was initiated.
+    pub timestamp: u64,
+    /// The verifying key of the requesting party.
+    pub verifying_key: VerifyingKey,
+    /// A signature of the wallet identifier, ephemeral key and timestamp by the requesting party.
+    pub signature: Signature,
+}
+
+/// A device migration package (i.e the party state encrypted to the ephemeral key of the new device).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceMigrationPackage {
+    /// The identifier of the wallet.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub wallet_id: [u8; 32],
+    /// The SEC1 encoded secp256k1 ephemeral public key of the old device.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub ephemeral_key: Vec<u8>,
+    /// The encryption/decryption nonce.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub nonce: Vec<u8>,
+    /// The encrypted party state.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub ciphertext: Vec<u8>,
+    /// The verifying key of the migrating party.
+    pub verifying_key: VerifyingKey,
+    /// A signature of the wallet identifier, both ephemeral keys, nonce and ciphertext by the migrating party.
+    pub signature: Signature,
+}
+
+/// A keystore export contribution (i.e a party's "secret share" encrypted to the ephemeral key of the exporting party).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeystoreExportContribution {
+    /// The identifier of the exported wallet.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub wallet_id: [u8; 32],
+    /// The index of the contributing party (i.e the x-coordinate of its "secret share").
+    pub idx: u16,
+    /// The SEC1 encoded secp256k1 ephemeral public key of the contributing party.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub ephemeral_key: Vec<u8>,
+    /// The encryption/decryption nonce.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub nonce: Vec<u8>,
+    /// The encrypted "secret share".
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub ciphertext: Vec<u8>,
+    /// The verifying key of the contributing party.
+    pub verifying_key: VerifyingKey,
+    /// A signature of the wallet identifier, request, index, ephemeral keys, nonce and ciphertext by the contributing party.
+    pub signature: Signature,
+}
+
+/// A keystore export record (i.e a quorum approved keystore export request with signed contributions from all parties).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeystoreExportRecord {
+    /// The identifier of the exported wallet.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub wallet_id: [u8; 32],
+    /// The SEC1 encoded public key of the exported wallet.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub public_key: Vec<u8>,
+    /// The quorum approved keystore export request.
+    pub request: IdentityAuthedRequestPayload,
+    /// Signed contributions from all parties.
+    pub contributions: Vec<KeystoreExportContribution>,
+}
+
+/// A key destruction certificate (i.e a quorum approved key destruction request with destruction attestations from all parties).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DestructionCertificate {
+    /// The identifier of the destroyed wallet.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub wallet_id: [u8; 32],
+    /// The quorum approved key destruction request.
+    pub request: IdentityAuthedRequestPayload,
+    /// Destruction attestations (i.e signatures of the wallet identifier and request by parties that wiped their share material).
+    pub attestations: Vec<(VerifyingKey, Signature)>,
 }
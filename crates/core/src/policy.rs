@@ -0,0 +1,435 @@
+//! Per-wallet signing policies (i.e destination allowlists, amount limits, required approvers and time-of-day windows)
+//! evaluated by each party before joining a signing session.
+//!
+//! Policies can only be updated with quorum approved requests:
+//! 1. A party initiates a policy update request for the new policy (see [`initiate_update`]),
+//!    which binds the request to the policy hash.
+//! 2. Other parties review the new policy and approve the request (see [`verify_update_request_and_initiate_challenge`]
+//!    and [`quorum_approved_request`]).
+//! 3. Each party verifies the quorum approval and replaces its current policy (see [`apply_update`]).
+//!
+//! **NOTE:** Policies are enforced locally by each party, so a signing request is only rejected
+//! if enough parties to prevent a signing quorum enforce the policy.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use sha2::{Digest, Sha256};
+
+use crate::cbor::CanonicalCbor;
+use crate::crypto::VerifyingKey;
+use crate::errors::{IdentityAuthedRequestError, PolicyError};
+use crate::payloads::{
+    CommandApprovalPayload, IdentityAuthedRequestPayload, QuorumApprovedChallengeResponsePayload,
+};
+use crate::traits::{Clock, IdentityProvider};
+use crate::utils::SystemClock;
+use crate::{identity_authed_request, quorum_approved_request, utils};
+
+/// The command name of policy update requests.
+const POLICY_UPDATE: &str = "policy-update";
+
+/// Separator between the command name and the policy hash in policy update commands.
+const POLICY_HASH_SEPARATOR: char = '#';
+
+/// The number of seconds in a day.
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// A signing policy rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PolicyRule {
+    /// Only destinations (e.g addresses) in the allowlist can be signed for.
+    DestinationAllowlist(
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::seq"))] Vec<Vec<u8>>,
+    ),
+    /// Only amounts up to (and including) the limit can be signed for.
+    AmountLimit(u64),
+    /// All the given parties must approve the signing request.
+    RequiredApprovers(Vec<VerifyingKey>),
+    /// Signing requests are only allowed in the time-of-day window
+    /// from `start` (inclusive) to `end` (exclusive) in seconds since midnight UTC.
+    ///
+    /// **NOTE:** Windows with `start` after `end` wrap around midnight.
+    TimeWindow { start: u32, end: u32 },
+}
+
+/// A per-wallet signing policy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Policy {
+    /// The identifier of the wallet.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub wallet_id: [u8; 32],
+    /// The policy version (incremented by each policy update).
+    pub version: u64,
+    /// The policy rules (all of which must be satisfied).
+    pub rules: Vec<PolicyRule>,
+}
+
+/// The parameters of a signing request that are evaluated against a signing policy.
+///
+/// **NOTE:** Destinations and amounts are chain specific, so they must be derived by the party from the message to be signed
+/// (i.e not taken from the signing request as is).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SigningIntent {
+    /// The destination (e.g an address).
+    pub destination: Vec<u8>,
+    /// The amount (in the smallest unit of the asset).
+    pub amount: u64,
+    /// Verifying keys of the parties that approved the signing request (e.g the verified approving quorum).
+    pub approvers: Vec<VerifyingKey>,
+}
+
+impl Policy {
+    /// Returns an empty (i.e allow all) version 0 policy for the wallet.
+    pub fn new(wallet_id: [u8; 32]) -> Self {
+        Self {
+            wallet_id,
+            version: 0,
+            rules: Vec::new(),
+        }
+    }
+
+    /// Sets the policy version.
+    pub fn with_version(mut self, version: u64) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Adds a rule to the policy.
+    pub fn with_rule(mut self, rule: PolicyRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Returns the policy hash (i.e a SHA-256 hash of the canonical CBOR encoding of the policy).
+    pub fn hash(&self) -> [u8; 32] {
+        Sha256::digest(self.to_cbor()).into()
+    }
+
+    /// Given the identifier of the wallet and a signing intent,
+    /// returns an ok result if the signing intent satisfies all policy rules or an appropriate error result otherwise.
+    ///
+    /// **NOTE:** Time-of-day windows are checked against the local time of the party (i.e not a time chosen by the initiator).
+    pub fn evaluate(
+        &self,
+        wallet_id: &[u8; 32],
+        intent: &SigningIntent,
+    ) -> Result<(), PolicyError> {
+        self.evaluate_with_clock(wallet_id, intent, &SystemClock)
+    }
+
+    /// Same as [`Policy::evaluate`] but reads the current time (i.e for time-of-day windows) from the given clock.
+    pub fn evaluate_with_clock(
+        &self,
+        wallet_id: &[u8; 32],
+        intent: &SigningIntent,
+        clock: &impl Clock,
+    ) -> Result<(), PolicyError> {
+        if &self.wallet_id != wallet_id {
+            return Err(PolicyError::WalletMismatch);
+        }
+        self.rules.iter().try_for_each(|rule| match rule {
+            PolicyRule::DestinationAllowlist(destinations) => {
+                if destinations.contains(&intent.destination) {
+                    Ok(())
+                } else {
+                    Err(PolicyError::DestinationNotAllowed)
+                }
+            }
+            PolicyRule::AmountLimit(limit) => {
+                if intent.amount <= *limit {
+                    Ok(())
+                } else {
+                    Err(PolicyError::AmountLimitExceeded)
+                }
+            }
+            PolicyRule::RequiredApprovers(approvers) => {
+                if approvers
+                    .iter()
+                    .all(|approver| intent.approvers.contains(approver))
+                {
+                    Ok(())
+                } else {
+                    Err(PolicyError::MissingApprover)
+                }
+            }
+            PolicyRule::TimeWindow { start, end } => {
+                let time_of_day = clock.unix_timestamp() % SECONDS_PER_DAY;
+                let (start, end) = (u64::from(*start), u64::from(*end));
+                let is_in_window = if start <= end {
+                    start <= time_of_day && time_of_day < end
+                } else {
+                    start <= time_of_day || time_of_day < end
+                };
+                if is_in_window {
+                    Ok(())
+                } else {
+                    Err(PolicyError::OutsideTimeWindow)
+                }
+            }
+        })
+    }
+
+    /// Returns the command of the policy update request for the policy (i.e the command name followed by the policy hash).
+    pub fn update_command(&self) -> String {
+        format!(
+            "{POLICY_UPDATE}{POLICY_HASH_SEPARATOR}{}",
+            utils::to_hex(&self.hash())
+        )
+    }
+}
+
+/// Given a new policy and an identity provider, returns the payload for initiating a policy update request.
+pub fn initiate_update(
+    policy: &Policy,
+    identity_provider: &impl IdentityProvider,
+) -> IdentityAuthedRequestPayload {
+    identity_authed_request::initiate_with_command(policy.update_command(), identity_provider)
+}
+
+/// Given a new policy, a policy update request payload, an identity provider and a list of verifying keys for the other parties,
+/// returns an ok result with a command approval payload for a valid request or an appropriate error result for an invalid request.
+///
+/// **NOTE:** The new policy should be reviewed (e.g by the user) before approving the request.
+pub fn verify_update_request_and_initiate_challenge(
+    policy: &Policy,
+    request: &IdentityAuthedRequestPayload,
+    identity_provider: &impl IdentityProvider,
+    verified_parties: &[VerifyingKey],
+) -> Result<CommandApprovalPayload, IdentityAuthedRequestError> {
+    quorum_approved_request::verify_request_and_initiate_challenge(
+        &policy.update_command(),
+        request,
+        identity_provider,
+        verified_parties,
+    )
+}
+
+/// Given the current policy, a new policy, the quorum approved challenge response of the initiating party,
+/// a list of command approval payloads, a policy update request payload, a quorum size and a list of verifying keys for the other parties,
+/// returns an ok result with the new policy if the policy update is approved by at least a quorum of parties,
+/// or an appropriate error result otherwise.
+pub fn apply_update(
+    current: &Policy,
+    update: Policy,
+    response: &QuorumApprovedChallengeResponsePayload,
+    approvals: &[CommandApprovalPayload],
+    request: &IdentityAuthedRequestPayload,
+    quorum_size: usize,
+    verified_parties: &[VerifyingKey],
+) -> Result<Policy, PolicyError> {
+    if update.wallet_id != current.wallet_id {
+        return Err(PolicyError::WalletMismatch);
+    }
+    if update.version <= current.version {
+        return Err(PolicyError::StaleVersion);
+    }
+    if request.command != update.update_command() {
+        return Err(PolicyError::CommandMismatch);
+    }
+    quorum_approved_request::verify_challenge_response(
+        response,
+        approvals,
+        &request.verifying_key,
+        request,
+        quorum_size,
+        verified_parties,
+    )?;
+    Ok(update)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::QuorumApprovedRequestError;
+    use crate::test_utils::{MockClock, MockECDSAIdentityProvider};
+    use alloc::vec;
+
+    #[test]
+    fn policy_works() {
+        // Generates identity providers and a roster.
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (0..3)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let verified_parties: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let wallet_id = [1u8; 32];
+
+        // Creates a policy (with a time-of-day window from 22:00 to 06:00 UTC).
+        let policy = Policy::new(wallet_id)
+            .with_version(1)
+            .with_rule(PolicyRule::DestinationAllowlist(vec![vec![0xab, 0xcd]]))
+            .with_rule(PolicyRule::AmountLimit(100))
+            .with_rule(PolicyRule::RequiredApprovers(vec![
+                verified_parties[1].clone()
+            ]))
+            .with_rule(PolicyRule::TimeWindow {
+                start: 22 * 60 * 60,
+                end: 6 * 60 * 60,
+            });
+        assert_eq!(Policy::from_cbor(&policy.to_cbor()), Ok(policy.clone()));
+
+        // Signing intents that satisfy all rules are allowed (at 1970-01-02 23:00 UTC and 1970-01-02 05:59:59 UTC).
+        let intent = SigningIntent {
+            destination: vec![0xab, 0xcd],
+            amount: 100,
+            approvers: verified_parties[..2].to_vec(),
+        };
+        let clock = MockClock::new(SECONDS_PER_DAY + 23 * 60 * 60);
+        assert_eq!(
+            policy.evaluate_with_clock(&wallet_id, &intent, &clock),
+            Ok(())
+        );
+        assert_eq!(
+            policy.evaluate_with_clock(
+                &wallet_id,
+                &intent,
+                &MockClock::new(SECONDS_PER_DAY + 6 * 60 * 60 - 1)
+            ),
+            Ok(())
+        );
+
+        // Signing intents that violate any rule are rejected.
+        for (modified_intent, error) in [
+            (
+                SigningIntent {
+                    destination: vec![0xab],
+                    ..intent.clone()
+                },
+                PolicyError::DestinationNotAllowed,
+            ),
+            (
+                SigningIntent {
+                    amount: 101,
+                    ..intent.clone()
+                },
+                PolicyError::AmountLimitExceeded,
+            ),
+            (
+                SigningIntent {
+                    approvers: vec![verified_parties[0].clone(), verified_parties[2].clone()],
+                    ..intent.clone()
+                },
+                PolicyError::MissingApprover,
+            ),
+        ] {
+            assert_eq!(
+                policy.evaluate_with_clock(&wallet_id, &modified_intent, &clock),
+                Err(error)
+            );
+        }
+        assert_eq!(
+            policy.evaluate_with_clock(&[2u8; 32], &intent, &clock),
+            Err(PolicyError::WalletMismatch)
+        );
+
+        // Signing intents outside the time-of-day window of the local clock (i.e at 1970-01-02 12:00 UTC) are rejected.
+        assert_eq!(
+            policy.evaluate_with_clock(
+                &wallet_id,
+                &intent,
+                &MockClock::new(SECONDS_PER_DAY + 12 * 60 * 60)
+            ),
+            Err(PolicyError::OutsideTimeWindow)
+        );
+
+        // Initiates and approves a policy update (that raises the amount limit).
+        let update = Policy {
+            version: 2,
+            rules: vec![PolicyRule::AmountLimit(1000)],
+            ..policy.clone()
+        };
+        let request = initiate_update(&update, &identity_providers[0]);
+        assert_eq!(
+            verify_update_request_and_initiate_challenge(
+                &policy,
+                &request,
+                &identity_providers[1],
+                &verified_parties
+            )
+            .err(),
+            Some(IdentityAuthedRequestError::CommandMismatch)
+        );
+        let approvals: Vec<CommandApprovalPayload> = identity_providers[1..]
+            .iter()
+            .map(|identity_provider| {
+                verify_update_request_and_initiate_challenge(
+                    &update,
+                    &request,
+                    identity_provider,
+                    &verified_parties,
+                )
+                .unwrap()
+            })
+            .collect();
+        let response = quorum_approved_request::challenge_response(
+            &approvals,
+            &identity_providers[0],
+            &request,
+            2,
+            &verified_parties,
+        )
+        .unwrap();
+
+        // Approved policy updates are applied.
+        assert_eq!(
+            apply_update(
+                &policy,
+                update.clone(),
+                &response,
+                &approvals,
+                &request,
+                2,
+                &verified_parties
+            ),
+            Ok(update.clone())
+        );
+
+        // Policy updates for other policies, stale versions or without a quorum are rejected.
+        assert_eq!(
+            apply_update(
+                &policy,
+                Policy {
+                    rules: Vec::new(),
+                    ..update.clone()
+                },
+                &response,
+                &approvals,
+                &request,
+                2,
+                &verified_parties
+            ),
+            Err(PolicyError::CommandMismatch)
+        );
+        assert_eq!(
+            apply_update(
+                &update,
+                update.clone(),
+                &response,
+                &approvals,
+                &request,
+                2,
+                &verified_parties
+            ),
+            Err(PolicyError::StaleVersion)
+        );
+        assert_eq!(
+            apply_update(
+                &policy,
+                update,
+                &response,
+                &approvals[..1],
+                &request,
+                3,
+                &verified_parties
+            ),
+            Err(PolicyError::Approval(
+                QuorumApprovedRequestError::InsufficientApprovals
+            ))
+        );
+    }
+}
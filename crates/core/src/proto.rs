@@ -0,0 +1,665 @@
+//! [Protobuf](https://protobuf.dev/) codecs for payloads (i.e hand-written `prost` messages for the `proto/wamu.proto` schema).
+//!
+//! Allows gRPC-based relays and mobile apps to exchange Wamu messages natively.
+//!
+//! **NOTE:** Messages are converted to payloads with `From` and from payloads with `TryFrom`
+//! (i.e decoding fails with [`Error::Encoding`] for missing fields, unknown enum values and invalid byte lengths).
+
+use crate::crypto;
+use crate::errors::Error;
+use crate::payloads;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// A signature algorithm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum SignatureAlgorithm {
+    Ecdsa = 0,
+    EdDsa = 1,
+}
+
+/// An elliptic curve.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum EllipticCurve {
+    Secp256k1 = 0,
+    Curve25519 = 1,
+}
+
+/// A cryptographic message digest/hash function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum MessageDigest {
+    Sha256 = 0,
+    Sha512 = 1,
+    Keccak256 = 2,
+}
+
+/// A key encoding format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum KeyEncoding {
+    Sec1 = 0,
+    Eip55 = 1,
+    Rfc8032 = 2,
+}
+
+/// A signature encoding format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum SignatureEncoding {
+    Der = 0,
+    Rlp = 1,
+    Rfc8032 = 2,
+}
+
+/// A verifying key.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct VerifyingKey {
+    #[prost(bytes = "vec", tag = "1")]
+    pub key: Vec<u8>,
+    #[prost(enumeration = "SignatureAlgorithm", tag = "2")]
+    pub algo: i32,
+    #[prost(enumeration = "EllipticCurve", tag = "3")]
+    pub curve: i32,
+    #[prost(enumeration = "KeyEncoding", tag = "4")]
+    pub enc: i32,
+}
+
+/// A signature.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Signature {
+    #[prost(bytes = "vec", tag = "1")]
+    pub sig: Vec<u8>,
+    #[prost(enumeration = "SignatureAlgorithm", tag = "2")]
+    pub algo: i32,
+    #[prost(enumeration = "EllipticCurve", tag = "3")]
+    pub curve: i32,
+    #[prost(enumeration = "MessageDigest", tag = "4")]
+    pub hash: i32,
+    #[prost(enumeration = "SignatureEncoding", tag = "5")]
+    pub enc: i32,
+}
+
+/// An identity authenticated request payload.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct IdentityAuthedRequest {
+    #[prost(string, tag = "1")]
+    pub command: String,
+    #[prost(message, optional, tag = "2")]
+    pub verifying_key: Option<VerifyingKey>,
+    #[prost(uint64, tag = "3")]
+    pub timestamp: u64,
+    #[prost(message, optional, tag = "4")]
+    pub signature: Option<Signature>,
+    #[prost(uint64, optional, tag = "5")]
+    pub counter: Option<u64>,
+}
+
+/// An identity rotation challenge response payload.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct IdentityRotationChallengeResponse {
+    #[prost(message, optional, tag = "1")]
+    pub new_verifying_key: Option<VerifyingKey>,
+    #[prost(message, optional, tag = "2")]
+    pub current_signature: Option<Signature>,
+    #[prost(message, optional, tag = "3")]
+    pub new_signature: Option<Signature>,
+}
+
+/// A counter-signature of an identity rotation.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CounterSignature {
+    #[prost(message, optional, tag = "1")]
+    pub verifying_key: Option<VerifyingKey>,
+    #[prost(message, optional, tag = "2")]
+    pub signature: Option<Signature>,
+}
+
+/// An identity rotation certificate.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RotationCertificate {
+    #[prost(message, optional, tag = "1")]
+    pub old_verifying_key: Option<VerifyingKey>,
+    #[prost(message, optional, tag = "2")]
+    pub new_verifying_key: Option<VerifyingKey>,
+    #[prost(uint64, tag = "3")]
+    pub epoch: u64,
+    #[prost(bytes = "vec", tag = "4")]
+    pub previous_hash: Vec<u8>,
+    #[prost(message, repeated, tag = "5")]
+    pub signatures: Vec<CounterSignature>,
+}
+
+/// A command approval payload.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CommandApproval {
+    #[prost(bytes = "vec", tag = "1")]
+    pub challenge_fragment: Vec<u8>,
+    #[prost(message, optional, tag = "2")]
+    pub verifying_key: Option<VerifyingKey>,
+    #[prost(message, optional, tag = "3")]
+    pub signature: Option<Signature>,
+    #[prost(message, optional, tag = "4")]
+    pub delegation: Option<ApprovalDelegation>,
+}
+
+/// An approval delegation payload.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ApprovalDelegation {
+    #[prost(message, optional, tag = "1")]
+    pub delegator: Option<VerifyingKey>,
+    #[prost(message, optional, tag = "2")]
+    pub delegate: Option<VerifyingKey>,
+    #[prost(uint64, tag = "3")]
+    pub expires_at: u64,
+    #[prost(message, optional, tag = "4")]
+    pub signature: Option<Signature>,
+}
+
+/// A command cancellation payload.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CommandCancellation {
+    #[prost(message, optional, tag = "1")]
+    pub verifying_key: Option<VerifyingKey>,
+    #[prost(message, optional, tag = "2")]
+    pub signature: Option<Signature>,
+}
+
+/// A quorum approved challenge response payload.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QuorumApprovedChallengeResponse {
+    #[prost(message, optional, tag = "1")]
+    pub signature: Option<Signature>,
+    #[prost(message, repeated, tag = "2")]
+    pub approving_quorum: Vec<VerifyingKey>,
+}
+
+/// A protocol version offer payload.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct VersionOffer {
+    #[prost(uint32, tag = "1")]
+    pub min_version: u32,
+    #[prost(uint32, tag = "2")]
+    pub max_version: u32,
+    #[prost(message, optional, tag = "3")]
+    pub verifying_key: Option<VerifyingKey>,
+    #[prost(message, optional, tag = "4")]
+    pub signature: Option<Signature>,
+}
+
+/// An offline request payload.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct OfflineRequest {
+    #[prost(bytes = "vec", tag = "1")]
+    pub wallet_id: Vec<u8>,
+    #[prost(message, optional, tag = "2")]
+    pub request: Option<IdentityAuthedRequest>,
+    #[prost(message, optional, tag = "3")]
+    pub verifying_key: Option<VerifyingKey>,
+    #[prost(message, optional, tag = "4")]
+    pub signature: Option<Signature>,
+}
+
+/// An encrypted share backup.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct EncryptedShareBackup {
+    #[prost(bytes = "vec", tag = "1")]
+    pub signing_share: Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub sub_share_x: Vec<u8>,
+    #[prost(bytes = "vec", tag = "3")]
+    pub sub_share_y: Vec<u8>,
+    #[prost(bytes = "vec", tag = "4")]
+    pub nonce: Vec<u8>,
+}
+
+/// Implements conversions between protocol enums and `prost` enumerations (encoded as `i32`).
+macro_rules! impl_enum_conversions {
+    ($core_type:path, $proto_type:ident { $($core_variant:ident => $proto_variant:ident),+ $(,)? }) => {
+        impl From<$core_type> for $proto_type {
+            fn from(value: $core_type) -> Self {
+                match value {
+                    $(<$core_type>::$core_variant => $proto_type::$proto_variant,)+
+                }
+            }
+        }
+
+        impl TryFrom<i32> for $core_type {
+            type Error = Error;
+
+            fn try_from(value: i32) -> Result<Self, Self::Error> {
+                match $proto_type::from_i32(value).ok_or(Error::Encoding)? {
+                    $($proto_type::$proto_variant => Ok(<$core_type>::$core_variant),)+
+                }
+            }
+        }
+    };
+}
+
+impl_enum_conversions!(crypto::SignatureAlgorithm, SignatureAlgorithm {
+    ECDSA => Ecdsa,
+    EdDSA => EdDsa,
+});
+impl_enum_conversions!(crypto::EllipticCurve, EllipticCurve {
+    Secp256k1 => Secp256k1,
+    Curve25519 => Curve25519,
+});
+impl_enum_conversions!(crypto::MessageDigest, MessageDigest {
+    SHA256 => Sha256,
+    SHA512 => Sha512,
+    Keccak256 => Keccak256,
+});
+impl_enum_conversions!(crypto::KeyEncoding, KeyEncoding {
+    SEC1 => Sec1,
+    EIP55 => Eip55,
+    RFC8032 => Rfc8032,
+});
+impl_enum_conversions!(crypto::SignatureEncoding, SignatureEncoding {
+    DER => Der,
+    RLP => Rlp,
+    RFC8032 => Rfc8032,
+});
+
+/// Returns the converted value of a required message field or an encoding error if it's missing.
+fn required<T, U: TryFrom<T, Error = Error>>(value: Option<T>) -> Result<U, Error> {
+    value.ok_or(Error::Encoding)?.try_into()
+}
+
+impl From<crypto::VerifyingKey> for VerifyingKey {
+    fn from(value: crypto::VerifyingKey) -> Self {
+        Self {
+            key: value.key,
+            algo: SignatureAlgorithm::from(value.algo) as i32,
+            curve: EllipticCurve::from(value.curve) as i32,
+            enc: KeyEncoding::from(value.enc) as i32,
+        }
+    }
+}
+
+impl TryFrom<VerifyingKey> for crypto::VerifyingKey {
+    type Error = Error;
+
+    fn try_from(value: VerifyingKey) -> Result<Self, Self::Error> {
+        Ok(Self {
+            key: value.key,
+            algo: value.algo.try_into()?,
+            curve: value.curve.try_into()?,
+            enc: value.enc.try_into()?,
+        })
+    }
+}
+
+impl From<crypto::Signature> for Signature {
+    fn from(value: crypto::Signature) -> Self {
+        Self {
+            sig: value.sig,
+            algo: SignatureAlgorithm::from(value.algo) as i32,
+            curve: EllipticCurve::from(value.curve) as i32,
+            hash: MessageDigest::from(value.hash) as i32,
+            enc: SignatureEncoding::from(value.enc) as i32,
+        }
+    }
+}
+
+impl TryFrom<Signature> for crypto::Signature {
+    type Error = Error;
+
+    fn try_from(value: Signature) -> Result<Self, Self::Error> {
+        Ok(Self {
+            sig: value.sig,
+            algo: value.algo.try_into()?,
+            curve: value.curve.try_into()?,
+            hash: value.hash.try_into()?,
+            enc: value.enc.try_into()?,
+        })
+    }
+}
+
+impl From<payloads::IdentityAuthedRequestPayload> for IdentityAuthedRequest {
+    fn from(value: payloads::IdentityAuthedRequestPayload) -> Self {
+        Self {
+            command: value.command,
+            verifying_key: Some(value.verifying_key.into()),
+            timestamp: value.timestamp,
+            signature: Some(value.signature.into()),
+            counter: value.counter,
+        }
+    }
+}
+
+impl TryFrom<IdentityAuthedRequest> for payloads::IdentityAuthedRequestPayload {
+    type Error = Error;
+
+    fn try_from(value: IdentityAuthedRequest) -> Result<Self, Self::Error> {
+        Ok(Self {
+            command: value.command,
+            verifying_key: required(value.verifying_key)?,
+            timestamp: value.timestamp,
+            counter: value.counter,
+            signature: required(value.signature)?,
+        })
+    }
+}
+
+impl From<payloads::IdentityRotationChallengeResponsePayload>
+    for IdentityRotationChallengeResponse
+{
+    fn from(value: payloads::IdentityRotationChallengeResponsePayload) -> Self {
+        Self {
+            new_verifying_key: Some(value.new_verifying_key.into()),
+            current_signature: Some(value.current_signature.into()),
+            new_signature: Some(value.new_signature.into()),
+        }
+    }
+}
+
+impl TryFrom<IdentityRotationChallengeResponse>
+    for payloads::IdentityRotationChallengeResponsePayload
+{
+    type Error = Error;
+
+    fn try_from(value: IdentityRotationChallengeResponse) -> Result<Self, Self::Error> {
+        Ok(Self {
+            new_verifying_key: required(value.new_verifying_key)?,
+            current_signature: required(value.current_signature)?,
+            new_signature: required(value.new_signature)?,
+        })
+    }
+}
+
+impl From<(crypto::VerifyingKey, crypto::Signature)> for CounterSignature {
+    fn from((verifying_key, signature): (crypto::VerifyingKey, crypto::Signature)) -> Self {
+        Self {
+            verifying_key: Some(verifying_key.into()),
+            signature: Some(signature.into()),
+        }
+    }
+}
+
+impl TryFrom<CounterSignature> for (crypto::VerifyingKey, crypto::Signature) {
+    type Error = Error;
+
+    fn try_from(value: CounterSignature) -> Result<Self, Self::Error> {
+        Ok((required(value.verifying_key)?, required(value.signature)?))
+    }
+}
+
+impl From<payloads::RotationCertificate> for RotationCertificate {
+    fn from(value: payloads::RotationCertificate) -> Self {
+        Self {
+            old_verifying_key: Some(value.old_verifying_key.into()),
+            new_verifying_key: Some(value.new_verifying_key.into()),
+            epoch: value.epoch,
+            previous_hash: value.previous_hash.to_vec(),
+            signatures: value.signatures.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl TryFrom<RotationCertificate> for payloads::RotationCertificate {
+    type Error = Error;
+
+    fn try_from(value: RotationCertificate) -> Result<Self, Self::Error> {
+        Ok(Self {
+            old_verifying_key: required(value.old_verifying_key)?,
+            new_verifying_key: required(value.new_verifying_key)?,
+            epoch: value.epoch,
+            previous_hash: value
+                .previous_hash
+                .try_into()
+                .map_err(|_| Error::Encoding)?,
+            signatures: value
+                .signatures
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl From<payloads::CommandApprovalPayload> for CommandApproval {
+    fn from(value: payloads::CommandApprovalPayload) -> Self {
+        Self {
+            challenge_fragment: value.challenge_fragment.to_be_bytes().to_vec(),
+            verifying_key: Some(value.verifying_key.into()),
+            signature: Some(value.signature.into()),
+            delegation: value.delegation.map(Into::into),
+        }
+    }
+}
+
+impl TryFrom<CommandApproval> for payloads::CommandApprovalPayload {
+    type Error = Error;
+
+    fn try_from(value: CommandApproval) -> Result<Self, Self::Error> {
+        Ok(Self {
+            challenge_fragment: value.challenge_fragment.as_slice().try_into()?,
+            verifying_key: required(value.verifying_key)?,
+            signature: required(value.signature)?,
+            delegation: value.delegation.map(TryInto::try_into).transpose()?,
+        })
+    }
+}
+
+impl From<payloads::ApprovalDelegationPayload> for ApprovalDelegation {
+    fn from(value: payloads::ApprovalDelegationPayload) -> Self {
+        Self {
+            delegator: Some(value.delegator.into()),
+            delegate: Some(value.delegate.into()),
+            expires_at: value.expires_at,
+            signature: Some(value.signature.into()),
+        }
+    }
+}
+
+impl TryFrom<ApprovalDelegation> for payloads::ApprovalDelegationPayload {
+    type Error = Error;
+
+    fn try_from(value: ApprovalDelegation) -> Result<Self, Self::Error> {
+        Ok(Self {
+            delegator: required(value.delegator)?,
+            delegate: required(value.delegate)?,
+            expires_at: value.expires_at,
+            signature: required(value.signature)?,
+        })
+    }
+}
+
+impl From<payloads::CommandCancellationPayload> for CommandCancellation {
+    fn from(value: payloads::CommandCancellationPayload) -> Self {
+        Self {
+            verifying_key: Some(value.verifying_key.into()),
+            signature: Some(value.signature.into()),
+        }
+    }
+}
+
+impl TryFrom<CommandCancellation> for payloads::CommandCancellationPayload {
+    type Error = Error;
+
+    fn try_from(value: CommandCancellation) -> Result<Self, Self::Error> {
+        Ok(Self {
+            verifying_key: required(value.verifying_key)?,
+            signature: required(value.signature)?,
+        })
+    }
+}
+
+impl From<payloads::QuorumApprovedChallengeResponsePayload> for QuorumApprovedChallengeResponse {
+    fn from(value: payloads::QuorumApprovedChallengeResponsePayload) -> Self {
+        Self {
+            signature: Some(value.signature.into()),
+            approving_quorum: value.approving_quorum.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl TryFrom<QuorumApprovedChallengeResponse> for payloads::QuorumApprovedChallengeResponsePayload {
+    type Error = Error;
+
+    fn try_from(value: QuorumApprovedChallengeResponse) -> Result<Self, Self::Error> {
+        Ok(Self {
+            signature: required(value.signature)?,
+            approving_quorum: value
+                .approving_quorum
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl From<payloads::VersionOfferPayload> for VersionOffer {
+    fn from(value: payloads::VersionOfferPayload) -> Self {
+        Self {
+            min_version: value.min_version.into(),
+            max_version: value.max_version.into(),
+            verifying_key: Some(value.verifying_key.into()),
+            signature: Some(value.signature.into()),
+        }
+    }
+}
+
+impl TryFrom<VersionOffer> for payloads::VersionOfferPayload {
+    type Error = Error;
+
+    fn try_from(value: VersionOffer) -> Result<Self, Self::Error> {
+        Ok(Self {
+            min_version: value.min_version.try_into().map_err(|_| Error::Encoding)?,
+            max_version: value.max_version.try_into().map_err(|_| Error::Encoding)?,
+            verifying_key: required(value.verifying_key)?,
+            signature: required(value.signature)?,
+        })
+    }
+}
+
+impl From<payloads::OfflineRequestPayload> for OfflineRequest {
+    fn from(value: payloads::OfflineRequestPayload) -> Self {
+        Self {
+            wallet_id: value.wallet_id.to_vec(),
+            request: Some(value.request.into()),
+            verifying_key: Some(value.verifying_key.into()),
+            signature: Some(value.signature.into()),
+        }
+    }
+}
+
+impl TryFrom<OfflineRequest> for payloads::OfflineRequestPayload {
+    type Error = Error;
+
+    fn try_from(value: OfflineRequest) -> Result<Self, Self::Error> {
+        Ok(Self {
+            wallet_id: value.wallet_id.try_into().map_err(|_| Error::Encoding)?,
+            request: required(value.request)?,
+            verifying_key: required(value.verifying_key)?,
+            signature: required(value.signature)?,
+        })
+    }
+}
+
+impl From<payloads::EncryptedShareBackup> for EncryptedShareBackup {
+    fn from(value: payloads::EncryptedShareBackup) -> Self {
+        Self {
+            signing_share: value.signing_share,
+            sub_share_x: value.sub_share.0,
+            sub_share_y: value.sub_share.1,
+            nonce: value.nonce,
+        }
+    }
+}
+
+impl From<EncryptedShareBackup> for payloads::EncryptedShareBackup {
+    fn from(value: EncryptedShareBackup) -> Self {
+        Self {
+            signing_share: value.signing_share,
+            sub_share: (value.sub_share_x, value.sub_share_y),
+            nonce: value.nonce,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{MockECDSAIdentityProvider, MockEdDSAIdentityProvider};
+    use crate::{identity_authed_request, quorum_approved_request, IdentityProvider};
+    use prost::Message;
+
+    #[test]
+    fn proto_encoding_works() {
+        // Generates identity providers.
+        let identity_provider = MockECDSAIdentityProvider::generate();
+        let delegator = MockEdDSAIdentityProvider::generate();
+        let verified_parties = [identity_provider.verifying_key()];
+
+        // Identity authenticated requests round trip.
+        let request = identity_authed_request::initiate("command", &identity_provider);
+        let encoded = IdentityAuthedRequest::from(request.clone()).encode_to_vec();
+        let decoded = payloads::IdentityAuthedRequestPayload::try_from(
+            IdentityAuthedRequest::decode(encoded.as_slice()).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(decoded.command, request.command);
+        assert_eq!(decoded.verifying_key, request.verifying_key);
+        assert_eq!(decoded.timestamp, request.timestamp);
+        assert_eq!(decoded.counter, request.counter);
+        assert_eq!(decoded.signature, request.signature);
+        assert!(identity_authed_request::verify(&decoded, &verified_parties).is_ok());
+
+        // Delegated command approvals round trip.
+        let mut approval = quorum_approved_request::verify_request_and_initiate_challenge(
+            "command",
+            &request,
+            &identity_provider,
+            &verified_parties,
+        )
+        .unwrap();
+        approval.delegation = Some(quorum_approved_request::delegate(
+            &identity_provider.verifying_key(),
+            u64::MAX,
+            &verified_parties,
+            &delegator,
+        ));
+        let encoded = CommandApproval::from(approval.clone()).encode_to_vec();
+        let decoded = payloads::CommandApprovalPayload::try_from(
+            CommandApproval::decode(encoded.as_slice()).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(decoded.challenge_fragment, approval.challenge_fragment);
+        assert_eq!(decoded.verifying_key, approval.verifying_key);
+        assert_eq!(decoded.signature, approval.signature);
+        let (decoded_delegation, delegation) =
+            (decoded.delegation.unwrap(), approval.delegation.unwrap());
+        assert_eq!(decoded_delegation.delegator, delegation.delegator);
+        assert_eq!(decoded_delegation.delegate, delegation.delegate);
+        assert_eq!(decoded_delegation.expires_at, delegation.expires_at);
+        assert_eq!(decoded_delegation.signature, delegation.signature);
+
+        // Messages with missing fields, unknown enum values or invalid byte lengths are rejected.
+        let mut message = IdentityAuthedRequest::from(request.clone());
+        message.signature = None;
+        assert!(payloads::IdentityAuthedRequestPayload::try_from(message).is_err());
+        let mut message = IdentityAuthedRequest::from(request);
+        message.verifying_key.as_mut().unwrap().algo = 42;
+        assert!(payloads::IdentityAuthedRequestPayload::try_from(message).is_err());
+        let mut message = CommandApproval::from(approval_without_delegation(&identity_provider));
+        message.challenge_fragment.pop();
+        assert!(payloads::CommandApprovalPayload::try_from(message).is_err());
+    }
+
+    /// Returns a command approval payload without a delegation.
+    fn approval_without_delegation(
+        identity_provider: &MockECDSAIdentityProvider,
+    ) -> payloads::CommandApprovalPayload {
+        let request = quorum_approved_request::initiate("command", identity_provider);
+        quorum_approved_request::verify_request_and_initiate_challenge(
+            "command",
+            &request,
+            identity_provider,
+            &[identity_provider.verifying_key()],
+        )
+        .unwrap()
+    }
+}
@@ -2,13 +2,19 @@
 //!
 //! Ref: <https://wamu.tech/specification#quorum-approved-request>.
 
+use crate::canonical::{CanonicalEncode, CommandApprovalMessage, DelegationMessage};
+use crate::cbor::CanonicalCbor;
 use crate::crypto::{Random32Bytes, VerifyingKey};
-use crate::errors::{IdentityAuthedRequestError, QuorumApprovedRequestError};
+use crate::errors::{Error, IdentityAuthedRequestError, QuorumApprovedRequestError};
+use crate::identity_authed_request::RequestVerifier;
 use crate::payloads::{
-    CommandApprovalPayload, IdentityAuthedRequestPayload, QuorumApprovedChallengeResponsePayload,
+    ApprovalDelegationPayload, CommandApprovalPayload, IdentityAuthedRequestPayload,
+    QuorumApprovedChallengeResponsePayload,
 };
 use crate::traits::IdentityProvider;
 use crate::{crypto, identity_authed_request, identity_challenge, utils, wrappers};
+use alloc::vec::Vec;
+use sha2::{Digest, Sha256};
 
 /// Given a "command" and an identity provider, returns the payload for initiating an quorum approved request.
 pub fn initiate(
@@ -18,6 +24,20 @@ pub fn initiate(
     identity_authed_request::initiate(command, identity_provider)
 }
 
+/// Given a "command", a request counter and an identity provider,
+/// returns the payload for initiating a quorum approved request in monotonic counter mode
+/// (see [`identity_authed_request::initiate_with_counter`]).
+///
+/// **NOTE:** Requests in monotonic counter mode can only be approved with a request verifier
+/// (see [`verify_request_and_initiate_challenge_with_verifier`]).
+pub fn initiate_with_counter(
+    command: &'static str,
+    counter: u64,
+    identity_provider: &impl IdentityProvider,
+) -> IdentityAuthedRequestPayload {
+    identity_authed_request::initiate_with_counter(command, counter, identity_provider)
+}
+
 /// Given a "command" a quorum approved request initialization payload, an identity provider and a list of verifying keys for the other parties,
 /// returns an ok result with a "command" approval payload for initiating an identity challenge and approval acknowledgement for a valid request
 /// or an appropriate error result for an invalid request.
@@ -27,20 +47,165 @@ pub fn verify_request_and_initiate_challenge(
     identity_provider: &impl IdentityProvider,
     verified_parties: &[VerifyingKey],
 ) -> Result<CommandApprovalPayload, IdentityAuthedRequestError> {
-    let challenge_fragment = wrappers::verify_identity_authed_request_and_initiate_challenge(
+    create_approval(
         command,
         request,
+        identity_provider,
+        None,
         verified_parties,
-    )?;
-    let signature = identity_provider.sign(&command_approval_message_bytes(
-        &challenge_fragment,
-        request.command,
-        request.timestamp,
-    ));
+        None,
+    )
+}
+
+/// Same as [`verify_request_and_initiate_challenge`] but verifies the request with a request verifier
+/// (i.e identities with failed verifications are rate limited).
+pub fn verify_request_and_initiate_challenge_with_verifier(
+    command: &str,
+    request: &IdentityAuthedRequestPayload,
+    identity_provider: &impl IdentityProvider,
+    verified_parties: &[VerifyingKey],
+    verifier: &mut RequestVerifier,
+) -> Result<CommandApprovalPayload, IdentityAuthedRequestError> {
+    create_approval(
+        command,
+        request,
+        identity_provider,
+        None,
+        verified_parties,
+        Some(verifier),
+    )
+}
+
+/// Given a "command" a quorum approved request initialization payload, the identity provider of a delegate,
+/// an approval delegation payload and a list of verifying keys for the other parties,
+/// returns an ok result with a "command" approval payload on behalf of the delegating party for a valid request
+/// or an appropriate error result for an invalid request.
+pub fn verify_request_and_initiate_delegated_challenge(
+    command: &str,
+    request: &IdentityAuthedRequestPayload,
+    identity_provider: &impl IdentityProvider,
+    delegation: ApprovalDelegationPayload,
+    verified_parties: &[VerifyingKey],
+) -> Result<CommandApprovalPayload, IdentityAuthedRequestError> {
+    create_approval(
+        command,
+        request,
+        identity_provider,
+        Some(delegation),
+        verified_parties,
+        None,
+    )
+}
+
+/// Given the verifying key of a delegate, an expiry timestamp, a list of verifying keys for all parties (i.e the roster)
+/// and the identity provider of the delegating party, returns an approval delegation payload
+/// that authorizes the delegate to approve on behalf of the delegating party for the roster until the expiry timestamp.
+///
+/// **NOTE:** Delegations are scoped to the roster (i.e they're only valid when verified against the same list of verifying keys),
+/// and they aren't transitive (i.e the delegating party must be a verified party, so delegates can't re-delegate).
+pub fn delegate(
+    delegate: &VerifyingKey,
+    expires_at: u64,
+    roster: &[VerifyingKey],
+    identity_provider: &impl IdentityProvider,
+) -> ApprovalDelegationPayload {
+    ApprovalDelegationPayload {
+        delegator: identity_provider.verifying_key(),
+        delegate: delegate.clone(),
+        expires_at,
+        signature: identity_provider.sign(
+            &DelegationMessage {
+                delegate,
+                expires_at,
+                roster_hash: &roster_hash(roster),
+            }
+            .message_bytes(),
+        ),
+    }
+}
+
+/// Returns the hash that scopes approval delegations to a roster
+/// (i.e the SHA-256 hash of the sorted and deduplicated length prefixed canonical CBOR encodings of the verifying keys).
+pub fn roster_hash(roster: &[VerifyingKey]) -> [u8; 32] {
+    let mut keys: Vec<Vec<u8>> = roster.iter().map(CanonicalCbor::to_cbor).collect();
+    keys.sort();
+    keys.dedup();
+    keys.iter()
+        .fold(Sha256::new(), |hasher, key| {
+            hasher.chain_update(utils::length_prefix_bytes(key))
+        })
+        .finalize()
+        .into()
+}
+
+/// Given an approval delegation payload and a list of verifying keys for all parties (i.e the roster),
+/// returns an `Ok` result for a valid delegation from a verified party, or an appropriate `Err` result otherwise.
+pub fn verify_delegation(
+    delegation: &ApprovalDelegationPayload,
+    verified_parties: &[VerifyingKey],
+) -> Result<(), QuorumApprovedRequestError> {
+    if !verified_parties.contains(&delegation.delegator) {
+        // Delegator must be a verified party.
+        Err(QuorumApprovedRequestError::Unauthorized(
+            Error::UnauthorizedParty,
+        ))
+    } else if delegation.expires_at < utils::unix_timestamp() {
+        // Delegation must not be expired.
+        Err(QuorumApprovedRequestError::Expired)
+    } else {
+        // Delegation signature must be valid for the roster.
+        Ok(crypto::verify_signature(
+            &delegation.delegator,
+            &DelegationMessage {
+                delegate: &delegation.delegate,
+                expires_at: delegation.expires_at,
+                roster_hash: &roster_hash(verified_parties),
+            }
+            .message_bytes(),
+            &delegation.signature,
+        )?)
+    }
+}
+
+/// Returns a "command" approval payload for a valid request or an appropriate error result for an invalid request.
+fn create_approval(
+    command: &str,
+    request: &IdentityAuthedRequestPayload,
+    identity_provider: &impl IdentityProvider,
+    delegation: Option<ApprovalDelegationPayload>,
+    verified_parties: &[VerifyingKey],
+    verifier: Option<&mut RequestVerifier>,
+) -> Result<CommandApprovalPayload, IdentityAuthedRequestError> {
+    let challenge_fragment = match verifier {
+        Some(verifier) => {
+            wrappers::verify_identity_authed_request_and_initiate_challenge_with_verifier(
+                command,
+                request,
+                verified_parties,
+                verifier,
+            )
+        }
+        None => wrappers::verify_identity_authed_request_and_initiate_challenge(
+            command,
+            request,
+            verified_parties,
+        ),
+    }?;
+    let signature = identity_provider.sign(
+        &CommandApprovalMessage {
+            challenge_fragment: &challenge_fragment,
+            command: &request.command,
+            timestamp: request.timestamp,
+            counter: request.counter,
+            delegation: delegation.as_ref(),
+        }
+        .message_bytes(),
+    );
     Ok(CommandApprovalPayload {
         challenge_fragment,
         verifying_key: identity_provider.verifying_key(),
         signature,
+        delegation,
     })
 }
 
@@ -59,7 +224,7 @@ pub fn challenge_response(
     let valid_approvals = verify_approvals(approvals, request, quorum_size - 1, verified_parties)?;
     let approving_quorum = valid_approvals
         .iter()
-        .map(|approval| approval.verifying_key.clone())
+        .map(|approval| approver(approval).clone())
         .collect();
     Ok(QuorumApprovedChallengeResponsePayload {
         signature: identity_challenge::respond(
@@ -74,6 +239,20 @@ pub fn challenge_response(
 /// a verifying key for challenged party, a quorum approved request initialization payload,
 /// a quorum size and a list of verifying keys for the other parties,
 /// returns an `Ok` result for valid quorum approved challenge response, or an appropriate `Err` result otherwise.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(
+            command = %request.command,
+            verifying_key = %utils::to_hex(&verifying_key.key),
+            quorum_size,
+            approvals = approvals.len()
+        ),
+        err(level = "warn", Debug)
+    )
+)]
 pub fn verify_challenge_response(
     response: &QuorumApprovedChallengeResponsePayload,
     approvals: &[CommandApprovalPayload],
@@ -84,7 +263,7 @@ pub fn verify_challenge_response(
 ) -> Result<(), QuorumApprovedRequestError> {
     let initiator_acknowledged_approvals: Vec<CommandApprovalPayload> = approvals
         .iter()
-        .filter(|approval| response.approving_quorum.contains(&approval.verifying_key))
+        .filter(|approval| response.approving_quorum.contains(approver(approval)))
         .cloned()
         .collect();
     verify_approvals(
@@ -102,6 +281,167 @@ pub fn verify_challenge_response(
     )?)
 }
 
+/// An approval rule that determines the quorum size for a class of commands.
+///
+/// **NOTE:** This decouples approval rules from the signing threshold,
+/// e.g share removal can require all parties while ordinary requests only require a signing quorum (i.e threshold + 1).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum QuorumPolicy {
+    /// Quorum size = threshold + 1 (i.e the same as the signing quorum).
+    #[default]
+    SigningQuorum,
+    /// Quorum size = threshold + 1 + the given number of additional parties.
+    SigningQuorumPlus(usize),
+    /// Quorum size = total number of parties.
+    AllParties,
+    /// A fixed quorum size.
+    Fixed(usize),
+}
+
+impl QuorumPolicy {
+    /// Given the threshold and the total number of parties, returns the quorum size (including the initiator).
+    ///
+    /// **NOTE:** The quorum size is capped at the total number of parties.
+    pub fn quorum_size(&self, threshold: usize, n_parties: usize) -> usize {
+        let quorum_size = match self {
+            Self::SigningQuorum => threshold + 1,
+            Self::SigningQuorumPlus(extra) => threshold + 1 + extra,
+            Self::AllParties => n_parties,
+            Self::Fixed(quorum_size) => *quorum_size,
+        };
+        quorum_size.min(n_parties)
+    }
+}
+
+/// Incrementally collects and verifies command approvals for a quorum approved request.
+///
+/// Approvals are verified as they arrive and deduplicated by the verifying key of the approving party,
+/// so callers don't need to gather all approvals before verifying them.
+///
+/// **NOTE:** With the `serde` feature enabled, the tracker can be persisted and restored
+/// (e.g so that a coordinator can restart without losing a partially collected set of approvals).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QuorumTracker {
+    /// The quorum approved request initialization payload.
+    request: IdentityAuthedRequestPayload,
+    /// The quorum size (including the implicit approval from the initiator).
+    quorum_size: usize,
+    /// Verifying keys for the other parties.
+    verified_parties: Vec<VerifyingKey>,
+    /// Valid command approvals collected so far.
+    approvals: Vec<CommandApprovalPayload>,
+    /// The UTC timestamp after which no more approvals are accepted (if any).
+    deadline: Option<u64>,
+}
+
+impl QuorumTracker {
+    /// Given a quorum approved request initialization payload, a quorum size and a list of verifying keys for the other parties,
+    /// returns a tracker with no collected approvals.
+    pub fn new(
+        request: IdentityAuthedRequestPayload,
+        quorum_size: usize,
+        verified_parties: &[VerifyingKey],
+    ) -> Self {
+        Self {
+            request,
+            quorum_size,
+            verified_parties: verified_parties.to_vec(),
+            approvals: Vec::new(),
+            deadline: None,
+        }
+    }
+
+    /// Sets the UTC timestamp after which no more approvals are accepted.
+    pub fn with_deadline(mut self, deadline: u64) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Returns the UTC timestamp after which no more approvals are accepted (if any).
+    pub fn deadline(&self) -> Option<u64> {
+        self.deadline
+    }
+
+    /// Returns true if the collection deadline (if any) has passed.
+    pub fn is_expired(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| deadline < utils::unix_timestamp())
+    }
+
+    /// Verifies and adds a command approval payload,
+    /// returns an ok result with `true` if the approval was added or `false` if the approving party already approved
+    /// or an appropriate error result for an invalid approval.
+    pub fn add_approval(
+        &mut self,
+        approval: CommandApprovalPayload,
+    ) -> Result<bool, QuorumApprovedRequestError> {
+        if self.is_expired() {
+            // Approvals can't be collected after the deadline.
+            return Err(QuorumApprovedRequestError::Expired);
+        }
+        verify_approval(&approval, &self.request, &self.verified_parties)?;
+        if self
+            .approvals
+            .iter()
+            .any(|item| approver(item) == approver(&approval))
+        {
+            // Only one approval per approving party is counted.
+            Ok(false)
+        } else {
+            self.approvals.push(approval);
+            Ok(true)
+        }
+    }
+
+    /// Returns the quorum approved request initialization payload.
+    pub fn request(&self) -> &IdentityAuthedRequestPayload {
+        &self.request
+    }
+
+    /// Returns the valid command approvals collected so far.
+    pub fn approvals(&self) -> &[CommandApprovalPayload] {
+        &self.approvals
+    }
+
+    /// Returns the number of valid command approvals collected so far.
+    pub fn collected(&self) -> usize {
+        self.approvals.len()
+    }
+
+    /// Returns the number of command approvals required to form a quorum.
+    pub fn required(&self) -> usize {
+        // quorum_size - 1 because of implicit approval from initiator.
+        self.quorum_size.saturating_sub(1)
+    }
+
+    /// Returns progress as a (`collected`, `required`) tuple.
+    pub fn progress(&self) -> (usize, usize) {
+        (self.collected(), self.required())
+    }
+
+    /// Returns true if enough valid command approvals have been collected to form a quorum.
+    pub fn is_complete(&self) -> bool {
+        self.collected() >= self.required()
+    }
+
+    /// Given an identity provider, returns an ok result with a quorum approved challenge response payload
+    /// for the collected approvals or an appropriate error result if there aren't enough approvals.
+    pub fn challenge_response(
+        &self,
+        identity_provider: &impl IdentityProvider,
+    ) -> Result<QuorumApprovedChallengeResponsePayload, QuorumApprovedRequestError> {
+        challenge_response(
+            &self.approvals,
+            identity_provider,
+            &self.request,
+            self.quorum_size,
+            &self.verified_parties,
+        )
+    }
+}
+
 /// Given a list of command approval payloads, a quorum approved request initialization payload,
 /// a quorum size and a list of verifying keys for the other parties,
 /// returns an ok result with a list of valid command approval payloads if there are enough valid command approvals
@@ -122,39 +462,73 @@ fn verify_approvals(
 
 /// Given a list of command approval payloads, a quorum approved request initialization payload
 /// and a list of verifying keys for the other parties, returns a list of valid command approval payloads.
-fn filter_valid_approvals(
+///
+/// **NOTE:** Only the first valid approval for each approving party (or its delegate) is included.
+pub(crate) fn filter_valid_approvals(
     approvals: &[CommandApprovalPayload],
     request: &IdentityAuthedRequestPayload,
     verified_parties: &[VerifyingKey],
 ) -> Vec<CommandApprovalPayload> {
     approvals
         .iter()
-        .filter(|approval| {
-            verified_parties.contains(&approval.verifying_key)
-                && crypto::verify_signature(
-                    &approval.verifying_key,
-                    &command_approval_message_bytes(
-                        &approval.challenge_fragment,
-                        request.command,
-                        request.timestamp,
-                    ),
-                    &approval.signature,
-                )
-                .is_ok()
+        .filter(|approval| verify_approval(approval, request, verified_parties).is_ok())
+        .fold(Vec::new(), |mut acc, approval| {
+            if !acc
+                .iter()
+                .any(|item: &CommandApprovalPayload| approver(item) == approver(approval))
+            {
+                acc.push(approval.clone());
+            }
+            acc
         })
-        .cloned()
-        .collect()
 }
 
-/// Returns sign-able message bytes for the command approval.
-fn command_approval_message_bytes(
-    challenge_fragment: &Random32Bytes,
-    command: &str,
-    timestamp: u64,
-) -> Vec<u8> {
-    utils::prefix_message_bytes(
-        format!("{}{}{}", challenge_fragment, command, timestamp).as_bytes(),
-    )
+/// Given a command approval payload, a quorum approved request initialization payload
+/// and a list of verifying keys for the other parties,
+/// returns an `Ok` result for a valid command approval, or an appropriate `Err` result otherwise.
+fn verify_approval(
+    approval: &CommandApprovalPayload,
+    request: &IdentityAuthedRequestPayload,
+    verified_parties: &[VerifyingKey],
+) -> Result<(), QuorumApprovedRequestError> {
+    if let Some(delegation) = approval.delegation.as_ref() {
+        // Delegation must be valid and for the approving party.
+        verify_delegation(delegation, verified_parties)?;
+        if delegation.delegate != approval.verifying_key {
+            return Err(QuorumApprovedRequestError::Unauthorized(
+                Error::UnauthorizedParty,
+            ));
+        }
+    }
+    if !verified_parties.contains(approver(approval)) {
+        // Approver must be a verified party.
+        Err(QuorumApprovedRequestError::Unauthorized(
+            Error::UnauthorizedParty,
+        ))
+    } else {
+        // Approval signature must be valid.
+        Ok(crypto::verify_signature(
+            &approval.verifying_key,
+            &CommandApprovalMessage {
+                challenge_fragment: &approval.challenge_fragment,
+                command: &request.command,
+                timestamp: request.timestamp,
+                counter: request.counter,
+                delegation: approval.delegation.as_ref(),
+            }
+            .message_bytes(),
+            &approval.signature,
+        )?)
+    }
+}
+
+/// Returns the verifying key of the party on whose behalf the command approval is made
+/// (i.e the delegating party for delegated approvals, or the approving party otherwise).
+pub(crate) fn approver(approval: &CommandApprovalPayload) -> &VerifyingKey {
+    approval
+        .delegation
+        .as_ref()
+        .map_or(&approval.verifying_key, |delegation| &delegation.delegator)
 }
 
 /// Given a list of command approval payloads and an identity provider, returns a list of wrapped challenge fragments.
@@ -167,8 +541,9 @@ fn extract_challenge_fragments(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::errors::{CryptoError, Error};
+    use crate::errors::CryptoError;
     use crate::test_utils::MockECDSAIdentityProvider;
+    use alloc::vec;
     use crypto_bigint::U256;
 
     #[test]
@@ -262,15 +637,21 @@ mod tests {
                     .iter()
                     .map(|identity_provider| {
                         let challenge_fragment = Random32Bytes::from(U256::ONE);
-                        let signature = identity_provider.sign(&command_approval_message_bytes(
-                            &challenge_fragment,
-                            init_payload.command,
-                            init_payload.timestamp,
-                        ));
+                        let signature = identity_provider.sign(
+                            &CommandApprovalMessage {
+                                challenge_fragment: &challenge_fragment,
+                                command: &init_payload.command,
+                                timestamp: init_payload.timestamp,
+                                counter: None,
+                                delegation: None,
+                            }
+                            .message_bytes(),
+                        );
                         CommandApprovalPayload {
                             challenge_fragment,
                             verifying_key: identity_provider.verifying_key(),
                             signature,
+                            delegation: None,
                         }
                     })
                     .collect(),
@@ -309,4 +690,317 @@ mod tests {
             assert_eq!(challenge_result, expected_challenge_result);
         }
     }
+
+    #[test]
+    fn quorum_policy_works() {
+        let (threshold, n_parties) = (2, 5);
+        for (policy, expected_quorum_size) in [
+            // Signing quorum is threshold + 1.
+            (QuorumPolicy::SigningQuorum, 3),
+            // Additional parties are added to the signing quorum.
+            (QuorumPolicy::SigningQuorumPlus(1), 4),
+            // Quorum size can't be larger than the total number of parties.
+            (QuorumPolicy::SigningQuorumPlus(5), 5),
+            // All parties.
+            (QuorumPolicy::AllParties, 5),
+            // Fixed quorum size.
+            (QuorumPolicy::Fixed(2), 2),
+        ] {
+            assert_eq!(
+                policy.quorum_size(threshold, n_parties),
+                expected_quorum_size
+            );
+        }
+    }
+
+    #[test]
+    fn quorum_tracker_works() {
+        // Generates initiator identity provider.
+        let initiator_identity_provider = MockECDSAIdentityProvider::generate();
+
+        // Creates identity providers for all other parties.
+        let approver_identity_providers: Vec<MockECDSAIdentityProvider> = (0..3)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+
+        // Creates a list of verifying keys for all parties.
+        let verified_parties: Vec<VerifyingKey> = approver_identity_providers
+            .iter()
+            .map(|identity_provider| identity_provider.verifying_key())
+            .chain([initiator_identity_provider.verifying_key()])
+            .collect();
+
+        // Sets the command.
+        let command = "command";
+
+        // Generates quorum approved request initialization payload.
+        let init_payload = initiate(command, &initiator_identity_provider);
+
+        // Initializes tracker with a quorum size of 3 (i.e initiator + 2 approvals).
+        let mut tracker = QuorumTracker::new(init_payload.clone(), 3, &verified_parties);
+        assert_eq!(tracker.progress(), (0, 2));
+        assert!(!tracker.is_complete());
+
+        // Generates approvals.
+        let approvals: Vec<CommandApprovalPayload> = approver_identity_providers
+            .iter()
+            .map(|identity_provider| {
+                verify_request_and_initiate_challenge(
+                    command,
+                    &init_payload,
+                    identity_provider,
+                    &verified_parties,
+                )
+                .unwrap()
+            })
+            .collect();
+
+        // Approval from an unverified party should be rejected.
+        let unverified_approval = verify_request_and_initiate_challenge(
+            command,
+            &init_payload,
+            &MockECDSAIdentityProvider::generate(),
+            &verified_parties,
+        )
+        .unwrap();
+        assert_eq!(
+            tracker.add_approval(unverified_approval),
+            Err(QuorumApprovedRequestError::Unauthorized(
+                Error::UnauthorizedParty
+            ))
+        );
+
+        // Approval with an invalid signature should be rejected.
+        let mut invalid_approval = approvals[0].clone();
+        invalid_approval.challenge_fragment = Random32Bytes::from(U256::ONE);
+        assert_eq!(
+            tracker.add_approval(invalid_approval),
+            Err(QuorumApprovedRequestError::Unauthorized(Error::Crypto(
+                CryptoError::InvalidSignature,
+            )))
+        );
+
+        // Valid approvals are added and duplicates are ignored.
+        assert_eq!(tracker.add_approval(approvals[0].clone()), Ok(true));
+        assert_eq!(tracker.add_approval(approvals[0].clone()), Ok(false));
+        assert_eq!(tracker.progress(), (1, 2));
+        assert!(!tracker.is_complete());
+        assert_eq!(
+            tracker
+                .challenge_response(&initiator_identity_provider)
+                .map(|_| ()),
+            Err(QuorumApprovedRequestError::InsufficientApprovals)
+        );
+        assert_eq!(tracker.add_approval(approvals[1].clone()), Ok(true));
+        assert_eq!(tracker.progress(), (2, 2));
+        assert!(tracker.is_complete());
+
+        // Challenge response from the collected approvals should be accepted.
+        let challenge_payload = tracker
+            .challenge_response(&initiator_identity_provider)
+            .unwrap();
+        assert_eq!(
+            verify_challenge_response(
+                &challenge_payload,
+                &approvals,
+                &initiator_identity_provider.verifying_key(),
+                &init_payload,
+                3,
+                &verified_parties,
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn delegated_approval_works() {
+        // Generates initiator, approver and delegate identity providers.
+        let initiator_identity_provider = MockECDSAIdentityProvider::generate();
+        let approver_identity_provider = MockECDSAIdentityProvider::generate();
+        let other_approver_identity_provider = MockECDSAIdentityProvider::generate();
+        let delegate_identity_provider = MockECDSAIdentityProvider::generate();
+
+        // Creates a list of verifying keys for all parties (excluding the delegate).
+        let verified_parties = vec![
+            initiator_identity_provider.verifying_key(),
+            approver_identity_provider.verifying_key(),
+            other_approver_identity_provider.verifying_key(),
+        ];
+
+        // Sets the command.
+        let command = "command";
+
+        // Generates quorum approved request initialization payload.
+        let init_payload = initiate(command, &initiator_identity_provider);
+
+        // Generates a delegation from the approver to the delegate.
+        let expires_at = utils::unix_timestamp() + 3600;
+        let delegation = delegate(
+            &delegate_identity_provider.verifying_key(),
+            expires_at,
+            &verified_parties,
+            &approver_identity_provider,
+        );
+        assert_eq!(verify_delegation(&delegation, &verified_parties), Ok(()));
+
+        // Delegation for another roster should be rejected.
+        assert_eq!(
+            verify_delegation(&delegation, &verified_parties[..2]),
+            Err(QuorumApprovedRequestError::Unauthorized(Error::Crypto(
+                CryptoError::InvalidSignature
+            )))
+        );
+
+        // Delegated approval counts as an approval from the delegating party.
+        let approval = verify_request_and_initiate_delegated_challenge(
+            command,
+            &init_payload,
+            &delegate_identity_provider,
+            delegation.clone(),
+            &verified_parties,
+        )
+        .unwrap();
+        let mut tracker = QuorumTracker::new(init_payload.clone(), 2, &verified_parties);
+        assert_eq!(tracker.add_approval(approval.clone()), Ok(true));
+        assert!(tracker.is_complete());
+
+        // Direct approval from the delegating party is a duplicate.
+        let direct_approval = verify_request_and_initiate_challenge(
+            command,
+            &init_payload,
+            &approver_identity_provider,
+            &verified_parties,
+        )
+        .unwrap();
+        assert_eq!(tracker.add_approval(direct_approval), Ok(false));
+
+        // Challenge response is attributed to the delegating party.
+        let challenge_payload = tracker
+            .challenge_response(&initiator_identity_provider)
+            .unwrap();
+        assert_eq!(
+            challenge_payload.approving_quorum,
+            vec![approver_identity_provider.verifying_key()]
+        );
+        assert_eq!(
+            verify_challenge_response(
+                &challenge_payload,
+                core::slice::from_ref(&approval),
+                &initiator_identity_provider.verifying_key(),
+                &init_payload,
+                2,
+                &verified_parties,
+            ),
+            Ok(())
+        );
+
+        // Delegation from an unverified party should be rejected.
+        let unverified_delegation = delegate(
+            &delegate_identity_provider.verifying_key(),
+            expires_at,
+            &verified_parties,
+            &MockECDSAIdentityProvider::generate(),
+        );
+        assert_eq!(
+            verify_delegation(&unverified_delegation, &verified_parties),
+            Err(QuorumApprovedRequestError::Unauthorized(
+                Error::UnauthorizedParty
+            ))
+        );
+
+        // Expired delegation should be rejected.
+        let expired_delegation = delegate(
+            &delegate_identity_provider.verifying_key(),
+            utils::unix_timestamp() - 1,
+            &verified_parties,
+            &approver_identity_provider,
+        );
+        assert_eq!(
+            verify_delegation(&expired_delegation, &verified_parties),
+            Err(QuorumApprovedRequestError::Expired)
+        );
+
+        // Delegated approval from a party other than the delegate should be rejected.
+        let mut impostor_approval = verify_request_and_initiate_challenge(
+            command,
+            &init_payload,
+            &MockECDSAIdentityProvider::generate(),
+            &verified_parties,
+        )
+        .unwrap();
+        impostor_approval.delegation = Some(delegation);
+        let mut tracker = QuorumTracker::new(init_payload.clone(), 2, &verified_parties);
+        assert_eq!(
+            tracker.add_approval(impostor_approval),
+            Err(QuorumApprovedRequestError::Unauthorized(
+                Error::UnauthorizedParty
+            ))
+        );
+
+        // Delegated approval with a re-attached delegation (i.e from another party to the same delegate) should be rejected.
+        let mut reattached_approval = approval;
+        reattached_approval.delegation = Some(delegate(
+            &delegate_identity_provider.verifying_key(),
+            expires_at,
+            &verified_parties,
+            &other_approver_identity_provider,
+        ));
+        assert_eq!(
+            tracker.add_approval(reattached_approval),
+            Err(QuorumApprovedRequestError::Unauthorized(Error::Crypto(
+                CryptoError::InvalidSignature
+            )))
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn quorum_tracker_persistence_works() {
+        // Generates identity providers.
+        let initiator_identity_provider = MockECDSAIdentityProvider::generate();
+        let approver_identity_provider = MockECDSAIdentityProvider::generate();
+        let verified_parties = vec![
+            initiator_identity_provider.verifying_key(),
+            approver_identity_provider.verifying_key(),
+        ];
+
+        // Initializes tracker and adds an approval.
+        let init_payload = initiate("command", &initiator_identity_provider);
+        let mut tracker = QuorumTracker::new(init_payload.clone(), 3, &verified_parties)
+            .with_deadline(utils::unix_timestamp() + 60 * 60);
+        let approval = verify_request_and_initiate_challenge(
+            "command",
+            &init_payload,
+            &approver_identity_provider,
+            &verified_parties,
+        )
+        .unwrap();
+        assert_eq!(tracker.add_approval(approval.clone()), Ok(true));
+
+        // Persists and restores the tracker.
+        let serialized = serde_json::to_string(&tracker).unwrap();
+        let mut restored: QuorumTracker = serde_json::from_str(&serialized).unwrap();
+
+        // Verifies that the restored tracker retains its state.
+        assert_eq!(restored.progress(), tracker.progress());
+        assert_eq!(restored.deadline(), tracker.deadline());
+        assert_eq!(restored.request().command, init_payload.command);
+        assert_eq!(restored.add_approval(approval), Ok(false));
+
+        // Approvals after the deadline should be rejected.
+        let mut expired_tracker = restored.with_deadline(utils::unix_timestamp() - 1);
+        assert!(expired_tracker.is_expired());
+        assert_eq!(
+            expired_tracker.add_approval(
+                verify_request_and_initiate_challenge(
+                    "command",
+                    &init_payload,
+                    &approver_identity_provider,
+                    &verified_parties,
+                )
+                .unwrap()
+            ),
+            Err(QuorumApprovedRequestError::Expired)
+        );
+    }
 }
@@ -0,0 +1,206 @@
+//! Rate limiting and lockout for identity authenticated requests.
+//!
+//! Tracks failed request verifications per identity and enforces exponential backoff and temporary lockout
+//! to blunt online brute-force or spam attempts against always-on co-signers.
+//!
+//! **NOTE:** Requests are throttled when verified with a [`RequestVerifier`](crate::identity_authed_request::RequestVerifier)
+//! (e.g by [`Wallet::approve_request`](crate::wrappers::Wallet::approve_request)).
+
+use crate::crypto::VerifyingKey;
+use crate::utils;
+use alloc::vec::Vec;
+
+/// Default backoff (in seconds) after the first failed verification.
+const DEFAULT_BASE_BACKOFF: u64 = 1;
+
+/// Default maximum backoff (in seconds) between attempts.
+const DEFAULT_MAX_BACKOFF: u64 = 5 * 60; // 5 minutes.
+
+/// Default number of consecutive failed verifications before an identity is locked out.
+const DEFAULT_LOCKOUT_THRESHOLD: u32 = 10;
+
+/// Default lockout duration (in seconds).
+const DEFAULT_LOCKOUT_DURATION: u64 = 60 * 60; // 1 hour.
+
+/// Tracks failed request verifications per identity and enforces exponential backoff and temporary lockout.
+///
+/// **NOTE:** Only failures from verified parties are tracked
+/// (i.e requests from unverified parties are rejected before any expensive verification is performed).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RequestThrottle {
+    /// Backoff (in seconds) after the first failed verification (doubled after each consecutive failure).
+    base_backoff: u64,
+    /// Maximum backoff (in seconds) between attempts.
+    max_backoff: u64,
+    /// Number of consecutive failed verifications before an identity is locked out.
+    lockout_threshold: u32,
+    /// Lockout duration (in seconds).
+    lockout_duration: u64,
+    /// Failed verification records for identities with at least one consecutive failure.
+    failures: Vec<(VerifyingKey, FailureRecord)>,
+}
+
+/// Consecutive failed verifications for an identity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct FailureRecord {
+    /// Number of consecutive failed verifications.
+    count: u32,
+    /// UTC timestamp of the last failed verification.
+    last_failure: u64,
+}
+
+impl Default for RequestThrottle {
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_BASE_BACKOFF,
+            DEFAULT_MAX_BACKOFF,
+            DEFAULT_LOCKOUT_THRESHOLD,
+            DEFAULT_LOCKOUT_DURATION,
+        )
+    }
+}
+
+impl RequestThrottle {
+    /// Given a base backoff, a maximum backoff, a lockout threshold and a lockout duration (with all durations in seconds),
+    /// returns a request throttle.
+    pub fn new(
+        base_backoff: u64,
+        max_backoff: u64,
+        lockout_threshold: u32,
+        lockout_duration: u64,
+    ) -> Self {
+        Self {
+            base_backoff,
+            max_backoff,
+            lockout_threshold,
+            lockout_duration,
+            failures: Vec::new(),
+        }
+    }
+
+    /// Returns the UTC timestamp before which requests from the identity are rejected (if any).
+    pub fn retry_after(&self, verifying_key: &VerifyingKey) -> Option<u64> {
+        self.record(verifying_key)
+            .map(|record| {
+                record
+                    .last_failure
+                    .saturating_add(self.penalty(record.count))
+            })
+            .filter(|retry_after| utils::unix_timestamp() < *retry_after)
+    }
+
+    /// Returns true if requests from the identity are currently rejected (i.e either backing off or locked out).
+    pub fn is_throttled(&self, verifying_key: &VerifyingKey) -> bool {
+        self.retry_after(verifying_key).is_some()
+    }
+
+    /// Returns true if the identity is currently locked out.
+    pub fn is_locked_out(&self, verifying_key: &VerifyingKey) -> bool {
+        self.record(verifying_key)
+            .is_some_and(|record| record.count >= self.lockout_threshold)
+            && self.is_throttled(verifying_key)
+    }
+
+    /// Returns the number of consecutive failed verifications for the identity.
+    pub fn failures(&self, verifying_key: &VerifyingKey) -> u32 {
+        self.record(verifying_key).map_or(0, |record| record.count)
+    }
+
+    /// Records a failed verification for the identity.
+    pub fn record_failure(&mut self, verifying_key: &VerifyingKey) {
+        let now = utils::unix_timestamp();
+        match self
+            .failures
+            .iter_mut()
+            .find(|(key, _)| key == verifying_key)
+        {
+            Some((_, record)) => {
+                record.count = record.count.saturating_add(1);
+                record.last_failure = now;
+            }
+            None => self.failures.push((
+                verifying_key.clone(),
+                FailureRecord {
+                    count: 1,
+                    last_failure: now,
+                },
+            )),
+        }
+    }
+
+    /// Records a successful verification for the identity (i.e resets its consecutive failures).
+    pub fn record_success(&mut self, verifying_key: &VerifyingKey) {
+        self.failures.retain(|(key, _)| key != verifying_key);
+    }
+
+    /// Returns the failure record for the identity (if any).
+    fn record(&self, verifying_key: &VerifyingKey) -> Option<&FailureRecord> {
+        self.failures
+            .iter()
+            .find(|(key, _)| key == verifying_key)
+            .map(|(_, record)| record)
+    }
+
+    /// Returns the penalty (in seconds) for the given number of consecutive failures
+    /// (i.e the lockout duration once the lockout threshold is reached, or an exponential backoff otherwise).
+    fn penalty(&self, count: u32) -> u64 {
+        if count >= self.lockout_threshold {
+            self.lockout_duration
+        } else {
+            self.base_backoff
+                .saturating_mul(2u64.saturating_pow(count.saturating_sub(1)))
+                .min(self.max_backoff)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockECDSAIdentityProvider;
+    use crate::IdentityProvider;
+
+    #[test]
+    fn request_throttle_works() {
+        // Generates verifying keys.
+        let verifying_key = MockECDSAIdentityProvider::generate().verifying_key();
+        let other_verifying_key = MockECDSAIdentityProvider::generate().verifying_key();
+
+        // Initializes throttle with a 10 second base backoff, 25 second max backoff and lockout after 4 failures.
+        let mut throttle = RequestThrottle::new(10, 25, 4, 3600);
+        assert!(!throttle.is_throttled(&verifying_key));
+
+        // Backoff grows exponentially up to the maximum backoff.
+        for (count, backoff) in [(1, 10), (2, 20), (3, 25)] {
+            throttle.record_failure(&verifying_key);
+            assert_eq!(throttle.failures(&verifying_key), count);
+            assert!(throttle.is_throttled(&verifying_key));
+            assert!(!throttle.is_locked_out(&verifying_key));
+            let retry_after = throttle.retry_after(&verifying_key).unwrap();
+            assert!(retry_after <= utils::unix_timestamp() + backoff);
+            assert!(retry_after + 1 >= utils::unix_timestamp() + backoff);
+        }
+
+        // Identity is locked out after reaching the lockout threshold.
+        throttle.record_failure(&verifying_key);
+        assert!(throttle.is_locked_out(&verifying_key));
+        assert!(
+            throttle.retry_after(&verifying_key).unwrap() + 1 >= utils::unix_timestamp() + 3600
+        );
+
+        // Other identities are not affected.
+        assert!(!throttle.is_throttled(&other_verifying_key));
+
+        // Success resets consecutive failures.
+        throttle.record_success(&verifying_key);
+        assert_eq!(throttle.failures(&verifying_key), 0);
+        assert!(!throttle.is_throttled(&verifying_key));
+
+        // No backoff means no throttling.
+        let mut throttle = RequestThrottle::new(0, 0, u32::MAX, 0);
+        throttle.record_failure(&verifying_key);
+        assert!(!throttle.is_throttled(&verifying_key));
+    }
+}
@@ -0,0 +1,256 @@
+//! A roster changelog of chained identity rotation certificates.
+//!
+//! Allows auditors and late-joining devices to verify how the current roster evolved from a genesis roster.
+
+use alloc::vec::Vec;
+use sha2::{Digest, Sha256};
+
+use crate::canonical::CanonicalEncode;
+use crate::cbor::CanonicalCbor;
+use crate::crypto::VerifyingKey;
+use crate::errors::RosterLogError;
+use crate::payloads::RotationCertificate;
+use crate::{identity_rotation, utils};
+
+/// A roster log (i.e a genesis roster and a chain of identity rotation certificates).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RosterLog {
+    /// Verifying keys for all parties in the genesis roster.
+    genesis: Vec<VerifyingKey>,
+    /// Minimum number of counter-signatures from the roster for each certificate.
+    quorum_size: usize,
+    /// Chained identity rotation certificates.
+    certificates: Vec<RotationCertificate>,
+    /// Verifying keys for all parties in the current roster.
+    roster: Vec<VerifyingKey>,
+    /// Hash of the roster log head.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    head_hash: [u8; 32],
+}
+
+impl RosterLog {
+    /// Given a list of verifying keys for all parties in the genesis roster and
+    /// the minimum number of counter-signatures required for each certificate, returns an empty roster log.
+    pub fn new(genesis: Vec<VerifyingKey>, quorum_size: usize) -> Self {
+        let head_hash = genesis_hash(&genesis);
+        Self {
+            roster: genesis.clone(),
+            genesis,
+            quorum_size,
+            certificates: Vec::new(),
+            head_hash,
+        }
+    }
+
+    /// Given a list of verifying keys for all parties in the genesis roster,
+    /// the minimum number of counter-signatures required for each certificate and a list of identity rotation certificates,
+    /// returns an `Ok` result with a roster log if all certificates form a valid chain, or an appropriate `Err` result otherwise.
+    pub fn from_certificates(
+        genesis: Vec<VerifyingKey>,
+        quorum_size: usize,
+        certificates: Vec<RotationCertificate>,
+    ) -> Result<Self, RosterLogError> {
+        let mut roster_log = Self::new(genesis, quorum_size);
+        for certificate in certificates {
+            roster_log.append(certificate)?;
+        }
+        Ok(roster_log)
+    }
+
+    /// Returns the verifying keys for all parties in the genesis roster.
+    pub fn genesis(&self) -> &[VerifyingKey] {
+        &self.genesis
+    }
+
+    /// Returns the verifying keys for all parties in the current roster.
+    pub fn roster(&self) -> &[VerifyingKey] {
+        &self.roster
+    }
+
+    /// Returns the chained identity rotation certificates.
+    pub fn certificates(&self) -> &[RotationCertificate] {
+        &self.certificates
+    }
+
+    /// Returns the current roster epoch (i.e the number of certificates in the roster log).
+    pub fn epoch(&self) -> u64 {
+        self.certificates.len() as u64
+    }
+
+    /// Returns the hash of the roster log head.
+    pub fn head_hash(&self) -> [u8; 32] {
+        self.head_hash
+    }
+
+    /// Given an identity rotation certificate,
+    /// appends it to the roster log and applies the rotation to the current roster if it's valid,
+    /// or returns an appropriate `Err` result otherwise.
+    pub fn append(&mut self, certificate: RotationCertificate) -> Result<(), RosterLogError> {
+        if certificate.epoch != self.epoch() + 1 || certificate.previous_hash != self.head_hash {
+            // Certificate must extend the roster log head.
+            return Err(RosterLogError::InvalidChain);
+        }
+        let position = self
+            .roster
+            .iter()
+            .position(|key| key == &certificate.old_verifying_key);
+        let idx = match position {
+            // New verifying key must not already be in the roster.
+            Some(idx) if !self.roster.contains(&certificate.new_verifying_key) => idx,
+            // Old verifying key must be in the roster.
+            _ => return Err(RosterLogError::InvalidRotation),
+        };
+        // Certificate must be counter-signed by a quorum of the current roster.
+        identity_rotation::verify_certificate(&certificate, &self.roster, self.quorum_size)?;
+
+        // Applies the rotation and moves the roster log head.
+        self.roster[idx] = certificate.new_verifying_key.clone();
+        self.head_hash = certificate_hash(&certificate);
+        self.certificates.push(certificate);
+
+        Ok(())
+    }
+}
+
+/// Returns the hash of the genesis roster (i.e `sha256("roster-genesis" || lp(cbor(key)) || ...)`,
+/// where `lp` is the big-endian `u64` length prefixed bytes, so that each key is tagged with its algorithm and length).
+fn genesis_hash(genesis: &[VerifyingKey]) -> [u8; 32] {
+    genesis
+        .iter()
+        .fold(
+            Sha256::new().chain_update(b"roster-genesis"),
+            |hasher, key| hasher.chain_update(utils::length_prefix_bytes(&key.to_cbor())),
+        )
+        .finalize()
+        .into()
+}
+
+/// Returns the hash of the identity rotation certificate (i.e the new roster log head).
+fn certificate_hash(certificate: &RotationCertificate) -> [u8; 32] {
+    Sha256::digest(certificate.message_bytes()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{EllipticCurve, KeyEncoding, SignatureAlgorithm};
+    use crate::test_utils::MockECDSAIdentityProvider;
+    use crate::IdentityProvider;
+    use alloc::vec;
+
+    #[test]
+    fn roster_log_works() {
+        // Creates identity providers and a genesis roster for all parties.
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (0..4)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let genesis: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+
+        // Initializes roster log requiring 3 counter-signatures per certificate.
+        let mut roster_log = RosterLog::new(genesis.clone(), 3);
+        assert_eq!(roster_log.epoch(), 0);
+
+        // Rotates the identity of the first party.
+        let new_identity_provider = MockECDSAIdentityProvider::generate();
+        let old_verifying_key = identity_providers[0].verifying_key();
+        let new_verifying_key = new_identity_provider.verifying_key();
+        let epoch = roster_log.epoch() + 1;
+        let previous_hash = roster_log.head_hash();
+        let countersign = |identity_provider: &MockECDSAIdentityProvider| {
+            identity_rotation::countersign(
+                &old_verifying_key,
+                &new_verifying_key,
+                epoch,
+                &previous_hash,
+                identity_provider,
+            )
+        };
+
+        // Certificate without enough counter-signatures from the roster should be rejected.
+        let certificate = identity_rotation::certificate(
+            &old_verifying_key,
+            &new_verifying_key,
+            epoch,
+            &previous_hash,
+            vec![
+                countersign(&identity_providers[1]),
+                countersign(&identity_providers[1]),
+                countersign(&MockECDSAIdentityProvider::generate()),
+            ],
+        );
+        assert_eq!(
+            roster_log.append(certificate),
+            Err(RosterLogError::InsufficientSignatures)
+        );
+
+        // Certificate that doesn't extend the roster log head should be rejected.
+        let signatures: Vec<_> = identity_providers[1..].iter().map(countersign).collect();
+        let certificate = identity_rotation::certificate(
+            &old_verifying_key,
+            &new_verifying_key,
+            epoch,
+            &previous_hash,
+            signatures,
+        );
+        let mut wrong_epoch_certificate = certificate.clone();
+        wrong_epoch_certificate.epoch = epoch + 1;
+        assert_eq!(
+            roster_log.append(wrong_epoch_certificate),
+            Err(RosterLogError::InvalidChain)
+        );
+
+        // Valid certificate is appended and applied to the roster.
+        assert_eq!(roster_log.append(certificate.clone()), Ok(()));
+        assert_eq!(roster_log.epoch(), 1);
+        assert_eq!(roster_log.roster()[0], new_verifying_key);
+        assert_eq!(roster_log.genesis(), genesis.as_slice());
+        assert_ne!(roster_log.head_hash(), previous_hash);
+
+        // Replaying the same certificate should be rejected.
+        assert_eq!(
+            roster_log.append(certificate),
+            Err(RosterLogError::InvalidChain)
+        );
+
+        // Late-joining devices can verify the roster from the genesis roster and certificates.
+        let replayed_roster_log =
+            RosterLog::from_certificates(genesis, 3, roster_log.certificates().to_vec()).unwrap();
+        assert_eq!(replayed_roster_log, roster_log);
+
+        // Mixed rosters with the same concatenated key bytes have different genesis hashes.
+        let key = |key: &[u8], algo, curve, enc| VerifyingKey {
+            key: key.to_vec(),
+            algo,
+            curve,
+            enc,
+        };
+        let ecdsa = |bytes: &[u8]| {
+            key(
+                bytes,
+                SignatureAlgorithm::ECDSA,
+                EllipticCurve::Secp256k1,
+                KeyEncoding::SEC1,
+            )
+        };
+        let eddsa = |bytes: &[u8]| {
+            key(
+                bytes,
+                SignatureAlgorithm::EdDSA,
+                EllipticCurve::Curve25519,
+                KeyEncoding::RFC8032,
+            )
+        };
+        assert_ne!(
+            genesis_hash(&[ecdsa(&[1, 2]), eddsa(&[3])]),
+            genesis_hash(&[ecdsa(&[1]), eddsa(&[2, 3])])
+        );
+        assert_ne!(
+            genesis_hash(&[ecdsa(&[1, 2])]),
+            genesis_hash(&[eddsa(&[1, 2])])
+        );
+    }
+}
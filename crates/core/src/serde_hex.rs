@@ -0,0 +1,119 @@
+//! `serde` helpers for byte fields (i.e `0x` prefixed hex strings for human-readable formats like JSON, and raw bytes otherwise).
+
+use alloc::format;
+use alloc::vec::Vec;
+use core::fmt;
+
+use serde::de::{self, SeqAccess, Visitor};
+use serde::{Deserializer, Serializer};
+
+use crate::utils;
+
+/// Serializes bytes as a `0x` prefixed hex string for human-readable formats, or as raw bytes otherwise.
+pub fn serialize<T: AsRef<[u8]>, S: Serializer>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        serializer.serialize_str(&format!("0x{}", utils::to_hex(value.as_ref())))
+    } else {
+        serializer.serialize_bytes(value.as_ref())
+    }
+}
+
+/// Deserializes bytes from a (optionally `0x` prefixed) hex string for human-readable formats, or from raw bytes otherwise.
+pub fn deserialize<'de, T: TryFrom<Vec<u8>>, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<T, D::Error> {
+    let bytes = if deserializer.is_human_readable() {
+        deserializer.deserialize_str(BytesVisitor)?
+    } else {
+        deserializer.deserialize_byte_buf(BytesVisitor)?
+    };
+    let len = bytes.len();
+    T::try_from(bytes).map_err(|_| de::Error::invalid_length(len, &"the expected number of bytes"))
+}
+
+/// Visitor for hex strings, raw bytes and byte sequences.
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a hex string or bytes")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        utils::from_hex(value).ok_or_else(|| E::invalid_value(de::Unexpected::Str(value), &self))
+    }
+
+    fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<Self::Value, E> {
+        Ok(value.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, value: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(value)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        Ok(bytes)
+    }
+}
+
+/// `serde` helpers for pairs of byte fields.
+pub mod pair {
+    use alloc::vec::Vec;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    /// A byte field wrapper.
+    #[derive(Serialize, Deserialize)]
+    struct Bytes(#[serde(with = "super")] Vec<u8>);
+
+    /// Serializes a pair of byte fields as a tuple of hex strings or raw bytes (see [`super::serialize`]).
+    pub fn serialize<S: Serializer>(
+        value: &(Vec<u8>, Vec<u8>),
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        (Bytes(value.0.clone()), Bytes(value.1.clone())).serialize(serializer)
+    }
+
+    /// Deserializes a pair of byte fields from a tuple of hex strings or raw bytes (see [`super::deserialize`]).
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<(Vec<u8>, Vec<u8>), D::Error> {
+        let (Bytes(first), Bytes(second)) = Deserialize::deserialize(deserializer)?;
+        Ok((first, second))
+    }
+}
+
+/// `serde` helpers for sequences of byte fields.
+pub mod seq {
+    use alloc::vec::Vec;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    /// A byte field wrapper.
+    #[derive(Serialize, Deserialize)]
+    struct Bytes(#[serde(with = "super")] Vec<u8>);
+
+    /// Serializes a sequence of byte fields as a sequence of hex strings or raw bytes (see [`super::serialize`]).
+    pub fn serialize<S: Serializer>(value: &[Vec<u8>], serializer: S) -> Result<S::Ok, S::Error> {
+        value
+            .iter()
+            .map(|bytes| Bytes(bytes.clone()))
+            .collect::<Vec<Bytes>>()
+            .serialize(serializer)
+    }
+
+    /// Deserializes a sequence of byte fields from a sequence of hex strings or raw bytes (see [`super::deserialize`]).
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Vec<u8>>, D::Error> {
+        let values: Vec<Bytes> = Deserialize::deserialize(deserializer)?;
+        Ok(values.into_iter().map(|Bytes(bytes)| bytes).collect())
+    }
+}
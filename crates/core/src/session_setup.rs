@@ -0,0 +1,379 @@
+//! Session setup handshake (i.e agreement on the terms of a protocol session before round 1).
+//!
+//! The initiating party proposes the protocol, the participating parties (i.e a subset of the roster), the protocol specific message
+//! (e.g the message digest for signing) and deadlines, and each other participant accepts or rejects the proposal with a signed response.
+//! A session can only start once all participants have accepted the same signed terms before the response deadline.
+
+use crate::canonical::{CanonicalEncode, SessionProposalMessage, SessionResponseMessage};
+use crate::crypto::VerifyingKey;
+use crate::errors::{Error, SessionSetupError};
+use crate::payloads::{SessionProposalPayload, SessionResponsePayload, SessionTerms};
+use crate::traits::IdentityProvider;
+use crate::{crypto, utils};
+use alloc::vec::Vec;
+
+/// The status of a session setup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionSetupStatus {
+    /// Waiting for responses from some participants.
+    Pending,
+    /// All participants accepted the proposal (i.e the session can start).
+    Accepted,
+    /// A participant rejected the proposal.
+    Rejected(VerifyingKey),
+    /// The response deadline passed before all participants accepted the proposal.
+    Expired,
+}
+
+/// A session setup (i.e a verified session proposal and the verified responses of participants).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SessionSetup {
+    /// The verified session proposal.
+    proposal: SessionProposalPayload,
+    /// Verified responses of participants.
+    responses: Vec<SessionResponsePayload>,
+}
+
+impl SessionSetup {
+    /// Given a session proposal payload, the identifier of the wallet and a list of verifying keys for all parties,
+    /// returns a session setup for a valid proposal, or an appropriate `Err` result otherwise.
+    pub fn new(
+        proposal: SessionProposalPayload,
+        wallet_id: &[u8; 32],
+        verified_parties: &[VerifyingKey],
+    ) -> Result<Self, SessionSetupError> {
+        verify_proposal(&proposal, wallet_id, verified_parties)?;
+        Ok(Self {
+            proposal,
+            responses: Vec::new(),
+        })
+    }
+
+    /// Returns the session proposal payload.
+    pub fn proposal(&self) -> &SessionProposalPayload {
+        &self.proposal
+    }
+
+    /// Returns the proposed session terms.
+    pub fn terms(&self) -> &SessionTerms {
+        &self.proposal.terms
+    }
+
+    /// Given a session response payload, records the response if it's valid and was received before the response deadline
+    /// (exact duplicates are ignored), or returns an appropriate `Err` result otherwise.
+    pub fn add_response(
+        &mut self,
+        response: SessionResponsePayload,
+    ) -> Result<(), SessionSetupError> {
+        if utils::unix_timestamp() > self.proposal.terms.respond_by {
+            // Responses are only accepted before the response deadline.
+            return Err(SessionSetupError::Expired);
+        }
+        verify_response(&response, &self.proposal)?;
+        match self
+            .responses
+            .iter()
+            .find(|prev| prev.verifying_key == response.verifying_key)
+        {
+            // Ignores exact duplicates.
+            Some(prev) if prev.accepted == response.accepted => Ok(()),
+            // Rejects conflicting responses.
+            Some(_) => Err(SessionSetupError::ConflictingResponses),
+            None => {
+                self.responses.push(response);
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns the status of the session setup.
+    pub fn status(&self) -> SessionSetupStatus {
+        if let Some(rejection) = self.responses.iter().find(|response| !response.accepted) {
+            SessionSetupStatus::Rejected(rejection.verifying_key.clone())
+        } else if self.responses.len() + 1 == self.proposal.terms.participants.len() {
+            SessionSetupStatus::Accepted
+        } else if utils::unix_timestamp() > self.proposal.terms.respond_by {
+            SessionSetupStatus::Expired
+        } else {
+            SessionSetupStatus::Pending
+        }
+    }
+
+    /// Given a list of verifying keys for all parties, returns the (1-based) indices of the participants in the list in protocol order
+    /// (e.g for selecting the signers of a threshold signing session).
+    pub fn participant_indices(&self, verified_parties: &[VerifyingKey]) -> Vec<u16> {
+        self.proposal
+            .terms
+            .participants
+            .iter()
+            .filter_map(|participant| {
+                verified_parties
+                    .iter()
+                    .position(|party| party == participant)
+                    .map(|idx| idx as u16 + 1)
+            })
+            .collect()
+    }
+}
+
+/// Given session terms and the identity provider of the initiating party, returns a session proposal payload.
+pub fn propose(
+    terms: SessionTerms,
+    identity_provider: &impl IdentityProvider,
+) -> SessionProposalPayload {
+    let signature =
+        identity_provider.sign(&SessionProposalMessage { terms: &terms }.message_bytes());
+    SessionProposalPayload {
+        terms,
+        verifying_key: identity_provider.verifying_key(),
+        signature,
+    }
+}
+
+/// Given a session proposal payload, the identifier of the wallet and a list of verifying keys for all parties,
+/// returns an `Ok` result for a valid proposal from a verified party, or an appropriate `Err` result otherwise.
+pub fn verify_proposal(
+    proposal: &SessionProposalPayload,
+    wallet_id: &[u8; 32],
+    verified_parties: &[VerifyingKey],
+) -> Result<(), SessionSetupError> {
+    let terms = &proposal.terms;
+    if !verified_parties.contains(&proposal.verifying_key) {
+        // Initiating party must be a verified party.
+        Err(SessionSetupError::Unauthorized(Error::UnauthorizedParty))
+    } else if &terms.wallet_id != wallet_id {
+        // Proposal must be for the expected wallet.
+        Err(SessionSetupError::WalletMismatch)
+    } else if !terms.participants.contains(&proposal.verifying_key)
+        || terms
+            .participants
+            .iter()
+            .enumerate()
+            .any(|(idx, participant)| {
+                !verified_parties.contains(participant)
+                    || terms.participants[..idx].contains(participant)
+            })
+    {
+        // Participants must be distinct verified parties that include the initiating party.
+        Err(SessionSetupError::InvalidParticipants)
+    } else if terms.deadline < terms.respond_by {
+        // Session deadline can't be before the response deadline.
+        Err(SessionSetupError::InvalidDeadline)
+    } else if utils::unix_timestamp() > terms.respond_by {
+        // Proposal must not be expired.
+        Err(SessionSetupError::Expired)
+    } else {
+        // Proposal signature must be valid.
+        Ok(crypto::verify_signature(
+            &proposal.verifying_key,
+            &SessionProposalMessage { terms }.message_bytes(),
+            &proposal.signature,
+        )?)
+    }
+}
+
+/// Given a session proposal payload, a decision and an identity provider, returns a session response payload.
+///
+/// **NOTE:** The proposal should be verified (e.g with [`verify_proposal`]) and its terms checked against
+/// application policy (e.g the message to sign) before it's accepted.
+pub fn respond(
+    proposal: &SessionProposalPayload,
+    accepted: bool,
+    identity_provider: &impl IdentityProvider,
+) -> SessionResponsePayload {
+    SessionResponsePayload {
+        accepted,
+        verifying_key: identity_provider.verifying_key(),
+        signature: identity_provider.sign(
+            &SessionResponseMessage {
+                terms: &proposal.terms,
+                accepted,
+            }
+            .message_bytes(),
+        ),
+    }
+}
+
+/// Given a session response payload and the session proposal payload,
+/// returns an `Ok` result for a valid response from a participant (other than the initiating party), or an appropriate `Err` result otherwise.
+pub fn verify_response(
+    response: &SessionResponsePayload,
+    proposal: &SessionProposalPayload,
+) -> Result<(), SessionSetupError> {
+    if response.verifying_key == proposal.verifying_key
+        || !proposal
+            .terms
+            .participants
+            .contains(&response.verifying_key)
+    {
+        // Responding party must be a participant other than the initiating party.
+        Err(SessionSetupError::NotParticipant)
+    } else {
+        // Response signature must be valid.
+        Ok(crypto::verify_signature(
+            &response.verifying_key,
+            &SessionResponseMessage {
+                terms: &proposal.terms,
+                accepted: response.accepted,
+            }
+            .message_bytes(),
+            &response.signature,
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cbor::CanonicalCbor;
+    use crate::errors::CryptoError;
+    use crate::test_utils::MockECDSAIdentityProvider;
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    #[test]
+    fn session_setup_works() {
+        // Generates identity providers and a list of verifying keys for all parties.
+        let identity_providers: Vec<_> = (0..4)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let verified_parties: Vec<_> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let wallet_id = [1; 32];
+        let now = utils::unix_timestamp();
+
+        // Party 2 proposes a signing session with parties 4 and 1.
+        let terms = SessionTerms {
+            wallet_id,
+            session_id: [2; 32],
+            protocol: "sign".to_string(),
+            participants: vec![
+                verified_parties[1].clone(),
+                verified_parties[3].clone(),
+                verified_parties[0].clone(),
+            ],
+            message: vec![3; 32],
+            respond_by: now + 60,
+            deadline: now + 300,
+        };
+        let proposal = propose(terms.clone(), &identity_providers[1]);
+        let decoded = SessionProposalPayload::from_cbor(&proposal.to_cbor()).unwrap();
+        assert_eq!(decoded.terms, terms);
+        assert_eq!(decoded.to_cbor(), proposal.to_cbor());
+        let mut setup = SessionSetup::new(proposal.clone(), &wallet_id, &verified_parties).unwrap();
+        assert_eq!(setup.status(), SessionSetupStatus::Pending);
+        assert_eq!(setup.participant_indices(&verified_parties), vec![2, 4, 1]);
+
+        // Invalid proposals are rejected.
+        let mut tampered_proposal = proposal.clone();
+        tampered_proposal.terms.message = vec![4; 32];
+        let with_terms = |update: fn(&mut SessionTerms)| {
+            let mut terms = terms.clone();
+            update(&mut terms);
+            propose(terms, &identity_providers[1])
+        };
+        for (proposal, error) in [
+            (
+                tampered_proposal,
+                SessionSetupError::Unauthorized(Error::Crypto(CryptoError::InvalidSignature)),
+            ),
+            (
+                propose(terms.clone(), &MockECDSAIdentityProvider::generate()),
+                SessionSetupError::Unauthorized(Error::UnauthorizedParty),
+            ),
+            (
+                with_terms(|terms| terms.wallet_id = [2; 32]),
+                SessionSetupError::WalletMismatch,
+            ),
+            (
+                with_terms(|terms| {
+                    terms.participants.remove(0);
+                }),
+                SessionSetupError::InvalidParticipants,
+            ),
+            (
+                with_terms(|terms| terms.participants.push(terms.participants[1].clone())),
+                SessionSetupError::InvalidParticipants,
+            ),
+            (
+                with_terms(|terms| terms.deadline = terms.respond_by - 1),
+                SessionSetupError::InvalidDeadline,
+            ),
+            (
+                with_terms(|terms| {
+                    terms.respond_by = 1;
+                    terms.deadline = 1;
+                }),
+                SessionSetupError::Expired,
+            ),
+        ] {
+            assert_eq!(
+                verify_proposal(&proposal, &wallet_id, &verified_parties),
+                Err(error)
+            );
+        }
+
+        // Responses from non-participants (including the initiating party) and for other terms are rejected.
+        for (response, error) in [
+            (
+                respond(&proposal, true, &identity_providers[2]),
+                SessionSetupError::NotParticipant,
+            ),
+            (
+                respond(&proposal, true, &identity_providers[1]),
+                SessionSetupError::NotParticipant,
+            ),
+            (
+                respond(
+                    &with_terms(|terms| terms.session_id = [3; 32]),
+                    true,
+                    &identity_providers[3],
+                ),
+                SessionSetupError::Unauthorized(Error::Crypto(CryptoError::InvalidSignature)),
+            ),
+        ] {
+            assert_eq!(setup.add_response(response), Err(error));
+        }
+
+        // Responses round trip.
+        let response = respond(&proposal, true, &identity_providers[3]);
+        let decoded = SessionResponsePayload::from_cbor(&response.to_cbor()).unwrap();
+        assert!(decoded.accepted);
+        assert_eq!(decoded.verifying_key, response.verifying_key);
+        assert_eq!(decoded.signature, response.signature);
+
+        // Session can start once all participants accept, while exact duplicates are ignored and conflicting responses are rejected.
+        assert_eq!(
+            setup.add_response(respond(&proposal, true, &identity_providers[3])),
+            Ok(())
+        );
+        assert_eq!(
+            setup.add_response(respond(&proposal, true, &identity_providers[3])),
+            Ok(())
+        );
+        assert_eq!(setup.status(), SessionSetupStatus::Pending);
+        assert_eq!(
+            setup.add_response(respond(&proposal, false, &identity_providers[3])),
+            Err(SessionSetupError::ConflictingResponses)
+        );
+        assert_eq!(
+            setup.add_response(respond(&proposal, true, &identity_providers[0])),
+            Ok(())
+        );
+        assert_eq!(setup.status(), SessionSetupStatus::Accepted);
+
+        // A single rejection rejects the proposal.
+        let mut setup = SessionSetup::new(proposal.clone(), &wallet_id, &verified_parties).unwrap();
+        assert_eq!(
+            setup.add_response(respond(&proposal, false, &identity_providers[0])),
+            Ok(())
+        );
+        assert_eq!(
+            setup.status(),
+            SessionSetupStatus::Rejected(verified_parties[0].clone())
+        );
+    }
+}
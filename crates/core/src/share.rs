@@ -1,10 +1,11 @@
 //! Secret share and "sub-share" types, abstractions and utilities.
 
-use crypto_bigint::modular::constant_mod::ResidueParams;
-use crypto_bigint::{const_residue, U256};
+use core::marker::PhantomData;
+use crypto_bigint::modular::constant_mod::Residue;
+use crypto_bigint::U256;
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
-use crate::crypto::{Random32Bytes, Secp256k1Order};
+use crate::crypto::{CurveOrder, Random32Bytes, Secp256k1Order};
 use crate::errors::{ArithmeticError, Error};
 
 /// A "secret share" as defined by the Wamu protocol.
@@ -85,19 +86,28 @@ impl TryFrom<&[u8]> for SigningShare {
 
 /// A "sub-share" as defined by the Wamu protocol.
 ///
+/// "Sub-share" coordinates are elements of the scalar field of the curve whose order is `Q`
+/// (i.e `Secp256k1` by default, see [`CurveOrder`] for other curves).
+///
 /// Ref: <https://wamu.tech/specification#share-splitting-and-reconstruction>.
 #[derive(Clone, PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
-pub struct SubShare {
+pub struct SubShare<Q: CurveOrder = Secp256k1Order> {
     x: U256,
     y: U256,
+    #[zeroize(skip)]
+    order: PhantomData<Q>,
 }
 
-impl SubShare {
+impl<Q: CurveOrder> SubShare<Q> {
     /// Initializes a new "sub-share".
     pub fn new(x: U256, y: U256) -> Result<Self, ArithmeticError> {
-        // `x` or `y` coordinates must be less than the order of the `Secp256k1` curve.
-        if x < Secp256k1Order::MODULUS && y < Secp256k1Order::MODULUS {
-            Ok(Self { x, y })
+        // `x` or `y` coordinates must be less than the curve order.
+        if x < Q::MODULUS && y < Q::MODULUS {
+            Ok(Self {
+                x,
+                y,
+                order: PhantomData,
+            })
         } else {
             Err(ArithmeticError::ModulusOverflow)
         }
@@ -120,16 +130,18 @@ impl SubShare {
 }
 
 #[derive(Zeroize, ZeroizeOnDrop)]
-pub struct SubShareInterpolator {
+pub struct SubShareInterpolator<Q: CurveOrder = Secp256k1Order> {
     gradient: U256,
     intercept: U256,
+    #[zeroize(skip)]
+    order: PhantomData<Q>,
 }
 
-impl SubShareInterpolator {
+impl<Q: CurveOrder> SubShareInterpolator<Q> {
     /// Given 2 "sub-shares" A and B, returns a "sub-share" interpolator.
     ///
     /// i.e a line (a polynomial of degree 1) such that A and B are both points on the line.
-    pub fn new(point_a: &SubShare, point_b: &SubShare) -> Self {
+    pub fn new(point_a: &SubShare<Q>, point_b: &SubShare<Q>) -> Self {
         // dy/dx (mod q) is equivalent to dy * i where i is the modular multiplicative inverse of dx such that dx * i  ≡ 1 (mod q).
         // Ref: <http://en.wikipedia.org/wiki/Modular_multiplicative_inverse#Computation>.
         // NOTE: Since q is prime, gcd(dx, q) = 1, so a modular multiplicative inverse always exists and
@@ -139,17 +151,17 @@ impl SubShareInterpolator {
         let y_1 = point_a.y;
         let x_2 = point_b.x;
         let y_2 = point_b.y;
-        let dy = const_residue!(y_1, Secp256k1Order) - const_residue!(y_2, Secp256k1Order);
-        let dx = const_residue!(x_1, Secp256k1Order) - const_residue!(x_2, Secp256k1Order);
+        let dy = residue::<Q>(&y_1) - residue::<Q>(&y_2);
+        let dx = residue::<Q>(&x_1) - residue::<Q>(&x_2);
         let gradient = dy * dx.invert().0;
 
         // From y = mx + c (mod q), we compute the intercept c = y - mx (mod q).
-        let intercept_mod =
-            const_residue!(y_1, Secp256k1Order) - (gradient * const_residue!(x_1, Secp256k1Order));
+        let intercept_mod = residue::<Q>(&y_1) - (gradient * residue::<Q>(&x_1));
 
         Self {
             gradient: gradient.retrieve(),
             intercept: intercept_mod.retrieve(),
+            order: PhantomData,
         }
     }
 
@@ -159,21 +171,20 @@ impl SubShareInterpolator {
     }
 
     /// Returns a unique "sub-share" for the index.
-    pub fn sub_share(&self, idx: U256) -> Result<SubShare, ArithmeticError> {
+    pub fn sub_share(&self, idx: U256) -> Result<SubShare<Q>, ArithmeticError> {
         // The "index" should be:
-        // - less than the order of the `Secp256k1` curve.
+        // - less than the curve order.
         // - greater than zero (because the "sub-share" associated with the zero "index" is the "secret share").
-        if idx < Secp256k1Order::MODULUS && U256::ZERO < idx {
+        if idx < Q::MODULUS && U256::ZERO < idx {
             // Calculates the y-coordinate of the "sub-share".
             let gradient = self.gradient;
             let intercept = self.intercept;
-            let y_coord = (const_residue!(gradient, Secp256k1Order)
-                * const_residue!(idx, Secp256k1Order))
-                + const_residue!(intercept, Secp256k1Order);
+            let y_coord = (residue::<Q>(&gradient) * residue::<Q>(&idx)) + residue::<Q>(&intercept);
 
             Ok(SubShare {
                 x: idx,
                 y: y_coord.retrieve(),
+                order: PhantomData,
             })
         } else {
             Err(ArithmeticError::ModulusOverflow)
@@ -181,6 +192,11 @@ impl SubShareInterpolator {
     }
 }
 
+/// Returns the residue of the value modulo the curve order.
+fn residue<Q: CurveOrder>(value: &U256) -> Residue<Q, { U256::LIMBS }> {
+    Residue::new(value)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -190,7 +206,7 @@ mod tests {
         // Take line, y = x + 1 (mod q).
         // The "secret share" is 1 i.e at index 0, x = 0 and y = 1.
         let secret_share = U256::ONE;
-        let sub_share_0 = SubShare::new(U256::ZERO, secret_share).unwrap();
+        let sub_share_0: SubShare = SubShare::new(U256::ZERO, secret_share).unwrap();
 
         // The "sub-share" at index 1 is (1, 2) i.e when x = 1 and y = 2.
         let sub_share_1 = SubShare::new(U256::ONE, U256::from(2u8)).unwrap();
@@ -199,7 +215,7 @@ mod tests {
         let split_sub_share_interpolator = SubShareInterpolator::new(&sub_share_0, &sub_share_1);
 
         // The "sub-share" at index 2 is (2, 3),i.e when x = 2, y = 3.
-        let sub_share_2 = SubShare::new(U256::from(2u8), U256::from(3u8)).unwrap();
+        let sub_share_2: SubShare = SubShare::new(U256::from(2u8), U256::from(3u8)).unwrap();
 
         // Verify that the "sub-share" interpolator returns the right "sub-share" at index 2.
         assert_eq!(
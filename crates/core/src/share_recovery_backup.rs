@@ -14,33 +14,46 @@ use aes_gcm::{
 };
 use crypto_bigint::{Encoding, U256};
 use hkdf::Hkdf;
+use rand::rngs::OsRng;
 use sha2::Sha256;
 
-use crate::errors::ShareBackupRecoveryError;
+use crate::crypto::{CurveOrder, Secp256k1Order};
+use crate::errors::{BackupField, ShareBackupRecoveryError};
 use crate::payloads::EncryptedShareBackup;
 use crate::share::{SigningShare, SubShare};
 use crate::traits::IdentityProvider;
 
 /// Given an entropy seed (i.e typically a standardized phrase), "signing share", "sub-share" and identity provider,
 /// returns an ok result including the encrypted share backup (i.e an encrypted "signing share" and "sub-share", and a random nonce)
-/// or an encryption error result.
+/// or an encryption error result (including the field that failed to encrypt).
 ///
 /// Ref: <https://wamu.tech/specification#share-recovery-backup-encrypt>.
-pub fn backup(
+pub fn backup<Q: CurveOrder>(
     entropy_seed: &[u8],
     signing_share: &SigningShare,
-    sub_share: &SubShare,
+    sub_share: &SubShare<Q>,
     identity_provider: &impl IdentityProvider,
 ) -> Result<EncryptedShareBackup, ShareBackupRecoveryError> {
     // Generates nonce.
-    let nonce = Aes256Gcm::generate_nonce(&mut rand::thread_rng());
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
 
     // Encrypts the "signing share" and "sub-share".
     let cipher = generate_encryption_cipher(entropy_seed, identity_provider);
-    let encrypted_signing_share = cipher.encrypt(&nonce, signing_share.to_be_bytes().as_ref())?;
+    let encrypt = |field: BackupField, plaintext: &[u8]| {
+        cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| ShareBackupRecoveryError::EncryptionError {
+                field,
+                code: ShareBackupRecoveryError::ENCRYPTION_FAILED,
+            })
+    };
+    let encrypted_signing_share = encrypt(
+        BackupField::SigningShare,
+        signing_share.to_be_bytes().as_ref(),
+    )?;
     let encrypted_sub_share = (
-        cipher.encrypt(&nonce, sub_share.x().to_be_bytes().as_ref())?,
-        cipher.encrypt(&nonce, sub_share.y().to_be_bytes().as_ref())?,
+        encrypt(BackupField::SubShareX, sub_share.x().to_be_bytes().as_ref())?,
+        encrypt(BackupField::SubShareY, sub_share.y().to_be_bytes().as_ref())?,
     );
 
     // Returns the encrypted share backup.
@@ -53,7 +66,7 @@ pub fn backup(
 
 /// Given an entropy seed (i.e typically a standardized phrase), encrypted share backup
 /// (i.e an encrypted "signing share" and "sub-share", and a random nonce) and an identity provider,
-/// returns the decrypted "signing share" and "sub-share".
+/// returns the decrypted "signing share" and "sub-share" or an appropriate error (including the field that failed to decrypt or decode).
 ///
 /// Ref: <https://wamu.tech/specification#share-recovery-backup-decrypt>.
 pub fn recover(
@@ -61,30 +74,60 @@ pub fn recover(
     encrypted_share_backup: &EncryptedShareBackup,
     identity_provider: &impl IdentityProvider,
 ) -> Result<(SigningShare, SubShare), ShareBackupRecoveryError> {
+    recover_with_order::<Secp256k1Order>(entropy_seed, encrypted_share_backup, identity_provider)
+}
+
+/// Same as [`recover`] but for a "sub-share" in the scalar field of the curve whose order is `Q`
+/// (e.g [`Ed25519Order`](crate::crypto::Ed25519Order)).
+///
+/// Ref: <https://wamu.tech/specification#share-recovery-backup-decrypt>.
+pub fn recover_with_order<Q: CurveOrder>(
+    entropy_seed: &[u8],
+    encrypted_share_backup: &EncryptedShareBackup,
+    identity_provider: &impl IdentityProvider,
+) -> Result<(SigningShare, SubShare<Q>), ShareBackupRecoveryError> {
     // Generates nonce.
+    if encrypted_share_backup.nonce.len() != 12 {
+        return Err(ShareBackupRecoveryError::InvalidNonce);
+    }
     let nonce = aes_gcm::Nonce::from_slice(&encrypted_share_backup.nonce);
 
     // Decrypts the "signing share" and "sub-share".
     let cipher = generate_encryption_cipher(entropy_seed, identity_provider);
-    let signing_share_bytes =
-        cipher.decrypt(nonce, encrypted_share_backup.signing_share.as_ref())?;
+    let decrypt = |field: BackupField, ciphertext: &[u8]| {
+        cipher
+            .decrypt(nonce, ciphertext)
+            .map_err(|_| ShareBackupRecoveryError::EncryptionError {
+                field,
+                code: ShareBackupRecoveryError::DECRYPTION_FAILED,
+            })
+    };
+    let signing_share_bytes = decrypt(
+        BackupField::SigningShare,
+        encrypted_share_backup.signing_share.as_ref(),
+    )?;
     let signing_share = SigningShare::try_from(signing_share_bytes.as_ref())
         .map_err(|_| ShareBackupRecoveryError::InvalidSigningShare)?;
+    let decrypt_coordinate = |field: BackupField, ciphertext: &[u8]| {
+        decrypt(field, ciphertext)?
+            .try_into()
+            .ok()
+            .map(|bytes: [u8; 32]| U256::from_be_bytes(bytes))
+            // Coordinates must be less than the curve order.
+            .filter(|coordinate| coordinate < &Q::MODULUS)
+            .ok_or(ShareBackupRecoveryError::InvalidSubShare(field))
+    };
     let sub_share = SubShare::new(
-        U256::from_be_bytes(
-            cipher
-                .decrypt(nonce, encrypted_share_backup.sub_share.0.as_ref())?
-                .try_into()
-                .map_err(|_| ShareBackupRecoveryError::InvalidSubShare)?,
-        ),
-        U256::from_be_bytes(
-            cipher
-                .decrypt(nonce, encrypted_share_backup.sub_share.1.as_ref())?
-                .try_into()
-                .map_err(|_| ShareBackupRecoveryError::InvalidSubShare)?,
-        ),
+        decrypt_coordinate(
+            BackupField::SubShareX,
+            encrypted_share_backup.sub_share.0.as_ref(),
+        )?,
+        decrypt_coordinate(
+            BackupField::SubShareY,
+            encrypted_share_backup.sub_share.1.as_ref(),
+        )?,
     )
-    .map_err(|_| ShareBackupRecoveryError::InvalidSubShare)?;
+    .expect("Coordinates are less than the curve order");
 
     Ok((signing_share, sub_share))
 }
@@ -167,6 +210,38 @@ mod tests {
             &signing_share.to_be_bytes()
         );
         assert_eq!(recovered_sub_share.as_tuple(), sub_share.as_tuple());
+
+        // Verifies that recovery errors include the field that failed.
+        assert_eq!(
+            recover(
+                b"Another phrase.",
+                &encrypted_share_backup,
+                &identity_provider
+            )
+            .err(),
+            Some(ShareBackupRecoveryError::EncryptionError {
+                field: BackupField::SigningShare,
+                code: ShareBackupRecoveryError::DECRYPTION_FAILED,
+            })
+        );
+        let mut tampered_backup = EncryptedShareBackup {
+            signing_share: encrypted_share_backup.signing_share.clone(),
+            sub_share: encrypted_share_backup.sub_share.clone(),
+            nonce: encrypted_share_backup.nonce.clone(),
+        };
+        tampered_backup.sub_share.1[0] ^= 1;
+        assert_eq!(
+            recover(entropy_seed, &tampered_backup, &identity_provider).err(),
+            Some(ShareBackupRecoveryError::EncryptionError {
+                field: BackupField::SubShareY,
+                code: ShareBackupRecoveryError::DECRYPTION_FAILED,
+            })
+        );
+        tampered_backup.nonce.pop();
+        assert_eq!(
+            recover(entropy_seed, &tampered_backup, &identity_provider).err(),
+            Some(ShareBackupRecoveryError::InvalidNonce)
+        );
     }
 
     #[test]
@@ -2,8 +2,9 @@
 //!
 //! Ref: <https://wamu.tech/specification#share-splitting-and-reconstruction>.
 
-use crypto_bigint::{Encoding, U256};
+use crypto_bigint::{Encoding, NonZero, U256};
 
+use crate::crypto::{CurveOrder, Secp256k1Order};
 use crate::errors::Error;
 use crate::share::{SecretShare, SigningShare, SubShare, SubShareInterpolator};
 use crate::traits::IdentityProvider;
@@ -16,12 +17,30 @@ pub fn split(
     secret_share: &SecretShare,
     identity_provider: &impl IdentityProvider,
 ) -> Result<(SigningShare, SubShare), Error> {
+    split_with_order::<Secp256k1Order>(secret_share, identity_provider)
+}
+
+/// Same as [`split`] but for a "secret share" in the scalar field of the curve whose order is `Q`
+/// (e.g [`Ed25519Order`](crate::crypto::Ed25519Order)).
+///
+/// Ref: <https://wamu.tech/specification#share-splitting>.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(verifying_key = %crate::utils::to_hex(&identity_provider.verifying_key().key))
+    )
+)]
+pub fn split_with_order<Q: CurveOrder>(
+    secret_share: &SecretShare,
+    identity_provider: &impl IdentityProvider,
+) -> Result<(SigningShare, SubShare<Q>), Error> {
     // Generates "signing share".
     let signing_share = SigningShare::generate();
 
     // Computes "sub-share" a from "signing share".
-    let (r, s) = identity_provider.sign_message_share(&signing_share.to_be_bytes());
-    let sub_share_a = SubShare::new(U256::from_be_bytes(r), U256::from_be_bytes(s))?;
+    let sub_share_a = signing_sub_share(&signing_share, identity_provider);
 
     // Initializes the "sub-share" interpolator.
     let sub_share_interpolator = SubShareInterpolator::new(
@@ -44,10 +63,29 @@ pub fn reconstruct(
     signing_share: &SigningShare,
     sub_share_b: &SubShare,
     identity_provider: &impl IdentityProvider,
+) -> Result<SecretShare, Error> {
+    reconstruct_with_order(signing_share, sub_share_b, identity_provider)
+}
+
+/// Same as [`reconstruct`] but for a "sub-share" in the scalar field of the curve whose order is `Q`
+/// (e.g [`Ed25519Order`](crate::crypto::Ed25519Order)).
+///
+/// Ref: <https://wamu.tech/specification#share-reconstruction>.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(verifying_key = %crate::utils::to_hex(&identity_provider.verifying_key().key))
+    )
+)]
+pub fn reconstruct_with_order<Q: CurveOrder>(
+    signing_share: &SigningShare,
+    sub_share_b: &SubShare<Q>,
+    identity_provider: &impl IdentityProvider,
 ) -> Result<SecretShare, Error> {
     // Computes "sub-share" a from "signing share".
-    let (r, s) = identity_provider.sign_message_share(&signing_share.to_be_bytes());
-    let sub_share_a = SubShare::new(U256::from_be_bytes(r), U256::from_be_bytes(s))?;
+    let sub_share_a = signing_sub_share(signing_share, identity_provider);
 
     // Initializes the "sub-share" interpolator.
     let sub_share_interpolator = SubShareInterpolator::new(&sub_share_a, sub_share_b);
@@ -56,11 +94,29 @@ pub fn reconstruct(
     Ok(sub_share_interpolator.secret().into())
 }
 
+/// Returns "sub-share" a (i.e the identity provider's signature of the "signing share") reduced modulo the curve order.
+///
+/// **NOTE:** The reduction is a no-op for `Secp256k1` because ECDSA signature components are already less than its order.
+fn signing_sub_share<Q: CurveOrder>(
+    signing_share: &SigningShare,
+    identity_provider: &impl IdentityProvider,
+) -> SubShare<Q> {
+    let (r, s) = identity_provider.sign_message_share(&signing_share.to_be_bytes());
+    // Curve orders should be non-zero.
+    let modulus = NonZero::new(Q::MODULUS).unwrap();
+    SubShare::new(
+        U256::from_be_bytes(r).rem(&modulus),
+        U256::from_be_bytes(s).rem(&modulus),
+    )
+    .expect("Coordinates are reduced modulo the curve order")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::crypto::Random32Bytes;
+    use crate::crypto::{Ed25519Order, Random32Bytes};
     use crate::test_utils::MockECDSAIdentityProvider;
+    use crypto_bigint::modular::constant_mod::ResidueParams;
 
     #[test]
     fn share_splitting_and_reconstruction_works() {
@@ -83,4 +139,37 @@ mod tests {
             &secret_share.to_be_bytes()
         );
     }
+
+    #[test]
+    fn share_splitting_and_reconstruction_with_order_works() {
+        // Generates secret share in the `Ed25519` scalar field.
+        let secret_share = SecretShare::from(Random32Bytes::generate_mod_order::<Ed25519Order>());
+
+        // Generates identity provider.
+        let identity_provider = MockECDSAIdentityProvider::generate();
+
+        // Computes "signing share" and "sub-share".
+        let (signing_share, sub_share_b) =
+            split_with_order::<Ed25519Order>(&secret_share, &identity_provider).unwrap();
+
+        // Verifies that "sub-share" coordinates are in the `Ed25519` scalar field.
+        assert!(sub_share_b.x() < Ed25519Order::MODULUS && sub_share_b.y() < Ed25519Order::MODULUS);
+
+        // Reconstructs "secret share" from "signing share" and "sub-share".
+        let reconstructed_secret_share =
+            reconstruct_with_order(&signing_share, &sub_share_b, &identity_provider).unwrap();
+
+        // Verifies reconstructed "secret share".
+        assert_eq!(
+            &reconstructed_secret_share.to_be_bytes(),
+            &secret_share.to_be_bytes()
+        );
+
+        // Verifies that "secret shares" larger than the `Ed25519` order are rejected.
+        assert!(split_with_order::<Ed25519Order>(
+            &SecretShare::from(Ed25519Order::MODULUS),
+            &identity_provider
+        )
+        .is_err());
+    }
 }
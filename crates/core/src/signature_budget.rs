@@ -0,0 +1,140 @@
+//! Per key epoch signature budgets (i.e proactive key refresh after a number of signatures).
+//!
+//! Bounds the exposure of any single key epoch by requiring a proactive key refresh (e.g `AugmentedKeyRefresh`)
+//! after a configurable number of signatures, and refusing further signatures past a hard ceiling until the key is refreshed.
+
+use crate::errors::SignatureBudgetError;
+
+/// The key refresh status of a key epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum KeyRefreshStatus {
+    /// The key epoch is within its signature budget.
+    NotRequired,
+    /// The key epoch reached the key refresh threshold (i.e a key refresh should be triggered),
+    /// signing is still allowed until the hard ceiling.
+    Required,
+}
+
+/// Tracks the number of signatures in the current key epoch against a key refresh threshold and a hard ceiling.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SignatureBudget {
+    /// Number of signatures after which a key refresh is required.
+    refresh_after: u64,
+    /// Maximum number of signatures in a key epoch.
+    max_signatures: u64,
+    /// The current key epoch (i.e the number of key refreshes).
+    epoch: u64,
+    /// Number of signatures in the current key epoch.
+    count: u64,
+}
+
+impl Default for SignatureBudget {
+    /// Returns an unlimited signature budget.
+    fn default() -> Self {
+        Self::new(u64::MAX, u64::MAX)
+    }
+}
+
+impl SignatureBudget {
+    /// Given the number of signatures after which a key refresh is required and the maximum number of signatures in a key epoch,
+    /// returns a signature budget for key epoch 0.
+    ///
+    /// **NOTE:** The key refresh threshold is capped at the maximum number of signatures.
+    pub fn new(refresh_after: u64, max_signatures: u64) -> Self {
+        Self {
+            refresh_after: refresh_after.min(max_signatures),
+            max_signatures,
+            epoch: 0,
+            count: 0,
+        }
+    }
+
+    /// Returns the number of signatures after which a key refresh is required.
+    pub fn refresh_after(&self) -> u64 {
+        self.refresh_after
+    }
+
+    /// Returns the maximum number of signatures in a key epoch.
+    pub fn max_signatures(&self) -> u64 {
+        self.max_signatures
+    }
+
+    /// Returns the current key epoch.
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Returns the number of signatures in the current key epoch.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the number of signatures left in the current key epoch.
+    pub fn remaining(&self) -> u64 {
+        self.max_signatures - self.count
+    }
+
+    /// Returns the key refresh status of the current key epoch.
+    pub fn status(&self) -> KeyRefreshStatus {
+        if self.count >= self.refresh_after {
+            KeyRefreshStatus::Required
+        } else {
+            KeyRefreshStatus::NotRequired
+        }
+    }
+
+    /// Records a signature in the current key epoch and returns the updated key refresh status,
+    /// or an error if the current key epoch reached the maximum number of signatures.
+    ///
+    /// **NOTE:** This is meant to be called before joining a signing session.
+    pub fn record_signature(&mut self) -> Result<KeyRefreshStatus, SignatureBudgetError> {
+        if self.count >= self.max_signatures {
+            return Err(SignatureBudgetError::Exhausted);
+        }
+        self.count += 1;
+        Ok(self.status())
+    }
+
+    /// Starts a new key epoch (i.e after a successful key refresh).
+    pub fn advance_epoch(&mut self) {
+        self.epoch += 1;
+        self.count = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_budget_works() {
+        let mut budget = SignatureBudget::new(2, 3);
+        assert_eq!(budget.status(), KeyRefreshStatus::NotRequired);
+
+        // Signatures are allowed until the hard ceiling, with a key refresh required after the threshold.
+        assert_eq!(budget.record_signature(), Ok(KeyRefreshStatus::NotRequired));
+        assert_eq!(budget.record_signature(), Ok(KeyRefreshStatus::Required));
+        assert_eq!(budget.record_signature(), Ok(KeyRefreshStatus::Required));
+        assert_eq!(budget.remaining(), 0);
+        assert_eq!(
+            budget.record_signature(),
+            Err(SignatureBudgetError::Exhausted)
+        );
+        assert_eq!(budget.count(), 3);
+
+        // Key refresh starts a new key epoch with a fresh budget.
+        budget.advance_epoch();
+        assert_eq!(budget.epoch(), 1);
+        assert_eq!(budget.count(), 0);
+        assert_eq!(budget.record_signature(), Ok(KeyRefreshStatus::NotRequired));
+
+        // Key refresh thresholds are capped at the hard ceiling.
+        assert_eq!(SignatureBudget::new(10, 5).refresh_after(), 5);
+
+        // Default budgets are unlimited.
+        let mut budget = SignatureBudget::default();
+        assert_eq!(budget.record_signature(), Ok(KeyRefreshStatus::NotRequired));
+    }
+}
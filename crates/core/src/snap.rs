@@ -0,0 +1,210 @@
+//! Bridge types for driving Wamu requests from a browser extension context (e.g a [MetaMask Snap](https://docs.metamask.io/snaps/)).
+//!
+//! The extension (e.g the Snap's `onRpcRequest` handler) holds the decentralized identity of the party,
+//! and exchanges JSON messages in the versioned envelope of [`crate::json`] with the dapp or the Wamu node:
+//! - Requests (`{"version": 1, "type": "snap_request", "payload": {"method": "...", "params": {...}}}`) are:
+//!   - `get_verifying_key` (no params).
+//!   - `initiate_request` (`{"command": "..."}`).
+//!   - `approve_request` (`{"command": "...", "request": <identity authed request>, "verified_parties": [<verifying key>, ...]}`).
+//!   - `sign_message` (`{"message": "0x..."}`).
+//! - Responses (`{"version": 1, "type": "snap_response", "payload": {"type": "...", "value": ...}}`) are:
+//!   - `verifying_key` (a verifying key).
+//!   - `request` (an identity authenticated request).
+//!   - `approval` (a command approval).
+//!   - `signature` (a signature).
+//!   - `error` (an [`ErrorReport`]).
+//!
+//! **NOTE:** Extensions must ask the user to confirm requests that use the identity (see [`SnapRequest::requires_confirmation`])
+//! before handling them (e.g with a `snap_dialog` confirmation showing the command or message).
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::{Signature, VerifyingKey};
+use crate::error_report::ErrorReport;
+use crate::json::JsonPayload;
+use crate::payloads::{CommandApprovalPayload, IdentityAuthedRequestPayload};
+use crate::traits::IdentityProvider;
+use crate::{identity_authed_request, quorum_approved_request};
+
+/// A request from a dapp or a Wamu node to a browser extension.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "method", content = "params", rename_all = "snake_case")]
+pub enum SnapRequest {
+    /// Returns the verifying key of the party.
+    GetVerifyingKey,
+    /// Initiates an identity authenticated (or quorum approved) request for a command.
+    InitiateRequest {
+        /// The command.
+        command: String,
+    },
+    /// Verifies a quorum approved request and approves it.
+    ApproveRequest {
+        /// The expected command.
+        command: String,
+        /// The quorum approved request initialization payload.
+        request: IdentityAuthedRequestPayload,
+        /// Verifying keys for the other parties.
+        verified_parties: Vec<VerifyingKey>,
+    },
+    /// Signs a message with the decentralized identity of the party (e.g for identity authentication of augmented protocol messages).
+    SignMessage {
+        /// The message.
+        #[serde(with = "crate::serde_hex")]
+        message: Vec<u8>,
+    },
+}
+
+impl SnapRequest {
+    /// Returns true if the request uses the decentralized identity of the party (i.e the user must confirm it).
+    pub fn requires_confirmation(&self) -> bool {
+        !matches!(self, Self::GetVerifyingKey)
+    }
+}
+
+/// A response from a browser extension to a dapp or a Wamu node.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum SnapResponse {
+    /// The verifying key of the party.
+    VerifyingKey(VerifyingKey),
+    /// An identity authenticated request initialization payload.
+    Request(IdentityAuthedRequestPayload),
+    /// A command approval payload.
+    Approval(CommandApprovalPayload),
+    /// A signature.
+    Signature(Signature),
+    /// An error report.
+    Error(ErrorReport),
+}
+
+/// Given a request and the identity provider of the party, handles the request and returns the response.
+///
+/// **NOTE:** This must only be called after the user confirms the request (see [`SnapRequest::requires_confirmation`]).
+pub fn handle(request: SnapRequest, identity_provider: &impl IdentityProvider) -> SnapResponse {
+    match request {
+        SnapRequest::GetVerifyingKey => {
+            SnapResponse::VerifyingKey(identity_provider.verifying_key())
+        }
+        SnapRequest::InitiateRequest { command } => SnapResponse::Request(
+            identity_authed_request::initiate_with_command(command, identity_provider),
+        ),
+        SnapRequest::ApproveRequest {
+            command,
+            request,
+            verified_parties,
+        } => match quorum_approved_request::verify_request_and_initiate_challenge(
+            &command,
+            &request,
+            identity_provider,
+            &verified_parties,
+        ) {
+            Ok(approval) => SnapResponse::Approval(approval),
+            Err(error) => SnapResponse::Error(ErrorReport::new(&error)),
+        },
+        SnapRequest::SignMessage { message } => {
+            SnapResponse::Signature(identity_provider.sign(&message))
+        }
+    }
+}
+
+/// Given a JSON encoded request (in a versioned envelope) and the identity provider of the party,
+/// handles the request and returns the JSON encoded response (in a versioned envelope).
+///
+/// **NOTE:** Invalid requests are answered with an error response.
+pub fn handle_json(request: &str, identity_provider: &impl IdentityProvider) -> String {
+    match SnapRequest::from_json(request) {
+        Ok(request) => handle(request, identity_provider),
+        Err(error) => SnapResponse::Error(ErrorReport::new(&error)),
+    }
+    .to_json()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockECDSAIdentityProvider;
+    use alloc::string::ToString;
+    use alloc::vec;
+    use serde_json::{json, Value};
+
+    // Sends a request and returns the decoded response.
+    fn send(request: Value, identity_provider: &MockECDSAIdentityProvider) -> SnapResponse {
+        let envelope = json!({ "version": 1, "type": "snap_request", "payload": request });
+        SnapResponse::from_json(&handle_json(&envelope.to_string(), identity_provider)).unwrap()
+    }
+
+    #[test]
+    fn snap_works() {
+        // Generates identity providers for the initiating and extension parties.
+        let initiator = MockECDSAIdentityProvider::generate();
+        let identity_provider = MockECDSAIdentityProvider::generate();
+        let verifying_key = identity_provider.verifying_key();
+
+        // Returns the verifying key without confirmation.
+        let request = json!({ "method": "get_verifying_key" });
+        assert!(!serde_json::from_value::<SnapRequest>(request.clone())
+            .unwrap()
+            .requires_confirmation());
+        assert!(matches!(
+            send(request, &identity_provider),
+            SnapResponse::VerifyingKey(key) if key == verifying_key
+        ));
+
+        // Initiates a valid identity authenticated request.
+        let SnapResponse::Request(initiated_request) = send(
+            json!({ "method": "initiate_request", "params": { "command": "share-removal" } }),
+            &identity_provider,
+        ) else {
+            panic!("expected a request");
+        };
+        assert_eq!(initiated_request.command, "share-removal");
+        assert!(identity_authed_request::verify(
+            &initiated_request,
+            &[identity_provider.verifying_key()]
+        )
+        .is_ok());
+
+        // Approves a valid quorum approved request.
+        let request = quorum_approved_request::initiate("share-removal", &initiator);
+        let approve_request = |verified_parties: Vec<VerifyingKey>| SnapRequest::ApproveRequest {
+            command: "share-removal".to_string(),
+            request: request.clone(),
+            verified_parties,
+        };
+        let SnapResponse::Approval(approval) = SnapResponse::from_json(&handle_json(
+            &approve_request(vec![initiator.verifying_key()]).to_json(),
+            &identity_provider,
+        ))
+        .unwrap() else {
+            panic!("expected an approval");
+        };
+        assert_eq!(approval.verifying_key, verifying_key);
+
+        // Requests from unverified parties are rejected.
+        assert!(matches!(
+            handle(approve_request(Vec::new()), &identity_provider),
+            SnapResponse::Error(_)
+        ));
+
+        // Signs messages.
+        let SnapResponse::Signature(signature) = send(
+            json!({ "method": "sign_message", "params": { "message": "0x0102" } }),
+            &identity_provider,
+        ) else {
+            panic!("expected a signature");
+        };
+        assert!(crate::crypto::verify_signature(&verifying_key, &[1, 2], &signature).is_ok());
+
+        // Invalid requests are answered with an error response.
+        assert!(matches!(
+            SnapResponse::from_json(&handle_json("{}", &identity_provider)).unwrap(),
+            SnapResponse::Error(_)
+        ));
+        assert!(matches!(
+            send(json!({ "method": "unknown" }), &identity_provider),
+            SnapResponse::Error(_)
+        ));
+    }
+}
@@ -0,0 +1,518 @@
+//! A SQLite storage backend (i.e key store, inbox, command log and pre-signature pool),
+//! so a single embedded database can back a production co-signer.
+//!
+//! The database schema is versioned (with the SQLite `user_version` pragma) and migrated when the store is opened.
+//!
+//! Key material and pre-signatures are encrypted at rest (see [`FileKeyStore`](crate::key_store::FileKeyStore) for details),
+//! while inbox and command log entries (which are already signed and contain no secrets) are stored as JSON.
+//!
+//! **NOTE:** Only enabled with the `sqlite` feature.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+use zeroize::Zeroizing;
+
+use crate::command_log::{CommandLog, CommandLogEntry};
+use crate::errors::{CommandLogError, KeyStoreError};
+use crate::inbox::{Inbox, InboxEntry};
+use crate::key_store::{KeyCipher, KeyStore, StoredKey};
+use crate::traits::IdentityProvider;
+use crate::versioned::Versioned;
+
+/// Schema migrations (i.e the migration at index `i` upgrades the schema from version `i` to version `i + 1`).
+const MIGRATIONS: &[&str] = &["
+    CREATE TABLE keys (
+        wallet_id BLOB PRIMARY KEY NOT NULL,
+        sealed BLOB NOT NULL
+    );
+    CREATE TABLE inbox (
+        id INTEGER PRIMARY KEY NOT NULL,
+        wallet_id BLOB NOT NULL,
+        expires_at INTEGER NOT NULL,
+        entry TEXT NOT NULL
+    );
+    CREATE INDEX inbox_expires_at ON inbox (expires_at);
+    CREATE TABLE inbox_state (
+        id INTEGER PRIMARY KEY NOT NULL CHECK (id = 0),
+        next_id INTEGER NOT NULL
+    );
+    CREATE TABLE command_log (
+        wallet_id BLOB NOT NULL,
+        sequence INTEGER NOT NULL,
+        entry TEXT NOT NULL,
+        PRIMARY KEY (wallet_id, sequence)
+    );
+    CREATE TABLE presignatures (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        wallet_id BLOB NOT NULL,
+        sealed BLOB NOT NULL
+    );
+    CREATE INDEX presignatures_wallet_id ON presignatures (wallet_id, id);
+"];
+
+/// The context (appended to the wallet identifier) for sealing pre-signatures.
+const PRESIGNATURE_CONTEXT: &[u8] = b"presignature";
+
+/// A SQLite storage backend error.
+#[derive(Debug)]
+pub enum SqliteStoreError {
+    /// A database error.
+    Database(rusqlite::Error),
+    /// A key material (or pre-signature) encryption or decoding error.
+    KeyStore(KeyStoreError),
+    /// A stored entry that couldn't be encoded or decoded.
+    Encoding,
+    /// A command log entry that doesn't extend the stored command log.
+    CommandLog(CommandLogError),
+    /// A database schema that's newer than the latest supported schema version.
+    UnsupportedSchemaVersion,
+}
+
+impl From<rusqlite::Error> for SqliteStoreError {
+    fn from(error: rusqlite::Error) -> Self {
+        Self::Database(error)
+    }
+}
+
+impl From<KeyStoreError> for SqliteStoreError {
+    fn from(error: KeyStoreError) -> Self {
+        Self::KeyStore(error)
+    }
+}
+
+impl From<serde_json::Error> for SqliteStoreError {
+    fn from(_: serde_json::Error) -> Self {
+        Self::Encoding
+    }
+}
+
+impl From<CommandLogError> for SqliteStoreError {
+    fn from(error: CommandLogError) -> Self {
+        Self::CommandLog(error)
+    }
+}
+
+/// A SQLite storage backend (see module documentation for details).
+///
+/// **NOTE:** Key derivation requires a deterministic signature scheme (e.g RFC 6979 ECDSA or EdDSA).
+pub struct SqliteStore {
+    /// The database connection.
+    conn: Connection,
+    /// The cipher for key material and pre-signatures.
+    cipher: KeyCipher,
+}
+
+impl SqliteStore {
+    /// Given a database path (which is created if it doesn't exist) and an identity provider,
+    /// returns a SQLite store with a migrated schema.
+    pub fn open(
+        path: impl AsRef<Path>,
+        identity_provider: &impl IdentityProvider,
+    ) -> Result<Self, SqliteStoreError> {
+        Self::with_connection(Connection::open(path)?, identity_provider)
+    }
+
+    /// Given an identity provider, returns a SQLite store backed by an in-memory database (e.g for tests).
+    pub fn open_in_memory(
+        identity_provider: &impl IdentityProvider,
+    ) -> Result<Self, SqliteStoreError> {
+        Self::with_connection(Connection::open_in_memory()?, identity_provider)
+    }
+
+    /// Given a database connection and an identity provider, returns a SQLite store with a migrated schema.
+    fn with_connection(
+        mut conn: Connection,
+        identity_provider: &impl IdentityProvider,
+    ) -> Result<Self, SqliteStoreError> {
+        migrate(&mut conn)?;
+        Ok(Self {
+            conn,
+            cipher: KeyCipher::new(identity_provider),
+        })
+    }
+
+    /// Returns the schema version of the database.
+    pub fn schema_version(&self) -> Result<usize, SqliteStoreError> {
+        schema_version(&self.conn)
+    }
+
+    /// Saves an inbox entry (replacing any existing entry with the same identifier).
+    pub fn save_inbox_entry(&mut self, entry: &InboxEntry) -> Result<(), SqliteStoreError> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT OR REPLACE INTO inbox (id, wallet_id, expires_at, entry) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                entry.id,
+                entry.wallet_id,
+                to_sql_timestamp(entry.expires_at),
+                serde_json::to_string(entry)?
+            ],
+        )?;
+        // Identifiers are never reused (even after entries are deleted).
+        tx.execute(
+            "INSERT INTO inbox_state (id, next_id) VALUES (0, ?1)
+             ON CONFLICT (id) DO UPDATE SET next_id = MAX(next_id, excluded.next_id)",
+            params![entry.id + 1],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Deletes an inbox entry and returns true if it existed.
+    pub fn delete_inbox_entry(&mut self, id: u64) -> Result<bool, SqliteStoreError> {
+        Ok(self
+            .conn
+            .execute("DELETE FROM inbox WHERE id = ?1", params![id])?
+            > 0)
+    }
+
+    /// Given a UTC timestamp, deletes all inbox entries that expired before it and returns the number of deleted entries.
+    pub fn delete_expired_inbox_entries(&mut self, now: u64) -> Result<usize, SqliteStoreError> {
+        Ok(self.conn.execute(
+            "DELETE FROM inbox WHERE expires_at < ?1",
+            params![to_sql_timestamp(now)],
+        )?)
+    }
+
+    /// Returns an inbox with all stored entries.
+    pub fn load_inbox(&self) -> Result<Inbox, SqliteStoreError> {
+        let mut stmt = self.conn.prepare("SELECT entry FROM inbox ORDER BY id")?;
+        let entries = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .map(|entry| Ok(serde_json::from_str(&entry?)?))
+            .collect::<Result<Vec<InboxEntry>, SqliteStoreError>>()?;
+        let next_id = self
+            .conn
+            .query_row("SELECT next_id FROM inbox_state WHERE id = 0", [], |row| {
+                row.get(0)
+            })
+            .optional()?
+            .unwrap_or(0);
+        Ok(Inbox::from_entries(entries, next_id))
+    }
+
+    /// Appends an entry to the command log of a wallet
+    /// (the entry must extend the stored command log, see [`CommandLog::append`]).
+    pub fn append_command_log_entry(
+        &mut self,
+        wallet_id: &[u8; 32],
+        entry: &CommandLogEntry,
+    ) -> Result<(), SqliteStoreError> {
+        let tx = self.conn.transaction()?;
+        let head = tx
+            .query_row(
+                "SELECT entry FROM command_log WHERE wallet_id = ?1 ORDER BY sequence DESC LIMIT 1",
+                params![wallet_id],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?
+            .map(|head| serde_json::from_str::<CommandLogEntry>(&head))
+            .transpose()?;
+        let head_hash = match &head {
+            Some(head) => head.hash(),
+            None => CommandLog::new().head_hash(),
+        };
+        let head_sequence = head.as_ref().map_or(0, |head| head.sequence);
+        if entry.sequence != head_sequence + 1 || entry.previous_hash != head_hash {
+            return Err(CommandLogError::InvalidChain.into());
+        }
+        tx.execute(
+            "INSERT INTO command_log (wallet_id, sequence, entry) VALUES (?1, ?2, ?3)",
+            params![wallet_id, entry.sequence, serde_json::to_string(entry)?],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Returns the (verified) command log of a wallet.
+    pub fn load_command_log(&self, wallet_id: &[u8; 32]) -> Result<CommandLog, SqliteStoreError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT entry FROM command_log WHERE wallet_id = ?1 ORDER BY sequence")?;
+        let entries = stmt
+            .query_map(params![wallet_id], |row| row.get::<_, String>(0))?
+            .map(|entry| Ok(serde_json::from_str(&entry?)?))
+            .collect::<Result<Vec<CommandLogEntry>, SqliteStoreError>>()?;
+        Ok(CommandLog::from_entries(entries)?)
+    }
+
+    /// Adds a (protocol specific encoding of a) pre-signature to the pool of a wallet.
+    pub fn push_presignature(
+        &mut self,
+        wallet_id: &[u8; 32],
+        presignature: &[u8],
+    ) -> Result<(), SqliteStoreError> {
+        let sealed = self
+            .cipher
+            .seal(&presignature_context(wallet_id), presignature)?;
+        self.conn.execute(
+            "INSERT INTO presignatures (wallet_id, sealed) VALUES (?1, ?2)",
+            params![wallet_id, sealed],
+        )?;
+        Ok(())
+    }
+
+    /// Removes and returns the oldest pre-signature in the pool of a wallet (if any).
+    ///
+    /// **NOTE:** Pre-signatures are removed before they're returned, so they're never used twice
+    /// (reusing a pre-signature for different messages leaks the secret key).
+    pub fn take_presignature(
+        &mut self,
+        wallet_id: &[u8; 32],
+    ) -> Result<Option<Zeroizing<Vec<u8>>>, SqliteStoreError> {
+        let tx = self.conn.transaction()?;
+        let Some((id, sealed)) = tx
+            .query_row(
+                "SELECT id, sealed FROM presignatures WHERE wallet_id = ?1 ORDER BY id LIMIT 1",
+                params![wallet_id],
+                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Vec<u8>>(1)?)),
+            )
+            .optional()?
+        else {
+            return Ok(None);
+        };
+        tx.execute("DELETE FROM presignatures WHERE id = ?1", params![id])?;
+        tx.commit()?;
+        Ok(Some(
+            self.cipher
+                .open(&presignature_context(wallet_id), &sealed)?,
+        ))
+    }
+
+    /// Returns the number of pre-signatures in the pool of a wallet.
+    pub fn presignature_count(&self, wallet_id: &[u8; 32]) -> Result<usize, SqliteStoreError> {
+        Ok(self.conn.query_row(
+            "SELECT COUNT(*) FROM presignatures WHERE wallet_id = ?1",
+            params![wallet_id],
+            |row| row.get(0),
+        )?)
+    }
+}
+
+impl KeyStore for SqliteStore {
+    type Error = SqliteStoreError;
+
+    fn save(&mut self, wallet_id: &[u8; 32], key: &StoredKey) -> Result<(), Self::Error> {
+        let sealed = self
+            .cipher
+            .seal(wallet_id, &Zeroizing::new(key.to_versioned()))?;
+        self.conn.execute(
+            "INSERT OR REPLACE INTO keys (wallet_id, sealed) VALUES (?1, ?2)",
+            params![wallet_id, sealed],
+        )?;
+        Ok(())
+    }
+
+    fn load(&self, wallet_id: &[u8; 32]) -> Result<Option<StoredKey>, Self::Error> {
+        let Some(sealed) = self
+            .conn
+            .query_row(
+                "SELECT sealed FROM keys WHERE wallet_id = ?1",
+                params![wallet_id],
+                |row| row.get::<_, Vec<u8>>(0),
+            )
+            .optional()?
+        else {
+            return Ok(None);
+        };
+        let plaintext = self.cipher.open(wallet_id, &sealed)?;
+        Ok(Some(
+            StoredKey::from_versioned(&plaintext).map_err(KeyStoreError::from)?,
+        ))
+    }
+
+    fn delete(&mut self, wallet_id: &[u8; 32]) -> Result<bool, Self::Error> {
+        Ok(self
+            .conn
+            .execute("DELETE FROM keys WHERE wallet_id = ?1", params![wallet_id])?
+            > 0)
+    }
+
+    fn wallet_ids(&self) -> Result<Vec<[u8; 32]>, Self::Error> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT wallet_id FROM keys ORDER BY wallet_id")?;
+        let wallet_ids = stmt
+            .query_map([], |row| row.get::<_, [u8; 32]>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(wallet_ids)
+    }
+}
+
+/// Returns the schema version of the database.
+fn schema_version(conn: &Connection) -> Result<usize, SqliteStoreError> {
+    Ok(conn.pragma_query_value(None, "user_version", |row| row.get(0))?)
+}
+
+/// Applies all pending schema migrations (in a single transaction).
+fn migrate(conn: &mut Connection) -> Result<(), SqliteStoreError> {
+    let version = schema_version(conn)?;
+    if version > MIGRATIONS.len() {
+        return Err(SqliteStoreError::UnsupportedSchemaVersion);
+    }
+    let tx = conn.transaction()?;
+    for migration in &MIGRATIONS[version..] {
+        tx.execute_batch(migration)?;
+    }
+    tx.pragma_update(None, "user_version", MIGRATIONS.len())?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Returns the context for sealing pre-signatures of a wallet (i.e `wallet id || "presignature"`).
+fn presignature_context(wallet_id: &[u8; 32]) -> Vec<u8> {
+    [wallet_id.as_slice(), PRESIGNATURE_CONTEXT].concat()
+}
+
+/// Returns a UTC timestamp as a SQLite integer (i.e clamped to the range of a signed 64-bit integer).
+fn to_sql_timestamp(timestamp: u64) -> i64 {
+    i64::try_from(timestamp).unwrap_or(i64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cbor::CanonicalCbor;
+    use crate::command_log::CommandOutcome;
+    use crate::crypto::Random32Bytes;
+    use crate::inbox::InboxItem;
+    use crate::share::SecretShare;
+    use crate::test_utils::MockECDSAIdentityProvider;
+    use crate::{identity_authed_request, share_split_reconstruct};
+    use alloc::vec;
+
+    #[test]
+    fn sqlite_store_works() {
+        // Generates identity provider and a migrated in-memory store.
+        let identity_provider = MockECDSAIdentityProvider::generate();
+        let mut store = SqliteStore::open_in_memory(&identity_provider).unwrap();
+        assert_eq!(store.schema_version().unwrap(), MIGRATIONS.len());
+
+        // Migrations are idempotent.
+        migrate(&mut store.conn).unwrap();
+        assert_eq!(store.schema_version().unwrap(), MIGRATIONS.len());
+
+        // Key material round trips.
+        let (wallet_id, other_wallet_id) = ([1u8; 32], [2u8; 32]);
+        let secret_share = SecretShare::from(Random32Bytes::generate_mod_q());
+        let (signing_share, sub_share) =
+            share_split_reconstruct::split(&secret_share, &identity_provider).unwrap();
+        let key = StoredKey {
+            signing_share,
+            sub_share,
+            local_key: b"local key".to_vec(),
+            roster: vec![identity_provider.verifying_key()],
+            epoch: 1,
+        };
+        assert!(store.load(&wallet_id).unwrap().is_none());
+        store.save(&other_wallet_id, &key).unwrap();
+        store.save(&wallet_id, &key).unwrap();
+        assert_eq!(
+            store.load(&wallet_id).unwrap().unwrap().to_cbor(),
+            key.to_cbor()
+        );
+        assert_eq!(
+            store.wallet_ids().unwrap(),
+            vec![wallet_id, other_wallet_id]
+        );
+        assert!(store.delete(&other_wallet_id).unwrap());
+        assert!(!store.delete(&other_wallet_id).unwrap());
+
+        // Key material can't be decrypted by another identity.
+        let sealed: Vec<u8> = store
+            .conn
+            .query_row("SELECT sealed FROM keys", [], |row| row.get(0))
+            .unwrap();
+        let other_store =
+            SqliteStore::open_in_memory(&MockECDSAIdentityProvider::generate()).unwrap();
+        other_store
+            .conn
+            .execute(
+                "INSERT INTO keys (wallet_id, sealed) VALUES (?1, ?2)",
+                params![wallet_id, sealed],
+            )
+            .unwrap();
+        assert!(matches!(
+            other_store.load(&wallet_id),
+            Err(SqliteStoreError::KeyStore(KeyStoreError::Decryption))
+        ));
+
+        // Inbox entries round trip (and identifiers are never reused).
+        let request = identity_authed_request::initiate("command", &identity_provider);
+        let mut inbox = Inbox::new();
+        let id = inbox.push(wallet_id, InboxItem::Request(request.clone()));
+        let expired_id = inbox.push_with_expiry(wallet_id, InboxItem::Request(request.clone()), 0);
+        for entry in inbox.entries() {
+            store.save_inbox_entry(entry).unwrap();
+        }
+        assert_eq!(store.delete_expired_inbox_entries(1).unwrap(), 1);
+        let mut loaded_inbox = store.load_inbox().unwrap();
+        assert_eq!(loaded_inbox.len(), 1);
+        assert_eq!(
+            loaded_inbox.get(id).unwrap().item.request().command,
+            "command"
+        );
+        assert!(loaded_inbox.get(expired_id).is_none());
+        assert_eq!(
+            loaded_inbox.push(wallet_id, InboxItem::Request(request.clone())),
+            expired_id + 1
+        );
+        assert!(store.delete_inbox_entry(id).unwrap());
+        assert!(!store.delete_inbox_entry(id).unwrap());
+        assert!(store.load_inbox().unwrap().is_empty());
+
+        // Command log entries round trip (and must extend the stored command log).
+        let mut command_log = CommandLog::new();
+        command_log.append(request.clone(), Vec::new(), CommandOutcome::Succeeded);
+        let head_hash = command_log.append(request, Vec::new(), CommandOutcome::Failed);
+        let entries = command_log.entries();
+        assert!(matches!(
+            store.append_command_log_entry(&wallet_id, &entries[1]),
+            Err(SqliteStoreError::CommandLog(CommandLogError::InvalidChain))
+        ));
+        for entry in entries {
+            store.append_command_log_entry(&wallet_id, entry).unwrap();
+        }
+        assert_eq!(
+            store.load_command_log(&wallet_id).unwrap().head_hash(),
+            head_hash
+        );
+        assert!(store.load_command_log(&other_wallet_id).unwrap().is_empty());
+
+        // Pre-signatures are taken oldest first (and only once).
+        store.push_presignature(&wallet_id, b"first").unwrap();
+        store.push_presignature(&wallet_id, b"second").unwrap();
+        assert_eq!(store.presignature_count(&wallet_id).unwrap(), 2);
+        assert_eq!(store.presignature_count(&other_wallet_id).unwrap(), 0);
+        assert!(store.take_presignature(&other_wallet_id).unwrap().is_none());
+        assert_eq!(
+            store
+                .take_presignature(&wallet_id)
+                .unwrap()
+                .unwrap()
+                .as_slice(),
+            b"first"
+        );
+        assert_eq!(
+            store
+                .take_presignature(&wallet_id)
+                .unwrap()
+                .unwrap()
+                .as_slice(),
+            b"second"
+        );
+        assert!(store.take_presignature(&wallet_id).unwrap().is_none());
+
+        // Newer schemas are rejected.
+        store
+            .conn
+            .pragma_update(None, "user_version", MIGRATIONS.len() + 1)
+            .unwrap();
+        assert!(matches!(
+            migrate(&mut store.conn),
+            Err(SqliteStoreError::UnsupportedSchemaVersion)
+        ));
+    }
+}
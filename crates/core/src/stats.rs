@@ -0,0 +1,254 @@
+//! Per-wallet, per-party key usage statistics (i.e signatures produced, sessions aborted, refreshes completed and last seen times).
+//!
+//! Allows operators to monitor wallet health and detect parties that never participate
+//! (e.g lost devices or unresponsive co-signers that silently reduce the available signing quorums).
+
+use crate::crypto::VerifyingKey;
+use crate::utils;
+use alloc::vec::Vec;
+
+/// Key usage statistics for a party.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PartyStats {
+    /// Number of signatures the party participated in.
+    pub signatures: u64,
+    /// Number of aborted sessions the party participated in.
+    pub aborted_sessions: u64,
+    /// Number of aborted sessions the party was blamed for.
+    pub blamed_aborts: u64,
+    /// Number of key refreshes the party participated in.
+    pub refreshes: u64,
+    /// UTC timestamp of the last time the party participated in a session (if ever).
+    pub last_seen: Option<u64>,
+}
+
+/// Key usage statistics for a wallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WalletStats {
+    /// The identifier of the wallet.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub wallet_id: [u8; 32],
+    /// Number of signatures produced.
+    pub signatures: u64,
+    /// Number of aborted sessions.
+    pub aborted_sessions: u64,
+    /// Number of key refreshes completed.
+    pub refreshes: u64,
+    /// Key usage statistics for parties that participated in at least one session.
+    pub parties: Vec<(VerifyingKey, PartyStats)>,
+}
+
+impl WalletStats {
+    /// Returns an empty wallet statistics record for the wallet.
+    fn new(wallet_id: [u8; 32]) -> Self {
+        Self {
+            wallet_id,
+            signatures: 0,
+            aborted_sessions: 0,
+            refreshes: 0,
+            parties: Vec::new(),
+        }
+    }
+
+    /// Returns the key usage statistics for the party (if any).
+    pub fn party(&self, verifying_key: &VerifyingKey) -> Option<&PartyStats> {
+        self.parties
+            .iter()
+            .find(|(key, _)| key == verifying_key)
+            .map(|(_, stats)| stats)
+    }
+
+    /// Returns the key usage statistics for the party (inserting an empty record if necessary).
+    fn party_mut(&mut self, verifying_key: &VerifyingKey) -> &mut PartyStats {
+        let idx = match self
+            .parties
+            .iter()
+            .position(|(key, _)| key == verifying_key)
+        {
+            Some(idx) => idx,
+            None => {
+                self.parties
+                    .push((verifying_key.clone(), PartyStats::default()));
+                self.parties.len() - 1
+            }
+        };
+        &mut self.parties[idx].1
+    }
+
+    /// Given a list of participants, updates the statistics and last seen times of the participants.
+    fn record_participants(
+        &mut self,
+        participants: &[VerifyingKey],
+        update: impl Fn(&mut PartyStats),
+    ) {
+        let now = utils::unix_timestamp();
+        for participant in participants {
+            let stats = self.party_mut(participant);
+            update(stats);
+            stats.last_seen = Some(now);
+        }
+    }
+}
+
+/// Key usage statistics for all wallets of a party (or an operator).
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stats {
+    /// Key usage statistics for wallets with at least one recorded session.
+    wallets: Vec<WalletStats>,
+}
+
+impl Stats {
+    /// Returns empty key usage statistics.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the key usage statistics for the wallet (if any).
+    pub fn wallet(&self, wallet_id: &[u8; 32]) -> Option<&WalletStats> {
+        self.wallets
+            .iter()
+            .find(|stats| &stats.wallet_id == wallet_id)
+    }
+
+    /// Returns the key usage statistics for all wallets.
+    pub fn wallets(&self) -> &[WalletStats] {
+        &self.wallets
+    }
+
+    /// Returns the key usage statistics for the party in the wallet (if any).
+    pub fn party(&self, wallet_id: &[u8; 32], verifying_key: &VerifyingKey) -> Option<&PartyStats> {
+        self.wallet(wallet_id)?.party(verifying_key)
+    }
+
+    /// Given a wallet identifier and a list of verifying keys for the participants, records a produced signature.
+    pub fn record_signature(&mut self, wallet_id: &[u8; 32], participants: &[VerifyingKey]) {
+        let stats = self.wallet_mut(wallet_id);
+        stats.signatures += 1;
+        stats.record_participants(participants, |party| party.signatures += 1);
+    }
+
+    /// Given a wallet identifier, a list of verifying keys for the participants
+    /// and a list of verifying keys for the parties blamed for the abort (if any), records an aborted session.
+    pub fn record_aborted_session(
+        &mut self,
+        wallet_id: &[u8; 32],
+        participants: &[VerifyingKey],
+        culprits: &[VerifyingKey],
+    ) {
+        let stats = self.wallet_mut(wallet_id);
+        stats.aborted_sessions += 1;
+        stats.record_participants(participants, |party| party.aborted_sessions += 1);
+        for culprit in culprits {
+            stats.party_mut(culprit).blamed_aborts += 1;
+        }
+    }
+
+    /// Given a wallet identifier and a list of verifying keys for the participants, records a completed key refresh.
+    pub fn record_refresh(&mut self, wallet_id: &[u8; 32], participants: &[VerifyingKey]) {
+        let stats = self.wallet_mut(wallet_id);
+        stats.refreshes += 1;
+        stats.record_participants(participants, |party| party.refreshes += 1);
+    }
+
+    /// Given a wallet identifier, a list of verifying keys for all parties and a maximum idle duration (in seconds),
+    /// returns the verifying keys of parties that never participated in a session or were last seen before the maximum idle duration.
+    pub fn inactive_parties(
+        &self,
+        wallet_id: &[u8; 32],
+        roster: &[VerifyingKey],
+        max_idle: u64,
+    ) -> Vec<VerifyingKey> {
+        let cutoff = utils::unix_timestamp().saturating_sub(max_idle);
+        roster
+            .iter()
+            .filter(|party| {
+                !self
+                    .party(wallet_id, party)
+                    .and_then(|stats| stats.last_seen)
+                    .is_some_and(|last_seen| last_seen >= cutoff)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the key usage statistics for the wallet (inserting an empty record if necessary).
+    fn wallet_mut(&mut self, wallet_id: &[u8; 32]) -> &mut WalletStats {
+        let idx = match self
+            .wallets
+            .iter()
+            .position(|stats| &stats.wallet_id == wallet_id)
+        {
+            Some(idx) => idx,
+            None => {
+                self.wallets.push(WalletStats::new(*wallet_id));
+                self.wallets.len() - 1
+            }
+        };
+        &mut self.wallets[idx]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockECDSAIdentityProvider;
+    use crate::IdentityProvider;
+    use alloc::vec;
+
+    #[test]
+    fn stats_works() {
+        // Generates a roster.
+        let roster: Vec<VerifyingKey> = (0..3)
+            .map(|_| MockECDSAIdentityProvider::generate().verifying_key())
+            .collect();
+        let wallet_id = [1u8; 32];
+        let mut stats = Stats::new();
+        assert!(stats.wallet(&wallet_id).is_none());
+        assert_eq!(stats.inactive_parties(&wallet_id, &roster, 3600), roster);
+
+        // Records sessions (with the last party never participating).
+        stats.record_signature(&wallet_id, &roster[..2]);
+        stats.record_signature(&wallet_id, &roster[..2]);
+        stats.record_aborted_session(&wallet_id, &roster[..2], &roster[1..2]);
+        stats.record_refresh(&wallet_id, &roster[..2]);
+
+        let wallet_stats = stats.wallet(&wallet_id).unwrap();
+        assert_eq!(
+            (
+                wallet_stats.signatures,
+                wallet_stats.aborted_sessions,
+                wallet_stats.refreshes
+            ),
+            (2, 1, 1)
+        );
+        let party_stats = stats.party(&wallet_id, &roster[1]).unwrap();
+        assert_eq!(
+            (
+                party_stats.signatures,
+                party_stats.aborted_sessions,
+                party_stats.blamed_aborts,
+                party_stats.refreshes
+            ),
+            (2, 1, 1, 1)
+        );
+        assert_eq!(
+            stats.party(&wallet_id, &roster[0]).unwrap().blamed_aborts,
+            0
+        );
+        assert!(party_stats.last_seen.unwrap() <= utils::unix_timestamp());
+
+        // Parties that never participate are reported as inactive.
+        assert!(stats.party(&wallet_id, &roster[2]).is_none());
+        assert_eq!(
+            stats.inactive_parties(&wallet_id, &roster, 3600),
+            vec![roster[2].clone()]
+        );
+
+        // Statistics are per wallet.
+        assert!(stats.party(&[2u8; 32], &roster[0]).is_none());
+        assert_eq!(stats.wallets().len(), 1);
+    }
+}
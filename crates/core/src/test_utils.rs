@@ -1,12 +1,17 @@
 //! Test utilities.
 
+use alloc::borrow::ToOwned;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
 use k256::ecdsa::{signature::Signer, SigningKey};
+use rand::rngs::OsRng;
 
 use crate::crypto::{
-    EllipticCurve, KeyEncoding, MessageDigest, Signature, SignatureAlgorithm, SignatureEncoding,
-    VerifyingKey,
+    EllipticCurve, KeyEncoding, MessageDigest, Random32Bytes, Signature, SignatureAlgorithm,
+    SignatureEncoding, VerifyingKey,
 };
-use crate::IdentityProvider;
+use crate::{Clock, IdentityProvider};
 
 /// A mock ECDSA/Secp256k1/SHA-256 based identity provider.
 #[derive(Debug, Clone)]
@@ -17,10 +22,9 @@ pub struct MockECDSAIdentityProvider {
 impl MockECDSAIdentityProvider {
     /// Generates an ECDSA/Secp256k1/SHA-256 signing key.
     pub fn generate() -> Self {
-        let mut rng = rand::thread_rng();
         Self {
             // `k256::ecdsa::SigningKey` uses `Secp256k1` and `SHA-256`.
-            secret: SigningKey::random(&mut rng),
+            secret: SigningKey::random(&mut OsRng),
         }
     }
 }
@@ -69,6 +73,80 @@ impl MockECDSAIdentityProvider {
     }
 }
 
+/// A mock EdDSA/Curve25519 (i.e Ed25519) based identity provider.
+#[derive(Debug, Clone)]
+pub struct MockEdDSAIdentityProvider {
+    secret: ed25519_dalek::SigningKey,
+}
+
+impl MockEdDSAIdentityProvider {
+    /// Generates an Ed25519 signing key.
+    pub fn generate() -> Self {
+        Self {
+            secret: ed25519_dalek::SigningKey::from_bytes(&Random32Bytes::generate().to_be_bytes()),
+        }
+    }
+}
+
+impl IdentityProvider for MockEdDSAIdentityProvider {
+    /// Computes and serializes the Ed25519 verifying key (in RFC 8032 format).
+    fn verifying_key(&self) -> VerifyingKey {
+        VerifyingKey {
+            key: self.secret.verifying_key().to_bytes().to_vec(),
+            algo: SignatureAlgorithm::EdDSA,
+            curve: EllipticCurve::Curve25519,
+            enc: KeyEncoding::RFC8032,
+        }
+    }
+
+    /// Computes and serializes (in RFC 8032 format) the Ed25519 signature of a message.
+    fn sign(&self, msg: &[u8]) -> Signature {
+        let signature: ed25519_dalek::Signature = self.secret.sign(msg);
+        Signature {
+            sig: signature.to_bytes().to_vec(),
+            algo: SignatureAlgorithm::EdDSA,
+            curve: EllipticCurve::Curve25519,
+            hash: MessageDigest::SHA512,
+            enc: SignatureEncoding::RFC8032,
+        }
+    }
+
+    /// Computes the Ed25519 signature for a message and returns (`R`, `s`) as (`[u8; 32]`, `[u8; 32]`).
+    fn sign_message_share(&self, msg: &[u8]) -> ([u8; 32], [u8; 32]) {
+        let signature: ed25519_dalek::Signature = self.secret.sign(msg);
+        (
+            signature.r_bytes().to_owned(),
+            signature.s_bytes().to_owned(),
+        )
+    }
+}
+
+/// A mock clock with a settable current time (i.e clones share the same current time).
+#[derive(Debug, Clone, Default)]
+pub struct MockClock {
+    now: Arc<AtomicU64>,
+}
+
+impl MockClock {
+    /// Given a unix timestamp in seconds, returns a mock clock set to that time.
+    pub fn new(now: u64) -> Self {
+        Self {
+            now: Arc::new(AtomicU64::new(now)),
+        }
+    }
+
+    /// Moves the current time forward by the given number of seconds.
+    pub fn advance(&self, secs: u64) {
+        self.now.fetch_add(secs, Ordering::Relaxed);
+    }
+}
+
+impl Clock for MockClock {
+    fn unix_timestamp(&self) -> u64 {
+        self.now.load(Ordering::Relaxed)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -90,4 +168,31 @@ mod tests {
             crypto::verify_signature(&identity_provider.verifying_key(), msg, &signature).is_ok()
         );
     }
+
+    #[test]
+    fn local_eddsa_identity_provider_works() {
+        // Message to sign.
+        let msg = b"Hello, world!";
+
+        // Generate identity provider.
+        let identity_provider = MockEdDSAIdentityProvider::generate();
+
+        // Signing.
+        let signature = identity_provider.sign(msg);
+
+        // Verifying.
+        assert!(
+            crypto::verify_signature(&identity_provider.verifying_key(), msg, &signature).is_ok()
+        );
+
+        // Verifying with a verifying key from a different scheme.
+        assert_eq!(
+            crypto::verify_signature(
+                &MockECDSAIdentityProvider::generate().verifying_key(),
+                msg,
+                &signature
+            ),
+            Err(crate::errors::CryptoError::SchemeMismatch)
+        );
+    }
 }
@@ -0,0 +1,249 @@
+//! Time-locked execution of quorum approved requests.
+//!
+//! Sensitive commands (e.g threshold decrease or party removal) can be executed only after an enforced delay
+//! following the local approval of the request, during which any verified party can cancel the request with a signed cancellation.
+//!
+//! **NOTE:** Each party starts the time lock window from its own clock when it approves the request (i.e when the time lock is created),
+//! rather than from the request timestamp chosen by the initiator (e.g a backdated request can't skip the time lock window).
+
+use crate::canonical::{CancellationMessage, CanonicalEncode};
+use crate::crypto;
+use crate::crypto::VerifyingKey;
+use crate::errors::{Error, TimeLockError};
+use crate::payloads::{CommandCancellationPayload, IdentityAuthedRequestPayload};
+use crate::traits::{Clock, IdentityProvider};
+use crate::utils::SystemClock;
+
+/// A time lock for a quorum approved request.
+///
+/// **NOTE:** The time lock reads the current time from its clock (i.e [`SystemClock`] by default),
+/// and the clock isn't persisted (i.e deserialized time locks use the default clock).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimeLock<C: Clock = SystemClock> {
+    /// The quorum approved request initialization payload.
+    request: IdentityAuthedRequestPayload,
+    /// The UTC timestamp at which the time lock was created (i.e the local approval time).
+    locked_at: u64,
+    /// The enforced delay (in seconds) between local approval and execution.
+    delay: u64,
+    /// A valid cancellation of the request (if any).
+    cancellation: Option<CommandCancellationPayload>,
+    /// The source of the current time.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    clock: C,
+}
+
+impl TimeLock {
+    /// Given a quorum approved request initialization payload and a delay (in seconds),
+    /// returns a time lock that unlocks `delay` seconds after the current time.
+    pub fn new(request: IdentityAuthedRequestPayload, delay: u64) -> Self {
+        Self::new_with_clock(request, delay, SystemClock)
+    }
+}
+
+impl<C: Clock> TimeLock<C> {
+    /// Same as [`TimeLock::new`] but reads the current time from the given clock.
+    pub fn new_with_clock(request: IdentityAuthedRequestPayload, delay: u64, clock: C) -> Self {
+        Self {
+            request,
+            locked_at: clock.unix_timestamp(),
+            delay,
+            cancellation: None,
+            clock,
+        }
+    }
+
+    /// Returns the quorum approved request initialization payload.
+    pub fn request(&self) -> &IdentityAuthedRequestPayload {
+        &self.request
+    }
+
+    /// Returns the UTC timestamp after which the request can be executed.
+    pub fn unlocks_at(&self) -> u64 {
+        self.locked_at.saturating_add(self.delay)
+    }
+
+    /// Returns the remaining time (in seconds) before the request can be executed (i.e `0` if the time lock window has elapsed).
+    pub fn remaining(&self) -> u64 {
+        self.unlocks_at()
+            .saturating_sub(self.clock.unix_timestamp())
+    }
+
+    /// Returns true if the time lock window has elapsed.
+    pub fn is_unlocked(&self) -> bool {
+        self.clock.unix_timestamp() >= self.unlocks_at()
+    }
+
+    /// Returns the cancellation of the request (if any).
+    pub fn cancellation(&self) -> Option<&CommandCancellationPayload> {
+        self.cancellation.as_ref()
+    }
+
+    /// Returns true if the request was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.is_some()
+    }
+
+    /// Given a command cancellation payload and a list of verifying keys for the other parties,
+    /// records the cancellation if it's valid and was received during the time lock window,
+    /// or returns an appropriate `Err` result otherwise.
+    pub fn cancel(
+        &mut self,
+        cancellation: CommandCancellationPayload,
+        verified_parties: &[VerifyingKey],
+    ) -> Result<(), TimeLockError> {
+        if self.is_unlocked() {
+            // Cancellations are only accepted during the time lock window.
+            return Err(TimeLockError::Unlocked);
+        }
+        verify_cancellation(&cancellation, &self.request, verified_parties)?;
+        if self.cancellation.is_none() {
+            self.cancellation = Some(cancellation);
+        }
+        Ok(())
+    }
+
+    /// Returns an `Ok` result if the request can be executed
+    /// (i.e the time lock window has elapsed and the request wasn't cancelled), or an appropriate `Err` result otherwise.
+    pub fn verify_execution(&self) -> Result<(), TimeLockError> {
+        if self.is_cancelled() {
+            Err(TimeLockError::Cancelled)
+        } else if !self.is_unlocked() {
+            Err(TimeLockError::Locked)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Given a quorum approved request initialization payload and an identity provider,
+/// returns a command cancellation payload for the request.
+pub fn cancel(
+    request: &IdentityAuthedRequestPayload,
+    identity_provider: &impl IdentityProvider,
+) -> CommandCancellationPayload {
+    CommandCancellationPayload {
+        verifying_key: identity_provider.verifying_key(),
+        signature: identity_provider.sign(&CancellationMessage { request }.message_bytes()),
+    }
+}
+
+/// Given a command cancellation payload, a quorum approved request initialization payload and a list of verifying keys for the other parties,
+/// returns an `Ok` result for a valid cancellation from a verified party, or an appropriate `Err` result otherwise.
+pub fn verify_cancellation(
+    cancellation: &CommandCancellationPayload,
+    request: &IdentityAuthedRequestPayload,
+    verified_parties: &[VerifyingKey],
+) -> Result<(), TimeLockError> {
+    if !verified_parties.contains(&cancellation.verifying_key) {
+        // Cancelling party must be a verified party.
+        Err(TimeLockError::Unauthorized(Error::UnauthorizedParty))
+    } else {
+        // Cancellation signature must be valid.
+        Ok(crypto::verify_signature(
+            &cancellation.verifying_key,
+            &CancellationMessage { request }.message_bytes(),
+            &cancellation.signature,
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::CryptoError;
+    use crate::quorum_approved_request;
+    use crate::test_utils::{MockClock, MockECDSAIdentityProvider};
+    use alloc::vec;
+
+    #[test]
+    fn time_lock_works() {
+        // Generates initiator and other party identity providers.
+        let initiator_identity_provider = MockECDSAIdentityProvider::generate();
+        let party_identity_provider = MockECDSAIdentityProvider::generate();
+
+        // Creates a list of verifying keys for all parties.
+        let verified_parties = vec![
+            initiator_identity_provider.verifying_key(),
+            party_identity_provider.verifying_key(),
+        ];
+
+        // Generates quorum approved request initialization payload.
+        let request = quorum_approved_request::initiate("command", &initiator_identity_provider);
+
+        // Request can be executed immediately with no delay.
+        let time_lock = TimeLock::new(request.clone(), 0);
+        assert_eq!(time_lock.verify_execution(), Ok(()));
+
+        // Request can't be executed or cancelled after the time lock window.
+        let mut time_lock = TimeLock::new(request.clone(), 0);
+        assert_eq!(
+            time_lock.cancel(
+                cancel(&request, &party_identity_provider),
+                &verified_parties
+            ),
+            Err(TimeLockError::Unlocked)
+        );
+
+        // Request can't be executed during the time lock window.
+        let mut time_lock = TimeLock::new(request.clone(), 3600);
+        assert!(time_lock.remaining() > 0);
+        assert_eq!(time_lock.verify_execution(), Err(TimeLockError::Locked));
+
+        // Cancellation from an unverified party should be rejected.
+        assert_eq!(
+            time_lock.cancel(
+                cancel(&request, &MockECDSAIdentityProvider::generate()),
+                &verified_parties
+            ),
+            Err(TimeLockError::Unauthorized(Error::UnauthorizedParty))
+        );
+
+        // Cancellation for a different request should be rejected.
+        let other_request =
+            quorum_approved_request::initiate("other-command", &initiator_identity_provider);
+        assert_eq!(
+            time_lock.cancel(
+                cancel(&other_request, &party_identity_provider),
+                &verified_parties
+            ),
+            Err(TimeLockError::Unauthorized(Error::Crypto(
+                CryptoError::InvalidSignature
+            )))
+        );
+        assert!(!time_lock.is_cancelled());
+
+        // Valid cancellation during the time lock window is recorded and blocks execution.
+        assert_eq!(
+            time_lock.cancel(
+                cancel(&request, &party_identity_provider),
+                &verified_parties
+            ),
+            Ok(())
+        );
+        assert!(time_lock.is_cancelled());
+        assert_eq!(time_lock.verify_execution(), Err(TimeLockError::Cancelled));
+    }
+
+    #[test]
+    fn time_lock_with_clock_works() {
+        // Generates quorum approved request initialization payload.
+        let identity_provider = MockECDSAIdentityProvider::generate();
+        let request = quorum_approved_request::initiate("command", &identity_provider);
+
+        // Time lock window is checked against the given clock,
+        // and starts at the local approval time (i.e not at the request timestamp).
+        let clock = MockClock::new(request.timestamp + 3600);
+        let time_lock = TimeLock::new_with_clock(request.clone(), 60, clock.clone());
+        assert_eq!(time_lock.unlocks_at(), request.timestamp + 3660);
+        assert_eq!(time_lock.remaining(), 60);
+        assert_eq!(time_lock.verify_execution(), Err(TimeLockError::Locked));
+        clock.advance(59);
+        assert_eq!(time_lock.remaining(), 1);
+        assert_eq!(time_lock.verify_execution(), Err(TimeLockError::Locked));
+        clock.advance(1);
+        assert_eq!(time_lock.remaining(), 0);
+        assert_eq!(time_lock.verify_execution(), Ok(()));
+    }
+}
@@ -8,7 +8,7 @@ use crate::crypto::{Signature, VerifyingKey};
 /// the only requirement for decentralized identity providers is
 /// the ability to compute cryptographic signatures for any arbitrary message in such a way that
 /// the output signature can be verified in a non-interactive manner.
-pub trait IdentityProvider: Clone + std::fmt::Debug {
+pub trait IdentityProvider: Clone + core::fmt::Debug {
     /// Returns the verifying key (i.e public key or address) for the identity.
     fn verifying_key(&self) -> VerifyingKey;
 
@@ -18,3 +18,13 @@ pub trait IdentityProvider: Clone + std::fmt::Debug {
     /// Computes signature for a message and returns (`r`, `s`) as (`[u8; 32]`, `[u8; 32]`).
     fn sign_message_share(&self, msg: &[u8]) -> ([u8; 32], [u8; 32]);
 }
+
+/// Interface for a source of the current time.
+///
+/// **NOTE:** Time-dependent checks (e.g request expiry and time locks) use [`SystemClock`](crate::utils::SystemClock) by default
+/// (i.e the JavaScript `Date` API on `wasm32-unknown-unknown` and a host supplied clock function without `std`),
+/// and accept any other clock (e.g a trusted time source) via their `*_with_clock` variants.
+pub trait Clock {
+    /// Returns the current unix timestamp in seconds.
+    fn unix_timestamp(&self) -> u64;
+}
@@ -0,0 +1,159 @@
+//! `wamu:` URI encoding for payloads (i.e `wamu:<type>?v=<version>&p=<base64url payload>`).
+//!
+//! Allows payloads to be shared between party devices via deep links, QR codes and messaging apps.
+//!
+//! **NOTE:** Payloads are encoded as unpadded base64url encodings of their canonical CBOR encoding (see [`crate::cbor`]).
+//! Any breaking change to this format increments [`URI_VERSION`].
+
+use crate::cbor::CanonicalCbor;
+use crate::errors::UriError;
+use crate::payloads::{
+    ApprovalDelegationPayload, CommandApprovalPayload, CommandCancellationPayload,
+    DestructionCertificate, DeviceMigrationRequestPayload, IdentityAuthedRequestPayload,
+    IdentityRotationChallengeResponsePayload, OfflineRequestPayload,
+    QuorumApprovedChallengeResponsePayload, RotationCertificate, VersionOfferPayload,
+};
+use crate::utils;
+use alloc::format;
+use alloc::string::String;
+
+/// The URI scheme.
+pub const URI_SCHEME: &str = "wamu";
+
+/// The current version of the URI encoding.
+pub const URI_VERSION: u32 = 1;
+
+/// A payload with a `wamu:` URI encoding.
+pub trait UriPayload: CanonicalCbor {
+    /// The payload type name in the URI.
+    const TYPE: &'static str;
+
+    /// Returns the `wamu:` URI encoding of the payload.
+    fn to_uri(&self) -> String {
+        format!(
+            "{URI_SCHEME}:{}?v={URI_VERSION}&p={}",
+            Self::TYPE,
+            utils::to_base64_url(&self.to_cbor())
+        )
+    }
+
+    /// Returns the payload decoded from its `wamu:` URI encoding, or an appropriate error otherwise.
+    fn from_uri(uri: &str) -> Result<Self, UriError> {
+        let (r#type, query) = uri
+            .strip_prefix(URI_SCHEME)
+            .and_then(|rest| rest.strip_prefix(':'))
+            .and_then(|rest| rest.split_once('?'))
+            .ok_or(UriError::InvalidUri)?;
+        let (version, payload) = query
+            .strip_prefix("v=")
+            .and_then(|rest| rest.split_once("&p="))
+            .ok_or(UriError::InvalidUri)?;
+        if version.parse::<u32>().map_err(|_| UriError::InvalidUri)? != URI_VERSION {
+            Err(UriError::UnsupportedVersion)
+        } else if r#type != Self::TYPE {
+            Err(UriError::TypeMismatch)
+        } else {
+            let bytes = utils::from_base64_url(payload).ok_or(UriError::InvalidUri)?;
+            Ok(Self::from_cbor(&bytes)?)
+        }
+    }
+}
+
+/// Implements `UriPayload` for the payload type with the given type name.
+macro_rules! impl_uri_payload {
+    ($payload_type:ty, $type_name:literal) => {
+        impl UriPayload for $payload_type {
+            const TYPE: &'static str = $type_name;
+        }
+    };
+}
+
+impl_uri_payload!(IdentityAuthedRequestPayload, "identity_authed_request");
+impl_uri_payload!(
+    IdentityRotationChallengeResponsePayload,
+    "identity_rotation_challenge_response"
+);
+impl_uri_payload!(RotationCertificate, "rotation_certificate");
+impl_uri_payload!(CommandApprovalPayload, "command_approval");
+impl_uri_payload!(ApprovalDelegationPayload, "approval_delegation");
+impl_uri_payload!(CommandCancellationPayload, "command_cancellation");
+impl_uri_payload!(
+    QuorumApprovedChallengeResponsePayload,
+    "quorum_approved_challenge_response"
+);
+impl_uri_payload!(VersionOfferPayload, "version_offer");
+impl_uri_payload!(OfflineRequestPayload, "offline_request");
+impl_uri_payload!(DeviceMigrationRequestPayload, "device_migration_request");
+impl_uri_payload!(DestructionCertificate, "destruction_certificate");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::CborError;
+    use crate::identity_authed_request;
+    use crate::test_utils::MockECDSAIdentityProvider;
+    use crate::IdentityProvider;
+    use alloc::vec;
+
+    #[test]
+    fn uri_encoding_works() {
+        // Generates identity provider and request.
+        let identity_provider = MockECDSAIdentityProvider::generate();
+        let request = identity_authed_request::initiate("command", &identity_provider);
+
+        // Request roundtrips through its URI encoding.
+        let uri = request.to_uri();
+        assert!(uri.starts_with("wamu:identity_authed_request?v=1&p="));
+        let decoded = IdentityAuthedRequestPayload::from_uri(&uri).unwrap();
+        assert_eq!(decoded.command, request.command);
+        assert_eq!(decoded.verifying_key, identity_provider.verifying_key());
+        assert_eq!(decoded.timestamp, request.timestamp);
+        assert_eq!(decoded.signature, request.signature);
+
+        // Invalid URIs, unsupported versions, mismatched types and invalid payloads are rejected.
+        for (uri, expected) in [
+            (uri.replacen("wamu:", "http:", 1), UriError::InvalidUri),
+            (uri.replacen("v=1", "v=2", 1), UriError::UnsupportedVersion),
+            (
+                uri.replacen("identity_authed_request", "command_approval", 1),
+                UriError::TypeMismatch,
+            ),
+            (format!("{uri}="), UriError::InvalidUri),
+            (
+                format!(
+                    "wamu:identity_authed_request?v=1&p={}",
+                    utils::to_base64_url(&[request.to_cbor(), vec![0]].concat())
+                ),
+                UriError::Decoding(CborError::TrailingBytes),
+            ),
+        ] {
+            assert_eq!(
+                IdentityAuthedRequestPayload::from_uri(&uri).err(),
+                Some(expected)
+            );
+        }
+    }
+
+    #[test]
+    fn base64_url_encoding_works() {
+        // RFC 4648 test vectors (without padding).
+        for (bytes, encoded) in [
+            (b"".as_slice(), ""),
+            (b"f", "Zg"),
+            (b"fo", "Zm8"),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg"),
+            (b"fooba", "Zm9vYmE"),
+            (b"foobar", "Zm9vYmFy"),
+            (&[0xfb, 0xff], "-_8"),
+        ] {
+            assert_eq!(utils::to_base64_url(bytes), encoded);
+            assert_eq!(utils::from_base64_url(encoded).as_deref(), Some(bytes));
+        }
+
+        // Invalid characters, lengths and non-canonical trailing bits are rejected.
+        for encoded in ["Zm9v+", "Z", "Zm9vY", "Zh", "Zm9="] {
+            assert_eq!(utils::from_base64_url(encoded), None);
+        }
+    }
+}
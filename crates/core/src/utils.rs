@@ -1,7 +1,23 @@
 //! Utilities for core sub-protocols.
 
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(not(any(
+    feature = "std",
+    test,
+    all(target_arch = "wasm32", target_os = "unknown")
+)))]
+use core::sync::atomic::{AtomicPtr, Ordering};
+#[cfg(all(
+    any(feature = "std", test),
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use crate::traits::Clock;
+
 pub const WAMU_MESSAGE_PREFIX: &str = "\x15Wamu Signed Message:\n";
 
 /// Add predefined prefix to a given message.
@@ -12,10 +28,203 @@ pub fn prefix_message_bytes(message: &[u8]) -> Vec<u8> {
     result
 }
 
-/// Returns the unix timestamp in seconds.
+/// Prefixes the given bytes with their length (as a big-endian `u64`).
+pub fn length_prefix_bytes(bytes: &[u8]) -> Vec<u8> {
+    [(bytes.len() as u64).to_be_bytes().as_slice(), bytes].concat()
+}
+
+/// Returns the lowercase hex encoding of the bytes.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Returns the bytes for a hex encoding (with or without a `0x` prefix) or `None` for invalid input.
+pub fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    hex.strip_prefix("0x")
+        .unwrap_or(hex)
+        .as_bytes()
+        .chunks(2)
+        .map(|chunk| {
+            core::str::from_utf8(chunk)
+                .ok()
+                .filter(|byte| byte.len() == 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+        })
+        .collect()
+}
+
+/// The URL and filename safe base64 alphabet (i.e RFC 4648 "base64url").
+const BASE64_URL_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Returns the unpadded base64url encoding of the bytes.
+pub fn to_base64_url(bytes: &[u8]) -> String {
+    bytes
+        .chunks(3)
+        .flat_map(|chunk| {
+            let group = chunk.iter().enumerate().fold(0u32, |acc, (idx, byte)| {
+                acc | (*byte as u32) << (16 - 8 * idx)
+            });
+            // `n` bytes are encoded as `n + 1` characters.
+            (0..=chunk.len()).map(move |idx| {
+                BASE64_URL_ALPHABET[(group >> (18 - 6 * idx) & 0x3f) as usize] as char
+            })
+        })
+        .collect()
+}
+
+/// Returns the bytes for an unpadded base64url encoding or `None` for invalid (including non-canonical) input.
+pub fn from_base64_url(encoded: &str) -> Option<Vec<u8>> {
+    encoded
+        .as_bytes()
+        .chunks(4)
+        .map(|chunk| {
+            let group = chunk
+                .iter()
+                .enumerate()
+                .try_fold(0u32, |acc, (idx, char)| {
+                    let value = BASE64_URL_ALPHABET.iter().position(|item| item == char)?;
+                    Some(acc | (value as u32) << (18 - 6 * idx))
+                })?;
+            // `n + 1` characters decode to `n` bytes, and unused trailing bits must be zero.
+            let len = chunk.len().checked_sub(1).filter(|len| *len > 0)?;
+            let bytes: Vec<u8> = (0..len)
+                .map(|idx| (group >> (16 - 8 * idx)) as u8)
+                .collect();
+            (group & ((1 << (24 - 8 * len)) - 1) == 0).then_some(bytes)
+        })
+        .collect::<Option<Vec<Vec<u8>>>>()
+        .map(|chunks| chunks.concat())
+}
+
+/// The Bitcoin base58 alphabet.
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Returns the base58 encoding of the bytes (i.e as used by Bitcoin addresses and BIP-32 extended keys).
+pub fn to_base58(bytes: &[u8]) -> String {
+    // Leading zero bytes are encoded as leading '1' characters.
+    let zeros = bytes.iter().take_while(|byte| **byte == 0).count();
+    // Little-endian base58 digits of the remaining bytes.
+    let mut digits: Vec<u8> = Vec::new();
+    for byte in &bytes[zeros..] {
+        let mut carry = *byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    "1".repeat(zeros)
+        + &digits
+            .iter()
+            .rev()
+            .map(|digit| BASE58_ALPHABET[*digit as usize] as char)
+            .collect::<String>()
+}
+
+/// Returns the bytes for a base58 encoding or `None` for invalid input.
+pub fn from_base58(encoded: &str) -> Option<Vec<u8>> {
+    // Leading '1' characters are decoded as leading zero bytes.
+    let zeros = encoded.bytes().take_while(|char| *char == b'1').count();
+    // Little-endian bytes of the remaining characters.
+    let mut bytes: Vec<u8> = Vec::new();
+    for char in encoded.bytes().skip(zeros) {
+        let mut carry = BASE58_ALPHABET.iter().position(|item| *item == char)? as u32;
+        for byte in bytes.iter_mut() {
+            carry += (*byte as u32) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    bytes.extend(vec![0; zeros]);
+    bytes.reverse();
+    Some(bytes)
+}
+
+/// Returns the unix timestamp in seconds (see [`SystemClock`]).
 pub fn unix_timestamp() -> u64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .expect("Time went backwards")
-        .as_secs()
+    SystemClock.unix_timestamp()
+}
+
+/// A clock backed by the system time
+/// (i.e the JavaScript `Date` API on `wasm32-unknown-unknown` and the clock function set with `set_clock` without `std`).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+/// The clock function set with [`set_clock`] (if any).
+#[cfg(not(any(
+    feature = "std",
+    test,
+    all(target_arch = "wasm32", target_os = "unknown")
+)))]
+static CLOCK: AtomicPtr<()> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Sets the function that returns the current unix timestamp in seconds for [`SystemClock`] without `std`
+/// (e.g reading the real-time clock of a secure element or a timestamp supplied by the host of a TEE enclave).
+///
+/// **NOTE:** [`SystemClock`] (and [`unix_timestamp`]) panic if no clock function is set.
+#[cfg(not(any(
+    feature = "std",
+    test,
+    all(target_arch = "wasm32", target_os = "unknown")
+)))]
+#[doc(cfg(not(feature = "std")))]
+pub fn set_clock(clock: fn() -> u64) {
+    CLOCK.store(clock as *mut (), Ordering::Release);
+}
+
+impl Clock for SystemClock {
+    // NOTE: Tests always link `std`.
+    #[cfg(all(
+        any(feature = "std", test),
+        not(all(target_arch = "wasm32", target_os = "unknown"))
+    ))]
+    fn unix_timestamp(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Time went backwards")
+            .as_secs()
+    }
+
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    fn unix_timestamp(&self) -> u64 {
+        // `Date.now()` returns milliseconds since the unix epoch.
+        (js_sys::Date::now() / 1000.0) as u64
+    }
+
+    #[cfg(not(any(
+        feature = "std",
+        test,
+        all(target_arch = "wasm32", target_os = "unknown")
+    )))]
+    fn unix_timestamp(&self) -> u64 {
+        let clock = CLOCK.load(Ordering::Acquire);
+        assert!(
+            !clock.is_null(),
+            "No clock function is set (see `set_clock`)"
+        );
+        // SAFETY: Only `fn() -> u64` pointers are stored (see `set_clock`).
+        let clock = unsafe { core::mem::transmute::<*mut (), fn() -> u64>(clock) };
+        clock()
+    }
+}
+
+/// Returns cryptographically secure random bytes
+/// (i.e from the operating system or the Web Crypto API on `wasm32-unknown-unknown`).
+///
+/// # Panics
+///
+/// Panics if the random number generator is unavailable (like [`OsRng`](rand::rngs::OsRng)).
+pub fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    getrandom::getrandom(&mut bytes).expect("Random number generator is unavailable");
+    bytes
 }
@@ -0,0 +1,284 @@
+//! Version tags and migrations for persisted artifacts (e.g stored key material and encrypted share backups).
+//!
+//! Persisted artifacts are encoded with a version tag (i.e the canonical CBOR array `[type name, format version, payload]`),
+//! and artifacts in older formats are upgraded to the current format on load
+//! by chaining the migrations in a [`MigrationRegistry`] (i.e version `n` to `n + 1`, then `n + 1` to `n + 2` e.t.c),
+//! so crate upgrades don't brick existing wallets.
+//!
+//! **NOTE:** Untagged encodings (i.e persisted before version tags were introduced) are decoded as format version 1.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use zeroize::Zeroizing;
+
+use crate::cbor::{CanonicalCbor, Decoder, Encoder};
+use crate::errors::{CborError, MigrationError};
+use crate::key_store::StoredKey;
+use crate::payloads::EncryptedShareBackup;
+use crate::policy::Policy;
+
+/// The format version of untagged encodings.
+pub const UNTAGGED_VERSION: u32 = 1;
+
+/// A migration that upgrades the payload of an artifact by one format version.
+pub type Migration = fn(&[u8]) -> Result<Vec<u8>, MigrationError>;
+
+/// A persisted artifact with a versioned encoding.
+pub trait Versioned: CanonicalCbor {
+    /// The artifact type name in the version tag.
+    const TYPE: &'static str;
+
+    /// The current format version.
+    ///
+    /// **NOTE:** Any breaking change to the canonical CBOR encoding of the artifact must increment this version
+    /// and add a migration from the previous version to the built-in migrations (see [`MigrationRegistry::new`]).
+    const VERSION: u32;
+
+    /// Returns the encoding of the artifact with a version tag.
+    fn to_versioned(&self) -> Vec<u8> {
+        // The payload may contain secrets (e.g stored key material).
+        let payload = Zeroizing::new(self.to_cbor());
+        let mut encoder = Encoder::new();
+        encoder.array(3);
+        encoder.text(Self::TYPE);
+        encoder.uint(Self::VERSION as u64);
+        encoder.bytes(&payload);
+        encoder.finish()
+    }
+
+    /// Returns the artifact decoded from its (possibly untagged) versioned encoding and upgraded with the built-in migrations,
+    /// or an appropriate error otherwise.
+    fn from_versioned(bytes: &[u8]) -> Result<Self, MigrationError> {
+        Self::from_versioned_with(bytes, &MigrationRegistry::new())
+    }
+
+    /// Returns the artifact decoded from its (possibly untagged) versioned encoding and upgraded with the given migrations,
+    /// or an appropriate error otherwise.
+    fn from_versioned_with(
+        bytes: &[u8],
+        registry: &MigrationRegistry,
+    ) -> Result<Self, MigrationError> {
+        let (version, payload) = match decode_tag(bytes)? {
+            Some(tag) if tag.r#type != Self::TYPE => return Err(MigrationError::TypeMismatch),
+            Some(tag) => (tag.version, tag.payload),
+            None => (UNTAGGED_VERSION, Zeroizing::new(bytes.to_vec())),
+        };
+        if version > Self::VERSION {
+            return Err(MigrationError::UnsupportedVersion);
+        }
+        let payload = registry.migrate(Self::TYPE, version, Self::VERSION, payload)?;
+        Ok(Self::from_cbor(&payload)?)
+    }
+}
+
+/// A registry of migrations for persisted artifacts (keyed by artifact type name and the format version they upgrade from).
+#[derive(Debug, Clone, Default)]
+pub struct MigrationRegistry {
+    /// Migrations keyed by artifact type name and the format version they upgrade from.
+    migrations: BTreeMap<(&'static str, u32), Migration>,
+}
+
+impl MigrationRegistry {
+    /// Returns a registry with the built-in migrations for artifacts of this crate.
+    pub fn new() -> Self {
+        // NOTE: No artifact of this crate has had a breaking format change yet (i.e all artifacts are at version 1).
+        Self::default()
+    }
+
+    /// Adds a migration that upgrades the payload of an artifact from the given format version to the next format version
+    /// (replacing any existing migration for the same artifact type and format version),
+    /// e.g for protocol specific encodings of local keys.
+    pub fn with_migration(
+        mut self,
+        r#type: &'static str,
+        from_version: u32,
+        migration: Migration,
+    ) -> Self {
+        self.migrations.insert((r#type, from_version), migration);
+        self
+    }
+
+    /// Returns true if there's a migration for the given artifact type name and format version.
+    pub fn contains(&self, r#type: &str, from_version: u32) -> bool {
+        self.migrations.contains_key(&(r#type, from_version))
+    }
+
+    /// Given an artifact type name, the format version of the payload and a target format version,
+    /// returns the payload upgraded to the target format version or an appropriate error otherwise.
+    pub fn migrate(
+        &self,
+        r#type: &str,
+        version: u32,
+        target_version: u32,
+        payload: Zeroizing<Vec<u8>>,
+    ) -> Result<Zeroizing<Vec<u8>>, MigrationError> {
+        (version..target_version).try_fold(payload, |payload, from_version| {
+            let migration = self
+                .migrations
+                .get(&(r#type, from_version))
+                .ok_or(MigrationError::MissingMigration(from_version))?;
+            migration(&payload).map(Zeroizing::new)
+        })
+    }
+}
+
+/// The type name, format version and payload of a versioned encoding.
+struct VersionTag {
+    /// The artifact type name.
+    r#type: String,
+    /// The format version of the payload.
+    version: u32,
+    /// The payload (i.e the canonical CBOR encoding of the artifact in its format version).
+    payload: Zeroizing<Vec<u8>>,
+}
+
+/// Returns the version tag of a versioned encoding,
+/// or `None` for untagged encodings (i.e encodings that don't start with a type name).
+fn decode_tag(bytes: &[u8]) -> Result<Option<VersionTag>, MigrationError> {
+    let mut decoder = Decoder::new(bytes);
+    let Ok(r#type) = decoder.array_of_len(3).and_then(|_| decoder.text()) else {
+        return Ok(None);
+    };
+    let version = u32::try_from(decoder.uint()?).map_err(|_| CborError::InvalidValue)?;
+    let payload = Zeroizing::new(decoder.bytes()?);
+    decoder.finish()?;
+    Ok(Some(VersionTag {
+        r#type,
+        version,
+        payload,
+    }))
+}
+
+/// Implements `Versioned` for the artifact type with the given type name and format version.
+macro_rules! impl_versioned {
+    ($artifact_type:ty, $type_name:literal, $version:literal) => {
+        impl Versioned for $artifact_type {
+            const TYPE: &'static str = $type_name;
+            const VERSION: u32 = $version;
+        }
+    };
+}
+
+impl_versioned!(StoredKey, "stored_key", 1);
+impl_versioned!(EncryptedShareBackup, "encrypted_share_backup", 1);
+impl_versioned!(Policy, "policy", 1);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An artifact whose payload was a text string (version 1),
+    /// then a big-endian byte string (version 2) and then an unsigned integer (version 3).
+    #[derive(Debug, PartialEq, Eq)]
+    struct Counter(u64);
+
+    impl CanonicalCbor for Counter {
+        fn encode(&self, encoder: &mut Encoder) {
+            encoder.uint(self.0);
+        }
+
+        fn decode(decoder: &mut Decoder) -> Result<Self, CborError> {
+            decoder.uint().map(Self)
+        }
+    }
+
+    impl_versioned!(Counter, "counter", 3);
+
+    fn counter_v1_to_v2(payload: &[u8]) -> Result<Vec<u8>, MigrationError> {
+        let mut decoder = Decoder::new(payload);
+        let value: u64 = decoder
+            .text()?
+            .parse()
+            .map_err(|_| CborError::InvalidValue)?;
+        decoder.finish()?;
+        let mut encoder = Encoder::new();
+        encoder.bytes(&value.to_be_bytes());
+        Ok(encoder.finish())
+    }
+
+    fn counter_v2_to_v3(payload: &[u8]) -> Result<Vec<u8>, MigrationError> {
+        let mut decoder = Decoder::new(payload);
+        let bytes: [u8; 8] = decoder
+            .bytes()?
+            .try_into()
+            .map_err(|_| CborError::InvalidValue)?;
+        decoder.finish()?;
+        Ok(Counter(u64::from_be_bytes(bytes)).to_cbor())
+    }
+
+    fn tagged(r#type: &str, version: u64, payload: &[u8]) -> Vec<u8> {
+        let mut encoder = Encoder::new();
+        encoder.array(3);
+        encoder.text(r#type);
+        encoder.uint(version);
+        encoder.bytes(payload);
+        encoder.finish()
+    }
+
+    #[test]
+    fn versioned_works() {
+        let registry = MigrationRegistry::new()
+            .with_migration("counter", 1, counter_v1_to_v2)
+            .with_migration("counter", 2, counter_v2_to_v3);
+        assert!(registry.contains("counter", 1));
+        assert!(!registry.contains("counter", 3));
+
+        // Current versions round trip (with and without migrations).
+        let counter = Counter(42);
+        let bytes = counter.to_versioned();
+        assert_eq!(Counter::from_versioned(&bytes), Ok(Counter(42)));
+        assert_eq!(
+            Counter::from_versioned_with(&bytes, &registry),
+            Ok(Counter(42))
+        );
+
+        // Older versions are upgraded by chaining migrations.
+        let mut v1_payload = Encoder::new();
+        v1_payload.text("42");
+        let v1_bytes = tagged("counter", 1, &v1_payload.finish());
+        assert_eq!(
+            Counter::from_versioned_with(&v1_bytes, &registry),
+            Ok(Counter(42))
+        );
+        let mut v2_payload = Encoder::new();
+        v2_payload.bytes(&42u64.to_be_bytes());
+        let v2_bytes = tagged("counter", 2, &v2_payload.finish());
+        assert_eq!(
+            Counter::from_versioned_with(&v2_bytes, &registry),
+            Ok(Counter(42))
+        );
+
+        // Untagged encodings are upgraded from version 1.
+        let mut untagged = Encoder::new();
+        untagged.text("42");
+        assert_eq!(
+            Counter::from_versioned_with(&untagged.finish(), &registry),
+            Ok(Counter(42))
+        );
+
+        // Older versions without migrations, newer versions and other artifact types are rejected.
+        assert_eq!(
+            Counter::from_versioned(&v1_bytes),
+            Err(MigrationError::MissingMigration(1))
+        );
+        assert_eq!(
+            Counter::from_versioned_with(&tagged("counter", 4, &counter.to_cbor()), &registry),
+            Err(MigrationError::UnsupportedVersion)
+        );
+        assert_eq!(
+            Counter::from_versioned_with(&tagged("other", 3, &counter.to_cbor()), &registry),
+            Err(MigrationError::TypeMismatch)
+        );
+
+        // Malformed version tags and payloads are rejected.
+        assert_eq!(
+            Counter::from_versioned(&bytes[..bytes.len() - 1]),
+            Err(MigrationError::Decoding(CborError::UnexpectedEnd))
+        );
+        assert!(matches!(
+            Counter::from_versioned(&tagged("counter", 3, b"not cbor")),
+            Err(MigrationError::Decoding(_))
+        ));
+    }
+}
@@ -0,0 +1,240 @@
+//! [`wasm-bindgen`](https://rustwasm.github.io/docs/wasm-bindgen/) bindings for browser parties (i.e `wasm32-unknown-unknown`).
+//!
+//! Exposes byte APIs for share splitting and reconstruction, share recovery backups and identity authenticated request verification.
+//!
+//! Encodings:
+//! - "Secret shares" are 32 byte big-endian integers.
+//! - Split shares are 96 bytes (i.e "signing share" || "sub-share" x || "sub-share" y, each a 32 byte big-endian integer).
+//! - Verifying keys, identity authenticated requests and encrypted share backups use their canonical CBOR encoding (see [`crate::cbor`]),
+//!   and lists of verifying keys are CBOR arrays.
+//!
+//! **NOTE:** Errors are thrown as JavaScript `Error` objects whose message is the `Debug` representation of the error.
+
+use alloc::format;
+use alloc::vec::Vec;
+use core::fmt;
+use crypto_bigint::{Encoding, U256};
+use k256::ecdsa::{signature::Signer, SigningKey};
+use wasm_bindgen::prelude::*;
+
+use crate::cbor::{CanonicalCbor, Decoder};
+use crate::crypto::{
+    EllipticCurve, KeyEncoding, MessageDigest, Signature, SignatureAlgorithm, SignatureEncoding,
+    VerifyingKey,
+};
+use crate::errors::Error;
+use crate::payloads::{EncryptedShareBackup, IdentityAuthedRequestPayload};
+use crate::share::{SecretShare, SigningShare, SubShare};
+use crate::traits::IdentityProvider;
+use crate::{identity_authed_request, share_recovery_backup, share_split_reconstruct};
+
+/// An ECDSA/Secp256k1/SHA-256 based identity provider for browser parties
+/// (e.g backed by a key derived by a browser extension).
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct WasmIdentityProvider {
+    secret: SigningKey,
+}
+
+#[wasm_bindgen]
+impl WasmIdentityProvider {
+    /// Given a 32 byte secret key, returns an identity provider.
+    #[wasm_bindgen(constructor)]
+    pub fn new(secret_key: &[u8]) -> Result<WasmIdentityProvider, JsError> {
+        Ok(Self {
+            secret: SigningKey::from_slice(secret_key).map_err(|_| js_error(Error::Encoding))?,
+        })
+    }
+
+    /// Returns the canonical CBOR encoding of the verifying key.
+    #[wasm_bindgen(js_name = verifyingKey)]
+    pub fn verifying_key_cbor(&self) -> Vec<u8> {
+        IdentityProvider::verifying_key(self).to_cbor()
+    }
+}
+
+impl fmt::Debug for WasmIdentityProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never prints the secret key.
+        f.debug_struct("WasmIdentityProvider")
+            .finish_non_exhaustive()
+    }
+}
+
+impl IdentityProvider for WasmIdentityProvider {
+    /// Computes and serializes the ECDSA/Secp256k1 verifying key (in SEC1 format).
+    fn verifying_key(&self) -> VerifyingKey {
+        VerifyingKey {
+            key: k256::ecdsa::VerifyingKey::from(&self.secret)
+                .to_sec1_bytes()
+                .to_vec(),
+            algo: SignatureAlgorithm::ECDSA,
+            curve: EllipticCurve::Secp256k1,
+            enc: KeyEncoding::SEC1,
+        }
+    }
+
+    /// Computes and serializes (in DER format) the ECDSA/Secp256k1/SHA-256 signature of a message.
+    fn sign(&self, msg: &[u8]) -> Signature {
+        let signature: k256::ecdsa::Signature = self.secret.sign(msg);
+        Signature {
+            sig: signature.to_der().as_bytes().to_vec(),
+            algo: SignatureAlgorithm::ECDSA,
+            curve: EllipticCurve::Secp256k1,
+            hash: MessageDigest::SHA256,
+            enc: SignatureEncoding::DER,
+        }
+    }
+
+    /// Computes the ECDSA/Secp256k1/SHA-256 signature for a message and returns (`r`, `s`) as (`[u8; 32]`, `[u8; 32]`).
+    fn sign_message_share(&self, msg: &[u8]) -> ([u8; 32], [u8; 32]) {
+        let signature: k256::ecdsa::Signature = self.secret.sign(msg);
+        let (r, s) = signature.split_bytes();
+        (r.into(), s.into())
+    }
+}
+
+/// Given a "secret share" and an identity provider, returns the split "signing share" and "sub-share" (see [`share_split_reconstruct::split`]).
+#[wasm_bindgen(js_name = splitShare)]
+pub fn split_share(
+    secret_share: &[u8],
+    identity_provider: &WasmIdentityProvider,
+) -> Result<Vec<u8>, JsError> {
+    let secret_share = SecretShare::try_from(secret_share).map_err(js_error)?;
+    let (signing_share, sub_share) =
+        share_split_reconstruct::split(&secret_share, identity_provider).map_err(js_error)?;
+    Ok(encode_split_share(&signing_share, &sub_share))
+}
+
+/// Given a split "signing share" and "sub-share" and an identity provider,
+/// returns the "secret share" (see [`share_split_reconstruct::reconstruct`]).
+#[wasm_bindgen(js_name = reconstructShare)]
+pub fn reconstruct_share(
+    split_share: &[u8],
+    identity_provider: &WasmIdentityProvider,
+) -> Result<Vec<u8>, JsError> {
+    let (signing_share, sub_share) = decode_split_share(split_share).map_err(js_error)?;
+    let secret_share =
+        share_split_reconstruct::reconstruct(&signing_share, &sub_share, identity_provider)
+            .map_err(js_error)?;
+    Ok(secret_share.to_be_bytes().to_vec())
+}
+
+/// Given an entropy seed, a split "signing share" and "sub-share" and an identity provider,
+/// returns the encrypted share backup (see [`share_recovery_backup::backup`]).
+#[wasm_bindgen(js_name = backupShare)]
+pub fn backup_share(
+    entropy_seed: &[u8],
+    split_share: &[u8],
+    identity_provider: &WasmIdentityProvider,
+) -> Result<Vec<u8>, JsError> {
+    let (signing_share, sub_share) = decode_split_share(split_share).map_err(js_error)?;
+    let encrypted_share_backup =
+        share_recovery_backup::backup(entropy_seed, &signing_share, &sub_share, identity_provider)
+            .map_err(js_error)?;
+    Ok(encrypted_share_backup.to_cbor())
+}
+
+/// Given an entropy seed, an encrypted share backup and an identity provider,
+/// returns the decrypted "signing share" and "sub-share" (see [`share_recovery_backup::recover`]).
+#[wasm_bindgen(js_name = recoverShare)]
+pub fn recover_share(
+    entropy_seed: &[u8],
+    encrypted_share_backup: &[u8],
+    identity_provider: &WasmIdentityProvider,
+) -> Result<Vec<u8>, JsError> {
+    let encrypted_share_backup =
+        EncryptedShareBackup::from_cbor(encrypted_share_backup).map_err(js_error)?;
+    let (signing_share, sub_share) =
+        share_recovery_backup::recover(entropy_seed, &encrypted_share_backup, identity_provider)
+            .map_err(js_error)?;
+    Ok(encode_split_share(&signing_share, &sub_share))
+}
+
+/// Given an identity authenticated request and a list of verifying keys for the other parties,
+/// throws an error if the request is invalid (see [`identity_authed_request::verify`]).
+#[wasm_bindgen(js_name = verifyRequest)]
+pub fn verify_request(request: &[u8], verified_parties: &[u8]) -> Result<(), JsError> {
+    let request = IdentityAuthedRequestPayload::from_cbor(request).map_err(js_error)?;
+    let mut decoder = Decoder::new(verified_parties);
+    let verified_parties: Vec<VerifyingKey> = decoder.seq().map_err(js_error)?;
+    decoder.finish().map_err(js_error)?;
+    identity_authed_request::verify(&request, &verified_parties).map_err(js_error)
+}
+
+/// Returns the 96 byte encoding of a "signing share" and "sub-share".
+fn encode_split_share(signing_share: &SigningShare, sub_share: &SubShare) -> Vec<u8> {
+    [
+        signing_share.to_be_bytes(),
+        sub_share.x().to_be_bytes(),
+        sub_share.y().to_be_bytes(),
+    ]
+    .concat()
+}
+
+/// Returns the "signing share" and "sub-share" from their 96 byte encoding.
+fn decode_split_share(bytes: &[u8]) -> Result<(SigningShare, SubShare), Error> {
+    if bytes.len() != 96 {
+        return Err(Error::Encoding);
+    }
+    let coordinate = |offset: usize| U256::from_be_slice(&bytes[offset..offset + 32]);
+    let signing_share = SigningShare::try_from(&bytes[..32])?;
+    // Coordinates must be less than the curve order.
+    let sub_share = SubShare::new(coordinate(32), coordinate(64)).map_err(|_| Error::Encoding)?;
+    Ok((signing_share, sub_share))
+}
+
+/// Converts an error into a JavaScript `Error`.
+fn js_error<E: fmt::Debug>(error: E) -> JsError {
+    JsError::new(&format!("{error:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cbor::Encoder;
+    use crate::crypto::Random32Bytes;
+
+    #[test]
+    fn wasm_works() {
+        // Generates identity providers and a "secret share".
+        let identity_provider =
+            WasmIdentityProvider::new(&Random32Bytes::generate_mod_q().to_be_bytes()).unwrap();
+        let other_identity_provider =
+            WasmIdentityProvider::new(&Random32Bytes::generate_mod_q().to_be_bytes()).unwrap();
+        let secret_share = Random32Bytes::generate_mod_q().to_be_bytes();
+
+        // Splits and reconstructs the "secret share".
+        let split = split_share(&secret_share, &identity_provider).unwrap();
+        assert_eq!(split.len(), 96);
+        assert_eq!(
+            reconstruct_share(&split, &identity_provider).unwrap(),
+            secret_share
+        );
+        assert_ne!(
+            reconstruct_share(&split, &other_identity_provider).unwrap(),
+            secret_share
+        );
+
+        // Backs up and recovers the split "signing share" and "sub-share".
+        let entropy_seed = b"Hello, world!";
+        let backup = backup_share(entropy_seed, &split, &identity_provider).unwrap();
+        assert_eq!(
+            recover_share(entropy_seed, &backup, &identity_provider).unwrap(),
+            split
+        );
+
+        // Verifies identity authenticated requests from verified parties.
+        let request = identity_authed_request::initiate("command", &identity_provider);
+        let mut encoder = Encoder::new();
+        encoder.seq(&[
+            other_identity_provider.verifying_key(),
+            identity_provider.verifying_key(),
+        ]);
+        assert!(verify_request(&request.to_cbor(), &encoder.finish()).is_ok());
+        assert_eq!(
+            VerifyingKey::from_cbor(&identity_provider.verifying_key_cbor()),
+            Ok(identity_provider.verifying_key())
+        );
+    }
+}
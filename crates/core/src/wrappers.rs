@@ -1,12 +1,22 @@
 //! Convenience wrappers around core sub-protocols.
 
+use crate::canonical::CanonicalEncode;
+use crate::crypto;
 use crate::crypto::{Random32Bytes, Signature, VerifyingKey};
-use crate::errors::{Error, IdentityAuthedRequestError};
-use crate::identity_authed_request;
+use crate::errors::{
+    Error, IdentityAuthedRequestError, ShareBackupRecoveryError, SignatureBudgetError,
+};
+use crate::identity_authed_request::{self, RequestVerifier};
 use crate::identity_challenge;
-use crate::payloads::IdentityAuthedRequestPayload;
+use crate::identity_rotation;
+use crate::payloads::{CommandApprovalPayload, EncryptedShareBackup, IdentityAuthedRequestPayload};
+use crate::quorum_approved_request;
+use crate::share::{SecretShare, SigningShare, SubShare};
+use crate::share_recovery_backup;
+use crate::share_split_reconstruct;
+use crate::signature_budget::{KeyRefreshStatus, SignatureBudget};
 use crate::traits::IdentityProvider;
-use crate::{crypto, utils};
+use alloc::vec::Vec;
 
 /// Given random bytes and an identity provider, returns the verifying key and a signature of the random bytes.
 ///
@@ -15,7 +25,7 @@ pub fn initiate_request_with_signature(
     random_bytes: &[u8],
     identity_provider: &impl IdentityProvider,
 ) -> (VerifyingKey, Signature) {
-    let signature = identity_provider.sign(&utils::prefix_message_bytes(random_bytes));
+    let signature = identity_provider.sign(&random_bytes.message_bytes());
     (identity_provider.verifying_key(), signature)
 }
 
@@ -37,7 +47,7 @@ pub fn verify_request_with_signature(
         // Signature must be valid.
         Ok(crypto::verify_signature(
             verifying_key,
-            &utils::prefix_message_bytes(random_bytes),
+            &random_bytes.message_bytes(),
             signature,
         )?)
     }
@@ -51,20 +61,399 @@ pub fn verify_identity_authed_request_and_initiate_challenge(
     request: &IdentityAuthedRequestPayload,
     verified_parties: &[VerifyingKey],
 ) -> Result<Random32Bytes, IdentityAuthedRequestError> {
-    if command != request.command {
+    verify_command_and_initiate_challenge(command, request, || {
+        identity_authed_request::verify(request, verified_parties)
+    })
+}
+
+/// Same as [`verify_identity_authed_request_and_initiate_challenge`] but verifies the request with a request verifier
+/// (i.e identities with failed verifications are rate limited).
+pub fn verify_identity_authed_request_and_initiate_challenge_with_verifier(
+    command: &str,
+    request: &IdentityAuthedRequestPayload,
+    verified_parties: &[VerifyingKey],
+    verifier: &mut RequestVerifier,
+) -> Result<Random32Bytes, IdentityAuthedRequestError> {
+    verify_command_and_initiate_challenge(command, request, || {
+        verifier.verify(request, verified_parties)
+    })
+}
+
+/// Returns a challenge fragment for initiating an identity challenge if the "command" matches the request payload
+/// and the request is valid, or an appropriate error result otherwise.
+fn verify_command_and_initiate_challenge(
+    command: &str,
+    request: &IdentityAuthedRequestPayload,
+    verify: impl FnOnce() -> Result<(), IdentityAuthedRequestError>,
+) -> Result<Random32Bytes, IdentityAuthedRequestError> {
+    if command != request.command.as_str() {
         // Command doesn't match request payload.
         Err(IdentityAuthedRequestError::CommandMismatch)
     } else {
-        identity_authed_request::verify(request, verified_parties)?;
+        verify()?;
         Ok(identity_challenge::initiate())
     }
 }
 
+/// Pre/post hooks for identity authenticated and quorum approved request verification
+/// (e.g for custom policy checks, logging or user confirmation prompts).
+pub trait RequestInterceptor {
+    /// Given a "command" and an identity authenticated request payload,
+    /// returns an ok result to continue with verification or an error result to reject the request.
+    ///
+    /// Called before the request is verified.
+    fn before_verify(
+        &self,
+        _command: &str,
+        _request: &IdentityAuthedRequestPayload,
+    ) -> Result<(), IdentityAuthedRequestError> {
+        Ok(())
+    }
+
+    /// Given a "command", an identity authenticated request payload and the verification outcome,
+    /// returns an ok result to accept a verified request or an error result to reject it.
+    ///
+    /// Called after the request is verified (including rejected and invalid requests).
+    fn after_verify(
+        &self,
+        _command: &str,
+        _request: &IdentityAuthedRequestPayload,
+        outcome: Result<(), IdentityAuthedRequestError>,
+    ) -> Result<(), IdentityAuthedRequestError> {
+        outcome
+    }
+}
+
+/// A no-op request interceptor.
+impl RequestInterceptor for () {}
+
+/// Same as [`verify_identity_authed_request_and_initiate_challenge`] but with pre/post hooks from a request interceptor.
+pub fn verify_identity_authed_request_and_initiate_challenge_with_interceptor(
+    command: &str,
+    request: &IdentityAuthedRequestPayload,
+    verified_parties: &[VerifyingKey],
+    interceptor: &impl RequestInterceptor,
+) -> Result<Random32Bytes, IdentityAuthedRequestError> {
+    let outcome = interceptor.before_verify(command, request).and_then(|_| {
+        verify_identity_authed_request_and_initiate_challenge(command, request, verified_parties)
+    });
+    interceptor.after_verify(command, request, outcome.map(|_| ()))?;
+    outcome
+}
+
+/// Same as [`quorum_approved_request::verify_request_and_initiate_challenge`] but with pre/post hooks from a request interceptor.
+pub fn verify_quorum_approved_request_and_initiate_challenge_with_interceptor(
+    command: &str,
+    request: &IdentityAuthedRequestPayload,
+    identity_provider: &impl IdentityProvider,
+    verified_parties: &[VerifyingKey],
+    interceptor: &impl RequestInterceptor,
+) -> Result<CommandApprovalPayload, IdentityAuthedRequestError> {
+    let outcome = interceptor.before_verify(command, request).and_then(|_| {
+        quorum_approved_request::verify_request_and_initiate_challenge(
+            command,
+            request,
+            identity_provider,
+            verified_parties,
+        )
+    });
+    interceptor.after_verify(
+        command,
+        request,
+        outcome.as_ref().map(|_| ()).map_err(|error| *error),
+    )?;
+    outcome
+}
+
+/// A wallet party (i.e an identity provider, the verifying keys for all parties and the party's "signing share" and "sub-share")
+/// that orchestrates the core sub-protocols.
+pub struct Wallet<I: IdentityProvider> {
+    /// The decentralized identity provider of the party.
+    identity_provider: I,
+    /// Verifying keys for all parties (including the party itself).
+    roster: Vec<VerifyingKey>,
+    /// The "signing share" of the party.
+    signing_share: SigningShare,
+    /// The "sub-share" of the party.
+    sub_share: SubShare,
+    /// The signature budget of the current key epoch.
+    signature_budget: SignatureBudget,
+    /// The verifier for requests from other parties.
+    request_verifier: RequestVerifier,
+    /// The counter of the last request initiated in monotonic counter mode.
+    request_counter: u64,
+}
+
+impl<I: IdentityProvider> Wallet<I> {
+    /// Given an identity provider, a list of verifying keys for all parties and a "signing share" and "sub-share", returns a wallet party.
+    pub fn new(
+        identity_provider: I,
+        roster: Vec<VerifyingKey>,
+        signing_share: SigningShare,
+        sub_share: SubShare,
+    ) -> Self {
+        Self {
+            identity_provider,
+            roster,
+            signing_share,
+            sub_share,
+            signature_budget: SignatureBudget::default(),
+            request_verifier: RequestVerifier::default(),
+            request_counter: 0,
+        }
+    }
+
+    /// Sets the signature budget (i.e the key refresh threshold and hard ceiling for signatures in a key epoch).
+    pub fn with_signature_budget(mut self, signature_budget: SignatureBudget) -> Self {
+        self.signature_budget = signature_budget;
+        self
+    }
+
+    /// Sets the verifier for requests from other parties (e.g with a custom or restored request throttle).
+    pub fn with_request_verifier(mut self, request_verifier: RequestVerifier) -> Self {
+        self.request_verifier = request_verifier;
+        self
+    }
+
+    /// Sets the counter of the last request initiated in monotonic counter mode (e.g restored from storage).
+    pub fn with_request_counter(mut self, request_counter: u64) -> Self {
+        self.request_counter = request_counter;
+        self
+    }
+
+    /// Given an identity provider, a list of verifying keys for all parties and a "secret share",
+    /// returns a wallet party with the "secret share" split into a "signing share" and "sub-share", or an appropriate error otherwise.
+    pub fn from_secret_share(
+        identity_provider: I,
+        roster: Vec<VerifyingKey>,
+        secret_share: &SecretShare,
+    ) -> Result<Self, Error> {
+        let (signing_share, sub_share) =
+            share_split_reconstruct::split(secret_share, &identity_provider)?;
+        Ok(Self::new(
+            identity_provider,
+            roster,
+            signing_share,
+            sub_share,
+        ))
+    }
+
+    /// Returns the identity provider of the party.
+    pub fn identity_provider(&self) -> &I {
+        &self.identity_provider
+    }
+
+    /// Returns the verifying key of the party.
+    pub fn verifying_key(&self) -> VerifyingKey {
+        self.identity_provider.verifying_key()
+    }
+
+    /// Returns the verifying keys for all parties.
+    pub fn roster(&self) -> &[VerifyingKey] {
+        &self.roster
+    }
+
+    /// Replaces the verifying keys for all parties (e.g after another party's identity rotation).
+    pub fn set_roster(&mut self, roster: Vec<VerifyingKey>) {
+        self.roster = roster;
+    }
+
+    /// Returns the reconstructed "secret share" of the party or an appropriate error otherwise.
+    pub fn secret_share(&self) -> Result<SecretShare, Error> {
+        share_split_reconstruct::reconstruct(
+            &self.signing_share,
+            &self.sub_share,
+            &self.identity_provider,
+        )
+    }
+
+    /// Returns the verifier for requests from other parties (e.g for persisting its state).
+    pub fn request_verifier(&self) -> &RequestVerifier {
+        &self.request_verifier
+    }
+
+    /// Returns the counter of the last request initiated in monotonic counter mode (e.g for persisting it).
+    pub fn request_counter(&self) -> u64 {
+        self.request_counter
+    }
+
+    /// Returns the signature budget of the current key epoch.
+    pub fn signature_budget(&self) -> &SignatureBudget {
+        &self.signature_budget
+    }
+
+    /// Records a signature in the current key epoch and returns the key refresh status
+    /// (i.e [`KeyRefreshStatus::Required`] if a proactive key refresh should be triggered),
+    /// or an error if the current key epoch reached the maximum number of signatures.
+    ///
+    /// **NOTE:** This is meant to be called before joining a signing session (i.e signing must be refused on error).
+    pub fn record_signature(&mut self) -> Result<KeyRefreshStatus, SignatureBudgetError> {
+        self.signature_budget.record_signature()
+    }
+
+    /// Given the refreshed "signing share" and "sub-share" (i.e the output of a successful key refresh),
+    /// replaces the "signing share" and "sub-share" of the party and starts a new key epoch.
+    pub fn refresh(&mut self, signing_share: SigningShare, sub_share: SubShare) {
+        self.signing_share = signing_share;
+        self.sub_share = sub_share;
+        self.signature_budget.advance_epoch();
+    }
+
+    /// Given a message, returns the verifying key and a signature of the message for initiating signing.
+    pub fn initiate_signing(&self, message: &[u8]) -> (VerifyingKey, Signature) {
+        initiate_request_with_signature(message, &self.identity_provider)
+    }
+
+    /// Given a message, a verifying key for the sending party and a signature of the message,
+    /// returns an ok result if the signing request is from a party in the roster, or an appropriate error result otherwise.
+    pub fn verify_signing(
+        &self,
+        message: &[u8],
+        verifying_key: &VerifyingKey,
+        signature: &Signature,
+    ) -> Result<(), Error> {
+        verify_request_with_signature(message, verifying_key, signature, &self.roster)
+    }
+
+    /// Given a "command", returns an identity authenticated request payload for initiating a quorum approved request.
+    pub fn initiate_request(&self, command: &'static str) -> IdentityAuthedRequestPayload {
+        quorum_approved_request::initiate(command, &self.identity_provider)
+    }
+
+    /// Given a "command", returns an identity authenticated request payload for initiating a quorum approved request
+    /// in monotonic counter mode (i.e with the next request counter of the wallet).
+    ///
+    /// **NOTE:** The request counter must be persisted (see [`Wallet::request_counter`]),
+    /// otherwise other parties reject requests with reused counters as replays.
+    pub fn initiate_request_with_counter(
+        &mut self,
+        command: &'static str,
+    ) -> IdentityAuthedRequestPayload {
+        self.request_counter += 1;
+        quorum_approved_request::initiate_with_counter(
+            command,
+            self.request_counter,
+            &self.identity_provider,
+        )
+    }
+
+    /// Given a "command" and an identity authenticated request payload,
+    /// returns an approval for a valid request from a party in the roster or an appropriate error otherwise.
+    ///
+    /// **NOTE:** Requests are verified with the request verifier of the wallet (i.e identities with failed verifications are rate limited).
+    pub fn approve_request(
+        &mut self,
+        command: &str,
+        request: &IdentityAuthedRequestPayload,
+    ) -> Result<CommandApprovalPayload, IdentityAuthedRequestError> {
+        quorum_approved_request::verify_request_and_initiate_challenge_with_verifier(
+            command,
+            request,
+            &self.identity_provider,
+            &self.roster,
+            &mut self.request_verifier,
+        )
+    }
+
+    /// Same as [`Wallet::approve_request`] but with pre/post hooks from a request interceptor.
+    pub fn approve_request_with_interceptor(
+        &mut self,
+        command: &str,
+        request: &IdentityAuthedRequestPayload,
+        interceptor: &impl RequestInterceptor,
+    ) -> Result<CommandApprovalPayload, IdentityAuthedRequestError> {
+        let outcome = interceptor
+            .before_verify(command, request)
+            .and_then(|_| self.approve_request(command, request));
+        interceptor.after_verify(
+            command,
+            request,
+            outcome.as_ref().map(|_| ()).map_err(|error| *error),
+        )?;
+        outcome
+    }
+
+    /// Given a new identity provider, returns the wallet party with its "signing share" and "sub-share" rotated to
+    /// the new identity provider and its verifying key replaced in the roster, or an appropriate error otherwise.
+    ///
+    /// **NOTE:** Other parties must still verify the rotation (e.g via the identity rotation sub-protocol).
+    pub fn rotate_identity<J: IdentityProvider>(
+        self,
+        new_identity_provider: J,
+    ) -> Result<Wallet<J>, Error> {
+        let (signing_share, sub_share) = identity_rotation::rotate_signing_and_sub_share(
+            &self.signing_share,
+            &self.sub_share,
+            &self.identity_provider,
+            &new_identity_provider,
+        )?;
+        let verifying_key = self.verifying_key();
+        let new_verifying_key = new_identity_provider.verifying_key();
+        let roster = self
+            .roster
+            .iter()
+            .map(|key| {
+                if key == &verifying_key {
+                    new_verifying_key.clone()
+                } else {
+                    key.clone()
+                }
+            })
+            .collect();
+        Ok(
+            Wallet::new(new_identity_provider, roster, signing_share, sub_share)
+                .with_signature_budget(self.signature_budget)
+                .with_request_verifier(self.request_verifier)
+                .with_request_counter(self.request_counter),
+        )
+    }
+
+    /// Given an entropy seed (i.e typically a standardized phrase),
+    /// returns an encrypted backup of the "signing share" and "sub-share" or an appropriate error otherwise.
+    pub fn backup(
+        &self,
+        entropy_seed: &[u8],
+    ) -> Result<EncryptedShareBackup, ShareBackupRecoveryError> {
+        share_recovery_backup::backup(
+            entropy_seed,
+            &self.signing_share,
+            &self.sub_share,
+            &self.identity_provider,
+        )
+    }
+
+    /// Given an entropy seed (i.e typically a standardized phrase), an encrypted share backup,
+    /// an identity provider and a list of verifying keys for all parties,
+    /// returns the recovered wallet party or an appropriate error otherwise.
+    pub fn recover(
+        entropy_seed: &[u8],
+        encrypted_share_backup: &EncryptedShareBackup,
+        identity_provider: I,
+        roster: Vec<VerifyingKey>,
+    ) -> Result<Self, ShareBackupRecoveryError> {
+        let (signing_share, sub_share) = share_recovery_backup::recover(
+            entropy_seed,
+            encrypted_share_backup,
+            &identity_provider,
+        )?;
+        Ok(Self::new(
+            identity_provider,
+            roster,
+            signing_share,
+            sub_share,
+        ))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::canonical::CommandMessage;
+    use crate::crypto::Random32Bytes;
     use crate::errors::CryptoError;
     use crate::test_utils::MockECDSAIdentityProvider;
+    use alloc::vec;
+    use core::cell::RefCell;
 
     #[test]
     fn initiate_and_verify_request_with_signature_works() {
@@ -102,4 +491,256 @@ mod tests {
             assert_eq!(result, expected_result);
         }
     }
+
+    #[test]
+    fn request_interceptor_works() {
+        // Request interceptor that rejects blocked commands and logs verification outcomes.
+        struct PolicyInterceptor {
+            outcomes: RefCell<Vec<Result<(), IdentityAuthedRequestError>>>,
+        }
+        impl RequestInterceptor for PolicyInterceptor {
+            fn before_verify(
+                &self,
+                command: &str,
+                _request: &IdentityAuthedRequestPayload,
+            ) -> Result<(), IdentityAuthedRequestError> {
+                if command == "blocked" {
+                    Err(IdentityAuthedRequestError::Rejected)
+                } else {
+                    Ok(())
+                }
+            }
+
+            fn after_verify(
+                &self,
+                _command: &str,
+                _request: &IdentityAuthedRequestPayload,
+                outcome: Result<(), IdentityAuthedRequestError>,
+            ) -> Result<(), IdentityAuthedRequestError> {
+                self.outcomes.borrow_mut().push(outcome);
+                outcome
+            }
+        }
+        let interceptor = PolicyInterceptor {
+            outcomes: RefCell::new(Vec::new()),
+        };
+
+        // Generates identity providers.
+        let initiator = MockECDSAIdentityProvider::generate();
+        let approver = MockECDSAIdentityProvider::generate();
+        let verified_parties = vec![initiator.verifying_key(), approver.verifying_key()];
+
+        // Allowed requests are verified.
+        let request = identity_authed_request::initiate("allowed", &initiator);
+        assert!(
+            verify_identity_authed_request_and_initiate_challenge_with_interceptor(
+                "allowed",
+                &request,
+                &verified_parties,
+                &interceptor,
+            )
+            .is_ok()
+        );
+        assert!(
+            verify_quorum_approved_request_and_initiate_challenge_with_interceptor(
+                "allowed",
+                &request,
+                &approver,
+                &verified_parties,
+                &interceptor,
+            )
+            .is_ok()
+        );
+
+        // Blocked requests are rejected before verification.
+        let request = identity_authed_request::initiate("blocked", &initiator);
+        assert_eq!(
+            verify_identity_authed_request_and_initiate_challenge_with_interceptor(
+                "blocked",
+                &request,
+                &verified_parties,
+                &interceptor,
+            )
+            .err(),
+            Some(IdentityAuthedRequestError::Rejected)
+        );
+
+        // Post hooks observe all outcomes.
+        assert_eq!(
+            interceptor.outcomes.into_inner(),
+            vec![Ok(()), Ok(()), Err(IdentityAuthedRequestError::Rejected)]
+        );
+    }
+
+    #[test]
+    fn wallet_works() {
+        // Generates identity providers and a roster for all parties.
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (0..3)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let roster: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+
+        // Initializes wallets for all parties.
+        let secret_share = SecretShare::from(Random32Bytes::generate_mod_q());
+        let secret_share_bytes = secret_share.to_be_bytes();
+        let mut wallets: Vec<Wallet<MockECDSAIdentityProvider>> = identity_providers
+            .into_iter()
+            .map(|identity_provider| {
+                Wallet::from_secret_share(identity_provider, roster.clone(), &secret_share).unwrap()
+            })
+            .collect();
+
+        // Signing requests are verified against the roster.
+        let message = b"message";
+        let (verifying_key, signature) = wallets[0].initiate_signing(message);
+        assert_eq!(
+            wallets[1].verify_signing(message, &verifying_key, &signature),
+            Ok(())
+        );
+
+        // Requests are approved by other parties.
+        let command = "command";
+        let request = wallets[0].initiate_request(command);
+        let approval = wallets[1].approve_request(command, &request).unwrap();
+        assert_eq!(approval.verifying_key, roster[1]);
+        assert_eq!(
+            wallets[1].approve_request("other", &request).err(),
+            Some(IdentityAuthedRequestError::CommandMismatch)
+        );
+
+        // Requests in monotonic counter mode are approved once (i.e replays are rejected).
+        let counter_request = wallets[0].initiate_request_with_counter(command);
+        assert_eq!(counter_request.counter, Some(1));
+        let approvals = vec![wallets[2]
+            .approve_request(command, &counter_request)
+            .unwrap()];
+        let response = quorum_approved_request::challenge_response(
+            &approvals,
+            wallets[0].identity_provider(),
+            &counter_request,
+            2,
+            &roster,
+        )
+        .unwrap();
+        assert_eq!(
+            quorum_approved_request::verify_challenge_response(
+                &response,
+                &approvals,
+                &roster[0],
+                &counter_request,
+                2,
+                &roster
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            wallets[2].approve_request(command, &counter_request).err(),
+            Some(IdentityAuthedRequestError::InvalidCounter)
+        );
+
+        // Approvals are bound to the request counter (i.e they can't be re-attached to the next request for the same command).
+        let next_counter_request = wallets[0].initiate_request_with_counter(command);
+        assert_eq!(next_counter_request.counter, Some(2));
+        assert!(quorum_approved_request::verify_challenge_response(
+            &response,
+            &approvals,
+            &roster[0],
+            &next_counter_request,
+            2,
+            &roster
+        )
+        .is_err());
+
+        // Requests in monotonic counter mode can't be verified without the last-seen counters.
+        assert_eq!(
+            quorum_approved_request::verify_request_and_initiate_challenge(
+                command,
+                &next_counter_request,
+                wallets[2].identity_provider(),
+                &roster
+            )
+            .err(),
+            Some(IdentityAuthedRequestError::InvalidCounter)
+        );
+        assert!(wallets[2]
+            .approve_request(command, &next_counter_request)
+            .is_ok());
+
+        // Identities with failed verifications of authentic requests (e.g expired requests) are rate limited.
+        let mut expired_request = request.clone();
+        expired_request.timestamp -= 2 * 60 * 60;
+        expired_request.signature = wallets[0].identity_provider().sign(
+            &CommandMessage {
+                command,
+                timestamp: expired_request.timestamp,
+            }
+            .message_bytes(),
+        );
+        assert_eq!(
+            wallets[1].approve_request(command, &expired_request).err(),
+            Some(IdentityAuthedRequestError::Expired)
+        );
+        assert_eq!(
+            wallets[1].approve_request(command, &request).err(),
+            Some(IdentityAuthedRequestError::Throttled)
+        );
+        assert_eq!(
+            wallets[1]
+                .request_verifier()
+                .throttle()
+                .failures(&roster[0]),
+            1
+        );
+
+        // Backups can be recovered.
+        let mut wallets = wallets.into_iter();
+        let wallet = wallets.next().unwrap();
+        let entropy_seed = b"Hello, world!";
+        let backup = wallet.backup(entropy_seed).unwrap();
+        let wallet = Wallet::recover(
+            entropy_seed,
+            &backup,
+            wallet.identity_provider().clone(),
+            roster.clone(),
+        )
+        .unwrap();
+        assert_eq!(
+            wallet.secret_share().unwrap().to_be_bytes(),
+            secret_share_bytes
+        );
+
+        // Rotated identities preserve the "secret share" and update the roster.
+        let new_identity_provider = MockECDSAIdentityProvider::generate();
+        let new_verifying_key = new_identity_provider.verifying_key();
+        let wallet = wallet.rotate_identity(new_identity_provider).unwrap();
+        assert_eq!(
+            wallet.secret_share().unwrap().to_be_bytes(),
+            secret_share_bytes
+        );
+        assert_eq!(wallet.roster()[0], new_verifying_key);
+        assert_eq!(&wallet.roster()[1..], &roster[1..]);
+
+        // Signatures past the hard ceiling are refused until the key is refreshed.
+        let mut wallet = wallet.with_signature_budget(SignatureBudget::new(1, 2));
+        assert_eq!(wallet.record_signature(), Ok(KeyRefreshStatus::Required));
+        assert_eq!(wallet.record_signature(), Ok(KeyRefreshStatus::Required));
+        assert_eq!(
+            wallet.record_signature(),
+            Err(SignatureBudgetError::Exhausted)
+        );
+        let refreshed_secret_share = SecretShare::from(Random32Bytes::generate_mod_q());
+        let (signing_share, sub_share) =
+            share_split_reconstruct::split(&refreshed_secret_share, wallet.identity_provider())
+                .unwrap();
+        wallet.refresh(signing_share, sub_share);
+        assert_eq!(
+            wallet.secret_share().unwrap().to_be_bytes(),
+            refreshed_secret_share.to_be_bytes()
+        );
+        assert_eq!(wallet.signature_budget().epoch(), 1);
+        assert_eq!(wallet.record_signature(), Ok(KeyRefreshStatus::Required));
+    }
 }
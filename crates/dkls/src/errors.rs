@@ -0,0 +1,42 @@
+//! Types, abstractions and utilities for two-party ECDSA protocol errors.
+
+use round_based::IsCritical;
+
+/// A two-party ECDSA protocol error.
+#[derive(Debug)]
+pub enum Error {
+    /// A core Wamu error (e.g a share splitting or reconstruction error).
+    Core(wamu_core::Error),
+    /// Invalid protocol parameters (e.g party index or verifying keys).
+    InvalidConfig,
+    /// A message with an invalid identity authentication signature or from an unauthorized party.
+    InvalidAuthentication {
+        sender: u16,
+        round: u16,
+        error: wamu_core::Error,
+    },
+    /// A message that isn't expected (e.g from the party itself, to a different receiver or with malformed OT data).
+    UnexpectedMessage { sender: u16, round: u16 },
+    /// A duplicate message from the same sender for the same round (not critical).
+    DuplicateMessage { sender: u16, round: u16 },
+    /// An invalid proof of knowledge of the secret key share (key generation).
+    InvalidProofOfKnowledge { sender: u16 },
+    /// A nonce that doesn't match the sender's nonce commitment (signing).
+    InvalidNonceCommitment { sender: u16 },
+    /// The aggregate signature isn't a valid ECDSA signature of the message digest by the public key.
+    InvalidSignature,
+    /// The output was already picked.
+    AlreadyPicked,
+}
+
+impl From<wamu_core::Error> for Error {
+    fn from(error: wamu_core::Error) -> Self {
+        Self::Core(error)
+    }
+}
+
+impl IsCritical for Error {
+    fn is_critical(&self) -> bool {
+        !matches!(self, Error::DuplicateMessage { .. })
+    }
+}
@@ -0,0 +1,412 @@
+//! Augmented two-party key generation implementation.
+//!
+//! Each party samples an additive share of the secret key and broadcasts its public share
+//! with a proof of knowledge of the secret share, and the resulting "secret share" is split into a "signing share" and "sub-share".
+//!
+//! Refs:
+//! - <https://eprint.iacr.org/2023/765.pdf> (DKLs23).
+//! - <https://wamu.tech/specification#key-generation>.
+
+use k256::elliptic_curve::ff::Field;
+use k256::{ProjectivePoint, Scalar};
+use round_based::{Msg, StateMachine};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+use wamu_core::cbor::CanonicalCbor;
+use wamu_core::crypto::VerifyingKey;
+use wamu_core::{IdentityProvider, SecretShare, SigningShare, SubShare};
+use zeroize::Zeroize;
+
+use crate::errors::Error;
+use crate::identity_auth::{AuthedMessage, RoundMessage};
+use crate::{identity_auth, primitives};
+
+const KEYGEN_SESSION_TAG: &str = "wamu-dkls/keygen";
+const PROOF_OF_KNOWLEDGE_TAG: &str = "wamu-dkls/keygen/proof-of-knowledge";
+
+/// The public key material of a party (i.e the public key and public shares of both parties).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyShare {
+    /// Party index (i.e 1 or 2).
+    pub idx: u16,
+    /// The public key.
+    pub public_key: ProjectivePoint,
+    /// Public shares (i.e public keys of the additive secret shares) of both parties (in party index order).
+    pub public_shares: [ProjectivePoint; 2],
+}
+
+impl KeyShare {
+    /// Returns the index of the other party.
+    pub fn peer_idx(&self) -> u16 {
+        3 - self.idx
+    }
+
+    /// Returns the ECDSA verifying key for the public key.
+    pub fn verifying_key(&self) -> k256::ecdsa::VerifyingKey {
+        k256::ecdsa::VerifyingKey::from_affine(self.public_key.to_affine())
+            .expect("public key isn't the identity point")
+    }
+}
+
+/// The output of key generation (i.e the public key material, "signing share" and "sub-share" of the party).
+pub struct WalletShare {
+    /// The public key material of the party.
+    pub key_share: KeyShare,
+    /// The "signing share" of the party.
+    pub signing_share: SigningShare,
+    /// The "sub-share" of the party.
+    pub sub_share: SubShare,
+}
+
+/// A key generation message body.
+#[derive(Debug, Clone)]
+pub enum KeyGenMessageBody {
+    /// Round 1 (broadcast): the public share and a proof of knowledge of the secret share.
+    Round1 {
+        /// Public share.
+        public_share: ProjectivePoint,
+        /// Nonce commitment of the proof of knowledge.
+        proof_commitment: ProjectivePoint,
+        /// Response of the proof of knowledge.
+        proof_response: Scalar,
+    },
+}
+
+impl RoundMessage for KeyGenMessageBody {
+    fn round(&self) -> u16 {
+        1
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        match self {
+            Self::Round1 {
+                public_share,
+                proof_commitment,
+                proof_response,
+            } => [
+                primitives::encode_point(public_share),
+                primitives::encode_point(proof_commitment),
+                proof_response.to_bytes().to_vec(),
+            ]
+            .concat(),
+        }
+    }
+}
+
+/// An identity authenticated key generation message.
+pub type KeyGenMessage = Msg<AuthedMessage<KeyGenMessageBody>>;
+
+/// A [StateMachine](StateMachine) that implements augmented two-party key generation.
+pub struct AugmentedKeyGen<'a, I: IdentityProvider> {
+    /// The decentralized identity provider of the party.
+    identity_provider: &'a I,
+    /// Verifying keys for both parties (in party index order).
+    verified_parties: &'a [VerifyingKey],
+    /// Party index.
+    idx: u16,
+    /// Hash of the session parameters (for binding messages and proofs to the session).
+    session_hash: Vec<u8>,
+    /// Additive secret share of the party.
+    secret_share: Scalar,
+    /// Public shares of both parties (in party index order).
+    public_shares: [Option<ProjectivePoint>; 2],
+    /// Current round.
+    round: u16,
+    /// Outgoing message queue.
+    message_queue: Vec<KeyGenMessage>,
+    /// Protocol output.
+    output: Option<WalletShare>,
+    /// Whether or not the output was already picked.
+    is_picked: bool,
+}
+
+impl<'a, I: IdentityProvider> AugmentedKeyGen<'a, I> {
+    /// Initializes party for the augmented two-party key generation protocol given its identity provider,
+    /// verifying keys for both parties (in party index order), the party index (i.e 1 or 2)
+    /// and a random session identifier shared by both parties.
+    pub fn new(
+        identity_provider: &'a I,
+        verified_parties: &'a [VerifyingKey],
+        idx: u16,
+        session_id: [u8; 32],
+    ) -> Result<Self, Error> {
+        if !(1..=2).contains(&idx)
+            || verified_parties.len() != 2
+            || verified_parties[idx as usize - 1] != identity_provider.verifying_key()
+        {
+            return Err(Error::InvalidConfig);
+        }
+
+        // Computes the session hash.
+        let session_hash = verified_parties
+            .iter()
+            .fold(
+                Sha256::new()
+                    .chain_update(KEYGEN_SESSION_TAG)
+                    .chain_update(session_id),
+                |hasher, verifying_key| hasher.chain_update(verifying_key.to_cbor()),
+            )
+            .finalize()
+            .to_vec();
+
+        // Samples an additive secret share and proves knowledge of it.
+        let mut rng = rand::thread_rng();
+        let secret_share = Scalar::random(&mut rng);
+        let public_share = ProjectivePoint::GENERATOR * secret_share;
+        let nonce = Scalar::random(&mut rng);
+        let proof_commitment = ProjectivePoint::GENERATOR * nonce;
+        let proof_response = nonce
+            + secret_share * proof_challenge(&session_hash, idx, &public_share, &proof_commitment);
+
+        // Broadcasts the public share and the proof of knowledge.
+        let message_queue = vec![identity_auth::authenticate(
+            KeyGenMessageBody::Round1 {
+                public_share,
+                proof_commitment,
+                proof_response,
+            },
+            &session_hash,
+            idx,
+            None,
+            identity_provider,
+        )];
+        let mut public_shares = [None; 2];
+        public_shares[idx as usize - 1] = Some(public_share);
+
+        Ok(Self {
+            identity_provider,
+            verified_parties,
+            idx,
+            session_hash,
+            secret_share,
+            public_shares,
+            round: 1,
+            message_queue,
+            output: None,
+            is_picked: false,
+        })
+    }
+
+    // Computes the output.
+    fn finalize(&mut self) -> Result<(), Error> {
+        let public_shares = self
+            .public_shares
+            .map(|public_share| public_share.expect("public shares are received in round 1"));
+        let public_key = public_shares[0] + public_shares[1];
+        if public_key == ProjectivePoint::IDENTITY {
+            return Err(Error::InvalidProofOfKnowledge {
+                sender: 3 - self.idx,
+            });
+        }
+
+        // Splits the secret share into a "signing share" and "sub-share".
+        let (signing_share, sub_share) = SecretShare::try_from(
+            self.secret_share.to_bytes().as_slice(),
+        )
+        .and_then(|secret_share| {
+            wamu_core::share_split_reconstruct::split(&secret_share, self.identity_provider)
+        })?;
+        self.secret_share.zeroize();
+
+        self.output = Some(WalletShare {
+            key_share: KeyShare {
+                idx: self.idx,
+                public_key,
+                public_shares,
+            },
+            signing_share,
+            sub_share,
+        });
+        Ok(())
+    }
+}
+
+impl<'a, I: IdentityProvider> StateMachine for AugmentedKeyGen<'a, I> {
+    type MessageBody = AuthedMessage<KeyGenMessageBody>;
+    type Err = Error;
+    type Output = WalletShare;
+
+    fn handle_incoming(&mut self, msg: Msg<Self::MessageBody>) -> Result<(), Self::Err> {
+        // Verifies the identity authentication of the message.
+        identity_auth::verify(&msg, &self.session_hash, self.verified_parties)?;
+
+        let (sender, round) = (msg.sender, msg.body.body.round());
+        if sender == self.idx || msg.receiver.is_some() {
+            return Err(Error::UnexpectedMessage { sender, round });
+        }
+        match msg.body.body {
+            KeyGenMessageBody::Round1 {
+                public_share,
+                proof_commitment,
+                proof_response,
+            } => {
+                if self.public_shares[sender as usize - 1].is_some() {
+                    return Err(Error::DuplicateMessage { sender, round });
+                }
+                // Verifies the proof of knowledge of the secret share.
+                let proof_challenge =
+                    proof_challenge(&self.session_hash, sender, &public_share, &proof_commitment);
+                if ProjectivePoint::GENERATOR * proof_response
+                    != proof_commitment + public_share * proof_challenge
+                {
+                    return Err(Error::InvalidProofOfKnowledge { sender });
+                }
+                self.public_shares[sender as usize - 1] = Some(public_share);
+            }
+        }
+        Ok(())
+    }
+
+    fn message_queue(&mut self) -> &mut Vec<Msg<Self::MessageBody>> {
+        &mut self.message_queue
+    }
+
+    fn wants_to_proceed(&self) -> bool {
+        self.round == 1 && self.public_shares.iter().all(Option::is_some)
+    }
+
+    fn proceed(&mut self) -> Result<(), Self::Err> {
+        if !self.wants_to_proceed() {
+            return Ok(());
+        }
+        self.finalize()?;
+        self.round += 1;
+        Ok(())
+    }
+
+    fn round_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    fn round_timeout_reached(&mut self) -> Self::Err {
+        panic!("no timeout was set")
+    }
+
+    fn is_finished(&self) -> bool {
+        self.output.is_some() || self.is_picked
+    }
+
+    fn pick_output(&mut self) -> Option<Result<Self::Output, Self::Err>> {
+        // Return an error if output was already picked.
+        if self.is_picked {
+            return Some(Err(Error::AlreadyPicked));
+        }
+
+        self.output.take().map(|output| {
+            self.is_picked = true;
+            Ok(output)
+        })
+    }
+
+    fn current_round(&self) -> u16 {
+        self.round
+    }
+
+    fn total_rounds(&self) -> Option<u16> {
+        Some(1)
+    }
+
+    fn party_ind(&self) -> u16 {
+        self.idx
+    }
+
+    fn parties(&self) -> u16 {
+        2
+    }
+}
+
+impl<'a, I: IdentityProvider> Drop for AugmentedKeyGen<'a, I> {
+    fn drop(&mut self) {
+        self.secret_share.zeroize();
+    }
+}
+
+// Returns the challenge of the proof of knowledge of the secret share for a party.
+fn proof_challenge(
+    session_hash: &[u8],
+    idx: u16,
+    public_share: &ProjectivePoint,
+    proof_commitment: &ProjectivePoint,
+) -> Scalar {
+    primitives::hash_to_scalar(
+        PROOF_OF_KNOWLEDGE_TAG,
+        &[
+            session_hash,
+            &idx.to_be_bytes(),
+            &primitives::encode_point(public_share),
+            &primitives::encode_point(proof_commitment),
+        ],
+    )
+}
+
+// Implement `Debug` trait for `AugmentedKeyGen` for test simulations.
+#[cfg(any(test, feature = "dev"))]
+impl<'a, I: IdentityProvider> std::fmt::Debug for AugmentedKeyGen<'a, I> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Augmented Two-Party KeyGen")
+    }
+}
+
+#[cfg(any(test, feature = "dev"))]
+pub mod tests {
+    use super::*;
+    use k256::elliptic_curve::PrimeField;
+    use round_based::dev::Simulation;
+    use wamu_core::test_utils::MockECDSAIdentityProvider;
+
+    pub fn simulate_keygen() -> (Vec<WalletShare>, Vec<MockECDSAIdentityProvider>) {
+        // Creates identity providers for both parties.
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (1..=2)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let session_id = wamu_core::crypto::Random32Bytes::generate().to_be_bytes();
+
+        // Runs key gen simulation.
+        let mut simulation = Simulation::<AugmentedKeyGen<MockECDSAIdentityProvider>>::new();
+        for (i, identity_provider) in identity_providers.iter().enumerate() {
+            simulation.add_party(
+                AugmentedKeyGen::new(identity_provider, &verifying_keys, i as u16 + 1, session_id)
+                    .unwrap(),
+            );
+        }
+        let wallet_shares = simulation.run().unwrap();
+        // Releases borrows of the identity providers.
+        drop(simulation);
+
+        // Verifies that both parties agree on the public key material.
+        assert_eq!(wallet_shares.len(), 2);
+        for (i, wallet_share) in wallet_shares.iter().enumerate() {
+            let key_share = &wallet_share.key_share;
+            assert_eq!(key_share.idx, i as u16 + 1);
+            assert_eq!(key_share.public_key, wallet_shares[0].key_share.public_key);
+            assert_eq!(
+                key_share.public_shares,
+                wallet_shares[0].key_share.public_shares
+            );
+
+            // Verifies that the reconstructed secret share matches the public share.
+            let secret_share = wamu_core::share_split_reconstruct::reconstruct(
+                &wallet_share.signing_share,
+                &wallet_share.sub_share,
+                &identity_providers[i],
+            )
+            .unwrap();
+            let secret_share = Scalar::from_repr(secret_share.to_be_bytes().into()).unwrap();
+            assert_eq!(
+                ProjectivePoint::GENERATOR * secret_share,
+                key_share.public_shares[i]
+            );
+        }
+
+        (wallet_shares, identity_providers)
+    }
+
+    #[test]
+    fn keygen_works() {
+        simulate_keygen();
+    }
+}
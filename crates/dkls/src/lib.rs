@@ -0,0 +1,21 @@
+//! A Rust implementation of [DKLs23](https://eprint.iacr.org/2023/765.pdf)-style two-party (i.e 2-of-2) ECDSA over secp256k1 with augmentations as described by the [Wamu protocol](https://wamu.tech/specification) for computation of threshold signatures by multiple decentralized identities.
+//!
+//! Signing uses OT-based multiplication instead of Paillier encryption, so neither key generation nor signing
+//! requires Paillier/ring-Pedersen setup or range proofs (i.e it's a much cheaper alternative to CGGMP20 for the 2-of-2 case, e.g user device + service).
+
+#![feature(doc_cfg)]
+
+pub use self::errors::Error;
+pub use self::identity_auth::AuthedMessage;
+pub use self::keygen::{AugmentedKeyGen, KeyGenMessage, KeyGenMessageBody, KeyShare, WalletShare};
+pub use self::sign::{AugmentedSigning, SigningMessage, SigningMessageBody};
+
+#[cfg(feature = "dev")]
+#[doc(cfg(feature = "dev"))]
+pub use self::{keygen::tests::simulate_keygen, sign::tests::simulate_sign};
+
+mod errors;
+mod identity_auth;
+mod keygen;
+mod primitives;
+mod sign;
@@ -0,0 +1,240 @@
+//! Hashing, encoding and OT-based multiplication primitives over secp256k1.
+//!
+//! Two-party multiplication (i.e converting multiplicative shares into additive shares) is implemented
+//! as Gilboa multiplication over "Simplest OT" (i.e Chou-Orlandi) base OTs,
+//! with the receiver's input randomly encoded (as in DKLs19) so that a malicious sender's selective failure attacks
+//! learn nothing about the receiver's input.
+//!
+//! Refs:
+//! - <https://eprint.iacr.org/2023/765.pdf> (DKLs23).
+//! - <https://eprint.iacr.org/2019/523.pdf> (DKLs19, Section 5 and Appendix).
+//! - <https://eprint.iacr.org/2015/267.pdf> ("Simplest OT").
+
+use k256::elliptic_curve::ff::Field;
+use k256::elliptic_curve::group::GroupEncoding;
+use k256::elliptic_curve::ops::Reduce;
+use k256::{CompressedPoint, ProjectivePoint, Scalar, U256};
+use sha2::{Digest, Sha256};
+
+/// Number of base OTs per multiplication (i.e the length of the receiver's input encoding).
+///
+/// NOTE: 256 bits for the binary encoding of the input and 256 random bits for statistical hiding
+/// (i.e `ξ = κ + 2s` with `κ = 256` and `s = 128`).
+pub(crate) const ENCODING_LEN: usize = 512;
+/// Number of bits of the binary part of the encoding.
+const BINARY_LEN: usize = 256;
+
+/// Gadget vector tag.
+const GADGET_TAG: &str = "wamu-dkls/gadget";
+/// OT pad tag.
+const OT_PAD_TAG: &str = "wamu-dkls/ot-pad";
+
+/// Returns the tagged hash of the data (i.e `SHA256(SHA256(tag) || SHA256(tag) || data)`).
+pub(crate) fn tagged_hash(tag: &str, data: &[&[u8]]) -> [u8; 32] {
+    let tag_hash = Sha256::digest(tag.as_bytes());
+    data.iter()
+        .fold(
+            Sha256::new().chain_update(tag_hash).chain_update(tag_hash),
+            |hasher, item| hasher.chain_update(item),
+        )
+        .finalize()
+        .into()
+}
+
+/// Returns the tagged hash of the data reduced to a scalar.
+pub(crate) fn hash_to_scalar(tag: &str, data: &[&[u8]]) -> Scalar {
+    <Scalar as Reduce<U256>>::reduce_bytes(&tagged_hash(tag, data).into())
+}
+
+/// Returns the compressed SEC1 encoding of a point.
+pub(crate) fn encode_point(point: &ProjectivePoint) -> Vec<u8> {
+    point.to_bytes().to_vec()
+}
+
+/// Returns the point for a compressed SEC1 encoding,
+/// or `None` if the encoding is invalid or non-canonical, or if the point is the identity.
+pub(crate) fn decode_point(bytes: &[u8]) -> Option<ProjectivePoint> {
+    if bytes.len() != 33 {
+        return None;
+    }
+    Option::<ProjectivePoint>::from(ProjectivePoint::from_bytes(
+        &CompressedPoint::clone_from_slice(bytes),
+    ))
+    .filter(|point| *point != ProjectivePoint::IDENTITY && encode_point(point) == bytes)
+}
+
+/// Returns the gadget vector for a session (i.e powers of 2 for the binary part, and random scalars for the random part).
+pub(crate) fn gadget(session: &[u8]) -> Vec<Scalar> {
+    let mut power_of_two = Scalar::ONE;
+    (0..ENCODING_LEN)
+        .map(|idx| {
+            if idx < BINARY_LEN {
+                let value = power_of_two;
+                power_of_two = power_of_two.double();
+                value
+            } else {
+                hash_to_scalar(GADGET_TAG, &[session, &(idx as u64).to_be_bytes()])
+            }
+        })
+        .collect()
+}
+
+/// Returns a random encoding of the input (i.e bits whose inner product with the gadget vector is the input).
+pub(crate) fn encode(input: &Scalar, gadget: &[Scalar]) -> Vec<bool> {
+    let mut rng = rand::thread_rng();
+    let random_bits: Vec<bool> = (BINARY_LEN..ENCODING_LEN)
+        .map(|_| rand::Rng::gen(&mut rng))
+        .collect();
+    let residual = random_bits
+        .iter()
+        .zip(&gadget[BINARY_LEN..])
+        .filter(|(bit, _)| **bit)
+        .fold(*input, |acc, (_, element)| acc - element);
+    let residual_bytes = residual.to_bytes();
+    (0..BINARY_LEN)
+        .map(|idx| (residual_bytes[31 - idx / 8] >> (idx % 8)) & 1 == 1)
+        .chain(random_bits)
+        .collect()
+}
+
+/// Returns the OT pad for a session, OT index and shared point.
+fn ot_pad(session: &[u8], idx: usize, shared_point: &ProjectivePoint) -> [Scalar; 2] {
+    [0u8, 1].map(|element| {
+        hash_to_scalar(
+            OT_PAD_TAG,
+            &[
+                session,
+                &(idx as u64).to_be_bytes(),
+                &encode_point(shared_point),
+                &[element],
+            ],
+        )
+    })
+}
+
+/// Given the sender's OT setup point (i.e `A = a * G`) and choice bits,
+/// returns the receiver's OT choice points (i.e `B = c * G + b * A`) and the pads for the chosen messages.
+pub(crate) fn ot_receive(
+    session: &[u8],
+    sender_setup: &ProjectivePoint,
+    choice_bits: &[bool],
+) -> (Vec<ProjectivePoint>, Vec<[Scalar; 2]>) {
+    let mut rng = rand::thread_rng();
+    choice_bits
+        .iter()
+        .enumerate()
+        .map(|(idx, bit)| {
+            let secret = Scalar::random(&mut rng);
+            let mut choice_point = ProjectivePoint::GENERATOR * secret;
+            if *bit {
+                choice_point += sender_setup;
+            }
+            (
+                choice_point,
+                ot_pad(session, idx, &(sender_setup * &secret)),
+            )
+        })
+        .unzip()
+}
+
+/// Given the sender's OT setup secret (i.e `a`), the receiver's OT choice points and the sender's input,
+/// returns the OT corrections for the receiver and the sender's additive share of the product.
+pub(crate) fn ot_send(
+    session: &[u8],
+    setup_secret: &Scalar,
+    choice_points: &[ProjectivePoint],
+    input: &[Scalar; 2],
+    gadget: &[Scalar],
+) -> (Vec<[Scalar; 2]>, [Scalar; 2]) {
+    let sender_setup = ProjectivePoint::GENERATOR * setup_secret;
+    let mut share = [Scalar::ZERO; 2];
+    let corrections = choice_points
+        .iter()
+        .zip(gadget)
+        .enumerate()
+        .map(|(idx, (choice_point, element))| {
+            let pad_0 = ot_pad(session, idx, &(choice_point * setup_secret));
+            let pad_1 = ot_pad(
+                session,
+                idx,
+                &((choice_point - &sender_setup) * setup_secret),
+            );
+            // The receiver learns `pad_0` if its choice bit is 0 or `pad_0 + input` otherwise.
+            [0, 1].map(|i| {
+                share[i] -= pad_0[i] * element;
+                pad_0[i] + input[i] - pad_1[i]
+            })
+        })
+        .collect();
+    (corrections, share)
+}
+
+/// Given the receiver's choice bits, pads for the chosen messages and the sender's OT corrections,
+/// returns the receiver's additive share of the product.
+pub(crate) fn ot_finalize(
+    choice_bits: &[bool],
+    pads: &[[Scalar; 2]],
+    corrections: &[[Scalar; 2]],
+    gadget: &[Scalar],
+) -> [Scalar; 2] {
+    choice_bits
+        .iter()
+        .zip(pads)
+        .zip(corrections)
+        .zip(gadget)
+        .fold(
+            [Scalar::ZERO; 2],
+            |mut share, (((bit, pad), correction), element)| {
+                for i in 0..2 {
+                    let value = if *bit { pad[i] + correction[i] } else { pad[i] };
+                    share[i] += value * element;
+                }
+                share
+            },
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ot_multiplication_works() {
+        let session = b"session";
+        let gadget = gadget(session);
+        let mut rng = rand::thread_rng();
+
+        // Verifies that the encoding is correct.
+        let receiver_input = Scalar::random(&mut rng);
+        let choice_bits = encode(&receiver_input, &gadget);
+        assert_eq!(choice_bits.len(), ENCODING_LEN);
+        assert_eq!(
+            choice_bits
+                .iter()
+                .zip(&gadget)
+                .filter(|(bit, _)| **bit)
+                .fold(Scalar::ZERO, |acc, (_, element)| acc + element),
+            receiver_input
+        );
+
+        // Verifies that the shares of the sender and receiver add up to the products.
+        let setup_secret = Scalar::random(&mut rng);
+        let sender_setup = ProjectivePoint::GENERATOR * setup_secret;
+        let sender_input = [Scalar::random(&mut rng), Scalar::random(&mut rng)];
+        let (choice_points, pads) = ot_receive(session, &sender_setup, &choice_bits);
+        let (corrections, sender_share) = ot_send(
+            session,
+            &setup_secret,
+            &choice_points,
+            &sender_input,
+            &gadget,
+        );
+        let receiver_share = ot_finalize(&choice_bits, &pads, &corrections, &gadget);
+        for i in 0..2 {
+            assert_eq!(
+                sender_share[i] + receiver_share[i],
+                receiver_input * sender_input[i]
+            );
+        }
+    }
+}
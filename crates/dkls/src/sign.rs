@@ -0,0 +1,629 @@
+//! Augmented two-party signing implementation.
+//!
+//! Implements DKLs23-style two-party ECDSA signing (i.e without Paillier encryption or range proofs),
+//! with identity authenticated rounds and the "secret share" reconstructed from the "signing share" and "sub-share".
+//!
+//! Each party `i` samples an additive nonce share `r_i` and a mask `φ_i`, so that `R = (r_1 + r_2) * G`,
+//! and OT-based multiplication converts the cross terms of `φ * k` and `φ * x` into additive shares
+//! (where `φ = φ_1 + φ_2`, `k = r_1 + r_2` and `x = x_1 + x_2`).
+//! Both parties then reveal `w_i = m * φ_i + r * v_i` and `u_i` (their shares of `φ * (m + r * x)` and `φ * k`)
+//! and compute `s = (w_1 + w_2) / (u_1 + u_2)`.
+//!
+//! Refs:
+//! - <https://eprint.iacr.org/2023/765.pdf> (DKLs23, Protocol 3.6).
+//! - <https://wamu.tech/specification#signing>.
+
+use k256::ecdsa::signature::hazmat::PrehashVerifier;
+use k256::ecdsa::{RecoveryId, Signature};
+use k256::elliptic_curve::ff::Field;
+use k256::elliptic_curve::ops::Reduce;
+use k256::elliptic_curve::point::AffineCoordinates;
+use k256::elliptic_curve::PrimeField;
+use k256::{ProjectivePoint, Scalar, U256};
+use round_based::{Msg, StateMachine};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+use wamu_core::cbor::CanonicalCbor;
+use wamu_core::crypto::VerifyingKey;
+use wamu_core::IdentityProvider;
+use zeroize::Zeroize;
+
+use crate::errors::Error;
+use crate::identity_auth::{AuthedMessage, RoundMessage};
+use crate::keygen::{KeyShare, WalletShare};
+use crate::{identity_auth, primitives};
+
+const SIGNING_SESSION_TAG: &str = "wamu-dkls/sign";
+const NONCE_COMMITMENT_TAG: &str = "wamu-dkls/sign/nonce-commitment";
+
+/// A signing message body.
+#[derive(Debug, Clone)]
+pub enum SigningMessageBody {
+    /// Round 1: the OT setup point (i.e as the OT sender) and a commitment to the nonce share.
+    Round1 {
+        /// Compressed SEC1 encoding of the OT setup point (i.e `A = a * G`).
+        ot_setup: Vec<u8>,
+        /// Commitment to the nonce share (i.e `R_i`).
+        nonce_commitment: [u8; 32],
+    },
+    /// Round 2: OT choice points (i.e as the OT receiver) for the encoded mask and the nonce share.
+    Round2 {
+        /// OT choice points (i.e `B = c * G + b * A`).
+        ot_choices: Vec<ProjectivePoint>,
+        /// Nonce share (i.e `R_i`).
+        nonce: ProjectivePoint,
+    },
+    /// Round 3: OT corrections (i.e as the OT sender) for the nonce share and secret share.
+    Round3 {
+        /// OT corrections.
+        ot_corrections: Vec<[Scalar; 2]>,
+    },
+    /// Round 4: shares of `φ * (m + r * x)` and `φ * k`.
+    Round4 {
+        /// Share of `φ * (m + r * x)`.
+        w: Scalar,
+        /// Share of `φ * k`.
+        u: Scalar,
+    },
+}
+
+impl RoundMessage for SigningMessageBody {
+    fn round(&self) -> u16 {
+        match self {
+            Self::Round1 { .. } => 1,
+            Self::Round2 { .. } => 2,
+            Self::Round3 { .. } => 3,
+            Self::Round4 { .. } => 4,
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        match self {
+            Self::Round1 {
+                ot_setup,
+                nonce_commitment,
+            } => [ot_setup.as_slice(), nonce_commitment.as_slice()].concat(),
+            Self::Round2 { ot_choices, nonce } => ot_choices
+                .iter()
+                .chain(std::iter::once(nonce))
+                .flat_map(primitives::encode_point)
+                .collect(),
+            Self::Round3 { ot_corrections } => ot_corrections
+                .iter()
+                .flatten()
+                .flat_map(|it| it.to_bytes())
+                .collect(),
+            Self::Round4 { w, u } => [w.to_bytes(), u.to_bytes()].concat(),
+        }
+    }
+}
+
+/// An identity authenticated signing message.
+pub type SigningMessage = Msg<AuthedMessage<SigningMessageBody>>;
+
+/// A [StateMachine](StateMachine) that implements augmented two-party signing.
+pub struct AugmentedSigning<'a, I: IdentityProvider> {
+    /// The decentralized identity provider of the party.
+    identity_provider: &'a I,
+    /// Verifying keys for both parties (in party index order).
+    verified_parties: &'a [VerifyingKey],
+    /// The public key material of the party.
+    key_share: &'a KeyShare,
+    /// The message digest to sign.
+    message_digest: &'a [u8; 32],
+    /// Hash of the session parameters (for binding messages and OTs to the session).
+    session_hash: Vec<u8>,
+    /// Additive secret share of the party (i.e `x_i`).
+    secret_share: Scalar,
+    /// Nonce share of the party (i.e `r_i`).
+    nonce: Scalar,
+    /// Mask of the party (i.e `φ_i`).
+    mask: Scalar,
+    /// OT setup secret of the party (i.e `a`).
+    ot_setup_secret: Scalar,
+    /// OT choice bits of the party (i.e the encoded mask).
+    ot_choice_bits: Vec<bool>,
+    /// OT pads for the chosen messages.
+    ot_pads: Vec<[Scalar; 2]>,
+    /// The party's shares of the products as the OT sender.
+    sender_shares: [Scalar; 2],
+    /// The aggregate nonce (i.e `R`).
+    group_nonce: Option<ProjectivePoint>,
+    /// The party's round 4 shares (i.e `w_i` and `u_i`).
+    own_shares: Option<(Scalar, Scalar)>,
+    /// Messages received from the other party indexed by round.
+    peer_messages: [Option<SigningMessageBody>; 4],
+    /// Current round.
+    round: u16,
+    /// Outgoing message queue.
+    message_queue: Vec<SigningMessage>,
+    /// Protocol output.
+    output: Option<(Signature, RecoveryId)>,
+    /// Whether or not the output was already picked.
+    is_picked: bool,
+}
+
+impl<'a, I: IdentityProvider> AugmentedSigning<'a, I> {
+    /// Initializes party for the augmented two-party signing protocol given its wallet share (i.e from key generation),
+    /// identity provider, verifying keys for both parties (in party index order), the message digest to sign
+    /// and a random session identifier shared by both parties.
+    pub fn new(
+        wallet_share: &'a WalletShare,
+        identity_provider: &'a I,
+        verified_parties: &'a [VerifyingKey],
+        message_digest: &'a [u8; 32],
+        session_id: [u8; 32],
+    ) -> Result<Self, Error> {
+        let key_share = &wallet_share.key_share;
+        let idx = key_share.idx;
+        if !(1..=2).contains(&idx)
+            || verified_parties.len() != 2
+            || verified_parties[idx as usize - 1] != identity_provider.verifying_key()
+        {
+            return Err(Error::InvalidConfig);
+        }
+
+        // Reconstructs the secret share.
+        let secret_share = wamu_core::share_split_reconstruct::reconstruct(
+            &wallet_share.signing_share,
+            &wallet_share.sub_share,
+            identity_provider,
+        )?;
+        let secret_share = Option::from(Scalar::from_repr(secret_share.to_be_bytes().into()))
+            .ok_or(wamu_core::Error::Encoding)?;
+
+        // Computes the session hash.
+        let session_hash = verified_parties
+            .iter()
+            .fold(
+                Sha256::new()
+                    .chain_update(SIGNING_SESSION_TAG)
+                    .chain_update(session_id)
+                    .chain_update(primitives::encode_point(&key_share.public_key))
+                    .chain_update(message_digest),
+                |hasher, verifying_key| hasher.chain_update(verifying_key.to_cbor()),
+            )
+            .finalize()
+            .to_vec();
+
+        // Samples the nonce share, mask and OT setup secret, and commits to the nonce share.
+        let mut rng = rand::thread_rng();
+        let nonce = Scalar::random(&mut rng);
+        let mask = Scalar::random(&mut rng);
+        let ot_setup_secret = Scalar::random(&mut rng);
+        let message_queue = vec![identity_auth::authenticate(
+            SigningMessageBody::Round1 {
+                ot_setup: primitives::encode_point(&(ProjectivePoint::GENERATOR * ot_setup_secret)),
+                nonce_commitment: nonce_commitment(
+                    &session_hash,
+                    idx,
+                    &(ProjectivePoint::GENERATOR * nonce),
+                ),
+            },
+            &session_hash,
+            idx,
+            Some(key_share.peer_idx()),
+            identity_provider,
+        )];
+
+        Ok(Self {
+            identity_provider,
+            verified_parties,
+            key_share,
+            message_digest,
+            session_hash,
+            secret_share,
+            nonce,
+            mask,
+            ot_setup_secret,
+            ot_choice_bits: Vec::new(),
+            ot_pads: Vec::new(),
+            sender_shares: [Scalar::ZERO; 2],
+            group_nonce: None,
+            own_shares: None,
+            peer_messages: [None, None, None, None],
+            round: 1,
+            message_queue,
+            output: None,
+            is_picked: false,
+        })
+    }
+
+    // Returns the OT session for a receiver (i.e for domain separation of the OTs in each direction).
+    fn ot_session(&self, receiver: u16) -> Vec<u8> {
+        [self.session_hash.as_slice(), &receiver.to_be_bytes()].concat()
+    }
+
+    // Queues an identity authenticated message for the other party.
+    fn send(&mut self, body: SigningMessageBody) {
+        self.message_queue.push(identity_auth::authenticate(
+            body,
+            &self.session_hash,
+            self.key_share.idx,
+            Some(self.key_share.peer_idx()),
+            self.identity_provider,
+        ));
+    }
+
+    // Sends the OT choice points for the encoded mask and reveals the nonce share.
+    fn send_ot_choices(&mut self) {
+        let Some(SigningMessageBody::Round1 { ot_setup, .. }) = &self.peer_messages[0] else {
+            unreachable!("round 1 message is received before proceeding");
+        };
+        let ot_setup =
+            primitives::decode_point(ot_setup).expect("OT setup point is verified on receipt");
+        let session = self.ot_session(self.key_share.idx);
+        self.ot_choice_bits = primitives::encode(&self.mask, &primitives::gadget(&session));
+        let (ot_choices, ot_pads) =
+            primitives::ot_receive(&session, &ot_setup, &self.ot_choice_bits);
+        self.ot_pads = ot_pads;
+        self.send(SigningMessageBody::Round2 {
+            ot_choices,
+            nonce: ProjectivePoint::GENERATOR * self.nonce,
+        });
+    }
+
+    // Verifies the other party's nonce share and sends the OT corrections for the nonce share and secret share.
+    fn send_ot_corrections(&mut self) -> Result<(), Error> {
+        let peer_idx = self.key_share.peer_idx();
+        let (
+            Some(SigningMessageBody::Round1 {
+                nonce_commitment: peer_nonce_commitment,
+                ..
+            }),
+            Some(SigningMessageBody::Round2 {
+                ot_choices,
+                nonce: peer_nonce,
+            }),
+        ) = (&self.peer_messages[0], &self.peer_messages[1])
+        else {
+            unreachable!("round 1 and 2 messages are received before proceeding");
+        };
+        if nonce_commitment(&self.session_hash, peer_idx, peer_nonce) != *peer_nonce_commitment {
+            return Err(Error::InvalidNonceCommitment { sender: peer_idx });
+        }
+        let group_nonce = ProjectivePoint::GENERATOR * self.nonce + peer_nonce;
+        if group_nonce == ProjectivePoint::IDENTITY {
+            return Err(Error::InvalidNonceCommitment { sender: peer_idx });
+        }
+
+        let session = self.ot_session(peer_idx);
+        let (ot_corrections, sender_shares) = primitives::ot_send(
+            &session,
+            &self.ot_setup_secret,
+            ot_choices,
+            &[self.nonce, self.secret_share],
+            &primitives::gadget(&session),
+        );
+        self.ot_setup_secret.zeroize();
+        self.sender_shares = sender_shares;
+        self.group_nonce = Some(group_nonce);
+        self.send(SigningMessageBody::Round3 { ot_corrections });
+        Ok(())
+    }
+
+    // Computes and reveals the party's shares of `φ * (m + r * x)` and `φ * k`.
+    fn send_shares(&mut self) {
+        let Some(SigningMessageBody::Round3 { ot_corrections }) = &self.peer_messages[2] else {
+            unreachable!("round 3 message is received before proceeding");
+        };
+        let session = self.ot_session(self.key_share.idx);
+        let receiver_shares = primitives::ot_finalize(
+            &self.ot_choice_bits,
+            &self.ot_pads,
+            ot_corrections,
+            &primitives::gadget(&session),
+        );
+        // Shares of `φ * k` and `φ * x`.
+        let u = self.mask * self.nonce + receiver_shares[0] + self.sender_shares[0];
+        let v = self.mask * self.secret_share + receiver_shares[1] + self.sender_shares[1];
+        let w = self.message_scalar() * self.mask + self.r() * v;
+        self.zeroize_secrets();
+
+        self.own_shares = Some((w, u));
+        self.send(SigningMessageBody::Round4 { w, u });
+    }
+
+    // Computes and verifies the signature.
+    fn finalize(&mut self) -> Result<(), Error> {
+        let (
+            Some((own_w, own_u)),
+            Some(SigningMessageBody::Round4 {
+                w: peer_w,
+                u: peer_u,
+            }),
+        ) = (self.own_shares, &self.peer_messages[3])
+        else {
+            unreachable!("round 4 shares are computed and received before proceeding");
+        };
+        let u_inverse =
+            Option::<Scalar>::from((own_u + peer_u).invert()).ok_or(Error::InvalidSignature)?;
+        let s = (own_w + peer_w) * u_inverse;
+        let signature = Signature::from_scalars(self.r().to_bytes(), s.to_bytes())
+            .map_err(|_| Error::InvalidSignature)?;
+        // Normalizes the signature to "low-s" form.
+        let signature = signature.normalize_s().unwrap_or(signature);
+
+        let verifying_key = self.key_share.verifying_key();
+        verifying_key
+            .verify_prehash(self.message_digest, &signature)
+            .map_err(|_| Error::InvalidSignature)?;
+        let recovery_id = RecoveryId::trial_recovery_from_prehash(
+            &verifying_key,
+            self.message_digest,
+            &signature,
+        )
+        .map_err(|_| Error::InvalidSignature)?;
+
+        self.output = Some((signature, recovery_id));
+        Ok(())
+    }
+
+    // Returns the x coordinate of the aggregate nonce reduced to a scalar (i.e r).
+    fn r(&self) -> Scalar {
+        let group_nonce = self
+            .group_nonce
+            .expect("aggregate nonce is computed in round 2");
+        <Scalar as Reduce<U256>>::reduce_bytes(&group_nonce.to_affine().x())
+    }
+
+    // Returns the message digest reduced to a scalar (i.e m).
+    fn message_scalar(&self) -> Scalar {
+        <Scalar as Reduce<U256>>::reduce_bytes(&(*self.message_digest).into())
+    }
+
+    // Zeroizes all secrets of the party.
+    fn zeroize_secrets(&mut self) {
+        self.secret_share.zeroize();
+        self.nonce.zeroize();
+        self.mask.zeroize();
+        self.ot_setup_secret.zeroize();
+        self.ot_choice_bits.iter_mut().for_each(|bit| *bit = false);
+        self.ot_pads.zeroize();
+        self.sender_shares.zeroize();
+    }
+}
+
+impl<'a, I: IdentityProvider> StateMachine for AugmentedSigning<'a, I> {
+    type MessageBody = AuthedMessage<SigningMessageBody>;
+    type Err = Error;
+    type Output = (Signature, RecoveryId);
+
+    fn handle_incoming(&mut self, msg: Msg<Self::MessageBody>) -> Result<(), Self::Err> {
+        // Verifies the identity authentication of the message.
+        identity_auth::verify(&msg, &self.session_hash, self.verified_parties)?;
+
+        let (sender, round) = (msg.sender, msg.body.body.round());
+        if sender != self.key_share.peer_idx() || msg.receiver != Some(self.key_share.idx) {
+            return Err(Error::UnexpectedMessage { sender, round });
+        }
+        // The OT setup point must be a canonically encoded non-identity point,
+        // because the receiver's OT pads are derived from it.
+        let is_malformed = match &msg.body.body {
+            SigningMessageBody::Round1 { ot_setup, .. } => {
+                primitives::decode_point(ot_setup).is_none()
+            }
+            SigningMessageBody::Round2 { ot_choices, .. } => {
+                ot_choices.len() != primitives::ENCODING_LEN
+            }
+            SigningMessageBody::Round3 { ot_corrections } => {
+                ot_corrections.len() != primitives::ENCODING_LEN
+            }
+            _ => false,
+        };
+        if is_malformed {
+            return Err(Error::UnexpectedMessage { sender, round });
+        }
+        let peer_message = &mut self.peer_messages[round as usize - 1];
+        if peer_message.is_some() {
+            return Err(Error::DuplicateMessage { sender, round });
+        }
+        *peer_message = Some(msg.body.body);
+        Ok(())
+    }
+
+    fn message_queue(&mut self) -> &mut Vec<Msg<Self::MessageBody>> {
+        &mut self.message_queue
+    }
+
+    fn wants_to_proceed(&self) -> bool {
+        (1..=4).contains(&self.round) && self.peer_messages[self.round as usize - 1].is_some()
+    }
+
+    fn proceed(&mut self) -> Result<(), Self::Err> {
+        if !self.wants_to_proceed() {
+            return Ok(());
+        }
+        match self.round {
+            1 => self.send_ot_choices(),
+            2 => self.send_ot_corrections()?,
+            3 => self.send_shares(),
+            _ => self.finalize()?,
+        }
+        self.round += 1;
+        Ok(())
+    }
+
+    fn round_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    fn round_timeout_reached(&mut self) -> Self::Err {
+        panic!("no timeout was set")
+    }
+
+    fn is_finished(&self) -> bool {
+        self.output.is_some() || self.is_picked
+    }
+
+    fn pick_output(&mut self) -> Option<Result<Self::Output, Self::Err>> {
+        // Return an error if output was already picked.
+        if self.is_picked {
+            return Some(Err(Error::AlreadyPicked));
+        }
+
+        self.output.take().map(|output| {
+            self.is_picked = true;
+            Ok(output)
+        })
+    }
+
+    fn current_round(&self) -> u16 {
+        self.round
+    }
+
+    fn total_rounds(&self) -> Option<u16> {
+        Some(4)
+    }
+
+    fn party_ind(&self) -> u16 {
+        self.key_share.idx
+    }
+
+    fn parties(&self) -> u16 {
+        2
+    }
+}
+
+impl<'a, I: IdentityProvider> Drop for AugmentedSigning<'a, I> {
+    fn drop(&mut self) {
+        self.zeroize_secrets();
+    }
+}
+
+// Returns the commitment to a party's nonce share.
+fn nonce_commitment(session_hash: &[u8], idx: u16, nonce: &ProjectivePoint) -> [u8; 32] {
+    primitives::tagged_hash(
+        NONCE_COMMITMENT_TAG,
+        &[
+            session_hash,
+            &idx.to_be_bytes(),
+            &primitives::encode_point(nonce),
+        ],
+    )
+}
+
+// Implement `Debug` trait for `AugmentedSigning` for test simulations.
+#[cfg(any(test, feature = "dev"))]
+impl<'a, I: IdentityProvider> std::fmt::Debug for AugmentedSigning<'a, I> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Augmented Two-Party Signing")
+    }
+}
+
+#[cfg(any(test, feature = "dev"))]
+pub mod tests {
+    use super::*;
+    use round_based::dev::Simulation;
+    use wamu_core::test_utils::MockECDSAIdentityProvider;
+
+    pub fn simulate_sign(
+        wallet_shares: &[WalletShare],
+        identity_providers: &[MockECDSAIdentityProvider],
+        message_digest: &[u8; 32],
+    ) -> (Signature, RecoveryId) {
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let session_id = wamu_core::crypto::Random32Bytes::generate().to_be_bytes();
+
+        // Runs signing simulation.
+        let mut simulation = Simulation::<AugmentedSigning<MockECDSAIdentityProvider>>::new();
+        for (wallet_share, identity_provider) in wallet_shares.iter().zip(identity_providers) {
+            simulation.add_party(
+                AugmentedSigning::new(
+                    wallet_share,
+                    identity_provider,
+                    &verifying_keys,
+                    message_digest,
+                    session_id,
+                )
+                .unwrap(),
+            );
+        }
+        let signatures = simulation.run().unwrap();
+
+        // Verifies that both parties output the same signature.
+        assert_eq!(signatures.len(), 2);
+        assert_eq!(signatures[0], signatures[1]);
+
+        signatures[0]
+    }
+
+    #[test]
+    fn sign_works() {
+        let (wallet_shares, identity_providers) = crate::keygen::tests::simulate_keygen();
+        let verifying_key = wallet_shares[0].key_share.verifying_key();
+
+        for message in [b"Hello Wamu!".as_slice(), b"Hello DKLs23!".as_slice()] {
+            let message_digest: [u8; 32] = Sha256::digest(message).into();
+            let (signature, recovery_id) =
+                simulate_sign(&wallet_shares, &identity_providers, &message_digest);
+
+            // Verifies the signature and public key recovery with a standard ECDSA implementation.
+            assert!(signature.normalize_s().is_none());
+            assert!(verifying_key
+                .verify_prehash(&message_digest, &signature)
+                .is_ok());
+            assert_eq!(
+                k256::ecdsa::VerifyingKey::recover_from_prehash(
+                    &message_digest,
+                    &signature,
+                    recovery_id
+                )
+                .unwrap(),
+                verifying_key
+            );
+        }
+    }
+
+    #[test]
+    fn sign_rejects_invalid_ot_setup_points() {
+        let (wallet_shares, identity_providers) = crate::keygen::tests::simulate_keygen();
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let session_id = wamu_core::crypto::Random32Bytes::generate().to_be_bytes();
+        let message_digest: [u8; 32] = Sha256::digest(b"Hello Wamu!").into();
+        let mut party = AugmentedSigning::new(
+            &wallet_shares[0],
+            &identity_providers[0],
+            &verifying_keys,
+            &message_digest,
+            session_id,
+        )
+        .unwrap();
+        let (sender, receiver) = (party.key_share.peer_idx(), party.key_share.idx);
+
+        // The identity point and non-canonical encodings (i.e an x coordinate that's not reduced modulo the field prime)
+        // are rejected before any OT pads are derived from them.
+        let field_modulus = [[0xFF; 27].as_slice(), &[0xFE, 0xFF, 0xFF, 0xFC, 0x2F]].concat();
+        for ot_setup in [
+            primitives::encode_point(&ProjectivePoint::IDENTITY),
+            [[0x02].as_slice(), &field_modulus].concat(),
+            [
+                [0x04].as_slice(),
+                &primitives::encode_point(&ProjectivePoint::GENERATOR)[1..],
+            ]
+            .concat(),
+        ] {
+            let msg = identity_auth::authenticate(
+                SigningMessageBody::Round1 {
+                    ot_setup,
+                    nonce_commitment: [0; 32],
+                },
+                &party.session_hash,
+                sender,
+                Some(receiver),
+                &identity_providers[1],
+            );
+            assert!(matches!(
+                party.handle_incoming(msg),
+                Err(Error::UnexpectedMessage { round: 1, .. })
+            ));
+        }
+        assert!(!party.wants_to_proceed());
+    }
+}
@@ -0,0 +1,142 @@
+//! Types, abstractions and utilities for FROST ciphersuites (i.e the group, hash functions and signature encoding).
+//!
+//! Ref: <https://datatracker.ietf.org/doc/html/rfc9591#name-ciphersuites>.
+
+use k256::elliptic_curve::ff::PrimeField;
+use k256::elliptic_curve::group::{Group, GroupEncoding};
+use k256::{ProjectivePoint, Scalar};
+use std::fmt::Debug;
+use wamu_core::SecretShare;
+use zeroize::Zeroize;
+
+use crate::errors::Error;
+use crate::primitives;
+use crate::sign::Tweak;
+
+/// A FROST ciphersuite.
+pub trait Ciphersuite: Debug + Clone + Copy + PartialEq + Eq + 'static {
+    /// The scalar field.
+    type Scalar: PrimeField + Zeroize;
+    /// The group.
+    type Point: Group<Scalar = Self::Scalar> + GroupEncoding;
+    /// The signature produced by signing.
+    type Signature: Debug + Clone + PartialEq;
+
+    /// A unique identifier for the ciphersuite (for domain separation).
+    const ID: &'static str;
+
+    /// Returns the hash of the tagged data reduced to a scalar.
+    fn hash_to_scalar(tag: &str, data: &[&[u8]]) -> Self::Scalar;
+
+    /// Given a group public key and a tweak, returns the output public key,
+    /// or an error if the tweak isn't supported by the ciphersuite.
+    fn output_key(public_key: &Self::Point, tweak: &Tweak) -> Result<OutputKey<Self>, Error>;
+
+    /// Returns the factor that the nonces must be multiplied by for the aggregate nonce commitment.
+    fn nonce_factor(group_commitment: &Self::Point) -> Self::Scalar;
+
+    /// Returns the signature challenge for an aggregate nonce commitment, output public key and message.
+    fn challenge(
+        group_commitment: &Self::Point,
+        output_key: &Self::Point,
+        message: &[u8],
+    ) -> Self::Scalar;
+
+    /// Returns the signature for an aggregate nonce commitment and response
+    /// if it's a valid signature of the message by the output public key.
+    fn signature(
+        group_commitment: &Self::Point,
+        response: &Self::Scalar,
+        output_key: &Self::Point,
+        message: &[u8],
+    ) -> Result<Self::Signature, Error>;
+
+    /// Converts a scalar into a Wamu "secret share".
+    fn to_secret_share(scalar: &Self::Scalar) -> Result<SecretShare, wamu_core::Error>;
+
+    /// Converts a Wamu "secret share" into a scalar.
+    fn from_secret_share(secret_share: &SecretShare) -> Result<Self::Scalar, wamu_core::Error>;
+}
+
+/// An output public key and the factors for computing signature shares for it
+/// (i.e `Q = key_factor * x * G + factored_tweak * G` where `x * G` is the group public key).
+#[derive(Debug, Clone, Copy)]
+pub struct OutputKey<C: Ciphersuite> {
+    /// The output public key.
+    pub point: C::Point,
+    /// The factor that the secret key must be multiplied by.
+    pub key_factor: C::Scalar,
+    /// The tweak multiplied by its factor.
+    pub factored_tweak: C::Scalar,
+}
+
+/// FROST over secp256k1 producing BIP-340 (i.e Taproot) Schnorr signatures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Secp256k1;
+
+impl Ciphersuite for Secp256k1 {
+    type Scalar = Scalar;
+    type Point = ProjectivePoint;
+    type Signature = k256::schnorr::Signature;
+
+    const ID: &'static str = "secp256k1-bip340";
+
+    fn hash_to_scalar(tag: &str, data: &[&[u8]]) -> Scalar {
+        primitives::hash_to_scalar(tag, data)
+    }
+
+    fn output_key(public_key: &ProjectivePoint, tweak: &Tweak) -> Result<OutputKey<Self>, Error> {
+        let tweak = match tweak {
+            Tweak::None => None,
+            Tweak::Taproot { merkle_root } => {
+                Some(primitives::taproot_tweak(public_key, merkle_root.as_ref()))
+            }
+        };
+        let (point, key_factor, tweak_factor) = primitives::output_key(public_key, tweak.as_ref());
+        Ok(OutputKey {
+            point,
+            key_factor,
+            factored_tweak: tweak.map_or(Scalar::ZERO, |tweak| tweak * tweak_factor),
+        })
+    }
+
+    fn nonce_factor(group_commitment: &ProjectivePoint) -> Scalar {
+        // BIP-340 uses the even y representation of the nonce commitment.
+        primitives::even_y_factor(group_commitment)
+    }
+
+    fn challenge(
+        group_commitment: &ProjectivePoint,
+        output_key: &ProjectivePoint,
+        message: &[u8],
+    ) -> Scalar {
+        primitives::challenge(group_commitment, output_key, message)
+    }
+
+    fn signature(
+        group_commitment: &ProjectivePoint,
+        response: &Scalar,
+        output_key: &ProjectivePoint,
+        message: &[u8],
+    ) -> Result<k256::schnorr::Signature, Error> {
+        let signature_bytes = [
+            primitives::x_only(group_commitment).as_slice(),
+            response.to_bytes().as_slice(),
+        ]
+        .concat();
+        let signature = k256::schnorr::Signature::try_from(signature_bytes.as_slice())
+            .map_err(|_| Error::InvalidSignature)?;
+        k256::schnorr::VerifyingKey::from_bytes(&primitives::x_only(output_key))
+            .and_then(|verifying_key| verifying_key.verify_raw(message, &signature))
+            .map_err(|_| Error::InvalidSignature)?;
+        Ok(signature)
+    }
+
+    fn to_secret_share(scalar: &Scalar) -> Result<SecretShare, wamu_core::Error> {
+        primitives::to_secret_share(scalar)
+    }
+
+    fn from_secret_share(secret_share: &SecretShare) -> Result<Scalar, wamu_core::Error> {
+        primitives::from_secret_share(secret_share)
+    }
+}
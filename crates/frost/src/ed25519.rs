@@ -0,0 +1,136 @@
+//! FROST over Ed25519 producing RFC 8032 (i.e Ed25519) signatures (e.g for Solana, Aptos and NEAR accounts).
+//!
+//! Refs:
+//! - <https://datatracker.ietf.org/doc/html/rfc8032#section-5.1>.
+//! - <https://datatracker.ietf.org/doc/html/rfc9591#name-frosted25519-sha-512>.
+
+use curve25519_dalek::{EdwardsPoint, Scalar};
+use sha2::{Digest, Sha512};
+use wamu_core::SecretShare;
+
+use crate::ciphersuite::{Ciphersuite, OutputKey};
+use crate::errors::Error;
+use crate::keygen::KeyShare;
+use crate::sign::Tweak;
+
+/// FROST over Ed25519 producing RFC 8032 (i.e Ed25519) signatures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ed25519;
+
+impl Ciphersuite for Ed25519 {
+    type Scalar = Scalar;
+    type Point = EdwardsPoint;
+    type Signature = ed25519_dalek::Signature;
+
+    const ID: &'static str = "ed25519-sha512";
+
+    fn hash_to_scalar(tag: &str, data: &[&[u8]]) -> Scalar {
+        let tag_hash = Sha512::digest(tag.as_bytes());
+        let hash = data
+            .iter()
+            .fold(Sha512::new().chain_update(tag_hash), |hasher, item| {
+                hasher.chain_update(item)
+            })
+            .finalize();
+        Scalar::from_bytes_mod_order_wide(&hash.into())
+    }
+
+    fn output_key(public_key: &EdwardsPoint, tweak: &Tweak) -> Result<OutputKey<Self>, Error> {
+        match tweak {
+            Tweak::None => Ok(OutputKey {
+                point: *public_key,
+                key_factor: Scalar::ONE,
+                factored_tweak: Scalar::ZERO,
+            }),
+            // Taproot tweaks are specific to secp256k1.
+            Tweak::Taproot { .. } => Err(Error::InvalidConfig),
+        }
+    }
+
+    fn nonce_factor(_: &EdwardsPoint) -> Scalar {
+        Scalar::ONE
+    }
+
+    fn challenge(
+        group_commitment: &EdwardsPoint,
+        output_key: &EdwardsPoint,
+        message: &[u8],
+    ) -> Scalar {
+        // `SHA512(R || A || M)` as per RFC 8032.
+        let hash = Sha512::new()
+            .chain_update(group_commitment.compress().as_bytes())
+            .chain_update(output_key.compress().as_bytes())
+            .chain_update(message)
+            .finalize();
+        Scalar::from_bytes_mod_order_wide(&hash.into())
+    }
+
+    fn signature(
+        group_commitment: &EdwardsPoint,
+        response: &Scalar,
+        output_key: &EdwardsPoint,
+        message: &[u8],
+    ) -> Result<ed25519_dalek::Signature, Error> {
+        let signature = ed25519_dalek::Signature::from_components(
+            group_commitment.compress().to_bytes(),
+            response.to_bytes(),
+        );
+        ed25519_dalek::VerifyingKey::from_bytes(&output_key.compress().to_bytes())
+            .and_then(|verifying_key| verifying_key.verify_strict(message, &signature))
+            .map_err(|_| Error::InvalidSignature)?;
+        Ok(signature)
+    }
+
+    fn to_secret_share(scalar: &Scalar) -> Result<SecretShare, wamu_core::Error> {
+        // Ed25519 scalars are little-endian, while "secret shares" are big-endian.
+        // NOTE: The Ed25519 group order is less than the secp256k1 group order,
+        // so all Ed25519 scalars are valid "secret shares".
+        let mut bytes = scalar.to_bytes();
+        bytes.reverse();
+        SecretShare::try_from(bytes.as_slice())
+    }
+
+    fn from_secret_share(secret_share: &SecretShare) -> Result<Scalar, wamu_core::Error> {
+        let mut bytes = secret_share.to_be_bytes();
+        bytes.reverse();
+        Option::from(Scalar::from_canonical_bytes(bytes)).ok_or(wamu_core::Error::Encoding)
+    }
+}
+
+impl KeyShare<Ed25519> {
+    /// Returns the 32 byte (i.e compressed Edwards y) encoding of the group public key
+    /// (e.g the Solana address is its base58 encoding, and the NEAR implicit account ID is its hex encoding).
+    pub fn public_key_bytes(&self) -> [u8; 32] {
+        self.public_key.compress().to_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen::tests::simulate_keygen;
+    use crate::sign::tests::simulate_sign;
+    use ed25519_dalek::Verifier;
+
+    #[test]
+    fn ed25519_sign_works() {
+        let (wallet_shares, identity_providers) = simulate_keygen::<Ed25519>(1, 3);
+        let key_share = &wallet_shares[0].key_share;
+        let verifying_key =
+            ed25519_dalek::VerifyingKey::from_bytes(&key_share.public_key_bytes()).unwrap();
+        let message = b"Hello Solana!";
+
+        for participants in [vec![1, 2], vec![3, 1], vec![1, 2, 3]] {
+            let signature = simulate_sign(
+                &wallet_shares,
+                &identity_providers,
+                &participants,
+                message,
+                Tweak::None,
+            );
+            // Verifies the signature with a standard Ed25519 implementation.
+            assert!(verifying_key.verify(message, &signature).is_ok());
+            assert!(verifying_key.verify(b"Hello NEAR!", &signature).is_err());
+        }
+    }
+}
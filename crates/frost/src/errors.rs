@@ -0,0 +1,44 @@
+//! Types, abstractions and utilities for FROST protocol errors.
+
+use round_based::IsCritical;
+
+/// A FROST protocol error.
+#[derive(Debug)]
+pub enum Error {
+    /// A core Wamu error (e.g a share splitting or reconstruction error).
+    Core(wamu_core::Error),
+    /// Invalid protocol parameters (e.g threshold, party index, participants, verifying keys or an unsupported tweak).
+    InvalidConfig,
+    /// A message with an invalid identity authentication signature or from an unauthorized party.
+    InvalidAuthentication {
+        sender: u16,
+        round: u16,
+        error: wamu_core::Error,
+    },
+    /// A message that isn't expected (e.g from a non-participant or to a different receiver).
+    UnexpectedMessage { sender: u16, round: u16 },
+    /// A duplicate message from the same sender for the same round (not critical).
+    DuplicateMessage { sender: u16, round: u16 },
+    /// An invalid proof of knowledge of the secret constant term (key generation).
+    InvalidProofOfKnowledge { sender: u16 },
+    /// A secret share that doesn't match the sender's commitments (key generation).
+    InvalidShare { sender: u16 },
+    /// A signature share that doesn't match the sender's nonce commitments and verification share (signing).
+    InvalidSignatureShare { sender: u16 },
+    /// The aggregate signature isn't a valid signature (e.g BIP-340 or Ed25519) of the message by the output public key.
+    InvalidSignature,
+    /// The output was already picked.
+    AlreadyPicked,
+}
+
+impl From<wamu_core::Error> for Error {
+    fn from(error: wamu_core::Error) -> Self {
+        Self::Core(error)
+    }
+}
+
+impl IsCritical for Error {
+    fn is_critical(&self) -> bool {
+        !matches!(self, Error::DuplicateMessage { .. })
+    }
+}
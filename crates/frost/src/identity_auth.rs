@@ -0,0 +1,101 @@
+//! Identity authentication of round messages.
+//!
+//! All round messages are signed by the sender's decentralized identity,
+//! and the signature binds the message to the session, sender, receiver and round.
+
+use round_based::Msg;
+use sha2::{Digest, Sha256};
+use wamu_core::crypto::{Signature, VerifyingKey};
+use wamu_core::IdentityProvider;
+
+use crate::errors::Error;
+
+/// A message body with its identity authentication parameters
+/// (i.e the sender's verifying key and signature).
+#[derive(Debug, Clone)]
+pub struct AuthedMessage<B> {
+    /// The message body.
+    pub body: B,
+    /// Verifying key of the sender.
+    pub verifying_key: VerifyingKey,
+    /// Signature of the message by the sender.
+    pub signature: Signature,
+}
+
+/// A message body with a round number and a deterministic encoding (for identity authentication).
+pub(crate) trait RoundMessage {
+    /// Returns the round of the message.
+    fn round(&self) -> u16;
+
+    /// Returns the deterministic encoding of the message body.
+    fn encode(&self) -> Vec<u8>;
+}
+
+/// Returns an identity authenticated message.
+pub(crate) fn authenticate<B: RoundMessage>(
+    body: B,
+    session_hash: &[u8],
+    sender: u16,
+    receiver: Option<u16>,
+    identity_provider: &impl IdentityProvider,
+) -> Msg<AuthedMessage<B>> {
+    let (verifying_key, signature) = wamu_core::wrappers::initiate_request_with_signature(
+        &message_hash(&body, session_hash, sender, receiver),
+        identity_provider,
+    );
+    Msg {
+        sender,
+        receiver,
+        body: AuthedMessage {
+            body,
+            verifying_key,
+            signature,
+        },
+    }
+}
+
+/// Verifies that a message is signed by the verifying key of the sender (i.e the verifying key at the sender's index).
+pub(crate) fn verify<B: RoundMessage>(
+    msg: &Msg<AuthedMessage<B>>,
+    session_hash: &[u8],
+    verified_parties: &[VerifyingKey],
+) -> Result<(), Error> {
+    let round = msg.body.body.round();
+    let sender_key = msg
+        .sender
+        .checked_sub(1)
+        .and_then(|i| verified_parties.get(i as usize))
+        .ok_or(Error::UnexpectedMessage {
+            sender: msg.sender,
+            round,
+        })?;
+    wamu_core::wrappers::verify_request_with_signature(
+        &message_hash(&msg.body.body, session_hash, msg.sender, msg.receiver),
+        &msg.body.verifying_key,
+        &msg.body.signature,
+        std::slice::from_ref(sender_key),
+    )
+    .map_err(|error| Error::InvalidAuthentication {
+        sender: msg.sender,
+        round,
+        error,
+    })
+}
+
+// Returns the hash of a message that's signed by the sender.
+fn message_hash<B: RoundMessage>(
+    body: &B,
+    session_hash: &[u8],
+    sender: u16,
+    receiver: Option<u16>,
+) -> Vec<u8> {
+    Sha256::new()
+        .chain_update(session_hash)
+        .chain_update(sender.to_be_bytes())
+        // Broadcast messages use zero as the receiver (i.e party indices start from one).
+        .chain_update(receiver.unwrap_or(0).to_be_bytes())
+        .chain_update(body.round().to_be_bytes())
+        .chain_update(body.encode())
+        .finalize()
+        .to_vec()
+}
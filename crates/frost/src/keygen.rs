@@ -0,0 +1,583 @@
+//! Augmented FROST distributed key generation implementation.
+//!
+//! Implements the FROST key generation protocol (i.e Pedersen DKG with proofs of knowledge of the secret constant terms),
+//! with identity authenticated rounds and the resulting "secret share" split into a "signing share" and "sub-share".
+//!
+//! Refs:
+//! - <https://eprint.iacr.org/2020/852.pdf> (Figure 1).
+//! - <https://wamu.tech/specification#key-generation>.
+//!
+//! **NOTE:** Round 2 messages are peer-to-peer messages that contain secret shares,
+//! so the transport must guarantee their confidentiality (i.e secure channels between parties).
+
+use k256::elliptic_curve::ff::Field;
+use k256::elliptic_curve::group::Group;
+use round_based::{Msg, StateMachine};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use wamu_core::cbor::CanonicalCbor;
+use wamu_core::crypto::VerifyingKey;
+use wamu_core::{IdentityProvider, SigningShare, SubShare};
+use zeroize::Zeroize;
+
+use crate::ciphersuite::{Ciphersuite, Secp256k1};
+use crate::errors::Error;
+use crate::identity_auth::{AuthedMessage, RoundMessage};
+use crate::{identity_auth, primitives};
+
+const KEYGEN_SESSION_TAG: &str = "wamu-frost/keygen";
+const PROOF_OF_KNOWLEDGE_TAG: &str = "wamu-frost/keygen/proof-of-knowledge";
+
+/// The public key material of a party (i.e the group public key and verification shares of all parties).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyShare<C: Ciphersuite = Secp256k1> {
+    /// Party index.
+    pub idx: u16,
+    /// The threshold.
+    // NOTE: Quorum size = threshold + 1
+    pub threshold: u16,
+    /// The group public key.
+    pub public_key: C::Point,
+    /// Verification shares (i.e public keys of the secret shares) for all parties (in party index order).
+    pub verification_shares: Vec<C::Point>,
+}
+
+impl<C: Ciphersuite> KeyShare<C> {
+    /// Returns the total number of parties.
+    pub fn n_parties(&self) -> u16 {
+        self.verification_shares.len() as u16
+    }
+}
+
+impl KeyShare<Secp256k1> {
+    /// Returns the x-only (i.e BIP-340) encoding of the group public key.
+    pub fn x_only_public_key(&self) -> [u8; 32] {
+        primitives::x_only(&self.public_key)
+    }
+
+    /// Returns the x-only (i.e BIP-340) encoding of the Taproot output key for the group public key (as the internal key)
+    /// and an optional script tree merkle root (i.e `None` for key path only outputs as per BIP-86).
+    pub fn taproot_output_key(&self, merkle_root: Option<&[u8; 32]>) -> [u8; 32] {
+        let tweak = primitives::taproot_tweak(&self.public_key, merkle_root);
+        let (output_key, _, _) = primitives::output_key(&self.public_key, Some(&tweak));
+        primitives::x_only(&output_key)
+    }
+}
+
+/// The output of key generation (i.e the public key material, "signing share" and "sub-share" of the party).
+pub struct WalletShare<C: Ciphersuite = Secp256k1> {
+    /// The public key material of the party.
+    pub key_share: KeyShare<C>,
+    /// The "signing share" of the party.
+    pub signing_share: SigningShare,
+    /// The "sub-share" of the party.
+    pub sub_share: SubShare,
+}
+
+/// A key generation message body.
+#[derive(Debug, Clone)]
+pub enum KeyGenMessageBody<C: Ciphersuite = Secp256k1> {
+    /// Round 1 (broadcast): commitments to the polynomial's coefficients and a proof of knowledge of the constant term.
+    Round1 {
+        /// Commitments to the coefficients (in ascending order).
+        commitments: Vec<C::Point>,
+        /// Nonce commitment (i.e R) of the proof of knowledge.
+        proof_commitment: C::Point,
+        /// Response (i.e mu) of the proof of knowledge.
+        proof_response: C::Scalar,
+    },
+    /// Round 2 (peer-to-peer): the secret share for the receiver (i.e the polynomial evaluated at the receiver's index).
+    Round2 {
+        /// The secret share.
+        share: C::Scalar,
+    },
+}
+
+impl<C: Ciphersuite> RoundMessage for KeyGenMessageBody<C> {
+    fn round(&self) -> u16 {
+        match self {
+            Self::Round1 { .. } => 1,
+            Self::Round2 { .. } => 2,
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        match self {
+            Self::Round1 {
+                commitments,
+                proof_commitment,
+                proof_response,
+            } => {
+                let mut encoded: Vec<u8> = commitments
+                    .iter()
+                    .flat_map(primitives::encode_point)
+                    .collect();
+                encoded.extend(primitives::encode_point(proof_commitment));
+                encoded.extend(primitives::encode_scalar(proof_response));
+                encoded
+            }
+            Self::Round2 { share } => primitives::encode_scalar(share),
+        }
+    }
+}
+
+/// An identity authenticated key generation message.
+pub type KeyGenMessage<C = Secp256k1> = Msg<AuthedMessage<KeyGenMessageBody<C>>>;
+
+/// A [StateMachine](StateMachine) that implements augmented FROST key generation.
+pub struct AugmentedKeyGen<'a, I: IdentityProvider, C: Ciphersuite = Secp256k1> {
+    /// The decentralized identity provider of the party.
+    identity_provider: &'a I,
+    /// Verifying keys for all parties (in party index order).
+    verified_parties: &'a [VerifyingKey],
+    /// Party index.
+    idx: u16,
+    /// The threshold.
+    threshold: u16,
+    /// Total number of parties.
+    n_parties: u16,
+    /// Hash of the session parameters (for binding messages and proofs to the session).
+    session_hash: Vec<u8>,
+    /// Secret polynomial coefficients (in ascending order).
+    coefficients: Vec<C::Scalar>,
+    /// Verified commitments of all parties indexed by party.
+    commitments: BTreeMap<u16, Vec<C::Point>>,
+    /// Received (unverified) secret shares indexed by sender.
+    shares: BTreeMap<u16, C::Scalar>,
+    /// Current round.
+    round: u16,
+    /// Outgoing message queue.
+    message_queue: Vec<KeyGenMessage<C>>,
+    /// Protocol output.
+    output: Option<WalletShare<C>>,
+    /// Whether or not the output was already picked.
+    is_picked: bool,
+}
+
+impl<'a, I: IdentityProvider, C: Ciphersuite> AugmentedKeyGen<'a, I, C> {
+    /// Initializes party for the augmented key generation protocol given its identity provider,
+    /// verifying keys for all parties (in party index order), the party index, the threshold, the total number of parties
+    /// and a random session identifier shared by all parties.
+    pub fn new(
+        identity_provider: &'a I,
+        verified_parties: &'a [VerifyingKey],
+        idx: u16,
+        // NOTE: Quorum size = threshold + 1
+        threshold: u16,
+        n_parties: u16,
+        session_id: [u8; 32],
+    ) -> Result<Self, Error> {
+        if threshold == 0
+            || threshold >= n_parties
+            || !(1..=n_parties).contains(&idx)
+            || verified_parties.len() != n_parties as usize
+            || verified_parties[idx as usize - 1] != identity_provider.verifying_key()
+        {
+            return Err(Error::InvalidConfig);
+        }
+
+        // Computes the session hash.
+        let session_hash = verified_parties
+            .iter()
+            .fold(
+                Sha256::new()
+                    .chain_update(KEYGEN_SESSION_TAG)
+                    .chain_update(C::ID)
+                    .chain_update(session_id)
+                    .chain_update(threshold.to_be_bytes())
+                    .chain_update(n_parties.to_be_bytes()),
+                |hasher, verifying_key| hasher.chain_update(verifying_key.to_cbor()),
+            )
+            .finalize()
+            .to_vec();
+
+        // Samples a random polynomial of degree threshold and commits to its coefficients.
+        let mut rng = rand::thread_rng();
+        let coefficients: Vec<C::Scalar> = (0..=threshold)
+            .map(|_| C::Scalar::random(&mut rng))
+            .collect();
+        let commitments: Vec<C::Point> = coefficients
+            .iter()
+            .map(|coefficient| C::Point::generator() * coefficient)
+            .collect();
+
+        // Proves knowledge of the constant term.
+        let nonce = C::Scalar::random(&mut rng);
+        let proof_commitment = C::Point::generator() * nonce;
+        let proof_challenge =
+            proof_challenge::<C>(&session_hash, idx, &commitments[0], &proof_commitment);
+        let proof_response = nonce + coefficients[0] * proof_challenge;
+
+        // Broadcasts the commitments and the proof of knowledge.
+        let message_queue = vec![identity_auth::authenticate(
+            KeyGenMessageBody::Round1 {
+                commitments: commitments.clone(),
+                proof_commitment,
+                proof_response,
+            },
+            &session_hash,
+            idx,
+            None,
+            identity_provider,
+        )];
+
+        Ok(Self {
+            identity_provider,
+            verified_parties,
+            idx,
+            threshold,
+            n_parties,
+            session_hash,
+            coefficients,
+            commitments: BTreeMap::from([(idx, commitments)]),
+            shares: BTreeMap::new(),
+            round: 1,
+            message_queue,
+            output: None,
+            is_picked: false,
+        })
+    }
+
+    // Sends secret shares to all other parties.
+    fn send_shares(&mut self) {
+        for receiver in (1..=self.n_parties).filter(|receiver| *receiver != self.idx) {
+            let share = primitives::evaluate_polynomial(&self.coefficients, receiver);
+            self.message_queue.push(identity_auth::authenticate(
+                KeyGenMessageBody::Round2 { share },
+                &self.session_hash,
+                self.idx,
+                Some(receiver),
+                self.identity_provider,
+            ));
+        }
+        let own_share = primitives::evaluate_polynomial(&self.coefficients, self.idx);
+        self.shares.insert(self.idx, own_share);
+        self.coefficients.zeroize();
+    }
+
+    // Verifies all secret shares and computes the output.
+    fn finalize(&mut self) -> Result<(), Error> {
+        // Verifies secret shares against the senders' commitments.
+        for (sender, share) in self.shares.iter() {
+            let commitments = &self.commitments[sender];
+            if C::Point::generator() * share
+                != primitives::evaluate_commitments(commitments, self.idx)
+            {
+                return Err(Error::InvalidShare { sender: *sender });
+            }
+        }
+
+        // Computes the secret share, group public key and verification shares.
+        let mut secret_share = self
+            .shares
+            .values()
+            .fold(C::Scalar::ZERO, |acc, it| acc + it);
+        self.shares.values_mut().for_each(Zeroize::zeroize);
+        let public_key = self
+            .commitments
+            .values()
+            .fold(C::Point::identity(), |acc, it| acc + it[0]);
+        let verification_shares = (1..=self.n_parties)
+            .map(|idx| {
+                self.commitments
+                    .values()
+                    .fold(C::Point::identity(), |acc, it| {
+                        acc + primitives::evaluate_commitments(it, idx)
+                    })
+            })
+            .collect();
+
+        // Splits the secret share into a "signing share" and "sub-share".
+        let result = C::to_secret_share(&secret_share).and_then(|secret_share| {
+            wamu_core::share_split_reconstruct::split(&secret_share, self.identity_provider)
+        });
+        secret_share.zeroize();
+        let (signing_share, sub_share) = result?;
+
+        self.output = Some(WalletShare {
+            key_share: KeyShare {
+                idx: self.idx,
+                threshold: self.threshold,
+                public_key,
+                verification_shares,
+            },
+            signing_share,
+            sub_share,
+        });
+        Ok(())
+    }
+}
+
+impl<'a, I: IdentityProvider, C: Ciphersuite> StateMachine for AugmentedKeyGen<'a, I, C> {
+    type MessageBody = AuthedMessage<KeyGenMessageBody<C>>;
+    type Err = Error;
+    type Output = WalletShare<C>;
+
+    fn handle_incoming(&mut self, msg: Msg<Self::MessageBody>) -> Result<(), Self::Err> {
+        // Verifies the identity authentication of the message.
+        identity_auth::verify(&msg, &self.session_hash, self.verified_parties)?;
+
+        let (sender, round) = (msg.sender, msg.body.body.round());
+        if sender == self.idx {
+            return Err(Error::UnexpectedMessage { sender, round });
+        }
+        match msg.body.body {
+            KeyGenMessageBody::Round1 {
+                commitments,
+                proof_commitment,
+                proof_response,
+            } => {
+                if self.commitments.contains_key(&sender) {
+                    return Err(Error::DuplicateMessage { sender, round });
+                }
+                if msg.receiver.is_some() || commitments.len() != self.threshold as usize + 1 {
+                    return Err(Error::UnexpectedMessage { sender, round });
+                }
+                // Verifies the proof of knowledge of the constant term.
+                let proof_challenge = proof_challenge::<C>(
+                    &self.session_hash,
+                    sender,
+                    &commitments[0],
+                    &proof_commitment,
+                );
+                if C::Point::generator() * proof_response
+                    != proof_commitment + commitments[0] * proof_challenge
+                {
+                    return Err(Error::InvalidProofOfKnowledge { sender });
+                }
+                self.commitments.insert(sender, commitments);
+            }
+            KeyGenMessageBody::Round2 { share } => {
+                if self.shares.contains_key(&sender) {
+                    return Err(Error::DuplicateMessage { sender, round });
+                }
+                if msg.receiver != Some(self.idx) {
+                    return Err(Error::UnexpectedMessage { sender, round });
+                }
+                // NOTE: Shares are verified after commitments from all parties are received.
+                self.shares.insert(sender, share);
+            }
+        }
+        Ok(())
+    }
+
+    fn message_queue(&mut self) -> &mut Vec<Msg<Self::MessageBody>> {
+        &mut self.message_queue
+    }
+
+    fn wants_to_proceed(&self) -> bool {
+        let n_parties = self.n_parties as usize;
+        match self.round {
+            1 => self.commitments.len() == n_parties,
+            2 => self.shares.len() == n_parties,
+            _ => false,
+        }
+    }
+
+    fn proceed(&mut self) -> Result<(), Self::Err> {
+        if !self.wants_to_proceed() {
+            return Ok(());
+        }
+        match self.round {
+            1 => self.send_shares(),
+            _ => self.finalize()?,
+        }
+        self.round += 1;
+        Ok(())
+    }
+
+    fn round_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    fn round_timeout_reached(&mut self) -> Self::Err {
+        panic!("no timeout was set")
+    }
+
+    fn is_finished(&self) -> bool {
+        self.output.is_some() || self.is_picked
+    }
+
+    fn pick_output(&mut self) -> Option<Result<Self::Output, Self::Err>> {
+        // Return an error if output was already picked.
+        if self.is_picked {
+            return Some(Err(Error::AlreadyPicked));
+        }
+
+        self.output.take().map(|output| {
+            self.is_picked = true;
+            Ok(output)
+        })
+    }
+
+    fn current_round(&self) -> u16 {
+        self.round
+    }
+
+    fn total_rounds(&self) -> Option<u16> {
+        Some(2)
+    }
+
+    fn party_ind(&self) -> u16 {
+        self.idx
+    }
+
+    fn parties(&self) -> u16 {
+        self.n_parties
+    }
+}
+
+impl<'a, I: IdentityProvider, C: Ciphersuite> Drop for AugmentedKeyGen<'a, I, C> {
+    fn drop(&mut self) {
+        self.coefficients.zeroize();
+        self.shares.values_mut().for_each(Zeroize::zeroize);
+    }
+}
+
+// Returns the challenge of the proof of knowledge of the constant term for a party.
+fn proof_challenge<C: Ciphersuite>(
+    session_hash: &[u8],
+    idx: u16,
+    constant_commitment: &C::Point,
+    proof_commitment: &C::Point,
+) -> C::Scalar {
+    C::hash_to_scalar(
+        PROOF_OF_KNOWLEDGE_TAG,
+        &[
+            session_hash,
+            &idx.to_be_bytes(),
+            &primitives::encode_point(constant_commitment),
+            &primitives::encode_point(proof_commitment),
+        ],
+    )
+}
+
+// Implement `Debug` trait for `AugmentedKeyGen` for test simulations.
+#[cfg(any(test, feature = "dev"))]
+impl<'a, I: IdentityProvider, C: Ciphersuite> std::fmt::Debug for AugmentedKeyGen<'a, I, C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Augmented FROST KeyGen")
+    }
+}
+
+#[cfg(any(test, feature = "dev"))]
+pub mod tests {
+    use super::*;
+    use round_based::dev::Simulation;
+    use wamu_core::test_utils::MockECDSAIdentityProvider;
+
+    pub fn simulate_keygen<C: Ciphersuite>(
+        threshold: u16,
+        n_parties: u16,
+    ) -> (Vec<WalletShare<C>>, Vec<MockECDSAIdentityProvider>) {
+        // Creates identity providers for all parties.
+        let identity_providers: Vec<MockECDSAIdentityProvider> = (1..=n_parties)
+            .map(|_| MockECDSAIdentityProvider::generate())
+            .collect();
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let session_id = wamu_core::crypto::Random32Bytes::generate().to_be_bytes();
+
+        // Runs key gen simulation for test parameters.
+        let mut simulation = Simulation::<AugmentedKeyGen<MockECDSAIdentityProvider, C>>::new();
+        for (i, identity_provider) in identity_providers.iter().enumerate() {
+            simulation.add_party(
+                AugmentedKeyGen::new(
+                    identity_provider,
+                    &verifying_keys,
+                    i as u16 + 1,
+                    threshold,
+                    n_parties,
+                    session_id,
+                )
+                .unwrap(),
+            );
+        }
+        let wallet_shares = simulation.run().unwrap();
+        // Releases borrows of the identity providers.
+        drop(simulation);
+
+        // Verifies that all parties agree on the public key material.
+        assert_eq!(wallet_shares.len(), n_parties as usize);
+        for (i, wallet_share) in wallet_shares.iter().enumerate() {
+            let key_share = &wallet_share.key_share;
+            assert_eq!(key_share.idx, i as u16 + 1);
+            assert_eq!(key_share.threshold, threshold);
+            assert_eq!(key_share.n_parties(), n_parties);
+            assert_eq!(key_share.public_key, wallet_shares[0].key_share.public_key);
+            assert_eq!(
+                key_share.verification_shares,
+                wallet_shares[0].key_share.verification_shares
+            );
+
+            // Verifies that the reconstructed secret share matches the verification share.
+            let secret_share = wamu_core::share_split_reconstruct::reconstruct(
+                &wallet_share.signing_share,
+                &wallet_share.sub_share,
+                &identity_providers[i],
+            )
+            .unwrap();
+            assert_eq!(
+                C::Point::generator() * C::from_secret_share(&secret_share).unwrap(),
+                key_share.verification_shares[i]
+            );
+        }
+
+        (wallet_shares, identity_providers)
+    }
+
+    #[test]
+    fn keygen_works() {
+        for (threshold, n_parties) in [(1, 2), (1, 3), (2, 3), (2, 5)] {
+            simulate_keygen::<Secp256k1>(threshold, n_parties);
+            #[cfg(feature = "ed25519")]
+            simulate_keygen::<crate::ed25519::Ed25519>(threshold, n_parties);
+        }
+    }
+
+    #[test]
+    fn keygen_rejects_invalid_config() {
+        let identity_provider = MockECDSAIdentityProvider::generate();
+        let verifying_keys = vec![
+            MockECDSAIdentityProvider::generate().verifying_key(),
+            identity_provider.verifying_key(),
+        ];
+        let session_id = [0; 32];
+        // Mismatched verifying key.
+        assert!(matches!(
+            AugmentedKeyGen::<_, Secp256k1>::new(
+                &identity_provider,
+                &verifying_keys,
+                1,
+                1,
+                2,
+                session_id
+            ),
+            Err(Error::InvalidConfig)
+        ));
+        // Threshold not less than the number of parties.
+        assert!(matches!(
+            AugmentedKeyGen::<_, Secp256k1>::new(
+                &identity_provider,
+                &verifying_keys,
+                2,
+                2,
+                2,
+                session_id
+            ),
+            Err(Error::InvalidConfig)
+        ));
+        assert!(AugmentedKeyGen::<_, Secp256k1>::new(
+            &identity_provider,
+            &verifying_keys,
+            2,
+            1,
+            2,
+            session_id
+        )
+        .is_ok());
+    }
+}
@@ -0,0 +1,29 @@
+//! A Rust implementation of [FROST](https://eprint.iacr.org/2020/852.pdf) over secp256k1 (producing [BIP-340](https://github.com/bitcoin/bips/blob/master/bip-0340.mediawiki) signatures for [Taproot](https://github.com/bitcoin/bips/blob/master/bip-0341.mediawiki)) with augmentations as described by the [Wamu protocol](https://wamu.tech/specification) for computation of threshold signatures by multiple decentralized identities.
+//!
+//! Key generation and signing are generic over a [`Ciphersuite`], with [`Secp256k1`] (i.e BIP-340/Taproot) as the default
+//! and [`Ed25519`] (i.e RFC 8032, e.g for Solana, Aptos and NEAR accounts) behind the `ed25519` feature.
+
+#![feature(doc_cfg)]
+
+pub use self::ciphersuite::{Ciphersuite, OutputKey, Secp256k1};
+pub use self::errors::Error;
+pub use self::identity_auth::AuthedMessage;
+pub use self::keygen::{AugmentedKeyGen, KeyGenMessage, KeyGenMessageBody, KeyShare, WalletShare};
+pub use self::sign::{AugmentedSigning, SigningMessage, SigningMessageBody, Tweak};
+
+#[cfg(feature = "ed25519")]
+#[doc(cfg(feature = "ed25519"))]
+pub use self::ed25519::Ed25519;
+
+#[cfg(feature = "dev")]
+#[doc(cfg(feature = "dev"))]
+pub use self::{keygen::tests::simulate_keygen, sign::tests::simulate_sign};
+
+mod ciphersuite;
+#[cfg(feature = "ed25519")]
+mod ed25519;
+mod errors;
+mod identity_auth;
+mod keygen;
+mod primitives;
+mod sign;
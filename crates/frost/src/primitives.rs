@@ -0,0 +1,204 @@
+//! FROST (i.e polynomial and Lagrange interpolation) primitives and BIP-340 primitives over secp256k1.
+//!
+//! Refs:
+//! - <https://eprint.iacr.org/2020/852.pdf>.
+//! - <https://github.com/bitcoin/bips/blob/master/bip-0340.mediawiki>.
+//! - <https://github.com/bitcoin/bips/blob/master/bip-0341.mediawiki>.
+
+use k256::elliptic_curve::ff::PrimeField;
+use k256::elliptic_curve::group::{Group, GroupEncoding};
+use k256::elliptic_curve::ops::Reduce;
+use k256::elliptic_curve::point::AffineCoordinates;
+use k256::{ProjectivePoint, Scalar, U256};
+use sha2::{Digest, Sha256};
+use wamu_core::SecretShare;
+
+/// BIP-340 challenge tag.
+const CHALLENGE_TAG: &str = "BIP0340/challenge";
+/// BIP-341 tweak tag.
+const TAP_TWEAK_TAG: &str = "TapTweak";
+
+/// Returns the BIP-340 tagged hash of the data (i.e `SHA256(SHA256(tag) || SHA256(tag) || data)`).
+pub(crate) fn tagged_hash(tag: &str, data: &[&[u8]]) -> [u8; 32] {
+    let tag_hash = Sha256::digest(tag.as_bytes());
+    data.iter()
+        .fold(
+            Sha256::new().chain_update(tag_hash).chain_update(tag_hash),
+            |hasher, item| hasher.chain_update(item),
+        )
+        .finalize()
+        .into()
+}
+
+/// Returns the tagged hash of the data reduced to a scalar.
+pub(crate) fn hash_to_scalar(tag: &str, data: &[&[u8]]) -> Scalar {
+    <Scalar as Reduce<U256>>::reduce_bytes(&tagged_hash(tag, data).into())
+}
+
+/// Returns the BIP-340 challenge for a nonce commitment, x-only public key and message.
+pub(crate) fn challenge(
+    big_r: &ProjectivePoint,
+    public_key: &ProjectivePoint,
+    msg: &[u8],
+) -> Scalar {
+    hash_to_scalar(CHALLENGE_TAG, &[&x_only(big_r), &x_only(public_key), msg])
+}
+
+/// Returns the canonical encoding of a point (e.g the compressed SEC1 encoding for secp256k1).
+pub(crate) fn encode_point<G: GroupEncoding>(point: &G) -> Vec<u8> {
+    point.to_bytes().as_ref().to_vec()
+}
+
+/// Returns the canonical encoding of a scalar.
+pub(crate) fn encode_scalar<F: PrimeField>(scalar: &F) -> Vec<u8> {
+    scalar.to_repr().as_ref().to_vec()
+}
+
+/// Returns the x-only (i.e BIP-340) encoding of a point.
+pub(crate) fn x_only(point: &ProjectivePoint) -> [u8; 32] {
+    point.to_affine().x().into()
+}
+
+/// Returns true if the y coordinate of the point is odd.
+pub(crate) fn has_odd_y(point: &ProjectivePoint) -> bool {
+    point.to_affine().y_is_odd().into()
+}
+
+/// Returns `-1` if the y coordinate of the point is odd or `1` otherwise
+/// (i.e the factor that maps the point to its BIP-340 even y representation).
+pub(crate) fn even_y_factor(point: &ProjectivePoint) -> Scalar {
+    if has_odd_y(point) {
+        -Scalar::ONE
+    } else {
+        Scalar::ONE
+    }
+}
+
+/// Returns the party index as a scalar.
+pub(crate) fn idx_scalar<F: PrimeField>(idx: u16) -> F {
+    F::from(u64::from(idx))
+}
+
+/// Evaluates a polynomial (given its coefficients in ascending order) at a party index.
+pub(crate) fn evaluate_polynomial<F: PrimeField>(coefficients: &[F], idx: u16) -> F {
+    let x: F = idx_scalar(idx);
+    coefficients
+        .iter()
+        .rev()
+        .fold(F::ZERO, |acc, coefficient| acc * x + coefficient)
+}
+
+/// Evaluates the commitments to a polynomial's coefficients (in ascending order) at a party index.
+pub(crate) fn evaluate_commitments<G: Group>(commitments: &[G], idx: u16) -> G
+where
+    G::Scalar: PrimeField,
+{
+    let x: G::Scalar = idx_scalar(idx);
+    commitments
+        .iter()
+        .rev()
+        .fold(G::identity(), |acc, commitment| acc * x + commitment)
+}
+
+/// Returns the Lagrange coefficient (at zero) of a party index for a set of participant indices,
+/// or `None` if the participant indices are invalid (i.e duplicated, zero or don't include the party index).
+pub(crate) fn lagrange_coefficient<F: PrimeField>(idx: u16, participants: &[u16]) -> Option<F> {
+    let mut sorted_participants = participants.to_vec();
+    sorted_participants.sort_unstable();
+    sorted_participants.dedup();
+    if sorted_participants.len() != participants.len()
+        || sorted_participants.contains(&0)
+        || !sorted_participants.contains(&idx)
+    {
+        return None;
+    }
+    let (numerator, denominator) = participants.iter().filter(|other| **other != idx).fold(
+        (F::ONE, F::ONE),
+        |(numerator, denominator), other| {
+            (
+                numerator * idx_scalar::<F>(*other),
+                denominator * (idx_scalar::<F>(*other) - idx_scalar::<F>(idx)),
+            )
+        },
+    );
+    Option::from(denominator.invert()).map(|inverse: F| numerator * inverse)
+}
+
+/// Returns the BIP-341 tweak for a public key and an optional script tree merkle root
+/// (i.e `hash_TapTweak(P.x || merkle_root)`, with an empty merkle root for key path only outputs as per BIP-86).
+pub(crate) fn taproot_tweak(
+    public_key: &ProjectivePoint,
+    merkle_root: Option<&[u8; 32]>,
+) -> Scalar {
+    let merkle_root = merkle_root.map_or(&[][..], |it| it.as_slice());
+    hash_to_scalar(TAP_TWEAK_TAG, &[&x_only(public_key), merkle_root])
+}
+
+/// Given a group public key and an optional tweak,
+/// returns the (even y) output public key, and the factors that the secret key and the tweak must be multiplied by
+/// (i.e `Q = key_factor * x * G + tweak_factor * t * G` where `x * G` is the group public key).
+pub(crate) fn output_key(
+    public_key: &ProjectivePoint,
+    tweak: Option<&Scalar>,
+) -> (ProjectivePoint, Scalar, Scalar) {
+    // BIP-340 uses the even y representation of the internal key.
+    let internal_factor = even_y_factor(public_key);
+    let internal_key = public_key * &internal_factor;
+    match tweak {
+        Some(tweak) => {
+            let tweaked_key = internal_key + ProjectivePoint::GENERATOR * tweak;
+            let output_factor = even_y_factor(&tweaked_key);
+            (
+                tweaked_key * output_factor,
+                internal_factor * output_factor,
+                output_factor,
+            )
+        }
+        None => (internal_key, internal_factor, Scalar::ZERO),
+    }
+}
+
+/// Converts a scalar into a Wamu "secret share".
+pub(crate) fn to_secret_share(scalar: &Scalar) -> Result<SecretShare, wamu_core::Error> {
+    SecretShare::try_from(scalar.to_bytes().as_slice())
+}
+
+/// Converts a Wamu "secret share" into a scalar.
+pub(crate) fn from_secret_share(secret_share: &SecretShare) -> Result<Scalar, wamu_core::Error> {
+    Option::from(Scalar::from_repr(secret_share.to_be_bytes().into()))
+        .ok_or(wamu_core::Error::Encoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::elliptic_curve::ff::Field;
+
+    #[test]
+    fn lagrange_interpolation_works() {
+        // Evaluates a random degree 2 polynomial at 4 points and interpolates the constant term from any 3 of them.
+        let coefficients: Vec<Scalar> = (0..3)
+            .map(|_| Scalar::random(&mut rand::thread_rng()))
+            .collect();
+        for participants in [[1, 2, 3], [1, 3, 4], [2, 3, 4]] {
+            let secret = participants.iter().fold(Scalar::ZERO, |acc, idx| {
+                acc + evaluate_polynomial(&coefficients, *idx)
+                    * lagrange_coefficient::<Scalar>(*idx, &participants).unwrap()
+            });
+            assert_eq!(secret, coefficients[0]);
+        }
+
+        // Verifies that commitments evaluate to the commitment of the polynomial's evaluation.
+        let commitments: Vec<ProjectivePoint> = coefficients
+            .iter()
+            .map(|coefficient| ProjectivePoint::GENERATOR * coefficient)
+            .collect();
+        assert_eq!(
+            evaluate_commitments(&commitments, 5),
+            ProjectivePoint::GENERATOR * evaluate_polynomial(&coefficients, 5)
+        );
+
+        // Verifies that duplicate participants are rejected.
+        assert!(lagrange_coefficient::<Scalar>(1, &[1, 2, 2]).is_none());
+    }
+}
@@ -0,0 +1,558 @@
+//! Augmented FROST signing implementation.
+//!
+//! Implements the 2-round FROST signing protocol producing BIP-340 (i.e Taproot) Schnorr signatures,
+//! with identity authenticated rounds and the "secret share" reconstructed from the "signing share" and "sub-share".
+//!
+//! Refs:
+//! - <https://eprint.iacr.org/2020/852.pdf> (Figure 3).
+//! - <https://github.com/bitcoin/bips/blob/master/bip-0340.mediawiki>.
+//! - <https://github.com/bitcoin/bips/blob/master/bip-0341.mediawiki>.
+//! - <https://wamu.tech/specification#signing>.
+
+use k256::elliptic_curve::ff::Field;
+use k256::elliptic_curve::group::Group;
+use round_based::{Msg, StateMachine};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use wamu_core::cbor::CanonicalCbor;
+use wamu_core::crypto::VerifyingKey;
+use wamu_core::IdentityProvider;
+use zeroize::Zeroize;
+
+use crate::ciphersuite::{Ciphersuite, OutputKey, Secp256k1};
+use crate::errors::Error;
+use crate::identity_auth::{AuthedMessage, RoundMessage};
+use crate::keygen::{KeyShare, WalletShare};
+use crate::{identity_auth, primitives};
+
+const SIGNING_SESSION_TAG: &str = "wamu-frost/sign";
+const BINDING_FACTOR_TAG: &str = "wamu-frost/sign/binding-factor";
+
+/// The tweak applied to the group public key for signing (i.e only Taproot tweaks for secp256k1 are supported).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tweak {
+    /// No tweak (i.e signs for the x-only group public key).
+    None,
+    /// A BIP-341 Taproot tweak with an optional script tree merkle root
+    /// (i.e signs for the Taproot output key, `None` for key path only outputs as per BIP-86).
+    Taproot { merkle_root: Option<[u8; 32]> },
+}
+
+/// A signing message body.
+#[derive(Debug, Clone)]
+pub enum SigningMessageBody<C: Ciphersuite = Secp256k1> {
+    /// Round 1 (broadcast): commitments to the party's hiding and binding nonces (i.e D and E).
+    Round1 {
+        /// Hiding nonce commitment.
+        hiding_commitment: C::Point,
+        /// Binding nonce commitment.
+        binding_commitment: C::Point,
+    },
+    /// Round 2 (broadcast): the party's signature share (i.e z).
+    Round2 {
+        /// The signature share.
+        signature_share: C::Scalar,
+    },
+}
+
+impl<C: Ciphersuite> RoundMessage for SigningMessageBody<C> {
+    fn round(&self) -> u16 {
+        match self {
+            Self::Round1 { .. } => 1,
+            Self::Round2 { .. } => 2,
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        match self {
+            Self::Round1 {
+                hiding_commitment,
+                binding_commitment,
+            } => [
+                primitives::encode_point(hiding_commitment),
+                primitives::encode_point(binding_commitment),
+            ]
+            .concat(),
+            Self::Round2 { signature_share } => primitives::encode_scalar(signature_share),
+        }
+    }
+}
+
+/// An identity authenticated signing message.
+pub type SigningMessage<C = Secp256k1> = Msg<AuthedMessage<SigningMessageBody<C>>>;
+
+/// A [StateMachine](StateMachine) that implements augmented FROST signing.
+pub struct AugmentedSigning<'a, I: IdentityProvider, C: Ciphersuite = Secp256k1> {
+    /// The decentralized identity provider of the party.
+    identity_provider: &'a I,
+    /// Verifying keys for all parties (in party index order).
+    verified_parties: &'a [VerifyingKey],
+    /// The public key material of the party.
+    key_share: &'a KeyShare<C>,
+    /// Indices of all participants (in ascending order).
+    participants: Vec<u16>,
+    /// The message to sign.
+    message: &'a [u8],
+    /// Hash of the session parameters (for binding messages and binding factors to the session).
+    session_hash: Vec<u8>,
+    /// The output public key.
+    output_key: OutputKey<C>,
+    /// The secret share of the party.
+    secret_share: C::Scalar,
+    /// Hiding and binding nonces of the party.
+    nonces: (C::Scalar, C::Scalar),
+    /// Nonce commitments of all participants indexed by party.
+    commitments: BTreeMap<u16, (C::Point, C::Point)>,
+    /// Signature shares of all participants indexed by party.
+    signature_shares: BTreeMap<u16, C::Scalar>,
+    /// The aggregate nonce commitment (i.e R) and its nonce factor.
+    group_commitment: Option<(C::Point, C::Scalar)>,
+    /// Current round.
+    round: u16,
+    /// Outgoing message queue.
+    message_queue: Vec<SigningMessage<C>>,
+    /// Protocol output.
+    output: Option<C::Signature>,
+    /// Whether or not the output was already picked.
+    is_picked: bool,
+}
+
+impl<'a, I: IdentityProvider, C: Ciphersuite> AugmentedSigning<'a, I, C> {
+    /// Initializes party for the augmented signing protocol given its wallet share (i.e from key generation),
+    /// identity provider, verifying keys for all parties (in party index order),
+    /// indices of all participants, the message to sign, the tweak to apply to the group public key
+    /// and a random session identifier shared by all participants.
+    ///
+    /// **NOTE:** The message is signed as is (e.g BIP-340 and Ed25519 sign arbitrary messages, and Taproot signs the 32 byte sighash).
+    pub fn new(
+        wallet_share: &'a WalletShare<C>,
+        identity_provider: &'a I,
+        verified_parties: &'a [VerifyingKey],
+        participants: &[u16],
+        message: &'a [u8],
+        tweak: Tweak,
+        session_id: [u8; 32],
+    ) -> Result<Self, Error> {
+        let key_share = &wallet_share.key_share;
+        let idx = key_share.idx;
+        let n_parties = key_share.n_parties();
+        let mut participants = participants.to_vec();
+        participants.sort_unstable();
+        participants.dedup();
+        if participants.len() <= key_share.threshold as usize
+            || participants.contains(&0)
+            || participants.iter().any(|it| *it > n_parties)
+            || !participants.contains(&idx)
+            || verified_parties.len() != n_parties as usize
+            || verified_parties[idx as usize - 1] != identity_provider.verifying_key()
+        {
+            return Err(Error::InvalidConfig);
+        }
+
+        // Reconstructs the secret share.
+        let secret_share = wamu_core::share_split_reconstruct::reconstruct(
+            &wallet_share.signing_share,
+            &wallet_share.sub_share,
+            identity_provider,
+        )?;
+        let secret_share = C::from_secret_share(&secret_share)?;
+
+        // Computes the output key.
+        let output_key = C::output_key(&key_share.public_key, &tweak)?;
+
+        // Computes the session hash.
+        let session_hash = verified_parties
+            .iter()
+            .fold(
+                participants.iter().fold(
+                    Sha256::new()
+                        .chain_update(SIGNING_SESSION_TAG)
+                        .chain_update(C::ID)
+                        .chain_update(session_id)
+                        .chain_update(primitives::encode_point(&output_key.point))
+                        .chain_update((message.len() as u64).to_be_bytes())
+                        .chain_update(message),
+                    |hasher, participant| hasher.chain_update(participant.to_be_bytes()),
+                ),
+                |hasher, verifying_key| hasher.chain_update(verifying_key.to_cbor()),
+            )
+            .finalize()
+            .to_vec();
+
+        // Samples hiding and binding nonces and broadcasts their commitments.
+        let mut rng = rand::thread_rng();
+        let nonces = (C::Scalar::random(&mut rng), C::Scalar::random(&mut rng));
+        let own_commitments = (
+            C::Point::generator() * nonces.0,
+            C::Point::generator() * nonces.1,
+        );
+        let message_queue = vec![identity_auth::authenticate(
+            SigningMessageBody::Round1 {
+                hiding_commitment: own_commitments.0,
+                binding_commitment: own_commitments.1,
+            },
+            &session_hash,
+            idx,
+            None,
+            identity_provider,
+        )];
+
+        Ok(Self {
+            identity_provider,
+            verified_parties,
+            key_share,
+            participants,
+            message,
+            session_hash,
+            output_key,
+            secret_share,
+            nonces,
+            commitments: BTreeMap::from([(idx, own_commitments)]),
+            signature_shares: BTreeMap::new(),
+            group_commitment: None,
+            round: 1,
+            message_queue,
+            output: None,
+            is_picked: false,
+        })
+    }
+
+    // Returns the binding factors (i.e rho) of all participants.
+    fn binding_factors(&self) -> BTreeMap<u16, C::Scalar> {
+        let encoded_commitments: Vec<u8> = self
+            .commitments
+            .iter()
+            .flat_map(|(idx, (hiding, binding))| {
+                [
+                    idx.to_be_bytes().to_vec(),
+                    primitives::encode_point(hiding),
+                    primitives::encode_point(binding),
+                ]
+                .concat()
+            })
+            .collect();
+        self.participants
+            .iter()
+            .map(|idx| {
+                (
+                    *idx,
+                    C::hash_to_scalar(
+                        BINDING_FACTOR_TAG,
+                        &[&self.session_hash, &encoded_commitments, &idx.to_be_bytes()],
+                    ),
+                )
+            })
+            .collect()
+    }
+
+    // Returns the (unnormalized) nonce commitment of a participant (i.e D + rho * E).
+    fn nonce_commitment(&self, idx: u16, binding_factors: &BTreeMap<u16, C::Scalar>) -> C::Point {
+        let (hiding, binding) = self.commitments[&idx];
+        hiding + binding * binding_factors[&idx]
+    }
+
+    // Returns the weight of a participant's verification share/secret share in the signature share
+    // (i.e `c * lambda * key_factor`).
+    fn share_weight(&self, idx: u16, challenge: &C::Scalar) -> C::Scalar {
+        let lagrange_coefficient: C::Scalar =
+            primitives::lagrange_coefficient(idx, &self.participants)
+                .expect("participants are validated at initialization");
+        *challenge * lagrange_coefficient * self.output_key.key_factor
+    }
+
+    // Computes and broadcasts the party's signature share.
+    fn send_signature_share(&mut self) {
+        let binding_factors = self.binding_factors();
+        let group_commitment = self
+            .participants
+            .iter()
+            .fold(C::Point::identity(), |acc, idx| {
+                acc + self.nonce_commitment(*idx, &binding_factors)
+            });
+        let nonce_factor = C::nonce_factor(&group_commitment);
+        let challenge = C::challenge(&group_commitment, &self.output_key.point, self.message);
+
+        let idx = self.key_share.idx;
+        let signature_share = nonce_factor
+            * (self.nonces.0 + self.nonces.1 * binding_factors[&idx])
+            + self.share_weight(idx, &challenge) * self.secret_share;
+        self.nonces.0.zeroize();
+        self.nonces.1.zeroize();
+        self.secret_share.zeroize();
+
+        self.message_queue.push(identity_auth::authenticate(
+            SigningMessageBody::Round2 { signature_share },
+            &self.session_hash,
+            idx,
+            None,
+            self.identity_provider,
+        ));
+        self.signature_shares.insert(idx, signature_share);
+        self.group_commitment = Some((group_commitment, nonce_factor));
+    }
+
+    // Verifies all signature shares and computes the aggregate signature.
+    fn finalize(&mut self) -> Result<(), Error> {
+        let (group_commitment, nonce_factor) = self
+            .group_commitment
+            .expect("group commitment is computed in round 1");
+        let binding_factors = self.binding_factors();
+        let challenge = C::challenge(&group_commitment, &self.output_key.point, self.message);
+
+        // Verifies signature shares against the senders' nonce commitments and verification shares.
+        for (sender, signature_share) in self.signature_shares.iter() {
+            let verification_share = self.key_share.verification_shares[*sender as usize - 1];
+            if C::Point::generator() * signature_share
+                != self.nonce_commitment(*sender, &binding_factors) * nonce_factor
+                    + verification_share * self.share_weight(*sender, &challenge)
+            {
+                return Err(Error::InvalidSignatureShare { sender: *sender });
+            }
+        }
+
+        // Aggregates the signature shares and verifies the signature.
+        let aggregate_share = self
+            .signature_shares
+            .values()
+            .fold(challenge * self.output_key.factored_tweak, |acc, it| {
+                acc + it
+            });
+        self.output = Some(C::signature(
+            &group_commitment,
+            &aggregate_share,
+            &self.output_key.point,
+            self.message,
+        )?);
+        Ok(())
+    }
+}
+
+impl<'a, I: IdentityProvider, C: Ciphersuite> StateMachine for AugmentedSigning<'a, I, C> {
+    type MessageBody = AuthedMessage<SigningMessageBody<C>>;
+    type Err = Error;
+    type Output = C::Signature;
+
+    fn handle_incoming(&mut self, msg: Msg<Self::MessageBody>) -> Result<(), Self::Err> {
+        // Verifies the identity authentication of the message.
+        identity_auth::verify(&msg, &self.session_hash, self.verified_parties)?;
+
+        let (sender, round) = (msg.sender, msg.body.body.round());
+        if sender == self.key_share.idx
+            || msg.receiver.is_some()
+            || !self.participants.contains(&sender)
+        {
+            return Err(Error::UnexpectedMessage { sender, round });
+        }
+        match msg.body.body {
+            SigningMessageBody::Round1 {
+                hiding_commitment,
+                binding_commitment,
+            } => {
+                if self.commitments.contains_key(&sender) {
+                    return Err(Error::DuplicateMessage { sender, round });
+                }
+                self.commitments
+                    .insert(sender, (hiding_commitment, binding_commitment));
+            }
+            SigningMessageBody::Round2 { signature_share } => {
+                if self.signature_shares.contains_key(&sender) {
+                    return Err(Error::DuplicateMessage { sender, round });
+                }
+                // NOTE: Signature shares are verified after nonce commitments from all participants are received.
+                self.signature_shares.insert(sender, signature_share);
+            }
+        }
+        Ok(())
+    }
+
+    fn message_queue(&mut self) -> &mut Vec<Msg<Self::MessageBody>> {
+        &mut self.message_queue
+    }
+
+    fn wants_to_proceed(&self) -> bool {
+        let n_participants = self.participants.len();
+        match self.round {
+            1 => self.commitments.len() == n_participants,
+            2 => self.signature_shares.len() == n_participants,
+            _ => false,
+        }
+    }
+
+    fn proceed(&mut self) -> Result<(), Self::Err> {
+        if !self.wants_to_proceed() {
+            return Ok(());
+        }
+        match self.round {
+            1 => self.send_signature_share(),
+            _ => self.finalize()?,
+        }
+        self.round += 1;
+        Ok(())
+    }
+
+    fn round_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    fn round_timeout_reached(&mut self) -> Self::Err {
+        panic!("no timeout was set")
+    }
+
+    fn is_finished(&self) -> bool {
+        self.output.is_some() || self.is_picked
+    }
+
+    fn pick_output(&mut self) -> Option<Result<Self::Output, Self::Err>> {
+        // Return an error if output was already picked.
+        if self.is_picked {
+            return Some(Err(Error::AlreadyPicked));
+        }
+
+        self.output.take().map(|output| {
+            self.is_picked = true;
+            Ok(output)
+        })
+    }
+
+    fn current_round(&self) -> u16 {
+        self.round
+    }
+
+    fn total_rounds(&self) -> Option<u16> {
+        Some(2)
+    }
+
+    fn party_ind(&self) -> u16 {
+        self.key_share.idx
+    }
+
+    fn parties(&self) -> u16 {
+        self.key_share.n_parties()
+    }
+}
+
+impl<'a, I: IdentityProvider, C: Ciphersuite> Drop for AugmentedSigning<'a, I, C> {
+    fn drop(&mut self) {
+        self.secret_share.zeroize();
+        self.nonces.0.zeroize();
+        self.nonces.1.zeroize();
+    }
+}
+
+// Implement `Debug` trait for `AugmentedSigning` for test simulations.
+#[cfg(any(test, feature = "dev"))]
+impl<'a, I: IdentityProvider, C: Ciphersuite> std::fmt::Debug for AugmentedSigning<'a, I, C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Augmented FROST Signing")
+    }
+}
+
+#[cfg(any(test, feature = "dev"))]
+pub mod tests {
+    use super::*;
+    use round_based::dev::Simulation;
+    use wamu_core::test_utils::MockECDSAIdentityProvider;
+
+    pub fn simulate_sign<C: Ciphersuite>(
+        wallet_shares: &[WalletShare<C>],
+        identity_providers: &[MockECDSAIdentityProvider],
+        participants: &[u16],
+        message: &[u8],
+        tweak: Tweak,
+    ) -> C::Signature {
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        let session_id = wamu_core::crypto::Random32Bytes::generate().to_be_bytes();
+
+        // Runs signing simulation for test parameters.
+        let mut simulation = Simulation::<AugmentedSigning<MockECDSAIdentityProvider, C>>::new();
+        for idx in participants {
+            let wallet_share = &wallet_shares[*idx as usize - 1];
+            simulation.add_party(
+                AugmentedSigning::new(
+                    wallet_share,
+                    &identity_providers[*idx as usize - 1],
+                    &verifying_keys,
+                    participants,
+                    message,
+                    tweak,
+                    session_id,
+                )
+                .unwrap(),
+            );
+        }
+        let signatures = simulation.run().unwrap();
+
+        // Verifies that all participants output the same signature.
+        assert_eq!(signatures.len(), participants.len());
+        for signature in signatures.iter() {
+            assert_eq!(signature, &signatures[0]);
+        }
+
+        signatures[0].clone()
+    }
+
+    #[test]
+    fn sign_works() {
+        let (wallet_shares, identity_providers) =
+            crate::keygen::tests::simulate_keygen::<Secp256k1>(2, 4);
+        let key_share = &wallet_shares[0].key_share;
+        let message = [0x2a; 32];
+
+        for participants in [vec![1, 2, 3], vec![2, 4, 3], vec![1, 2, 3, 4]] {
+            // Verifies signatures for the x-only group public key.
+            let signature = simulate_sign(
+                &wallet_shares,
+                &identity_providers,
+                &participants,
+                &message,
+                Tweak::None,
+            );
+            let verifying_key =
+                k256::schnorr::VerifyingKey::from_bytes(&key_share.x_only_public_key()).unwrap();
+            assert!(verifying_key.verify_raw(&message, &signature).is_ok());
+
+            // Verifies signatures for Taproot output keys (with and without a script tree).
+            for merkle_root in [None, Some([0x11; 32])] {
+                let signature = simulate_sign(
+                    &wallet_shares,
+                    &identity_providers,
+                    &participants,
+                    &message,
+                    Tweak::Taproot { merkle_root },
+                );
+                let output_key = k256::schnorr::VerifyingKey::from_bytes(
+                    &key_share.taproot_output_key(merkle_root.as_ref()),
+                )
+                .unwrap();
+                assert!(output_key.verify_raw(&message, &signature).is_ok());
+                assert!(verifying_key.verify_raw(&message, &signature).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn sign_rejects_insufficient_participants() {
+        let (wallet_shares, identity_providers) =
+            crate::keygen::tests::simulate_keygen::<Secp256k1>(2, 3);
+        let verifying_keys: Vec<VerifyingKey> = identity_providers
+            .iter()
+            .map(IdentityProvider::verifying_key)
+            .collect();
+        assert!(matches!(
+            AugmentedSigning::new(
+                &wallet_shares[0],
+                &identity_providers[0],
+                &verifying_keys,
+                &[1, 2],
+                b"message",
+                Tweak::None,
+                [0; 32],
+            ),
+            Err(Error::InvalidConfig)
+        ));
+    }
+}